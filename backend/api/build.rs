@@ -0,0 +1,32 @@
+use std::fs;
+use std::path::Path;
+
+/// Embeds the migrations into the binary, they are versioned by the date prefix of their directory as diesel does
+fn main() {
+    let migrations_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../migrations");
+    println!("cargo:rerun-if-changed={}", migrations_dir.display());
+
+    let mut dirs = fs::read_dir(&migrations_dir).expect("Failed to read migrations directory")
+        .map(|entry| entry.expect("Failed to read migrations directory").path())
+        .filter(|path| path.join("up.sql").is_file() && path.join("down.sql").is_file())
+        .collect::<Vec<_>>();
+
+    dirs.sort();
+
+    let migrations = dirs.iter()
+        .map(|dir| {
+            let name = dir.file_name().unwrap().to_string_lossy();
+            let version = name.split('_').next().unwrap().replace('-', "");
+
+            format!(
+                "    EmbeddedMigration {{ version: {:?}, name: {:?}, up_sql: include_str!({:?}), down_sql: include_str!({:?}) }},\n",
+                version, name, dir.join("up.sql"), dir.join("down.sql")
+            )
+        })
+        .collect::<String>();
+
+    let out_file = Path::new(&std::env::var("OUT_DIR").unwrap()).join("migrations.rs");
+
+    fs::write(out_file, format!("pub static MIGRATIONS: &[EmbeddedMigration] = &[\n{}];\n", migrations))
+        .expect("Failed to write embedded migrations");
+}
@@ -1,21 +1,40 @@
 #[macro_use]
 extern crate lazy_static;
 
+use std::any::Any;
+use std::fs::File;
+use std::io::BufReader;
 use std::sync::Arc;
 use std::sync::mpsc::channel;
 
 use actix::prelude::*;
-use actix_cors::Cors;
-use actix_web::{App, http::header, HttpServer, middleware};
+use actix_tls::rustls::TlsStream;
+use actix_web::{App, HttpServer, middleware};
+use actix_web::dev::Extensions;
+use actix_web::rt::net::TcpStream;
+use actix_web::rt::signal::unix::{signal, SignalKind};
 use diesel::{PgConnection, r2d2};
 use diesel::r2d2::ConnectionManager;
+use futures::future;
+use log::{error, info};
+use rustls::{AllowAnyAnonymousOrAuthenticatedClient, NoClientAuth, RootCertStore, ServerConfig, Session};
+use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
 
-use core::Config;
+use core::{Config, SecurityConfig};
 use core::error::Algorithm;
+use core::middlewares::security::{cors, security_headers};
 use core::types::DBPool;
-use core::utils::Hash;
-use experiment::ExperimentServer;
-use service::{ClientServices, MailClient, MailClientMock, MailService, SendMailMessage};
+use core::utils::{Hash, TokenKeys};
+use experiment::{Backplane, build_graphql_schema, ClientCertificate, ExperimentCleaner, ExperimentServer, JobArchiver, listen_audit_events, listen_job_events, Reaper, RetentionPolicy, RunnerPolicy, RunnerService, SessionLimits, ShutdownServerMessage, StatsAggregator};
+use experiment::models::runner::Runner;
+use service::{ClientServices, MailClient, MailClientMock, MailService, OidcClient, OidcConfig, SendMailMessage};
+use user::models::login_throttle::ThrottlePolicy;
+use user::models::two_factor::TwoFactorPolicy;
+
+mod migrations;
+mod openapi;
+
+const DEFAULT_TOKEN_KEY_ID: &str = "default";
 
 lazy_static! {
     static ref SECRET_KEY: String = std::env::var("SECRET_KEY").expect("SECRET_KEY is not provided in env");
@@ -52,16 +71,79 @@ fn setup_services() -> ClientServices {
 
     let mail_service = MailService::new(send_mail_recipient);
 
+    // login with the OpenID Connect provider is offered if an issuer is given
+    let oidc = std::env::var("OIDC_ISSUER").ok().map(|issuer| OidcClient::new(OidcConfig {
+        issuer,
+        client_id: std::env::var("OIDC_CLIENT_ID").expect("OIDC_CLIENT_ID is not provided in env"),
+        client_secret: std::env::var("OIDC_CLIENT_SECRET").expect("OIDC_CLIENT_SECRET is not provided in env"),
+        redirect_url: std::env::var("OIDC_REDIRECT_URL").expect("OIDC_REDIRECT_URL is not provided in env"),
+        role_claim: std::env::var("OIDC_ROLE_CLAIM").ok(),
+        admin_roles: parse_roles("OIDC_ADMIN_ROLES"),
+        auditor_roles: parse_roles("OIDC_AUDITOR_ROLES"),
+    }));
+
     ClientServices {
-        mail: mail_service
+        mail: mail_service,
+        oidc,
     }
 }
 
-fn setup_experiment_server(pool: DBPool) -> Addr<ExperimentServer> {
+/// Comma separated values of the OIDC role claim
+fn parse_roles(var: &str) -> Vec<String> {
+    std::env::var(var).unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|role| !role.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Replicas share the runner fleet over the Postgres backplane if EXPERIMENT_BACKPLANE is enabled. Finished jobs
+/// are kept forever unless JOB_RETENTION_DAYS or JOB_RETENTION_COUNT is given, they are moved into the archive once
+/// they are older than JOB_ARCHIVE_AFTER_DAYS if it is given. Users are mailed about the new api keys and the logins
+/// from the new devices with the given mail service.
+fn setup_experiment_server(pool: DBPool, mail: MailService) -> Addr<ExperimentServer> {
+    let retention = RetentionPolicy {
+        max_age_days: std::env::var("JOB_RETENTION_DAYS").ok()
+            .map(|days| days.parse::<i32>().ok().filter(|days| *days > 0)
+                .expect("Invalid JOB_RETENTION_DAYS is provided, please give a positive integer")),
+        max_jobs: std::env::var("JOB_RETENTION_COUNT").ok()
+            .map(|count| count.parse::<i32>().ok().filter(|count| *count > 0)
+                .expect("Invalid JOB_RETENTION_COUNT is provided, please give a positive integer")),
+    };
+
+    let archive_after_days = std::env::var("JOB_ARCHIVE_AFTER_DAYS").ok()
+        .map(|days| days.parse::<i32>().ok().filter(|days| *days > 0)
+            .expect("Invalid JOB_ARCHIVE_AFTER_DAYS is provided, please give a positive integer"));
+
+    let backplane = if std::env::var("EXPERIMENT_BACKPLANE").map_or(false, |enabled| enabled == "true") {
+        Some(Backplane::new(pool.clone()))
+    } else {
+        None
+    };
+
     let (tx, rx) = channel::<Addr<ExperimentServer>>();
     std::thread::Builder::new().name("experiment_server".to_string()).spawn(move || {
         let sys = System::new("experiment_server");
-        let experiment_server = ExperimentServer::new(pool).start();
+        let experiment_server = ExperimentServer::new(pool.clone(), backplane.clone()).start();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL is not provided in env");
+
+        if let Some(backplane) = backplane {
+            backplane.listen(database_url.clone(), experiment_server.clone());
+        }
+
+        listen_job_events(database_url.clone(), experiment_server.clone());
+        listen_audit_events(database_url, pool.clone(), experiment_server.clone(), mail);
+
+        Reaper::new(pool.clone(), experiment_server.clone()).start();
+        ExperimentCleaner::new(pool.clone(), retention).start();
+
+        if let Some(archive_after_days) = archive_after_days {
+            JobArchiver::new(pool.clone(), archive_after_days).start();
+        }
+
+        StatsAggregator::new(pool, experiment_server.clone()).start();
         tx.send(experiment_server).expect("Failed to send ExperimentServer from thread");
         sys.run()
     }).expect("Failed to initialize thread");
@@ -69,6 +151,68 @@ fn setup_experiment_server(pool: DBPool) -> Addr<ExperimentServer> {
     rx.recv().expect("Failed to receive ExperimentServer from thread")
 }
 
+/// Waits for SIGINT or SIGTERM, then disconnects the runners with a shutdown reason before stopping
+/// the server gracefully, so that they can connect to another server without backing off.
+async fn shutdown_on_signal(srv: actix_web::dev::Server, experiment_server: Addr<ExperimentServer>) {
+    let mut interrupt = signal(SignalKind::interrupt()).expect("Failed to listen SIGINT");
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen SIGTERM");
+
+    future::select(Box::pin(interrupt.recv()), Box::pin(terminate.recv())).await;
+
+    if let Err(e) = experiment_server.send(ShutdownServerMessage).await {
+        error!("disconnecting runners is failed: {:?}", e);
+    }
+
+    srv.stop(true).await;
+}
+
+/// TLS is terminated by the app if a certificate is given. Clients may present a certificate signed by
+/// the client CA, which is used for authenticating runners.
+fn setup_tls() -> Option<ServerConfig> {
+    let cert_file = std::env::var("TLS_CERT_FILE").ok()?;
+    let key_file = std::env::var("TLS_KEY_FILE").expect("TLS_KEY_FILE is not provided in env");
+
+    let mut config = match std::env::var("TLS_CLIENT_CA_FILE") {
+        Ok(ca_file) => {
+            let mut roots = RootCertStore::empty();
+            roots.add_pem_file(&mut BufReader::new(File::open(ca_file).expect("Failed to open TLS_CLIENT_CA_FILE")))
+                .expect("Failed to read TLS_CLIENT_CA_FILE");
+
+            ServerConfig::new(AllowAnyAnonymousOrAuthenticatedClient::new(roots))
+        }
+        Err(_) => ServerConfig::new(NoClientAuth::new())
+    };
+
+    let cert_chain = certs(&mut BufReader::new(File::open(cert_file).expect("Failed to open TLS_CERT_FILE")))
+        .expect("Failed to read TLS_CERT_FILE");
+
+    let mut keys = pkcs8_private_keys(&mut BufReader::new(File::open(&key_file).expect("Failed to open TLS_KEY_FILE")))
+        .expect("Failed to read TLS_KEY_FILE");
+
+    if keys.is_empty() {
+        keys = rsa_private_keys(&mut BufReader::new(File::open(&key_file).expect("Failed to open TLS_KEY_FILE")))
+            .expect("Failed to read TLS_KEY_FILE");
+    }
+
+    config.set_single_cert(cert_chain, keys.pop().expect("No private key is found in TLS_KEY_FILE"))
+        .expect("Invalid TLS certificate or key");
+
+    Some(config)
+}
+
+/// Passes the client certificate of the connection to the handlers
+fn extract_client_certificate(connection: &dyn Any, data: &mut Extensions) {
+    if let Some(stream) = connection.downcast_ref::<TlsStream<TcpStream>>() {
+        let certificate = stream.get_ref().1
+            .get_peer_certificates()
+            .and_then(|certificates| certificates.into_iter().next());
+
+        if let Some(certificate) = certificate {
+            data.insert(ClientCertificate::from_der(&certificate.0));
+        }
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Load .env
@@ -80,13 +224,42 @@ async fn main() -> std::io::Result<()> {
     // Setup database
     let pool = setup_database();
 
+    // pending migrations are run on startup if it is requested, app refuses to start with an out of date schema
+    let run_migrations = std::env::args().any(|arg| arg == "--migrate")
+        || std::env::var("RUN_MIGRATIONS").map_or(false, |run| run == "true");
+
+    if let Err(e) = migrations::check_migrations(&pool, run_migrations) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+
     // Setup services
     let client_services = setup_services();
 
     // Create utils
-    let hash = Hash::new(&*SECRET_KEY, Algorithm::HS256);
+    // tokens are signed with the secret key unless the token keys are given for rotating them
+    let token_keys = match std::env::var("TOKEN_KEYS") {
+        Ok(keys) => TokenKeys::parse(
+            keys.as_str(),
+            std::env::var("TOKEN_KEY_ID").expect("TOKEN_KEY_ID is not provided in env").as_str(),
+        )
+            .expect("Invalid TOKEN_KEYS is provided"),
+        Err(_) => TokenKeys::single(DEFAULT_TOKEN_KEY_ID, SECRET_KEY.as_str())
+    };
+
+    let hash = Hash::new(&*SECRET_KEY, token_keys, Algorithm::HS256);
 
-    let experiment_server = setup_experiment_server(pool.clone());
+    // access keys stored in plain before they are hashed are hashed with the secret key
+    match Runner::hash_legacy_access_keys(&hash, &pool.get().unwrap()) {
+        Ok(0) => {}
+        Ok(hashed) => info!("{} legacy runner access keys are hashed", hashed),
+        Err(e) => {
+            error!("hashing legacy runner access keys is failed: {:?}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let experiment_server = setup_experiment_server(pool.clone(), client_services.mail.clone());
 
     let config = Arc::new(Config {
         web_app_url: std::env::var("WEB_APP_URL").expect("WEB_APP_URL is not provided in env"),
@@ -94,27 +267,92 @@ async fn main() -> std::io::Result<()> {
         storage_path: std::env::var("STORAGE_PATH").expect("STORAGE_PATH is not provided in env"),
     });
 
-    let srv = HttpServer::new(move || {
-        let cors = Cors::default()
-            .allowed_origin(std::env::var("ALLOWED_ORIGIN").expect("ALLOWED_ORIGIN is not provided in env").as_str())
-            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
-            .allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT, header::CONTENT_TYPE])
-            .allowed_header("enctype")
-            .max_age(60);
+    let runner_policy = RunnerPolicy::new(
+        std::env::var("RUNNER_ALLOWED_NETWORKS").unwrap_or_default().as_str(),
+        std::env::var("RUNNER_MAX_CONNECTIONS_PER_TOKEN").ok()
+            .map(|max| max.parse::<usize>().expect("Invalid RUNNER_MAX_CONNECTIONS_PER_TOKEN is provided, please give a positive integer")),
+        std::env::var("TRUST_FORWARDED_FOR").map_or(false, |trust| trust == "true"),
+        std::env::var("TRUST_CLIENT_CERT_HEADER").map_or(false, |trust| trust == "true"),
+    )
+        .expect("Invalid RUNNER_ALLOWED_NETWORKS is provided");
 
+    let session_limits = SessionLimits::new(
+        std::env::var("RUNNER_MAX_FRAME_SIZE").map_or(16 * 1024 * 1024, |size| size.parse::<usize>()
+            .expect("Invalid RUNNER_MAX_FRAME_SIZE is provided, please give a positive integer")),
+        std::env::var("RUNNER_MAX_MESSAGES_PER_SECOND").map_or(50, |max| max.parse::<u32>()
+            .expect("Invalid RUNNER_MAX_MESSAGES_PER_SECOND is provided, please give a positive integer")),
+        std::env::var("RUNNER_MAX_BYTES_PER_MINUTE").map_or(256 * 1024 * 1024, |max| max.parse::<usize>()
+            .expect("Invalid RUNNER_MAX_BYTES_PER_MINUTE is provided, please give a positive integer")),
+    );
+
+    let security_config = SecurityConfig {
+        allowed_origins: std::env::var("ALLOWED_ORIGIN").expect("ALLOWED_ORIGIN is not provided in env")
+            .split(',')
+            .map(|origin| origin.trim().to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect(),
+        allow_credentials: std::env::var("CORS_ALLOW_CREDENTIALS").is_ok_and(|allow| allow == "true"),
+        hsts_max_age: std::env::var("HSTS_MAX_AGE").ok()
+            .map(|max_age| max_age.parse::<u64>().expect("Invalid HSTS_MAX_AGE is provided, please give a positive integer")),
+        frame_options: std::env::var("FRAME_OPTIONS").unwrap_or_else(|_| String::from("DENY")),
+    };
+
+    let throttle_policy = ThrottlePolicy {
+        trust_forwarded_for: std::env::var("TRUST_FORWARDED_FOR").is_ok_and(|trust| trust == "true"),
+    };
+
+    let two_factor_policy = TwoFactorPolicy {
+        require_for_admins: std::env::var("REQUIRE_ADMIN_TWO_FACTOR").is_ok_and(|require| require == "true"),
+    };
+
+    let tls_config = setup_tls();
+
+    // grpc transport is offered to the runners besides the websocket if an address is given
+    if let Ok(grpc_bind_address) = std::env::var("RUNNER_GRPC_BIND_ADDRESS") {
+        let grpc_bind_address = grpc_bind_address.parse()
+            .expect("Invalid RUNNER_GRPC_BIND_ADDRESS is provided, please give an address like 0.0.0.0:8043");
+
+        RunnerService::new(pool.clone(), hash.clone(), experiment_server.clone(), runner_policy.clone(), session_limits.clone())
+            .listen(grpc_bind_address, tls_config.clone());
+    }
+
+    let graphql_schema = build_graphql_schema(pool.clone(), experiment_server.clone());
+
+    // runners are disconnected through the experiment server on shutdown
+    let shutdown_experiment_server = experiment_server.clone();
+
+    let srv = HttpServer::new(move || {
         App::new()
-            .wrap(cors)
+            // responses are compressed with the encoding the client accepts, e.g. gzip or brotli. Handlers opt out by
+            // setting the encoding of their response to identity with BodyEncoding, e.g. if the body is already
+            // compressed. Websocket handshakes are never compressed.
+            .wrap(middleware::Compress::default())
+            .wrap(security_headers(&security_config))
+            .wrap(cors(&security_config))
             .wrap(middleware::Logger::default())
             .data(experiment_server.clone())
             .data(hash.clone())
             .data(pool.clone())
             .data(config.clone())
             .data(client_services.clone())
+            .data(runner_policy.clone())
+            .data(session_limits.clone())
+            .data(throttle_policy.clone())
+            .data(two_factor_policy.clone())
+            .data(graphql_schema.clone())
             .configure(user::register)
             .configure(auth::register)
             .configure(experiment::register)
+            .configure(openapi::register)
     })
-        .bind(std::env::var("APP_BIND_ADDRESS").expect("APP_BIND_ADDRESS is not provided in env").as_str())?;
+        .on_connect(extract_client_certificate);
+
+    let bind_address = std::env::var("APP_BIND_ADDRESS").expect("APP_BIND_ADDRESS is not provided in env");
+
+    let srv = match tls_config {
+        Some(tls_config) => srv.bind_rustls(bind_address.as_str(), tls_config)?,
+        None => srv.bind(bind_address.as_str())?
+    };
 
     let srv = if let Ok(w) = std::env::var("NUM_WORKERS") {
         match w.parse::<usize>() {
@@ -125,7 +363,11 @@ async fn main() -> std::io::Result<()> {
         srv
     };
 
-    srv
-        .run()
-        .await
+    let srv = srv
+        .disable_signals()
+        .run();
+
+    actix_web::rt::spawn(shutdown_on_signal(srv.clone(), shutdown_experiment_server));
+
+    srv.await
 }
@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+use diesel::connection::SimpleConnection;
+use diesel::migration::{Migration, RunMigrationsError};
+use diesel_migrations::MigrationConnection;
+use log::info;
+
+use core::types::DBPool;
+
+// version diesel cli records for its own setup migration, it is not shipped with the app
+const DIESEL_SETUP_VERSION: &str = "00000000000000";
+
+pub struct EmbeddedMigration {
+    version: &'static str,
+    name: &'static str,
+    up_sql: &'static str,
+    down_sql: &'static str,
+}
+
+impl Migration for EmbeddedMigration {
+    fn version(&self) -> &str {
+        self.version
+    }
+
+    fn run(&self, conn: &dyn SimpleConnection) -> Result<(), RunMigrationsError> {
+        conn.batch_execute(self.up_sql).map_err(Into::into)
+    }
+
+    fn revert(&self, conn: &dyn SimpleConnection) -> Result<(), RunMigrationsError> {
+        conn.batch_execute(self.down_sql).map_err(Into::into)
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/migrations.rs"));
+
+/// Compares the migrations applied to the database with the ones embedded in the binary. App refuses to start
+/// if the database has migrations unknown to it, i.e. the schema is ahead of the binary. Pending migrations are
+/// run if it is requested, otherwise the app refuses to start with a behind schema too.
+pub fn check_migrations(pool: &DBPool, run_pending: bool) -> Result<(), String> {
+    let conn = pool.get().map_err(|e| format!("Failed to get a connection, {}", e))?;
+
+    diesel_migrations::setup_database(&*conn)
+        .map_err(|e| format!("Failed to create migrations table, {}", e))?;
+
+    let applied = conn.previously_run_migration_versions()
+        .map_err(|e| format!("Failed to fetch applied migrations, {}", e))?;
+
+    let embedded = MIGRATIONS.iter()
+        .map(|migration| migration.version)
+        .collect::<HashSet<_>>();
+
+    let mut unknown = applied.iter()
+        .filter(|version| version.as_str() != DIESEL_SETUP_VERSION && !embedded.contains(version.as_str()))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+
+    if !unknown.is_empty() {
+        unknown.sort_unstable();
+
+        return Err(format!("Database schema is ahead of the app, unknown migrations are applied: {}", unknown.join(", ")));
+    }
+
+    let pending = MIGRATIONS.iter()
+        .filter(|migration| !applied.contains(migration.version))
+        .collect::<Vec<_>>();
+
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let names = pending.iter()
+        .map(|migration| migration.name)
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if !run_pending {
+        return Err(format!("Database schema is behind the app, pending migrations: {}. Start with --migrate or RUN_MIGRATIONS=true to run them", names));
+    }
+
+    info!("running pending migrations: {}", names);
+
+    // replicas starting at the same time wait for each other instead of running the same migrations
+    conn.batch_execute("SELECT pg_advisory_lock(hashtext('__diesel_schema_migrations'))")
+        .map_err(|e| format!("Failed to lock migrations, {}", e))?;
+
+    let result = diesel_migrations::run_migrations(&*conn, MIGRATIONS.iter().map(|migration| migration as &dyn Migration), &mut std::io::sink())
+        .map_err(|e| format!("Failed to run migrations, {}", e));
+
+    conn.batch_execute("SELECT pg_advisory_unlock(hashtext('__diesel_schema_migrations'))")
+        .map_err(|e| format!("Failed to unlock migrations, {}", e))?;
+
+    result
+}
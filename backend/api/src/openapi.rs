@@ -0,0 +1,128 @@
+use actix_web::{get, HttpResponse, web};
+use serde_json::{json, Value};
+use utoipa::{Modify, OpenApi};
+use utoipa::openapi::{ContentBuilder, Ref, ResponseBuilder};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+
+use core::error::HttpError;
+
+lazy_static! {
+    static ref SPECIFICATION: String = {
+        let mut specification = serde_json::to_value(ApiDoc::openapi()).expect("Failed to serialize the openapi specification");
+
+        inline_model_ids(&mut specification);
+
+        specification.to_string()
+    };
+}
+
+// ids are declared with the ModelId alias, which the schema derive can not resolve, it refers to a schema named i32 instead
+const MODEL_ID_REF: &str = "#/components/schemas/i32";
+
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <title>nrg-testbed api</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+<div id="swagger-ui"></div>
+<script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+<script>
+    SwaggerUIBundle({url: "/openapi.json", dom_id: "#swagger-ui"});
+</script>
+</body>
+</html>
+"##;
+
+/// Specification of the REST api, paths of each crate are nested under the scope they are registered.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "nrg-testbed"),
+    nest(
+        (path = "/api/user", api = user::ApiDoc),
+        (path = "/api/auth", api = auth::ApiDoc),
+        (path = "/api/experiment", api = experiment::ApiDoc),
+    ),
+    components(schemas(HttpError)),
+    modifiers(&BearerAuth, &ErrorResponse),
+)]
+pub struct ApiDoc;
+
+/// Token given by the login, paths behind the auth middleware refer to it.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme("bearer", SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build()
+            ));
+        }
+    }
+}
+
+/// Errors of all the handlers share the same body, it is added to the operations here instead of
+/// repeating it on each handler.
+struct ErrorResponse;
+
+impl Modify for ErrorResponse {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let response = ResponseBuilder::new()
+            .description("Error identified by its errorCode")
+            .content("application/json", ContentBuilder::new().schema(Some(Ref::from_schema_name("HttpError"))).build())
+            .build();
+
+        for item in openapi.paths.paths.values_mut() {
+            let operations = vec![&mut item.get, &mut item.put, &mut item.post, &mut item.delete];
+
+            for operation in operations.into_iter().flatten() {
+                operation.responses.responses.insert(String::from("default"), response.clone().into());
+            }
+        }
+    }
+}
+
+/// Replaces the references to the i32 schema with the integer schema itself.
+fn inline_model_ids(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            if map.get("$ref").and_then(Value::as_str) == Some(MODEL_ID_REF) {
+                *value = json!({"type": "integer", "format": "int32"});
+                return;
+            }
+
+            if let Some(Value::Object(schemas)) = map.get_mut("components").and_then(|components| components.get_mut("schemas")) {
+                schemas.remove("i32");
+            }
+
+            map.values_mut().for_each(inline_model_ids);
+        }
+        Value::Array(items) => items.iter_mut().for_each(inline_model_ids),
+        _ => {}
+    }
+}
+
+pub fn register(config: &mut web::ServiceConfig) {
+    config
+        .service(fetch_specification)
+        .service(swagger_ui);
+}
+
+#[get("/openapi.json")]
+async fn fetch_specification() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(SPECIFICATION.as_str())
+}
+
+#[get("/docs")]
+async fn swagger_ui() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(SWAGGER_UI)
+}
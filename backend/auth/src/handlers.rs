@@ -1,8 +1,12 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
-use actix_web::{HttpResponse, post, put, web};
+use actix_web::{get, HttpMessage, HttpRequest, HttpResponse, post, web};
+use actix_web::cookie::{Cookie, SameSite};
 use askama::Template;
+use chrono::{Duration, Utc};
 use diesel::prelude::*;
+use diesel::PgConnection;
 use diesel::result::{DatabaseErrorKind, Error};
 use validator::Validate;
 
@@ -10,56 +14,415 @@ use core::Config;
 use core::db::DieselEnum;
 use core::error::{ErrorMessaging, ValidationError};
 use core::ErrorMessage as CoreErrorMessage;
-use core::models::token::{AuthToken, IdentityToken, IdentityTokenKind};
-use core::responses::{SuccessResponse, TokenResponse};
+use core::models::audit_log::AuditEntry;
+use core::models::role::Roles;
+use core::models::token::{AuthToken, IdentityToken, IdentityTokenKind, OidcState};
+use core::responses::{RedirectResponse, SessionTokenResponse, SuccessResponse};
 use core::sanitized::SanitizedJson;
-use core::schema::users;
-use core::types::DBPool;
-use core::utils::Hash;
-use service::ClientServices;
-use user::models::user::{User, UserStatus};
+use core::schema::{sessions, users};
+use core::types::{DBPool, ModelId, UserId};
+use core::utils::{Hash, random_key};
+use service::{ClientServices, OidcClaims, OidcRole};
+use user::models::password_reset::PasswordResetToken;
+use user::ErrorMessage as UserErrorMessage;
+use user::models::identity::Identity;
+use user::models::login_throttle::{LoginThrottle, Throttle, ThrottlePolicy};
+use user::models::session::Session;
+use user::models::two_factor::TwoFactor;
+use user::models::user::{User, UserInsert, UserStatus};
 
 use crate::ErrorMessage;
-use crate::requests::{ForgotPasswordRequest, LoginRequest, ResetPasswordRequest, SignUpRequest, VerifyAccountRequest};
+use crate::requests::{ForgotPasswordRequest, LoginRequest, OidcLoginRequest, RefreshTokenRequest, ResendVerificationRequest, ResetPasswordRequest, SignUpRequest, VerifyAccountRequest};
 use crate::templates::{ForgotPasswordMailTemplate, ResetPasswordMailTemplate, VerifyAccountMailTemplate};
 
 const TIMEOUT: i64 = 60 * 60 * 24;
-
+// verification links can not be requested more often than this, so that the mailbox is not flooded
+const RESEND_VERIFICATION_COOLDOWN: i64 = 60 * 5;
+// access tokens of the sessions are short lived, they are refreshed with the refresh token of the session
+const ACCESS_TOKEN_TIMEOUT: i64 = 60 * 15;
+// users created by the OpenID Connect provider are given a random password nobody knows
+const OIDC_PASSWORD_LENGTH: usize = 32;
+// nonce of the OpenID Connect state is kept in this cookie, so that the state is only accepted from the browser
+// which started the authorization
+const OIDC_STATE_COOKIE: &str = "oidc_state";
+
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses((status = 200, body = SessionTokenResponse)),
+)]
 #[post("/login")]
-pub async fn login(pool: web::Data<DBPool>, hash: web::Data<Hash>, request: SanitizedJson<LoginRequest>) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+pub async fn login(pool: web::Data<DBPool>, hash: web::Data<Hash>, req: HttpRequest, request: SanitizedJson<LoginRequest>)
+                   -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
     let conn = pool.get().unwrap();
     let hash = hash.into_inner();
     let request = request.into_inner();
 
+    let user_agent = req.headers().get("User-Agent")
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .map(String::from);
+    let ip = client_ip(&req);
+
     let password = hash.sign512(&request.password);
-    let user = web::block(move || -> Result<User, Box<dyn ErrorMessaging>> {
-        let result: QueryResult<User> = users::table
-            .filter(users::email.eq(&request.email).and(users::password.eq(&password)))
-            .first::<User>(&conn);
+    let session_hash = hash.clone();
+    let attempt = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        // attempts of the account and the address wait for each other, so that the parallel guesses are recorded
+        // before the next one is checked against the throttle
+        LoginThrottle::lock(request.email.as_str(), ip.as_deref(), &conn)?;
+
+        // rejected attempt is the result of the transaction, so that its recorded failure is not rolled back
+        Ok(attempt_login(request, password, ip, user_agent, &session_hash, &conn))
+    }))
+        .await?;
 
-        match result {
-            Ok(user) => {
-                match user.status {
-                    UserStatus::NotVerified => Err(Box::new(ErrorMessage::NotVerified)),
-                    UserStatus::Banned => Err(Box::new(ErrorMessage::Banned)),
-                    UserStatus::Verified => Ok(user)
+    let (user, (session_id, refresh_token)) = attempt?;
+
+    Ok(HttpResponse::Ok().json(session_token(&hash, &user, session_id, refresh_token)?))
+}
+
+fn attempt_login(request: LoginRequest, password: String, ip: Option<String>, user_agent: Option<String>, hash: &Hash, conn: &PgConnection)
+                 -> Result<(User, (ModelId, String)), Box<dyn ErrorMessaging>> {
+    // throttled attempts are rejected before the credentials are checked, so that the passwords can not be guessed
+    match LoginThrottle::check(request.email.as_str(), ip.as_deref(), conn)? {
+        Some(Throttle::Locked(_)) => return Err(Box::new(ErrorMessage::AccountLocked)),
+        Some(Throttle::Delayed(_)) => return Err(Box::new(ErrorMessage::TooManyLoginAttempts)),
+        None => {}
+    }
+
+    let user = match users::table
+        .filter(users::email.eq(&request.email).and(users::password.eq(&password)))
+        .first::<User>(conn)
+        .optional()? {
+        Some(user) => user,
+        None => return Err(login_failure(request.email.as_str(), ip.as_deref(), ErrorMessage::InvalidCredentialsOrUser, conn))
+    };
+
+    // unverified users can sign in, actions requiring a verified email are rejected by their handlers
+    if user.status == UserStatus::Banned {
+        return Err(Box::new(ErrorMessage::Banned));
+    }
+
+    if user.status == UserStatus::Deactivated {
+        return Err(Box::new(ErrorMessage::Deactivated));
+    }
+
+    if TwoFactor::is_enabled(user.id, conn)? {
+        let code = request.code.as_deref()
+            .ok_or(UserErrorMessage::TwoFactorRequired)?;
+
+        if !TwoFactor::verify(user.id, code, hash, conn)? {
+            return Err(login_failure(request.email.as_str(), ip.as_deref(), UserErrorMessage::InvalidTwoFactorCode, conn));
+        }
+    }
+
+    LoginThrottle::clear(request.email.as_str(), conn)?;
+
+    let session = Session::start(user.id, user_agent, ip, hash, conn)?;
+    Ok((user, session))
+}
+
+/// Issues a new access token for the session of the refresh token. Refresh token is rotated, the returned one
+/// must be used for the next refresh.
+#[utoipa::path(
+    post,
+    path = "/refresh",
+    tag = "auth",
+    request_body = RefreshTokenRequest,
+    responses((status = 200, body = SessionTokenResponse)),
+)]
+#[post("/refresh")]
+pub async fn refresh_session(pool: web::Data<DBPool>, hash: web::Data<Hash>, request: web::Json<RefreshTokenRequest>)
+                           -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    let conn = pool.get().unwrap();
+    let hash = hash.into_inner();
+    let request = request.into_inner();
+
+    let session_hash = hash.clone();
+    // unknown token is not an error for the transaction, since revoking the session on a reused token must be kept
+    let session = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let (session_id, user_id, refresh_token) = match Session::rotate(request.refresh_token.as_str(), &session_hash, &conn)? {
+            Some(session) => session,
+            None => return Ok(None)
+        };
+
+        let user = users::table.find(user_id).first::<User>(&conn)?;
+
+        if user.status == UserStatus::Banned {
+            return Err(Box::new(ErrorMessage::Banned));
+        }
+
+        if user.status == UserStatus::Deactivated {
+            return Err(Box::new(ErrorMessage::Deactivated));
+        }
+
+        Ok(Some((user, (session_id, refresh_token))))
+    }))
+        .await?;
+
+    let (user, (session_id, refresh_token)) = session.ok_or(CoreErrorMessage::InvalidToken)?;
+
+    Ok(HttpResponse::Ok().json(session_token(&hash, &user, session_id, refresh_token)?))
+}
+
+/// Login page of the OpenID Connect provider. Provider redirects back to the web app, which completes the login
+/// with the returned code and state. State is bound to the browser with an HttpOnly cookie.
+#[utoipa::path(
+    get,
+    path = "/oidc/authorize",
+    tag = "auth",
+    responses((status = 200, body = RedirectResponse)),
+)]
+#[get("/oidc/authorize")]
+pub async fn oidc_authorize(hash: web::Data<Hash>, client_services: web::Data<ClientServices>) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    let oidc = client_services.oidc.as_ref()
+        .ok_or(UserErrorMessage::OidcNotConfigured)?;
+
+    let state = OidcState::new(None);
+
+    let url = oidc.authorization_url(hash.encode(&state)?.as_str(), state.nonce.as_str())
+        .await
+        .map_err(|_| UserErrorMessage::OidcFailed)?;
+
+    let cookie = Cookie::build(OIDC_STATE_COOKIE, state.nonce)
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Lax)
+        .finish();
+
+    Ok(HttpResponse::Ok().cookie(cookie).json(RedirectResponse { url }))
+}
+
+/// Signs in with the account of the OpenID Connect provider. An account is created for the user if the account of
+/// the provider is not linked yet, unless its email is used by an existing user who has to link it first. Role of
+/// the user follows the claims of the provider if the role mapping is configured. Users with the two factor
+/// authentication enabled give their code along with the provider's, like `login`.
+#[utoipa::path(
+    post,
+    path = "/oidc/login",
+    tag = "auth",
+    request_body = OidcLoginRequest,
+    responses((status = 200, body = SessionTokenResponse)),
+)]
+#[post("/oidc/login")]
+pub async fn oidc_login(
+    pool: web::Data<DBPool>,
+    hash: web::Data<Hash>,
+    client_services: web::Data<ClientServices>,
+    req: HttpRequest,
+    request: web::Json<OidcLoginRequest>,
+) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    let conn = pool.get().unwrap();
+    let hash = hash.into_inner();
+    let request = request.into_inner();
+
+    let oidc = client_services.oidc.clone()
+        .ok_or(UserErrorMessage::OidcNotConfigured)?;
+
+    let state = hash.decode::<OidcState>(request.state.as_str())
+        .map_err(|_| CoreErrorMessage::InvalidToken)?;
+
+    // states of the linking are completed by the user endpoints
+    if state.user_id.is_some() {
+        return Err(CoreErrorMessage::InvalidToken.into());
+    }
+
+    // state must come back to the browser which started the authorization, otherwise a user could be signed into
+    // the account of someone else with a state of theirs
+    let state_cookie = req.cookie(OIDC_STATE_COOKIE)
+        .ok_or(CoreErrorMessage::InvalidToken)?;
+
+    if state_cookie.value() != state.nonce {
+        return Err(CoreErrorMessage::InvalidToken.into());
+    }
+
+    let claims = oidc.exchange(request.code.as_str(), state.nonce.as_str())
+        .await
+        .map_err(|_| UserErrorMessage::OidcFailed)?;
+
+    let user_agent = req.headers().get("User-Agent")
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .map(String::from);
+    let ip = client_ip(&req);
+    let two_factor_code = request.two_factor_code;
+
+    let session_hash = hash.clone();
+    let (user, (session_id, refresh_token)) = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let mut user = match Identity::sign_in(oidc.issuer(), claims.subject.as_str(), &conn)? {
+            Some(user_id) => users::table.find(user_id).first::<User>(&conn)?,
+            None => {
+                let email = claims.email.clone()
+                    .ok_or(UserErrorMessage::OidcFailed)?;
+
+                let exists = diesel::select(diesel::dsl::exists(users::table.filter(users::email.eq(&email))))
+                    .get_result::<bool>(&conn)?;
+
+                if exists {
+                    return Err(UserErrorMessage::OidcAccountNotLinked.into());
                 }
+
+                let (first_name, last_name) = oidc_name(&claims, email.as_str());
+                let status = if claims.email_verified { UserStatus::Verified } else { UserStatus::NotVerified };
+
+                // users created by the provider sign in with it, they can set a password through the forgot password
+                let user = diesel::insert_into(users::table)
+                    .values((
+                        &UserInsert {
+                            first_name,
+                            last_name,
+                            email,
+                            password: session_hash.sign512(random_key(OIDC_PASSWORD_LENGTH).as_str()),
+                            role_id: Roles::User as ModelId,
+                        },
+                        users::status.eq(status.value())
+                    ))
+                    .get_result::<User>(&conn)?;
+
+                Identity::link(user.id, oidc.issuer(), claims.subject.as_str(), claims.email.as_deref(), &conn)?;
+
+                AuditEntry::new(Some(user.id), "user.oidc_sign_up")
+                    .details(oidc.issuer().to_string())
+                    .record(&conn)?;
+
+                user
             }
-            Err(err) => match err {
-                diesel::result::Error::NotFound => Err(Box::new(ErrorMessage::InvalidCredentialsOrUser)),
-                _ => Err(Box::new(err))
+        };
+
+        if user.status == UserStatus::Banned {
+            return Err(ErrorMessage::Banned.into());
+        }
+
+        if user.status == UserStatus::Deactivated {
+            return Err(ErrorMessage::Deactivated.into());
+        }
+
+        // provider does not stand in for the second factor, it is checked the same as the password login
+        if TwoFactor::is_enabled(user.id, &conn)? {
+            let code = two_factor_code.as_deref()
+                .ok_or(UserErrorMessage::TwoFactorRequired)?;
+
+            if !TwoFactor::verify(user.id, code, &session_hash, &conn)? {
+                return Err(UserErrorMessage::InvalidTwoFactorCode.into());
             }
         }
-    })
+
+        if let Some(role) = claims.role {
+            let role_id = match role {
+                OidcRole::Admin => Roles::Admin as ModelId,
+                OidcRole::Auditor => Roles::Auditor as ModelId,
+                OidcRole::User => Roles::User as ModelId,
+            };
+
+            if user.role_id != role_id {
+                diesel::update(&user)
+                    .set(users::role_id.eq(role_id))
+                    .execute(&conn)?;
+
+                AuditEntry::new(Some(user.id), "user.role_mapped")
+                    .target("user", user.id)
+                    .details(format!("role {} from the claims of {}", role_id, oidc.issuer()))
+                    .record(&conn)?;
+
+                user.role_id = role_id;
+            }
+        }
+
+        let session = Session::start(user.id, user_agent, ip, &session_hash, &conn)?;
+
+        Ok((user, session))
+    }))
         .await?;
 
-    let token = hash.encode(&AuthToken::new(user.id, user.role_id, TIMEOUT))?;
+    // state is used once
+    Ok(HttpResponse::Ok().del_cookie(&state_cookie).json(session_token(&hash, &user, session_id, refresh_token)?))
+}
+
+// names are taken from the claims of the provider if they are given, email is used otherwise
+fn oidc_name(claims: &OidcClaims, email: &str) -> (String, String) {
+    if let (Some(given_name), Some(family_name)) = (&claims.given_name, &claims.family_name) {
+        return (given_name.clone(), family_name.clone());
+    }
 
-    let token = TokenResponse { token };
+    if let Some(name) = &claims.name {
+        let mut parts = name.rsplitn(2, ' ');
+        let last_name = parts.next().unwrap_or_default().to_string();
 
-    Ok(HttpResponse::Ok().json(token))
+        if let Some(first_name) = parts.next() {
+            return (first_name.to_string(), last_name);
+        }
+
+        return (last_name, String::new());
+    }
+
+    (email.split('@').next().unwrap_or_default().to_string(), String::new())
 }
 
+/// Records the failed login and returns the error of it, account is locked once it fails too many times
+fn login_failure<E: ErrorMessaging + 'static>(email: &str, ip: Option<&str>, error: E, conn: &PgConnection) -> Box<dyn ErrorMessaging> {
+    let result = conn.transaction::<_, Error, _>(|| {
+        if !LoginThrottle::record_failure(email, ip, conn)? {
+            return Ok(());
+        }
+
+        let user_id = users::table
+            .filter(users::email.eq(email))
+            .select(users::id)
+            .first::<UserId>(conn)
+            .optional()?;
+
+        let mut entry = AuditEntry::new(None, "user.lockout")
+            .details(format!("{} from {}", email, ip.unwrap_or("unknown address")));
+
+        if let Some(user_id) = user_id {
+            entry = entry.target("user", user_id);
+        }
+
+        entry.record(conn)
+    });
+
+    match result {
+        Ok(()) => Box::new(error),
+        Err(e) => Box::new(e)
+    }
+}
+
+// peer address is used unless the reverse proxy is trusted, since the clients could rotate X-Forwarded-For to evade
+// the throttle. Port is dropped so that the attempts of an address are counted together.
+fn client_ip(req: &HttpRequest) -> Option<String> {
+    let trust_forwarded_for = req.app_data::<web::Data<ThrottlePolicy>>()
+        .is_some_and(|policy| policy.trust_forwarded_for);
+
+    if !trust_forwarded_for {
+        return req.peer_addr().map(|addr| addr.ip().to_string());
+    }
+
+    let connection_info = req.connection_info();
+    let addr = connection_info.realip_remote_addr()?;
+
+    Some(addr.parse::<SocketAddr>()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|_| addr.to_string()))
+}
+
+fn session_token(hash: &Hash, user: &User, session_id: ModelId, refresh_token: String) -> Result<SessionTokenResponse, Box<dyn ErrorMessaging>> {
+    let auth_token = AuthToken::new(user.id, user.role_id, session_id, ACCESS_TOKEN_TIMEOUT);
+
+    Ok(SessionTokenResponse {
+        token: hash.encode(&auth_token)?,
+        expires_at: auth_token.claims.exp,
+        refresh_token,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/sign-up",
+    tag = "auth",
+    request_body = SignUpRequest,
+    responses((status = 200, body = SuccessResponse)),
+)]
 #[post("/sign-up")]
 pub async fn sign_up(
     pool: web::Data<DBPool>,
@@ -80,7 +443,7 @@ pub async fn sign_up(
 
     let user = web::block(move || -> Result<User, Box<dyn ErrorMessaging>> {
         let result = diesel::insert_into(users::table)
-            .values(&insert_model)
+            .values((&insert_model, users::verification_sent_at.eq(Utc::now().naive_utc())))
             .get_result::<User>(&conn);
 
         match result {
@@ -96,6 +459,12 @@ pub async fn sign_up(
     })
         .await?;
 
+    send_verification_mail(&hash, &config, &client_services, user)?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+fn send_verification_mail(hash: &Hash, config: &Config, client_services: &ClientServices, user: User) -> Result<(), Box<dyn ErrorMessaging>> {
     let token = hash.encode(
         &IdentityToken::new(user.id, IdentityTokenKind::VerifyAccount, TIMEOUT)
     )?;
@@ -112,9 +481,61 @@ pub async fn sign_up(
 
     client_services.mail.send_mail(user.email, full_name, text);
 
+    Ok(())
+}
+
+/// Mails a new verification link to the user. Response does not tell whether the email belongs to an unverified
+/// user, and no mail is sent if a link has been sent to the user recently.
+#[utoipa::path(
+    post,
+    path = "/resend-verification",
+    tag = "auth",
+    request_body = ResendVerificationRequest,
+    responses((status = 200, body = SuccessResponse)),
+)]
+#[post("/resend-verification")]
+pub async fn resend_verification(
+    hash: web::Data<Hash>,
+    pool: web::Data<DBPool>,
+    config: web::Data<Arc<Config>>,
+    client_services: web::Data<ClientServices>,
+    request: SanitizedJson<ResendVerificationRequest>,
+) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    let conn = pool.get().unwrap();
+    let request = request.into_inner();
+
+    let user = web::block(move || conn.transaction::<_, Error, _>(|| {
+        let now = Utc::now().naive_utc();
+        let sent_before = now - Duration::seconds(RESEND_VERIFICATION_COOLDOWN);
+
+        // cooldown is checked within the update so that concurrent requests do not send more than one mail
+        diesel::update(users::table
+            .filter(users::email.eq(request.email))
+            .filter(users::status.eq(UserStatus::NotVerified.value()))
+            .filter(users::verification_sent_at.is_null().or(users::verification_sent_at.lt(sent_before)))
+        )
+            .set(users::verification_sent_at.eq(now))
+            .get_result::<User>(&conn)
+            .optional()
+    }))
+        .await?;
+
+    if let Some(user) = user {
+        send_verification_mail(&hash, &config, &client_services, user)?;
+    }
+
     Ok(HttpResponse::Ok().json(SuccessResponse::default()))
 }
 
+/// Mails a reset link to the user. Response does not tell whether the email belongs to a user, and no mail is
+/// sent if the user has requested too many resets recently.
+#[utoipa::path(
+    post,
+    path = "/forgot-password",
+    tag = "auth",
+    request_body = ForgotPasswordRequest,
+    responses((status = 200, body = SuccessResponse)),
+)]
 #[post("/forgot-password")]
 pub async fn forgot_password(
     hash: web::Data<Hash>,
@@ -126,29 +547,52 @@ pub async fn forgot_password(
     let conn = pool.get().unwrap();
     let request = request.into_inner();
 
-    let user = web::block(move || users::table
-        .filter(users::email.eq(request.email))
-        .select(users::all_columns)
-        .first::<User>(&conn)
-    )
+    let reset = web::block(move || conn.transaction::<_, Error, _>(|| {
+        let user = match users::table
+            .filter(users::email.eq(request.email))
+            .first::<User>(&conn)
+            .optional()? {
+            Some(user) => user,
+            None => return Ok(None)
+        };
+
+        let token = match PasswordResetToken::issue(user.id, &hash, &conn)? {
+            Some(token) => token,
+            None => return Ok(None)
+        };
+
+        AuditEntry::new(Some(user.id), "password.reset_request")
+            .record(&conn)?;
+
+        Ok(Some((user, token)))
+    }))
         .await?;
 
-    let token = hash.encode(&IdentityToken::new(user.id, IdentityTokenKind::ForgotPassword, TIMEOUT))?;
+    if let Some((user, token)) = reset {
+        let text = ForgotPasswordMailTemplate {
+            web_app_url: config.web_app_url.as_str(),
+            full_name: user.full_name().as_str(),
+            token: token.as_str(),
+        }
+            .render()
+            .map_err(|_| CoreErrorMessage::AskamaError)?;
 
-    let text = ForgotPasswordMailTemplate {
-        web_app_url: config.web_app_url.as_str(),
-        full_name: user.full_name().as_str(),
-        token: token.as_str(),
+        client_services.mail.send_mail(user.email.clone(), user.full_name(), text);
     }
-        .render()
-        .map_err(|_| CoreErrorMessage::AskamaError)?;
-
-    client_services.mail.send_mail(user.full_name(), user.email, text);
 
     Ok(HttpResponse::Ok().json(SuccessResponse::default()))
 }
 
-#[put("/reset-password")]
+/// Sets the password of the user with the token mailed by the forgot password. Token can only be used once, and
+/// the sessions of the user are revoked since the old password may have been compromised.
+#[utoipa::path(
+    post,
+    path = "/reset-password",
+    tag = "auth",
+    request_body = ResetPasswordRequest,
+    responses((status = 200, body = SuccessResponse)),
+)]
+#[post("/reset-password")]
 pub async fn reset_password(
     hash: web::Data<Hash>,
     pool: web::Data<DBPool>,
@@ -162,23 +606,24 @@ pub async fn reset_password(
     request.validate()
         .map_err(|e| ValidationError::from(e))?;
 
-    let reset_password_token = hash.decode::<IdentityToken>(request.token.as_str())
-        .map_err(|_| CoreErrorMessage::InvalidToken)?;
-
-    if reset_password_token.kind != IdentityTokenKind::ForgotPassword {
-        Err(CoreErrorMessage::InvalidToken)?;
-    }
+    let user = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let user_id = PasswordResetToken::consume(request.token.as_str(), &hash, &conn)?
+            .ok_or(CoreErrorMessage::InvalidToken)?;
 
-    let hash = hash.sign512(request.password.as_str());
+        let user = users::table.find(user_id).first::<User>(&conn)?;
 
-    let user = web::block(move || -> Result<User, Error> {
-        let user = users::table.find(reset_password_token.user_id).first::<User>(&conn)?;
         diesel::update(&user)
-            .set(users::password.eq(hash))
+            .set(users::password.eq(hash.sign512(request.password.as_str())))
+            .execute(&conn)?;
+
+        diesel::delete(sessions::table.filter(sessions::user_id.eq(user.id)))
             .execute(&conn)?;
 
+        AuditEntry::new(Some(user.id), "password.reset")
+            .record(&conn)?;
+
         Ok(user)
-    })
+    }))
         .await?;
 
     let text = ResetPasswordMailTemplate {
@@ -187,12 +632,18 @@ pub async fn reset_password(
         .render()
         .map_err(|_| CoreErrorMessage::AskamaError)?;
 
-    client_services.mail.send_mail(user.full_name(), user.email, text);
-
+    client_services.mail.send_mail(user.email.clone(), user.full_name(), text);
 
     Ok(HttpResponse::Ok().json(SuccessResponse::default()))
 }
 
+#[utoipa::path(
+    post,
+    path = "/verify-account",
+    tag = "auth",
+    request_body = VerifyAccountRequest,
+    responses((status = 200, body = SuccessResponse)),
+)]
 #[post("verify-account")]
 pub async fn verify_account(
     hash: web::Data<Hash>,
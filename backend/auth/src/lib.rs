@@ -1,5 +1,6 @@
 use actix_web::http::StatusCode;
 use actix_web::web;
+use utoipa::OpenApi;
 
 use core::error::{ErrorMessaging, HttpError};
 
@@ -7,15 +8,23 @@ mod handlers;
 mod requests;
 mod templates;
 
+#[derive(OpenApi)]
+#[openapi(paths(handlers::login, handlers::refresh_session, handlers::sign_up, handlers::forgot_password, handlers::reset_password, handlers::verify_account, handlers::resend_verification, handlers::oidc_authorize, handlers::oidc_login))]
+pub struct ApiDoc;
+
 pub fn register(config: &mut web::ServiceConfig) {
     config
         .service(
             web::scope("/api/auth")
                 .service(handlers::login)
+                .service(handlers::refresh_session)
                 .service(handlers::sign_up)
                 .service(handlers::forgot_password)
                 .service(handlers::reset_password)
                 .service(handlers::verify_account)
+                .service(handlers::resend_verification)
+                .service(handlers::oidc_authorize)
+                .service(handlers::oidc_login)
         );
 }
 
@@ -25,6 +34,9 @@ pub enum ErrorMessage {
     InvalidCredentialsOrUser,
     NotVerified,
     Banned,
+    TooManyLoginAttempts,
+    AccountLocked,
+    Deactivated,
 }
 
 impl ErrorMessaging for ErrorMessage {
@@ -49,6 +61,21 @@ impl ErrorMessaging for ErrorMessage {
                 code: StatusCode::UNPROCESSABLE_ENTITY,
                 error_code: 102,
                 message: String::from("banned"),
+            },
+            ErrorMessage::TooManyLoginAttempts => HttpError {
+                code: StatusCode::TOO_MANY_REQUESTS,
+                error_code: 103,
+                message: String::from("too_many_login_attempts"),
+            },
+            ErrorMessage::AccountLocked => HttpError {
+                code: StatusCode::LOCKED,
+                error_code: 104,
+                message: String::from("account_locked"),
+            },
+            ErrorMessage::Deactivated => HttpError {
+                code: StatusCode::FORBIDDEN,
+                error_code: 105,
+                message: String::from("account_deactivated"),
             }
         }
     }
@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 
 use serde::Deserialize;
+use utoipa::ToSchema;
 use validator::Validate;
 
 use core::models::role::Roles;
@@ -10,13 +11,15 @@ use core::utils::Hash;
 use derive::Sanitize;
 use user::models::user::UserInsert;
 
-#[derive(Debug, Deserialize, Sanitize)]
+#[derive(Debug, Deserialize, Sanitize, ToSchema)]
 pub struct LoginRequest {
     pub email: String,
     pub password: String,
+    // code of the authenticator app or a recovery code, required if the user has enabled the two factor authentication
+    pub code: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Sanitize, Validate)]
+#[derive(Debug, Deserialize, Sanitize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SignUpRequest {
     #[validate(length(max = 122))]
@@ -41,19 +44,38 @@ impl SignUpRequest {
     }
 }
 
-#[derive(Deserialize, Sanitize)]
+#[derive(Deserialize, Sanitize, ToSchema)]
 pub struct ForgotPasswordRequest {
     pub email: String
 }
 
-#[derive(Deserialize, Sanitize, Validate)]
+#[derive(Deserialize, Sanitize, Validate, ToSchema)]
 pub struct ResetPasswordRequest {
     pub token: String,
     #[validate(length(min = 8, max = 128))]
     pub password: String,
 }
 
-#[derive(Deserialize, Sanitize)]
+#[derive(Deserialize, Sanitize, ToSchema)]
+pub struct ResendVerificationRequest {
+    pub email: String
+}
+
+#[derive(Deserialize, Sanitize, ToSchema)]
 pub struct VerifyAccountRequest {
     pub token: String
-}
\ No newline at end of file
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String
+}
+/// Authorization code and the state the OpenID Connect provider redirects back with
+#[derive(Deserialize, ToSchema)]
+pub struct OidcLoginRequest {
+    pub code: String,
+    pub state: String,
+    // code of the authenticator app or a recovery code, required if the user has enabled the two factor authentication
+    pub two_factor_code: Option<String>,
+}
@@ -0,0 +1,22 @@
+use clap::ArgMatches;
+
+use uuid::Uuid;
+
+/// Parses the value of the argument, exits with a usage error if it is not a valid id.
+pub fn id(matches: &ArgMatches, name: &str) -> Uuid {
+    parse(matches.value_of(name).unwrap(), "id")
+}
+
+pub fn ids(matches: &ArgMatches, name: &str) -> Vec<Uuid> {
+    matches.values_of(name)
+        .map_or_else(Vec::new, |values| values.map(|value| parse(value, "id")).collect())
+}
+
+pub fn number(matches: &ArgMatches, name: &str) -> i64 {
+    parse(matches.value_of(name).unwrap(), "number")
+}
+
+fn parse<T: std::str::FromStr>(value: &str, kind: &str) -> T {
+    value.parse()
+        .unwrap_or_else(|_| clap::Error::value_validation_auto(format!("{} is not a valid {}", value, kind)).exit())
+}
@@ -0,0 +1,125 @@
+use awc::http::Method;
+use serde::Serialize;
+use uuid::Uuid;
+
+use cli::client::{Client, Error};
+use cli::models::{ClaimCode, Job, ModelId, ProvisionedRunner, Runner, RunnerRequest, SlimJob, TokenResponse};
+
+// audit logs are fetched in pages of this size while exporting
+const AUDIT_LOG_PAGE: i64 = 1000;
+
+pub async fn list_runners(client: &Client) -> Result<i32, Error> {
+    let runners = client.get::<Vec<Runner>>("experiment/runners").await?;
+
+    for runner in runners {
+        let state = match (runner.disabled, runner.online) {
+            (true, _) => "disabled",
+            (false, true) => "online",
+            (false, false) => "offline"
+        };
+
+        println!("{}\t{}\t{}\t{} queued\t{}", runner.id, runner.name, state, runner.queue_length, runner.labels.join(","));
+    }
+
+    Ok(0)
+}
+
+/// Creates a runner and prints its id and token, the token is given to the runner for connecting.
+pub async fn provision_runner(client: &Client, name: &str, labels: Option<Vec<&str>>) -> Result<i32, Error> {
+    let provisioned = client.send::<_, ProvisionedRunner>(Method::POST, "experiment/admin/runner", &RunnerRequest { name, labels })
+        .await?;
+
+    println!("{}\t{}", provisioned.runner_id, provisioned.token);
+
+    Ok(0)
+}
+
+/// Issues a new token for the runner, previous token of the runner stops working.
+pub async fn issue_access_key(client: &Client, runner_id: Uuid) -> Result<i32, Error> {
+    let response = client.post::<TokenResponse>(format!("experiment/admin/runner/{}/access-key", runner_id).as_str(), &[])
+        .await?;
+
+    println!("{}", response.token);
+
+    Ok(0)
+}
+
+pub async fn create_claim_code(client: &Client, name: &str, labels: Option<Vec<&str>>) -> Result<i32, Error> {
+    let claim_code = client.send::<_, ClaimCode>(Method::POST, "experiment/admin/claim-code", &RunnerRequest { name, labels })
+        .await?;
+
+    println!("{}\texpires at {}", claim_code.code, claim_code.expires_at);
+
+    Ok(0)
+}
+
+pub async fn list_stuck_jobs(client: &Client, older_than: Option<i64>) -> Result<i32, Error> {
+    let path = match older_than {
+        Some(older_than) => format!("experiment/admin/jobs/stuck?olderThan={}", older_than),
+        None => "experiment/admin/jobs/stuck".to_string()
+    };
+
+    let jobs = client.get::<Vec<SlimJob>>(path.as_str()).await?;
+
+    for job in jobs {
+        let runner = job.runner_id.map_or_else(|| "-".to_string(), |runner_id| runner_id.to_string());
+        let since = job.started_at.unwrap_or(job.created_at);
+
+        println!("{}\texperiment {}\trunner {}\t{} since {}", job.id, job.experiment_id, runner, job.status, since);
+    }
+
+    Ok(0)
+}
+
+/// Requeues the jobs one by one and prints the ids of the new jobs, continues with the rest if one of them
+/// can not be requeued.
+pub async fn requeue_jobs(client: &Client, job_ids: Vec<Uuid>, runner_id: Option<Uuid>) -> Result<i32, Error> {
+    let mut code = 0;
+
+    for job_id in job_ids {
+        let mut path = format!("experiment/admin/job/{}/requeue", job_id);
+
+        if let Some(runner_id) = runner_id {
+            path = format!("{}?runnerId={}", path, runner_id);
+        }
+
+        match client.post::<Job>(path.as_str(), &[]).await {
+            Ok(job) => println!("{}\t{}", job_id, job.id),
+            Err(e) => {
+                eprintln!("job {} could not be requeued, {}", job_id, e);
+                code = 1;
+            }
+        }
+    }
+
+    Ok(code)
+}
+
+#[derive(Serialize)]
+struct AuditLogsQuery<'a> {
+    after: ModelId,
+    limit: i64,
+    since: Option<&'a str>,
+    until: Option<&'a str>,
+    action: Option<&'a str>,
+}
+
+/// Writes the audit log entries to stdout as JSON lines, in the order they are recorded. Time range is
+/// given in UTC, e.g. 2021-01-01T00:00:00.
+pub async fn export_audit_logs(client: &Client, since: Option<&str>, until: Option<&str>, action: Option<&str>) -> Result<i32, Error> {
+    let mut after = 0;
+
+    loop {
+        let query = AuditLogsQuery { after, limit: AUDIT_LOG_PAGE, since, until, action };
+        let entries = client.get_query::<_, Vec<serde_json::Value>>("experiment/admin/audit-logs", &query).await?;
+
+        for entry in &entries {
+            println!("{}", entry);
+        }
+
+        match entries.last().and_then(|entry| entry["id"].as_i64()) {
+            Some(id) if entries.len() as i64 == AUDIT_LOG_PAGE => after = id as ModelId,
+            _ => return Ok(0)
+        }
+    }
+}
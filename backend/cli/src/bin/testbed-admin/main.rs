@@ -0,0 +1,150 @@
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+
+use cli::args::{id, ids, number};
+use cli::client::{Client, Error};
+
+mod commands;
+
+fn app() -> App<'static, 'static> {
+    let labels = || Arg::with_name("label")
+        .long("label")
+        .value_name("LABEL")
+        .help("Label of the runner, can be given multiple times")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1);
+
+    App::new("testbed-admin")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Operates the testbed server, requires the api key of an admin")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(Arg::with_name("server-url")
+            .long("server-url")
+            .value_name("URL")
+            .help("Server url, e.g. https://testbed.example.com")
+            .env("TESTBED_SERVER_URL")
+            .required(true)
+            .takes_value(true))
+        .arg(Arg::with_name("api-key")
+            .long("api-key")
+            .value_name("KEY")
+            .help("Api key of an admin user")
+            .env("TESTBED_API_KEY")
+            .hide_env_values(true)
+            .required(true)
+            .takes_value(true))
+        .subcommand(SubCommand::with_name("runners")
+            .about("Manages the runners")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("list")
+                .about("Lists the runners with their state"))
+            .subcommand(SubCommand::with_name("provision")
+                .about("Creates a runner and prints its id and token")
+                .arg(Arg::with_name("name")
+                    .value_name("NAME")
+                    .required(true))
+                .arg(labels()))
+            .subcommand(SubCommand::with_name("issue-key")
+                .about("Issues a new token for the runner, previous token stops working")
+                .arg(Arg::with_name("runner-id")
+                    .value_name("RUNNER_ID")
+                    .required(true))))
+        .subcommand(SubCommand::with_name("claim-codes")
+            .about("Manages the claim codes of the new runners")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("create")
+                .about("Creates a one-time code which a runner enrolls itself with")
+                .arg(Arg::with_name("name")
+                    .value_name("NAME")
+                    .help("Name of the runner enrolled with the code")
+                    .required(true))
+                .arg(labels())))
+        .subcommand(SubCommand::with_name("jobs")
+            .about("Manages the jobs of all users")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("stuck")
+                .about("Lists the jobs pending or running for longer than expected")
+                .arg(Arg::with_name("older-than")
+                    .long("older-than")
+                    .value_name("SECONDS")
+                    .help("Jobs pending or running for longer than this are listed, an hour by default")
+                    .takes_value(true)))
+            .subcommand(SubCommand::with_name("requeue")
+                .about("Queues a copy of the jobs and cancels the unfinished ones, prints the ids of the new jobs")
+                .arg(Arg::with_name("job-id")
+                    .value_name("JOB_ID")
+                    .required(true)
+                    .multiple(true))
+                .arg(Arg::with_name("runner")
+                    .long("runner")
+                    .value_name("RUNNER_ID")
+                    .help("Runner the jobs are queued for, previous runner of each job if it is not given")
+                    .takes_value(true))))
+        .subcommand(SubCommand::with_name("audit")
+            .about("Reads the audit log")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("export")
+                .about("Writes the audit log entries as JSON lines")
+                .arg(Arg::with_name("since")
+                    .long("since")
+                    .value_name("TIME")
+                    .help("Entries recorded at or after this UTC time, e.g. 2021-01-01T00:00:00")
+                    .takes_value(true))
+                .arg(Arg::with_name("until")
+                    .long("until")
+                    .value_name("TIME")
+                    .help("Entries recorded before this UTC time")
+                    .takes_value(true))
+                .arg(Arg::with_name("action")
+                    .long("action")
+                    .value_name("ACTION")
+                    .help("Only the entries of the action, e.g. runner.provision")
+                    .takes_value(true))))
+}
+
+async fn execute(client: Client, matches: ArgMatches<'static>) -> Result<i32, Error> {
+    match matches.subcommand() {
+        ("runners", Some(matches)) => match matches.subcommand() {
+            ("list", Some(_)) => commands::list_runners(&client).await,
+            ("provision", Some(matches)) => commands::provision_runner(&client, matches.value_of("name").unwrap(), matches.values_of("label").map(Iterator::collect)).await,
+            ("issue-key", Some(matches)) => commands::issue_access_key(&client, id(matches, "runner-id")).await,
+            _ => unreachable!()
+        },
+        ("claim-codes", Some(matches)) => match matches.subcommand() {
+            ("create", Some(matches)) => commands::create_claim_code(&client, matches.value_of("name").unwrap(), matches.values_of("label").map(Iterator::collect)).await,
+            _ => unreachable!()
+        },
+        ("jobs", Some(matches)) => match matches.subcommand() {
+            ("stuck", Some(matches)) => {
+                let older_than = matches.value_of("older-than").map(|_| number(matches, "older-than"));
+
+                commands::list_stuck_jobs(&client, older_than).await
+            }
+            ("requeue", Some(matches)) => {
+                let runner_id = matches.value_of("runner").map(|_| id(matches, "runner"));
+
+                commands::requeue_jobs(&client, ids(matches, "job-id"), runner_id).await
+            }
+            _ => unreachable!()
+        },
+        ("audit", Some(matches)) => match matches.subcommand() {
+            ("export", Some(matches)) => commands::export_audit_logs(&client, matches.value_of("since"), matches.value_of("until"), matches.value_of("action")).await,
+            _ => unreachable!()
+        },
+        _ => unreachable!()
+    }
+}
+
+fn main() {
+    let matches = app().get_matches();
+
+    let client = Client::new(matches.value_of("server-url").unwrap(), matches.value_of("api-key").unwrap().to_string());
+
+    let code = actix_rt::System::new("testbed-admin").block_on(execute(client, matches))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            1
+        });
+
+    std::process::exit(code);
+}
@@ -0,0 +1,214 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_codec::Framed;
+use awc::{BoxedSocket, ClientRequest, ClientResponse, Connector};
+use awc::error::{PayloadError, SendRequestError, WsClientError, WsProtocolError};
+use awc::http::{Method, StatusCode};
+use awc::ws::{Codec, Frame, Message};
+use bytes::Bytes;
+use futures::{SinkExt, Stream, StreamExt};
+use rustls::ClientConfig;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::models::Notification;
+
+// json responses of the api, e.g. an experiment along with its code, are far below this
+const MAX_JSON_SIZE: usize = 16 * 1024 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Client of the REST and websocket api, requests are authenticated with the api key of the user.
+pub struct Client {
+    client: awc::Client,
+    server_url: String,
+    api_key: String,
+}
+
+impl Client {
+    pub fn new(server_url: &str, api_key: String) -> Self {
+        let mut config = ClientConfig::new();
+        config.root_store.add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+
+        if let Ok(ca_file) = std::env::var("TLS_CA_FILE") {
+            let file = std::fs::File::open(ca_file).expect("Failed to open TLS_CA_FILE");
+            config.root_store.add_pem_file(&mut std::io::BufReader::new(file))
+                .expect("Failed to read TLS_CA_FILE");
+        }
+
+        let client = awc::Client::builder()
+            .connector(Connector::new().rustls(Arc::new(config)).finish())
+            .timeout(REQUEST_TIMEOUT)
+            .finish();
+
+        Client {
+            client,
+            server_url: server_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let response = self.request(Method::GET, path)
+            .send()
+            .await
+            .map_err(Error::Send)?;
+
+        parse(response).await
+    }
+
+    /// Same as `get`, query of the path is built from the given value.
+    pub async fn get_query<Q: Serialize, T: DeserializeOwned>(&self, path: &str, query: &Q) -> Result<T, Error> {
+        let response = self.request(Method::GET, path)
+            .query(query)
+            .map_err(|e| Error::Request(e.to_string()))?
+            .send()
+            .await
+            .map_err(Error::Send)?;
+
+        parse(response).await
+    }
+
+    pub async fn send<B: Serialize, T: DeserializeOwned>(&self, method: Method, path: &str, body: &B) -> Result<T, Error> {
+        let response = self.request(method, path)
+            .send_json(body)
+            .await
+            .map_err(Error::Send)?;
+
+        parse(response).await
+    }
+
+    pub async fn post<T: DeserializeOwned>(&self, path: &str, headers: &[(&str, &str)]) -> Result<T, Error> {
+        let mut request = self.request(Method::POST, path);
+
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(Error::Send)?;
+
+        parse(response).await
+    }
+
+    /// Plain text body of the path, e.g. the output of a job, chunks are yielded as they arrive.
+    pub async fn text(&self, path: &str) -> Result<impl Stream<Item=Result<Bytes, PayloadError>> + Unpin, Error> {
+        let response = self.request(Method::GET, path)
+            // outputs of the long jobs may take a while to be transferred
+            .timeout(Duration::from_secs(600))
+            .send()
+            .await
+            .map_err(Error::Send)?;
+
+        if !response.status().is_success() {
+            return Err(status(response).await);
+        }
+
+        Ok(response)
+    }
+
+    /// Connects to the notifications of the user, the job updates are received over it.
+    pub async fn notifications(&self) -> Result<Notifications, Error> {
+        let (_, framed) = self.client.ws(self.url("experiment/notifications"))
+            .bearer_auth(&self.api_key)
+            .connect()
+            .await
+            .map_err(Error::Websocket)?;
+
+        Ok(Notifications { framed })
+    }
+
+    fn request(&self, method: Method, path: &str) -> ClientRequest {
+        self.client.request(method, self.url(path))
+            .bearer_auth(&self.api_key)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/{}", self.server_url, path)
+    }
+}
+
+pub struct Notifications {
+    framed: Framed<BoxedSocket, Codec>,
+}
+
+impl Notifications {
+    /// Waits for the next notification, returns None once the server closes the connection.
+    pub async fn next(&mut self) -> Result<Option<Notification>, Error> {
+        while let Some(frame) = self.framed.next().await {
+            match frame.map_err(Error::Protocol)? {
+                Frame::Text(text) => match serde_json::from_slice(&text) {
+                    Ok(notification) => return Ok(Some(notification)),
+                    // notifications added to the server later are skipped
+                    Err(_) => continue
+                },
+                // server closes the connection if its heartbeats are not answered
+                Frame::Ping(bytes) => self.framed.send(Message::Pong(bytes)).await.map_err(Error::Protocol)?,
+                Frame::Close(_) => return Ok(None),
+                _ => {}
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+async fn parse<S, T>(mut response: ClientResponse<S>) -> Result<T, Error>
+    where S: Stream<Item=Result<Bytes, PayloadError>> + Unpin,
+          T: DeserializeOwned {
+    if !response.status().is_success() {
+        return Err(status(response).await);
+    }
+
+    response.json::<T>()
+        .limit(MAX_JSON_SIZE)
+        .await
+        .map_err(|e| Error::Payload(e.to_string()))
+}
+
+async fn status<S>(mut response: ClientResponse<S>) -> Error
+    where S: Stream<Item=Result<Bytes, PayloadError>> + Unpin {
+    let message = response.json::<ErrorResponse>()
+        .await
+        .ok()
+        .map(|error| error.message);
+
+    Error::Status(response.status(), message)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Request(String),
+    Send(SendRequestError),
+    Websocket(WsClientError),
+    Protocol(WsProtocolError),
+    Status(StatusCode, Option<String>),
+    Payload(String),
+    Closed,
+    Read(std::io::Error),
+    Write(std::io::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Request(e) => write!(f, "building request is failed, {}", e),
+            Error::Send(e) => write!(f, "sending request is failed, {}", e),
+            Error::Websocket(e) => write!(f, "connecting to notifications is failed, {}", e),
+            Error::Protocol(e) => write!(f, "notifications connection is broken, {}", e),
+            Error::Status(status, Some(message)) => write!(f, "request is rejected with status {}, {}", status, message),
+            Error::Status(status, None) => write!(f, "request is rejected with status {}", status),
+            Error::Payload(e) => write!(f, "invalid response, {}", e),
+            Error::Closed => write!(f, "notifications connection is closed by the server"),
+            Error::Read(e) => write!(f, "reading code is failed, {}", e),
+            Error::Write(e) => write!(f, "writing output is failed, {}", e),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+use awc::http::Method;
+use futures::StreamExt;
+use uuid::Uuid;
+
+use cli::client::{Client, Error};
+use cli::models::{Experiment, ExperimentCodeRequest, ExperimentNameRequest, is_finished, Job, Notification, Pagination};
+
+pub async fn list_experiments(client: &Client, page: i64, per_page: i64) -> Result<i32, Error> {
+    let experiments = client.get::<Pagination<Experiment>>(format!("experiment/experiments?page={}&perPage={}", page, per_page).as_str())
+        .await?;
+
+    for experiment in &experiments.items {
+        println!("{}\t{}\t{}", experiment.id, experiment.name, experiment.updated_at);
+    }
+
+    eprintln!("page {} of {}, {} experiments in total", experiments.page, experiments.total_pages, experiments.total);
+
+    Ok(0)
+}
+
+/// Creates the experiment and pushes its code if it is given, prints the id of the experiment.
+pub async fn create_experiment(client: &Client, name: &str, code_file: Option<&str>) -> Result<i32, Error> {
+    let experiment = client.send::<_, Experiment>(Method::POST, "experiment/experiment", &ExperimentNameRequest { name })
+        .await?;
+
+    if let Some(code_file) = code_file {
+        push_code(client, experiment.id, code_file).await?;
+    }
+
+    println!("{}", experiment.id);
+
+    Ok(0)
+}
+
+/// Replaces the code of the experiment with the content of the file, `-` reads the code from stdin.
+pub async fn push_code(client: &Client, experiment_id: Uuid, code_file: &str) -> Result<i32, Error> {
+    let code = match code_file {
+        "-" => {
+            let mut code = String::new();
+            std::io::stdin().read_to_string(&mut code).map_err(Error::Read)?;
+            code
+        }
+        file => std::fs::read_to_string(file).map_err(Error::Read)?
+    };
+
+    client.send::<_, serde_json::Value>(Method::PUT, format!("experiment/experiment/{}/code", experiment_id).as_str(), &ExperimentCodeRequest { code })
+        .await?;
+
+    Ok(0)
+}
+
+/// Runs the experiment on the runner and prints the id of the job. Output of the job is printed once it
+/// finishes if waiting is requested.
+pub async fn run(client: &Client, experiment_id: Uuid, runner_id: Uuid, hooks: Option<&str>, idempotency_key: Option<&str>, wait: bool)
+                 -> Result<i32, Error> {
+    let mut path = format!("experiment/experiment/{}/run/{}", experiment_id, runner_id);
+
+    if let Some(hooks) = hooks {
+        path = format!("{}?hooks={}", path, hooks);
+    }
+
+    let headers = idempotency_key.map_or_else(Vec::new, |key| vec![("Idempotency-Key", key)]);
+
+    let job = client.post::<Job>(path.as_str(), &headers)
+        .await?;
+
+    println!("{}", job.id);
+
+    if !wait {
+        return Ok(0);
+    }
+
+    tail_logs(client, job.id, None).await
+}
+
+/// Prints the status changes and the queue positions of the jobs. If no job is given, every job of the user,
+/// or of the experiment if it is given, is watched until the connection is closed. Otherwise, it returns
+/// once all of the given jobs are finished, exit code tells whether all of them are successful.
+pub async fn watch_jobs(client: &Client, job_ids: Vec<Uuid>, experiment_id: Option<Uuid>) -> Result<i32, Error> {
+    // subscribed before fetching the jobs, so that no status change is missed in between
+    let mut notifications = client.notifications().await?;
+
+    let mut watched = HashSet::new();
+    let mut failed = false;
+
+    for job_id in &job_ids {
+        let job = client.get::<Job>(format!("experiment/job/{}", job_id).as_str()).await?;
+
+        println!("job {} (experiment {}): {}", job.id, job.experiment_id, describe_status(&job.status, job.failure_reason.as_deref()));
+
+        if !job.is_finished() {
+            watched.insert(job.id);
+        } else if job.status != "Successful" {
+            failed = true;
+        }
+    }
+
+    while job_ids.is_empty() || !watched.is_empty() {
+        let notification = match notifications.next().await? {
+            Some(notification) => notification,
+            None => break
+        };
+
+        if !job_ids.is_empty() && !watched.contains(&notification.job_id()) {
+            continue;
+        }
+
+        if matches!(experiment_id, Some(experiment_id) if experiment_id != notification.experiment_id()) {
+            continue;
+        }
+
+        match notification {
+            Notification::JobStatus(status) => {
+                println!("job {} (experiment {}): {}", status.job_id, status.experiment_id, describe_status(&status.status, status.failure_reason.as_deref()));
+
+                if is_finished(status.status.as_str()) && watched.remove(&status.job_id) && status.status != "Successful" {
+                    failed = true;
+                }
+            }
+            Notification::QueueInfo(info) => match info.eta_seconds {
+                Some(eta) => println!("job {} (experiment {}): queued at position {}, starts in about {}s", info.job_id, info.experiment_id, info.position, eta.round()),
+                None => println!("job {} (experiment {}): queued at position {}", info.job_id, info.experiment_id, info.position),
+            }
+        }
+    }
+
+    Ok(if failed { 1 } else { 0 })
+}
+
+/// Prints the output of the job, or one of its named streams. Runners report the output along with the
+/// result, so it waits until the job finishes. Exit code tells whether the job is successful.
+pub async fn tail_logs(client: &Client, job_id: Uuid, stream: Option<&str>) -> Result<i32, Error> {
+    // subscribed before fetching the job, so that its finish is not missed in between
+    let mut notifications = client.notifications().await?;
+
+    let job = client.get::<Job>(format!("experiment/job/{}", job_id).as_str()).await?;
+    let mut status = job.status;
+
+    while !is_finished(status.as_str()) {
+        eprintln!("job {} is {}, waiting for it to finish", job_id, status.to_lowercase());
+
+        status = loop {
+            match notifications.next().await? {
+                Some(Notification::JobStatus(notification)) if notification.job_id == job_id => break notification.status,
+                Some(_) => continue,
+                None => return Err(Error::Closed)
+            }
+        };
+    }
+
+    let path = match stream {
+        Some(stream) => format!("experiment/job/{}/stream/{}", job_id, stream),
+        None => format!("experiment/job/{}/output", job_id)
+    };
+
+    let mut output = client.text(path.as_str()).await?;
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+
+    while let Some(chunk) = output.next().await {
+        let chunk = chunk.map_err(|e| Error::Payload(e.to_string()))?;
+        stdout.write_all(&chunk).map_err(Error::Write)?;
+    }
+
+    stdout.flush().map_err(Error::Write)?;
+
+    if status != "Successful" {
+        eprintln!("job {} is {}", job_id, status.to_lowercase());
+        return Ok(1);
+    }
+
+    Ok(0)
+}
+
+fn describe_status(status: &str, failure_reason: Option<&str>) -> String {
+    match failure_reason {
+        Some(reason) => format!("{} ({})", status, reason),
+        None => status.to_string()
+    }
+}
@@ -0,0 +1,157 @@
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+
+use cli::args::{id, ids, number};
+use cli::client::{Client, Error};
+
+mod commands;
+
+fn app() -> App<'static, 'static> {
+    let job_id = || Arg::with_name("job-id")
+        .value_name("JOB_ID")
+        .required(true);
+
+    App::new("testbed-cli")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Manages the experiments and the jobs of a testbed user")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(Arg::with_name("server-url")
+            .long("server-url")
+            .value_name("URL")
+            .help("Server url, e.g. https://testbed.example.com")
+            .env("TESTBED_SERVER_URL")
+            .required(true)
+            .takes_value(true))
+        .arg(Arg::with_name("api-key")
+            .long("api-key")
+            .value_name("KEY")
+            .help("Api key of the user, created from the profile")
+            .env("TESTBED_API_KEY")
+            .hide_env_values(true)
+            .required(true)
+            .takes_value(true))
+        .subcommand(SubCommand::with_name("experiment")
+            .about("Manages the experiments")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("list")
+                .about("Lists the experiments, latest first")
+                .arg(Arg::with_name("page")
+                    .long("page")
+                    .value_name("PAGE")
+                    .default_value("1"))
+                .arg(Arg::with_name("per-page")
+                    .long("per-page")
+                    .value_name("COUNT")
+                    .default_value("20")))
+            .subcommand(SubCommand::with_name("create")
+                .about("Creates an experiment and prints its id")
+                .arg(Arg::with_name("name")
+                    .value_name("NAME")
+                    .required(true))
+                .arg(Arg::with_name("code")
+                    .long("code")
+                    .value_name("FILE")
+                    .help("Code of the experiment, - reads it from stdin")
+                    .takes_value(true)))
+            .subcommand(SubCommand::with_name("push-code")
+                .about("Replaces the code of an experiment")
+                .arg(Arg::with_name("experiment-id")
+                    .value_name("EXPERIMENT_ID")
+                    .required(true))
+                .arg(Arg::with_name("file")
+                    .value_name("FILE")
+                    .help("Code of the experiment, - reads it from stdin")
+                    .required(true))))
+        .subcommand(SubCommand::with_name("run")
+            .about("Runs an experiment on a runner and prints the id of the job")
+            .arg(Arg::with_name("experiment-id")
+                .value_name("EXPERIMENT_ID")
+                .required(true))
+            .arg(Arg::with_name("runner-id")
+                .value_name("RUNNER_ID")
+                .required(true))
+            .arg(Arg::with_name("hooks")
+                .long("hooks")
+                .value_name("HOOKS")
+                .help("Comma separated hooks of the runner run around the job, e.g. power_cycle")
+                .takes_value(true))
+            .arg(Arg::with_name("idempotency-key")
+                .long("idempotency-key")
+                .value_name("KEY")
+                .help("Retrying with the same key returns the job of the first request instead of creating a new one")
+                .takes_value(true))
+            .arg(Arg::with_name("wait")
+                .long("wait")
+                .help("Waits for the job to finish and prints its output, exits with 1 if the job is not successful")))
+        .subcommand(SubCommand::with_name("jobs")
+            .about("Follows the jobs")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("watch")
+                .about("Prints the status changes of the jobs, until the given jobs finish")
+                .arg(job_id()
+                    .required(false)
+                    .multiple(true))
+                .arg(Arg::with_name("experiment")
+                    .long("experiment")
+                    .value_name("EXPERIMENT_ID")
+                    .help("Only the jobs of the experiment are watched")
+                    .takes_value(true))))
+        .subcommand(SubCommand::with_name("logs")
+            .about("Prints the logs of the jobs")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("tail")
+                .about("Waits for the job to finish and prints its output, exits with 1 if the job is not successful")
+                .arg(job_id())
+                .arg(Arg::with_name("stream")
+                    .long("stream")
+                    .value_name("NAME")
+                    .help("Named output stream of the job instead of its output, e.g. serial")
+                    .takes_value(true))))
+}
+
+async fn execute(client: Client, matches: ArgMatches<'static>) -> Result<i32, Error> {
+    match matches.subcommand() {
+        ("experiment", Some(matches)) => match matches.subcommand() {
+            ("list", Some(matches)) => commands::list_experiments(&client, number(matches, "page"), number(matches, "per-page")).await,
+            ("create", Some(matches)) => commands::create_experiment(&client, matches.value_of("name").unwrap(), matches.value_of("code")).await,
+            ("push-code", Some(matches)) => commands::push_code(&client, id(matches, "experiment-id"), matches.value_of("file").unwrap()).await,
+            _ => unreachable!()
+        },
+        ("run", Some(matches)) => commands::run(
+            &client,
+            id(matches, "experiment-id"),
+            id(matches, "runner-id"),
+            matches.value_of("hooks"),
+            matches.value_of("idempotency-key"),
+            matches.is_present("wait"),
+        )
+            .await,
+        ("jobs", Some(matches)) => match matches.subcommand() {
+            ("watch", Some(matches)) => {
+                let job_ids = ids(matches, "job-id");
+                let experiment_id = matches.value_of("experiment").map(|_| id(matches, "experiment"));
+
+                commands::watch_jobs(&client, job_ids, experiment_id).await
+            }
+            _ => unreachable!()
+        },
+        ("logs", Some(matches)) => match matches.subcommand() {
+            ("tail", Some(matches)) => commands::tail_logs(&client, id(matches, "job-id"), matches.value_of("stream")).await,
+            _ => unreachable!()
+        },
+        _ => unreachable!()
+    }
+}
+
+fn main() {
+    let matches = app().get_matches();
+
+    let client = Client::new(matches.value_of("server-url").unwrap(), matches.value_of("api-key").unwrap().to_string());
+
+    let code = actix_rt::System::new("testbed-cli").block_on(execute(client, matches))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            1
+        });
+
+    std::process::exit(code);
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub type ModelId = i32;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination<T> {
+    pub total: i64,
+    pub page: i64,
+    pub total_pages: i64,
+    pub items: Vec<T>,
+}
+
+#[derive(Deserialize)]
+pub struct Experiment {
+    pub id: Uuid,
+    pub name: String,
+    pub updated_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct Job {
+    pub id: Uuid,
+    pub experiment_id: Uuid,
+    pub status: String,
+    pub failure_reason: Option<String>,
+}
+
+impl Job {
+    pub fn is_finished(&self) -> bool {
+        is_finished(self.status.as_str())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", content = "data")]
+pub enum Notification {
+    JobStatus(JobStatusNotification),
+    QueueInfo(QueueInfoNotification),
+}
+
+impl Notification {
+    pub fn job_id(&self) -> Uuid {
+        match self {
+            Notification::JobStatus(status) => status.job_id,
+            Notification::QueueInfo(info) => info.job_id,
+        }
+    }
+
+    pub fn experiment_id(&self) -> Uuid {
+        match self {
+            Notification::JobStatus(status) => status.experiment_id,
+            Notification::QueueInfo(info) => info.experiment_id,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatusNotification {
+    pub job_id: Uuid,
+    pub experiment_id: Uuid,
+    pub status: String,
+    pub failure_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueInfoNotification {
+    pub job_id: Uuid,
+    pub experiment_id: Uuid,
+    pub position: usize,
+    pub eta_seconds: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct ExperimentNameRequest<'a> {
+    pub name: &'a str,
+}
+
+#[derive(Serialize)]
+pub struct ExperimentCodeRequest {
+    pub code: String,
+}
+
+/// Jobs do not change their status once they are in any of the statuses other than pending and running
+pub fn is_finished(status: &str) -> bool {
+    status != "Pending" && status != "Running"
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Runner {
+    pub id: Uuid,
+    pub name: String,
+    pub labels: Vec<String>,
+    pub online: bool,
+    pub queue_length: i64,
+    pub disabled: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlimJob {
+    pub id: Uuid,
+    pub experiment_id: Uuid,
+    pub runner_id: Option<Uuid>,
+    pub status: String,
+    pub created_at: String,
+    pub started_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProvisionedRunner {
+    pub runner_id: Uuid,
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimCode {
+    pub code: String,
+    pub expires_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct TokenResponse {
+    pub token: String,
+}
+
+#[derive(Serialize)]
+pub struct RunnerRequest<'a> {
+    pub name: &'a str,
+    pub labels: Option<Vec<&'a str>>,
+}
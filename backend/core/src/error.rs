@@ -8,12 +8,16 @@ use diesel::result::{DatabaseErrorKind, Error};
 pub use jsonwebtoken::Algorithm;
 use jsonwebtoken::errors::Error as JWTErrors;
 use serde::{ser::SerializeStruct, Serialize, Serializer};
+use utoipa::ToSchema;
 use validator::ValidationErrors;
 
 use crate::ErrorMessage;
 
-#[derive(Debug)]
+/// Body of the error responses, `errorCode` identifies the error independently of the message.
+#[derive(Debug, ToSchema)]
+#[schema(rename_all = "camelCase")]
 pub struct HttpError {
+    #[schema(value_type = u16)]
     pub code: StatusCode,
     pub error_code: i32,
     pub message: String,
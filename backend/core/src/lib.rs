@@ -37,6 +37,7 @@ pub enum ErrorMessage {
     HashFailed,
     AskamaError,
     InvalidOperationForStatus,
+    NotAllowed,
 }
 
 impl ErrorMessaging for ErrorMessage {
@@ -106,6 +107,11 @@ impl ErrorMessaging for ErrorMessage {
                 code: StatusCode::BAD_REQUEST,
                 error_code: 113,
                 message: String::from("web_socket_connection_error"),
+            },
+            ErrorMessage::NotAllowed => HttpError {
+                code: StatusCode::FORBIDDEN,
+                error_code: 114,
+                message: String::from("not_allowed"),
             }
         }
     }
@@ -117,6 +123,20 @@ pub struct Config {
     pub storage_path: String,
 }
 
+/// Cross origin and security header settings of the responses, so that the web app can be served from another
+/// origin safely
+#[derive(Clone)]
+pub struct SecurityConfig {
+    // origins allowed to make cross origin requests, e.g. the origin of the web app
+    pub allowed_origins: Vec<String>,
+    // whether the browsers may send the credentials, e.g. the cookies, with the cross origin requests
+    pub allow_credentials: bool,
+    // Strict-Transport-Security is only sent if it is given, give it once the app is served over https
+    pub hsts_max_age: Option<u64>,
+    // value of the X-Frame-Options header, e.g. DENY or SAMEORIGIN
+    pub frame_options: String,
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
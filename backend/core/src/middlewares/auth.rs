@@ -5,7 +5,7 @@ use futures::task::{Context, Poll};
 use regex::Regex;
 
 use crate::ErrorMessage;
-use crate::models::token::AuthToken;
+use crate::models::token::{Audience, AuthToken, Scope};
 use crate::utils::{Hash, JWTErrorKind};
 use crate::error::ErrorMessaging;
 
@@ -86,7 +86,7 @@ fn parse_user(req: &ServiceRequest) -> Result<AuthToken, ErrorMessage> {
     };
 
 
-    let auth_token = hash.decode::<AuthToken>(token.as_str())
+    let auth_token = hash.decode_for::<AuthToken>(token.as_str(), Audience::User)
         .map_err(|e| {
             match e.kind() {
                 JWTErrorKind::ExpiredSignature => ErrorMessage::ExpiredToken,
@@ -94,5 +94,10 @@ fn parse_user(req: &ServiceRequest) -> Result<AuthToken, ErrorMessage> {
             }
         })?;
 
+    if !auth_token.claims.has_scope(Scope::Session) && !auth_token.claims.has_scope(Scope::ApiKey)
+        && !auth_token.claims.has_scope(Scope::Impersonation) {
+        return Err(ErrorMessage::InvalidToken);
+    }
+
     Ok(auth_token)
 }
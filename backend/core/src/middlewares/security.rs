@@ -0,0 +1,35 @@
+use actix_cors::Cors;
+use actix_web::http::header;
+use actix_web::middleware::DefaultHeaders;
+
+use crate::SecurityConfig;
+
+/// Cross origin requests are only allowed from the configured origins, e.g. the origin of the web app
+pub fn cors(config: &SecurityConfig) -> Cors {
+    let mut cors = config.allowed_origins.iter()
+        .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin.as_str()))
+        .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
+        .allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT, header::CONTENT_TYPE])
+        .allowed_header("enctype")
+        .allowed_header("idempotency-key")
+        .max_age(60);
+
+    if config.allow_credentials {
+        cors = cors.supports_credentials();
+    }
+
+    cors
+}
+
+/// Headers sent with every response unless the handler sets them itself
+pub fn security_headers(config: &SecurityConfig) -> DefaultHeaders {
+    let headers = DefaultHeaders::new()
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .header(header::X_FRAME_OPTIONS, config.frame_options.as_str())
+        .header(header::REFERRER_POLICY, "no-referrer");
+
+    match config.hsts_max_age {
+        Some(max_age) => headers.header(header::STRICT_TRANSPORT_SECURITY, format!("max-age={}; includeSubDomains", max_age)),
+        None => headers
+    }
+}
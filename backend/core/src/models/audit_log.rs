@@ -0,0 +1,59 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::schema::audit_logs;
+use crate::types::{ModelId, UserId};
+
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLog {
+    pub id: ModelId,
+    // user performing the action, missing if the user is deleted or the action is not performed by a user
+    pub actor_id: Option<UserId>,
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<ModelId>,
+    pub details: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Action to be recorded in the audit log, e.g. `runner.disable`. Entries should be recorded in the same
+/// transaction as the action, so that only the applied actions are recorded.
+#[derive(Insertable)]
+#[table_name = "audit_logs"]
+pub struct AuditEntry {
+    actor_id: Option<UserId>,
+    action: &'static str,
+    target_type: Option<&'static str>,
+    target_id: Option<ModelId>,
+    details: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(actor_id: Option<UserId>, action: &'static str) -> Self {
+        AuditEntry {
+            actor_id,
+            action,
+            target_type: None,
+            target_id: None,
+            details: None,
+        }
+    }
+
+    pub fn target<I: Into<ModelId>>(self, target_type: &'static str, target_id: I) -> Self {
+        AuditEntry { target_type: Some(target_type), target_id: Some(target_id.into()), ..self }
+    }
+
+    pub fn details(self, details: String) -> Self {
+        AuditEntry { details: Some(details), ..self }
+    }
+
+    pub fn record(self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::insert_into(audit_logs::table)
+            .values(&self)
+            .execute(conn)
+            .map(|_| ())
+    }
+}
@@ -1,3 +1,4 @@
+pub mod audit_log;
 pub mod paginate;
 pub mod role;
 pub mod token;
@@ -1,3 +1,4 @@
+use actix_web::HttpRequest;
 use diesel::expression::NonAggregate;
 use diesel::pg::Pg;
 use diesel::prelude::*;
@@ -5,6 +6,7 @@ use diesel::query_builder::*;
 use diesel::query_dsl::methods::LoadQuery;
 use diesel::sql_types::BigInt;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 pub trait Paginate: Sized {
     fn paginate(self, page: Option<i64>) -> Paginated<Self>;
@@ -13,7 +15,7 @@ pub trait Paginate: Sized {
 impl<T> Paginate for T {
     fn paginate(self, page: Option<i64>) -> Paginated<Self> {
         let page = match page {
-            Some(num) => num,
+            Some(num) => num.max(1),
             None => 1
         };
 
@@ -26,6 +28,7 @@ impl<T> Paginate for T {
 }
 
 const DEFAULT_PER_PAGE: i64 = 10;
+const MAX_PER_PAGE: i64 = 100;
 
 #[derive(Debug, Clone, Copy, QueryId)]
 pub struct Paginated<T> {
@@ -37,7 +40,7 @@ pub struct Paginated<T> {
 impl<T> Paginated<T> {
     pub fn per_page(self, per_page: Option<i64>) -> Self {
         let per_page = match per_page {
-            Some(num) => num,
+            Some(num) => num.clamp(1, MAX_PER_PAGE),
             None => DEFAULT_PER_PAGE,
         };
 
@@ -57,10 +60,12 @@ impl<T> Paginated<T> {
         let items = results.into_iter().map(|x| x.0).collect();
         let total_pages = (total as f64 / per_page as f64).ceil() as i64;
         Ok(Pagination {
+            total,
+            page,
             per_page,
-            current_page: page,
-            total_items: total,
             total_pages,
+            next: None,
+            prev: None,
             items,
         })
     }
@@ -88,18 +93,50 @@ impl<T> QueryFragment<Pg> for Paginated<T>
     }
 }
 
-#[derive(Serialize)]
+/// Envelope of the list responses. Links of the next and the previous pages are relative to the host, they are
+/// null on the last and the first pages.
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Pagination<T> {
+    total: i64,
+    page: i64,
     per_page: i64,
-    current_page: i64,
     total_pages: i64,
-    total_items: i64,
+    next: Option<String>,
+    prev: Option<String>,
     items: Vec<T>,
 }
 
-#[derive(Deserialize)]
+impl<T> Pagination<T> {
+    /// Fills the links of the next and the previous pages, other parameters of the request are kept as they are
+    pub fn with_links(self, req: &HttpRequest) -> Self {
+        let params = req.query_string()
+            .split('&')
+            .filter(|param| !param.is_empty() && !param.starts_with("page="))
+            .collect::<Vec<&str>>();
+
+        let link = |page: i64| {
+            let page = format!("page={}", page);
+            let query = params.iter()
+                .copied()
+                .chain(std::iter::once(page.as_str()))
+                .collect::<Vec<&str>>()
+                .join("&");
+
+            format!("{}?{}", req.path(), query)
+        };
+
+        Pagination {
+            next: Some(self.page + 1).filter(|next| *next <= self.total_pages).map(&link),
+            prev: Some(self.page - 1).filter(|prev| *prev >= 1).map(|prev| link(prev.min(self.total_pages.max(1)))),
+            ..self
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
 pub struct PaginationRequest {
     pub per_page: Option<i64>,
     pub page: Option<i64>,
@@ -2,4 +2,6 @@
 pub enum Roles {
     Admin = 1,
     User = 2,
+    // views the experiments, jobs and audit logs of all the users without modifying or running anything
+    Auditor = 3,
 }
\ No newline at end of file
@@ -1,36 +1,169 @@
+use std::fmt;
+use std::str::FromStr;
+
 use chrono::Utc;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::types::{ModelId, UserId};
+use crate::utils::random_key;
+
+/// Issuer of the user and the runner tokens
+pub const TOKEN_ISSUER: &str = "nrg-testbed";
+
+const OIDC_NONCE_LENGTH: usize = 16;
+// users have this long to sign in at the OpenID Connect provider
+const OIDC_STATE_TIMEOUT: i64 = 60 * 10;
 
-use crate::types::ModelId;
+/// What the token is issued for, tokens are rejected by the other audiences, e.g. a runner token can not be used
+/// in place of a user token
+#[derive(Clone, Copy, PartialEq)]
+pub enum Audience {
+    User,
+    Runner,
+}
 
+impl Audience {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Audience::User => "user",
+            Audience::Runner => "runner",
+        }
+    }
+}
+
+/// Operations the token grants
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    // tokens of the login and the refresh
+    Session,
+    // tokens of the api keys, they are valid until the key is revoked or expired
+    ApiKey,
+    // runners connect to the server and rotate their tokens with it
+    RunnerConnect,
+    // short lived tokens admins are given to act as another user, they can not be refreshed
+    Impersonation,
+}
+
+/// Registered claims of the user and the runner tokens, subject is the id of the user or the runner the token is
+/// issued for and it is encoded as a string
 #[derive(Clone, Serialize, Deserialize)]
-pub struct AuthToken {
+#[serde(bound(serialize = "S: fmt::Display", deserialize = "S: FromStr"))]
+pub struct Claims<S> {
+    pub iss: String,
+    pub aud: String,
+    #[serde(with = "subject")]
+    pub sub: S,
     // issued at
     pub iat: i64,
     // expire time
     pub exp: i64,
-    // user id
-    pub user_id: ModelId,
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
+}
+
+impl<S> Claims<S> {
+    pub fn new(audience: Audience, sub: S, scopes: Vec<Scope>, iat: i64, exp: i64) -> Self {
+        Claims {
+            iss: TOKEN_ISSUER.to_string(),
+            aud: audience.as_str().to_string(),
+            sub,
+            iat,
+            exp,
+            scopes,
+        }
+    }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+mod subject {
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    use serde::de::Error;
+
+    use super::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(sub: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(sub)
+    }
+
+    pub fn deserialize<'de, T: FromStr, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(|_| D::Error::custom("invalid subject"))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuthToken {
+    #[serde(flatten)]
+    pub claims: Claims<UserId>,
     // role id
     pub role_id: ModelId,
+    // api key the token is issued for, tokens of the api keys are valid until the key is revoked or expired
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_id: Option<ModelId>,
+    // session the token is issued for by the login or a refresh, tokens of the revoked sessions are rejected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<ModelId>,
+    // admin the impersonation token is issued to, requests made with it are recorded in the audit log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonator_id: Option<UserId>,
+    // shown by the clients while the impersonation token is used, e.g. "Jane Doe is impersonating John Doe"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub banner: Option<String>,
 }
 
 impl AuthToken {
-    pub fn new(user_id: ModelId, role_id: ModelId, timeout: i64) -> Self {
+    pub fn new(user_id: UserId, role_id: ModelId, session_id: ModelId, timeout: i64) -> Self {
         let now = Utc::now().timestamp();
 
         AuthToken {
-            iat: now,
-            exp: now + timeout,
-            user_id,
+            claims: Claims::new(Audience::User, user_id, vec![Scope::Session], now, now + timeout),
+            role_id,
+            api_key_id: None,
+            session_id: Some(session_id),
+            impersonator_id: None,
+            banner: None,
+        }
+    }
+
+    pub fn api_key(user_id: UserId, role_id: ModelId, api_key_id: ModelId, iat: i64, exp: i64) -> Self {
+        AuthToken {
+            claims: Claims::new(Audience::User, user_id, vec![Scope::ApiKey], iat, exp),
+            role_id,
+            api_key_id: Some(api_key_id),
+            session_id: None,
+            impersonator_id: None,
+            banner: None,
+        }
+    }
+
+    pub fn impersonation(user_id: UserId, role_id: ModelId, impersonator_id: UserId, banner: String, timeout: i64) -> Self {
+        let now = Utc::now().timestamp();
+
+        AuthToken {
+            claims: Claims::new(Audience::User, user_id, vec![Scope::Impersonation], now, now + timeout),
             role_id,
+            api_key_id: None,
+            session_id: None,
+            impersonator_id: Some(impersonator_id),
+            banner: Some(banner),
         }
     }
+
+    pub fn user_id(&self) -> UserId {
+        self.claims.sub
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct IdentityToken {
-    pub user_id: ModelId,
+    pub user_id: UserId,
     pub iat: i64,
     pub exp: i64,
     pub kind: IdentityTokenKind,
@@ -38,12 +171,11 @@ pub struct IdentityToken {
 
 #[derive(Deserialize, Serialize, PartialEq)]
 pub enum IdentityTokenKind {
-    ForgotPassword,
     VerifyAccount,
 }
 
 impl IdentityToken {
-    pub fn new(user_id: ModelId, kind: IdentityTokenKind, timeout: i64) -> Self {
+    pub fn new(user_id: UserId, kind: IdentityTokenKind, timeout: i64) -> Self {
         let now = Utc::now().timestamp();
 
         IdentityToken {
@@ -53,4 +185,28 @@ impl IdentityToken {
             kind,
         }
     }
-}
\ No newline at end of file
+}
+/// State of an authorization request to the OpenID Connect provider, it is passed through the provider and checked
+/// once the user is redirected back. User is given if the request links the account of the provider to an
+/// existing user instead of signing in.
+#[derive(Serialize, Deserialize)]
+pub struct OidcState {
+    pub iat: i64,
+    pub exp: i64,
+    pub nonce: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<UserId>,
+}
+
+impl OidcState {
+    pub fn new(user_id: Option<UserId>) -> Self {
+        let now = Utc::now().timestamp();
+
+        OidcState {
+            iat: now,
+            exp: now + OIDC_STATE_TIMEOUT,
+            nonce: random_key(OIDC_NONCE_LENGTH),
+            user_id,
+        }
+    }
+}
@@ -1,11 +1,32 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
 
-#[derive(Serialize, Deserialize)]
+use crate::error::ErrorMessaging;
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct TokenResponse {
     pub token: String
 }
 
-#[derive(Serialize)]
+/// Short lived access token of a session, a new one is obtained with the refresh token before it expires.
+/// Refresh token is replaced on each refresh.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionTokenResponse {
+    pub token: String,
+    // unix timestamp of the expire time of the access token
+    pub expires_at: i64,
+    pub refresh_token: String,
+}
+
+/// Url of an external page the user is redirected to, e.g. the login page of the OpenID Connect provider
+#[derive(Serialize, ToSchema)]
+pub struct RedirectResponse {
+    pub url: String
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct SuccessResponse {
     pub message: String
 }
@@ -17,3 +38,35 @@ impl Default for SuccessResponse {
         }
     }
 }
+
+/// Outcome of an operation applied to many items, reported for each item separately.
+#[derive(Serialize, ToSchema)]
+pub struct BulkResponse {
+    pub results: Vec<BulkItemResult>
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkItemResult {
+    pub id: Uuid,
+    pub successful: bool,
+    pub error: Option<String>,
+}
+
+impl BulkItemResult {
+    pub fn success(id: Uuid) -> Self {
+        BulkItemResult {
+            id,
+            successful: true,
+            error: None,
+        }
+    }
+
+    pub fn failure<E: ErrorMessaging>(id: Uuid, error: E) -> Self {
+        BulkItemResult {
+            id,
+            successful: false,
+            error: Some(error.value().message),
+        }
+    }
+}
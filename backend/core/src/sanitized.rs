@@ -24,6 +24,14 @@ impl<T> Sanitize for Option<T> where T: Sanitize {
     }
 }
 
+impl<T> Sanitize for Vec<T> where T: Sanitize {
+    fn sanitize(self) -> Self {
+        self.into_iter()
+            .map(|t| t.sanitize())
+            .collect()
+    }
+}
+
 pub struct SanitizedPath<T>(T);
 
 impl<T> SanitizedPath<T> {
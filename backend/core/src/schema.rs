@@ -1,27 +1,478 @@
 table! {
-    experiments (id) {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    all_job_streams (id) {
         id -> Int4,
-        user_id -> Int4,
+        job_id -> JobId,
         name -> Varchar,
-        code -> Text,
+        output -> Text,
+        truncated -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    all_jobs (id) {
+        id -> JobId,
+        experiment_id -> ExperimentId,
+        runner_id -> Nullable<RunnerId>,
+        code_hash -> Varchar,
+        status -> Varchar,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        output -> Text,
+        output_truncated -> Bool,
+        ansi_mode -> Varchar,
+        failure_reason -> Nullable<Varchar>,
+        started_at -> Nullable<Timestamp>,
+        finished_at -> Nullable<Timestamp>,
+        hooks -> Array<Text>,
+        firmware_id -> Nullable<Int4>,
+        flash_status -> Nullable<Varchar>,
+        uuid -> Uuid,
+        protected -> Bool,
+        batch_id -> Nullable<Int4>,
+        lease_expires_at -> Nullable<Timestamp>,
+        reclaim_count -> Int4,
+        queue_priority -> Int4,
+        annotation -> Nullable<Text>,
+        verdict -> Nullable<Varchar>,
+        annotated_at -> Nullable<Timestamp>,
+        derived_verdict -> Nullable<Varchar>,
+        warmup -> Bool,
     }
 }
 
 table! {
-    jobs (id) {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    archived_job_streams (id) {
+        id -> Int4,
+        job_id -> JobId,
+        name -> Varchar,
+        output -> Text,
+        truncated -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    archived_jobs (id) {
+        id -> JobId,
+        experiment_id -> ExperimentId,
+        runner_id -> Nullable<RunnerId>,
+        code_hash -> Varchar,
+        status -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        output -> Text,
+        output_truncated -> Bool,
+        ansi_mode -> Varchar,
+        failure_reason -> Nullable<Varchar>,
+        started_at -> Nullable<Timestamp>,
+        finished_at -> Nullable<Timestamp>,
+        hooks -> Array<Text>,
+        firmware_id -> Nullable<Int4>,
+        flash_status -> Nullable<Varchar>,
+        uuid -> Uuid,
+        protected -> Bool,
+        batch_id -> Nullable<Int4>,
+        lease_expires_at -> Nullable<Timestamp>,
+        reclaim_count -> Int4,
+        queue_priority -> Int4,
+        annotation -> Nullable<Text>,
+        verdict -> Nullable<Varchar>,
+        annotated_at -> Nullable<Timestamp>,
+        derived_verdict -> Nullable<Varchar>,
+        warmup -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    audit_logs (id) {
         id -> Int4,
-        experiment_id -> Int4,
-        runner_id -> Int4,
+        actor_id -> Nullable<UserId>,
+        action -> Varchar,
+        target_type -> Nullable<Varchar>,
+        target_id -> Nullable<Int4>,
+        details -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    claim_codes (id) {
+        id -> Int4,
+        code_hash -> Varchar,
+        name -> Varchar,
+        labels -> Array<Text>,
+        created_by -> UserId,
+        runner_id -> Nullable<RunnerId>,
+        expires_at -> Timestamp,
+        claimed_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    client_releases (id) {
+        id -> Int4,
+        version -> Varchar,
+        os -> Varchar,
+        arch -> Varchar,
+        url -> Text,
+        signature -> Varchar,
+        created_at -> Timestamp,
+        sha256 -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    code_blobs (hash) {
+        hash -> Varchar,
         code -> Text,
+        ref_count -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    experiment_activities (id) {
+        id -> Int4,
+        experiment_id -> ExperimentId,
+        actor_id -> Nullable<UserId>,
+        kind -> Varchar,
+        job_id -> Nullable<JobId>,
+        details -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    experiment_cost_centers (experiment_id) {
+        experiment_id -> ExperimentId,
+        cost_center -> Varchar,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    experiment_job_stats (experiment_id) {
+        experiment_id -> ExperimentId,
+        job_count -> Int8,
+        p50_duration -> Nullable<Float8>,
+        p95_duration -> Nullable<Float8>,
+        failure_rate -> Float8,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    experiment_sdr_settings (experiment_id) {
+        experiment_id -> ExperimentId,
+        frequency_hz -> Int8,
+        gain_db -> Float8,
+        sample_rate -> Int4,
+        recording -> Nullable<Varchar>,
+        recording_ms -> Nullable<Int4>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    experiments (id) {
+        id -> ExperimentId,
+        user_id -> UserId,
+        name -> Varchar,
+        code -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        firmware_id -> Nullable<Int4>,
+        uuid -> Uuid,
+        starred -> Bool,
+        description -> Text,
+        archived_at -> Nullable<Timestamp>,
+        slug -> Varchar,
+        deleted_at -> Nullable<Timestamp>,
+        retention_days -> Nullable<Int4>,
+        retention_jobs -> Nullable<Int4>,
+        runner_strategy -> Varchar,
+        preferred_labels -> Array<Text>,
+        success_criteria -> Nullable<Text>,
+        warmup_runs -> Int4,
+        max_temperature -> Nullable<Float8>,
+        thermal_timeout -> Int4,
+        cpu_governor -> Nullable<Varchar>,
+        cpu_frequency_khz -> Nullable<Int4>,
+        disable_turbo -> Bool,
+        netem_delay_ms -> Nullable<Int4>,
+        netem_jitter_ms -> Nullable<Int4>,
+        netem_loss_percent -> Nullable<Float8>,
+        netem_rate_kbit -> Nullable<Int4>,
+        project_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    failed_logins (id) {
+        id -> Int4,
+        email -> Varchar,
+        ip -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    firmwares (id) {
+        id -> Int4,
+        user_id -> UserId,
+        name -> Varchar,
+        data -> Bytea,
+        size -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    idempotency_keys (id) {
+        id -> Int4,
+        user_id -> UserId,
+        key -> Varchar,
+        job_id -> JobId,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    job_batches (id) {
+        id -> Int4,
+        uuid -> Uuid,
+        experiment_id -> ExperimentId,
+        created_by -> Nullable<UserId>,
+        created_at -> Timestamp,
+        abort_after_failures -> Nullable<Int4>,
+        abort_metric -> Nullable<Varchar>,
+        abort_threshold -> Nullable<Float8>,
+        abort_crossing -> Nullable<Varchar>,
+        aborted_at -> Nullable<Timestamp>,
+        repeat_times -> Int4,
+        repeat_metrics -> Array<Text>,
+        max_variation -> Nullable<Float8>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    job_artifacts (id) {
+        id -> Int4,
+        job_id -> JobId,
+        name -> Varchar,
+        data -> Bytea,
+        size -> Int4,
+        created_at -> Timestamp,
+        metadata -> Text,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    job_costs (job_id) {
+        job_id -> JobId,
+        job_uuid -> Uuid,
+        experiment_uuid -> Uuid,
+        experiment_name -> Varchar,
+        user_email -> Varchar,
+        runner_name -> Nullable<Varchar>,
+        cost_center -> Varchar,
+        status -> Varchar,
+        runtime_seconds -> Float8,
+        energy_mj -> Nullable<Float8>,
+        storage_bytes -> Int8,
+        finished_at -> Timestamp,
+        recorded_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    job_environments (job_id) {
+        job_id -> JobId,
+        snapshot -> Text,
+        recorded_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    job_streams (id) {
+        id -> Int4,
+        job_id -> JobId,
+        name -> Varchar,
+        output -> Text,
+        truncated -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    jobs (id) {
+        id -> JobId,
+        experiment_id -> ExperimentId,
+        runner_id -> Nullable<RunnerId>,
+        code_hash -> Varchar,
         status -> Varchar,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        output -> Text,
+        output_truncated -> Bool,
+        ansi_mode -> Varchar,
+        failure_reason -> Nullable<Varchar>,
+        started_at -> Nullable<Timestamp>,
+        finished_at -> Nullable<Timestamp>,
+        hooks -> Array<Text>,
+        firmware_id -> Nullable<Int4>,
+        flash_status -> Nullable<Varchar>,
+        uuid -> Uuid,
+        protected -> Bool,
+        batch_id -> Nullable<Int4>,
+        lease_expires_at -> Nullable<Timestamp>,
+        reclaim_count -> Int4,
+        queue_priority -> Int4,
+        annotation -> Nullable<Text>,
+        verdict -> Nullable<Varchar>,
+        annotated_at -> Nullable<Timestamp>,
+        derived_verdict -> Nullable<Varchar>,
+        warmup -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    monthly_costs (month, cost_center) {
+        month -> Date,
+        cost_center -> Varchar,
+        job_count -> Int8,
+        metered_jobs -> Int8,
+        runtime_seconds -> Float8,
+        energy_mj -> Float8,
+        storage_bytes -> Int8,
     }
 }
 
 table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    password_reset_tokens (id) {
+        id -> Int4,
+        user_id -> UserId,
+        token_hash -> Varchar,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    processed_run_results (job_id, message_id) {
+        job_id -> JobId,
+        message_id -> Varchar,
+        processed_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    project_members (project_id, user_id) {
+        project_id -> Int4,
+        user_id -> UserId,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    projects (id) {
+        id -> Int4,
+        uuid -> Uuid,
+        name -> Varchar,
+        owner_id -> UserId,
+        max_experiments -> Nullable<Int4>,
+        max_active_jobs -> Nullable<Int4>,
+        default_labels -> Array<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
     roles (id) {
         id -> Int4,
         name -> Varchar,
@@ -29,34 +480,327 @@ table! {
 }
 
 table! {
-    runners (id) {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    runner_client_logs (id) {
         id -> Int4,
+        runner_id -> RunnerId,
+        lines -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    runner_commands (id) {
+        id -> Int4,
+        runner_id -> RunnerId,
+        kind -> Varchar,
+        status -> Varchar,
+        output -> Nullable<Text>,
+        created_by -> Nullable<UserId>,
+        created_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    runner_connect_tickets (ticket_hash) {
+        ticket_hash -> Varchar,
+        runner_id -> RunnerId,
+        access_key_hash -> Varchar,
+        token_expires_at -> Int8,
+        expires_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    runner_legacy_access_keys (runner_id) {
+        runner_id -> RunnerId,
         access_key -> Varchar,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    runner_job_stats (runner_id) {
+        runner_id -> RunnerId,
+        job_count -> Int8,
+        p50_duration -> Nullable<Float8>,
+        p95_duration -> Nullable<Float8>,
+        failure_rate -> Float8,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    runners (id) {
+        id -> RunnerId,
+        access_key_hash -> Varchar,
         created_at -> Timestamp,
+        name -> Varchar,
+        labels -> Array<Text>,
+        last_seen_at -> Nullable<Timestamp>,
+        os -> Nullable<Varchar>,
+        arch -> Nullable<Varchar>,
+        client_version -> Nullable<Varchar>,
+        disabled -> Bool,
+        previous_access_key_hash -> Nullable<Varchar>,
+        allowed_networks -> Array<Text>,
+        certificate_fingerprint -> Nullable<Varchar>,
+        auto_update -> Bool,
+        kernel -> Nullable<Varchar>,
+        cpu_model -> Nullable<Varchar>,
+        memory_bytes -> Nullable<Int8>,
+        peripherals -> Array<Text>,
+        capabilities -> Array<Text>,
+        uuid -> Uuid,
     }
 }
 
 table! {
-    users (id) {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    scheduled_runs (job_id) {
+        job_id -> JobId,
+        position -> Int8,
+        runner_id -> Nullable<RunnerId>,
+        assigned_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    security_mails (audit_log_id) {
+        audit_log_id -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    sessions (id) {
+        id -> Int4,
+        user_id -> UserId,
+        refresh_token_hash -> Varchar,
+        user_agent -> Nullable<Varchar>,
+        ip -> Nullable<Varchar>,
+        expires_at -> Timestamp,
+        last_used_at -> Timestamp,
+        created_at -> Timestamp,
+        previous_refresh_token_hash -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    user_api_keys (id) {
+        id -> Int4,
+        user_id -> UserId,
+        name -> Varchar,
+        expires_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    user_identities (id) {
         id -> Int4,
+        user_id -> UserId,
+        issuer -> Varchar,
+        subject -> Varchar,
+        email -> Nullable<Varchar>,
+        last_login_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    user_job_stats (id) {
+        id -> Int4,
+        user_id -> UserId,
+        runner_id -> Nullable<RunnerId>,
+        day -> Date,
+        job_count -> Int8,
+        successful_jobs -> Int8,
+        failed_jobs -> Int8,
+        runtime_seconds -> Float8,
+        energy_mj -> Float8,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    user_recovery_codes (id) {
+        id -> Int4,
+        user_id -> UserId,
+        code_hash -> Varchar,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    user_two_factors (user_id) {
+        user_id -> UserId,
+        secret -> Varchar,
+        enabled_at -> Nullable<Timestamp>,
+        last_used_step -> Nullable<Int8>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::sql_types::*;
+
+    users (id) {
+        id -> UserId,
         first_name -> Varchar,
         last_name -> Varchar,
         email -> Varchar,
         password -> Varchar,
         status -> Varchar,
         role_id -> Int4,
+        verification_sent_at -> Nullable<Timestamp>,
+        status_before_deactivation -> Nullable<Varchar>,
     }
 }
 
+joinable!(all_job_streams -> all_jobs (job_id));
+joinable!(all_jobs -> code_blobs (code_hash));
+joinable!(all_jobs -> experiments (experiment_id));
+joinable!(all_jobs -> runners (runner_id));
+joinable!(archived_job_streams -> archived_jobs (job_id));
+joinable!(archived_jobs -> code_blobs (code_hash));
+joinable!(archived_jobs -> experiments (experiment_id));
+joinable!(archived_jobs -> firmwares (firmware_id));
+joinable!(archived_jobs -> job_batches (batch_id));
+joinable!(archived_jobs -> runners (runner_id));
+joinable!(audit_logs -> users (actor_id));
+joinable!(claim_codes -> runners (runner_id));
+joinable!(claim_codes -> users (created_by));
+joinable!(experiment_activities -> experiments (experiment_id));
+joinable!(experiment_activities -> jobs (job_id));
+joinable!(experiment_activities -> users (actor_id));
+joinable!(experiment_cost_centers -> experiments (experiment_id));
+joinable!(experiment_job_stats -> experiments (experiment_id));
+joinable!(experiment_sdr_settings -> experiments (experiment_id));
+joinable!(experiments -> firmwares (firmware_id));
+joinable!(experiments -> projects (project_id));
 joinable!(experiments -> users (user_id));
+joinable!(firmwares -> users (user_id));
+joinable!(idempotency_keys -> jobs (job_id));
+joinable!(idempotency_keys -> users (user_id));
+joinable!(job_batches -> experiments (experiment_id));
+joinable!(job_batches -> users (created_by));
+joinable!(job_artifacts -> all_jobs (job_id));
+joinable!(job_artifacts -> jobs (job_id));
+joinable!(job_environments -> jobs (job_id));
+joinable!(job_streams -> jobs (job_id));
+joinable!(jobs -> code_blobs (code_hash));
 joinable!(jobs -> experiments (experiment_id));
+joinable!(jobs -> firmwares (firmware_id));
+joinable!(jobs -> job_batches (batch_id));
 joinable!(jobs -> runners (runner_id));
+joinable!(password_reset_tokens -> users (user_id));
+joinable!(processed_run_results -> jobs (job_id));
+joinable!(project_members -> projects (project_id));
+joinable!(project_members -> users (user_id));
+joinable!(projects -> users (owner_id));
+joinable!(runner_client_logs -> runners (runner_id));
+joinable!(runner_commands -> runners (runner_id));
+joinable!(runner_commands -> users (created_by));
+joinable!(runner_connect_tickets -> runners (runner_id));
+joinable!(runner_job_stats -> runners (runner_id));
+joinable!(runner_legacy_access_keys -> runners (runner_id));
+joinable!(scheduled_runs -> jobs (job_id));
+joinable!(scheduled_runs -> runners (runner_id));
+joinable!(security_mails -> audit_logs (audit_log_id));
+joinable!(sessions -> users (user_id));
+joinable!(user_api_keys -> users (user_id));
+joinable!(user_identities -> users (user_id));
+joinable!(user_job_stats -> runners (runner_id));
+joinable!(user_job_stats -> users (user_id));
+joinable!(user_recovery_codes -> users (user_id));
+joinable!(user_two_factors -> users (user_id));
 joinable!(users -> roles (role_id));
 
 allow_tables_to_appear_in_same_query!(
+    all_job_streams,
+    all_jobs,
+    archived_job_streams,
+    archived_jobs,
+    audit_logs,
+    claim_codes,
+    client_releases,
+    code_blobs,
+    experiment_activities,
+    experiment_cost_centers,
+    experiment_job_stats,
+    experiment_sdr_settings,
     experiments,
+    failed_logins,
+    firmwares,
+    idempotency_keys,
+    job_artifacts,
+    job_batches,
+    job_costs,
+    job_environments,
+    job_streams,
     jobs,
+    monthly_costs,
+    password_reset_tokens,
+    processed_run_results,
+    project_members,
+    projects,
     roles,
+    runner_client_logs,
+    runner_commands,
+    runner_connect_tickets,
+    runner_job_stats,
+    runner_legacy_access_keys,
     runners,
+    scheduled_runs,
+    security_mails,
+    sessions,
+    user_api_keys,
+    user_identities,
+    user_job_stats,
+    user_recovery_codes,
+    user_two_factors,
     users,
 );
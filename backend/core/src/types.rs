@@ -1,9 +1,21 @@
+use std::fmt;
+use std::io::Write;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
 use actix_web::HttpResponse;
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
 use diesel::PgConnection;
 use diesel::r2d2::{self, ConnectionManager};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Integer;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::error::ErrorMessaging;
 
+/// Id of the models that are not given a type of their own, e.g. firmwares and audit logs
 pub type ModelId = i32;
 pub type SessionId = i64;
 
@@ -12,3 +24,90 @@ pub type DBPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 pub type Result<T> = std::result::Result<T, Box<dyn ErrorMessaging>>;
 
 pub type DefaultResponse = Result<HttpResponse>;
+
+/// SQL types of the id columns, they are integers in the database. Columns of the different models can not be
+/// compared with each other, nor with the ids of the other models.
+pub mod sql_types {
+    use diesel::sql_types::SqlOrd;
+
+    macro_rules! sql_id {
+        ($($name:ident;)*) => {$(
+            #[derive(Clone, Copy, Debug, Default, SqlType, QueryId)]
+            #[postgres(oid = "23", array_oid = "1007")]
+            pub struct $name;
+
+            impl SqlOrd for $name {}
+        )*};
+    }
+
+    sql_id! {
+        ExperimentId;
+        JobId;
+        RunnerId;
+        UserId;
+    }
+}
+
+/// Declares an id stored in an integer column. Ids of the different models do not mix, e.g. a runner id can not
+/// be passed where an experiment id is expected, they are serialized as plain integers though. Ids can still be
+/// read from and bound as plain integers in the raw queries.
+macro_rules! model_id {
+    ($($(#[$meta:meta])* $name:ident = $sql_type:tt;)*) => {$(
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, AsExpression, FromSqlRow, ToSchema)]
+        #[serde(transparent)]
+        #[sql_type = $sql_type]
+        pub struct $name(pub ModelId);
+
+        impl ToSql<Integer, Pg> for $name {
+            fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+                <ModelId as ToSql<Integer, Pg>>::to_sql(&self.0, out)
+            }
+        }
+
+        impl FromSql<Integer, Pg> for $name {
+            fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+                <ModelId as FromSql<Integer, Pg>>::from_sql(bytes).map($name)
+            }
+        }
+
+        impl ToSql<sql_types::$name, Pg> for $name {
+            fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+                <ModelId as ToSql<Integer, Pg>>::to_sql(&self.0, out)
+            }
+        }
+
+        impl FromSql<sql_types::$name, Pg> for $name {
+            fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+                <ModelId as FromSql<Integer, Pg>>::from_sql(bytes).map($name)
+            }
+        }
+
+        impl From<$name> for ModelId {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseIntError;
+
+            fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+                s.parse::<ModelId>().map($name)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    )*};
+}
+
+model_id! {
+    ExperimentId = "sql_types::ExperimentId";
+    JobId = "sql_types::JobId";
+    RunnerId = "sql_types::RunnerId";
+    UserId = "sql_types::UserId";
+}
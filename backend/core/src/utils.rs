@@ -1,31 +1,107 @@
-use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, errors::Error as JWTErrors, Header, Validation};
+use std::collections::HashMap;
+
+pub use jsonwebtoken::Algorithm;
+use jsonwebtoken::{DecodingKey, EncodingKey, errors::Error as JWTErrors, Header, Validation};
 pub use jsonwebtoken::errors::ErrorKind as JWTErrorKind;
-use ring::hmac;
+use ring::{digest, hmac};
+use ring::rand::{SecureRandom, SystemRandom};
 use serde::{de::DeserializeOwned, Serialize};
 
+use crate::models::token::{Audience, TOKEN_ISSUER};
+
+/// Keys signing the tokens, tokens carry the id of their key in the `kid` header. Tokens are signed with the
+/// current key while all of the keys are accepted, a key is rotated by
+/// 1. adding the new key and keeping the current one, so that every replica accepts the tokens of the new key,
+/// 2. making the new key the current one,
+/// 3. removing the old key once the tokens signed with it are expired, e.g. the api keys and the runner tokens.
+pub struct TokenKeys {
+    current: String,
+    keys: Vec<(String, String)>,
+    // tokens issued before the keys are versioned do not have a key id, they are only accepted with a single key
+    legacy: Option<String>,
+}
+
+impl TokenKeys {
+    /// Only key is the given secret, tokens without a key id are verified with it as well since they are issued
+    /// with the same secret before the keys are versioned
+    pub fn single(id: &str, secret: &str) -> Self {
+        TokenKeys {
+            current: id.to_string(),
+            keys: vec![(id.to_string(), secret.to_string())],
+            legacy: Some(secret.to_string()),
+        }
+    }
+
+    /// Parses the keys given as comma separated `id:secret` pairs, the current key must be one of them. Tokens
+    /// without a key id are rejected, so that the secret they are signed with is retired along with the keys.
+    pub fn parse(keys: &str, current: &str) -> Result<Self, String> {
+        let mut parsed: Vec<(String, String)> = Vec::new();
+
+        for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+            let mut parts = key.splitn(2, ':');
+            let id = parts.next().unwrap_or_default().trim();
+            let secret = parts.next().unwrap_or_default();
+
+            if id.is_empty() || secret.is_empty() {
+                return Err(String::from("keys must be given as id:secret pairs"));
+            }
+
+            if parsed.iter().any(|(parsed_id, _)| parsed_id == id) {
+                return Err(format!("key {} is given more than once", id));
+            }
+
+            parsed.push((id.to_string(), secret.to_string()));
+        }
+
+        if !parsed.iter().any(|(id, _)| id == current) {
+            return Err(format!("current key {} is not one of the keys", current));
+        }
+
+        Ok(TokenKeys {
+            current: current.to_string(),
+            keys: parsed,
+            legacy: None,
+        })
+    }
+}
+
 #[derive(Clone)]
-pub struct Hash{
+pub struct Hash {
     encoding_key: EncodingKey,
-    decoding_key: DecodingKey<'static>,
+    decoding_keys: HashMap<String, DecodingKey<'static>>,
+    // tokens without a key id are rejected if it is none, see `TokenKeys`
+    legacy_decoding_key: Option<DecodingKey<'static>>,
     hmac256_key: hmac::Key,
     hmac512_key: hmac::Key,
-    crypto_algorithm: Algorithm,
     header: Header,
     validation: Validation,
-    secret: String,
 }
 
 impl Hash {
-    pub fn new(secret: &'static String, crypto_algorithm: Algorithm) -> Hash {
+    /// Digests stored in the database, e.g. the passwords, are signed with the secret. Unlike the tokens they can
+    /// not be verified with another key, so the secret can not be rotated with the token keys.
+    pub fn new(secret: &'static String, token_keys: TokenKeys, crypto_algorithm: Algorithm) -> Hash {
+        let encoding_key = token_keys.keys.iter()
+            .find(|(id, _)| *id == token_keys.current)
+            .map(|(_, key)| EncodingKey::from_secret(key.as_bytes()))
+            .expect("current key is not one of the keys");
+
+        let decoding_keys = token_keys.keys.iter()
+            .map(|(id, key)| (id.clone(), DecodingKey::from_secret(key.as_bytes()).into_static()))
+            .collect();
+
+        let mut header = Header::new(crypto_algorithm);
+        header.kid = Some(token_keys.current);
+
         Hash {
-            encoding_key: EncodingKey::from_secret(secret.as_str().as_bytes()),
-            decoding_key: DecodingKey::from_secret(secret.as_str().as_bytes()),
+            encoding_key,
+            decoding_keys,
+            legacy_decoding_key: token_keys.legacy.as_ref()
+                .map(|legacy| DecodingKey::from_secret(legacy.as_bytes()).into_static()),
             hmac256_key: hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
             hmac512_key: hmac::Key::new(hmac::HMAC_SHA512, secret.as_bytes()),
-            crypto_algorithm,
-            header: Header::new(crypto_algorithm),
+            header,
             validation: Validation::new(crypto_algorithm),
-            secret: secret.clone(),
         }
     }
 
@@ -37,12 +113,233 @@ impl Hash {
         base64::encode(hmac::sign(&self.hmac256_key, message.as_bytes()))
     }
 
+    /// Verifies the signature given by `sign256` in constant time
+    pub fn verify256(&self, message: &str, signature: &str) -> bool {
+        base64::decode(signature)
+            .is_ok_and(|signature| hmac::verify(&self.hmac256_key, message.as_bytes(), &signature).is_ok())
+    }
+
     pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, JWTErrors> {
         jsonwebtoken::encode(&self.header, claims, &self.encoding_key)
     }
 
+    /// Verifies the token with the key it is signed with, tokens of the unknown keys are rejected
     pub fn decode<T: DeserializeOwned>(&self, token: &str) -> Result<T, JWTErrors> {
-        jsonwebtoken::decode::<T>(token, &self.decoding_key, &self.validation)
+        self.decode_with(token, &self.validation)
+    }
+
+    /// Verifies the token like `decode`, in addition the token must be issued by the app for the given audience
+    pub fn decode_for<T: DeserializeOwned>(&self, token: &str, audience: Audience) -> Result<T, JWTErrors> {
+        let mut validation = self.validation.clone();
+        validation.iss = Some(TOKEN_ISSUER.to_string());
+        validation.set_audience(&[audience.as_str()]);
+
+        self.decode_with(token, &validation)
+    }
+
+    fn decode_with<T: DeserializeOwned>(&self, token: &str, validation: &Validation) -> Result<T, JWTErrors> {
+        let decoding_key = match jsonwebtoken::decode_header(token)?.kid {
+            Some(kid) => self.decoding_keys.get(&kid)
+                .ok_or_else(|| JWTErrors::from(JWTErrorKind::InvalidToken))?,
+            None => self.legacy_decoding_key.as_ref()
+                .ok_or_else(|| JWTErrors::from(JWTErrorKind::InvalidToken))?
+        };
+
+        jsonwebtoken::decode::<T>(token, decoding_key, validation)
             .map(|t| t.claims)
     }
-}
\ No newline at end of file
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+// codes of the authenticator apps change every 30 seconds
+const TOTP_STEP: i64 = 30;
+
+/// Generates the given number of random bytes
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+
+    SystemRandom::new().fill(&mut bytes).unwrap();
+
+    bytes
+}
+
+/// Generates a url safe random key from the given number of random bytes
+pub fn random_key(len: usize) -> String {
+    base64::encode_config(random_bytes(len), base64::URL_SAFE_NO_PAD)
+}
+
+/// Encodes the bytes with the base32 alphabet of RFC 4648 without padding, the form authenticator apps expect the
+/// secrets in
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+
+        buffer &= (1 << bits) - 1;
+    }
+
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+
+    encoded
+}
+
+/// Decodes the base32 encoded text, None is returned if it contains characters out of the alphabet
+pub fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in encoded.bytes().filter(|c| *c != b'=') {
+        let value = BASE32_ALPHABET.iter().position(|a| *a == c.to_ascii_uppercase())? as u32;
+
+        buffer = (buffer << 5) | value;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(bytes)
+}
+
+/// Step of the time based one time passwords the given unix timestamp falls in
+pub fn totp_step(timestamp: i64) -> i64 {
+    timestamp / TOTP_STEP
+}
+
+/// Six digit time based one time password of RFC 6238 for the given step, i.e. the code authenticator apps show
+pub fn totp(secret: &[u8], step: i64) -> u32 {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let tag = hmac::sign(&key, &step.to_be_bytes());
+    let digest = tag.as_ref();
+
+    // dynamic truncation, last nibble of the digest points to the four bytes making up the code
+    let offset = (digest[digest.len() - 1] & 0xf) as usize;
+    let code = u32::from_be_bytes([digest[offset] & 0x7f, digest[offset + 1], digest[offset + 2], digest[offset + 3]]);
+
+    code % 1_000_000
+}
+
+/// Hex encoded SHA-256 digest of the given bytes, used for identifying certificates and the code of the jobs
+pub fn fingerprint(bytes: &[u8]) -> String {
+    digest::digest(&digest::SHA256, bytes)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Claims {
+        exp: i64,
+    }
+
+    fn claims() -> Claims {
+        Claims { exp: chrono::Utc::now().timestamp() + 60 }
+    }
+
+    fn hash(token_keys: TokenKeys) -> Hash {
+        Hash::new(Box::leak(Box::new(String::from("secret"))), token_keys, Algorithm::HS256)
+    }
+
+    fn legacy_token(secret: &str) -> String {
+        jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims(), &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn parses_token_keys() {
+        let token_keys = TokenKeys::parse(" 2021-01:secret1 , 2021-02:secret:2,", "2021-02").unwrap();
+
+        assert_eq!(token_keys.current, "2021-02");
+        assert_eq!(token_keys.keys, vec![
+            (String::from("2021-01"), String::from("secret1")),
+            (String::from("2021-02"), String::from("secret:2")),
+        ]);
+        assert!(token_keys.legacy.is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_token_keys() {
+        assert!(TokenKeys::parse("2021-01", "2021-01").is_err());
+        assert!(TokenKeys::parse("2021-01:", "2021-01").is_err());
+        assert!(TokenKeys::parse(":secret", "2021-01").is_err());
+        assert!(TokenKeys::parse("2021-01:secret1,2021-01:secret2", "2021-01").is_err());
+        assert!(TokenKeys::parse("2021-01:secret1", "2021-02").is_err());
+        assert!(TokenKeys::parse("", "2021-01").is_err());
+    }
+
+    #[test]
+    fn decodes_tokens_of_all_keys() {
+        let old = hash(TokenKeys::parse("2021-01:secret1", "2021-01").unwrap());
+        let rotated = hash(TokenKeys::parse("2021-01:secret1,2021-02:secret2", "2021-02").unwrap());
+        let removed = hash(TokenKeys::parse("2021-02:secret2", "2021-02").unwrap());
+
+        let token = old.encode(&claims()).unwrap();
+
+        assert!(rotated.decode::<Claims>(&token).is_ok());
+        assert!(removed.decode::<Claims>(&token).is_err());
+        assert!(rotated.decode::<Claims>(&rotated.encode(&claims()).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn generates_the_codes_of_rfc_6238() {
+        let secret = b"12345678901234567890";
+
+        // test vectors of the RFC for SHA1, truncated to six digits
+        for (timestamp, code) in [(59, 287082), (1111111109, 81804), (1111111111, 50471), (1234567890, 5924),
+            (2000000000, 279037), (20000000000, 353130)] {
+            assert_eq!(totp(secret, totp_step(timestamp)), code, "code at {}", timestamp);
+        }
+    }
+
+    #[test]
+    fn steps_every_thirty_seconds() {
+        assert_eq!(totp_step(0), 0);
+        assert_eq!(totp_step(29), 0);
+        assert_eq!(totp_step(30), 1);
+        assert_eq!(totp_step(59), 1);
+        assert_eq!(totp_step(1234567890), 41152263);
+    }
+
+    #[test]
+    fn encodes_and_decodes_base32() {
+        // test vectors of RFC 4648 without the padding
+        for (decoded, encoded) in [("", ""), ("f", "MY"), ("fo", "MZXQ"), ("foo", "MZXW6"), ("foob", "MZXW6YQ"),
+            ("fooba", "MZXW6YTB"), ("foobar", "MZXW6YTBOI")] {
+            assert_eq!(base32_encode(decoded.as_bytes()), encoded);
+            assert_eq!(base32_decode(encoded), Some(decoded.as_bytes().to_vec()));
+        }
+
+        assert_eq!(base32_decode("mzxw6ytboi======"), Some(b"foobar".to_vec()));
+        assert_eq!(base32_decode("MZXW1"), None);
+    }
+
+    #[test]
+    fn accepts_tokens_without_key_id_only_with_single_key() {
+        let token = legacy_token("secret");
+
+        assert!(hash(TokenKeys::single("default", "secret")).decode::<Claims>(&token).is_ok());
+        assert!(hash(TokenKeys::single("default", "other")).decode::<Claims>(&token).is_err());
+        assert!(hash(TokenKeys::parse("default:secret", "default").unwrap()).decode::<Claims>(&token).is_err());
+    }
+}
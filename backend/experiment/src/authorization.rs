@@ -0,0 +1,224 @@
+use diesel::dsl::{And, Eq, exists, Filter, IsNull, Nullable, Or};
+use diesel::expression::exists::Exists;
+use diesel::prelude::*;
+
+use core::models::role::Roles;
+use core::schema::{experiments, project_members, projects, users};
+use core::types::{ModelId, UserId};
+use user::models::user::{User, UserStatus};
+
+/// Filter of the experiments the user has access to. It is applied to the queries of the experiments, and of the
+/// jobs joined with their experiments, so that the rules are kept in a single place. Deleted experiments are not
+/// accessible while they wait to be cleaned up.
+pub type ExperimentFilter = And<Eq<experiments::user_id, UserId>, IsNull<experiments::deleted_at>>;
+
+/// Same as [ExperimentFilter], along with the experiments of the projects the user is a member of. Auditors view the
+/// experiments of all the users.
+pub type SharedExperimentFilter = And<Or<Or<Eq<experiments::user_id, UserId>, Exists<ExperimentProjectMember>>, Exists<Auditor>>, IsNull<experiments::deleted_at>>;
+
+/// Experiments owned by the user, see [owned_by].
+pub type OwnedExperimentFilter = Eq<experiments::user_id, UserId>;
+
+type ExperimentProjectMember = Filter<Filter<project_members::table, Eq<Nullable<project_members::project_id>, experiments::project_id>>, Eq<project_members::user_id, UserId>>;
+
+/// Filter of the projects the user is a member of, auditors view all the projects
+pub type ProjectFilter = Or<Exists<Filter<Filter<project_members::table, Eq<project_members::project_id, projects::id>>, Eq<project_members::user_id, UserId>>>, Exists<Auditor>>;
+
+type Auditor = Filter<Filter<users::table, Eq<users::id, UserId>>, Eq<users::role_id, ModelId>>;
+
+// role is looked up by the filters, so that they are applied with the user id alone like in the GraphQL resolvers
+fn is_auditor(user_id: UserId) -> Exists<Auditor> {
+    exists(
+        users::table
+            .filter(users::id.eq(user_id))
+            .filter(users::role_id.eq(Roles::Auditor as ModelId))
+    )
+}
+
+/// Experiments the user may view, along with their jobs, outputs and statistics. Experiments of a project are
+/// viewed by all the members of the project, while they are still modified only by their owners.
+pub fn can_view_experiment(user_id: UserId) -> SharedExperimentFilter {
+    let project_member = project_members::table
+        .filter(project_members::project_id.nullable().eq(experiments::project_id))
+        .filter(project_members::user_id.eq(user_id));
+
+    experiments::user_id.eq(user_id)
+        .or(exists(project_member))
+        .or(is_auditor(user_id))
+        .and(experiments::deleted_at.is_null())
+}
+
+/// Experiments the user may modify, e.g. update the code, run, delete or purge the jobs of
+pub fn can_edit_experiment(user_id: UserId) -> ExperimentFilter {
+    experiments::user_id.eq(user_id).and(experiments::deleted_at.is_null())
+}
+
+/// Experiments owned by the user, leaving out the ones shared through the projects. It narrows the listings and
+/// selects the experiments of a user for the admins, it does not grant any access by itself, so it is applied along
+/// with [can_view_experiment] or [can_edit_experiment] on the requests of the users.
+pub fn owned_by(user_id: UserId) -> OwnedExperimentFilter {
+    experiments::user_id.eq(user_id)
+}
+
+/// Projects the user may view, along with their members and experiments
+pub fn can_view_project(user_id: UserId) -> ProjectFilter {
+    exists(
+        project_members::table
+            .filter(project_members::project_id.eq(projects::id))
+            .filter(project_members::user_id.eq(user_id))
+    )
+        .or(is_auditor(user_id))
+}
+
+// deactivated and banned users are rejected before they reach the policy, they are not granted anything either way
+fn is_active(user: &User) -> bool {
+    match user.status {
+        UserStatus::Deactivated | UserStatus::Banned => false,
+        UserStatus::NotVerified | UserStatus::Verified => true,
+    }
+}
+
+/// Whether the user may manage the project, e.g. add or remove its members, only the owners manage their projects
+pub fn can_manage_project(user: &User, owner_id: UserId) -> bool {
+    user.id == owner_id && is_active(user)
+}
+
+/// Whether the user may create experiments or projects, or dispatch anything to the runners. Auditors only view the
+/// testbed, they do not own anything to modify either.
+pub fn can_write(user: &User) -> bool {
+    !user.is_auditor() && is_active(user)
+}
+
+/// Whether the user may run experiments on the runners, users can prepare their experiments before they confirm
+/// their email though
+pub fn can_run(user: &User) -> bool {
+    user.status == UserStatus::Verified && can_write(user)
+}
+
+/// Whether the user may manage the runners, e.g. provision, rename, command them or publish the client releases
+pub fn can_admin_runner(user: &User) -> bool {
+    user.is_admin() && is_active(user)
+}
+
+/// Whether the user may view the logs the runner clients ship, e.g. while debugging a runner
+pub fn can_view_runner_logs(user: &User) -> bool {
+    (user.is_admin() || user.is_auditor()) && is_active(user)
+}
+
+/// Whether the user may manage the jobs of all the users, e.g. requeue the stuck jobs
+pub fn can_admin_jobs(user: &User) -> bool {
+    user.is_admin() && is_active(user)
+}
+
+/// Whether the user may view the audit logs of all the users
+pub fn can_view_audit_logs(user: &User) -> bool {
+    (user.is_admin() || user.is_auditor()) && is_active(user)
+}
+
+/// Whether the user may view the statistics of the whole testbed
+pub fn can_view_stats(user: &User) -> bool {
+    (user.is_admin() || user.is_auditor()) && is_active(user)
+}
+
+/// Whether the user may view and export the costs of the jobs of all the users for the chargeback reports
+pub fn can_view_accounting(user: &User) -> bool {
+    (user.is_admin() || user.is_auditor()) && is_active(user)
+}
+
+/// Whether the user may set the quotas of the projects
+pub fn can_admin_projects(user: &User) -> bool {
+    user.is_admin() && is_active(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(role: Roles, status: UserStatus) -> User {
+        User {
+            id: UserId(1),
+            first_name: String::from("Jane"),
+            last_name: String::from("Doe"),
+            email: String::from("jane@example.com"),
+            password: String::new(),
+            status,
+            role_id: role as ModelId,
+            verification_sent_at: None,
+            status_before_deactivation: None,
+        }
+    }
+
+    // what the user may do besides writing and running, i.e. administering and viewing the whole testbed
+    fn privileges(user: &User) -> [bool; 7] {
+        [
+            can_admin_runner(user),
+            can_admin_jobs(user),
+            can_admin_projects(user),
+            can_view_runner_logs(user),
+            can_view_audit_logs(user),
+            can_view_stats(user),
+            can_view_accounting(user),
+        ]
+    }
+
+    #[test]
+    fn admins_administer_and_view_the_testbed() {
+        let admin = user(Roles::Admin, UserStatus::Verified);
+
+        assert!(can_write(&admin));
+        assert!(can_run(&admin));
+        assert_eq!(privileges(&admin), [true; 7]);
+    }
+
+    #[test]
+    fn users_only_write_and_run() {
+        let verified = user(Roles::User, UserStatus::Verified);
+        let not_verified = user(Roles::User, UserStatus::NotVerified);
+
+        assert!(can_write(&verified));
+        assert!(can_run(&verified));
+        assert_eq!(privileges(&verified), [false; 7]);
+
+        // experiments are prepared before the email is confirmed, they are run afterwards
+        assert!(can_write(&not_verified));
+        assert!(!can_run(&not_verified));
+    }
+
+    #[test]
+    fn auditors_only_view() {
+        let auditor = user(Roles::Auditor, UserStatus::Verified);
+
+        assert!(!can_write(&auditor));
+        assert!(!can_run(&auditor));
+        assert_eq!(privileges(&auditor), [false, false, false, true, true, true, true]);
+    }
+
+    #[test]
+    fn deactivated_and_banned_users_are_not_granted_anything() {
+        let users = [
+            user(Roles::Admin, UserStatus::Deactivated),
+            user(Roles::Admin, UserStatus::Banned),
+            user(Roles::User, UserStatus::Deactivated),
+            user(Roles::User, UserStatus::Banned),
+            user(Roles::Auditor, UserStatus::Deactivated),
+            user(Roles::Auditor, UserStatus::Banned),
+        ];
+
+        for user in &users {
+            assert!(!can_write(user));
+            assert!(!can_run(user));
+            assert!(!can_manage_project(user, user.id));
+            assert_eq!(privileges(user), [false; 7]);
+        }
+    }
+
+    #[test]
+    fn only_owners_manage_their_projects() {
+        let owner = user(Roles::User, UserStatus::Verified);
+        let admin = User { id: UserId(2), ..user(Roles::Admin, UserStatus::Verified) };
+
+        assert!(can_manage_project(&owner, owner.id));
+        assert!(!can_manage_project(&owner, admin.id));
+        assert!(!can_manage_project(&admin, owner.id));
+    }
+}
@@ -0,0 +1,133 @@
+use actix_web::HttpRequest;
+
+use core::utils::fingerprint;
+
+// Header set by the reverse proxy terminating TLS, contains url encoded PEM of the client certificate
+pub const CLIENT_CERT_HEADER: &str = "X-Client-Cert";
+
+/// Certificate presented by the client, inserted into the request extensions when TLS is terminated
+/// by the app.
+#[derive(Clone)]
+pub struct ClientCertificate {
+    pub fingerprint: String,
+}
+
+impl ClientCertificate {
+    pub fn from_der(der: &[u8]) -> Self {
+        ClientCertificate {
+            fingerprint: fingerprint(der)
+        }
+    }
+
+    fn from_pem(pem: &str) -> Option<Self> {
+        let body = pem.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with("-----"))
+            .collect::<String>();
+
+        base64::decode(body)
+            .ok()
+            .map(|der| ClientCertificate::from_der(&der))
+    }
+}
+
+/// Returns the certificate of the client, reading the proxy header only if it is trusted.
+pub fn client_certificate(req: &HttpRequest, trust_header: bool) -> Option<ClientCertificate> {
+    if let Some(certificate) = req.extensions().get::<ClientCertificate>() {
+        return Some(certificate.clone());
+    }
+
+    if !trust_header {
+        return None;
+    }
+
+    from_header(req.headers().get(CLIENT_CERT_HEADER)?.to_str().ok()?)
+}
+
+/// Same as [client_certificate], for the runners connecting over grpc.
+pub fn grpc_client_certificate<T>(request: &tonic::Request<T>, trust_header: bool) -> Option<ClientCertificate> {
+    let peer_certificate = request.peer_certs()
+        .and_then(|certificates| certificates.first().map(|certificate| ClientCertificate::from_der(certificate.get_ref())));
+
+    if peer_certificate.is_some() {
+        return peer_certificate;
+    }
+
+    if !trust_header {
+        return None;
+    }
+
+    from_header(request.metadata().get(CLIENT_CERT_HEADER)?.to_str().ok()?)
+}
+
+fn from_header(header: &str) -> Option<ClientCertificate> {
+    let pem = percent_encoding::percent_decode_str(header).decode_utf8().ok()?;
+
+    ClientCertificate::from_pem(&pem)
+}
+
+/// Normalizes the fingerprint into lowercase hex without separators
+pub fn normalize_fingerprint(fingerprint: &str) -> Option<String> {
+    let fingerprint = fingerprint.chars()
+        .filter(|c| *c != ':')
+        .collect::<String>()
+        .to_lowercase();
+
+    if fingerprint.len() == 64 && fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(fingerprint)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FINGERPRINT: &str = "3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192a";
+
+    #[test]
+    fn test_normalize_fingerprint() {
+        let colon_separated = FINGERPRINT.as_bytes()
+            .chunks(2)
+            .map(|pair| std::str::from_utf8(pair).unwrap())
+            .collect::<Vec<&str>>()
+            .join(":");
+
+        assert_eq!(Some(FINGERPRINT.to_string()), normalize_fingerprint(FINGERPRINT));
+        assert_eq!(Some(FINGERPRINT.to_string()), normalize_fingerprint(FINGERPRINT.to_uppercase().as_str()));
+        assert_eq!(Some(FINGERPRINT.to_string()), normalize_fingerprint(colon_separated.as_str()));
+        assert_eq!(Some(FINGERPRINT.to_string()), normalize_fingerprint(colon_separated.to_uppercase().as_str()));
+    }
+
+    #[test]
+    fn test_normalize_fingerprint_rejects_malformed() {
+        assert_eq!(None, normalize_fingerprint(""));
+        assert_eq!(None, normalize_fingerprint(&FINGERPRINT[2..]));
+        assert_eq!(None, normalize_fingerprint(format!("{}00", FINGERPRINT).as_str()));
+        assert_eq!(None, normalize_fingerprint(FINGERPRINT.replace('a', "g").as_str()));
+    }
+
+    #[test]
+    fn test_from_pem() {
+        let pem = format!("-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n", base64::encode(b"certificate"));
+
+        let certificate = ClientCertificate::from_pem(pem.as_str()).unwrap();
+
+        assert_eq!(fingerprint(b"certificate"), certificate.fingerprint);
+    }
+
+    #[test]
+    fn test_from_pem_rejects_malformed() {
+        assert!(ClientCertificate::from_pem("-----BEGIN CERTIFICATE-----\nnot base64!\n-----END CERTIFICATE-----\n").is_none());
+        assert!(from_header("%ZZ%ZZ").is_none());
+    }
+
+    #[test]
+    fn test_from_header() {
+        let pem = format!("-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n", base64::encode(b"certificate"));
+        let header = percent_encoding::utf8_percent_encode(pem.as_str(), percent_encoding::NON_ALPHANUMERIC).to_string();
+
+        assert_eq!(fingerprint(b"certificate"), from_header(header.as_str()).unwrap().fingerprint);
+    }
+}
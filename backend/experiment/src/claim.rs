@@ -0,0 +1,98 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::pg::expression::extensions::IntervalDsl;
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use core::error::ErrorMessaging;
+use core::schema::{claim_codes, runners};
+use core::types::{ModelId, UserId};
+use core::utils::{Hash, random_key};
+
+use crate::ErrorMessage;
+use crate::models::runner::{Runner, RUNNER_TOKEN_TIMEOUT, RunnerToken};
+
+// Claim codes can not be used after this many seconds
+const CLAIM_CODE_TIMEOUT: i64 = 60 * 60;
+
+const CLAIM_CODE_LENGTH: usize = 9;
+
+/// Claim code is only shown once, only the hash of it is stored.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimCode {
+    pub code: String,
+    pub expires_at: NaiveDateTime,
+}
+
+/// Token of a runner provisioned by an admin, it is only shown once.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProvisionedRunner {
+    pub runner_id: Uuid,
+    pub token: String,
+}
+
+pub fn create_code(user_id: UserId, name: String, labels: Vec<String>, hash: &Hash, conn: &PgConnection) -> QueryResult<ClaimCode> {
+    let code = random_key(CLAIM_CODE_LENGTH);
+
+    let expires_at = diesel::insert_into(claim_codes::table)
+        .values((
+            claim_codes::code_hash.eq(hash.sign256(code.as_str())),
+            claim_codes::name.eq(name),
+            claim_codes::labels.eq(labels),
+            claim_codes::created_by.eq(user_id),
+            claim_codes::expires_at.eq(now + CLAIM_CODE_TIMEOUT.seconds())
+        ))
+        .returning(claim_codes::expires_at)
+        .get_result::<NaiveDateTime>(conn)?;
+
+    Ok(ClaimCode { code, expires_at })
+}
+
+/// Creates the runner described by the claim code and returns the encoded token of it. Each code can be
+/// claimed only once.
+pub fn claim(code: &str, hash: &Hash, conn: &PgConnection) -> Result<String, Box<dyn ErrorMessaging>> {
+    conn.transaction(|| {
+        let (claim_code_id, name, labels) = claim_codes::table
+            .filter(claim_codes::code_hash.eq(hash.sign256(code)))
+            .filter(claim_codes::runner_id.is_null())
+            .filter(claim_codes::claimed_at.is_null())
+            .filter(claim_codes::expires_at.gt(now))
+            .select((claim_codes::id, claim_codes::name, claim_codes::labels))
+            .for_update()
+            .first::<(ModelId, String, Vec<String>)>(conn)
+            .optional()?
+            .ok_or_else(|| ErrorMessage::InvalidClaimCode)?;
+
+        let (runner, token) = create_runner(name, labels, hash, conn)?;
+
+        diesel::update(claim_codes::table.find(claim_code_id))
+            .set((
+                claim_codes::runner_id.eq(runner.id),
+                claim_codes::claimed_at.eq(now.nullable())
+            ))
+            .execute(conn)?;
+
+        Ok(token)
+    })
+}
+
+/// Creates a runner with a fresh access key, returns the runner along with its encoded token.
+pub fn create_runner(name: String, labels: Vec<String>, hash: &Hash, conn: &PgConnection) -> Result<(Runner, String), Box<dyn ErrorMessaging>> {
+    let (access_key, access_key_hash) = RunnerToken::generate_access_key(hash);
+
+    let runner = diesel::insert_into(runners::table)
+        .values((
+            runners::access_key_hash.eq(access_key_hash),
+            runners::name.eq(name),
+            runners::labels.eq(labels)
+        ))
+        .get_result::<Runner>(conn)?;
+
+    let (token, _) = RunnerToken::new(runner.id, access_key, RUNNER_TOKEN_TIMEOUT).encode(hash)?;
+
+    Ok((runner, token))
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use actix_web::web;
+use diesel::prelude::*;
+use log::info;
+
+use core::error::ErrorMessaging;
+use core::ErrorMessage;
+use core::schema::runners;
+use core::types::DBPool;
+use core::utils::Hash;
+
+use crate::certificate::ClientCertificate;
+use crate::ErrorMessage as ExperimentErrorMessage;
+use crate::models::runner::{ConnectTicket, Runner, RunnerToken};
+use crate::policy::RunnerPolicy;
+
+/// Sessions open with each credential, either a token or a certificate, shared by the transports. Slot of a session is
+/// reserved while its runner is admitted, so that the concurrent connections with a credential can not exceed the
+/// limit, and it is released once the session is dropped.
+#[derive(Clone, Default)]
+pub struct ConnectionSlots {
+    counts: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl ConnectionSlots {
+    /// Reserves a slot for the credential, unless it has as many open connections as the limit
+    pub fn reserve(&self, credential: &str, max_connections: Option<usize>) -> Option<ConnectionSlot> {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.get(credential).copied().unwrap_or(0);
+
+        if let Some(max_connections) = max_connections {
+            if count >= max_connections {
+                return None;
+            }
+        }
+
+        counts.insert(credential.to_string(), count + 1);
+
+        Some(ConnectionSlot { slots: self.clone(), credential: credential.to_string() })
+    }
+
+    fn release(&self, credential: &str) {
+        let mut counts = self.counts.lock().unwrap();
+
+        if let Some(count) = counts.get_mut(credential) {
+            *count -= 1;
+
+            if *count == 0 {
+                counts.remove(credential);
+            }
+        }
+    }
+}
+
+/// Connection reserved for a credential until it is dropped, see [ConnectionSlots]
+pub struct ConnectionSlot {
+    slots: ConnectionSlots,
+    credential: String,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.slots.release(&self.credential);
+    }
+}
+
+/// Runner which is allowed to join the server.
+pub struct Admission {
+    pub runner: Runner,
+    // connection of the credential the runner is connected with, held by its session
+    pub slot: ConnectionSlot,
+    // hash of the access key and the expire time of the token, if the runner is connected with a token
+    pub token: Option<(String, i64)>,
+    // token is issued in the previous format, see `RunnerToken::decode`
+    pub legacy_token: bool,
+}
+
+/// Credential the runner joins the server with, its client certificate is used if it does not give one
+#[derive(Clone, Copy)]
+pub enum Credential<'a> {
+    Token(&'a str),
+    // minted with a token by `runner/ticket`, see `ConnectTicket`
+    Ticket(&'a str),
+}
+
+/// Identifies the runner with its token or ticket, or with its client certificate if there is neither, then checks
+/// whether the runner may connect. Runners are admitted the same way whichever transport they connect over.
+pub async fn admit(
+    pool: &DBPool,
+    hash: &Hash,
+    policy: &RunnerPolicy,
+    credential: Option<Credential<'_>>,
+    certificate: Option<ClientCertificate>,
+    remote_addr: Option<IpAddr>,
+) -> Result<Admission, Box<dyn ErrorMessaging>> {
+    let (runner, credential, token, legacy_token) = match credential {
+        Some(Credential::Token(token)) => {
+            let (runner, access_key_hash, token) = identify(pool, hash, token).await?;
+
+            (runner, access_key_hash.clone(), Some((access_key_hash, token.claims.exp)), token.legacy)
+        }
+        Some(Credential::Ticket(ticket)) => {
+            let conn = pool.get().unwrap();
+            let ticket = ticket.to_string();
+            let hash = hash.clone();
+
+            let (runner, access_key_hash, expires_at) = web::block(move || ConnectTicket::redeem(ticket.as_str(), &hash, &conn))
+                .await?
+                .ok_or(ErrorMessage::InvalidToken)?;
+
+            // connections of a ticket are counted along with the ones of its token
+            (runner, access_key_hash.clone(), Some((access_key_hash, expires_at)), false)
+        }
+        None => {
+            let conn = pool.get().unwrap();
+            let certificate = certificate
+                .ok_or(ExperimentErrorMessage::CredentialsNotFound)?;
+            let fingerprint = certificate.fingerprint.clone();
+
+            let runner = web::block(move || runners::table
+                .filter(runners::certificate_fingerprint.eq(fingerprint))
+                .first::<Runner>(&conn)
+            )
+                .await?;
+
+            (runner, format!("certificate:{}", certificate.fingerprint), None, false)
+        }
+    };
+
+    if runner.disabled {
+        return Err(ExperimentErrorMessage::RunnerDisabled.into());
+    }
+
+    let allowed = match remote_addr {
+        Some(addr) => policy.is_allowed(addr, &runner.allowed_networks),
+        None => false
+    };
+
+    if !allowed {
+        info!("runner {} is rejected, connecting from a not allowed address", runner.id);
+        return Err(ExperimentErrorMessage::AddressNotAllowed.into());
+    }
+
+    let slot = policy.reserve_connection(&credential)
+        .ok_or(ExperimentErrorMessage::TooManyConnections)?;
+
+    Ok(Admission {
+        runner,
+        slot,
+        token,
+        legacy_token,
+    })
+}
+
+/// Identifies the runner with its token and returns it along with the hash of the access key and the decoded token
+pub async fn identify(pool: &DBPool, hash: &Hash, token: &str) -> Result<(Runner, String, RunnerToken), Box<dyn ErrorMessaging>> {
+    let conn = pool.get().unwrap();
+    let token = RunnerToken::decode(token, hash)?;
+    let access_key_hash = hash.sign256(token.access_key.as_str());
+
+    let runner = {
+        let access_key_hash = access_key_hash.clone();
+
+        web::block(move || -> Result<Runner, diesel::result::Error> {
+            let runner = runners::table
+                .filter(runners::access_key_hash.eq(&access_key_hash).or(runners::previous_access_key_hash.eq(&access_key_hash)))
+                .first::<Runner>(&conn)?;
+
+            // Runner has received the rotated key, previous one is not needed anymore
+            if runner.access_key_hash == access_key_hash && runner.previous_access_key_hash.is_some() {
+                diesel::update(runners::table.find(runner.id))
+                    .set(runners::previous_access_key_hash.eq(None::<String>))
+                    .execute(&conn)?;
+            }
+
+            Ok(runner)
+        })
+            .await?
+    };
+
+    // access key of another runner is not accepted, even though the token is signed
+    if !token.is_issued_for(runner.id) {
+        return Err(ErrorMessage::InvalidToken.into());
+    }
+
+    Ok((runner, access_key_hash, token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slots_are_limited_per_credential() {
+        let slots = ConnectionSlots::default();
+
+        let first = slots.reserve("token", Some(2)).unwrap();
+        let _second = slots.reserve("token", Some(2)).unwrap();
+
+        assert!(slots.reserve("token", Some(2)).is_none());
+        // other credentials are counted separately
+        assert!(slots.reserve("certificate:ab", Some(2)).is_some());
+
+        drop(first);
+
+        assert!(slots.reserve("token", Some(2)).is_some());
+    }
+
+    #[test]
+    fn test_slots_are_released_once_dropped() {
+        let slots = ConnectionSlots::default();
+
+        drop(slots.reserve("token", None).unwrap());
+
+        assert!(slots.counts.lock().unwrap().is_empty());
+        assert!(slots.reserve("token", Some(0)).is_none());
+        assert!(slots.counts.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_reservations_do_not_exceed_limit() {
+        let slots = ConnectionSlots::default();
+
+        let reserved = (0..16)
+            .map(|_| {
+                let slots = slots.clone();
+                std::thread::spawn(move || slots.reserve("token", Some(3)))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|handle| handle.join().unwrap())
+            .collect::<Vec<ConnectionSlot>>();
+
+        assert_eq!(reserved.len(), 3);
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix::prelude::*;
+use actix_web::web;
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Nullable, Text, Timestamp};
+use log::error;
+
+use core::db::DieselEnum;
+use core::schema::{runner_job_stats, user_job_stats};
+use core::types::{DBPool, RunnerId};
+
+use crate::connection::messages::RunnerScoresMessage;
+use crate::connection::server::ExperimentServer;
+use crate::models::accounting;
+use crate::models::job::JobStatus;
+
+const AGGREGATE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+// Only the jobs finished in this many days are taken into account
+const STATS_WINDOW_DAYS: i32 = 7;
+
+// Keeps the score of the runners failing all of their jobs finite
+const MIN_SUCCESS_RATE: f64 = 0.05;
+
+/// Periodically computes the duration percentiles and the failure rate of the recently finished jobs
+/// per runner and per experiment, and records the costs of the finished jobs for the accounting. Runner statistics are also sent to the `ExperimentServer` so that it
+/// prefers fast and reliable runners.
+pub struct StatsAggregator {
+    pool: DBPool,
+    experiment_server: Addr<ExperimentServer>,
+}
+
+impl StatsAggregator {
+    pub fn new(pool: DBPool, experiment_server: Addr<ExperimentServer>) -> Self {
+        StatsAggregator {
+            pool,
+            experiment_server,
+        }
+    }
+
+    fn aggregate(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let conn = self.pool.get().unwrap();
+        let experiment_server = self.experiment_server.clone();
+
+        async move {
+            let scores = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+                aggregate_into("runner_job_stats", "runner_id", &conn)?;
+                aggregate_into("experiment_job_stats", "experiment_id", &conn)?;
+                // energy of the jobs is rolled up from their costs
+                accounting::record_job_costs(&conn)?;
+                rollup_user_stats(&conn)?;
+                accounting::rollup_monthly_costs(&conn)?;
+
+                runner_scores(&conn)
+            }))
+                .await;
+
+            match scores {
+                Ok(scores) => experiment_server.do_send(RunnerScoresMessage { scores }),
+                Err(e) => error!("aggregating job stats is failed: {:?}", e)
+            }
+        }
+            .into_actor(self)
+            .spawn(ctx);
+    }
+}
+
+/// Replaces the statistics in `table` with the ones of the jobs grouped by `column`. Jobs which are
+/// not started by a runner, like the ones cancelled while pending, are not counted.
+fn aggregate_into(table: &str, column: &str, conn: &PgConnection) -> QueryResult<()> {
+    diesel::sql_query(format!("DELETE FROM {}", table))
+        .execute(conn)?;
+
+    diesel::sql_query(format!(
+        "INSERT INTO {table} ({column}, job_count, p50_duration, p95_duration, failure_rate)
+         SELECT {column},
+                COUNT(*),
+                percentile_cont(0.5) WITHIN GROUP (ORDER BY date_part('epoch', finished_at - started_at)),
+                percentile_cont(0.95) WITHIN GROUP (ORDER BY date_part('epoch', finished_at - started_at)),
+                AVG(CASE WHEN status = $1 THEN 0.0 ELSE 1.0 END)::double precision
+         FROM all_jobs
+         WHERE {column} IS NOT NULL
+           AND status IN ($1, $2, $3)
+           AND started_at IS NOT NULL
+           AND finished_at > CURRENT_TIMESTAMP - make_interval(days => $4)
+         GROUP BY {column}",
+        table = table,
+        column = column,
+    ))
+        .bind::<Text, _>(JobStatus::Successful.value())
+        .bind::<Text, _>(JobStatus::Failed.value())
+        .bind::<Text, _>(JobStatus::TimedOut.value())
+        .bind::<Integer, _>(STATS_WINDOW_DAYS)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Rolls the finished jobs up into the daily statistics of their users, per runner. Finished jobs do not change, so
+/// only the days since the last rolled up one are computed again, jobs of that day may have finished after the
+/// previous rollup. Jobs moved into the archive are rolled up as well. Energy is taken from the recorded costs of the
+/// jobs, the ones which did not report it are not counted in the energy.
+fn rollup_user_stats(conn: &PgConnection) -> QueryResult<()> {
+    let since = user_job_stats::table
+        .select(diesel::dsl::max(user_job_stats::day))
+        .first::<Option<NaiveDate>>(conn)?;
+
+    diesel::delete(user_job_stats::table.filter(user_job_stats::day.nullable().ge(since)))
+        .execute(conn)?;
+
+    diesel::sql_query(
+        "INSERT INTO user_job_stats (user_id, runner_id, day, job_count, successful_jobs, failed_jobs, runtime_seconds, energy_mj)
+         SELECT experiments.user_id,
+                all_jobs.runner_id,
+                all_jobs.finished_at::date,
+                COUNT(*),
+                COUNT(*) FILTER (WHERE all_jobs.status = $1),
+                COUNT(*) FILTER (WHERE all_jobs.status IN ($2, $3)),
+                COALESCE(SUM(date_part('epoch', all_jobs.finished_at - all_jobs.started_at)), 0),
+                COALESCE(SUM(job_costs.energy_mj), 0)
+         FROM all_jobs
+         INNER JOIN experiments ON experiments.id = all_jobs.experiment_id
+         LEFT JOIN job_costs ON job_costs.job_id = all_jobs.id
+         WHERE all_jobs.finished_at IS NOT NULL
+           AND ($4 IS NULL OR all_jobs.finished_at >= $4)
+         GROUP BY experiments.user_id, all_jobs.runner_id, all_jobs.finished_at::date"
+    )
+        .bind::<Text, _>(JobStatus::Successful.value())
+        .bind::<Text, _>(JobStatus::Failed.value())
+        .bind::<Text, _>(JobStatus::TimedOut.value())
+        .bind::<Nullable<Timestamp>, _>(since.map(|since| since.and_hms_opt(0, 0, 0).unwrap()))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Score of a runner is its expected duration of a successful job, i.e. the median duration
+/// weighted by how often the jobs fail on it
+fn runner_scores(conn: &PgConnection) -> QueryResult<HashMap<RunnerId, f64>> {
+    let stats = runner_job_stats::table
+        .select((runner_job_stats::runner_id, runner_job_stats::p50_duration, runner_job_stats::failure_rate))
+        .load::<(RunnerId, Option<f64>, f64)>(conn)?;
+
+    Ok(stats.into_iter()
+        .filter_map(|(runner_id, p50_duration, failure_rate)| {
+            p50_duration.map(|p50| (runner_id, p50 / (1.0 - failure_rate).max(MIN_SUCCESS_RATE)))
+        })
+        .collect())
+}
+
+impl Actor for StatsAggregator {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        // Scores are kept in memory by the server, compute them right away instead of waiting an interval
+        self.aggregate(ctx);
+        ctx.run_interval(AGGREGATE_INTERVAL, |act, ctx| act.aggregate(ctx));
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use actix::prelude::*;
+use actix_web::web;
+use diesel::dsl::now;
+use diesel::pg::expression::extensions::IntervalDsl;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, Integer};
+use log::{error, info};
+
+use core::db::DieselEnum;
+use core::schema::jobs;
+use core::types::{DBPool, JobId};
+
+use crate::models::job::JobStatus;
+
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Jobs are moved in batches, each in its own transaction, so that their rows are not locked for long
+const JOB_BATCH_SIZE: i64 = 500;
+
+// Columns are named so that a column added to the jobs without the archive fails loudly instead of being shifted
+// into another one. Columns added to the jobs should be added to the archive, here and to the all_jobs view.
+const JOB_COLUMNS: &str = "id, experiment_id, runner_id, code_hash, status, created_at, updated_at, output, output_truncated, \
+    ansi_mode, failure_reason, started_at, finished_at, hooks, firmware_id, flash_status, uuid, protected, batch_id, \
+    lease_expires_at, reclaim_count, queue_priority, annotation, verdict, annotated_at, derived_verdict, warmup";
+const JOB_STREAM_COLUMNS: &str = "id, job_id, name, output, truncated, created_at";
+
+/// Periodically moves the jobs finished earlier than `archive_after_days` into the archive along with their streams,
+/// so that the tables of the active jobs stay small on the long-lived deployments. Artifacts, environments and
+/// activities of the jobs stay where they are. Archived jobs are still served by the job endpoints and listed when
+/// they are asked for, though they can not be modified anymore, e.g. annotated or protected.
+pub struct JobArchiver {
+    pool: DBPool,
+    archive_after_days: i32,
+}
+
+impl JobArchiver {
+    pub fn new(pool: DBPool, archive_after_days: i32) -> Self {
+        JobArchiver {
+            pool,
+            archive_after_days,
+        }
+    }
+
+    fn archive(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let conn = self.pool.get().unwrap();
+        let archive_after_days = self.archive_after_days;
+
+        async move {
+            match web::block(move || archive_finished_jobs(archive_after_days, &conn)).await {
+                Ok(archived) => {
+                    if archived > 0 {
+                        info!("{} jobs are archived", archived);
+                    }
+                }
+                Err(e) => error!("archiving jobs is failed: {:?}", e)
+            }
+        }
+            .into_actor(self)
+            .spawn(ctx);
+    }
+}
+
+/// Returns the number of the archived jobs. Jobs locked by another replica archiving them are skipped.
+fn archive_finished_jobs(archive_after_days: i32, conn: &PgConnection) -> QueryResult<usize> {
+    let mut archived = 0;
+
+    loop {
+        let moved = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let job_ids = jobs::table
+                .filter(jobs::status.eq_any(vec![
+                    JobStatus::Successful.value(),
+                    JobStatus::Failed.value(),
+                    JobStatus::Cancelled.value(),
+                    JobStatus::TimedOut.value(),
+                ]))
+                .filter(jobs::finished_at.lt((now - archive_after_days.days()).nullable()))
+                .order(jobs::id.asc())
+                .limit(JOB_BATCH_SIZE)
+                .select(jobs::id)
+                .for_update()
+                .skip_locked()
+                .load::<JobId>(conn)?;
+
+            if job_ids.is_empty() {
+                return Ok(0);
+            }
+
+            diesel::sql_query(format!("INSERT INTO archived_jobs ({0}) SELECT {0} FROM jobs WHERE id = ANY($1)", JOB_COLUMNS))
+                .bind::<Array<Integer>, _>(&job_ids)
+                .execute(conn)?;
+
+            diesel::sql_query(format!("INSERT INTO archived_job_streams ({0}) SELECT {0} FROM job_streams WHERE job_id = ANY($1)", JOB_STREAM_COLUMNS))
+                .bind::<Array<Integer>, _>(&job_ids)
+                .execute(conn)?;
+
+            // streams are removed by the cascade, while the artifacts, the environments and the activities are kept
+            // since the jobs are in the archive
+            diesel::delete(jobs::table.filter(jobs::id.eq_any(&job_ids)))
+                .execute(conn)
+        })?;
+
+        if moved == 0 {
+            return Ok(archived);
+        }
+
+        archived += moved;
+    }
+}
+
+impl Actor for JobArchiver {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(ARCHIVE_INTERVAL, |act, ctx| act.archive(ctx));
+    }
+}
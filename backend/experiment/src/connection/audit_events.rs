@@ -0,0 +1,106 @@
+use actix::Addr;
+use askama::Template;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use log::error;
+use serde::Deserialize;
+
+use core::schema::{security_mails, users};
+use core::types::{DBPool, ModelId, UserId};
+use service::MailService;
+
+use crate::connection::listener;
+use crate::connection::messages::SecurityEventMessage;
+use crate::connection::server::ExperimentServer;
+use crate::notifications::{SecurityEvent, SecurityNotification};
+
+// Notified by the audit_event_notify trigger for the security relevant entries of the audit log
+const CHANNEL: &str = "audit_events";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuditEvent {
+    id: ModelId,
+    user_id: UserId,
+    action: String,
+    details: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Template)]
+#[template(path = "mails/security-event.html")]
+struct SecurityEventMailTemplate<'a> {
+    full_name: &'a str,
+    new_device: bool,
+    details: &'a str,
+}
+
+/// Notifies the users over their websocket connections and mails them when an api key is created for them or they
+/// sign in from a new device. Entries are emitted by the database, so the ones recorded by any replica are notified.
+pub fn listen_audit_events(database_url: String, pool: DBPool, experiment_server: Addr<ExperimentServer>, mail: MailService) {
+    listener::listen("audit_events", database_url, &[CHANNEL], || {}, move |_, payload| {
+        let event = match serde_json::from_str::<AuditEvent>(payload) {
+            Ok(event) => event,
+            Err(e) => {
+                error!("invalid audit event is received: {:?}", e);
+                return;
+            }
+        };
+
+        let kind = match event.action.as_str() {
+            "api_key.create" => SecurityEvent::ApiKeyCreated,
+            "session.new_device" => SecurityEvent::NewDeviceLogin,
+            _ => return
+        };
+
+        let notification = SecurityNotification {
+            event: kind,
+            details: event.details,
+            created_at: event.created_at,
+        };
+
+        experiment_server.do_send(SecurityEventMessage {
+            user_id: event.user_id,
+            notification: notification.clone(),
+        });
+
+        if let Err(e) = mail_event(event.id, event.user_id, &notification, &pool, &mail) {
+            error!("mailing audit event {} is failed: {:?}", event.id, e);
+        }
+    });
+}
+
+/// Mails the event to the user, unless another replica has already mailed it
+fn mail_event(audit_log_id: ModelId, user_id: UserId, notification: &SecurityNotification, pool: &DBPool, mail: &MailService)
+              -> Result<(), Box<dyn std::error::Error>> {
+    let conn = pool.get()?;
+
+    let claimed = diesel::insert_into(security_mails::table)
+        .values(security_mails::audit_log_id.eq(audit_log_id))
+        .on_conflict_do_nothing()
+        .execute(&conn)?;
+
+    if claimed == 0 {
+        return Ok(());
+    }
+
+    let (email, first_name, last_name) = users::table
+        .find(user_id)
+        .select((users::email, users::first_name, users::last_name))
+        .first::<(String, String, String)>(&conn)?;
+
+    let new_device = notification.event == SecurityEvent::NewDeviceLogin;
+
+    let text = SecurityEventMailTemplate {
+        full_name: format!("{} {}", first_name, last_name).as_str(),
+        new_device,
+        details: notification.details.as_deref().unwrap_or_default(),
+    }
+        .render()?;
+
+    let subject = if new_device { "New sign in to your account" } else { "New api key for your account" };
+
+    mail.send_mail(email, subject.to_string(), text);
+
+    Ok(())
+}
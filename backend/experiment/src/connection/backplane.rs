@@ -0,0 +1,97 @@
+use actix::Addr;
+use actix_web::web;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use core::types::{DBPool, JobId, RunnerId, UserId};
+
+use crate::connection::listener;
+use crate::connection::messages::{BackplaneEventMessage, SyncPendingRunsMessage};
+use crate::connection::server::ExperimentServer;
+use crate::notifications::Notification;
+
+const CHANNEL: &str = "experiment_server";
+
+/// Events shared between the backend replicas
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "kind", content = "data")]
+pub enum Event {
+    // Job is waiting for an idle runner, any replica may dispatch it
+    JobPending { job_id: JobId },
+    // Job is dispatched by a replica, others can drop it from their queue
+    JobClaimed { job_id: JobId },
+    // Notification should reach the user sessions connected to any replica
+    Notify { user_id: UserId, notification: Notification },
+    // Runners connected to the publishing replica, sent periodically
+    Presence { runner_ids: Vec<RunnerId> },
+}
+
+#[derive(Deserialize, Serialize)]
+struct Envelope {
+    origin: u64,
+    event: Event,
+}
+
+/// Postgres LISTEN/NOTIFY backplane which lets multiple backend replicas share the runner fleet.
+/// Every replica dispatches jobs to its own runners, a job is claimed by moving it into Running
+/// state so that it is run only once even if several replicas try to dispatch it.
+#[derive(Clone)]
+pub struct Backplane {
+    pool: DBPool,
+    // identifies the events published by this replica, which are ignored when received back
+    origin: u64,
+}
+
+impl Backplane {
+    pub fn new(pool: DBPool) -> Self {
+        Backplane {
+            pool,
+            origin: rand::random(),
+        }
+    }
+
+    /// Publishes the event to the other replicas
+    pub async fn publish(self, event: Event) {
+        let payload = match serde_json::to_string(&Envelope { origin: self.origin, event }) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("serializing backplane event is failed: {:?}", e);
+                return;
+            }
+        };
+
+        let conn = self.pool.get().unwrap();
+
+        if let Err(e) = web::block(move || diesel::sql_query("SELECT pg_notify($1, $2)")
+            .bind::<Text, _>(CHANNEL)
+            .bind::<Text, _>(payload)
+            .execute(&conn)
+        )
+            .await {
+            error!("publishing backplane event is failed: {:?}", e);
+        }
+    }
+
+    /// Listens the events of the other replicas and forwards them to the server. Pending runs are synced
+    /// whenever the backplane is (re)connected.
+    pub fn listen(&self, database_url: String, experiment_server: Addr<ExperimentServer>) {
+        let origin = self.origin;
+        let server = experiment_server.clone();
+
+        listener::listen("backplane", database_url, &[CHANNEL], move || server.do_send(SyncPendingRunsMessage), move |_, payload| {
+            let envelope = match serde_json::from_str::<Envelope>(payload) {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    error!("invalid backplane event is received: {:?}", e);
+                    return;
+                }
+            };
+
+            if envelope.origin != origin {
+                experiment_server.do_send(BackplaneEventMessage { event: envelope.event });
+            }
+        });
+    }
+}
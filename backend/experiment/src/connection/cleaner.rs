@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use actix::prelude::*;
+use actix_web::web;
+use diesel::dsl::{exists, not};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+use log::{error, info};
+
+use core::db::DieselEnum;
+use core::schema::{archived_jobs, experiments, firmwares, jobs};
+use core::types::{DBPool, ExperimentId, JobId, ModelId};
+
+use crate::models::job::{JobStatus, TransitionError};
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+// Experiments cleaned up at each interval, the rest are left to the following ones
+const EXPERIMENT_BATCH_SIZE: i64 = 10;
+
+// Jobs are deleted in batches, so that deleting a large experiment does not lock its rows for long
+const JOB_BATCH_SIZE: i64 = 500;
+
+/// Retention of the finished jobs, a job is removed once it is older than `max_age_days` or there are
+/// `max_jobs` newer jobs in its experiment. Experiments may override both, protected jobs are always kept.
+#[derive(Clone, Copy)]
+pub struct RetentionPolicy {
+    pub max_age_days: Option<i32>,
+    pub max_jobs: Option<i32>,
+}
+
+/// Periodically removes the deleted experiments along with their jobs, job streams and the firmwares which are
+/// not used anymore, then the jobs which are out of the retention. Experiments are only marked as deleted by the
+/// handlers, so that deleting them returns immediately regardless of how many jobs they have.
+pub struct ExperimentCleaner {
+    pool: DBPool,
+    retention: RetentionPolicy,
+}
+
+impl ExperimentCleaner {
+    pub fn new(pool: DBPool, retention: RetentionPolicy) -> Self {
+        ExperimentCleaner {
+            pool,
+            retention,
+        }
+    }
+
+    fn clean(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let conn = self.pool.get().unwrap();
+        let retention = self.retention;
+
+        async move {
+            let cleaned = web::block(move || -> QueryResult<_> {
+                let experiments = clean_deleted_experiments(&conn)?;
+                let jobs = purge_expired_jobs(retention, &conn)?;
+
+                Ok((experiments, jobs))
+            })
+                .await;
+
+            match cleaned {
+                Ok((experiments, jobs)) => {
+                    if experiments > 0 {
+                        info!("{} deleted experiments are cleaned up", experiments);
+                    }
+
+                    if jobs > 0 {
+                        info!("{} jobs are purged by the retention policy", jobs);
+                    }
+                }
+                Err(e) => error!("cleaning up is failed: {:?}", e)
+            }
+        }
+            .into_actor(self)
+            .spawn(ctx);
+    }
+}
+
+/// Cancels the pending and running jobs of a deleted experiment, so that they are not dispatched while the
+/// experiment waits to be cleaned up. Should be called in the same transaction the experiment is deleted.
+pub fn cancel_active_jobs(experiment_id: ExperimentId, conn: &PgConnection) -> QueryResult<()> {
+    let active_jobs = jobs::table
+        .filter(jobs::experiment_id.eq(experiment_id))
+        .filter(jobs::status.eq_any(vec![JobStatus::Pending.value(), JobStatus::Running.value()]))
+        .select(jobs::id)
+        .load::<JobId>(conn)?;
+
+    for job_id in active_jobs {
+        // Job may have finished in the meantime, which is rejected by the transition
+        match JobStatus::transition_to(job_id, JobStatus::Cancelled).apply(conn) {
+            Ok(()) | Err(TransitionError::Illegal { .. }) => {}
+            Err(TransitionError::DB(e)) => return Err(e)
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the number of the cleaned up experiments. Each batch of jobs is deleted in its own statement, an
+/// experiment interrupted midway is picked up again at the next interval.
+fn clean_deleted_experiments(conn: &PgConnection) -> QueryResult<usize> {
+    let deleted_experiments = experiments::table
+        .filter(experiments::deleted_at.is_not_null())
+        .order(experiments::deleted_at.asc())
+        .select((experiments::id, experiments::firmware_id))
+        .limit(EXPERIMENT_BATCH_SIZE)
+        .load::<(ExperimentId, Option<ModelId>)>(conn)?;
+
+    for (experiment_id, firmware_id) in &deleted_experiments {
+        // firmwares are collected before the jobs referring to them are gone
+        let mut firmware_ids = jobs::table
+            .filter(jobs::experiment_id.eq(experiment_id))
+            .filter(jobs::firmware_id.is_not_null())
+            .select(jobs::firmware_id)
+            .distinct()
+            .load::<Option<ModelId>>(conn)?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<ModelId>>();
+
+        firmware_ids.extend(
+            archived_jobs::table
+                .filter(archived_jobs::experiment_id.eq(experiment_id))
+                .filter(archived_jobs::firmware_id.is_not_null())
+                .select(archived_jobs::firmware_id)
+                .distinct()
+                .load::<Option<ModelId>>(conn)?
+                .into_iter()
+                .flatten()
+        );
+
+        firmware_ids.extend(firmware_id);
+
+        // streams, idempotency keys and scheduled runs of the jobs are removed by the cascades
+        for table in &["jobs", "archived_jobs"] {
+            loop {
+                let deleted = diesel::sql_query(format!("DELETE FROM {0} WHERE id IN (SELECT id FROM {0} WHERE experiment_id = $1 LIMIT $2)", table))
+                    .bind::<Integer, _>(experiment_id)
+                    .bind::<BigInt, _>(JOB_BATCH_SIZE)
+                    .execute(conn)?;
+
+                if deleted == 0 {
+                    break;
+                }
+            }
+        }
+
+        diesel::delete(experiments::table.find(experiment_id))
+            .execute(conn)?;
+
+        // firmwares are kept as long as another experiment or job refers to them
+        diesel::delete(
+            firmwares::table
+                .filter(firmwares::id.eq_any(firmware_ids))
+                .filter(not(exists(experiments::table.filter(experiments::firmware_id.eq(firmwares::id.nullable())))))
+                .filter(not(exists(jobs::table.filter(jobs::firmware_id.eq(firmwares::id.nullable())))))
+                .filter(not(exists(archived_jobs::table.filter(archived_jobs::firmware_id.eq(firmwares::id.nullable())))))
+        )
+            .execute(conn)?;
+    }
+
+    Ok(deleted_experiments.len())
+}
+
+/// Removes the finished jobs which are out of the retention of their experiments in batches, returns the number of
+/// the removed jobs. Protected jobs are not removed, they are still counted among the latest jobs though. Jobs are
+/// ranked along with the archived ones, which are removed the same way.
+fn purge_expired_jobs(retention: RetentionPolicy, conn: &PgConnection) -> QueryResult<usize> {
+    let mut purged = 0;
+
+    for table in &["jobs", "archived_jobs"] {
+        loop {
+            let deleted = diesel::sql_query(format!(
+                "DELETE FROM {} WHERE id IN (
+                     SELECT id
+                     FROM (SELECT all_jobs.id,
+                                  all_jobs.status,
+                                  all_jobs.protected,
+                                  all_jobs.created_at,
+                                  row_number() OVER (PARTITION BY all_jobs.experiment_id ORDER BY all_jobs.id DESC) AS rank,
+                                  COALESCE(experiments.retention_days, $1) AS retention_days,
+                                  COALESCE(experiments.retention_jobs, $2) AS retention_jobs
+                           FROM all_jobs
+                           INNER JOIN experiments ON experiments.id = all_jobs.experiment_id
+                           WHERE experiments.deleted_at IS NULL) AS ranked
+                     WHERE NOT protected
+                       AND status IN ($3, $4, $5, $6)
+                       AND (rank > retention_jobs OR created_at < CURRENT_TIMESTAMP - make_interval(days => retention_days))
+                     LIMIT $7
+                 )",
+                table
+            ))
+                .bind::<Nullable<Integer>, _>(retention.max_age_days)
+                .bind::<Nullable<Integer>, _>(retention.max_jobs)
+                .bind::<Text, _>(JobStatus::Successful.value())
+                .bind::<Text, _>(JobStatus::Failed.value())
+                .bind::<Text, _>(JobStatus::Cancelled.value())
+                .bind::<Text, _>(JobStatus::TimedOut.value())
+                .bind::<BigInt, _>(JOB_BATCH_SIZE)
+                .execute(conn)?;
+
+            if deleted == 0 {
+                break;
+            }
+
+            purged += deleted;
+        }
+    }
+
+    Ok(purged)
+}
+
+impl Actor for ExperimentCleaner {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(CLEANUP_INTERVAL, |act, ctx| act.clean(ctx));
+    }
+}
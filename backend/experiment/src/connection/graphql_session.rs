@@ -0,0 +1,108 @@
+use std::time::Instant;
+
+use actix::prelude::*;
+use actix_web_actors::ws::{CloseCode, CloseReason, Message, ProtocolError, WebsocketContext};
+use async_graphql::Data;
+use async_graphql::http::{WebSocket, WebSocketProtocols, WsMessage};
+use futures::channel::mpsc;
+use log::info;
+
+use core::types::UserId;
+
+use crate::connection::session::{CLIENT_TIMEOUT, HEARTBEAT_INTERVAL};
+use crate::graphql::TestbedSchema;
+
+/// Websocket session of a user running graphql subscriptions. Messages of the user are passed to the
+/// graphql websocket protocol, and its replies are written back.
+pub struct GraphqlSession {
+    schema: TestbedSchema,
+    protocol: WebSocketProtocols,
+    user_id: UserId,
+    // taken when the session is started
+    data: Option<Data>,
+    messages: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    hb: Instant,
+}
+
+impl GraphqlSession {
+    pub fn new(schema: TestbedSchema, protocol: WebSocketProtocols, user_id: UserId, data: Data) -> Self {
+        GraphqlSession {
+            schema,
+            protocol,
+            user_id,
+            data: Some(data),
+            messages: None,
+            hb: Instant::now(),
+        }
+    }
+
+    fn hb(&self, ctx: &mut WebsocketContext<Self>) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
+                info!("graphql session of user {} is timed out, disconnecting", act.user_id);
+                ctx.stop();
+                return;
+            }
+
+            ctx.ping(b"");
+        });
+    }
+
+    fn forward(&self, message: Vec<u8>, ctx: &mut WebsocketContext<Self>) {
+        let sent = match &self.messages {
+            Some(messages) => messages.unbounded_send(message).is_ok(),
+            None => false
+        };
+
+        if !sent {
+            ctx.stop();
+        }
+    }
+}
+
+impl Actor for GraphqlSession {
+    type Context = WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.hb(ctx);
+
+        let (sender, receiver) = mpsc::unbounded();
+        self.messages = Some(sender);
+
+        let websocket = WebSocket::new(self.schema.clone(), receiver, self.protocol)
+            .connection_data(self.data.take().unwrap_or_default());
+
+        ctx.add_stream(websocket);
+    }
+}
+
+impl StreamHandler<Result<Message, ProtocolError>> for GraphqlSession {
+    fn handle(&mut self, msg: Result<Message, ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(Message::Ping(bytes)) => {
+                self.hb = Instant::now();
+                ctx.pong(&bytes);
+            }
+            Ok(Message::Pong(_)) => self.hb = Instant::now(),
+            Ok(Message::Text(text)) => self.forward(text.into_bytes(), ctx),
+            Ok(Message::Binary(bytes)) => self.forward(bytes.to_vec(), ctx),
+            Ok(Message::Close(_)) | Err(_) => ctx.stop(),
+            Ok(_) => {}
+        }
+    }
+}
+
+impl StreamHandler<WsMessage> for GraphqlSession {
+    fn handle(&mut self, msg: WsMessage, ctx: &mut Self::Context) {
+        match msg {
+            WsMessage::Text(text) => ctx.text(text),
+            WsMessage::Close(code, description) => {
+                ctx.close(Some(CloseReason {
+                    code: CloseCode::from(code),
+                    description: Some(description),
+                }));
+                ctx.stop();
+            }
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
+
+use actix::prelude::*;
+use actix_codec::{Decoder, Encoder};
+use actix_http::ws::{CloseCode, CloseReason, Codec, Frame as WebsocketFrame, Message};
+use actix_web::error::PayloadError;
+use actix_web::http::StatusCode;
+use actix_web::web::{Bytes, BytesMut};
+use actix_web_actors::ws::WebsocketContext;
+use futures::{Future, SinkExt, StreamExt};
+use futures::channel::mpsc;
+use log::error;
+use rustls::ServerConfig;
+use tonic::{Code, Request, Response, Status, Streaming};
+use tonic::transport::{Server, ServerTlsConfig};
+
+use core::error::ErrorMessaging;
+use core::types::DBPool;
+use core::utils::Hash;
+use shared::grpc::{Close, Frame};
+use shared::grpc::frame::Kind;
+use shared::grpc::runner_server::{Runner, RunnerServer};
+
+use crate::connection::admission::{admit, Credential};
+use crate::connection::limits::SessionLimits;
+use crate::connection::server::ExperimentServer;
+use crate::connection::session::Session;
+use crate::connection::write_buffer::TrackedStream;
+use crate::policy::RunnerPolicy;
+
+// session is not polled while this many frames are waiting to be taken by the grpc stream, same as a
+// websocket connection whose write buffer is full
+const FRAME_BUFFER: usize = 16;
+// grpc runners are mostly behind NATs and proxies which drop the idle connections
+const TCP_KEEPALIVE: Duration = Duration::from_secs(30);
+
+/// Grpc transport of the runners. Each stream is served by a runner session through the websocket codec,
+/// frames of the runner are encoded the way a websocket client does and the frames of the session are
+/// decoded back. Runners get the same heartbeats, limits and close reasons over both transports.
+pub struct RunnerService {
+    pool: DBPool,
+    hash: Hash,
+    experiment_server: Addr<ExperimentServer>,
+    policy: RunnerPolicy,
+    limits: SessionLimits,
+    // sessions can not be moved between the tasks of the grpc server, they run on this arbiter instead
+    arbiter: Arbiter,
+}
+
+impl RunnerService {
+    pub fn new(pool: DBPool, hash: Hash, experiment_server: Addr<ExperimentServer>, policy: RunnerPolicy, limits: SessionLimits) -> Self {
+        RunnerService {
+            pool,
+            hash,
+            experiment_server,
+            policy,
+            limits,
+            arbiter: Arbiter::new(),
+        }
+    }
+
+    /// Serves the runners on the address in the background, TLS is terminated with the given configuration.
+    pub fn listen(self, address: SocketAddr, tls_config: Option<ServerConfig>) {
+        let mut server = Server::builder()
+            .tcp_keepalive(Some(TCP_KEEPALIVE));
+
+        if let Some(mut tls_config) = tls_config {
+            // grpc is only served over HTTP/2
+            tls_config.set_protocols(&[b"h2".to_vec()]);
+
+            let mut config = ServerTlsConfig::new();
+            config.rustls_server_config(tls_config);
+
+            server = server.tls_config(config)
+                .expect("Invalid TLS configuration for the grpc server");
+        }
+
+        let router = server.add_service(RunnerServer::new(self));
+
+        Arbiter::spawn(async move {
+            if let Err(e) = router.serve(address).await {
+                error!("serving runners over grpc is failed: {:?}", e);
+            }
+        });
+    }
+
+    /// Admits the runner the same way as the websocket, then starts its session on the arbiter.
+    async fn connect(&self, request: Request<Streaming<Frame>>) -> Result<Response<mpsc::Receiver<Result<Frame, Status>>>, Status> {
+        let token = request.metadata().get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(String::from);
+
+        let admission = admit(
+            &self.pool,
+            &self.hash,
+            &self.policy,
+            token.as_deref().map(Credential::Token),
+            self.policy.grpc_client_certificate(&request),
+            self.policy.grpc_remote_addr(&request),
+        )
+            .await
+            .map_err(status)?;
+
+        let (sender, receiver) = mpsc::channel(FRAME_BUFFER);
+        let frames = request.into_inner();
+
+        let experiment_server = self.experiment_server.clone();
+        let pool = self.pool.clone();
+        let hash = self.hash.clone();
+        let limits = self.limits.clone();
+
+        self.arbiter.exec_fn(move || {
+            let codec = Codec::new().max_size(limits.max_frame_size());
+
+            let session = Session::new(
+                experiment_server,
+                pool,
+                hash,
+                admission.runner.id,
+                admission.slot,
+                admission.token,
+                limits,
+            )
+                .refresh_token_on_join(admission.legacy_token);
+
+            Arbiter::spawn(serve(session, codec, frames, sender));
+        });
+
+        Ok(Response::new(receiver))
+    }
+}
+
+// implemented without async_trait, since its expansion refers to the std core crate which is shadowed by ours
+impl Runner for RunnerService {
+    type JoinStream = mpsc::Receiver<Result<Frame, Status>>;
+
+    fn join<'a, 'b>(&'a self, request: Request<Streaming<Frame>>) -> Pin<Box<dyn Future<Output=Result<Response<Self::JoinStream>, Status>> + Send + 'b>>
+        where 'a: 'b, Self: 'b {
+        Box::pin(self.connect(request))
+    }
+}
+
+/// Runs the session until either the session or the runner ends the stream.
+async fn serve(session: Session, codec: Codec, frames: Streaming<Frame>, mut sender: mpsc::Sender<Result<Frame, Status>>) {
+    let write_buffer = session.write_buffer().clone();
+
+    let mut encoder = Codec::new().client_mode();
+
+    let input = frames
+        .filter_map(|frame| async move {
+            match frame {
+                Ok(frame) => into_message(frame).map(Ok),
+                // stream of the runner is broken, session stops on the error
+                Err(e) => Some(Err(PayloadError::Io(io::Error::new(io::ErrorKind::ConnectionAborted, e.message().to_string()))))
+            }
+        })
+        .map(move |message| {
+            let mut bytes = BytesMut::new();
+
+            encoder.encode(message?, &mut bytes)
+                .map_err(|_| PayloadError::EncodingCorrupted)?;
+
+            Ok::<Bytes, PayloadError>(bytes.freeze())
+        });
+
+    let mut output = TrackedStream::new(Box::pin(WebsocketContext::with_codec(session, input, codec)), write_buffer);
+
+    // frames written by the session are only limited by its write buffer
+    let mut decoder = Codec::new().client_mode().max_size(usize::MAX);
+    let mut buffer = BytesMut::new();
+
+    while let Some(Ok(bytes)) = output.next().await {
+        buffer.extend_from_slice(&bytes);
+
+        loop {
+            let frame = match decoder.decode(&mut buffer) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(e) => {
+                    error!("decoding the frame of a grpc session is failed: {:?}", e);
+                    return;
+                }
+            };
+
+            if let Some(frame) = from_frame(frame) {
+                // runner has ended the stream
+                if sender.send(Ok(frame)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn into_message(frame: Frame) -> Option<Message> {
+    let message = match frame.kind? {
+        Kind::Text(text) => Message::Text(text),
+        Kind::Binary(bytes) => Message::Binary(Bytes::from(bytes)),
+        Kind::Ping(bytes) => Message::Ping(Bytes::from(bytes)),
+        Kind::Pong(bytes) => Message::Pong(Bytes::from(bytes)),
+        Kind::Close(close) => Message::Close(close_reason(close)),
+    };
+
+    Some(message)
+}
+
+fn from_frame(frame: WebsocketFrame) -> Option<Frame> {
+    let kind = match frame {
+        WebsocketFrame::Text(bytes) => Kind::Text(String::from_utf8_lossy(&bytes).into_owned()),
+        WebsocketFrame::Binary(bytes) => Kind::Binary(bytes.to_vec()),
+        WebsocketFrame::Ping(bytes) => Kind::Ping(bytes.to_vec()),
+        WebsocketFrame::Pong(bytes) => Kind::Pong(bytes.to_vec()),
+        WebsocketFrame::Close(reason) => Kind::Close(reason.map_or_else(Close::default, |reason| Close {
+            code: Into::<u16>::into(reason.code) as u32,
+            description: reason.description.unwrap_or_default(),
+        })),
+        // session does not fragment its messages
+        WebsocketFrame::Continuation(_) => return None
+    };
+
+    Some(Frame { kind: Some(kind) })
+}
+
+// code is zero if the close frame has no reason
+fn close_reason(close: Close) -> Option<CloseReason> {
+    if close.code == 0 {
+        return None;
+    }
+
+    Some(CloseReason {
+        code: CloseCode::from(close.code as u16),
+        description: Some(close.description).filter(|description| !description.is_empty()),
+    })
+}
+
+/// Runner is rejected with the grpc code closest to the http status of the error
+fn status(error: Box<dyn ErrorMessaging>) -> Status {
+    let error = error.value();
+
+    let code = match error.code {
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN => Code::PermissionDenied,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::TOO_MANY_REQUESTS => Code::ResourceExhausted,
+        StatusCode::UNPROCESSABLE_ENTITY => Code::FailedPrecondition,
+        _ => Code::Internal
+    };
+
+    Status::new(code, error.message)
+}
@@ -0,0 +1,49 @@
+use actix::Addr;
+use log::error;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use core::types::UserId;
+
+use crate::connection::listener;
+use crate::connection::messages::JobStatusChangedMessage;
+use crate::connection::server::ExperimentServer;
+use crate::models::job::{FailureReason, JobStatus};
+use crate::notifications::JobStatusNotification;
+
+// Notified by the job_status_notify trigger whenever the status of a job changes
+const CHANNEL: &str = "job_status";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JobStatusEvent {
+    user_id: UserId,
+    job_id: Uuid,
+    experiment_id: Uuid,
+    status: JobStatus,
+    failure_reason: Option<FailureReason>,
+}
+
+/// Forwards the job status changes to the server, which notifies the users about their jobs. Changes are
+/// emitted by the database, so the ones made by other processes or by hand are reflected as well.
+pub fn listen_job_events(database_url: String, experiment_server: Addr<ExperimentServer>) {
+    listener::listen("job_events", database_url, &[CHANNEL], || {}, move |_, payload| {
+        let event = match serde_json::from_str::<JobStatusEvent>(payload) {
+            Ok(event) => event,
+            Err(e) => {
+                error!("invalid job status event is received: {:?}", e);
+                return;
+            }
+        };
+
+        experiment_server.do_send(JobStatusChangedMessage {
+            user_id: event.user_id,
+            notification: JobStatusNotification {
+                job_id: event.job_id,
+                experiment_id: event.experiment_id,
+                status: event.status,
+                failure_reason: event.failure_reason,
+            },
+        });
+    });
+}
@@ -0,0 +1,100 @@
+use diesel::dsl::now;
+use diesel::pg::expression::extensions::IntervalDsl;
+use diesel::prelude::*;
+
+use core::db::DieselEnum;
+use core::schema::jobs;
+use core::types::{JobId, RunnerId};
+
+use crate::connection::schedule;
+use crate::models::job::{FailureReason, JobStatus, TransitionError};
+
+// Should be greater than the CLIENT_TIMEOUT, so that a runner reconnecting over a flaky link keeps its job
+const LEASE_SECONDS: i32 = 90;
+
+// Jobs reclaimed this many times are failed instead, so that a job crashing its runners is not retried forever
+const MAX_RECLAIMS: i32 = 3;
+
+/// Leases the job to its runner, should be called in the same transaction the job is moved into Running
+pub fn grant(job_id: JobId, conn: &PgConnection) -> QueryResult<()> {
+    diesel::update(jobs::table.find(job_id))
+        .set(jobs::lease_expires_at.eq((now + LEASE_SECONDS.seconds()).nullable()))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Extends the lease of the job if it is still running on the runner, returns whether the lease is renewed
+pub fn renew(job_id: JobId, runner_id: RunnerId, conn: &PgConnection) -> QueryResult<bool> {
+    diesel::update(
+        jobs::table
+            .find(job_id)
+            .filter(jobs::runner_id.eq(runner_id))
+            .filter(jobs::status.eq(JobStatus::Running.value()))
+    )
+        .set(jobs::lease_expires_at.eq((now + LEASE_SECONDS.seconds()).nullable()))
+        .execute(conn)
+        .map(|updated| updated > 0)
+}
+
+/// Whether the job is still leased to the runner, i.e. it is not reclaimed and dispatched to another runner
+pub fn is_holder(job_id: JobId, runner_id: RunnerId, conn: &PgConnection) -> QueryResult<bool> {
+    jobs::table
+        .find(job_id)
+        .select(jobs::runner_id)
+        .first::<Option<RunnerId>>(conn)
+        .map(|holder| holder == Some(runner_id))
+}
+
+/// Puts the running jobs whose leases have expired back into the queue, the ones reclaimed too many times are failed
+/// instead. Returns the ids of the jobs put back into the queue, they should be dispatched again by the server.
+pub fn reclaim_expired(conn: &PgConnection) -> Result<Vec<JobId>, TransitionError> {
+    let expired_jobs = jobs::table
+        .filter(jobs::status.eq(JobStatus::Running.value()))
+        .filter(jobs::lease_expires_at.lt(now))
+        .select(jobs::id)
+        .load::<JobId>(conn)?;
+
+    let mut reclaimed_jobs = Vec::new();
+
+    for job_id in expired_jobs {
+        let reclaimed = conn.transaction::<_, TransitionError, _>(|| {
+            // Lease may have been renewed or the job may have finished in the meantime, the row is locked so that
+            // the lease is not renewed until the job is put back into the queue or failed
+            let reclaim_count = jobs::table
+                .find(job_id)
+                .filter(jobs::status.eq(JobStatus::Running.value()))
+                .filter(jobs::lease_expires_at.lt(now))
+                .select(jobs::reclaim_count)
+                .for_update()
+                .first::<i32>(conn)
+                .optional()?;
+
+            let reclaim_count = match reclaim_count {
+                Some(reclaim_count) => reclaim_count,
+                None => return Ok(false)
+            };
+
+            if reclaim_count >= MAX_RECLAIMS {
+                JobStatus::transition_to(job_id, JobStatus::Failed)
+                    .failure_reason(FailureReason::LostRunner)
+                    .apply(conn)?;
+
+                return Ok(false);
+            }
+
+            JobStatus::transition_to(job_id, JobStatus::Pending)
+                .apply(conn)?;
+
+            schedule::remove(job_id, conn)?;
+
+            Ok(true)
+        })?;
+
+        if reclaimed {
+            reclaimed_jobs.push(job_id);
+        }
+    }
+
+    Ok(reclaimed_jobs)
+}
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use actix_web_actors::ws::Message;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+const SECOND: Duration = Duration::from_secs(1);
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Limits applied to the messages received from the runners. Counters of the violations are shared
+/// between all the sessions.
+#[derive(Clone)]
+pub struct SessionLimits {
+    max_frame_size: usize,
+    max_messages_per_second: u32,
+    max_bytes_per_minute: usize,
+    metrics: Arc<LimitMetrics>,
+}
+
+#[derive(Default)]
+struct LimitMetrics {
+    frame_size: AtomicU64,
+    message_rate: AtomicU64,
+    byte_rate: AtomicU64,
+    disconnects: AtomicU64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitMetricsSnapshot {
+    pub frame_size_violations: u64,
+    pub message_rate_violations: u64,
+    pub byte_rate_violations: u64,
+    pub disconnects: u64,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Violation {
+    FrameSize,
+    MessageRate,
+    ByteRate,
+}
+
+/// Messages and bytes received by a session within the current windows.
+pub struct RateWindow {
+    second_start: Instant,
+    messages: u32,
+    minute_start: Instant,
+    bytes: usize,
+    // violations are counted once per window, further messages in the window are dropped silently
+    message_rate_exceeded: bool,
+    byte_rate_exceeded: bool,
+}
+
+impl SessionLimits {
+    pub fn new(max_frame_size: usize, max_messages_per_second: u32, max_bytes_per_minute: usize) -> Self {
+        SessionLimits {
+            max_frame_size,
+            max_messages_per_second,
+            max_bytes_per_minute,
+            metrics: Arc::new(LimitMetrics::default()),
+        }
+    }
+
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// Accounts the message into the window. Returns `Ok(false)` if the message should be dropped
+    /// without counting a new violation, since the limit is already exceeded within the window.
+    pub fn check(&self, window: &mut RateWindow, msg: &Message) -> Result<bool, Violation> {
+        let now = Instant::now();
+
+        if now.duration_since(window.second_start) >= SECOND {
+            window.second_start = now;
+            window.messages = 0;
+            window.message_rate_exceeded = false;
+        }
+
+        if now.duration_since(window.minute_start) >= MINUTE {
+            window.minute_start = now;
+            window.bytes = 0;
+            window.byte_rate_exceeded = false;
+        }
+
+        window.messages += 1;
+        window.bytes += message_size(msg);
+
+        if window.messages > self.max_messages_per_second {
+            if window.message_rate_exceeded {
+                return Ok(false);
+            }
+
+            window.message_rate_exceeded = true;
+            return Err(Violation::MessageRate);
+        }
+
+        if window.bytes > self.max_bytes_per_minute {
+            if window.byte_rate_exceeded {
+                return Ok(false);
+            }
+
+            window.byte_rate_exceeded = true;
+            return Err(Violation::ByteRate);
+        }
+
+        Ok(true)
+    }
+
+    pub fn record(&self, violation: Violation) {
+        let counter = match violation {
+            Violation::FrameSize => &self.metrics.frame_size,
+            Violation::MessageRate => &self.metrics.message_rate,
+            Violation::ByteRate => &self.metrics.byte_rate,
+        };
+
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_disconnect(&self) {
+        self.metrics.disconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn metrics(&self) -> LimitMetricsSnapshot {
+        LimitMetricsSnapshot {
+            frame_size_violations: self.metrics.frame_size.load(Ordering::Relaxed),
+            message_rate_violations: self.metrics.message_rate.load(Ordering::Relaxed),
+            byte_rate_violations: self.metrics.byte_rate.load(Ordering::Relaxed),
+            disconnects: self.metrics.disconnects.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for RateWindow {
+    fn default() -> Self {
+        let now = Instant::now();
+
+        RateWindow {
+            second_start: now,
+            messages: 0,
+            minute_start: now,
+            bytes: 0,
+            message_rate_exceeded: false,
+            byte_rate_exceeded: false,
+        }
+    }
+}
+
+fn message_size(msg: &Message) -> usize {
+    match msg {
+        Message::Text(text) => text.len(),
+        Message::Binary(bytes) | Message::Ping(bytes) | Message::Pong(bytes) => bytes.len(),
+        _ => 0
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use log::{error, info};
+use postgres::{Client, NoTls};
+use postgres::fallible_iterator::FallibleIterator;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Listens the Postgres channels on a dedicated thread and passes the payloads of the notifications
+/// to `handle`. Connection is reestablished when it is lost, `connected` is called after every
+/// connection since the notifications sent in the meantime are missed.
+pub fn listen<C, H>(name: &str, database_url: String, channels: &'static [&'static str], connected: C, handle: H)
+    where C: Fn() + Send + 'static,
+          H: Fn(&str, &str) + Send + 'static {
+    let thread_name = name.to_string();
+
+    std::thread::Builder::new().name(name.to_string()).spawn(move || loop {
+        if let Err(e) = listen_channels(&database_url, channels, &connected, &handle) {
+            error!("listening {} is failed: {:?}", thread_name, e);
+        }
+
+        std::thread::sleep(RECONNECT_DELAY);
+    }).expect("Failed to initialize listener thread");
+}
+
+fn listen_channels<C, H>(database_url: &str, channels: &[&str], connected: &C, handle: &H) -> Result<(), postgres::Error>
+    where C: Fn(),
+          H: Fn(&str, &str) {
+    let mut client = Client::connect(database_url, NoTls)?;
+
+    for channel in channels {
+        client.batch_execute(format!("LISTEN {}", channel).as_str())?;
+    }
+
+    info!("listening channels {:?}", channels);
+    connected();
+
+    let mut notifications = client.notifications();
+    let mut iter = notifications.blocking_iter();
+
+    while let Some(notification) = iter.next()? {
+        handle(notification.channel(), notification.payload());
+    }
+
+    Ok(())
+}
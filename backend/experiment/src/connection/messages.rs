@@ -1,27 +1,266 @@
+use std::collections::{HashMap, HashSet};
+
 use actix::{Addr, Message};
+use futures::channel::{mpsc, oneshot};
 
-use core::types::ModelId;
+use core::types::{JobId, RunnerId, UserId};
+use shared::close::CloseKind;
+use shared::websocket_messages::{client, server};
 
+use crate::connection::backplane::Event;
 use crate::connection::session::Session;
+use crate::connection::user_session::UserSession;
+use crate::models::artifact::NewJobArtifact;
+use crate::notifications::{JobStatusNotification, Notification, SecurityNotification};
 
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct RunMessage {
-    pub job_id: ModelId,
+    pub job_id: JobId,
     pub code: String,
+    pub hooks: Vec<String>,
+    pub firmware: Option<client::Firmware>,
+    pub performance: Option<client::PerformanceSettings>,
+    pub network: Option<client::NetworkEmulation>,
+    pub sdr: Option<client::SdrSettings>,
 }
 
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct JoinServerMessage {
-    pub runner_id: ModelId,
+    pub runner_id: RunnerId,
+    pub addr: Addr<Session>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct LeaveServerMessage {
+    pub runner_id: RunnerId,
     pub addr: Addr<Session>,
 }
 
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct HeartbeatMessage {
+    pub runner_id: RunnerId,
+}
+
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct RunResultMessage {
-    pub runner_id: ModelId,
-    pub job_id: ModelId,
+    pub runner_id: RunnerId,
+    pub job_id: JobId,
     pub successful: bool,
-}
\ No newline at end of file
+    pub output: String,
+    pub truncated: bool,
+    pub streams: Vec<server::LogStream>,
+    pub flashed: Option<bool>,
+    pub message_id: Option<String>,
+    pub environment: Option<server::Environment>,
+    pub artifacts: Vec<NewJobArtifact>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct RunnerInfoMessage {
+    pub runner_id: RunnerId,
+    pub os: String,
+    pub arch: String,
+    pub client_version: String,
+    pub kernel: Option<String>,
+    pub cpu_model: Option<String>,
+    pub memory_bytes: Option<u64>,
+    pub peripherals: Vec<String>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct DiskPressureMessage {
+    pub runner_id: RunnerId,
+    pub under_pressure: bool,
+}
+
+/// Hottest thermal zone of the runner, none if the runner does not report any zone
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ThermalStateMessage {
+    pub runner_id: RunnerId,
+    pub temperature: Option<f64>,
+}
+
+/// Runners which are either connected or have sent a heartbeat recently.
+#[derive(Message)]
+#[rtype(result = "HashSet<RunnerId>")]
+pub struct FetchLiveRunnersMessage;
+
+#[derive(Message)]
+#[rtype(result = "HashSet<RunnerId>")]
+pub struct FetchConnectedRunnersMessage;
+
+/// Moves the pending jobs to the top of the queue, in the given order. Only the queue of this replica is reordered,
+/// the other replicas pick the new priorities up when they sync their pending runs.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct BumpPendingRunsMessage {
+    pub job_ids: Vec<JobId>,
+}
+
+/// Jobs put back into the queue since their leases have expired, they are dispatched again
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ReclaimedJobsMessage {
+    pub job_ids: Vec<JobId>,
+}
+
+/// Notifications of the user are also sent into the channel until its receiver is dropped
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SubscribeNotificationsMessage {
+    pub user_id: UserId,
+    pub sender: mpsc::UnboundedSender<Notification>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct JoinUserMessage {
+    pub user_id: UserId,
+    pub addr: Addr<UserSession>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct LeaveUserMessage {
+    pub user_id: UserId,
+    pub addr: Addr<UserSession>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct NotificationMessage {
+    pub notification: Notification,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetRunnerDisabledMessage {
+    pub runner_id: RunnerId,
+    pub disabled: bool,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct RemoveRunnerMessage {
+    pub runner_id: RunnerId,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct DisconnectMessage {
+    pub kind: CloseKind,
+}
+
+/// Disconnects all the runners of this server before it shuts down, so that they can connect to
+/// another server without waiting.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ShutdownServerMessage;
+
+/// Sends the latest client release to the given runners which opted in to auto update and
+/// run another version. All connected runners are checked if no runner is given.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct CheckClientUpdateMessage {
+    pub runner_ids: Option<Vec<RunnerId>>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ClientUpdateMessage {
+    pub update: client::ClientUpdate,
+}
+
+/// Forwards the command to the session of the runner, returns whether the runner is connected
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub struct RunnerCommandMessage {
+    pub runner_id: RunnerId,
+    pub command: client::RunnerCommand,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct CommandMessage {
+    pub command: client::RunnerCommand,
+}
+
+/// Forwards the log level to the session of the runner, returns whether the runner is connected
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub struct RunnerLogLevelMessage {
+    pub runner_id: RunnerId,
+    pub log_level: client::SetLogLevel,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct LogLevelMessage {
+    pub log_level: client::SetLogLevel,
+}
+
+/// Sends the code to the runner for validation, any connected and enabled runner is picked if the runner is
+/// not given. Returns the picked runner and the receiver of the diagnostics, if there is a runner.
+#[derive(Message)]
+#[rtype(result = "Option<(RunnerId, oneshot::Receiver<Vec<server::Diagnostic>>)>")]
+pub struct RunnerValidationMessage {
+    pub runner_id: Option<RunnerId>,
+    pub code: String,
+    pub hooks: Vec<String>,
+    pub has_firmware: bool,
+}
+
+/// Diagnostics are sent to `result` when the runner answers, it is dropped if the session stops before
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ValidationMessage {
+    pub code: String,
+    pub hooks: Vec<String>,
+    pub has_firmware: bool,
+    pub result: oneshot::Sender<Vec<server::Diagnostic>>,
+}
+
+/// Scores of the runners computed from their job statistics, lower is better. Idle runners are picked
+/// by their score when a job is dispatched.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct RunnerScoresMessage {
+    pub scores: HashMap<RunnerId, f64>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct BackplaneEventMessage {
+    pub event: Event,
+}
+
+/// Loads the pending jobs from the database into the queue, sent when the backplane is (re)connected
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SyncPendingRunsMessage;
+
+/// Status of a job is changed in the database, only the users connected to this replica are notified
+/// since every replica receives the change
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct JobStatusChangedMessage {
+    pub user_id: UserId,
+    pub notification: JobStatusNotification,
+}
+
+/// Security relevant entry of the audit log is recorded for the user, every replica receives it like the job status
+/// changes
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SecurityEventMessage {
+    pub user_id: UserId,
+    pub notification: SecurityNotification,
+}
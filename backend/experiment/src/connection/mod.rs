@@ -1,3 +1,20 @@
-mod messages;
+pub mod admission;
+pub mod aggregator;
+pub mod archiver;
+pub mod audit_events;
+pub mod backplane;
+pub mod cleaner;
+pub mod graphql_session;
+pub mod grpc;
+pub mod job_events;
+pub mod lease;
+pub mod limits;
+pub mod listener;
+pub mod messages;
+pub mod reaper;
+pub mod schedule;
+pub mod strategy;
 pub mod session;
-pub mod server;
\ No newline at end of file
+pub mod server;
+pub mod user_session;
+pub mod write_buffer;
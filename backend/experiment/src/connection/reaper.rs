@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use actix::prelude::*;
+use actix_web::web;
+use diesel::prelude::*;
+use log::{error, info};
+
+use core::db::DieselEnum;
+use core::schema::jobs;
+use core::types::{DBPool, JobId, RunnerId};
+
+use crate::connection::lease;
+use crate::connection::messages::{FetchLiveRunnersMessage, ReclaimedJobsMessage};
+use crate::connection::server::ExperimentServer;
+use crate::models::job::{FailureReason, JobStatus, TransitionError};
+
+// Should be greater than the CLIENT_TIMEOUT so that runners get a chance to reconnect after a restart
+const REAP_INTERVAL: Duration = Duration::from_secs(60);
+// Heartbeats are only kept in memory, runners which are not reconnected yet after a restart would look lost. Their
+// jobs are not failed until this long after the start, which leaves room for the backoff of the runners.
+const STARTUP_GRACE_PERIOD: Duration = Duration::from_secs(5 * 60);
+
+/// Periodically puts the jobs whose leases have expired back into the queue, and fails the jobs which are stuck in
+/// Running state without a lease since their runner is gone.
+pub struct Reaper {
+    pool: DBPool,
+    experiment_server: Addr<ExperimentServer>,
+    started_at: Instant,
+}
+
+impl Reaper {
+    pub fn new(pool: DBPool, experiment_server: Addr<ExperimentServer>) -> Self {
+        Reaper {
+            pool,
+            experiment_server,
+            started_at: Instant::now(),
+        }
+    }
+
+    fn reap(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let conn = self.pool.get().unwrap();
+        let experiment_server = self.experiment_server.clone();
+        let in_grace_period = self.started_at.elapsed() < STARTUP_GRACE_PERIOD;
+
+        async move {
+            let live_runners = match experiment_server.send(FetchLiveRunnersMessage).await {
+                Ok(live_runners) => live_runners,
+                Err(e) => {
+                    error!("fetching live runners is failed: {:?}", e);
+                    return;
+                }
+            };
+
+            let (lost_jobs, reclaimed_jobs) = match web::block(move || -> Result<_, TransitionError> {
+                let lost_jobs = if in_grace_period { Vec::new() } else { fail_lost_jobs(live_runners, &conn)? };
+
+                Ok((lost_jobs, lease::reclaim_expired(&conn)?))
+            }).await {
+                Ok(jobs) => jobs,
+                Err(e) => {
+                    error!("reaping jobs is failed: {:?}", e);
+                    return;
+                }
+            };
+
+            for job_id in &reclaimed_jobs {
+                info!("lease of job {} is expired, putting it back into the queue", job_id);
+            }
+
+            if !reclaimed_jobs.is_empty() {
+                experiment_server.do_send(ReclaimedJobsMessage { job_ids: reclaimed_jobs });
+            }
+
+            // Users are notified about the failed jobs by the job status events
+            for job_id in lost_jobs {
+                info!("job {} is failed since its runner is lost", job_id);
+            }
+        }
+            .into_actor(self)
+            .spawn(ctx);
+    }
+}
+
+/// Returns the ids of the failed jobs. Leased jobs are reclaimed once their leases expire instead.
+fn fail_lost_jobs(live_runners: HashSet<RunnerId>, conn: &PgConnection) -> Result<Vec<JobId>, TransitionError> {
+    let live_runners = live_runners.into_iter().collect::<Vec<RunnerId>>();
+
+    let lost_jobs = jobs::table
+        .filter(jobs::status.eq(JobStatus::Running.value()))
+        .filter(jobs::lease_expires_at.is_null())
+        .filter(jobs::runner_id.ne_all(live_runners))
+        .select(jobs::id)
+        .load::<JobId>(conn)
+        .map_err(TransitionError::DB)?;
+
+    let mut failed_jobs = Vec::new();
+
+    for lost_job in lost_jobs {
+        // Job may have finished in the meantime, which is rejected by the transition
+        match JobStatus::transition_to(lost_job, JobStatus::Failed)
+            .failure_reason(FailureReason::LostRunner)
+            .apply(conn) {
+            Ok(()) => failed_jobs.push(lost_job),
+            Err(TransitionError::Illegal { .. }) => {}
+            Err(e) => return Err(e)
+        }
+    }
+
+    Ok(failed_jobs)
+}
+
+impl Actor for Reaper {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(REAP_INTERVAL, |act, ctx| act.reap(ctx));
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use core::db::DieselEnum;
+use core::schema::{jobs, scheduled_runs};
+use core::types::{JobId, RunnerId};
+
+use crate::models::job::JobStatus;
+
+/// Scheduler state restored by the `ExperimentServer` after a restart
+pub struct ScheduleState {
+    // pending jobs in the order they are queued
+    pub pending_runs: Vec<JobId>,
+    // runner_id -> job dispatched to the runner, whose result has not arrived yet
+    pub assignments: HashMap<RunnerId, JobId>,
+}
+
+/// Records the job as waiting in the queue, keeping its position if it is already queued
+pub fn enqueue(job_id: JobId, conn: &PgConnection) -> QueryResult<()> {
+    diesel::insert_into(scheduled_runs::table)
+        .values(scheduled_runs::job_id.eq(job_id))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Records the job as dispatched to the runner
+pub fn assign(job_id: JobId, runner_id: RunnerId, conn: &PgConnection) -> QueryResult<()> {
+    diesel::insert_into(scheduled_runs::table)
+        .values((
+            scheduled_runs::job_id.eq(job_id),
+            scheduled_runs::runner_id.eq(runner_id),
+            scheduled_runs::assigned_at.eq(now),
+        ))
+        .on_conflict(scheduled_runs::job_id)
+        .do_update()
+        .set((
+            scheduled_runs::runner_id.eq(runner_id),
+            scheduled_runs::assigned_at.eq(now),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Forgets the job after it is finished or skipped
+pub fn remove(job_id: JobId, conn: &PgConnection) -> QueryResult<()> {
+    diesel::delete(scheduled_runs::table.find(job_id))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Loads the scheduler state. Records of the jobs which are finished in the meantime, e.g. cancelled
+/// or failed by the reaper, are dropped.
+pub fn load(conn: &PgConnection) -> QueryResult<ScheduleState> {
+    conn.transaction(|| {
+        let stale_jobs = scheduled_runs::table
+            .inner_join(jobs::table)
+            .filter(jobs::status.ne_all(vec![JobStatus::Pending.value(), JobStatus::Running.value()]))
+            .select(scheduled_runs::job_id)
+            .load::<JobId>(conn)?;
+
+        diesel::delete(scheduled_runs::table.filter(scheduled_runs::job_id.eq_any(stale_jobs)))
+            .execute(conn)?;
+
+        let runs = scheduled_runs::table
+            .order(scheduled_runs::position.asc())
+            .select((scheduled_runs::job_id, scheduled_runs::runner_id))
+            .load::<(JobId, Option<RunnerId>)>(conn)?;
+
+        let mut state = ScheduleState {
+            pending_runs: Vec::new(),
+            assignments: HashMap::new(),
+        };
+
+        for (job_id, runner_id) in runs {
+            match runner_id {
+                Some(runner_id) => { state.assignments.insert(runner_id, job_id); }
+                None => state.pending_runs.push(job_id)
+            }
+        }
+
+        Ok(state)
+    })
+}
@@ -1,96 +1,614 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
 
 use actix::prelude::*;
+use actix_web::error::BlockingError;
 use actix_web::web;
 use diesel::prelude::*;
+use futures::channel::{mpsc, oneshot};
 use log::{error, info};
 
 use core::db::DieselEnum;
-use core::schema::jobs;
-use core::types::{DBPool, ModelId};
+use core::schema::{client_releases, firmwares, jobs, runners};
+use core::types::{DBPool, JobId, RunnerId, UserId};
+use shared::close::CloseKind;
+use shared::websocket_messages::client;
 
-use crate::connection::messages::{JoinServerMessage, RunMessage, RunResultMessage};
-use crate::connection::session::Session;
-use crate::models::job::{Job, JobStatus};
+use crate::connection::backplane::{Backplane, Event};
+use crate::connection::lease;
+use crate::connection::messages::{BackplaneEventMessage, BumpPendingRunsMessage, CheckClientUpdateMessage, ClientUpdateMessage, CommandMessage, DisconnectMessage, DiskPressureMessage, FetchConnectedRunnersMessage, FetchLiveRunnersMessage, HeartbeatMessage, JobStatusChangedMessage, JoinServerMessage, JoinUserMessage, LeaveServerMessage, LeaveUserMessage, LogLevelMessage, NotificationMessage, ReclaimedJobsMessage, RemoveRunnerMessage, RunMessage, RunnerCommandMessage, RunnerInfoMessage, RunnerLogLevelMessage, RunnerScoresMessage, RunnerValidationMessage, RunResultMessage, SecurityEventMessage, SetRunnerDisabledMessage, ShutdownServerMessage, SubscribeNotificationsMessage, SyncPendingRunsMessage, ThermalStateMessage, ValidationMessage};
+use crate::connection::schedule;
+use crate::connection::session::{CLIENT_TIMEOUT, Session};
+use crate::connection::strategy::{self, Candidate, Placement, Strategies};
+use crate::connection::user_session::UserSession;
+use crate::criteria;
+use crate::idempotency;
+use crate::models::artifact;
+use crate::models::batch;
+use crate::models::code;
+use crate::models::environment;
+use crate::models::experiment;
+use crate::models::job::{FailureReason, FlashStatus, Job, JobStatus, NewJobStream, TransitionError};
+use crate::models::release::ClientRelease;
+use crate::models::runner::capability_labels;
+use crate::notifications::{Notification, QueueInfoNotification};
+use crate::queue;
+
+// Should be less than the CLIENT_TIMEOUT so that the runners of the other replicas are not considered lost
+const PRESENCE_INTERVAL: Duration = Duration::from_secs(10);
+
+// Leases are renewed by the heartbeats at most this often, should be well below the lease duration
+const LEASE_RENEW_INTERVAL: Duration = Duration::from_secs(30);
 
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct RunExperimentMessage {
-    pub job_id: ModelId
+    pub job_id: JobId
 }
 
 pub struct ExperimentServer {
     pool: DBPool,
-    pending_runs: Vec<ModelId>,
+    // jobs waiting for a runner, in the order they are dispatched
+    pending_runs: VecDeque<JobId>,
     // run_id -> (session, run_id)
-    runners: HashMap<ModelId, (Addr<Session>, Option<ModelId>)>,
+    runners: HashMap<RunnerId, (Addr<Session>, Option<JobId>)>,
+    // runner_id -> last time a heartbeat is received, kept after the runner disconnects
+    last_seen: HashMap<RunnerId, Instant>,
+    // user_id -> sessions of the user
+    users: HashMap<UserId, Vec<Addr<UserSession>>>,
+    // user_id -> channels subscribed to the notifications of the user, e.g. by the graphql subscriptions
+    subscribers: HashMap<UserId, Vec<mpsc::UnboundedSender<Notification>>>,
+    // runners which are disabled while they are connected, jobs are not dispatched to them
+    disabled: HashSet<RunnerId>,
+    // runners which are low on disk space, they are skipped like the disabled ones until they recover
+    disk_pressure: HashSet<RunnerId>,
+    // runner_id -> temperature of the hottest thermal zone of the runner in degrees Celsius, as last reported
+    temperatures: HashMap<RunnerId, f64>,
+    // job_id -> since when the job is held back by the thermal guard of its experiment
+    thermal_waits: HashMap<JobId, Instant>,
+    // runner_id -> score computed from the recent jobs of the runner, lower is better
+    scores: HashMap<RunnerId, f64>,
+    // picks the runners for the jobs, per the strategies of their experiments
+    strategies: Strategies,
+    // shares the jobs and the notifications with the other replicas, if there are any
+    backplane: Option<Backplane>,
+    // runner_id -> job dispatched to the runner before it disconnected, restored when it connects again
+    assignments: HashMap<RunnerId, JobId>,
+    // runner_id -> last time the lease of the runner's job is renewed
+    lease_renewals: HashMap<RunnerId, Instant>,
 }
 
 impl ExperimentServer {
-    pub fn new(pool: DBPool) -> Self {
+    pub fn new(pool: DBPool, backplane: Option<Backplane>) -> Self {
         ExperimentServer {
             pool,
-            pending_runs: Vec::new(),
+            pending_runs: VecDeque::new(),
             runners: HashMap::new(),
+            last_seen: HashMap::new(),
+            users: HashMap::new(),
+            subscribers: HashMap::new(),
+            disabled: HashSet::new(),
+            disk_pressure: HashSet::new(),
+            temperatures: HashMap::new(),
+            thermal_waits: HashMap::new(),
+            scores: HashMap::new(),
+            strategies: Strategies::new(),
+            backplane,
+            assignments: HashMap::new(),
+            lease_renewals: HashMap::new(),
+        }
+    }
+
+    /// Dispatches a waiting job to the runner if it is idle
+    fn run_pending(&mut self, runner_id: RunnerId, ctx: &mut <Self as Actor>::Context) {
+        let is_idle = match self.runners.get(&runner_id) {
+            Some((_, job_id)) => job_id.is_none(),
+            None => false
+        };
+
+        if is_idle {
+            self.run_next(ctx);
         }
     }
 
-    fn run(&mut self, job_id: ModelId, ctx: &mut <Self as Actor>::Context) {
-        let mut inactive_runner_id: Option<ModelId> = None;
+    /// Dispatches the job at the front of the queue, it keeps its place if it can not be dispatched yet
+    fn run_next(&mut self, ctx: &mut <Self as Actor>::Context) {
+        if let Some(job_id) = self.pending_runs.pop_front() {
+            self.try_run(job_id, true, ctx);
+        }
+    }
+
+    /// Dispatches the job to an idle runner picked by the strategy of its experiment, or pushes it into pending.
+    /// Returns whether there is an idle runner, the job is pushed into pending later if the runners become busy
+    /// while its strategy is loaded.
+    fn run(&mut self, job_id: JobId, ctx: &mut <Self as Actor>::Context) -> bool {
+        self.try_run(job_id, false, ctx)
+    }
+
+    /// Same as `run`, the job is put back to the front of the queue instead of the back if it is taken from there
+    fn try_run(&mut self, job_id: JobId, from_queue: bool, ctx: &mut <Self as Actor>::Context) -> bool {
+        let idle_runners = self.idle_runners();
+
+        if idle_runners.is_empty() {
+            self.queue(job_id, from_queue, ctx);
+            return false;
+        }
+
+        let conn = self.pool.get().unwrap();
+
+        async move {
+            web::block(move || strategy::load_candidates(job_id, idle_runners, &conn)).await
+        }
+            .into_actor(self)
+            .map(move |result, act, ctx| {
+                let (placement, candidates) = match result {
+                    Ok(loaded) => loaded,
+                    Err(e) => {
+                        error!("loading runner candidates of job {} is failed: {:?}", job_id, e);
+                        (Placement::default(), act.idle_runners().into_iter().map(Candidate::new).collect())
+                    }
+                };
+
+                // Runners may have become busy or disconnected in the meantime
+                let idle_runners = act.idle_runners();
+                let candidates = candidates.into_iter()
+                    .filter(|candidate| idle_runners.contains(&candidate.runner_id))
+                    .map(|candidate| Candidate { score: act.scores.get(&candidate.runner_id).copied(), ..candidate })
+                    .collect::<Vec<Candidate>>();
+
+                let candidates = act.thermal_guard(job_id, &placement, candidates, ctx);
+
+                match act.strategies.select(&placement, &candidates) {
+                    Some(runner_id) => {
+                        act.thermal_waits.remove(&job_id);
+                        act.dispatch(job_id, runner_id, ctx);
+                    }
+                    None => act.queue(job_id, from_queue, ctx)
+                }
+            })
+            .spawn(ctx);
 
-        for (id, v) in &self.runners {
-            if let None = v.1 {
-                inactive_runner_id = Some(*id);
-                break;
+        true
+    }
+
+    /// Leaves out the runners which are not cooler than the limit of the job's experiment, including the ones which
+    /// do not report their temperature. Job is held back until a runner cools down, the guard is lifted once the job
+    /// has waited longer than the thermal timeout of its experiment.
+    fn thermal_guard(&mut self, job_id: JobId, placement: &Placement, candidates: Vec<Candidate>, ctx: &mut <Self as Actor>::Context) -> Vec<Candidate> {
+        let max_temperature = match placement.max_temperature {
+            Some(max_temperature) if !candidates.is_empty() => max_temperature,
+            _ => return candidates
+        };
+
+        let waiting_since = match self.thermal_waits.get(&job_id) {
+            Some(waiting_since) => *waiting_since,
+            None => {
+                self.thermal_waits.insert(job_id, Instant::now());
+
+                // Runners may not report again if they are not cooling down, the job is tried once more after the timeout
+                ctx.run_later(placement.thermal_timeout, |act, ctx| {
+                    for runner_id in act.idle_runners() {
+                        act.run_pending(runner_id, ctx);
+                    }
+                });
+
+                Instant::now()
             }
+        };
+
+        if waiting_since.elapsed() >= placement.thermal_timeout {
+            info!("job {} waited for a runner below {} °C for {:?}, dispatching it regardless", job_id, max_temperature, placement.thermal_timeout);
+            return candidates;
         }
 
-        // If there is an inactive runner
-        if let Some(runner_id) = inactive_runner_id {
-            let runner = self.runners.get_mut(&runner_id).unwrap();
-            // mark this runner as active
-            runner.1 = Some(job_id);
+        let candidates = candidates.into_iter()
+            .filter(|candidate| self.temperatures.get(&candidate.runner_id).is_some_and(|temperature| *temperature < max_temperature))
+            .collect::<Vec<Candidate>>();
+
+        if candidates.is_empty() {
+            info!("job {} is held back until a runner is below {} °C", job_id, max_temperature);
+        }
 
-            let addr = runner.0.clone();
+        candidates
+    }
 
-            let conn = self.pool.get().unwrap();
-            async move {
-                let job = web::block(move || jobs::table.find(job_id).first::<Job>(&conn))
-                    .await
+    /// Runners which can take a job, i.e. the ones without a job which are not disabled or under disk pressure
+    fn idle_runners(&self) -> Vec<RunnerId> {
+        self.runners.iter()
+            .filter(|(id, v)| v.1.is_none() && !self.disabled.contains(id) && !self.disk_pressure.contains(id))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Pushes the job into pending until a runner becomes idle, to the front if it is already its turn
+    fn queue(&mut self, job_id: JobId, front: bool, ctx: &mut <Self as Actor>::Context) {
+        if front {
+            self.pending_runs.push_front(job_id);
+        } else {
+            self.pending_runs.push_back(job_id);
+        }
+
+        self.persist(move |conn| schedule::enqueue(job_id, conn), ctx);
+    }
+
+    /// Dispatches the job to the runner, which should be idle
+    fn dispatch(&mut self, job_id: JobId, runner_id: RunnerId, ctx: &mut <Self as Actor>::Context) {
+        let runner = self.runners.get_mut(&runner_id).unwrap();
+        // mark this runner as active
+        runner.1 = Some(job_id);
+
+        let addr = runner.0.clone();
+
+        let conn = self.pool.get().unwrap();
+        async move {
+            let (job, code, firmware, performance, network, sdr) = web::block(move || -> Result<_, Error> {
+                let job = jobs::table.find(job_id)
+                    .first::<Job>(&conn)
                     .map_err(|_| Error::DB(job_id))?;
 
-                // We have to decode the job.code in order to replace encoded html characters like < char
-                addr.send(RunMessage { job_id, code: core::decode_html(job.code.as_str()).unwrap() })
-                    .await
-                    .map_err(|_| Error::Send(job_id))?;
+                // Job may be cancelled while it is waiting in the queue
+                if job.status != JobStatus::Pending {
+                    return Err(Error::NotPending(job_id));
+                }
+
+                let performance = experiment::load_performance_settings(job.experiment_id, &conn)
+                    .map_err(|_| Error::DB(job_id))?;
 
-                Ok(job_id)
+                let network = experiment::load_network_emulation(job.experiment_id, &conn)
+                    .map_err(|_| Error::DB(job_id))?;
+
+                let sdr = experiment::load_sdr_settings(job.experiment_id, &conn)
+                    .map_err(|_| Error::DB(job_id))?;
+
+                let code = code::load(job.code_hash.as_str(), &conn)
+                    .map_err(|_| Error::DB(job_id))?;
+
+                let firmware = match job.firmware_id {
+                    Some(firmware_id) => Some(firmwares::table
+                        .find(firmware_id)
+                        .select((firmwares::name, firmwares::data))
+                        .first::<(String, Vec<u8>)>(&conn)
+                        .map_err(|_| Error::DB(job_id))?),
+                    None => None
+                };
+
+                // Moving the job into Running claims it, so that it is dispatched only once even if other
+                // replicas try to dispatch it as well. Job may be dispatched to a different runner than
+                // the requested one.
+                conn.transaction(|| {
+                    JobStatus::transition_to(job_id, JobStatus::Running)
+                        .runner_id(runner_id)
+                        .apply(&conn)?;
+
+                    schedule::assign(job_id, runner_id, &conn)?;
+                    lease::grant(job_id, &conn)?;
+
+                    Ok(())
+                })
+                    .map_err(|e| match e {
+                        TransitionError::Illegal { .. } => Error::NotPending(job_id),
+                        TransitionError::DB(_) => Error::DB(job_id)
+                    })?;
+
+                Ok((job, code, firmware, performance, network, sdr))
+            })
+                .await
+                .map_err(|e| match e {
+                    BlockingError::Error(e) => e,
+                    BlockingError::Canceled => Error::DB(job_id)
+                })?;
+
+            // We have to decode the code in order to replace encoded html characters like < char
+            let firmware = firmware.map(|(name, data)| client::Firmware { name, data: base64::encode(data) });
+
+            let run = RunMessage {
+                job_id,
+                code: core::decode_html(code.as_str()).unwrap(),
+                hooks: job.hooks,
+                firmware,
+                performance,
+                network,
+                sdr,
+            };
+
+            addr.send(run)
+                .await
+                .map_err(|_| Error::Send(job_id))?;
+
+            Ok(job_id)
+        }
+            .into_actor(self)
+            .then(move |result, act, ctx| {
+                if result.is_err() {
+                    act.release_runner(runner_id, ctx);
+                }
+
+                let conn = act.pool.get().unwrap();
+
+                async move {
+                    let job_id = match result {
+                        Ok(job_id) => return Some(job_id),
+                        Err(Error::Send(job_id)) | Err(Error::DB(job_id)) => job_id,
+                        Err(Error::NotPending(job_id)) => {
+                            info!("job {} is not pending anymore, skipping it", job_id);
+                            return None;
+                        }
+                    };
+
+                    if let Err(e) = web::block(move || JobStatus::transition_to(job_id, JobStatus::Failed)
+                        .runner_id(runner_id)
+                        .apply(&conn)
+                    )
+                        .await {
+                        error!("setting job status is failed: {:?}", e);
+                    }
+
+                    None
+                }
+                    .into_actor(act)
+                    .map(|dispatched, act, ctx| {
+                        if let Some(job_id) = dispatched {
+                            act.publish(Event::JobClaimed { job_id }, ctx);
+                            // Queue has moved, remaining jobs are one step closer to run
+                            act.notify_queue(ctx);
+                        }
+                    })
+            })
+            .spawn(ctx);
+    }
+
+    /// Applies a change of the scheduler state to the database, so that it can be restored after a restart
+    fn persist<F>(&self, change: F, ctx: &mut <Self as Actor>::Context)
+        where F: FnOnce(&PgConnection) -> QueryResult<()> + Send + 'static {
+        let conn = self.pool.get().unwrap();
+
+        async move {
+            if let Err(e) = web::block(move || change(&conn)).await {
+                error!("persisting scheduler state is failed: {:?}", e);
             }
+        }
+            .into_actor(self)
+            .spawn(ctx);
+    }
+
+    /// Publishes the event to the other replicas, if the backplane is enabled
+    fn publish(&self, event: Event, ctx: &mut <Self as Actor>::Context) {
+        if let Some(backplane) = &self.backplane {
+            backplane.clone()
+                .publish(event)
                 .into_actor(self)
-                .then(|result, act, _| {
-                    let conn = act.pool.get().unwrap();
-
-                    async move {
-                        let (status, job_id) = match result {
-                            Ok(job_id) => (JobStatus::Running, job_id),
-                            Err(Error::Send(job_id)) | Err(Error::DB(job_id)) => (JobStatus::Failed, job_id),
-                        };
-
-                        if let Err(e) = web::block(move || diesel::update(jobs::table.find(job_id))
-                            .set(jobs::status.eq(status.value()))
-                            .execute(&conn)
-                        )
-                            .await {
-                            error!("setting job status is failed: {:?}", e);
+                .spawn(ctx);
+        }
+    }
+
+    /// Notifies the users about the queue position and ETA of their pending jobs
+    fn notify_queue(&self, ctx: &mut <Self as Actor>::Context) {
+        let conn = self.pool.get().unwrap();
+
+        async move {
+            web::block(move || queue::queued_jobs(None, &conn)).await
+        }
+            .into_actor(self)
+            .map(|result, act, ctx| {
+                let queued = match result {
+                    Ok(queued) => queued,
+                    Err(e) => {
+                        error!("fetching queued jobs is failed: {:?}", e);
+                        return;
+                    }
+                };
+
+                for job in queued {
+                    act.notify(job.user_id, Notification::QueueInfo(QueueInfoNotification {
+                        job_id: job.info.job_id,
+                        experiment_id: job.experiment_id,
+                        position: job.info.position,
+                        eta_seconds: job.info.eta_seconds,
+                    }), ctx);
+                }
+            })
+            .spawn(ctx);
+    }
+
+    /// Notifies the user sessions connected to this replica and the other ones
+    fn notify(&mut self, user_id: UserId, notification: Notification, ctx: &mut <Self as Actor>::Context) {
+        self.notify_user(user_id, notification.clone());
+        self.publish(Event::Notify { user_id, notification }, ctx);
+    }
+
+    fn notify_user(&mut self, user_id: UserId, notification: Notification) {
+        if let Some(sessions) = self.users.get(&user_id) {
+            for addr in sessions {
+                addr.do_send(NotificationMessage { notification: notification.clone() });
+            }
+        }
+
+        if let Some(subscribers) = self.subscribers.get_mut(&user_id) {
+            // channels of the finished subscriptions are closed
+            subscribers.retain(|sender| sender.unbounded_send(notification.clone()).is_ok());
+
+            if subscribers.is_empty() {
+                self.subscribers.remove(&user_id);
+            }
+        }
+    }
+
+    /// Records the current time as the runner's last seen time
+    fn touch_runner(&self, runner_id: RunnerId, ctx: &mut <Self as Actor>::Context) {
+        let conn = self.pool.get().unwrap();
+
+        async move {
+            if let Err(e) = web::block(move || diesel::update(runners::table.find(runner_id))
+                .set(runners::last_seen_at.eq(diesel::dsl::now))
+                .execute(&conn)
+            )
+                .await {
+                error!("updating runner last seen time is failed: {:?}", e);
+            }
+        }
+            .into_actor(self)
+            .spawn(ctx);
+    }
+
+    /// Sends the latest release of their platform to the connected runners which opted in to auto update
+    fn check_client_update(&self, runner_ids: Vec<RunnerId>, ctx: &mut <Self as Actor>::Context) {
+        let conn = self.pool.get().unwrap();
+
+        async move {
+            web::block(move || -> Result<_, diesel::result::Error> {
+                let runners = runners::table
+                    .filter(runners::id.eq_any(runner_ids))
+                    .filter(runners::auto_update.eq(true))
+                    .select((runners::id, runners::os, runners::arch, runners::client_version))
+                    .load::<(RunnerId, Option<String>, Option<String>, Option<String>)>(&conn)?;
+
+                if runners.is_empty() {
+                    return Ok(Vec::new());
+                }
+
+                let releases = client_releases::table
+                    .filter(client_releases::sha256.is_not_null())
+                    .order(client_releases::created_at.desc())
+                    .load::<ClientRelease>(&conn)?;
+
+                Ok(runners
+                    .into_iter()
+                    .filter_map(|(runner_id, os, arch, client_version)| {
+                        // releases are ordered by creation, hence the first match is the latest one
+                        let release = releases.iter()
+                            .find(|r| Some(&r.os) == os.as_ref() && Some(&r.arch) == arch.as_ref())?;
+
+                        if Some(&release.version) == client_version.as_ref() {
+                            return None;
+                        }
+
+                        Some((runner_id, client::ClientUpdate {
+                            version: release.version.clone(),
+                            os: release.os.clone(),
+                            arch: release.arch.clone(),
+                            url: release.url.clone(),
+                            sha256: release.sha256.clone()?,
+                            signature: release.signature.clone(),
+                        }))
+                    })
+                    .collect::<Vec<(RunnerId, client::ClientUpdate)>>())
+            })
+                .await
+        }
+            .into_actor(self)
+            .then(|res, act, _| {
+                match res {
+                    Ok(updates) => {
+                        for (runner_id, update) in updates {
+                            if let Some((addr, _)) = act.runners.get(&runner_id) {
+                                info!("sending client update {} to runner {}", update.version, runner_id);
+                                addr.do_send(ClientUpdateMessage { update });
+                            }
                         }
                     }
-                        .into_actor(act)
-                })
-                .spawn(ctx)
+                    Err(e) => error!("checking client updates is failed: {:?}", e)
+                }
+
+                fut::ready(())
+            })
+            .spawn(ctx);
+    }
+
+    /// Restores the queue and the jobs dispatched to the runners from the database
+    fn restore(&self, ctx: &mut <Self as Actor>::Context) {
+        let conn = self.pool.get().unwrap();
+
+        async move {
+            web::block(move || schedule::load(&conn)).await
+        }
+            .into_actor(self)
+            .map(|result, act, ctx| {
+                let state = match result {
+                    Ok(state) => state,
+                    Err(e) => {
+                        error!("restoring scheduler state is failed: {:?}", e);
+                        return;
+                    }
+                };
+
+                info!("restored {} pending and {} dispatched jobs", state.pending_runs.len(), state.assignments.len());
+
+                // Jobs may have been queued while the state is being loaded, they are queued after the restored ones
+                let queued = std::mem::replace(&mut act.pending_runs, state.pending_runs.into_iter().collect());
+                for job_id in queued {
+                    if !act.pending_runs.contains(&job_id) {
+                        act.pending_runs.push_back(job_id);
+                    }
+                }
+
+                for (runner_id, job_id) in state.assignments {
+                    match act.runners.get_mut(&runner_id) {
+                        Some(runner) if runner.1.is_none() => {
+                            runner.1 = Some(job_id);
+                            act.verify_assignment(runner_id, job_id, ctx);
+                        }
+                        Some(_) => {}
+                        None => { act.assignments.insert(runner_id, job_id); }
+                    }
+                }
+
+                let idle_runners = act.runners.iter()
+                    .filter(|(_, (_, job_id))| job_id.is_none())
+                    .map(|(runner_id, _)| *runner_id)
+                    .collect::<Vec<RunnerId>>();
+
+                for runner_id in idle_runners {
+                    act.run_pending(runner_id, ctx);
+                }
+            })
+            .spawn(ctx);
+    }
+
+    /// Releases the runner if its restored job is not running anymore, e.g. it is failed by the reaper
+    /// while the runner was away
+    fn verify_assignment(&self, runner_id: RunnerId, job_id: JobId, ctx: &mut <Self as Actor>::Context) {
+        let conn = self.pool.get().unwrap();
+
+        async move {
+            web::block(move || jobs::table
+                .find(job_id)
+                .select((jobs::status, jobs::runner_id))
+                .first::<(JobStatus, Option<RunnerId>)>(&conn)
+            )
+                .await
         }
-        // Otherwise push it into pending
-        else {
-            self.pending_runs.push(job_id);
+            .into_actor(self)
+            .map(move |result, act, ctx| {
+                // Job may have been reclaimed and dispatched to another runner while the runner was away
+                let running = match result {
+                    Ok((status, holder)) => status == JobStatus::Running && holder == Some(runner_id),
+                    Err(e) => {
+                        error!("fetching job status is failed: {:?}", e);
+                        false
+                    }
+                };
+
+                let is_assigned = match act.runners.get(&runner_id) {
+                    Some((_, current)) => *current == Some(job_id),
+                    None => false
+                };
+
+                if !running && is_assigned {
+                    info!("job {} of runner {} is not running anymore, releasing the runner", job_id, runner_id);
+                    act.release_runner(runner_id, ctx);
+                }
+            })
+            .spawn(ctx);
+    }
+
+    /// Marks the runner as inactive and dispatches the next pending job, if there is any.
+    fn release_runner(&mut self, runner_id: RunnerId, ctx: &mut <Self as Actor>::Context) {
+        if let Some(runner) = self.runners.get_mut(&runner_id) {
+            if let Some(job_id) = runner.1.take() {
+                self.persist(move |conn| schedule::remove(job_id, conn), ctx);
+            }
+
+            self.run_next(ctx);
         }
     }
 }
@@ -98,7 +616,17 @@ impl ExperimentServer {
 impl Actor for ExperimentServer {
     type Context = Context<Self>;
 
-    fn started(&mut self, _: &mut Self::Context) {}
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.restore(ctx);
+
+        if self.backplane.is_some() {
+            // Lets the other replicas know that the runners connected to this one are alive
+            ctx.run_interval(PRESENCE_INTERVAL, |act, ctx| {
+                let runner_ids = act.runners.keys().copied().collect();
+                act.publish(Event::Presence { runner_ids }, ctx);
+            });
+        }
+    }
 
     fn stopped(&mut self, _: &mut Self::Context) {}
 }
@@ -109,15 +637,473 @@ impl Handler<RunExperimentMessage> for ExperimentServer {
     fn handle(&mut self, msg: RunExperimentMessage, ctx: &mut Self::Context) {
         info!("Job with id {} received ", msg.job_id);
 
-        self.run(msg.job_id, ctx);
+        if !self.run(msg.job_id, ctx) {
+            // One of the other replicas may have an idle runner
+            self.publish(Event::JobPending { job_id: msg.job_id }, ctx);
+        }
+    }
+}
+
+impl Handler<BackplaneEventMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: BackplaneEventMessage, ctx: &mut Self::Context) {
+        match msg.event {
+            Event::JobPending { job_id } => {
+                if !self.pending_runs.contains(&job_id) {
+                    self.run(job_id, ctx);
+                }
+            }
+            Event::JobClaimed { job_id } => self.pending_runs.retain(|id| *id != job_id),
+            Event::Notify { user_id, notification } => self.notify_user(user_id, notification),
+            Event::Presence { runner_ids } => {
+                // Runners of the other replicas are considered alive like the ones sending heartbeats
+                let now = Instant::now();
+
+                for runner_id in runner_ids {
+                    self.last_seen.insert(runner_id, now);
+                }
+            }
+        }
+    }
+}
+
+impl Handler<SyncPendingRunsMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, _: SyncPendingRunsMessage, ctx: &mut Self::Context) {
+        let conn = self.pool.get().unwrap();
+
+        // Jobs with the highest priorities, then the oldest ones are dispatched first, as the queue positions are
+        // reported by `queue::queued_jobs`
+        async move {
+            web::block(move || jobs::table
+                .filter(jobs::status.eq(JobStatus::Pending.value()))
+                .order((jobs::queue_priority.desc(), jobs::created_at.asc(), jobs::id.asc()))
+                .select(jobs::id)
+                .load::<JobId>(&conn)
+            )
+                .await
+        }
+            .into_actor(self)
+            .map(|result, act, ctx| {
+                let job_ids = match result {
+                    Ok(job_ids) => job_ids,
+                    Err(e) => {
+                        error!("fetching pending jobs is failed: {:?}", e);
+                        return;
+                    }
+                };
+
+                for job_id in job_ids {
+                    if !act.pending_runs.contains(&job_id) {
+                        act.pending_runs.push_back(job_id);
+                    }
+                }
+
+                let idle_runners = act.runners.iter()
+                    .filter(|(_, (_, job_id))| job_id.is_none())
+                    .map(|(runner_id, _)| *runner_id)
+                    .collect::<Vec<RunnerId>>();
+
+                for runner_id in idle_runners {
+                    act.run_pending(runner_id, ctx);
+                }
+            })
+            .spawn(ctx);
+    }
+}
+
+impl Handler<BumpPendingRunsMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: BumpPendingRunsMessage, _: &mut Self::Context) {
+        // Bumped jobs are moved to the front in the order they are given
+        for job_id in msg.job_ids.into_iter().rev() {
+            if let Some(index) = self.pending_runs.iter().position(|pending| *pending == job_id) {
+                self.pending_runs.remove(index);
+                self.pending_runs.push_front(job_id);
+            }
+        }
     }
 }
 
 impl Handler<JoinServerMessage> for ExperimentServer {
     type Result = ();
 
-    fn handle(&mut self, msg: JoinServerMessage, _: &mut Self::Context) {
-        self.runners.insert(msg.runner_id, (msg.addr, None));
+    fn handle(&mut self, msg: JoinServerMessage, ctx: &mut Self::Context) {
+        let runner_id = msg.runner_id;
+
+        self.last_seen.insert(runner_id, Instant::now());
+
+        // Runner may still be running the job dispatched before it disconnected, or before a restart
+        let job_id = self.runners.get(&runner_id)
+            .and_then(|(_, job_id)| *job_id)
+            .or_else(|| self.assignments.remove(&runner_id));
+
+        // Previous session is replaced, it may still be open if another client is connected with the same credentials
+        if let Some((addr, _)) = self.runners.get(&runner_id) {
+            if *addr != msg.addr {
+                info!("runner {} connected again, disconnecting its previous session", runner_id);
+                addr.do_send(DisconnectMessage { kind: CloseKind::DuplicateConnection });
+            }
+        }
+
+        self.runners.insert(runner_id, (msg.addr, job_id));
+        self.touch_runner(runner_id, ctx);
+
+        match job_id {
+            Some(job_id) => self.verify_assignment(runner_id, job_id, ctx),
+            None => self.run_pending(runner_id, ctx)
+        }
+    }
+}
+
+impl Handler<LeaveServerMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: LeaveServerMessage, ctx: &mut Self::Context) {
+        // Runner may have already reconnected with a new session
+        let is_current_session = match self.runners.get(&msg.runner_id) {
+            Some((addr, _)) => *addr == msg.addr,
+            None => false
+        };
+
+        if is_current_session {
+            info!("runner {} left the server", msg.runner_id);
+
+            if let Some((_, Some(job_id))) = self.runners.remove(&msg.runner_id) {
+                self.assignments.insert(msg.runner_id, job_id);
+            }
+
+            self.disk_pressure.remove(&msg.runner_id);
+            self.temperatures.remove(&msg.runner_id);
+            self.touch_runner(msg.runner_id, ctx);
+        }
+    }
+}
+
+impl Handler<HeartbeatMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: HeartbeatMessage, ctx: &mut Self::Context) {
+        let runner_id = msg.runner_id;
+        let now = Instant::now();
+
+        self.last_seen.insert(runner_id, now);
+
+        // Leases are piggybacked on the heartbeats, the job is kept by the runner as long as they arrive
+        let job_id = match self.runners.get(&runner_id) {
+            Some((_, Some(job_id))) => *job_id,
+            _ => return
+        };
+
+        let renewal_due = self.lease_renewals.get(&runner_id)
+            .is_none_or(|renewed_at| now.duration_since(*renewed_at) >= LEASE_RENEW_INTERVAL);
+
+        if !renewal_due {
+            return;
+        }
+
+        self.lease_renewals.insert(runner_id, now);
+
+        let conn = self.pool.get().unwrap();
+
+        async move {
+            match web::block(move || lease::renew(job_id, runner_id, &conn)).await {
+                Ok(true) => {}
+                Ok(false) => info!("lease of job {} is not held by runner {} anymore", job_id, runner_id),
+                Err(e) => error!("renewing lease of job {} is failed: {:?}", job_id, e)
+            }
+        }
+            .into_actor(self)
+            .spawn(ctx);
+    }
+}
+
+impl Handler<RunnerInfoMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: RunnerInfoMessage, ctx: &mut Self::Context) {
+        let conn = self.pool.get().unwrap();
+
+        let runner_id = msg.runner_id;
+
+        async move {
+            let capabilities = capability_labels(&msg.os, &msg.arch, &msg.peripherals);
+
+            if let Err(e) = web::block(move || diesel::update(runners::table.find(msg.runner_id))
+                .set((
+                    runners::os.eq(msg.os),
+                    runners::arch.eq(msg.arch),
+                    runners::client_version.eq(msg.client_version),
+                    runners::kernel.eq(msg.kernel),
+                    runners::cpu_model.eq(msg.cpu_model),
+                    runners::memory_bytes.eq(msg.memory_bytes.map(|bytes| bytes as i64)),
+                    runners::peripherals.eq(msg.peripherals),
+                    runners::capabilities.eq(capabilities),
+                ))
+                .execute(&conn)
+            )
+                .await {
+                error!("updating runner info is failed: {:?}", e);
+            }
+        }
+            .into_actor(self)
+            // platform and version of the runner are known now
+            .then(move |_, act, ctx| {
+                act.check_client_update(vec![runner_id], ctx);
+                fut::ready(())
+            })
+            .spawn(ctx);
+    }
+}
+
+impl Handler<FetchLiveRunnersMessage> for ExperimentServer {
+    type Result = MessageResult<FetchLiveRunnersMessage>;
+
+    fn handle(&mut self, _: FetchLiveRunnersMessage, _: &mut Self::Context) -> Self::Result {
+        let now = Instant::now();
+
+        let live = self.last_seen
+            .iter()
+            .filter(|(_, last_seen)| now.duration_since(**last_seen) <= CLIENT_TIMEOUT)
+            .map(|(runner_id, _)| *runner_id)
+            .chain(self.runners.keys().copied())
+            .collect::<HashSet<RunnerId>>();
+
+        MessageResult(live)
+    }
+}
+
+impl Handler<FetchConnectedRunnersMessage> for ExperimentServer {
+    type Result = MessageResult<FetchConnectedRunnersMessage>;
+
+    fn handle(&mut self, _: FetchConnectedRunnersMessage, _: &mut Self::Context) -> Self::Result {
+        MessageResult(self.runners.keys().copied().collect())
+    }
+}
+
+impl Handler<SetRunnerDisabledMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetRunnerDisabledMessage, ctx: &mut Self::Context) {
+        if msg.disabled {
+            self.disabled.insert(msg.runner_id);
+            return;
+        }
+
+        self.disabled.remove(&msg.runner_id);
+
+        // Enabled runner may pick up a waiting job
+        self.run_pending(msg.runner_id, ctx);
+    }
+}
+
+impl Handler<DiskPressureMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: DiskPressureMessage, ctx: &mut Self::Context) {
+        if msg.under_pressure {
+            self.disk_pressure.insert(msg.runner_id);
+            return;
+        }
+
+        if self.disk_pressure.remove(&msg.runner_id) {
+            // Recovered runner may pick up a waiting job
+            self.run_pending(msg.runner_id, ctx);
+        }
+    }
+}
+
+impl Handler<ThermalStateMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: ThermalStateMessage, ctx: &mut Self::Context) {
+        match msg.temperature {
+            Some(temperature) => self.temperatures.insert(msg.runner_id, temperature),
+            None => self.temperatures.remove(&msg.runner_id)
+        };
+
+        // Runner may have cooled down enough for a job held back by the thermal guard
+        self.run_pending(msg.runner_id, ctx);
+    }
+}
+
+impl Handler<RemoveRunnerMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: RemoveRunnerMessage, _: &mut Self::Context) {
+        self.disabled.remove(&msg.runner_id);
+        self.disk_pressure.remove(&msg.runner_id);
+        self.temperatures.remove(&msg.runner_id);
+        self.last_seen.remove(&msg.runner_id);
+        self.assignments.remove(&msg.runner_id);
+
+        if let Some((addr, _)) = self.runners.remove(&msg.runner_id) {
+            info!("runner {} is removed, disconnecting", msg.runner_id);
+            addr.do_send(DisconnectMessage { kind: CloseKind::KickedByAdmin });
+        }
+    }
+}
+
+impl Handler<ShutdownServerMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, _: ShutdownServerMessage, _: &mut Self::Context) {
+        info!("server is shutting down, disconnecting {} runners", self.runners.len());
+
+        for (addr, _) in self.runners.values() {
+            addr.do_send(DisconnectMessage { kind: CloseKind::ServerShutdown });
+        }
+    }
+}
+
+impl Handler<RunnerScoresMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: RunnerScoresMessage, _: &mut Self::Context) {
+        self.scores = msg.scores;
+    }
+}
+
+impl Handler<CheckClientUpdateMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: CheckClientUpdateMessage, ctx: &mut Self::Context) {
+        let runner_ids = match msg.runner_ids {
+            Some(runner_ids) => runner_ids,
+            None => self.runners.keys().copied().collect()
+        };
+
+        self.check_client_update(runner_ids, ctx);
+    }
+}
+
+impl Handler<RunnerCommandMessage> for ExperimentServer {
+    type Result = bool;
+
+    fn handle(&mut self, msg: RunnerCommandMessage, _: &mut Self::Context) -> Self::Result {
+        match self.runners.get(&msg.runner_id) {
+            Some((addr, _)) => {
+                addr.do_send(CommandMessage { command: msg.command });
+                true
+            }
+            None => false
+        }
+    }
+}
+
+impl Handler<RunnerLogLevelMessage> for ExperimentServer {
+    type Result = bool;
+
+    fn handle(&mut self, msg: RunnerLogLevelMessage, _: &mut Self::Context) -> Self::Result {
+        match self.runners.get(&msg.runner_id) {
+            Some((addr, _)) => {
+                addr.do_send(LogLevelMessage { log_level: msg.log_level });
+                true
+            }
+            None => false
+        }
+    }
+}
+
+impl Handler<RunnerValidationMessage> for ExperimentServer {
+    type Result = MessageResult<RunnerValidationMessage>;
+
+    fn handle(&mut self, msg: RunnerValidationMessage, _: &mut Self::Context) -> Self::Result {
+        let runner_id = match msg.runner_id {
+            Some(runner_id) => Some(runner_id),
+            // idle runners are preferred, validation does not wait for the current job anyway
+            None => self.runners.iter()
+                .filter(|(id, _)| !self.disabled.contains(id))
+                .min_by_key(|(id, (_, job_id))| (job_id.is_some(), **id))
+                .map(|(id, _)| *id)
+        };
+
+        let (runner_id, addr) = match runner_id.and_then(|id| self.runners.get(&id).map(|(addr, _)| (id, addr))) {
+            Some(runner) => runner,
+            None => return MessageResult(None)
+        };
+
+        let (tx, rx) = oneshot::channel();
+
+        addr.do_send(ValidationMessage {
+            code: msg.code,
+            hooks: msg.hooks,
+            has_firmware: msg.has_firmware,
+            result: tx,
+        });
+
+        MessageResult(Some((runner_id, rx)))
+    }
+}
+
+impl Handler<JoinUserMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: JoinUserMessage, _: &mut Self::Context) {
+        self.users.entry(msg.user_id)
+            .or_insert_with(Vec::new)
+            .push(msg.addr);
+    }
+}
+
+impl Handler<SubscribeNotificationsMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SubscribeNotificationsMessage, _: &mut Self::Context) {
+        let subscribers = self.subscribers.entry(msg.user_id)
+            .or_default();
+
+        subscribers.retain(|sender| !sender.is_closed());
+        subscribers.push(msg.sender);
+    }
+}
+
+impl Handler<LeaveUserMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: LeaveUserMessage, _: &mut Self::Context) {
+        if let Some(sessions) = self.users.get_mut(&msg.user_id) {
+            sessions.retain(|addr| *addr != msg.addr);
+
+            if sessions.is_empty() {
+                self.users.remove(&msg.user_id);
+            }
+        }
+    }
+}
+
+impl Handler<JobStatusChangedMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: JobStatusChangedMessage, _: &mut Self::Context) {
+        self.notify_user(msg.user_id, Notification::JobStatus(msg.notification));
+    }
+}
+
+impl Handler<SecurityEventMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SecurityEventMessage, _: &mut Self::Context) {
+        self.notify_user(msg.user_id, Notification::Security(msg.notification));
+    }
+}
+
+impl Handler<ReclaimedJobsMessage> for ExperimentServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReclaimedJobsMessage, ctx: &mut Self::Context) {
+        for job_id in msg.job_ids {
+            // Runner is not given its previous job back if it connects again
+            self.assignments.retain(|_, assigned| *assigned != job_id);
+
+            if !self.run(job_id, ctx) {
+                // One of the other replicas may have an idle runner
+                self.publish(Event::JobPending { job_id }, ctx);
+            }
+        }
     }
 }
 
@@ -134,11 +1120,7 @@ impl Handler<RunResultMessage> for ExperimentServer {
             };
 
             if correct_run {
-                runner.1 = None;
-
-                if let Some(job_id) = self.pending_runs.pop() {
-                    self.run(job_id, ctx);
-                }
+                self.release_runner(msg.runner_id, ctx);
             }
         }
 
@@ -148,22 +1130,70 @@ impl Handler<RunResultMessage> for ExperimentServer {
         };
 
         let conn = self.pool.get().unwrap();
+        let runner_id = msg.runner_id;
+        let output = msg.output.clone();
 
         async move {
-            if let Err(e) = web::block(move ||
-                diesel::update(jobs::table.find(msg.job_id))
-                    .set(jobs::status.eq(status.value()))
-                    .execute(&conn)
-            )
-                .await {
-                error!("updating jobs status is failed: {:?}", e);
+            let result = web::block(move || conn.transaction::<_, TransitionError, _>(|| {
+                let job_id = msg.job_id;
+
+                // Result of a reclaimed job is discarded, the job is either queued or running on another runner
+                if !lease::is_holder(job_id, runner_id, &conn)? {
+                    info!("result of job {} from runner {} is discarded since it does not hold the job", job_id, runner_id);
+                    return Ok(0);
+                }
+
+                // Results resent after a reconnect are acknowledged, but they are not applied again
+                if let Some(message_id) = &msg.message_id {
+                    if !idempotency::record_result(job_id, message_id, &conn)? {
+                        info!("result {} of job {} is already processed, skipping it", message_id, job_id);
+                        return Ok(0);
+                    }
+                }
+
+                let streams = msg.streams.into_iter()
+                    .map(|s| NewJobStream { job_id, name: s.name, output: s.output, truncated: s.truncated })
+                    .collect();
+
+                let transition = JobStatus::transition_to(job_id, status)
+                    .output(msg.output, msg.truncated)
+                    .streams(streams);
+
+                let transition = match msg.flashed {
+                    Some(true) => transition.flash_status(FlashStatus::Flashed),
+                    Some(false) => transition.flash_status(FlashStatus::Failed).failure_reason(FailureReason::FlashFailed),
+                    None => transition
+                };
+
+                transition.apply(&conn)?;
+
+                if let Some(environment) = &msg.environment {
+                    environment::record(job_id, environment, &conn)?;
+                }
+
+                artifact::record(job_id, msg.artifacts, &conn)?;
+
+                criteria::evaluate_job(job_id, &output, &conn)?;
+
+                // siblings of the job in its batch are cancelled as the results arrive if the batch asks so
+                batch::abort_if_triggered(job_id, &output, &conn)
+                    .map_err(TransitionError::DB)
+            }))
+                .await;
+
+            match result {
+                Ok(cancelled) if cancelled > 0 => info!("{} pending jobs are cancelled by the abort policy of their batch", cancelled),
+                Ok(_) => {}
+                Err(e) => error!("updating jobs status is failed: {:?}", e)
             }
         }.into_actor(self)
             .spawn(ctx);
     }
 }
 
+#[derive(Debug)]
 pub enum Error {
-    DB(ModelId),
-    Send(ModelId),
+    DB(JobId),
+    Send(JobId),
+    NotPending(JobId),
 }
\ No newline at end of file
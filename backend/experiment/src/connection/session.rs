@@ -1,51 +1,396 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
 use actix::prelude::*;
-use actix_web_actors::ws::{Message, ProtocolError, WebsocketContext};
-use log::{error, info};
+use actix_web::web;
+use actix_web_actors::ws::{CloseCode, CloseReason, Message, ProtocolError, WebsocketContext};
+use chrono::Utc;
+use diesel::dsl::now;
+use diesel::pg::expression::extensions::IntervalDsl;
+use diesel::prelude::*;
+use futures::channel::oneshot;
+use log::{error, info, warn};
+use serde::Serialize;
 
-use core::types::ModelId;
+use core::schema::runner_client_logs;
+use core::types::{DBPool, JobId, ModelId, RunnerId};
+use core::utils::Hash;
+use shared::close::CloseKind;
 use shared::SocketErrorKind;
 use shared::websocket_messages::{client, server};
 
-use crate::connection::messages::{JoinServerMessage, RunMessage, RunResultMessage};
+use crate::connection::admission::ConnectionSlot;
+use crate::connection::limits::{RateWindow, SessionLimits, Violation};
+use crate::connection::messages::{ClientUpdateMessage, CommandMessage, DisconnectMessage, DiskPressureMessage, HeartbeatMessage, JoinServerMessage, LeaveServerMessage, LogLevelMessage, RunMessage, RunnerInfoMessage, RunResultMessage, ThermalStateMessage, ValidationMessage};
 use crate::connection::server::ExperimentServer;
+use crate::connection::write_buffer::WriteBuffer;
+use crate::models::artifact::NewJobArtifact;
+use crate::models::command::{CommandStatus, RunnerCommand};
+use crate::models::runner::RunnerToken;
+
+// shipping window of the client logs is capped, so is the size of each shipment
+pub const MAX_LOG_SHIPPING: Duration = Duration::from_secs(60 * 60);
+const MAX_LOG_LINES: usize = 1000;
+const MAX_LOG_LINE_LENGTH: usize = 4096;
+// shipped logs are kept for this many days
+const LOG_RETENTION_DAYS: i32 = 7;
+// named output streams of a job besides stdout and stderr, e.g. the serial console
+const MAX_JOB_STREAMS: usize = 8;
+const MAX_JOB_STREAM_NAME_LENGTH: usize = 64;
+// identifies a run result across its resends
+const MAX_MESSAGE_ID_LENGTH: usize = 64;
+// files collected by the runner during a job, e.g. the packet captures
+const MAX_JOB_ARTIFACTS: usize = 16;
+const MAX_JOB_ARTIFACT_NAME_LENGTH: usize = 64;
+const MAX_JOB_ARTIFACT_METADATA: usize = 16;
+const MAX_JOB_ARTIFACT_METADATA_LENGTH: usize = 256;
+// total size of the decoded artifacts of a job
+const MAX_JOB_ARTIFACTS_SIZE: usize = 8 * 1024 * 1024;
+// environment of the runner reported along with a run result
+const MAX_ENVIRONMENT_ENTRIES: usize = 256;
+const MAX_ENVIRONMENT_FIELD_LENGTH: usize = 255;
+// hardware inventory reported by the runner
+const MAX_INVENTORY_FIELD_LENGTH: usize = 255;
+const MAX_PERIPHERALS: usize = 32;
+const MAX_PERIPHERAL_NAME_LENGTH: usize = 64;
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+// runner is disconnected if this many bytes are waiting to be sent to it, runs may carry firmware images
+const MAX_WRITE_BUFFER: usize = 64 * 1024 * 1024;
+// runner is disconnected after this many limit violations, unless it behaves for a while in between
+const MAX_LIMIT_VIOLATIONS: u32 = 3;
+const LIMIT_VIOLATION_RESET: Duration = Duration::from_secs(10 * 60);
+// tokens expiring in this many seconds are refreshed over the connection
+const TOKEN_REFRESH_BEFORE: i64 = 60 * 60 * 24 * 30;
 
 pub struct Session {
     experiment_server: Addr<ExperimentServer>,
-    runner_id: ModelId,
+    pool: DBPool,
+    hash: Hash,
+    runner_id: RunnerId,
+    hb: Instant,
+    // connection reserved for the credential the runner is connected with, released once the session is dropped
+    _slot: ConnectionSlot,
+    // hash of the access key and the expire time of the token, if the runner is connected with a token
+    token: Option<(String, i64)>,
+    refreshing_token: bool,
+    // token is refreshed as soon as the runner joins instead of close to its expire time
+    refresh_token_on_join: bool,
+    // commands sent over this session which are not answered yet
+    commands: HashSet<ModelId>,
+    // client logs are accepted until this time, only if they are requested
+    log_shipping_until: Option<Instant>,
+    // validations sent over this session which are not answered yet
+    validations: HashMap<u64, oneshot::Sender<Vec<server::Diagnostic>>>,
+    next_validation_id: u64,
+    write_buffer: WriteBuffer,
+    limits: SessionLimits,
+    rate_window: RateWindow,
+    // number of the limit violations and the time of the last one
+    violations: (u32, Instant),
 }
 
 impl Session {
-    pub fn new(experiment_server: Addr<ExperimentServer>, runner_id: ModelId) -> Self {
+    pub fn new(
+        experiment_server: Addr<ExperimentServer>,
+        pool: DBPool,
+        hash: Hash,
+        runner_id: RunnerId,
+        slot: ConnectionSlot,
+        token: Option<(String, i64)>,
+        limits: SessionLimits,
+    ) -> Self {
         Session {
             experiment_server,
+            pool,
+            hash,
             runner_id,
+            hb: Instant::now(),
+            _slot: slot,
+            token,
+            refreshing_token: false,
+            refresh_token_on_join: false,
+            commands: HashSet::new(),
+            log_shipping_until: None,
+            validations: HashMap::new(),
+            next_validation_id: 0,
+            write_buffer: WriteBuffer::default(),
+            limits,
+            rate_window: RateWindow::default(),
+            violations: (0, Instant::now()),
+        }
+    }
+
+    /// Refreshes the token once the runner joins if it is given, e.g. the token is issued in the previous format
+    pub fn refresh_token_on_join(mut self, refresh: bool) -> Self {
+        self.refresh_token_on_join = refresh;
+        self
+    }
+
+    pub fn write_buffer(&self) -> &WriteBuffer {
+        &self.write_buffer
+    }
+
+    /// Writes the message into the context. Runner is disconnected if it does not take the messages,
+    /// instead of buffering them without a limit.
+    fn send<T: Serialize>(&self, kind: client::SocketMessageKind, data: T, ctx: &mut WebsocketContext<Self>) {
+        let queued = self.write_buffer.queued();
+
+        if queued > MAX_WRITE_BUFFER {
+            error!("runner {} does not keep up with the messages, {} bytes are queued, disconnecting", self.runner_id, queued);
+
+            ctx.close(Some(CloseReason {
+                code: CloseCode::Again,
+                description: Some("runner does not keep up with the messages".to_string()),
+            }));
+            ctx.stop();
+            return;
+        }
+
+        let text = serde_json::to_string(&client::SocketMessage { kind, data }).unwrap();
+
+        self.write_buffer.add(text.len());
+        ctx.text(text);
+    }
+
+    /// Logs and counts the violation. Runner is disconnected once it keeps violating the limits,
+    /// returns whether the session is still alive.
+    fn violated(&mut self, violation: Violation, ctx: &mut WebsocketContext<Self>) -> bool {
+        self.limits.record(violation);
+
+        let (count, last) = &mut self.violations;
+
+        if last.elapsed() > LIMIT_VIOLATION_RESET {
+            *count = 0;
+        }
+
+        *count += 1;
+        *last = Instant::now();
+
+        warn!("runner {} violated the {:?} limit, {} violations", self.runner_id, violation, count);
+
+        if *count < MAX_LIMIT_VIOLATIONS {
+            return true;
+        }
+
+        error!("runner {} keeps violating the limits, disconnecting", self.runner_id);
+
+        self.limits.record_disconnect();
+
+        ctx.close(Some(CloseReason {
+            code: CloseCode::Policy,
+            description: Some("runner exceeded the message limits".to_string()),
+        }));
+        ctx.stop();
+
+        false
+    }
+
+    /// Closes the connection with the reason, so that the runner can decide whether to reconnect.
+    fn close(&self, kind: CloseKind, ctx: &mut WebsocketContext<Self>) {
+        ctx.close(Some(CloseReason {
+            code: CloseCode::Other(kind.code()),
+            description: Some(kind.description().to_string()),
+        }));
+        ctx.stop();
+    }
+
+    // Spawned outside of the actor, since results are also recorded while the session is stopping
+    fn finish_command(&self, command_id: ModelId, status: CommandStatus, output: String) {
+        let conn = self.pool.get().unwrap();
+        let runner_id = self.runner_id;
+
+        Arbiter::spawn(async move {
+            if let Err(e) = web::block(move || RunnerCommand::finish(command_id, runner_id, status, output, &conn))
+                .await {
+                error!("recording result of command {} is failed: {:?}", command_id, e);
+            }
+        });
+    }
+
+    fn store_client_logs(&self, lines: Vec<String>, ctx: &mut WebsocketContext<Self>) {
+        let conn = self.pool.get().unwrap();
+        let runner_id = self.runner_id;
+        let lines = lines
+            .into_iter()
+            .map(|mut line| {
+                if line.len() > MAX_LOG_LINE_LENGTH {
+                    let mut end = MAX_LOG_LINE_LENGTH;
+
+                    while !line.is_char_boundary(end) {
+                        end -= 1;
+                    }
+
+                    line.truncate(end);
+                }
+
+                line
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        async move {
+            let res = web::block(move || -> Result<(), diesel::result::Error> {
+                diesel::delete(runner_client_logs::table
+                    .filter(runner_client_logs::runner_id.eq(runner_id))
+                    .filter(runner_client_logs::created_at.lt(now - LOG_RETENTION_DAYS.days()))
+                )
+                    .execute(&conn)?;
+
+                diesel::insert_into(runner_client_logs::table)
+                    .values((
+                        runner_client_logs::runner_id.eq(runner_id),
+                        runner_client_logs::lines.eq(lines),
+                    ))
+                    .execute(&conn)?;
+
+                Ok(())
+            })
+                .await;
+
+            if let Err(e) = res {
+                error!("storing client logs of runner {} is failed: {:?}", runner_id, e);
+            }
         }
+            .into_actor(self)
+            .spawn(ctx);
     }
 
-    fn handle_msg(&self, msg: Message, ctx: &mut WebsocketContext<Self>) -> Result<(), SocketErrorKind> {
+    fn hb(&self, ctx: &mut WebsocketContext<Self>) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
+                info!("runner {} heartbeat is timed out, disconnecting", act.runner_id);
+                ctx.stop();
+                return;
+            }
+
+            let token_expired = match act.token {
+                Some((_, exp)) => exp <= Utc::now().timestamp(),
+                None => false
+            };
+
+            // runner could not receive a refreshed token in time
+            if token_expired {
+                info!("token of runner {} is expired, disconnecting", act.runner_id);
+                act.close(CloseKind::AuthExpired, ctx);
+                return;
+            }
+
+            ctx.ping(b"");
+
+            let token_expiring = match act.token {
+                Some((_, exp)) => exp - Utc::now().timestamp() < TOKEN_REFRESH_BEFORE,
+                None => false
+            };
+
+            if token_expiring && !act.refreshing_token {
+                act.refresh_token(ctx);
+            }
+        });
+    }
+
+    /// Rotates the runner's access key and sends the new token to the runner. Key of this connection
+    /// stays valid until the runner connects with the new token.
+    fn refresh_token(&mut self, ctx: &mut WebsocketContext<Self>) {
+        let access_key_hash = match &self.token {
+            Some((access_key_hash, _)) => access_key_hash.clone(),
+            None => return
+        };
+
+        self.refreshing_token = true;
+
+        let conn = self.pool.get().unwrap();
+        let hash = self.hash.clone();
+        let runner_id = self.runner_id;
+
+        async move {
+            web::block(move || RunnerToken::issue(runner_id, Some(access_key_hash), &hash, &conn))
+                .await
+        }
+            .into_actor(self)
+            .then(|result, act, ctx| {
+                act.refreshing_token = false;
+
+                match result {
+                    Ok((token, exp)) => {
+                        info!("token of runner {} is refreshed", act.runner_id);
+
+                        if let Some((_, token_exp)) = &mut act.token {
+                            *token_exp = exp;
+                        }
+
+                        act.send(client::SocketMessageKind::TokenRefresh, client::TokenRefresh { token }, ctx);
+                    }
+                    Err(e) => error!("refreshing token of runner {} is failed: {:?}", act.runner_id, e)
+                }
+
+                fut::ready(())
+            })
+            .spawn(ctx);
+    }
+
+    fn beat(&mut self) {
+        self.hb = Instant::now();
+        self.experiment_server.do_send(HeartbeatMessage { runner_id: self.runner_id });
+    }
+
+    fn handle_msg(&mut self, msg: Message, ctx: &mut WebsocketContext<Self>) -> Result<(), SocketErrorKind> {
         match msg {
-            Message::Ping(_) | Message::Pong(_) => {
-                // self.hb = Instant::now()
+            Message::Ping(bytes) => {
+                self.beat();
+                ctx.pong(&bytes);
             }
+            Message::Pong(_) => self.beat(),
             Message::Text(text) => {
                 let text = text.as_str();
 
-                let base = serde_json::from_str::<'_, server::BaseMessage>(text)
-                    .map_err(|_| SocketErrorKind::InvalidMessage)?;
+                // a well formed message of an unknown kind is sent by an incompatible runner
+                let base = match serde_json::from_str::<'_, server::BaseMessage>(text) {
+                    Ok(base) => base,
+                    Err(e) if e.is_data() => {
+                        error!("runner {} sent a message of an unknown kind, disconnecting", self.runner_id);
+                        self.close(CloseKind::ProtocolMismatch, ctx);
+                        return Ok(());
+                    }
+                    Err(_) => return Err(SocketErrorKind::InvalidMessage)
+                };
 
                 match base.kind {
                     server::SocketMessageKind::RunResult => {
                         let run_result = serde_json::from_str::<'_, server::SocketMessage<server::RunResult>>(text)
                             .map_err(|_| SocketErrorKind::InvalidMessage)?;
 
-                        info!("received run result from runner, successful {}", run_result.data.successful);
+                        info!("received run result from runner, successful {}, truncated {}", run_result.data.successful, run_result.data.truncated);
+
+                        let invalid_streams = run_result.data.streams.len() > MAX_JOB_STREAMS ||
+                            run_result.data.streams.iter().any(|s| s.name.is_empty() || s.name.len() > MAX_JOB_STREAM_NAME_LENGTH);
+
+                        let invalid_message_id = run_result.data.message_id.as_ref()
+                            .is_some_and(|message_id| message_id.is_empty() || message_id.len() > MAX_MESSAGE_ID_LENGTH);
+
+                        let invalid_environment = run_result.data.environment.as_ref()
+                            .is_some_and(|environment| !is_valid_environment(environment));
+
+                        if invalid_streams || invalid_message_id || invalid_environment {
+                            return Err(SocketErrorKind::InvalidMessage);
+                        }
+
+                        let artifacts = decode_artifacts(run_result.data.artifacts)
+                            .ok_or(SocketErrorKind::InvalidMessage)?;
 
                         let exp_addr = self.experiment_server.clone();
 
                         let msg = RunResultMessage {
-                            job_id: run_result.data.job_id,
+                            job_id: JobId(run_result.data.job_id),
                             runner_id: self.runner_id,
                             successful: run_result.data.successful,
+                            output: run_result.data.output,
+                            truncated: run_result.data.truncated,
+                            streams: run_result.data.streams,
+                            flashed: run_result.data.flashed,
+                            message_id: run_result.data.message_id,
+                            environment: run_result.data.environment,
+                            artifacts,
                         };
 
                         async move {
@@ -57,6 +402,101 @@ impl Session {
                             .into_actor(self)
                             .spawn(ctx);
                     }
+                    server::SocketMessageKind::CommandResult => {
+                        let command_result = serde_json::from_str::<'_, server::SocketMessage<server::CommandResult>>(text)
+                            .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+                        let command_id = command_result.data.command_id;
+
+                        // only the results of the commands sent over this session are accepted
+                        if !self.commands.remove(&command_id) {
+                            return Err(SocketErrorKind::InvalidMessage);
+                        }
+
+                        let status = if command_result.data.successful { CommandStatus::Successful } else { CommandStatus::Failed };
+
+                        self.finish_command(command_id, status, command_result.data.output);
+                    }
+                    server::SocketMessageKind::ClientLogs => {
+                        let client_logs = serde_json::from_str::<'_, server::SocketMessage<server::ClientLogs>>(text)
+                            .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+                        // runner may still ship the logs buffered right before the window is closed
+                        let accepted = self.log_shipping_until
+                            .map_or(false, |until| Instant::now() <= until + HEARTBEAT_INTERVAL * 2);
+
+                        if !accepted || client_logs.data.lines.len() > MAX_LOG_LINES {
+                            return Err(SocketErrorKind::InvalidMessage);
+                        }
+
+                        self.store_client_logs(client_logs.data.lines, ctx);
+                    }
+                    server::SocketMessageKind::ValidationResult => {
+                        let validation_result = serde_json::from_str::<'_, server::SocketMessage<server::ValidationResult>>(text)
+                            .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+                        let result = self.validations.remove(&validation_result.data.validation_id)
+                            .ok_or(SocketErrorKind::InvalidMessage)?;
+
+                        // requester may have already timed out
+                        let _ = result.send(validation_result.data.diagnostics);
+                    }
+                    server::SocketMessageKind::RunnerInfo => {
+                        let runner_info = serde_json::from_str::<'_, server::SocketMessage<server::RunnerInfo>>(text)
+                            .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+                        let info = runner_info.data;
+
+                        let too_long = |value: &Option<String>| value.as_deref().unwrap_or_default().len() > MAX_INVENTORY_FIELD_LENGTH;
+
+                        let invalid_inventory = too_long(&info.kernel) || too_long(&info.cpu_model) ||
+                            info.peripherals.len() > MAX_PERIPHERALS ||
+                            info.peripherals.iter().any(|p| p.is_empty() || p.len() > MAX_PERIPHERAL_NAME_LENGTH);
+
+                        if invalid_inventory {
+                            return Err(SocketErrorKind::InvalidMessage);
+                        }
+
+                        self.experiment_server.do_send(RunnerInfoMessage {
+                            runner_id: self.runner_id,
+                            os: info.os,
+                            arch: info.arch,
+                            client_version: info.client_version,
+                            kernel: info.kernel,
+                            cpu_model: info.cpu_model,
+                            memory_bytes: info.memory_bytes,
+                            peripherals: info.peripherals,
+                        });
+                    }
+                    server::SocketMessageKind::DiskPressure => {
+                        let disk_pressure = serde_json::from_str::<'_, server::SocketMessage<server::DiskPressure>>(text)
+                            .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+                        info!("runner {} reported disk pressure {}, free space {} bytes", self.runner_id, disk_pressure.data.under_pressure, disk_pressure.data.free_space);
+
+                        self.experiment_server.do_send(DiskPressureMessage {
+                            runner_id: self.runner_id,
+                            under_pressure: disk_pressure.data.under_pressure,
+                        });
+                    }
+                    server::SocketMessageKind::ThermalState => {
+                        let thermal_state = serde_json::from_str::<'_, server::SocketMessage<server::ThermalState>>(text)
+                            .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+                        let temperatures = thermal_state.data.temperatures;
+
+                        let invalid_temperatures = temperatures.len() > MAX_ENVIRONMENT_ENTRIES ||
+                            temperatures.iter().any(|(zone, temperature)| zone.is_empty() || zone.len() > MAX_ENVIRONMENT_FIELD_LENGTH || !temperature.is_finite());
+
+                        if invalid_temperatures {
+                            return Err(SocketErrorKind::InvalidMessage);
+                        }
+
+                        self.experiment_server.do_send(ThermalStateMessage {
+                            runner_id: self.runner_id,
+                            temperature: temperatures.values().copied().reduce(f64::max),
+                        });
+                    }
                 }
             }
             Message::Close(_) => ctx.stop(),
@@ -67,10 +507,84 @@ impl Session {
     }
 }
 
+/// Results of the jobs and the commands, and the close frames are handled even if the runner exceeds the rates
+fn is_protocol_critical(msg: &Message) -> bool {
+    match msg {
+        Message::Close(_) => true,
+        Message::Text(text) => serde_json::from_str::<'_, server::BaseMessage>(text.as_str())
+            .is_ok_and(|base| matches!(base.kind, server::SocketMessageKind::RunResult | server::SocketMessageKind::CommandResult)),
+        _ => false
+    }
+}
+
+/// Returns none if the artifacts are not valid base64 or exceed the limits. Names are used in the download urls,
+/// hence they can not contain slashes.
+fn decode_artifacts(artifacts: Vec<server::Artifact>) -> Option<Vec<NewJobArtifact>> {
+    if artifacts.len() > MAX_JOB_ARTIFACTS {
+        return None;
+    }
+
+    let mut size = 0;
+    let mut decoded = Vec::with_capacity(artifacts.len());
+
+    for artifact in artifacts {
+        if artifact.name.is_empty() || artifact.name.len() > MAX_JOB_ARTIFACT_NAME_LENGTH || artifact.name.contains('/') {
+            return None;
+        }
+
+        let invalid_metadata = artifact.metadata.len() > MAX_JOB_ARTIFACT_METADATA || artifact.metadata.iter()
+            .any(|(key, value)| key.is_empty() || key.len() > MAX_JOB_ARTIFACT_METADATA_LENGTH || value.len() > MAX_JOB_ARTIFACT_METADATA_LENGTH);
+
+        if invalid_metadata {
+            return None;
+        }
+
+        let data = base64::decode(&artifact.data).ok()?;
+
+        size += data.len();
+
+        if size > MAX_JOB_ARTIFACTS_SIZE {
+            return None;
+        }
+
+        decoded.push(NewJobArtifact { name: artifact.name, data, metadata: artifact.metadata });
+    }
+
+    Some(decoded)
+}
+
+fn is_valid_environment(environment: &server::Environment) -> bool {
+    let too_long = |value: &str| value.is_empty() || value.len() > MAX_ENVIRONMENT_FIELD_LENGTH;
+
+    let performance_entries = environment.performance.as_ref().map_or(0, |performance| {
+        performance.governors.len() + performance.min_frequencies_khz.len() + performance.max_frequencies_khz.len() + performance.errors.len()
+    });
+
+    let entries = environment.governors.len() + environment.temperatures.len() + environment.firmware_versions.len() + performance_entries;
+
+    let valid_performance = environment.performance.as_ref().is_none_or(|performance| {
+        performance.governors.iter().all(|(cpu, governor)| !too_long(cpu) && !too_long(governor)) &&
+            performance.min_frequencies_khz.keys().chain(performance.max_frequencies_khz.keys()).all(|cpu| !too_long(cpu)) &&
+            performance.errors.iter().all(|error| !too_long(error))
+    });
+
+    entries <= MAX_ENVIRONMENT_ENTRIES && valid_performance &&
+        environment.kernel.as_deref().is_none_or(|kernel| !too_long(kernel)) &&
+        environment.governors.iter().all(|(cpu, governor)| !too_long(cpu) && !too_long(governor)) &&
+        environment.temperatures.iter().all(|(zone, temperature)| !too_long(zone) && temperature.is_finite()) &&
+        environment.firmware_versions.iter().all(|(device, version)| !too_long(device) && !too_long(version))
+}
+
 impl Actor for Session {
     type Context = WebsocketContext<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
+        self.hb(ctx);
+
+        if self.refresh_token_on_join {
+            self.refresh_token(ctx);
+        }
+
         let exp_addr = self.experiment_server.clone();
 
         let msg = JoinServerMessage {
@@ -88,19 +602,58 @@ impl Actor for Session {
             .spawn(ctx);
     }
 
-    fn stopped(&mut self, _: &mut Self::Context) {}
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        for command_id in std::mem::take(&mut self.commands) {
+            self.finish_command(command_id, CommandStatus::Failed, "runner disconnected before reporting the result".to_string());
+        }
+
+        self.experiment_server.do_send(LeaveServerMessage {
+            runner_id: self.runner_id,
+            addr: ctx.address(),
+        });
+    }
 }
 
 impl StreamHandler<Result<Message, ProtocolError>> for Session {
     fn handle(&mut self, msg: Result<Message, ProtocolError>, ctx: &mut Self::Context) {
-        match msg {
-            Ok(msg) => {
-                if let Err(e) = self.handle_msg(msg, ctx) {
-                    error!("{:?}", e);
-                }
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(ProtocolError::Overflow) => {
+                self.limits.record(Violation::FrameSize);
+                self.limits.record_disconnect();
+
+                error!("runner {} sent a frame larger than {} bytes, disconnecting", self.runner_id, self.limits.max_frame_size());
+
+                ctx.close(Some(CloseReason {
+                    code: CloseCode::Size,
+                    description: Some("frame is too large".to_string()),
+                }));
+                ctx.stop();
+                return;
+            }
+            Err(_) => {
+                ctx.stop();
+                return;
             }
-            Err(_) => ctx.stop()
         };
+
+        // messages exceeding the rates are dropped, except the ones the jobs and the commands depend on. Dropping a
+        // result would leave its job running until the lease expires, and the runner does not send it again
+        let allowed = match self.limits.check(&mut self.rate_window, &msg) {
+            Ok(allowed) => allowed,
+            Err(violation) => {
+                self.violated(violation, ctx);
+                false
+            }
+        };
+
+        if !allowed && !is_protocol_critical(&msg) {
+            return;
+        }
+
+        if let Err(e) = self.handle_msg(msg, ctx) {
+            error!("{:?}", e);
+        }
     }
 }
 
@@ -111,9 +664,89 @@ impl Handler<RunMessage> for Session {
         info!("got run message {}", msg.job_id);
 
         // TODO we can send directly message to client, instead of copying msg into RunExperiment
-        ctx.text(serde_json::to_string(&client::SocketMessage {
-            kind: client::SocketMessageKind::RunExperiment,
-            data: client::RunExperiment { job_id: msg.job_id, code: msg.code },
-        }).unwrap());
+        let run_experiment = client::RunExperiment {
+            job_id: msg.job_id.into(),
+            code: msg.code,
+            hooks: msg.hooks,
+            firmware: msg.firmware,
+            performance: msg.performance,
+            network: msg.network,
+            sdr: msg.sdr,
+        };
+
+        self.send(client::SocketMessageKind::RunExperiment, run_experiment, ctx);
     }
-}
\ No newline at end of file
+}
+
+impl Handler<ClientUpdateMessage> for Session {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClientUpdateMessage, ctx: &mut Self::Context) {
+        self.send(client::SocketMessageKind::ClientUpdate, msg.update, ctx);
+    }
+}
+
+impl Handler<CommandMessage> for Session {
+    type Result = ();
+
+    fn handle(&mut self, msg: CommandMessage, ctx: &mut Self::Context) {
+        self.commands.insert(msg.command.command_id);
+
+        self.send(client::SocketMessageKind::RunnerCommand, msg.command, ctx);
+    }
+}
+
+impl Handler<LogLevelMessage> for Session {
+    type Result = ();
+
+    fn handle(&mut self, msg: LogLevelMessage, ctx: &mut Self::Context) {
+        self.log_shipping_until = msg.log_level.ship_seconds
+            .map(|seconds| Instant::now() + Duration::from_secs(seconds).min(MAX_LOG_SHIPPING));
+
+        self.send(client::SocketMessageKind::SetLogLevel, msg.log_level, ctx);
+    }
+}
+
+impl Handler<ValidationMessage> for Session {
+    type Result = ();
+
+    fn handle(&mut self, msg: ValidationMessage, ctx: &mut Self::Context) {
+        let validation_id = self.next_validation_id;
+        self.next_validation_id += 1;
+
+        // answers of the abandoned validations are not awaited anymore
+        self.validations.retain(|_, result| !result.is_canceled());
+        self.validations.insert(validation_id, msg.result);
+
+        self.send(client::SocketMessageKind::ValidateExperiment, client::ValidateExperiment {
+            validation_id,
+            code: msg.code,
+            hooks: msg.hooks,
+            has_firmware: msg.has_firmware,
+        }, ctx);
+    }
+}
+
+impl Handler<DisconnectMessage> for Session {
+    type Result = ();
+
+    fn handle(&mut self, msg: DisconnectMessage, ctx: &mut Self::Context) {
+        self.close(msg.kind, ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_and_close_frames_are_critical() {
+        assert!(is_protocol_critical(&Message::Text(r#"{"kind":"RunResult","data":{}}"#.into())));
+        assert!(is_protocol_critical(&Message::Text(r#"{"kind":"CommandResult","data":{}}"#.into())));
+        assert!(is_protocol_critical(&Message::Close(None)));
+
+        assert!(!is_protocol_critical(&Message::Text(r#"{"kind":"ClientLogs","data":{}}"#.into())));
+        assert!(!is_protocol_critical(&Message::Text("not json".into())));
+        assert!(!is_protocol_critical(&Message::Ping(Default::default())));
+    }
+}
@@ -0,0 +1,218 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use diesel::prelude::*;
+
+use core::schema::{experiments, jobs, projects, runner_job_stats, runners};
+use core::types::{JobId, RunnerId};
+
+use crate::models::experiment::RunnerStrategy;
+
+/// Strategy of the experiment a job belongs to, along with its preferred runner labels and its thermal guard. Labels
+/// default to the ones of the experiment's project.
+#[derive(Default)]
+pub struct Placement {
+    pub strategy: RunnerStrategy,
+    pub labels: Vec<String>,
+    // job is held back until a runner is cooler than this many degrees Celsius, at most for the thermal timeout
+    pub max_temperature: Option<f64>,
+    pub thermal_timeout: Duration,
+}
+
+/// Idle runner which a job can be dispatched to
+pub struct Candidate {
+    pub runner_id: RunnerId,
+    // score computed by the `StatsAggregator`, lower is better
+    pub score: Option<f64>,
+    // number of the jobs the runner has finished recently
+    pub job_count: i64,
+    pub p50_duration: Option<f64>,
+    // labels given by the admins along with the capabilities reported by the runner
+    pub labels: Vec<String>,
+}
+
+impl Candidate {
+    pub fn new(runner_id: RunnerId) -> Self {
+        Candidate {
+            runner_id,
+            score: None,
+            job_count: 0,
+            p50_duration: None,
+            labels: Vec::new(),
+        }
+    }
+}
+
+/// Picks one of the idle runners for a job. Candidates are the runners which are connected to this replica and are
+/// not disabled or under disk pressure, a strategy returns none only if there is not any.
+pub trait Strategy {
+    fn select(&mut self, placement: &Placement, candidates: &[Candidate]) -> Option<RunnerId>;
+}
+
+// Runners without the stats sort last
+fn cmp_missing_last(a: Option<f64>, b: Option<f64>) -> Ordering {
+    a.unwrap_or(f64::INFINITY)
+        .partial_cmp(&b.unwrap_or(f64::INFINITY))
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Prefers the runner with the best score, i.e. the lowest expected duration of a successful job
+pub struct BestScore;
+
+impl Strategy for BestScore {
+    fn select(&mut self, _: &Placement, candidates: &[Candidate]) -> Option<RunnerId> {
+        candidates.iter()
+            .min_by(|a, b| cmp_missing_last(a.score, b.score))
+            .map(|candidate| candidate.runner_id)
+    }
+}
+
+/// Prefers the runner which has run the fewest jobs recently, ties are broken by the score
+pub struct LeastLoaded;
+
+impl Strategy for LeastLoaded {
+    fn select(&mut self, _: &Placement, candidates: &[Candidate]) -> Option<RunnerId> {
+        candidates.iter()
+            .min_by(|a, b| a.job_count.cmp(&b.job_count).then_with(|| cmp_missing_last(a.score, b.score)))
+            .map(|candidate| candidate.runner_id)
+    }
+}
+
+/// Takes the runners in turns, ordered by their ids
+#[derive(Default)]
+pub struct RoundRobin {
+    last: Option<RunnerId>,
+}
+
+impl Strategy for RoundRobin {
+    fn select(&mut self, _: &Placement, candidates: &[Candidate]) -> Option<RunnerId> {
+        let first = candidates.iter().map(|candidate| candidate.runner_id).min();
+
+        let next = match self.last {
+            Some(last) => candidates.iter()
+                .map(|candidate| candidate.runner_id)
+                .filter(|runner_id| *runner_id > last)
+                .min()
+                .or(first),
+            None => first
+        };
+
+        if next.is_some() {
+            self.last = next;
+        }
+
+        next
+    }
+}
+
+/// Prefers the runner with the lowest median job duration, regardless of how often the jobs fail on it
+pub struct FastestHistorical;
+
+impl Strategy for FastestHistorical {
+    fn select(&mut self, _: &Placement, candidates: &[Candidate]) -> Option<RunnerId> {
+        candidates.iter()
+            .min_by(|a, b| cmp_missing_last(a.p50_duration, b.p50_duration))
+            .map(|candidate| candidate.runner_id)
+    }
+}
+
+/// Prefers the runner having the most of the experiment's preferred labels, ties are broken by the score
+pub struct LabelAffinity;
+
+impl Strategy for LabelAffinity {
+    fn select(&mut self, placement: &Placement, candidates: &[Candidate]) -> Option<RunnerId> {
+        let matches = |candidate: &Candidate| placement.labels.iter()
+            .filter(|label| candidate.labels.contains(label))
+            .count();
+
+        candidates.iter()
+            .min_by(|a, b| matches(b).cmp(&matches(a)).then_with(|| cmp_missing_last(a.score, b.score)))
+            .map(|candidate| candidate.runner_id)
+    }
+}
+
+/// Instances of the strategies kept by the `ExperimentServer`, so that the stateful ones like `RoundRobin`
+/// remember where they are left
+pub struct Strategies {
+    strategies: HashMap<RunnerStrategy, Box<dyn Strategy>>,
+}
+
+impl Strategies {
+    pub fn new() -> Self {
+        let mut strategies: HashMap<RunnerStrategy, Box<dyn Strategy>> = HashMap::new();
+
+        strategies.insert(RunnerStrategy::BestScore, Box::new(BestScore));
+        strategies.insert(RunnerStrategy::LeastLoaded, Box::new(LeastLoaded));
+        strategies.insert(RunnerStrategy::RoundRobin, Box::new(RoundRobin::default()));
+        strategies.insert(RunnerStrategy::FastestHistorical, Box::new(FastestHistorical));
+        strategies.insert(RunnerStrategy::LabelAffinity, Box::new(LabelAffinity));
+
+        Strategies { strategies }
+    }
+
+    pub fn select(&mut self, placement: &Placement, candidates: &[Candidate]) -> Option<RunnerId> {
+        self.strategies.get_mut(&placement.strategy)
+            .expect("every strategy should be registered")
+            .select(placement, candidates)
+    }
+}
+
+/// Loads the placement of the job and the recent stats and the labels of the given runners. Scores are kept in the
+/// memory by the server, they are not filled.
+pub fn load_candidates(job_id: JobId, runner_ids: Vec<RunnerId>, conn: &PgConnection) -> QueryResult<(Placement, Vec<Candidate>)> {
+    let (strategy, labels, default_labels, max_temperature, thermal_timeout) = jobs::table
+        .inner_join(experiments::table.left_join(projects::table))
+        .filter(jobs::id.eq(job_id))
+        .select((
+            experiments::runner_strategy,
+            experiments::preferred_labels,
+            projects::default_labels.nullable(),
+            experiments::max_temperature,
+            experiments::thermal_timeout,
+        ))
+        .first::<(RunnerStrategy, Vec<String>, Option<Vec<String>>, Option<f64>, i32)>(conn)?;
+
+    // experiments without preferred labels of their own prefer the default ones of their project
+    let labels = match (labels.is_empty(), default_labels) {
+        (true, Some(default_labels)) => default_labels,
+        _ => labels
+    };
+
+    let stats = runner_job_stats::table
+        .filter(runner_job_stats::runner_id.eq_any(&runner_ids))
+        .select((runner_job_stats::runner_id, runner_job_stats::job_count, runner_job_stats::p50_duration))
+        .load::<(RunnerId, i64, Option<f64>)>(conn)?;
+
+    let runner_labels = runners::table
+        .filter(runners::id.eq_any(&runner_ids))
+        .select((runners::id, runners::labels, runners::capabilities))
+        .load::<(RunnerId, Vec<String>, Vec<String>)>(conn)?;
+
+    let mut candidates = runner_ids.into_iter()
+        .map(|runner_id| (runner_id, Candidate::new(runner_id)))
+        .collect::<HashMap<RunnerId, Candidate>>();
+
+    for (runner_id, job_count, p50_duration) in stats {
+        if let Some(candidate) = candidates.get_mut(&runner_id) {
+            candidate.job_count = job_count;
+            candidate.p50_duration = p50_duration;
+        }
+    }
+
+    for (runner_id, mut labels, capabilities) in runner_labels {
+        if let Some(candidate) = candidates.get_mut(&runner_id) {
+            labels.extend(capabilities);
+            candidate.labels = labels;
+        }
+    }
+
+    let placement = Placement {
+        strategy,
+        labels,
+        max_temperature,
+        thermal_timeout: Duration::from_secs(thermal_timeout as u64),
+    };
+
+    Ok((placement, candidates.into_values().collect()))
+}
@@ -0,0 +1,93 @@
+use std::time::Instant;
+
+use actix::prelude::*;
+use actix_web_actors::ws::{Message, ProtocolError, WebsocketContext};
+use log::{error, info};
+
+use core::types::UserId;
+
+use crate::connection::messages::{JoinUserMessage, LeaveUserMessage, NotificationMessage};
+use crate::connection::server::ExperimentServer;
+use crate::connection::session::{CLIENT_TIMEOUT, HEARTBEAT_INTERVAL};
+
+/// Websocket session of a user, only used for pushing notifications to the user.
+pub struct UserSession {
+    experiment_server: Addr<ExperimentServer>,
+    user_id: UserId,
+    hb: Instant,
+}
+
+impl UserSession {
+    pub fn new(experiment_server: Addr<ExperimentServer>, user_id: UserId) -> Self {
+        UserSession {
+            experiment_server,
+            user_id,
+            hb: Instant::now(),
+        }
+    }
+
+    fn hb(&self, ctx: &mut WebsocketContext<Self>) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
+                info!("user {} heartbeat is timed out, disconnecting", act.user_id);
+                ctx.stop();
+                return;
+            }
+
+            ctx.ping(b"");
+        });
+    }
+}
+
+impl Actor for UserSession {
+    type Context = WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.hb(ctx);
+
+        let exp_addr = self.experiment_server.clone();
+
+        let msg = JoinUserMessage {
+            user_id: self.user_id,
+            addr: ctx.address(),
+        };
+
+        async move {
+            if let Err(e) = exp_addr.send(msg)
+                .await {
+                error!("joining user into server is failed: {:?}", e);
+            }
+        }
+            .into_actor(self)
+            .spawn(ctx);
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        self.experiment_server.do_send(LeaveUserMessage {
+            user_id: self.user_id,
+            addr: ctx.address(),
+        });
+    }
+}
+
+impl StreamHandler<Result<Message, ProtocolError>> for UserSession {
+    fn handle(&mut self, msg: Result<Message, ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(Message::Ping(bytes)) => {
+                self.hb = Instant::now();
+                ctx.pong(&bytes);
+            }
+            Ok(Message::Pong(_)) => self.hb = Instant::now(),
+            Ok(Message::Close(_)) | Err(_) => ctx.stop(),
+            Ok(_) => {}
+        }
+    }
+}
+
+impl Handler<NotificationMessage> for UserSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: NotificationMessage, ctx: &mut Self::Context) {
+        ctx.text(serde_json::to_string(&msg.notification).unwrap());
+    }
+}
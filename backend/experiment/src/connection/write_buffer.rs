@@ -0,0 +1,54 @@
+use std::cell::Cell;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_web::web::Bytes;
+use futures::Stream;
+
+/// Size of the messages which are written into the websocket context of a session but not taken by the
+/// connection yet. Context buffers the messages without a limit, so this is how a runner which does not
+/// keep up is noticed.
+#[derive(Clone, Default)]
+pub struct WriteBuffer(Rc<Cell<usize>>);
+
+impl WriteBuffer {
+    pub fn queued(&self) -> usize {
+        self.0.get()
+    }
+
+    pub fn add(&self, size: usize) {
+        self.0.set(self.0.get() + size);
+    }
+
+    fn drain(&self, size: usize) {
+        // encoded frames are slightly larger than the messages due to the frame headers
+        self.0.set(self.0.get().saturating_sub(size));
+    }
+}
+
+/// Response stream of the websocket which drains the buffer as the encoded frames are taken by the connection
+pub struct TrackedStream<S> {
+    stream: S,
+    buffer: WriteBuffer,
+}
+
+impl<S> TrackedStream<S> {
+    pub fn new(stream: S, buffer: WriteBuffer) -> Self {
+        TrackedStream { stream, buffer }
+    }
+}
+
+impl<S, E> Stream for TrackedStream<S> where S: Stream<Item=Result<Bytes, E>> + Unpin {
+    type Item = Result<Bytes, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.stream).poll_next(cx);
+
+        if let Poll::Ready(Some(Ok(bytes))) = &poll {
+            self.buffer.drain(bytes.len());
+        }
+
+        poll
+    }
+}
@@ -0,0 +1,298 @@
+use diesel::prelude::*;
+use log::error;
+
+use core::db::DieselEnum;
+use core::schema::{experiments, jobs};
+use core::types::JobId;
+
+use crate::models::batch::parse_metric;
+use crate::models::job::Verdict;
+
+pub const MAX_CRITERIA_LENGTH: usize = 1024;
+
+/// Success criteria of an experiment over the metrics reported by its jobs, e.g.
+/// `energy_mj < 500 && (throughput > 10 || !(latency_ms >= 20))`. Metrics are compared with numbers, comparisons
+/// are combined with `&&`, `||` and `!`.
+#[derive(Debug)]
+pub enum Criteria {
+    Compare { metric: String, op: CompareOp, value: f64 },
+    Not(Box<Criteria>),
+    And(Box<Criteria>, Box<Criteria>),
+    Or(Box<Criteria>, Box<Criteria>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompareOp {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+impl CompareOp {
+    fn holds(&self, left: f64, right: f64) -> bool {
+        match self {
+            CompareOp::Less => left < right,
+            CompareOp::LessEqual => left <= right,
+            CompareOp::Greater => left > right,
+            CompareOp::GreaterEqual => left >= right,
+            CompareOp::Equal => left == right,
+            CompareOp::NotEqual => left != right,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Metric(String),
+    Number(f64),
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars = input.chars().collect::<Vec<char>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('<', Some('=')) => (Token::Op(CompareOp::LessEqual), 2),
+            ('>', Some('=')) => (Token::Op(CompareOp::GreaterEqual), 2),
+            ('=', Some('=')) => (Token::Op(CompareOp::Equal), 2),
+            ('!', Some('=')) => (Token::Op(CompareOp::NotEqual), 2),
+            ('<', _) => (Token::Op(CompareOp::Less), 1),
+            ('>', _) => (Token::Op(CompareOp::Greater), 1),
+            ('!', _) => (Token::Not, 1),
+            ('(', _) => (Token::Open, 1),
+            (')', _) => (Token::Close, 1),
+            (c, _) if c.is_ascii_alphabetic() || c == '_' => {
+                let len = chars[i..].iter()
+                    .take_while(|c| c.is_ascii_alphanumeric() || **c == '_' || **c == '.' || **c == '-')
+                    .count();
+
+                (Token::Metric(chars[i..i + len].iter().collect()), len)
+            }
+            (c, _) if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let len = 1 + chars[i + 1..].iter()
+                    .take_while(|c| c.is_ascii_digit() || **c == '.' || **c == 'e' || **c == 'E')
+                    .count();
+
+                let number = chars[i..i + len].iter().collect::<String>();
+
+                match number.parse::<f64>() {
+                    Ok(number) if number.is_finite() => (Token::Number(number), len),
+                    _ => return Err(format!("invalid number {}", number))
+                }
+            }
+            (c, _) => return Err(format!("unexpected character {}", c))
+        };
+
+        tokens.push(token);
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Criteria, String> {
+        let mut criteria = self.and()?;
+
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            criteria = Criteria::Or(Box::new(criteria), Box::new(self.and()?));
+        }
+
+        Ok(criteria)
+    }
+
+    fn and(&mut self) -> Result<Criteria, String> {
+        let mut criteria = self.unary()?;
+
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            criteria = Criteria::And(Box::new(criteria), Box::new(self.unary()?));
+        }
+
+        Ok(criteria)
+    }
+
+    fn unary(&mut self) -> Result<Criteria, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Criteria::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let criteria = self.or()?;
+
+                match self.next() {
+                    Some(Token::Close) => Ok(criteria),
+                    _ => Err(String::from("missing closing parenthesis"))
+                }
+            }
+            Some(Token::Metric(metric)) => match (self.next(), self.next()) {
+                (Some(Token::Op(op)), Some(Token::Number(value))) => Ok(Criteria::Compare { metric, op, value }),
+                _ => Err(format!("metric {} should be compared with a number", metric))
+            },
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err(String::from("unexpected end of the criteria"))
+        }
+    }
+}
+
+impl Criteria {
+    pub fn parse(input: &str) -> Result<Criteria, String> {
+        if input.len() > MAX_CRITERIA_LENGTH {
+            return Err(String::from("criteria is too long"));
+        }
+
+        let mut parser = Parser { tokens: tokenize(input)?, position: 0 };
+        let criteria = parser.or()?;
+
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected {:?}", token));
+        }
+
+        Ok(criteria)
+    }
+
+    /// Returns none if a metric needed to decide is not reported
+    pub fn evaluate(&self, metric: &impl Fn(&str) -> Option<f64>) -> Option<bool> {
+        match self {
+            Criteria::Compare { metric: name, op, value } => metric(name).map(|reported| op.holds(reported, *value)),
+            Criteria::Not(criteria) => criteria.evaluate(metric).map(|met| !met),
+            Criteria::And(left, right) => match left.evaluate(metric)? {
+                true => right.evaluate(metric),
+                false => Some(false)
+            },
+            Criteria::Or(left, right) => match left.evaluate(metric)? {
+                true => Some(true),
+                false => right.evaluate(metric)
+            },
+        }
+    }
+}
+
+/// Evaluates the success criteria of the job's experiment with the metrics in the output of the job, see
+/// `parse_metric`, and records the derived verdict of the job. Jobs not reporting a metric the criteria depends on
+/// are invalid. Returns none if the experiment does not have any criteria.
+pub fn evaluate_job(job_id: JobId, output: &str, conn: &PgConnection) -> QueryResult<Option<Verdict>> {
+    let criteria = jobs::table
+        .inner_join(experiments::table)
+        .filter(jobs::id.eq(job_id))
+        .select(experiments::success_criteria)
+        .first::<Option<String>>(conn)?;
+
+    let criteria = match criteria.as_deref().map(Criteria::parse) {
+        Some(Ok(criteria)) => criteria,
+        Some(Err(e)) => {
+            // criteria is validated before it is stored
+            error!("success criteria of job {} could not be parsed: {}", job_id, e);
+            return Ok(None);
+        }
+        None => return Ok(None)
+    };
+
+    let verdict = match criteria.evaluate(&|metric| parse_metric(output, metric)) {
+        Some(true) => Verdict::Passed,
+        Some(false) => Verdict::Failed,
+        None => Verdict::Invalid,
+    };
+
+    diesel::update(jobs::table.find(job_id))
+        .set(jobs::derived_verdict.eq(verdict.value()))
+        .execute(conn)?;
+
+    Ok(Some(verdict))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "booting\nMETRIC energy_mj=450\nMETRIC throughput=12.5\nMETRIC latency_ms=20\n";
+
+    fn evaluate(criteria: &str) -> Option<bool> {
+        Criteria::parse(criteria)
+            .unwrap()
+            .evaluate(&|metric| parse_metric(OUTPUT, metric))
+    }
+
+    #[test]
+    fn compares_with_each_operator() {
+        assert_eq!(evaluate("energy_mj < 500"), Some(true));
+        assert_eq!(evaluate("energy_mj < 450"), Some(false));
+        assert_eq!(evaluate("energy_mj <= 450"), Some(true));
+        assert_eq!(evaluate("energy_mj <= 449.9"), Some(false));
+        assert_eq!(evaluate("throughput > 12"), Some(true));
+        assert_eq!(evaluate("throughput > 12.5"), Some(false));
+        assert_eq!(evaluate("throughput >= 12.5"), Some(true));
+        assert_eq!(evaluate("throughput >= 13"), Some(false));
+        assert_eq!(evaluate("latency_ms == 20"), Some(true));
+        assert_eq!(evaluate("latency_ms == 2e1"), Some(true));
+        assert_eq!(evaluate("latency_ms == 21"), Some(false));
+        assert_eq!(evaluate("latency_ms != 21"), Some(true));
+        assert_eq!(evaluate("latency_ms != 20"), Some(false));
+        assert_eq!(evaluate("energy_mj > -1"), Some(true));
+    }
+
+    #[test]
+    fn combines_comparisons() {
+        assert_eq!(evaluate("energy_mj < 500 && (throughput > 10 || !(latency_ms >= 20))"), Some(true));
+        assert_eq!(evaluate("energy_mj < 400 || throughput > 20"), Some(false));
+        assert_eq!(evaluate("!energy_mj < 400"), Some(true));
+        // && binds tighter than ||
+        assert_eq!(evaluate("throughput > 20 && energy_mj < 500 || latency_ms == 20"), Some(true));
+    }
+
+    #[test]
+    fn missing_metrics_are_undecided() {
+        assert_eq!(evaluate("packets > 0"), None);
+        assert_eq!(evaluate("!(packets > 0)"), None);
+        assert_eq!(evaluate("energy_mj < 500 && packets > 0"), None);
+        assert_eq!(evaluate("packets > 0 || energy_mj < 500"), None);
+        // decided without the missing metric
+        assert_eq!(evaluate("energy_mj < 400 && packets > 0"), Some(false));
+        assert_eq!(evaluate("energy_mj < 500 || packets > 0"), Some(true));
+    }
+
+    #[test]
+    fn rejects_invalid_criteria() {
+        for criteria in ["", "energy_mj", "energy_mj <", "energy_mj < throughput", "500 > energy_mj", "energy_mj = 5",
+            "(energy_mj < 5", "energy_mj < 5)", "energy_mj < 5 &&", "energy_mj < 5 & x > 1", "energy_mj < 1.2.3",
+            "energy_mj < 1e999", "energy_mj < 5 $"] {
+            assert!(Criteria::parse(criteria).is_err(), "{} is accepted", criteria);
+        }
+
+        assert!(Criteria::parse(&"x < 1 && ".repeat(MAX_CRITERIA_LENGTH)).is_err());
+    }
+}
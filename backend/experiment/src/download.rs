@@ -0,0 +1,156 @@
+use chrono::Utc;
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use core::utils::Hash;
+
+use crate::ErrorMessage;
+use crate::requests::DownloadRequest;
+
+// signed urls are handed to browsers and download managers, they should not be usable for long
+const DOWNLOAD_URL_TIMEOUT: i64 = 60 * 5;
+
+/// Url the file of the job can be downloaded from without a token until it expires
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedUrl {
+    pub url: String,
+    pub expires_at: i64,
+}
+
+/// Files of the jobs which can be downloaded with a signed url. Files are stored in the database, so the signed
+/// urls are served by the API as well, they only spare the clients the token.
+pub enum Download {
+    Output(Uuid),
+    Artifact(Uuid, String),
+}
+
+impl Download {
+    /// Signs the download, the url is only valid for this file
+    pub fn sign(&self, app_url: &str, hash: &Hash) -> SignedUrl {
+        let path = self.path();
+        let expires_at = Utc::now().timestamp() + DOWNLOAD_URL_TIMEOUT;
+        let signature = hash.sign256(message(&path, expires_at).as_str());
+
+        SignedUrl {
+            url: format!(
+                "{}/api/experiment/{}?expires={}&signature={}",
+                app_url.trim_end_matches('/'),
+                path,
+                expires_at,
+                utf8_percent_encode(&signature, NON_ALPHANUMERIC)
+            ),
+            expires_at,
+        }
+    }
+
+    pub fn verify(&self, request: &DownloadRequest, hash: &Hash) -> Result<(), ErrorMessage> {
+        if request.expires < Utc::now().timestamp() || !hash.verify256(message(&self.path(), request.expires).as_str(), &request.signature) {
+            return Err(ErrorMessage::InvalidDownloadSignature);
+        }
+
+        Ok(())
+    }
+
+    fn path(&self) -> String {
+        match self {
+            Download::Output(job_id) => format!("download/job/{}/output", job_id),
+            Download::Artifact(job_id, name) => format!("download/job/{}/artifact/{}", job_id, utf8_percent_encode(name, NON_ALPHANUMERIC)),
+        }
+    }
+}
+
+// signatures of the downloads can not be mistaken for the other digests signed with the secret
+fn message(path: &str, expires: i64) -> String {
+    format!("download:{}:{}", path, expires)
+}
+
+#[cfg(test)]
+mod tests {
+    use percent_encoding::percent_decode_str;
+
+    use core::utils::{Algorithm, TokenKeys};
+
+    use super::*;
+
+    fn hash() -> Hash {
+        let token_keys = TokenKeys::parse("2021-01:secret", "2021-01").unwrap();
+
+        Hash::new(Box::leak(Box::new(String::from("secret"))), token_keys, Algorithm::HS256)
+    }
+
+    // query of the signed url, as it is parsed by the download handlers
+    fn request(url: &SignedUrl) -> DownloadRequest {
+        let query = url.url.split('?').nth(1).unwrap();
+        let param = |name: &str| query.split('&')
+            .find_map(|pair| pair.strip_prefix(format!("{}=", name).as_str()))
+            .map(|value| percent_decode_str(value).decode_utf8().unwrap().to_string())
+            .unwrap();
+
+        DownloadRequest {
+            expires: param("expires").parse().unwrap(),
+            signature: param("signature"),
+            ansi: None,
+        }
+    }
+
+    #[test]
+    fn test_signed_download_is_verified() {
+        let hash = hash();
+        let job_id = Uuid::new_v4();
+        let url = Download::Output(job_id).sign("https://testbed.example/", &hash);
+
+        assert_eq!(url.url.split('?').next().unwrap(), format!("https://testbed.example/api/experiment/download/job/{}/output", job_id));
+        assert!(Download::Output(job_id).verify(&request(&url), &hash).is_ok());
+    }
+
+    #[test]
+    fn test_expired_download_is_rejected() {
+        let hash = hash();
+        let job_id = Uuid::new_v4();
+        let expires = Utc::now().timestamp() - 1;
+        let request = DownloadRequest {
+            expires,
+            signature: hash.sign256(message(&Download::Output(job_id).path(), expires).as_str()),
+            ansi: None,
+        };
+
+        assert!(Download::Output(job_id).verify(&request, &hash).is_err());
+    }
+
+    #[test]
+    fn test_tampered_download_is_rejected() {
+        let hash = hash();
+        let job_id = Uuid::new_v4();
+        let url = Download::Artifact(job_id, String::from("trace.csv")).sign("https://testbed.example", &hash);
+
+        // signed for another file
+        assert!(Download::Artifact(job_id, String::from("secret.csv")).verify(&request(&url), &hash).is_err());
+        assert!(Download::Artifact(Uuid::new_v4(), String::from("trace.csv")).verify(&request(&url), &hash).is_err());
+        assert!(Download::Output(job_id).verify(&request(&url), &hash).is_err());
+
+        // expiry extended or signature altered
+        let extended = DownloadRequest { expires: request(&url).expires + 60, ..request(&url) };
+        assert!(Download::Artifact(job_id, String::from("trace.csv")).verify(&extended, &hash).is_err());
+
+        let mut signature = request(&url).signature;
+        signature.replace_range(0..1, if signature.starts_with('A') { "B" } else { "A" });
+        let altered = DownloadRequest { signature, ..request(&url) };
+        assert!(Download::Artifact(job_id, String::from("trace.csv")).verify(&altered, &hash).is_err());
+    }
+
+    #[test]
+    fn test_artifact_name_is_percent_encoded() {
+        let hash = hash();
+        let job_id = Uuid::new_v4();
+        let name = String::from("run 1/trace?.csv");
+        let url = Download::Artifact(job_id, name.clone()).sign("https://testbed.example", &hash);
+
+        assert!(url.url.contains(format!("download/job/{}/artifact/run%201%2Ftrace%3F%2Ecsv?", job_id).as_str()));
+        // handlers receive the decoded name from the path
+        assert!(Download::Artifact(job_id, name).verify(&request(&url), &hash).is_ok());
+        assert!(Download::Artifact(job_id, String::from("run%201%2Ftrace%3F%2Ecsv")).verify(&request(&url), &hash).is_err());
+    }
+}
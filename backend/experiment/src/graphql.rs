@@ -0,0 +1,548 @@
+use std::collections::HashMap;
+
+use actix::Addr;
+use actix_web::web;
+use async_graphql::{ComplexObject, Context, Data, EmptyMutation, Error, ID, Object, Result, Schema, SimpleObject, Subscription};
+use async_graphql::dataloader::{DataLoader, Loader};
+use chrono::NaiveDateTime;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use futures::{future, Stream, StreamExt};
+use futures::channel::mpsc;
+use log::error;
+use uuid::Uuid;
+
+use core::db::DieselEnum;
+use core::schema::{experiments, job_streams, jobs, runners};
+use core::types::{DBPool, JobId, ModelId, RunnerId, UserId};
+
+use crate::authorization::{can_view_experiment, owned_by};
+use crate::connection::messages::{FetchConnectedRunnersMessage, SubscribeNotificationsMessage};
+use crate::connection::server::ExperimentServer;
+use crate::models::experiment::Experiment;
+use crate::models::job::{JobStatus, JobStream, slim_job_columns, SlimJob};
+use crate::notifications::Notification;
+
+// nested selections like experiments -> jobs -> runner are allowed, deeper ones are rejected
+const MAX_QUERY_DEPTH: usize = 8;
+const MAX_QUERY_COMPLEXITY: usize = 500;
+const DEFAULT_LATEST_JOBS: i32 = 5;
+const MAX_LATEST_JOBS: i32 = 50;
+const MAX_PER_PAGE: i32 = 100;
+
+pub type TestbedSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+/// User making the request, results are limited to the experiments and jobs of this user.
+pub struct Viewer {
+    pub user_id: UserId,
+}
+
+pub fn build_schema(pool: DBPool, experiment_server: Addr<ExperimentServer>) -> TestbedSchema {
+    Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+        .data(pool)
+        .data(experiment_server)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .finish()
+}
+
+/// Data of a request or a websocket connection. Loaders batch the lookups of the nested fields,
+/// so that a list does not issue a query for each of its items.
+pub fn request_data(user_id: UserId, pool: DBPool, experiment_server: Addr<ExperimentServer>) -> Data {
+    let mut data = Data::default();
+
+    data.insert(Viewer { user_id });
+    data.insert(DataLoader::new(RunnerLoader { pool: pool.clone(), experiment_server }, actix_web::rt::spawn));
+    data.insert(DataLoader::new(LatestJobsLoader { pool: pool.clone() }, actix_web::rt::spawn));
+    data.insert(DataLoader::new(JobLogsLoader { pool }, actix_web::rt::spawn));
+
+    data
+}
+
+async fn query<F, T>(pool: &DBPool, f: F) -> Result<T>
+    where F: FnOnce(&PgConnection) -> QueryResult<T> + Send + 'static,
+          T: Send + 'static {
+    let conn = pool.get().unwrap();
+
+    web::block(move || f(&conn))
+        .await
+        .map_err(|e| {
+            error!("graphql query is failed: {:?}", e);
+            Error::new("unknown_error")
+        })
+}
+
+/// Resources are identified by their public ids, the ones that are not uuids cannot match any of them.
+fn parse_id(id: &ID) -> Result<Uuid> {
+    Uuid::parse_str(id.as_str()).map_err(|_| Error::new("item_not_found"))
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Experiment", complex)]
+pub struct ExperimentObject {
+    id: ID,
+    name: String,
+    code: String,
+    firmware_id: Option<ModelId>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+    starred: bool,
+    description: String,
+    archived_at: Option<NaiveDateTime>,
+    slug: String,
+}
+
+impl From<Experiment> for ExperimentObject {
+    fn from(experiment: Experiment) -> Self {
+        ExperimentObject {
+            id: experiment.uuid.into(),
+            name: experiment.name,
+            code: experiment.code,
+            firmware_id: experiment.firmware_id,
+            created_at: experiment.created_at,
+            updated_at: experiment.updated_at,
+            starred: experiment.starred,
+            description: experiment.description,
+            archived_at: experiment.archived_at,
+            slug: experiment.slug,
+        }
+    }
+}
+
+#[ComplexObject]
+impl ExperimentObject {
+    // Latest jobs of the experiment, newest first
+    #[graphql(complexity = "limit.unwrap_or(DEFAULT_LATEST_JOBS).clamp(0, MAX_LATEST_JOBS) as usize * child_complexity")]
+    async fn jobs(&self, ctx: &Context<'_>, limit: Option<i32>) -> Result<Vec<JobObject>> {
+        let limit = limit.unwrap_or(DEFAULT_LATEST_JOBS).clamp(0, MAX_LATEST_JOBS);
+
+        let jobs = ctx.data::<DataLoader<LatestJobsLoader>>()?
+            .load_one((parse_id(&self.id)?, limit))
+            .await?
+            .unwrap_or_default();
+
+        Ok(jobs)
+    }
+}
+
+#[derive(Clone, SimpleObject)]
+#[graphql(name = "Job", complex)]
+pub struct JobObject {
+    id: ID,
+    experiment_id: ID,
+    runner_id: Option<ID>,
+    status: String,
+    failure_reason: Option<String>,
+    created_at: NaiveDateTime,
+    started_at: Option<NaiveDateTime>,
+    finished_at: Option<NaiveDateTime>,
+}
+
+impl From<SlimJob> for JobObject {
+    fn from(job: SlimJob) -> Self {
+        JobObject {
+            id: job.id.into(),
+            experiment_id: job.experiment_id.into(),
+            runner_id: job.runner_id.map(Into::into),
+            status: job.status.value(),
+            failure_reason: job.failure_reason.map(|reason| reason.value()),
+            created_at: job.created_at,
+            started_at: job.started_at,
+            finished_at: job.finished_at,
+        }
+    }
+}
+
+#[ComplexObject]
+impl JobObject {
+    async fn runner(&self, ctx: &Context<'_>) -> Result<Option<RunnerObject>> {
+        let runner_id = match &self.runner_id {
+            Some(runner_id) => parse_id(runner_id)?,
+            None => return Ok(None)
+        };
+
+        ctx.data::<DataLoader<RunnerLoader>>()?.load_one(runner_id).await
+    }
+
+    // Output and the other streams of the job, empty until the job finishes
+    async fn logs(&self, ctx: &Context<'_>) -> Result<JobLogs> {
+        ctx.data::<DataLoader<JobLogsLoader>>()?
+            .load_one(parse_id(&self.id)?)
+            .await?
+            .ok_or_else(|| Error::new("item_not_found"))
+    }
+}
+
+#[derive(Clone, SimpleObject)]
+#[graphql(name = "Runner")]
+pub struct RunnerObject {
+    id: ID,
+    name: String,
+    labels: Vec<String>,
+    capabilities: Vec<String>,
+    online: bool,
+    disabled: bool,
+    os: Option<String>,
+    arch: Option<String>,
+    last_seen_at: Option<NaiveDateTime>,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Clone, SimpleObject)]
+pub struct JobLogs {
+    output: String,
+    truncated: bool,
+    streams: Vec<JobLogStream>,
+}
+
+#[derive(Clone, SimpleObject)]
+pub struct JobLogStream {
+    name: String,
+    output: String,
+    truncated: bool,
+}
+
+#[derive(SimpleObject)]
+pub struct JobStatusEvent {
+    job_id: ID,
+    experiment_id: ID,
+    status: String,
+    failure_reason: Option<String>,
+}
+
+async fn job_logs(job_id: Uuid, pool: &DBPool) -> Result<JobLogs> {
+    let ((output, truncated), streams) = query(pool, move |conn| {
+        let (job_id, output, truncated) = jobs::table
+            .filter(jobs::uuid.eq(job_id))
+            .select((jobs::id, jobs::output, jobs::output_truncated))
+            .first::<(JobId, String, bool)>(conn)?;
+
+        let streams = job_streams::table
+            .filter(job_streams::job_id.eq(job_id))
+            .order(job_streams::name.asc())
+            .load::<JobStream>(conn)?;
+
+        Ok(((output, truncated), streams))
+    })
+        .await?;
+
+    Ok(JobLogs {
+        output,
+        truncated,
+        streams: streams.into_iter()
+            .map(|stream| JobLogStream { name: stream.name, output: stream.output, truncated: stream.truncated })
+            .collect(),
+    })
+}
+
+pub struct RunnerLoader {
+    pool: DBPool,
+    experiment_server: Addr<ExperimentServer>,
+}
+
+impl Loader<Uuid> for RunnerLoader {
+    type Value = RunnerObject;
+    type Error = Error;
+
+    async fn load(&self, keys: &[Uuid]) -> Result<HashMap<Uuid, RunnerObject>> {
+        let connected_runners = self.experiment_server.send(FetchConnectedRunnersMessage)
+            .await
+            .map_err(|e| {
+                error!("Error while fetching connected runners from ExperimentServer: {:?}", e);
+                Error::new("unknown_error")
+            })?;
+
+        let keys = keys.to_vec();
+
+        let runners = query(&self.pool, move |conn| runners::table
+            .filter(runners::uuid.eq_any(keys))
+            .select((
+                runners::id,
+                runners::uuid,
+                runners::name,
+                runners::labels,
+                runners::capabilities,
+                runners::disabled,
+                runners::os,
+                runners::arch,
+                runners::last_seen_at,
+                runners::created_at,
+            ))
+            .load::<(RunnerId, Uuid, String, Vec<String>, Vec<String>, bool, Option<String>, Option<String>, Option<NaiveDateTime>, NaiveDateTime)>(conn)
+        )
+            .await?;
+
+        Ok(runners.into_iter()
+            .map(|(id, uuid, name, labels, capabilities, disabled, os, arch, last_seen_at, created_at)| (uuid, RunnerObject {
+                id: uuid.into(),
+                name,
+                labels,
+                capabilities,
+                online: connected_runners.contains(&id),
+                disabled,
+                os,
+                arch,
+                last_seen_at,
+                created_at,
+            }))
+            .collect())
+    }
+}
+
+/// Loads the latest jobs of the experiments, keyed by the experiment and the number of jobs.
+/// Experiments are checked by their parents, jobs are not filtered by the user here.
+pub struct LatestJobsLoader {
+    pool: DBPool,
+}
+
+impl Loader<(Uuid, i32)> for LatestJobsLoader {
+    type Value = Vec<JobObject>;
+    type Error = Error;
+
+    async fn load(&self, keys: &[(Uuid, i32)]) -> Result<HashMap<(Uuid, i32), Vec<JobObject>>> {
+        let experiment_ids = keys.iter().map(|(experiment_id, _)| *experiment_id).collect::<Vec<Uuid>>();
+        let limit = keys.iter().map(|(_, limit)| *limit).max().unwrap_or(0);
+
+        let jobs = query(&self.pool, move |conn| jobs::table
+            .inner_join(experiments::table)
+            .left_join(runners::table)
+            .filter(experiments::uuid.eq_any(experiment_ids))
+            // only the latest jobs of each experiment are loaded
+            .filter(sql::<Bool>(format!(
+                "jobs.id = ANY(ARRAY(SELECT latest.id FROM jobs latest WHERE latest.experiment_id = jobs.experiment_id \
+                 ORDER BY latest.created_at DESC, latest.id DESC LIMIT {}))", limit
+            ).as_str()))
+            .order((jobs::created_at.desc(), jobs::id.desc()))
+            .select(slim_job_columns())
+            .load::<SlimJob>(conn)
+        )
+            .await?;
+
+        let jobs = jobs.into_iter()
+            .map(|job| (job.experiment_id, JobObject::from(job)))
+            .collect::<Vec<(Uuid, JobObject)>>();
+
+        Ok(keys.iter()
+            .map(|(experiment_id, limit)| {
+                let latest = jobs.iter()
+                    .filter(|(job_experiment_id, _)| job_experiment_id == experiment_id)
+                    .take(*limit as usize)
+                    .map(|(_, job)| job.clone())
+                    .collect();
+
+                ((*experiment_id, *limit), latest)
+            })
+            .collect())
+    }
+}
+
+/// Loads the output and the other streams of the jobs, keyed by the job. Jobs are checked by their parents,
+/// they are not filtered by the user here.
+pub struct JobLogsLoader {
+    pool: DBPool,
+}
+
+impl Loader<Uuid> for JobLogsLoader {
+    type Value = JobLogs;
+    type Error = Error;
+
+    async fn load(&self, keys: &[Uuid]) -> Result<HashMap<Uuid, JobLogs>> {
+        let keys = keys.to_vec();
+
+        let (outputs, streams) = query(&self.pool, move |conn| {
+            let outputs = jobs::table
+                .filter(jobs::uuid.eq_any(keys))
+                .select((jobs::id, jobs::uuid, jobs::output, jobs::output_truncated))
+                .load::<(JobId, Uuid, String, bool)>(conn)?;
+
+            let streams = job_streams::table
+                .filter(job_streams::job_id.eq_any(outputs.iter().map(|(id, ..)| *id).collect::<Vec<JobId>>()))
+                .order(job_streams::name.asc())
+                .load::<JobStream>(conn)?;
+
+            Ok((outputs, streams))
+        })
+            .await?;
+
+        let mut logs = outputs.into_iter()
+            .map(|(id, uuid, output, truncated)| (id, (uuid, JobLogs { output, truncated, streams: Vec::new() })))
+            .collect::<HashMap<JobId, (Uuid, JobLogs)>>();
+
+        for stream in streams {
+            if let Some((_, job_logs)) = logs.get_mut(&stream.job_id) {
+                job_logs.streams.push(JobLogStream { name: stream.name, output: stream.output, truncated: stream.truncated });
+            }
+        }
+
+        Ok(logs.into_values().collect())
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    // Experiments of the user, starred ones first and newest first among them. Archived ones are hidden unless
+    // they are included explicitly. Experiments shared through the projects are not listed.
+    #[graphql(complexity = "per_page.unwrap_or(10).clamp(1, MAX_PER_PAGE) as usize * child_complexity")]
+    async fn experiments(&self, ctx: &Context<'_>, page: Option<i32>, per_page: Option<i32>, include_archived: Option<bool>)
+                         -> Result<Vec<ExperimentObject>> {
+        let user_id = ctx.data::<Viewer>()?.user_id;
+        let per_page = per_page.unwrap_or(10).clamp(1, MAX_PER_PAGE) as i64;
+        let offset = (page.unwrap_or(1).max(1) - 1) as i64 * per_page;
+
+        let experiments = query(ctx.data::<DBPool>()?, move |conn| {
+            let mut query = experiments::table
+                .filter(can_view_experiment(user_id))
+                .filter(owned_by(user_id))
+                .into_boxed();
+
+            if !include_archived.unwrap_or(false) {
+                query = query.filter(experiments::archived_at.is_null());
+            }
+
+            query
+                .order((experiments::starred.desc(), experiments::created_at.desc()))
+                .limit(per_page)
+                .offset(offset)
+                .load::<Experiment>(conn)
+        })
+            .await?;
+
+        Ok(experiments.into_iter().map(ExperimentObject::from).collect())
+    }
+
+    async fn experiment(&self, ctx: &Context<'_>, id: ID) -> Result<Option<ExperimentObject>> {
+        let user_id = ctx.data::<Viewer>()?.user_id;
+        let id = parse_id(&id)?;
+
+        let experiment = query(ctx.data::<DBPool>()?, move |conn| experiments::table
+            .filter(can_view_experiment(user_id))
+            .filter(experiments::uuid.eq(id))
+            .first::<Experiment>(conn)
+            .optional()
+        )
+            .await?;
+
+        Ok(experiment.map(ExperimentObject::from))
+    }
+
+    async fn job(&self, ctx: &Context<'_>, id: ID) -> Result<Option<JobObject>> {
+        let user_id = ctx.data::<Viewer>()?.user_id;
+        let id = parse_id(&id)?;
+
+        let job = query(ctx.data::<DBPool>()?, move |conn| jobs::table
+            .inner_join(experiments::table)
+            .left_join(runners::table)
+            .filter(can_view_experiment(user_id))
+            .filter(jobs::uuid.eq(id))
+            .select(slim_job_columns())
+            .first::<SlimJob>(conn)
+            .optional()
+        )
+            .await?;
+
+        Ok(job.map(JobObject::from))
+    }
+
+    async fn runners(&self, ctx: &Context<'_>) -> Result<Vec<RunnerObject>> {
+        let runner_ids = query(ctx.data::<DBPool>()?, |conn| runners::table
+            .order(runners::id.asc())
+            .select(runners::uuid)
+            .load::<Uuid>(conn)
+        )
+            .await?;
+
+        let mut runners = ctx.data::<DataLoader<RunnerLoader>>()?
+            .load_many(runner_ids.clone())
+            .await?;
+
+        // runners are listed in the order they are created
+        Ok(runner_ids.iter()
+            .filter_map(|runner_id| runners.remove(runner_id))
+            .collect())
+    }
+
+    async fn runner(&self, ctx: &Context<'_>, id: ID) -> Result<Option<RunnerObject>> {
+        ctx.data::<DataLoader<RunnerLoader>>()?.load_one(parse_id(&id)?).await
+    }
+}
+
+pub struct SubscriptionRoot;
+
+async fn subscribe(ctx: &Context<'_>) -> Result<mpsc::UnboundedReceiver<Notification>> {
+    let (sender, receiver) = mpsc::unbounded();
+
+    ctx.data::<Addr<ExperimentServer>>()?
+        .send(SubscribeNotificationsMessage { user_id: ctx.data::<Viewer>()?.user_id, sender })
+        .await
+        .map_err(|e| {
+            error!("Error while subscribing to ExperimentServer: {:?}", e);
+            Error::new("unknown_error")
+        })?;
+
+    Ok(receiver)
+}
+
+#[Subscription]
+impl SubscriptionRoot {
+    // Status changes of the jobs of the user, only of the given experiment if it is given
+    async fn job_status(&self, ctx: &Context<'_>, experiment_id: Option<ID>) -> Result<impl Stream<Item = JobStatusEvent>> {
+        let experiment_id = experiment_id.as_ref().map(parse_id).transpose()?;
+        let notifications = subscribe(ctx).await?;
+
+        Ok(notifications.filter_map(move |notification| future::ready(match notification {
+            Notification::JobStatus(status) if experiment_id.is_none() || experiment_id == Some(status.experiment_id) => Some(JobStatusEvent {
+                job_id: status.job_id.into(),
+                experiment_id: status.experiment_id.into(),
+                status: status.status.value(),
+                failure_reason: status.failure_reason.map(|reason| reason.value()),
+            }),
+            _ => None
+        })))
+    }
+
+    // Logs of the job. Runners report the output along with the result, so the logs are pushed once
+    // the job finishes, right away if it has already finished.
+    async fn job_logs(&self, ctx: &Context<'_>, job_id: ID) -> Result<impl Stream<Item = JobLogs>> {
+        let user_id = ctx.data::<Viewer>()?.user_id;
+        let job_id = parse_id(&job_id)?;
+        let pool = ctx.data::<DBPool>()?.clone();
+
+        // subscribed before checking the status, so that the finish of the job is not missed in between
+        let notifications = subscribe(ctx).await?;
+
+        let status = query(&pool, move |conn| jobs::table
+            .inner_join(experiments::table)
+            .filter(can_view_experiment(user_id))
+            .filter(jobs::uuid.eq(job_id))
+            .select(jobs::status)
+            .first::<JobStatus>(conn)
+            .optional()
+        )
+            .await?
+            .ok_or_else(|| Error::new("item_not_found"))?;
+
+        let finished = notifications
+            .filter(move |notification| future::ready(match notification {
+                Notification::JobStatus(status) => status.job_id == job_id && status.status.is_terminal(),
+                _ => false
+            }))
+            .map(|_| ());
+
+        let finished = if status.is_terminal() {
+            futures::stream::once(future::ready(())).boxed()
+        } else {
+            finished.boxed()
+        };
+
+        Ok(finished
+            .take(1)
+            .then(move |_| {
+                let pool = pool.clone();
+                async move { job_logs(job_id, &pool).await }
+            })
+            .filter_map(|logs| future::ready(logs.ok())))
+    }
+}
@@ -1,75 +1,313 @@
+use std::sync::Arc;
+
 use actix::Addr;
+use actix_http::ws::Codec;
+use actix::clock::{delay_for, Duration};
 use actix_web::{delete, get, HttpRequest, HttpResponse, post, put, web};
-use actix_web_actors::ws;
+use actix_web::dev::{Body, BodyEncoding, SizedStream};
+use actix_web::http::{ContentEncoding, header};
+use actix_web_actors::ws::{self, WebsocketContext};
+use async_graphql::http::WebSocketProtocols;
+use chrono::NaiveDateTime;
+use diesel::dsl::{exists, now, sql};
+use diesel::pg::expression::extensions::IntervalDsl;
 use diesel::prelude::*;
-use log::error;
+use diesel::result::DatabaseErrorKind;
+use diesel::sql_types::{BigInt, Double, Nullable};
+use futures::channel::mpsc;
+use futures::future::{self, Either};
+use futures::StreamExt;
+use log::{error, warn};
+use uuid::Uuid;
 
+use core::Config;
 use core::db::DieselEnum;
 use core::error::ErrorMessaging;
 use core::ErrorMessage;
-use core::models::paginate::{CountStarOver, Paginate, PaginationRequest};
-use core::responses::{SuccessResponse, TokenResponse};
+use core::models::audit_log::{AuditEntry, AuditLog};
+use core::models::paginate::{CountStarOver, Paginate, Pagination, PaginationRequest};
+use core::responses::{BulkItemResult, BulkResponse, SuccessResponse, TokenResponse};
 use core::sanitized::SanitizedJson;
-use core::schema::{experiments, jobs, runners};
-use core::types::{DBPool, DefaultResponse, ModelId};
+use core::schema::{all_job_streams, all_jobs, archived_jobs, audit_logs, client_releases, experiment_activities, experiment_cost_centers, experiment_job_stats, experiment_sdr_settings, experiments, firmwares, job_artifacts, job_batches, job_costs, jobs, monthly_costs, project_members, projects, runner_client_logs, runner_commands, runner_job_stats, runners, sessions, users};
+use core::types::{DBPool, DefaultResponse, ExperimentId, JobId, ModelId, RunnerId, UserId};
 use core::utils::Hash;
-use user::models::user::User;
+use shared::websocket_messages::client;
+use shared::websocket_messages::server::DiagnosticLevel;
+use user::models::user::{User, UserStatus};
 
+use crate::authorization::{can_admin_jobs, can_admin_projects, can_admin_runner, can_edit_experiment, can_manage_project, can_run, can_view_accounting, can_view_audit_logs, can_view_experiment, can_view_project, can_view_runner_logs, can_view_stats, can_write, owned_by};
+use crate::certificate::normalize_fingerprint;
+use crate::claim::{self, ClaimCode, ProvisionedRunner};
+use crate::connection::admission::{admit, Credential, identify};
+use crate::connection::cleaner;
+use crate::connection::graphql_session::GraphqlSession;
+use crate::connection::limits::{LimitMetricsSnapshot, SessionLimits};
+use crate::connection::messages::{BumpPendingRunsMessage, CheckClientUpdateMessage, FetchConnectedRunnersMessage, RemoveRunnerMessage, RunnerCommandMessage, RunnerLogLevelMessage, RunnerValidationMessage, SetRunnerDisabledMessage, SubscribeNotificationsMessage};
 use crate::connection::server::{ExperimentServer, RunExperimentMessage};
-use crate::connection::session::Session;
-use crate::models::experiment::{Experiment, SLIM_EXPERIMENT_COLUMNS, SlimExperiment};
-use crate::models::job::{Job, JobStatus};
-use crate::models::runner::{Runner, RunnerToken};
-use crate::requests::{ExperimentCodeRequest, ExperimentNameRequest};
+use crate::connection::session::{MAX_LOG_SHIPPING, Session};
+use crate::connection::user_session::UserSession;
+use crate::connection::write_buffer::TrackedStream;
+use crate::criteria::Criteria;
+use crate::download::{Download, SignedUrl};
+use crate::graphql::{self, TestbedSchema};
+use crate::idempotency::{self, IDEMPOTENT_REPLAYED_HEADER};
+use crate::ErrorMessage as ExperimentErrorMessage;
+use crate::logs::output_stream;
+use crate::models::accounting::{self, JOB_COST_COLUMNS, JobCost, MONTHLY_COST_COLUMNS, MonthlyCost};
+use crate::models::activity::{Activity, activity_columns, ActivityEntry, ActivityKind};
+use crate::models::artifact::{self, ByteRange, RangeRequest};
+use crate::models::batch::{BatchSummary, JOB_BATCH_COLUMNS, JobBatch};
+use crate::models::code;
+use crate::models::command::{CommandStatus, RUNNER_COMMAND_COLUMNS, RunnerCommand};
+use crate::models::environment;
+use crate::markdown;
+use crate::models::export;
+use crate::models::experiment::{Experiment, ExperimentValidation, RenderedDescription, SLIM_EXPERIMENT_COLUMNS, SlimExperiment, slugify};
+use crate::models::firmware::{Firmware, FIRMWARE_COLUMNS};
+use crate::models::job::{all_slim_job_columns, AnsiMode, Job, JobDetail, JobStatus, JobStream, PublicJob, slim_job_columns, SlimJob, TransitionError};
+use crate::models::project::{self, Project, PROJECT_COLUMNS, ProjectDetail};
+use crate::models::release::ClientRelease;
+use crate::models::runner::{ConnectTicket, Runner, RunnerClientLog, RunnerDetail, RunnerStats, RunnerStatus, RunnerToken, SLIM_RUNNER_COLUMNS, SlimRunner};
+use crate::models::stats::{AdminStats, busy_seconds_since, EXPERIMENT_JOB_STATS_COLUMNS, JobStats, RUNNER_JOB_STATS_COLUMNS, UserStats};
+use crate::notifications::Notification;
+use crate::policy::{parse_network, RunnerPolicy};
+use crate::queue::{self, QueueInfo, RunnerQueueEntry};
+use crate::requests::{AdminStatsRequest, AuditLogsRequest, BulkCancelJobsRequest, BulkDeleteExperimentsRequest, ClaimCodeRequest, ClaimRunnerRequest, ClientReleaseRequest, DownloadRequest, ExperimentCodeRequest, ExperimentCostCenterRequest, ExperimentDescriptionRequest, ExperimentNameRequest, ExperimentNetworkEmulationRequest, ExperimentPerformanceRequest, ExperimentProjectRequest, ExperimentRetentionRequest, ExperimentRunnerStrategyRequest, ExperimentSdrRequest, ExperimentsRequest, ExperimentSuccessCriteriaRequest, ExperimentThermalGuardRequest, ExperimentWarmupRunsRequest, FirmwareRequest, JobAnnotationRequest, JobCostsRequest, JobOutputRequest, JobProtectedRequest, JobSort, JobsRequest, JobWaitRequest, JoinServerRequest, MonthlyCostsRequest, ProjectDefaultLabelsRequest, ProjectMemberRequest, ProjectNameRequest, ProjectQuotaRequest, ProvisionRunnerRequest, PurgeJobsRequest, RequeueJobRequest, RunBatchRequest, RunExperimentPath, RunExperimentRequest, RunnerAllowedNetworksRequest, RunnerAutoUpdateRequest, RunnerCertificateRequest, RunnerCommandRequest, RunnerDetailRequest, RunnerDisabledRequest, RunnerLabelsRequest, RunnerLogLevelRequest, RunnerNameRequest, RunnerQueueReorderRequest, SortOrder, StuckJobsRequest, UserStatsRequest, ValidateExperimentRequest};
 
+/// Runners authenticate with a token given in the `Authorization` header, with a ticket minted by
+/// `runner/ticket` or with a client certificate pinned to them. Token in the query string is still accepted
+/// for the older clients, though it ends up in the logs of the proxies.
+#[utoipa::path(
+    get,
+    path = "/ws",
+    tag = "runner-client",
+    params(JoinServerRequest),
+    responses((status = 101, description = "Websocket connection of the runner")),
+)]
 #[get("ws")]
 pub async fn join_server(
     pool: web::Data<DBPool>,
     hash: web::Data<Hash>,
     experiment_server: web::Data<Addr<ExperimentServer>>,
+    policy: web::Data<RunnerPolicy>,
+    limits: web::Data<SessionLimits>,
     req: HttpRequest,
     stream: web::Payload,
-    token: web::Query<TokenResponse>,
+    request: web::Query<JoinServerRequest>,
 ) -> DefaultResponse {
-    let conn = pool.get().unwrap();
+    let credential = match (bearer_token(&req), request.ticket.as_deref(), request.token.as_deref()) {
+        (Some(token), _, _) => Some(Credential::Token(token)),
+        (None, Some(ticket), _) => Some(Credential::Ticket(ticket)),
+        (None, None, Some(token)) => {
+            warn!("runner is connecting with the token in the query string, which is deprecated");
+            Some(Credential::Token(token))
+        }
+        (None, None, None) => None
+    };
 
-    let token = hash.decode::<RunnerToken>(token.token.as_str())
-        .map_err(|_| ErrorMessage::InvalidToken)?;
-
-    let runner = web::block(move || runners::table
-        .filter(runners::access_key.eq(token.access_key))
-        .first::<Runner>(&conn)
+    let admission = admit(
+        pool.get_ref(),
+        hash.get_ref(),
+        policy.get_ref(),
+        credential,
+        policy.client_certificate(&req),
+        policy.remote_addr(&req),
     )
         .await?;
 
-    ws::start(Session::new(experiment_server.get_ref().clone(), runner.id), &req, stream)
+    let session = Session::new(
+        experiment_server.get_ref().clone(),
+        pool.get_ref().clone(),
+        hash.get_ref().clone(),
+        admission.runner.id,
+        admission.slot,
+        admission.token,
+        limits.get_ref().clone(),
+    )
+        .refresh_token_on_join(admission.legacy_token);
+
+    let write_buffer = session.write_buffer().clone();
+    let codec = Codec::new().max_size(limits.max_frame_size());
+
+    // Same as ws::start, except the size of the messages waiting to be sent is tracked and the size of the received frames is limited
+    let mut response = ws::handshake(&req)
+        .map_err(|_| Box::new(ErrorMessage::WebSocketConnectionError) as Box<dyn ErrorMessaging>)?;
+
+    Ok(response.streaming(TrackedStream::new(Box::pin(WebsocketContext::with_codec(session, stream, codec)), write_buffer)))
+}
+
+/// Token of the runner given in the `Authorization` header
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Websocket connection of users, used for notifying them about their jobs.
+#[utoipa::path(
+    get,
+    path = "/notifications",
+    tag = "notifications",
+    responses((status = 101, description = "Websocket connection notifying the user about the jobs")),
+    security(("bearer" = [])),
+)]
+#[get("notifications")]
+pub async fn join_user_server(
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    req: HttpRequest,
+    stream: web::Payload,
+    user: User,
+) -> DefaultResponse {
+    ws::start(UserSession::new(experiment_server.get_ref().clone(), user.id), &req, stream)
         .map_err(|_| Box::new(ErrorMessage::WebSocketConnectionError) as Box<dyn ErrorMessaging>)
 }
 
+/// Queries of the user, e.g. an experiment along with its latest jobs and their runners.
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    tag = "graphql",
+    request_body = Object,
+    responses((status = 200, body = Object)),
+    security(("bearer" = [])),
+)]
+#[post("graphql")]
+pub async fn execute_graphql(
+    schema: web::Data<TestbedSchema>,
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    user: User,
+    request: web::Json<async_graphql::Request>,
+) -> DefaultResponse {
+    let data = graphql::request_data(user.id, pool.get_ref().clone(), experiment_server.get_ref().clone());
+
+    let response = schema.execute(request.into_inner().data(data)).await;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Graphql subscriptions of the user, over either of the graphql websocket protocols.
+#[utoipa::path(
+    get,
+    path = "/graphql/ws",
+    tag = "graphql",
+    responses((status = 101, description = "Websocket connection running the graphql subscriptions")),
+    security(("bearer" = [])),
+)]
+#[get("graphql/ws")]
+pub async fn join_graphql_server(
+    schema: web::Data<TestbedSchema>,
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    req: HttpRequest,
+    stream: web::Payload,
+    user: User,
+) -> DefaultResponse {
+    let protocol = req.headers()
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|protocols| protocols.to_str().ok())
+        .and_then(|protocols| protocols.split(',').find_map(|protocol| protocol.trim().parse::<WebSocketProtocols>().ok()))
+        .ok_or_else(|| Box::new(ErrorMessage::WebSocketConnectionError) as Box<dyn ErrorMessaging>)?;
+
+    let data = graphql::request_data(user.id, pool.get_ref().clone(), experiment_server.get_ref().clone());
+    let session = GraphqlSession::new(schema.get_ref().clone(), protocol, user.id, data);
+
+    let mut response = ws::handshake_with_protocols(&req, &[protocol.sec_websocket_protocol()])
+        .map_err(|_| Box::new(ErrorMessage::WebSocketConnectionError) as Box<dyn ErrorMessaging>)?;
+
+    Ok(response.streaming(WebsocketContext::create(session, stream)))
+}
+
+/// Starred experiments are listed first, newest first among them. Archived experiments are not listed unless
+/// `include_archived` is given. Experiments of the other members of the user's projects are listed by
+/// `project/{id}/experiments`, or here along with the user's own ones if `include_shared` is given.
+#[utoipa::path(
+    get,
+    path = "/experiments",
+    tag = "experiments",
+    params(PaginationRequest, ExperimentsRequest),
+    responses((status = 200, body = Pagination<SlimExperiment>)),
+    security(("bearer" = [])),
+)]
 #[get("experiments")]
-pub async fn fetch_experiments(pool: web::Data<DBPool>, user: User, pagination: web::Query<PaginationRequest>) -> DefaultResponse {
+pub async fn fetch_experiments(
+    pool: web::Data<DBPool>,
+    user: User,
+    pagination: web::Query<PaginationRequest>,
+    request: web::Query<ExperimentsRequest>,
+    req: HttpRequest,
+) -> DefaultResponse {
     let conn = pool.get().unwrap();
 
-    let experiments = web::block(move || experiments::table
-        .filter(experiments::user_id.eq(user.id))
-        .order(experiments::created_at.desc())
-        .select((SLIM_EXPERIMENT_COLUMNS, CountStarOver))
-        .paginate(pagination.page)
-        .per_page(pagination.per_page)
-        .load_and_count_pages::<SlimExperiment>(&conn)
-    )
+    let experiments = web::block(move || {
+        let mut query = experiments::table
+            .filter(can_view_experiment(user.id))
+            .into_boxed();
+
+        if !request.include_shared.unwrap_or(false) {
+            query = query.filter(owned_by(user.id));
+        }
+
+        if let Some(starred) = request.starred {
+            query = query.filter(experiments::starred.eq(starred));
+        }
+
+        if !request.include_archived.unwrap_or(false) {
+            query = query.filter(experiments::archived_at.is_null());
+        }
+
+        query
+            .order((experiments::starred.desc(), experiments::created_at.desc()))
+            .select((SLIM_EXPERIMENT_COLUMNS, CountStarOver))
+            .paginate(pagination.page)
+            .per_page(pagination.per_page)
+            .load_and_count_pages::<SlimExperiment>(&conn)
+    })
         .await?;
 
-    Ok(HttpResponse::Ok().json(experiments))
+    Ok(HttpResponse::Ok().json(experiments.with_links(&req)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/experiment/{id}",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = Experiment)),
+    security(("bearer" = [])),
+)]
 #[get("experiment/{id}")]
-pub async fn fetch_experiment(pool: web::Data<DBPool>, experiment_id: web::Path<ModelId>, user: User) -> DefaultResponse {
+pub async fn fetch_experiment(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let experiment = web::block(move || experiments::table
+        .filter(can_view_experiment(user.id))
+        .filter(experiments::uuid.eq(experiment_id.into_inner()))
+        .first::<Experiment>(&conn)
+    )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(experiment))
+}
+
+/// Experiments can be addressed by their slugs as well as their ids, slugs are only unique per user
+#[utoipa::path(
+    get,
+    path = "/experiment/by-slug/{slug}",
+    tag = "experiments",
+    params(("slug" = String, Path)),
+    responses((status = 200, body = Experiment)),
+    security(("bearer" = [])),
+)]
+#[get("experiment/by-slug/{slug}")]
+pub async fn fetch_experiment_by_slug(pool: web::Data<DBPool>, slug: web::Path<String>, user: User) -> DefaultResponse {
     let conn = pool.get().unwrap();
 
     let experiment = web::block(move || experiments::table
-        .filter(experiments::user_id.eq(user.id))
-        .find(experiment_id.into_inner())
+        .filter(can_view_experiment(user.id))
+        .filter(owned_by(user.id))
+        .filter(experiments::slug.eq(slug.into_inner()))
         .first::<Experiment>(&conn)
     )
         .await?;
@@ -77,116 +315,4466 @@ pub async fn fetch_experiment(pool: web::Data<DBPool>, experiment_id: web::Path<
     Ok(HttpResponse::Ok().json(experiment))
 }
 
+/// Returns the rolling job statistics of the experiment, null if none of its jobs has finished recently.
+#[utoipa::path(
+    get,
+    path = "/experiment/{id}/job-stats",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = Option<JobStats>)),
+    security(("bearer" = [])),
+)]
+#[get("experiment/{id}/job-stats")]
+pub async fn fetch_experiment_job_stats(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let stats = web::block(move || -> Result<_, diesel::result::Error> {
+        let experiment_id = experiments::table
+            .filter(can_view_experiment(user.id))
+            .filter(experiments::uuid.eq(experiment_id.into_inner()))
+            .select(experiments::id)
+            .first::<ExperimentId>(&conn)?;
+
+        experiment_job_stats::table
+            .find(experiment_id)
+            .select(EXPERIMENT_JOB_STATS_COLUMNS)
+            .first::<JobStats>(&conn)
+            .optional()
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+/// History of the experiment, most recent event first. Changes are recorded along with the user making them,
+/// events of the runs are recorded as the jobs are started and finished.
+#[utoipa::path(
+    get,
+    path = "/experiment/{id}/activity",
+    tag = "experiments",
+    params(("id" = Uuid, Path), PaginationRequest),
+    responses((status = 200, body = Pagination<Activity>)),
+    security(("bearer" = [])),
+)]
+#[get("experiment/{id}/activity")]
+pub async fn fetch_experiment_activity(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    pagination: web::Query<PaginationRequest>,
+    req: HttpRequest,
+) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let activities = web::block(move || -> Result<_, diesel::result::Error> {
+        let experiment_id = experiments::table
+            .filter(can_view_experiment(user.id))
+            .filter(experiments::uuid.eq(experiment_id.into_inner()))
+            .select(experiments::id)
+            .first::<ExperimentId>(&conn)?;
+
+        experiment_activities::table
+            .left_join(jobs::table)
+            .filter(experiment_activities::experiment_id.eq(experiment_id))
+            .order(experiment_activities::id.desc())
+            .select((activity_columns(), CountStarOver))
+            .paginate(pagination.page)
+            .per_page(pagination.per_page)
+            .load_and_count_pages::<Activity>(&conn)
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(activities.with_links(&req)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/experiment",
+    tag = "experiments",
+    request_body = ExperimentNameRequest,
+    responses((status = 200, body = Experiment)),
+    security(("bearer" = [])),
+)]
 #[post("experiment")]
 pub async fn create_new_experiment(pool: web::Data<DBPool>, user: User, request: SanitizedJson<ExperimentNameRequest>) -> DefaultResponse {
+    if !can_write(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
     let conn = pool.get().unwrap();
     let request = request.into_inner();
+    let slug = slugify(request.name.as_str());
 
     let experiment = web::block(move || diesel::insert_into(experiments::table)
         .values(
-            (experiments::user_id.eq(user.id), experiments::name.eq(request.name))
+            (experiments::user_id.eq(user.id), experiments::name.eq(request.name), experiments::slug.eq(slug))
         )
         .get_result::<Experiment>(&conn)
+        .map_err(experiment_name_conflict)
     )
         .await?;
 
     Ok(HttpResponse::Ok().json(experiment))
 }
 
-/// This will return a SuccessResponse even though update may not occur if experiment's user id is not
-/// equal to user.id. Update endpoints will generally behave like this.
+/// Slug of the experiment follows its name, so renaming the experiment fails with 409 if another experiment of
+/// the user has a name resulting in the same slug. Fails with 404 if the experiment does not exist, and with 403
+/// if the user may not modify it. Update endpoints of the experiments behave like this.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentNameRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
 #[put("experiment/{id}")]
-pub async fn update_experiment_name(pool: web::Data<DBPool>, experiment_id: web::Path<ModelId>, user: User, request: SanitizedJson<ExperimentNameRequest>)
+pub async fn update_experiment_name(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User, request: SanitizedJson<ExperimentNameRequest>)
                                     -> DefaultResponse {
     let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+    let name = request.into_inner().name;
+    let slug = slugify(name.as_str());
 
-    web::block(move ||
-        diesel::update(
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let updated = diesel::update(
             experiments::table
-                .filter(experiments::user_id.eq(user.id))
-                .find(experiment_id.into_inner())
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
         )
-            .set(experiments::name.eq(request.into_inner().name))
-            .execute(&conn)
-    )
+            .set((experiments::name.eq(name), experiments::slug.eq(slug)))
+            .returning(experiments::id)
+            .get_result::<ExperimentId>(&conn)
+            .optional()
+            .map_err(experiment_name_conflict)?;
+
+        match updated {
+            Some(id) => ActivityEntry::new(id, Some(user.id), ActivityKind::NameUpdated).record(&conn)?,
+            None => return Err(experiment_not_affected(experiment_id, &conn))
+        }
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Starred experiments are listed first, starring does not change the experiment itself. Experiments shared through
+/// a project are starred only by their owners, since the flag is kept on the experiment.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/star",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/star")]
+pub async fn star_experiment(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    update_experiment_starred(pool, experiment_id.into_inner(), user, true).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/experiment/{id}/star",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[delete("experiment/{id}/star")]
+pub async fn unstar_experiment(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    update_experiment_starred(pool, experiment_id.into_inner(), user, false).await
+}
+
+/// Archived experiments are hidden from the listing and can not be run until they are unarchived, their jobs
+/// are kept unlike the deleted ones. Jobs which are already queued are not cancelled.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/archive",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/archive")]
+pub async fn archive_experiment(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    update_experiment_archived(pool, experiment_id.into_inner(), user, true).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/experiment/{id}/archive",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[delete("experiment/{id}/archive")]
+pub async fn unarchive_experiment(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    update_experiment_archived(pool, experiment_id.into_inner(), user, false).await
+}
+
+async fn update_experiment_archived(pool: web::Data<DBPool>, experiment_id: Uuid, user: User, archived: bool) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let target = experiments::table
+            .filter(can_edit_experiment(user.id))
+            .filter(experiments::uuid.eq(experiment_id));
+
+        // archiving twice keeps the time it is archived first
+        let updated = if archived {
+            diesel::update(target.filter(experiments::archived_at.is_null()))
+                .set(experiments::archived_at.eq(now.nullable()))
+                .returning(experiments::id)
+                .get_result::<ExperimentId>(&conn)
+        } else {
+            diesel::update(target.filter(experiments::archived_at.is_not_null()))
+                .set(experiments::archived_at.eq(None::<NaiveDateTime>))
+                .returning(experiments::id)
+                .get_result::<ExperimentId>(&conn)
+        }
+            .optional()?;
+
+        if let Some(id) = updated {
+            let kind = if archived { ActivityKind::Archived } else { ActivityKind::Unarchived };
+
+            ActivityEntry::new(id, Some(user.id), kind).record(&conn)?;
+
+            return Ok(());
+        }
+
+        // experiment is already in the requested state if the user may edit it
+        experiments::table
+            .filter(can_edit_experiment(user.id))
+            .filter(experiments::uuid.eq(experiment_id))
+            .select(experiments::id)
+            .first::<ExperimentId>(&conn)
+            .optional()?
+            .map(|_| ())
+            .ok_or_else(|| experiment_not_affected(experiment_id, &conn))
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+async fn update_experiment_starred(pool: web::Data<DBPool>, experiment_id: Uuid, user: User, starred: bool) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let updated = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set(experiments::starred.eq(starred))
+            .execute(&conn)?;
+
+        if updated == 0 {
+            return Err(experiment_not_affected(experiment_id, &conn));
+        }
+
+        Ok(())
+    })
         .await?;
 
     Ok(HttpResponse::Ok().json(SuccessResponse::default()))
 }
 
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/code",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentCodeRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
 #[put("experiment/{id}/code")]
-pub async fn update_experiment_code(pool: web::Data<DBPool>, experiment_id: web::Path<ModelId>, user: User, request: SanitizedJson<ExperimentCodeRequest>)
+pub async fn update_experiment_code(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User, request: SanitizedJson<ExperimentCodeRequest>)
                                     -> DefaultResponse {
     let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
 
-    web::block(move ||
-        diesel::update(
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let updated = diesel::update(
             experiments::table
-                .filter(experiments::user_id.eq(user.id))
-                .find(experiment_id.into_inner())
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
         )
             .set(experiments::code.eq(request.into_inner().code))
-            .execute(&conn)
+            .returning(experiments::id)
+            .get_result::<ExperimentId>(&conn)
+            .optional()?;
+
+        match updated {
+            Some(id) => ActivityEntry::new(id, Some(user.id), ActivityKind::CodeUpdated).record(&conn)?,
+            None => return Err(experiment_not_affected(experiment_id, &conn))
+        }
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+const MAX_DESCRIPTION_LENGTH: usize = 64 * 1024;
+
+/// Description is Markdown, it is stored as is and rendered by the `description/rendered` endpoint
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/description",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentDescriptionRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/description")]
+pub async fn update_experiment_description(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ExperimentDescriptionRequest>,
+) -> DefaultResponse {
+    let description = request.into_inner().description;
+
+    if description.len() > MAX_DESCRIPTION_LENGTH {
+        return Err(ExperimentErrorMessage::DescriptionTooLong.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let updated = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set(experiments::description.eq(description))
+            .returning(experiments::id)
+            .get_result::<ExperimentId>(&conn)
+            .optional()?;
+
+        match updated {
+            Some(id) => ActivityEntry::new(id, Some(user.id), ActivityKind::DescriptionUpdated).record(&conn)?,
+            None => return Err(experiment_not_affected(experiment_id, &conn))
+        }
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Description of the experiment rendered into sanitized HTML, it can be inserted into the page as is
+#[utoipa::path(
+    get,
+    path = "/experiment/{id}/description/rendered",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = RenderedDescription)),
+    security(("bearer" = [])),
+)]
+#[get("experiment/{id}/description/rendered")]
+pub async fn fetch_experiment_description(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let html = web::block(move || experiments::table
+        .filter(can_view_experiment(user.id))
+        .filter(experiments::uuid.eq(experiment_id.into_inner()))
+        .select(experiments::description)
+        .first::<String>(&conn)
+        .map(|description| markdown::render(description.as_str()))
     )
         .await?;
 
+    Ok(HttpResponse::Ok().json(RenderedDescription { html }))
+}
+
+const MAX_FIRMWARE_SIZE: usize = 8 * 1024 * 1024;
+const MAX_FIRMWARE_NAME_LENGTH: usize = 255;
+
+/// Uploads the firmware image of the experiment from the request body, it is flashed to the device
+/// before each run. Name of the image is kept since flashing tools may depend on its extension.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/firmware",
+    tag = "experiments",
+    params(("id" = Uuid, Path), FirmwareRequest),
+    request_body(content = [u8], content_type = "application/octet-stream"),
+    responses((status = 200, body = Firmware)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/firmware")]
+pub async fn update_experiment_firmware(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Query<FirmwareRequest>,
+    mut payload: web::Payload,
+) -> DefaultResponse {
+    let name = request.into_inner().name;
+
+    let valid_name = !name.is_empty() && name.len() <= MAX_FIRMWARE_NAME_LENGTH && name != "." && name != ".." &&
+        name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+
+    if !valid_name {
+        return Err(ExperimentErrorMessage::InvalidFirmware.into());
+    }
+
+    let mut data = web::BytesMut::new();
+
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|_| ExperimentErrorMessage::InvalidFirmware)?;
+
+        if data.len() + chunk.len() > MAX_FIRMWARE_SIZE {
+            return Err(ExperimentErrorMessage::FirmwareTooLarge.into());
+        }
+
+        data.extend_from_slice(&chunk);
+    }
+
+    if data.is_empty() {
+        return Err(ExperimentErrorMessage::InvalidFirmware.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    let firmware = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let experiment_id = experiments::table
+            .filter(can_edit_experiment(user.id))
+            .filter(experiments::uuid.eq(experiment_id))
+            .select(experiments::id)
+            .first::<ExperimentId>(&conn)?;
+
+        let firmware = diesel::insert_into(firmwares::table)
+            .values((
+                firmwares::user_id.eq(user.id),
+                firmwares::name.eq(name),
+                firmwares::size.eq(data.len() as i32),
+                firmwares::data.eq(data.to_vec()),
+            ))
+            .returning(FIRMWARE_COLUMNS)
+            .get_result::<Firmware>(&conn)?;
+
+        diesel::update(experiments::table.find(experiment_id))
+            .set(experiments::firmware_id.eq(firmware.id))
+            .execute(&conn)?;
+
+        ActivityEntry::new(experiment_id, Some(user.id), ActivityKind::FirmwareUpdated)
+            .details(firmware.name.clone())
+            .record(&conn)?;
+
+        Ok(firmware)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(firmware))
+}
+
+/// Experiment runs without flashing afterwards, the image is kept for the past jobs
+#[utoipa::path(
+    delete,
+    path = "/experiment/{id}/firmware",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[delete("experiment/{id}/firmware")]
+pub async fn delete_experiment_firmware(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let updated = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set(experiments::firmware_id.eq(None::<ModelId>))
+            .returning(experiments::id)
+            .get_result::<ExperimentId>(&conn)
+            .optional()?;
+
+        match updated {
+            Some(id) => ActivityEntry::new(id, Some(user.id), ActivityKind::FirmwareRemoved).record(&conn)?,
+            None => return Err(experiment_not_affected(experiment_id, &conn))
+        }
+
+        Ok(())
+    }))
+        .await?;
+
     Ok(HttpResponse::Ok().json(SuccessResponse::default()))
 }
 
+const MAX_JOB_HOOKS: usize = 8;
+const MAX_HOOK_NAME_LENGTH: usize = 32;
+
+/// Hooks are given as a comma separated list of names made of alphanumeric characters, `_` and `-`.
+/// Whether the runner knows them is only checked when the job is executed.
+fn parse_hooks(hooks: Option<&str>) -> Result<Vec<String>, ExperimentErrorMessage> {
+    let hooks = match hooks {
+        Some(hooks) => hooks.split(',')
+            .map(|hook| hook.trim())
+            .filter(|hook| !hook.is_empty())
+            .map(String::from)
+            .collect::<Vec<String>>(),
+        None => return Ok(Vec::new())
+    };
+
+    let valid = hooks.len() <= MAX_JOB_HOOKS && hooks.iter().all(|hook| hook.len() <= MAX_HOOK_NAME_LENGTH &&
+        hook.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'));
+
+    if !valid {
+        return Err(ExperimentErrorMessage::InvalidHook);
+    }
+
+    Ok(hooks)
+}
+
+/// Retried requests carrying the same `Idempotency-Key` header return the job created by the first
+/// request instead of creating a new one. Only the users with a verified email can run experiments. Runs fail with
+/// 409 if the project of the experiment has as many pending and running jobs as its quota.
+#[utoipa::path(
+    post,
+    path = "/experiment/{experiment_id}/run/{runner_id}",
+    tag = "experiments",
+    params(RunExperimentPath, ("Idempotency-Key" = Option<String>, Header), RunExperimentRequest),
+    responses((status = 200, body = PublicJob, headers(("Idempotent-Replayed" = String, description = "Set if the job is created by an earlier request with the same key")))),
+    security(("bearer" = [])),
+)]
 #[post("experiment/{experiment_id}/run/{runner_id}")]
 pub async fn run_experiment(
     pool: web::Data<DBPool>,
     experiment_server: web::Data<Addr<ExperimentServer>>,
-    ids: web::Path<(ModelId, ModelId)>,
+    path: web::Path<RunExperimentPath>,
     user: User,
+    request: web::Query<RunExperimentRequest>,
+    req: HttpRequest,
 ) -> DefaultResponse {
+    // unverified users can sign in and prepare their experiments, running them requires a confirmed email
+    if !can_run(&user) {
+        return Err(ExperimentErrorMessage::EmailNotVerified.into());
+    }
+
     let conn = pool.get().unwrap();
-    let (experiment_id, runner_id) = ids.into_inner();
+    let RunExperimentPath { experiment_id, runner_id } = path.into_inner();
+    let request = request.into_inner();
+    let ansi_mode = request.ansi.unwrap_or_default();
+    let hooks = parse_hooks(request.hooks.as_deref())?;
+    let idempotency_key = idempotency::idempotency_key(&req)?;
 
-    let job = web::block(move || {
-        let experiment = experiments::table
-            .filter(experiments::user_id.eq(user.id))
-            .find(experiment_id)
-            .first::<Experiment>(&conn)?;
+    let (job, replayed) = web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        // set if a concurrent request with the same key creates its job first
+        let mut key_taken = false;
 
-        let runner = runners::table
-            .find(runner_id)
-            .first::<Runner>(&conn)?;
+        let created = conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+            if let Some(key) = &idempotency_key {
+                if let Some(job) = idempotency::find_job(user.id, key, &conn)? {
+                    return Ok((PublicJob::load(job, &conn)?, true));
+                }
+            }
+
+            let experiment = experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+                .first::<Experiment>(&conn)?;
+
+            if experiment.archived_at.is_some() {
+                return Err(ExperimentErrorMessage::ExperimentArchived.into());
+            }
+
+            let runner = runners::table
+                .filter(runners::uuid.eq(runner_id))
+                .first::<Runner>(&conn)?;
+
+            if runner.disabled {
+                return Err(ExperimentErrorMessage::RunnerDisabled.into());
+            }
 
-        diesel::insert_into(jobs::table)
-            .values((jobs::experiment_id.eq(experiment.id), jobs::runner_id.eq(runner.id), jobs::code.eq(experiment.code)))
-            .get_result::<Job>(&conn)
+            project::check_job_quota(experiment.project_id, 1, &conn)?;
+
+            let job = insert_job(&experiment, runner.id, ansi_mode, &hooks, None, false, &conn)?;
+
+            ActivityEntry::new(experiment.id, Some(user.id), ActivityKind::RunStarted)
+                .job(job.id)
+                .details(runner.name)
+                .record(&conn)?;
+
+            if let Some(key) = &idempotency_key {
+                if !idempotency::store_key(user.id, key, job.id, &conn)? {
+                    key_taken = true;
+                    return Err(diesel::result::Error::RollbackTransaction.into());
+                }
+            }
+
+            Ok((PublicJob::load(job, &conn)?, false))
+        });
+
+        match (key_taken, &idempotency_key) {
+            (true, Some(key)) => {
+                let job = idempotency::find_job(user.id, key, &conn)?
+                    .ok_or(ErrorMessage::UnknownError)?;
+
+                Ok((PublicJob::load(job, &conn)?, true))
+            }
+            _ => created
+        }
     })
         .await?;
 
-    if let Err(e) = experiment_server.send(RunExperimentMessage { job_id: job.id })
+    if replayed {
+        return Ok(HttpResponse::Ok()
+            .header(IDEMPOTENT_REPLAYED_HEADER, "true")
+            .json(job));
+    }
+
+    let job_id = job.job.id;
+
+    if let Err(e) = experiment_server.send(RunExperimentMessage { job_id })
         .await {
         error!("Error while sending run to ExperimentServer: {:?}", e);
 
-        web::block(move || diesel::update(jobs::table.find(job.id))
-            .set(jobs::status.eq(JobStatus::Failed.value()))
-            .execute(&pool.get().unwrap())
+        web::block(move || JobStatus::transition_to(job_id, JobStatus::Failed)
+            .apply(&pool.get().unwrap())
         )
             .await?;
     }
 
-    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+    Ok(HttpResponse::Ok().json(job))
 }
 
-/// This will return a SuccessResponse even though delete may not occur if experiment's user id is not
-/// equal to user.id. Delete endpoints will generally behave like this.
-#[delete("experiment/{id}")]
-pub async fn delete_experiment(pool: web::Data<DBPool>, experiment_id: web::Path<ModelId>, user: User) -> DefaultResponse {
+fn insert_job(experiment: &Experiment, runner_id: RunnerId, ansi_mode: AnsiMode, hooks: &[String], batch_id: Option<ModelId>, warmup: bool, conn: &PgConnection)
+              -> QueryResult<Job> {
+    let code_hash = code::store(experiment.code.as_str(), conn)?;
+
+    diesel::insert_into(jobs::table)
+        .values((
+            jobs::experiment_id.eq(experiment.id),
+            jobs::runner_id.eq(runner_id),
+            jobs::code_hash.eq(code_hash),
+            jobs::ansi_mode.eq(ansi_mode.value()),
+            jobs::hooks.eq(hooks),
+            jobs::firmware_id.eq(experiment.firmware_id),
+            jobs::batch_id.eq(batch_id),
+            jobs::warmup.eq(warmup),
+        ))
+        .get_result::<Job>(conn)
+}
+
+const MAX_BATCH_RUNNERS: usize = 100;
+const MAX_BATCH_JOBS: usize = 200;
+
+/// Runs the experiment on each of the given runners at once, the jobs are grouped in a batch so that the sweep
+/// can be followed and cancelled together. Remaining jobs are cancelled by the server once the abort policy of the
+/// batch is triggered. Runs are repeated on each runner if the repeat policy asks so, the summary of the batch
+/// tells then whether the runs are flaky. Warm-up runs of the experiment are run on each runner before the others.
+#[utoipa::path(
+    post,
+    path = "/experiment/{id}/batch",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = RunBatchRequest,
+    responses((status = 200, body = BatchSummary)),
+    security(("bearer" = [])),
+)]
+#[post("experiment/{id}/batch")]
+pub async fn run_batch(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<RunBatchRequest>,
+) -> DefaultResponse {
+    if !can_run(&user) {
+        return Err(ExperimentErrorMessage::EmailNotVerified.into());
+    }
+
     let conn = pool.get().unwrap();
+    let request = request.into_inner();
+    let ansi_mode = request.ansi.unwrap_or_default();
+    let hooks = parse_hooks(request.hooks.as_deref())?;
+    let mut runner_ids = request.runner_ids;
 
-    web::block(move ||
-        diesel::delete(
-            experiments::table
-                .filter(experiments::user_id.eq(user.id))
-                .find(experiment_id.into_inner())
-        ).execute(&conn)
-    )
+    runner_ids.sort();
+    runner_ids.dedup();
+
+    let abort_policy = request.abort.unwrap_or_default();
+    let repeat_policy = request.repeat.unwrap_or_default();
+
+    if runner_ids.is_empty() ||
+        runner_ids.len() > MAX_BATCH_RUNNERS ||
+        !abort_policy.is_valid() ||
+        !repeat_policy.is_valid() ||
+        runner_ids.len() * repeat_policy.times as usize > MAX_BATCH_JOBS {
+        return Err(ExperimentErrorMessage::InvalidBatch.into());
+    }
+
+    let (summary, job_ids) = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let experiment = experiments::table
+            .filter(can_edit_experiment(user.id))
+            .filter(experiments::uuid.eq(experiment_id.into_inner()))
+            .first::<Experiment>(&conn)?;
+
+        if experiment.archived_at.is_some() {
+            return Err(ExperimentErrorMessage::ExperimentArchived.into());
+        }
+
+        let runs_per_runner = experiment.warmup_runs + repeat_policy.times;
+
+        if runner_ids.len() * runs_per_runner as usize > MAX_BATCH_JOBS {
+            return Err(ExperimentErrorMessage::InvalidBatch.into());
+        }
+
+        let runners = runners::table
+            .filter(runners::uuid.eq_any(&runner_ids))
+            .load::<Runner>(&conn)?;
+
+        if runners.len() != runner_ids.len() {
+            return Err(ErrorMessage::ItemNotFound.into());
+        }
+
+        if runners.iter().any(|runner| runner.disabled) {
+            return Err(ExperimentErrorMessage::RunnerDisabled.into());
+        }
+
+        project::check_job_quota(experiment.project_id, runners.len() * runs_per_runner as usize, &conn)?;
+
+        let metric = abort_policy.metric;
+
+        let batch_id = diesel::insert_into(job_batches::table)
+            .values((
+                job_batches::experiment_id.eq(experiment.id),
+                job_batches::created_by.eq(user.id),
+                job_batches::abort_after_failures.eq(abort_policy.after_failures),
+                job_batches::abort_metric.eq(metric.as_ref().map(|metric| metric.name.clone())),
+                job_batches::abort_threshold.eq(metric.as_ref().map(|metric| metric.threshold)),
+                job_batches::abort_crossing.eq(metric.as_ref().map(|metric| metric.crossing.value())),
+                job_batches::repeat_times.eq(repeat_policy.times),
+                job_batches::repeat_metrics.eq(&repeat_policy.metrics),
+                job_batches::max_variation.eq(repeat_policy.max_variation),
+            ))
+            .returning(job_batches::id)
+            .get_result::<ModelId>(&conn)?;
+
+        let mut job_ids = Vec::with_capacity(runners.len() * runs_per_runner as usize);
+
+        for runner in runners {
+            for run in 0..runs_per_runner {
+                let warmup = run < experiment.warmup_runs;
+                let job = insert_job(&experiment, runner.id, ansi_mode, &hooks, Some(batch_id), warmup, &conn)?;
+
+                ActivityEntry::new(experiment.id, Some(user.id), ActivityKind::RunStarted)
+                    .job(job.id)
+                    .details(runner.name.clone())
+                    .record(&conn)?;
+
+                job_ids.push(job.id);
+            }
+        }
+
+        let batch = job_batches::table
+            .inner_join(experiments::table)
+            .filter(job_batches::id.eq(batch_id))
+            .select(JOB_BATCH_COLUMNS)
+            .first::<JobBatch>(&conn)?;
+
+        Ok((BatchSummary::load(batch, &conn)?, job_ids))
+    }))
         .await?;
 
-    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
-}
\ No newline at end of file
+    for job_id in job_ids {
+        if let Err(e) = experiment_server.send(RunExperimentMessage { job_id })
+            .await {
+            error!("Error while sending run to ExperimentServer: {:?}", e);
+
+            let conn = pool.get().unwrap();
+
+            web::block(move || JobStatus::transition_to(job_id, JobStatus::Failed)
+                .apply(&conn)
+            )
+                .await?;
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(summary))
+}
+
+/// Summary of the jobs launched together in a batch, failures include the reasons if they are known.
+#[utoipa::path(
+    get,
+    path = "/batch/{id}",
+    tag = "jobs",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = BatchSummary)),
+    security(("bearer" = [])),
+)]
+#[get("batch/{id}")]
+pub async fn fetch_batch(pool: web::Data<DBPool>, batch_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let summary = web::block(move || -> QueryResult<BatchSummary> {
+        let batch = job_batches::table
+            .inner_join(experiments::table)
+            .filter(can_view_experiment(user.id))
+            .filter(job_batches::uuid.eq(batch_id.into_inner()))
+            .select(JOB_BATCH_COLUMNS)
+            .first::<JobBatch>(&conn)?;
+
+        BatchSummary::load(batch, &conn)
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(summary))
+}
+
+/// Cancels the pending and running jobs of a batch, e.g. to stop a sweep midway. The jobs which have already
+/// finished are left as they are.
+#[utoipa::path(
+    post,
+    path = "/batch/{id}/cancel",
+    tag = "jobs",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = BulkResponse)),
+    security(("bearer" = [])),
+)]
+#[post("batch/{id}/cancel")]
+pub async fn cancel_batch(pool: web::Data<DBPool>, batch_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let results = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let batch_id = job_batches::table
+            .inner_join(experiments::table)
+            .filter(can_edit_experiment(user.id))
+            .filter(job_batches::uuid.eq(batch_id.into_inner()))
+            .select(job_batches::id)
+            .first::<ModelId>(&conn)?;
+
+        let active_jobs = jobs::table
+            .filter(jobs::batch_id.eq(batch_id))
+            .filter(jobs::status.eq_any(vec![JobStatus::Pending.value(), JobStatus::Running.value()]))
+            .select((jobs::uuid, jobs::id))
+            .load::<(Uuid, JobId)>(&conn)?;
+
+        let mut results = Vec::with_capacity(active_jobs.len());
+
+        for (id, job_id) in active_jobs {
+            match JobStatus::transition_to(job_id, JobStatus::Cancelled).apply(&conn) {
+                Ok(()) => results.push(BulkItemResult::success(id)),
+                Err(TransitionError::DB(e)) => return Err(e),
+                Err(e) => results.push(BulkItemResult::failure(id, e)),
+            }
+        }
+
+        Ok(results)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(BulkResponse { results }))
+}
+
+// validation may pull the image of the docker backend on the runner
+const VALIDATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Checks the syntax and the imports of the experiment's code with the python of the runner, and probes the
+/// hardware the run would need, without running the code. Runner is picked by the server if it is not given.
+/// Auditors can not validate the experiments they view, since the code is sent to the runner.
+#[utoipa::path(
+    post,
+    path = "/experiment/{id}/validate",
+    tag = "experiments",
+    params(("id" = Uuid, Path), ValidateExperimentRequest),
+    responses((status = 200, body = ExperimentValidation)),
+    security(("bearer" = [])),
+)]
+#[post("experiment/{id}/validate")]
+pub async fn validate_experiment(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Query<ValidateExperimentRequest>,
+) -> DefaultResponse {
+    if !can_write(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let request = request.into_inner();
+    let hooks = parse_hooks(request.hooks.as_deref())?;
+    let runner_id = request.runner_id;
+
+    let (experiment, runner_id) = web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let experiment = experiments::table
+            .filter(can_view_experiment(user.id))
+            .filter(experiments::uuid.eq(experiment_id.into_inner()))
+            .first::<Experiment>(&conn)?;
+
+        let runner_id = match runner_id {
+            Some(runner_id) => {
+                let (runner_id, disabled) = runners::table
+                    .filter(runners::uuid.eq(runner_id))
+                    .select((runners::id, runners::disabled))
+                    .first::<(RunnerId, bool)>(&conn)?;
+
+                if disabled {
+                    return Err(ExperimentErrorMessage::RunnerDisabled.into());
+                }
+
+                Some(runner_id)
+            }
+            None => None
+        };
+
+        Ok((experiment, runner_id))
+    })
+        .await?;
+
+    let validation = experiment_server.send(RunnerValidationMessage {
+        runner_id,
+        // code is stored html encoded, like for the runs
+        code: core::decode_html(experiment.code.as_str()).unwrap(),
+        hooks,
+        has_firmware: experiment.firmware_id.is_some(),
+    })
+        .await
+        .map_err(|e| {
+            error!("Error while sending validation to ExperimentServer: {:?}", e);
+            ErrorMessage::UnknownError
+        })?;
+
+    let (runner_id, result) = validation.ok_or(ExperimentErrorMessage::RunnerNotConnected)?;
+
+    let diagnostics = match future::select(result, delay_for(VALIDATION_TIMEOUT)).await {
+        Either::Left((Ok(diagnostics), _)) => diagnostics,
+        // session of the runner is closed before it answered
+        Either::Left((Err(_), _)) => return Err(ExperimentErrorMessage::RunnerNotConnected.into()),
+        Either::Right(_) => return Err(ExperimentErrorMessage::ValidationTimedOut.into())
+    };
+
+    let conn = pool.get().unwrap();
+
+    // runner may be picked by the server, it is referred with its uuid in the response
+    let runner_id = web::block(move || runners::table
+        .find(runner_id)
+        .select(runners::uuid)
+        .first::<Uuid>(&conn)
+    )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ExperimentValidation {
+        valid: diagnostics.iter().all(|d| d.level != DiagnosticLevel::Error),
+        runner_id,
+        diagnostics,
+    }))
+}
+
+/// Lists the jobs of all the experiments of the user. Jobs which have not started or finished yet are listed last
+/// when sorted by the start or the finish times. Auditors list the jobs of all the users with `include_shared`.
+/// Jobs moved into the archive are only listed with `include_archived`.
+#[utoipa::path(
+    get,
+    path = "/jobs",
+    tag = "jobs",
+    params(PaginationRequest, JobsRequest),
+    responses((status = 200, body = Pagination<SlimJob>)),
+    security(("bearer" = [])),
+)]
+#[get("jobs")]
+pub async fn fetch_jobs(
+    pool: web::Data<DBPool>,
+    user: User,
+    pagination: web::Query<PaginationRequest>,
+    request: web::Query<JobsRequest>,
+    req: HttpRequest,
+) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let jobs = web::block(move || load_jobs(user.id, None, request.into_inner(), pagination.into_inner(), &conn))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(jobs.with_links(&req)))
+}
+
+/// Body of `load_jobs`, the jobs are queried from either the jobs table or the all_jobs view which includes the
+/// archived jobs as well
+macro_rules! query_jobs {
+    ($jobs:ident, $columns:expr, $user_id:expr, $project_id:expr, $request:expr, $pagination:expr, $conn:expr) => {{
+        let mut query = $jobs::table
+            .inner_join(experiments::table)
+            .left_join(runners::table)
+            .filter(can_view_experiment($user_id))
+            .into_boxed();
+
+        query = match ($project_id, $request.include_shared.unwrap_or(false)) {
+            (Some(project_id), _) => query.filter(experiments::project_id.eq(project_id)),
+            (None, true) => query,
+            (None, false) => query.filter(owned_by($user_id))
+        };
+
+        if let Some(status) = $request.status {
+            query = query.filter($jobs::status.eq(status.value()));
+        }
+
+        if let Some(experiment_id) = $request.experiment {
+            query = query.filter(experiments::uuid.eq(experiment_id));
+        }
+
+        if let Some(runner_id) = $request.runner {
+            query = query.filter(runners::uuid.eq(runner_id));
+        }
+
+        if let Some(since) = $request.since {
+            query = query.filter($jobs::created_at.ge(since));
+        }
+
+        query = match ($request.sort.unwrap_or(JobSort::Created), $request.order.unwrap_or(SortOrder::Desc)) {
+            (JobSort::Created, SortOrder::Asc) => query.order(($jobs::created_at.asc(), $jobs::id.asc())),
+            (JobSort::Created, SortOrder::Desc) => query.order(($jobs::created_at.desc(), $jobs::id.desc())),
+            (JobSort::Started, SortOrder::Asc) => query.order(($jobs::started_at.asc().nulls_last(), $jobs::id.asc())),
+            (JobSort::Started, SortOrder::Desc) => query.order(($jobs::started_at.desc().nulls_last(), $jobs::id.desc())),
+            (JobSort::Finished, SortOrder::Asc) => query.order(($jobs::finished_at.asc().nulls_last(), $jobs::id.asc())),
+            (JobSort::Finished, SortOrder::Desc) => query.order(($jobs::finished_at.desc().nulls_last(), $jobs::id.desc())),
+        };
+
+        query
+            .select(($columns, CountStarOver))
+            .paginate($pagination.page)
+            .per_page($pagination.per_page)
+            .load_and_count_pages::<SlimJob>($conn)
+    }};
+}
+
+/// Jobs of the user's own experiments, or of all the experiments of the project if it is given. Jobs of all the
+/// experiments the user may view are loaded if the shared ones are included. Archived jobs are only loaded if they
+/// are asked for, as listing them scans the archive.
+fn load_jobs(user_id: UserId, project_id: Option<ModelId>, request: JobsRequest, pagination: PaginationRequest, conn: &PgConnection)
+             -> QueryResult<Pagination<SlimJob>> {
+    if request.include_archived.unwrap_or(false) {
+        query_jobs!(all_jobs, all_slim_job_columns(), user_id, project_id, request, pagination, conn)
+    } else {
+        query_jobs!(jobs, slim_job_columns(), user_id, project_id, request, pagination, conn)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/job/{id}",
+    tag = "jobs",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = JobDetail)),
+    security(("bearer" = [])),
+)]
+#[get("job/{id}")]
+pub async fn fetch_job(pool: web::Data<DBPool>, job_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let job = web::block(move || load_job_detail(job_id.into_inner(), user.id, &conn))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(job))
+}
+
+fn load_job_detail(job_id: Uuid, user_id: UserId, conn: &PgConnection) -> QueryResult<JobDetail> {
+    // job is served wherever it is, in the archive or not
+    let job = all_jobs::table
+        .inner_join(experiments::table)
+        .filter(can_view_experiment(user_id))
+        .filter(all_jobs::uuid.eq(job_id))
+        .select(all_jobs::all_columns)
+        .first::<Job>(conn)?;
+
+    let streams = all_job_streams::table
+        .filter(all_job_streams::job_id.eq(job.id))
+        .order(all_job_streams::name.asc())
+        .load::<JobStream>(conn)?;
+
+    let environment = environment::load(job.id, conn)?;
+
+    let artifacts = artifact::load(job.id, conn)?;
+
+    Ok(JobDetail { job: PublicJob::load(job, conn)?, streams, environment, artifacts })
+}
+
+const DEFAULT_WAIT_SECONDS: u64 = 60;
+const MAX_WAIT_SECONDS: u64 = 300;
+
+/// Waits until the job finishes or the timeout elapses, then returns the job like `GET job/{id}`. Jobs which have
+/// not finished yet are returned as they are, so that the callers can check the status and wait again.
+#[utoipa::path(
+    get,
+    path = "/job/{id}/wait",
+    tag = "jobs",
+    params(("id" = Uuid, Path), JobWaitRequest),
+    responses((status = 200, body = JobDetail)),
+    security(("bearer" = [])),
+)]
+#[get("job/{id}/wait")]
+pub async fn wait_job(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    job_id: web::Path<Uuid>,
+    user: User,
+    request: web::Query<JobWaitRequest>,
+) -> DefaultResponse {
+    let job_id = job_id.into_inner();
+    let user_id = user.id;
+    let timeout = Duration::from_secs(request.timeout.unwrap_or(DEFAULT_WAIT_SECONDS).min(MAX_WAIT_SECONDS));
+    let (sender, notifications) = mpsc::unbounded();
+
+    // subscribed before checking the status, so that the finish of the job is not missed in between
+    experiment_server.send(SubscribeNotificationsMessage { user_id, sender })
+        .await
+        .map_err(|e| {
+            error!("Error while subscribing to ExperimentServer: {:?}", e);
+            ErrorMessage::UnknownError
+        })?;
+
+    let conn = pool.get().unwrap();
+
+    let status = web::block(move || jobs::table
+        .inner_join(experiments::table)
+        .filter(can_view_experiment(user_id))
+        .filter(jobs::uuid.eq(job_id))
+        .select(jobs::status)
+        .first::<JobStatus>(&conn)
+    )
+        .await?;
+
+    if !status.is_terminal() {
+        let finished = notifications
+            .filter(move |notification| future::ready(match notification {
+                Notification::JobStatus(status) => status.job_id == job_id && status.status.is_terminal(),
+                _ => false
+            }))
+            .into_future();
+
+        future::select(finished, delay_for(timeout)).await;
+    }
+
+    let conn = pool.get().unwrap();
+
+    let job = web::block(move || load_job_detail(job_id, user_id, &conn))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(job))
+}
+
+/// Serves a named output stream of the job, e.g. the serial console of the device. ANSI escape
+/// sequences are handled like in the job output.
+#[utoipa::path(
+    get,
+    path = "/job/{id}/stream/{name}",
+    tag = "jobs",
+    params(("id" = Uuid, Path), ("name" = String, Path), JobOutputRequest),
+    responses((status = 200, body = String, content_type = "text/plain")),
+    security(("bearer" = [])),
+)]
+#[get("job/{id}/stream/{name}")]
+pub async fn fetch_job_stream(pool: web::Data<DBPool>, path: web::Path<(Uuid, String)>, user: User, request: web::Query<JobOutputRequest>)
+                              -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let (job_id, name) = path.into_inner();
+
+    let (output, ansi_mode) = web::block(move || all_job_streams::table
+        .inner_join(all_jobs::table.inner_join(experiments::table))
+        .filter(can_view_experiment(user.id))
+        .filter(all_jobs::uuid.eq(job_id))
+        .filter(all_job_streams::name.eq(name))
+        .select((all_job_streams::output, all_jobs::ansi_mode))
+        .first::<(String, AnsiMode)>(&conn)
+    )
+        .await?;
+
+    let ansi_mode = request.into_inner().ansi.unwrap_or(ansi_mode);
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .streaming(output_stream(output, ansi_mode)))
+}
+
+/// Serves a file collected by the runner during the job, e.g. a compressed packet capture. A single range of
+/// the file can be requested with the Range header, e.g. for resuming the download.
+#[utoipa::path(
+    get,
+    path = "/job/{id}/artifact/{name}",
+    tag = "jobs",
+    params(("id" = Uuid, Path), ("name" = String, Path)),
+    responses(
+        (status = 200, body = [u8], content_type = "application/octet-stream"),
+        (status = 206, description = "Range of the artifact requested with the Range header", body = [u8], content_type = "application/octet-stream"),
+        (status = 416, description = "Requested range is out of the artifact"),
+    ),
+    security(("bearer" = [])),
+)]
+#[get("job/{id}/artifact/{name}")]
+pub async fn fetch_job_artifact(pool: web::Data<DBPool>, path: web::Path<(Uuid, String)>, user: User, req: HttpRequest) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let (job_id, name) = path.into_inner();
+    let filename = name.clone();
+
+    let (artifact_id, size) = web::block(move || job_artifacts::table
+        .inner_join(all_jobs::table.inner_join(experiments::table))
+        .filter(can_view_experiment(user.id))
+        .filter(all_jobs::uuid.eq(job_id))
+        .filter(job_artifacts::name.eq(name))
+        .select((job_artifacts::id, job_artifacts::size))
+        .first::<(ModelId, i32)>(&conn)
+    )
+        .await?;
+
+    Ok(artifact_response(&req, pool.get_ref(), artifact_id, filename.as_str(), size))
+}
+
+/// Serves the content of the artifact honoring the Range header, so that the downloads of the large artifacts can be
+/// resumed or fetched partially
+fn artifact_response(req: &HttpRequest, pool: &DBPool, artifact_id: ModelId, name: &str, size: i32) -> HttpResponse {
+    let size = size as i64;
+    let range = req.headers().get(header::RANGE).and_then(|value| value.to_str().ok());
+
+    let (mut response, range) = match artifact::parse_range(range, size) {
+        RangeRequest::Whole => (HttpResponse::Ok(), ByteRange::whole(size)),
+        RangeRequest::Partial(range) => {
+            let mut response = HttpResponse::PartialContent();
+            response.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.end, size));
+
+            (response, range)
+        }
+        RangeRequest::Unsatisfiable => return HttpResponse::RangeNotSatisfiable()
+            .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+            .finish()
+    };
+
+    let stream = artifact::content_stream(pool.clone(), artifact_id, range);
+
+    response
+        .content_type("application/octet-stream")
+        // ranges refer to the stored content, it can not be compressed on the fly
+        .encoding(ContentEncoding::Identity)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name.replace('"', "")))
+        .body(Body::from_message(SizedStream::new(range.length() as u64, Box::pin(stream))))
+}
+
+/// Signs a url the artifact can be downloaded from without a token, e.g. by a browser or a download manager. Url
+/// expires in a few minutes.
+#[utoipa::path(
+    post,
+    path = "/job/{id}/artifact/{name}/url",
+    tag = "jobs",
+    params(("id" = Uuid, Path), ("name" = String, Path)),
+    responses((status = 200, body = SignedUrl)),
+    security(("bearer" = [])),
+)]
+#[post("job/{id}/artifact/{name}/url")]
+pub async fn sign_job_artifact_url(pool: web::Data<DBPool>, hash: web::Data<Hash>, config: web::Data<Arc<Config>>, path: web::Path<(Uuid, String)>, user: User)
+                                   -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let (job_id, name) = path.into_inner();
+
+    {
+        let name = name.clone();
+
+        web::block(move || job_artifacts::table
+            .inner_join(all_jobs::table.inner_join(experiments::table))
+            .filter(can_view_experiment(user.id))
+            .filter(all_jobs::uuid.eq(job_id))
+            .filter(job_artifacts::name.eq(name))
+            .select(job_artifacts::id)
+            .first::<ModelId>(&conn)
+        )
+            .await?;
+    }
+
+    Ok(HttpResponse::Ok().json(Download::Artifact(job_id, name).sign(config.app_url.as_str(), &hash)))
+}
+
+/// Serves the artifact with a url signed by `job/{id}/artifact/{name}/url`, the token is not needed.
+#[utoipa::path(
+    get,
+    path = "/download/job/{id}/artifact/{name}",
+    tag = "jobs",
+    params(("id" = Uuid, Path), ("name" = String, Path), DownloadRequest),
+    responses(
+        (status = 200, body = [u8], content_type = "application/octet-stream"),
+        (status = 206, description = "Range of the artifact requested with the Range header", body = [u8], content_type = "application/octet-stream"),
+        (status = 416, description = "Requested range is out of the artifact"),
+    ),
+)]
+#[get("download/job/{id}/artifact/{name}")]
+pub async fn download_job_artifact(pool: web::Data<DBPool>, hash: web::Data<Hash>, path: web::Path<(Uuid, String)>, request: web::Query<DownloadRequest>,
+                                   req: HttpRequest) -> DefaultResponse {
+    let (job_id, name) = path.into_inner();
+
+    Download::Artifact(job_id, name.clone()).verify(&request, &hash)?;
+
+    let conn = pool.get().unwrap();
+    let filename = name.clone();
+
+    let (artifact_id, size) = web::block(move || job_artifacts::table
+        .inner_join(all_jobs::table)
+        .filter(all_jobs::uuid.eq(job_id))
+        .filter(job_artifacts::name.eq(name))
+        .select((job_artifacts::id, job_artifacts::size))
+        .first::<(ModelId, i32)>(&conn)
+    )
+        .await?;
+
+    Ok(artifact_response(&req, pool.get_ref(), artifact_id, filename.as_str(), size))
+}
+
+/// Returns the position of a pending job in the queue of its runner and the estimated time until it starts.
+#[utoipa::path(
+    get,
+    path = "/job/{id}/queue-info",
+    tag = "jobs",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = QueueInfo)),
+    security(("bearer" = [])),
+)]
+#[get("job/{id}/queue-info")]
+pub async fn fetch_job_queue_info(pool: web::Data<DBPool>, job_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let job_id = job_id.into_inner();
+
+    let info = web::block(move || -> Result<_, diesel::result::Error> {
+        let (status, runner_id) = jobs::table
+            .inner_join(experiments::table)
+            .filter(can_view_experiment(user.id))
+            .filter(jobs::uuid.eq(job_id))
+            .select((jobs::status, jobs::runner_id))
+            .first::<(JobStatus, Option<RunnerId>)>(&conn)?;
+
+        if status != JobStatus::Pending {
+            return Ok(None);
+        }
+
+        Ok(queue::queued_jobs(runner_id, &conn)?
+            .into_iter()
+            .map(|queued| queued.info)
+            .find(|info| info.job_id == job_id))
+    })
+        .await?
+        .ok_or(ErrorMessage::InvalidOperationForStatus)?;
+
+    Ok(HttpResponse::Ok().json(info))
+}
+
+/// Lists the pending jobs requested for the runner in the order they are expected to run. Admins see all of the
+/// jobs, other users only see their own jobs along with their positions in the whole queue.
+#[utoipa::path(
+    get,
+    path = "/runner/{id}/queue",
+    tag = "runners",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = [RunnerQueueEntry])),
+    security(("bearer" = [])),
+)]
+#[get("runner/{id}/queue")]
+pub async fn fetch_runner_queue(pool: web::Data<DBPool>, runner_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+    let view_all = can_admin_jobs(&user);
+
+    let queue = web::block(move || -> Result<_, diesel::result::Error> {
+        let runner_id = Runner::id_of(runner_id, &conn)?;
+
+        Ok(queue::queued_jobs(Some(runner_id), &conn)?
+            .into_iter()
+            .filter(|queued| view_all || queued.user_id == user.id)
+            .map(RunnerQueueEntry::from)
+            .collect::<Vec<RunnerQueueEntry>>())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(queue))
+}
+
+/// Moves the given pending jobs of the runner to the top of its queue, in the given order. Returns the reordered
+/// queue.
+#[utoipa::path(
+    post,
+    path = "/runner/{id}/queue/reorder",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    request_body = RunnerQueueReorderRequest,
+    responses((status = 200, body = [RunnerQueueEntry])),
+    security(("bearer" = [])),
+)]
+#[post("runner/{id}/queue/reorder")]
+pub async fn reorder_runner_queue(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    runner_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<RunnerQueueReorderRequest>,
+) -> DefaultResponse {
+    if !can_admin_jobs(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let job_ids = request.into_inner().job_ids;
+
+    if job_ids.len() > MAX_BULK_ITEMS {
+        return Err(ExperimentErrorMessage::TooManyItems.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+
+    let (queue, bumped) = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let runner_id = Runner::id_of(runner_id, &conn)?;
+
+        let pending_jobs = jobs::table
+            .filter(jobs::runner_id.eq(runner_id))
+            .filter(jobs::status.eq(JobStatus::Pending.value()))
+            .filter(jobs::uuid.eq_any(&job_ids))
+            .select((jobs::uuid, jobs::id))
+            .for_update()
+            .load::<(Uuid, JobId)>(&conn)?;
+
+        let mut bumped = Vec::with_capacity(job_ids.len());
+
+        for job_id in &job_ids {
+            match pending_jobs.iter().find(|(uuid, _)| uuid == job_id) {
+                Some((_, id)) if !bumped.contains(id) => bumped.push(*id),
+                Some(_) => {}
+                None => return Err(ErrorMessage::ItemNotFound.into())
+            }
+        }
+
+        let top_priority = jobs::table
+            .filter(jobs::runner_id.eq(runner_id))
+            .filter(jobs::status.eq(JobStatus::Pending.value()))
+            .select(diesel::dsl::max(jobs::queue_priority))
+            .first::<Option<i32>>(&conn)?
+            .unwrap_or(0);
+
+        for (index, job_id) in bumped.iter().enumerate() {
+            diesel::update(jobs::table.find(job_id))
+                .set(jobs::queue_priority.eq(top_priority + (bumped.len() - index) as i32))
+                .execute(&conn)?;
+        }
+
+        AuditEntry::new(Some(user.id), "runner.queue.reorder")
+            .target("runner", runner_id)
+            .details(bumped.iter().map(|job_id| job_id.to_string()).collect::<Vec<String>>().join(","))
+            .record(&conn)?;
+
+        let queue = queue::queued_jobs(Some(runner_id), &conn)?
+            .into_iter()
+            .map(RunnerQueueEntry::from)
+            .collect::<Vec<RunnerQueueEntry>>();
+
+        Ok((queue, bumped))
+    }))
+        .await?;
+
+    experiment_server.do_send(BumpPendingRunsMessage { job_ids: bumped });
+
+    Ok(HttpResponse::Ok().json(queue))
+}
+
+/// Serves the output of the job. ANSI escape sequences are stripped or preserved depending on the
+/// `ansi` query parameter, falling back to the mode given while running the job.
+#[utoipa::path(
+    get,
+    path = "/job/{id}/output",
+    tag = "jobs",
+    params(("id" = Uuid, Path), JobOutputRequest),
+    responses((status = 200, body = String, content_type = "text/plain")),
+    security(("bearer" = [])),
+)]
+#[get("job/{id}/output")]
+pub async fn fetch_job_output(pool: web::Data<DBPool>, job_id: web::Path<Uuid>, user: User, request: web::Query<JobOutputRequest>)
+                              -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let (output, ansi_mode) = web::block(move || all_jobs::table
+        .inner_join(experiments::table)
+        .filter(can_view_experiment(user.id))
+        .filter(all_jobs::uuid.eq(job_id.into_inner()))
+        .select((all_jobs::output, all_jobs::ansi_mode))
+        .first::<(String, AnsiMode)>(&conn)
+    )
+        .await?;
+
+    let ansi_mode = request.into_inner().ansi.unwrap_or(ansi_mode);
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .streaming(output_stream(output, ansi_mode)))
+}
+
+/// Signs a url the output of the job can be downloaded from without a token. Url expires in a few minutes.
+#[utoipa::path(
+    post,
+    path = "/job/{id}/output/url",
+    tag = "jobs",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = SignedUrl)),
+    security(("bearer" = [])),
+)]
+#[post("job/{id}/output/url")]
+pub async fn sign_job_output_url(pool: web::Data<DBPool>, hash: web::Data<Hash>, config: web::Data<Arc<Config>>, job_id: web::Path<Uuid>, user: User)
+                                 -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let job_id = job_id.into_inner();
+
+    web::block(move || all_jobs::table
+        .inner_join(experiments::table)
+        .filter(can_view_experiment(user.id))
+        .filter(all_jobs::uuid.eq(job_id))
+        .select(all_jobs::id)
+        .first::<JobId>(&conn)
+    )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(Download::Output(job_id).sign(config.app_url.as_str(), &hash)))
+}
+
+/// Serves the output of the job with a url signed by `job/{id}/output/url`, the token is not needed. ANSI escape
+/// sequences are handled like in the job output.
+#[utoipa::path(
+    get,
+    path = "/download/job/{id}/output",
+    tag = "jobs",
+    params(("id" = Uuid, Path), DownloadRequest),
+    responses((status = 200, body = String, content_type = "text/plain")),
+)]
+#[get("download/job/{id}/output")]
+pub async fn download_job_output(pool: web::Data<DBPool>, hash: web::Data<Hash>, job_id: web::Path<Uuid>, request: web::Query<DownloadRequest>)
+                                 -> DefaultResponse {
+    let job_id = job_id.into_inner();
+
+    Download::Output(job_id).verify(&request, &hash)?;
+
+    let conn = pool.get().unwrap();
+
+    let (output, ansi_mode) = web::block(move || all_jobs::table
+        .filter(all_jobs::uuid.eq(job_id))
+        .select((all_jobs::output, all_jobs::ansi_mode))
+        .first::<(String, AnsiMode)>(&conn)
+    )
+        .await?;
+
+    let ansi_mode = request.into_inner().ansi.unwrap_or(ansi_mode);
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.txt\"", job_id))
+        .streaming(output_stream(output, ansi_mode)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/runners",
+    tag = "runners",
+    responses((status = 200, body = Vec<RunnerStatus>)),
+    security(("bearer" = [])),
+)]
+#[get("runners")]
+pub async fn fetch_runners(pool: web::Data<DBPool>, experiment_server: web::Data<Addr<ExperimentServer>>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let connected_runners = experiment_server.send(FetchConnectedRunnersMessage)
+        .await
+        .map_err(|e| {
+            error!("Error while fetching connected runners from ExperimentServer: {:?}", e);
+            ErrorMessage::UnknownError
+        })?;
+
+    let (runners, queue_lengths, running_jobs) = web::block(move || -> Result<_, diesel::result::Error> {
+        let runners = runners::table
+            .order(runners::id.asc())
+            .select(SLIM_RUNNER_COLUMNS)
+            .load::<SlimRunner>(&conn)?;
+
+        let queue_lengths = jobs::table
+            .filter(jobs::status.eq(JobStatus::Pending.value()))
+            .group_by(jobs::runner_id)
+            .select((jobs::runner_id, sql::<BigInt>("COUNT(*)")))
+            .load::<(Option<RunnerId>, i64)>(&conn)?;
+
+        // Only the running jobs of the user are revealed
+        let running_jobs = jobs::table
+            .inner_join(experiments::table)
+            .filter(can_view_experiment(user.id))
+            .filter(jobs::status.eq(JobStatus::Running.value()))
+            .select((jobs::runner_id, jobs::uuid))
+            .load::<(Option<RunnerId>, Uuid)>(&conn)?;
+
+        Ok((runners, queue_lengths, running_jobs))
+    })
+        .await?;
+
+    let runners = runners.into_iter()
+        .map(|runner| RunnerStatus {
+            online: connected_runners.contains(&runner.id),
+            queue_length: queue_lengths.iter()
+                .find(|(runner_id, _)| *runner_id == Some(runner.id))
+                .map_or(0, |(_, length)| *length),
+            running_job_id: running_jobs.iter()
+                .find(|(runner_id, _)| *runner_id == Some(runner.id))
+                .map(|(_, job_id)| *job_id),
+            id: runner.uuid,
+            name: runner.name,
+            labels: runner.labels,
+            disabled: runner.disabled,
+            last_seen_at: runner.last_seen_at,
+            created_at: runner.created_at,
+        })
+        .collect::<Vec<RunnerStatus>>();
+
+    Ok(HttpResponse::Ok().json(runners))
+}
+
+const SECONDS_IN_DAY: f64 = 60.0 * 60.0 * 24.0;
+
+#[utoipa::path(
+    get,
+    path = "/runner/{id}",
+    tag = "runners",
+    params(("id" = Uuid, Path), RunnerDetailRequest, PaginationRequest),
+    responses((status = 200, body = RunnerDetail)),
+    security(("bearer" = [])),
+)]
+#[get("runner/{id}")]
+pub async fn fetch_runner(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    runner_id: web::Path<Uuid>,
+    user: User,
+    request: web::Query<RunnerDetailRequest>,
+    pagination: web::Query<PaginationRequest>,
+    req: HttpRequest,
+) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+    let include_archived = request.include_archived.unwrap_or(false);
+
+    let connected_runners = experiment_server.send(FetchConnectedRunnersMessage)
+        .await
+        .map_err(|e| {
+            error!("Error while fetching connected runners from ExperimentServer: {:?}", e);
+            ErrorMessage::UnknownError
+        })?;
+
+    let (runner, status_counts, busy_seconds, jobs) = web::block(move || -> Result<_, diesel::result::Error> {
+        let runner = runners::table
+            .filter(runners::uuid.eq(runner_id))
+            .first::<Runner>(&conn)?;
+
+        // totals cover the archived jobs as well, regardless of the listing
+        let status_counts = all_jobs::table
+            .filter(all_jobs::runner_id.eq(runner.id))
+            .group_by(all_jobs::status)
+            .select((all_jobs::status, sql::<BigInt>("COUNT(*)")))
+            .load::<(JobStatus, i64)>(&conn)?;
+
+        let busy_seconds = jobs::table
+            .filter(jobs::runner_id.eq(runner.id))
+            .filter(jobs::finished_at.gt(now.nullable() - 1.days()))
+            .select(sql::<Nullable<Double>>(busy_seconds_since(1).as_str()))
+            .first::<Option<f64>>(&conn)?;
+
+        let jobs = if include_archived {
+            all_jobs::table
+                .inner_join(experiments::table)
+                .left_join(runners::table)
+                .filter(can_view_experiment(user.id))
+                .filter(all_jobs::runner_id.eq(runner.id))
+                .order(all_jobs::created_at.desc())
+                .select((all_slim_job_columns(), CountStarOver))
+                .paginate(pagination.page)
+                .per_page(pagination.per_page)
+                .load_and_count_pages::<SlimJob>(&conn)?
+        } else {
+            jobs::table
+                .inner_join(experiments::table)
+                .left_join(runners::table)
+                .filter(can_view_experiment(user.id))
+                .filter(jobs::runner_id.eq(runner.id))
+                .order(jobs::created_at.desc())
+                .select((slim_job_columns(), CountStarOver))
+                .paginate(pagination.page)
+                .per_page(pagination.per_page)
+                .load_and_count_pages::<SlimJob>(&conn)?
+        };
+
+        Ok((runner, status_counts, busy_seconds.unwrap_or(0.0), jobs))
+    })
+        .await?;
+
+    let count_of = |status: JobStatus| status_counts.iter()
+        .find(|(s, _)| *s == status)
+        .map_or(0, |(_, count)| *count);
+
+    let stats = RunnerStats {
+        total_jobs: status_counts.iter().map(|(_, count)| count).sum(),
+        successful_jobs: count_of(JobStatus::Successful),
+        failed_jobs: count_of(JobStatus::Failed),
+        busy_seconds_last_day: busy_seconds,
+        utilization_last_day: (busy_seconds / SECONDS_IN_DAY).min(1.0),
+    };
+
+    Ok(HttpResponse::Ok().json(RunnerDetail {
+        online: connected_runners.contains(&runner.id),
+        id: runner.uuid,
+        name: runner.name,
+        labels: runner.labels,
+        os: runner.os,
+        arch: runner.arch,
+        client_version: runner.client_version,
+        kernel: runner.kernel,
+        cpu_model: runner.cpu_model,
+        memory_bytes: runner.memory_bytes,
+        peripherals: runner.peripherals,
+        capabilities: runner.capabilities,
+        disabled: runner.disabled,
+        auto_update: runner.auto_update,
+        last_seen_at: runner.last_seen_at,
+        created_at: runner.created_at,
+        stats,
+        jobs: jobs.with_links(&req),
+    }))
+}
+
+/// Returns the rolling job statistics of the runner, null if it has not finished any job recently.
+#[utoipa::path(
+    get,
+    path = "/runner/{id}/job-stats",
+    tag = "runners",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = Option<JobStats>)),
+    security(("bearer" = [])),
+)]
+#[get("runner/{id}/job-stats")]
+pub async fn fetch_runner_job_stats(pool: web::Data<DBPool>, runner_id: web::Path<Uuid>, _: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let stats = web::block(move || -> Result<_, diesel::result::Error> {
+        let runner_id = runners::table
+            .filter(runners::uuid.eq(runner_id.into_inner()))
+            .select(runners::id)
+            .first::<RunnerId>(&conn)?;
+
+        runner_job_stats::table
+            .find(runner_id)
+            .select(RUNNER_JOB_STATS_COLUMNS)
+            .first::<JobStats>(&conn)
+            .optional()
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+/// Experiment is marked as deleted and its active jobs are cancelled right away, the experiment and its jobs are
+/// removed in the background. Fails with 404 if the experiment does not exist, and with 403 if the user may not
+/// delete it.
+#[utoipa::path(
+    delete,
+    path = "/experiment/{id}",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[delete("experiment/{id}")]
+pub async fn delete_experiment(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let deleted = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set(experiments::deleted_at.eq(now.nullable()))
+            .returning(experiments::id)
+            .get_result::<ExperimentId>(&conn)
+            .optional()?;
+
+        match deleted {
+            Some(id) => cleaner::cancel_active_jobs(id, &conn)?,
+            None => return Err(experiment_not_affected(experiment_id, &conn))
+        }
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Slugs of the experiments are unique per user, names resulting in a slug which is already taken are rejected
+fn experiment_name_conflict(e: diesel::result::Error) -> Box<dyn ErrorMessaging> {
+    match e {
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => ExperimentErrorMessage::ExperimentNameExists.into(),
+        e => e.into()
+    }
+}
+
+/// Tells why an update or a delete of the experiment has not affected any row, so that a typo or a missing
+/// permission is not hidden behind a success response
+fn experiment_not_affected(experiment_id: Uuid, conn: &PgConnection) -> Box<dyn ErrorMessaging> {
+    let exists = diesel::select(exists(
+        experiments::table
+            .filter(experiments::uuid.eq(experiment_id))
+            .filter(experiments::deleted_at.is_null())
+    ))
+        .get_result::<bool>(conn);
+
+    match exists {
+        Ok(true) => ErrorMessage::NotAllowed.into(),
+        Ok(false) => ErrorMessage::ItemNotFound.into(),
+        Err(e) => e.into()
+    }
+}
+
+const MAX_BULK_ITEMS: usize = 1000;
+
+/// Deletes the experiments given by their ids, or the ones selected by the filters like `experiments` when the ids
+/// are not given and `all` is true. Jobs of the experiments are cancelled along the way.
+#[utoipa::path(
+    post,
+    path = "/experiments/bulk-delete",
+    tag = "experiments",
+    request_body = BulkDeleteExperimentsRequest,
+    responses((status = 200, body = BulkResponse)),
+    security(("bearer" = [])),
+)]
+#[post("experiments/bulk-delete")]
+pub async fn bulk_delete_experiments(pool: web::Data<DBPool>, user: User, request: web::Json<BulkDeleteExperimentsRequest>) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let request = request.into_inner();
+
+    if let Some(ids) = &request.ids {
+        if ids.len() > MAX_BULK_ITEMS {
+            return Err(Box::new(ExperimentErrorMessage::TooManyItems));
+        }
+    }
+
+    // experiments are not deleted by the filters alone unless it is confirmed explicitly
+    if request.ids.is_none() && !request.all.unwrap_or(false) {
+        return Err(Box::new(ExperimentErrorMessage::NoItemsSelected));
+    }
+
+    let results = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let ids = match request.ids {
+            Some(ids) => ids,
+            None => {
+                let mut query = experiments::table
+                    .filter(can_edit_experiment(user.id))
+                    .select(experiments::uuid)
+                    .into_boxed();
+
+                if let Some(starred) = request.starred {
+                    query = query.filter(experiments::starred.eq(starred));
+                }
+
+                if !request.include_archived.unwrap_or(false) {
+                    query = query.filter(experiments::archived_at.is_null());
+                }
+
+                query
+                    .order(experiments::created_at.asc())
+                    .limit(MAX_BULK_ITEMS as i64)
+                    .load::<Uuid>(&conn)?
+            }
+        };
+
+        let mut results = Vec::with_capacity(ids.len());
+
+        for id in ids {
+            let deleted = diesel::update(
+                experiments::table
+                    .filter(can_edit_experiment(user.id))
+                    .filter(experiments::uuid.eq(id))
+            )
+                .set(experiments::deleted_at.eq(now.nullable()))
+                .returning(experiments::id)
+                .get_result::<ExperimentId>(&conn)
+                .optional()?;
+
+            results.push(match deleted {
+                Some(experiment_id) => {
+                    cleaner::cancel_active_jobs(experiment_id, &conn)?;
+                    BulkItemResult::success(id)
+                }
+                None => BulkItemResult::failure(id, ErrorMessage::ItemNotFound)
+            });
+        }
+
+        Ok(results)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(BulkResponse { results }))
+}
+
+/// Cancels pending and running jobs. A running job is not interrupted on its runner, its result is
+/// discarded when it arrives.
+#[utoipa::path(
+    post,
+    path = "/jobs/bulk-cancel",
+    tag = "jobs",
+    request_body = BulkCancelJobsRequest,
+    responses((status = 200, body = BulkResponse)),
+    security(("bearer" = [])),
+)]
+#[post("jobs/bulk-cancel")]
+pub async fn bulk_cancel_jobs(pool: web::Data<DBPool>, user: User, request: web::Json<BulkCancelJobsRequest>) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let request = request.into_inner();
+    let user_id = user.id;
+
+    if let Some(ids) = &request.ids {
+        if ids.len() > MAX_BULK_ITEMS {
+            return Err(Box::new(ExperimentErrorMessage::TooManyItems));
+        }
+    }
+
+    let results = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let mut query = jobs::table
+            .inner_join(experiments::table)
+            .filter(can_edit_experiment(user_id))
+            .select((jobs::uuid, jobs::id))
+            .into_boxed();
+
+        if let Some(ids) = &request.ids {
+            query = query.filter(jobs::uuid.eq_any(ids.clone()));
+        } else {
+            query = query.filter(jobs::status.eq_any(vec![JobStatus::Pending.value(), JobStatus::Running.value()]));
+        }
+
+        if let Some(experiment_id) = request.experiment_id {
+            query = query.filter(experiments::uuid.eq(experiment_id));
+        }
+
+        if let Some(status) = request.status {
+            query = query.filter(jobs::status.eq(status.value()));
+        }
+
+        let owned_jobs = query
+            .limit(MAX_BULK_ITEMS as i64)
+            .load::<(Uuid, JobId)>(&conn)?;
+
+        let ids = match request.ids {
+            Some(ids) => ids,
+            None => owned_jobs.iter().map(|(id, _)| *id).collect()
+        };
+
+        let mut results = Vec::with_capacity(ids.len());
+
+        for id in ids {
+            let job_id = match owned_jobs.iter().find(|(owned, _)| *owned == id) {
+                Some((_, job_id)) => *job_id,
+                None => {
+                    results.push(BulkItemResult::failure(id, ErrorMessage::ItemNotFound));
+                    continue;
+                }
+            };
+
+            // Users are notified about the cancelled jobs by the job status events
+            match JobStatus::transition_to(job_id, JobStatus::Cancelled).apply(&conn) {
+                Ok(()) => results.push(BulkItemResult::success(id)),
+                Err(TransitionError::DB(e)) => return Err(e),
+                Err(e) => results.push(BulkItemResult::failure(id, e)),
+            }
+        }
+
+        Ok(results)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(BulkResponse { results }))
+}
+
+/// Overrides the retention policy of the app for the finished jobs of the experiment, the policy of the app is
+/// applied again for the values which are not given.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/retention",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentRetentionRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/retention")]
+pub async fn update_experiment_retention(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ExperimentRetentionRequest>,
+) -> DefaultResponse {
+    let request = request.into_inner();
+
+    if request.days.is_some_and(|days| days <= 0) || request.jobs.is_some_and(|jobs| jobs <= 0) {
+        return Err(ExperimentErrorMessage::InvalidRetention.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let updated = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set((experiments::retention_days.eq(request.days), experiments::retention_jobs.eq(request.jobs)))
+            .execute(&conn)?;
+
+        if updated == 0 {
+            return Err(experiment_not_affected(experiment_id, &conn));
+        }
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+const MAX_PREFERRED_LABELS: usize = 16;
+const MAX_LABEL_LENGTH: usize = 64;
+
+/// Selects how the server picks a runner for the jobs of the experiment. Preferred labels are matched against the
+/// labels and the capabilities of the runners by the `LabelAffinity` strategy.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/runner-strategy",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentRunnerStrategyRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/runner-strategy")]
+pub async fn update_experiment_runner_strategy(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ExperimentRunnerStrategyRequest>,
+) -> DefaultResponse {
+    let request = request.into_inner();
+    let strategy = request.strategy;
+    let labels = request.labels.unwrap_or_default();
+
+    if labels.len() > MAX_PREFERRED_LABELS || labels.iter().any(|label| label.is_empty() || label.len() > MAX_LABEL_LENGTH) {
+        return Err(ExperimentErrorMessage::InvalidLabels.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let updated = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set((experiments::runner_strategy.eq(strategy.value()), experiments::preferred_labels.eq(labels)))
+            .execute(&conn)?;
+
+        if updated == 0 {
+            return Err(experiment_not_affected(experiment_id, &conn));
+        }
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+const MAX_WARMUP_RUNS: i32 = 10;
+
+/// Batches of the experiment start with the given number of warm-up runs on each runner. Warm-up runs are run like
+/// the others, but they are left out of the statistics and the comparisons in the batch summaries.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/warmup-runs",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentWarmupRunsRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/warmup-runs")]
+pub async fn update_experiment_warmup_runs(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ExperimentWarmupRunsRequest>,
+) -> DefaultResponse {
+    let runs = request.runs;
+
+    if !(0..=MAX_WARMUP_RUNS).contains(&runs) {
+        return Err(ExperimentErrorMessage::InvalidWarmupRuns.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let updated = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set(experiments::warmup_runs.eq(runs))
+            .execute(&conn)?;
+
+        if updated == 0 {
+            return Err(experiment_not_affected(experiment_id, &conn));
+        }
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+const MAX_NETEM_DELAY_MS: i32 = 60 * 1000;
+const MAX_NETEM_RATE_KBIT: i32 = 10_000_000;
+
+/// Runners emulate the given latency, loss and bandwidth on their configured interface while the jobs of the
+/// experiment run, the emulation is removed after each job. Jobs fail on the runners without an emulation interface.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/network-emulation",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentNetworkEmulationRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/network-emulation")]
+pub async fn update_experiment_network_emulation(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ExperimentNetworkEmulationRequest>,
+) -> DefaultResponse {
+    let request = request.into_inner();
+
+    let valid_delay = request.delay_ms.is_none_or(|delay_ms| (0..=MAX_NETEM_DELAY_MS).contains(&delay_ms));
+    let valid_jitter = request.jitter_ms.is_none_or(|jitter_ms| {
+        request.delay_ms.is_some_and(|delay_ms| (0..=delay_ms).contains(&jitter_ms))
+    });
+    let valid_loss = request.loss_percent.is_none_or(|loss_percent| (0.0..=100.0).contains(&loss_percent));
+    let valid_rate = request.rate_kbit.is_none_or(|rate_kbit| rate_kbit > 0 && rate_kbit <= MAX_NETEM_RATE_KBIT);
+
+    if !valid_delay || !valid_jitter || !valid_loss || !valid_rate {
+        return Err(ExperimentErrorMessage::InvalidNetworkEmulation.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let updated = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set((
+                experiments::netem_delay_ms.eq(request.delay_ms),
+                experiments::netem_jitter_ms.eq(request.jitter_ms),
+                experiments::netem_loss_percent.eq(request.loss_percent),
+                experiments::netem_rate_kbit.eq(request.rate_kbit),
+            ))
+            .execute(&conn)?;
+
+        if updated == 0 {
+            return Err(experiment_not_affected(experiment_id, &conn));
+        }
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Jobs of the experiment are charged to the cost center once they finish, the ones finished before are charged to
+/// the previous one. Jobs are charged to the unassigned cost center if it is cleared.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/cost-center",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentCostCenterRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/cost-center")]
+pub async fn update_experiment_cost_center(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ExperimentCostCenterRequest>,
+) -> DefaultResponse {
+    let cost_center = request.into_inner().cost_center;
+
+    if cost_center.as_deref().is_some_and(|cost_center| !accounting::is_valid_cost_center(cost_center)) {
+        return Err(ExperimentErrorMessage::InvalidCostCenter.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let id = experiments::table
+            .filter(can_edit_experiment(user.id))
+            .filter(experiments::uuid.eq(experiment_id))
+            .select(experiments::id)
+            .first::<ExperimentId>(&conn)
+            .optional()?;
+
+        let id = match id {
+            Some(id) => id,
+            None => return Err(experiment_not_affected(experiment_id, &conn))
+        };
+
+        match cost_center {
+            Some(cost_center) => diesel::insert_into(experiment_cost_centers::table)
+                .values((
+                    experiment_cost_centers::experiment_id.eq(id),
+                    experiment_cost_centers::cost_center.eq(&cost_center),
+                ))
+                .on_conflict(experiment_cost_centers::experiment_id)
+                .do_update()
+                .set(experiment_cost_centers::cost_center.eq(&cost_center))
+                .execute(&conn)?,
+            None => diesel::delete(experiment_cost_centers::table.find(id))
+                .execute(&conn)?
+        };
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Moves the experiment into one of the user's projects, so that it is shared with the members of the project, or
+/// removes it from its project. Fails with 409 if the project has as many experiments as its quota.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/project",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentProjectRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/project")]
+pub async fn update_experiment_project(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ExperimentProjectRequest>,
+) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+    let project_id = request.into_inner().project_id;
+
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let project_id = match project_id {
+            Some(project_id) => {
+                let (project_id, _) = find_project(project_id, user.id, &conn)?;
+                project::check_experiment_quota(project_id, experiment_id, &conn)?;
+                Some(project_id)
+            }
+            None => None
+        };
+
+        let updated = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set(experiments::project_id.eq(project_id))
+            .execute(&conn)?;
+
+        if updated == 0 {
+            return Err(experiment_not_affected(experiment_id, &conn));
+        }
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+const MAX_SDR_FREQUENCY_HZ: i64 = 10_000_000_000;
+const MAX_SDR_SAMPLE_RATE: i32 = 100_000_000;
+const MAX_SDR_RECORDING_MS: i32 = 60 * 1000;
+const SDR_RECORDINGS: [&str; 2] = ["iq", "spectrum"];
+
+/// Runners tune their SDR to the given frequency, gain and sample rate before the jobs of the experiment, and upload
+/// the IQ samples or the spectrum snapshot recorded while the jobs run as their artifacts. Jobs fail on the runners
+/// without an SDR.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/sdr",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentSdrRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/sdr")]
+pub async fn update_experiment_sdr(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ExperimentSdrRequest>,
+) -> DefaultResponse {
+    let request = request.into_inner();
+
+    let tuned = request.frequency_hz.is_some();
+    let valid_tuning = request.gain_db.is_some() == tuned && request.sample_rate.is_some() == tuned;
+    let valid_frequency = request.frequency_hz.is_none_or(|frequency_hz| frequency_hz > 0 && frequency_hz <= MAX_SDR_FREQUENCY_HZ);
+    let valid_gain = request.gain_db.is_none_or(|gain_db| gain_db.is_finite());
+    let valid_sample_rate = request.sample_rate.is_none_or(|sample_rate| sample_rate > 0 && sample_rate <= MAX_SDR_SAMPLE_RATE);
+    let valid_recording = request.recording.as_deref().is_none_or(|recording| tuned && SDR_RECORDINGS.contains(&recording)) &&
+        request.recording_ms.is_some() == request.recording.is_some() &&
+        request.recording_ms.is_none_or(|recording_ms| recording_ms > 0 && recording_ms <= MAX_SDR_RECORDING_MS);
+
+    if !valid_tuning || !valid_frequency || !valid_gain || !valid_sample_rate || !valid_recording {
+        return Err(ExperimentErrorMessage::InvalidSdrSettings.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let id = experiments::table
+            .filter(can_edit_experiment(user.id))
+            .filter(experiments::uuid.eq(experiment_id))
+            .select(experiments::id)
+            .first::<ExperimentId>(&conn)
+            .optional()?;
+
+        let id = match id {
+            Some(id) => id,
+            None => return Err(experiment_not_affected(experiment_id, &conn))
+        };
+
+        // validated to be given together
+        let (frequency_hz, gain_db, sample_rate) = match (request.frequency_hz, request.gain_db, request.sample_rate) {
+            (Some(frequency_hz), Some(gain_db), Some(sample_rate)) => (frequency_hz, gain_db, sample_rate),
+            _ => {
+                diesel::delete(experiment_sdr_settings::table.find(id))
+                    .execute(&conn)?;
+
+                return Ok(());
+            }
+        };
+
+        diesel::insert_into(experiment_sdr_settings::table)
+            .values((
+                experiment_sdr_settings::experiment_id.eq(id),
+                experiment_sdr_settings::frequency_hz.eq(frequency_hz),
+                experiment_sdr_settings::gain_db.eq(gain_db),
+                experiment_sdr_settings::sample_rate.eq(sample_rate),
+                experiment_sdr_settings::recording.eq(&request.recording),
+                experiment_sdr_settings::recording_ms.eq(request.recording_ms),
+            ))
+            .on_conflict(experiment_sdr_settings::experiment_id)
+            .do_update()
+            .set((
+                experiment_sdr_settings::frequency_hz.eq(frequency_hz),
+                experiment_sdr_settings::gain_db.eq(gain_db),
+                experiment_sdr_settings::sample_rate.eq(sample_rate),
+                experiment_sdr_settings::recording.eq(&request.recording),
+                experiment_sdr_settings::recording_ms.eq(request.recording_ms),
+            ))
+            .execute(&conn)?;
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+// governors of the linux cpufreq subsystem
+const CPU_GOVERNORS: [&str; 6] = ["performance", "powersave", "userspace", "ondemand", "conservative", "schedutil"];
+const MAX_CPU_FREQUENCY_KHZ: i32 = 10_000_000;
+
+/// Runners apply the given performance settings while the jobs of the experiment run and restore them afterwards.
+/// Settings which are not given are left as is. Settings actually applied are reported in the environment of each job.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/performance",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentPerformanceRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/performance")]
+pub async fn update_experiment_performance(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ExperimentPerformanceRequest>,
+) -> DefaultResponse {
+    let request = request.into_inner();
+
+    let valid_governor = request.governor.as_deref()
+        .is_none_or(|governor| CPU_GOVERNORS.contains(&governor));
+
+    let valid_frequency = request.frequency_khz
+        .is_none_or(|frequency_khz| frequency_khz > 0 && frequency_khz <= MAX_CPU_FREQUENCY_KHZ);
+
+    if !valid_governor || !valid_frequency {
+        return Err(ExperimentErrorMessage::InvalidPerformanceSettings.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let updated = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set((
+                experiments::cpu_governor.eq(request.governor),
+                experiments::cpu_frequency_khz.eq(request.frequency_khz),
+                experiments::disable_turbo.eq(request.disable_turbo),
+            ))
+            .execute(&conn)?;
+
+        if updated == 0 {
+            return Err(experiment_not_affected(experiment_id, &conn));
+        }
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+// temperatures above this are rejected, no device under test is expected to run that hot
+const MAX_THERMAL_LIMIT: f64 = 150.0;
+const MAX_THERMAL_TIMEOUT: i32 = 60 * 60;
+
+/// Jobs of the experiment are dispatched only to the runners reporting that their hottest thermal zone is below the
+/// given temperature, so that the energy measurements are not skewed by a device still hot from the previous job.
+/// Jobs are dispatched regardless once they have waited for a cool runner longer than the timeout.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/thermal-guard",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentThermalGuardRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/thermal-guard")]
+pub async fn update_experiment_thermal_guard(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ExperimentThermalGuardRequest>,
+) -> DefaultResponse {
+    let request = request.into_inner();
+
+    let valid_limit = request.max_temperature
+        .is_none_or(|max_temperature| max_temperature > 0.0 && max_temperature <= MAX_THERMAL_LIMIT);
+
+    if !valid_limit || !(1..=MAX_THERMAL_TIMEOUT).contains(&request.timeout) {
+        return Err(ExperimentErrorMessage::InvalidThermalGuard.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let updated = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set((
+                experiments::max_temperature.eq(request.max_temperature),
+                experiments::thermal_timeout.eq(request.timeout),
+            ))
+            .execute(&conn)?;
+
+        if updated == 0 {
+            return Err(experiment_not_affected(experiment_id, &conn));
+        }
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Success criteria are evaluated with the metrics of the jobs as their results arrive, see `criteria::Criteria`.
+/// Verdicts derived from them are kept apart from the statuses of the jobs.
+#[utoipa::path(
+    put,
+    path = "/experiment/{id}/success-criteria",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = ExperimentSuccessCriteriaRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("experiment/{id}/success-criteria")]
+pub async fn update_experiment_success_criteria(
+    pool: web::Data<DBPool>,
+    experiment_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ExperimentSuccessCriteriaRequest>,
+) -> DefaultResponse {
+    let criteria = request.into_inner().criteria
+        .map(|criteria| criteria.trim().to_string())
+        .filter(|criteria| !criteria.is_empty());
+
+    if criteria.as_deref().is_some_and(|criteria| Criteria::parse(criteria).is_err()) {
+        return Err(ExperimentErrorMessage::InvalidCriteria.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let updated = diesel::update(
+            experiments::table
+                .filter(can_edit_experiment(user.id))
+                .filter(experiments::uuid.eq(experiment_id))
+        )
+            .set(experiments::success_criteria.eq(criteria))
+            .execute(&conn)?;
+
+        if updated == 0 {
+            return Err(experiment_not_affected(experiment_id, &conn));
+        }
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Protected jobs are kept by the retention policy and the purges, they are still deleted along with their
+/// experiment.
+#[utoipa::path(
+    put,
+    path = "/job/{id}/protected",
+    tag = "jobs",
+    params(("id" = Uuid, Path)),
+    request_body = JobProtectedRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("job/{id}/protected")]
+pub async fn update_job_protected(pool: web::Data<DBPool>, job_id: web::Path<Uuid>, user: User, request: web::Json<JobProtectedRequest>)
+                                  -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let protected = request.protected;
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let job_id = jobs::table
+            .inner_join(experiments::table)
+            .filter(can_edit_experiment(user.id))
+            .filter(jobs::uuid.eq(job_id.into_inner()))
+            .select(jobs::id)
+            .first::<JobId>(&conn)?;
+
+        diesel::update(jobs::table.find(job_id))
+            .set(jobs::protected.eq(protected))
+            .execute(&conn)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+const MAX_ANNOTATION_LENGTH: usize = 4096;
+
+/// Annotates the job along with a verdict on its outcome, the status reported by the runner is kept as it is.
+#[utoipa::path(
+    put,
+    path = "/job/{id}/annotation",
+    tag = "jobs",
+    params(("id" = Uuid, Path)),
+    request_body = JobAnnotationRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("job/{id}/annotation")]
+pub async fn update_job_annotation(pool: web::Data<DBPool>, job_id: web::Path<Uuid>, user: User, request: SanitizedJson<JobAnnotationRequest>)
+                                   -> DefaultResponse {
+    let request = request.into_inner();
+
+    if request.annotation.as_ref().is_some_and(|annotation| annotation.len() > MAX_ANNOTATION_LENGTH) {
+        return Err(ExperimentErrorMessage::AnnotationTooLong.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let job_id = jobs::table
+            .inner_join(experiments::table)
+            .filter(can_edit_experiment(user.id))
+            .filter(jobs::uuid.eq(job_id.into_inner()))
+            .select(jobs::id)
+            .first::<JobId>(&conn)?;
+
+        if request.annotation.is_none() && request.verdict.is_none() {
+            return diesel::update(jobs::table.find(job_id))
+                .set((
+                    jobs::annotation.eq(None::<String>),
+                    jobs::verdict.eq(None::<String>),
+                    jobs::annotated_at.eq(None::<NaiveDateTime>),
+                ))
+                .execute(&conn);
+        }
+
+        diesel::update(jobs::table.find(job_id))
+            .set((
+                jobs::annotation.eq(request.annotation),
+                jobs::verdict.eq(request.verdict.map(|verdict| verdict.value())),
+                jobs::annotated_at.eq(now.nullable()),
+            ))
+            .execute(&conn)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Deletes the finished jobs of the experiment, optionally only the ones in given statuses. Protected jobs are kept.
+#[utoipa::path(
+    post,
+    path = "/experiment/{id}/jobs/purge",
+    tag = "experiments",
+    params(("id" = Uuid, Path)),
+    request_body = PurgeJobsRequest,
+    responses((status = 200, body = BulkResponse)),
+    security(("bearer" = [])),
+)]
+#[post("experiment/{id}/jobs/purge")]
+pub async fn purge_jobs(pool: web::Data<DBPool>, experiment_id: web::Path<Uuid>, user: User, request: web::Json<PurgeJobsRequest>)
+                        -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let experiment_id = experiment_id.into_inner();
+
+    let statuses = request.into_inner().statuses
+        .unwrap_or_else(|| vec![JobStatus::Successful, JobStatus::Failed, JobStatus::Cancelled, JobStatus::TimedOut]);
+
+    if statuses.iter().any(|status| !status.is_terminal()) {
+        return Err(Box::new(ErrorMessage::InvalidOperationForStatus));
+    }
+
+    let results = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let experiment = experiments::table
+            .filter(can_edit_experiment(user.id))
+            .filter(experiments::uuid.eq(experiment_id))
+            .first::<Experiment>(&conn)?;
+
+        let statuses = statuses.iter().map(|s| s.value()).collect::<Vec<String>>();
+
+        let mut purged = diesel::delete(
+            jobs::table
+                .filter(jobs::experiment_id.eq(experiment.id))
+                .filter(jobs::status.eq_any(&statuses))
+                .filter(jobs::protected.eq(false))
+        )
+            .returning(jobs::uuid)
+            .get_results::<Uuid>(&conn)?;
+
+        // archived jobs are purged along with the recent ones
+        purged.extend(diesel::delete(
+            archived_jobs::table
+                .filter(archived_jobs::experiment_id.eq(experiment.id))
+                .filter(archived_jobs::status.eq_any(&statuses))
+                .filter(archived_jobs::protected.eq(false))
+        )
+            .returning(archived_jobs::uuid)
+            .get_results::<Uuid>(&conn)?);
+
+        Ok(purged.into_iter().map(BulkItemResult::success).collect::<Vec<BulkItemResult>>())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(BulkResponse { results }))
+}
+
+/// Projects the user is a member of, newest first
+#[utoipa::path(
+    get,
+    path = "/projects",
+    tag = "projects",
+    params(PaginationRequest),
+    responses((status = 200, body = Pagination<Project>)),
+    security(("bearer" = [])),
+)]
+#[get("projects")]
+pub async fn fetch_projects(pool: web::Data<DBPool>, user: User, pagination: web::Query<PaginationRequest>, req: HttpRequest) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let projects = web::block(move || projects::table
+        .filter(can_view_project(user.id))
+        .order(projects::created_at.desc())
+        .select((PROJECT_COLUMNS, CountStarOver))
+        .paginate(pagination.page)
+        .per_page(pagination.per_page)
+        .load_and_count_pages::<Project>(&conn)
+    )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(projects.with_links(&req)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/project/{id}",
+    tag = "projects",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = ProjectDetail)),
+    security(("bearer" = [])),
+)]
+#[get("project/{id}")]
+pub async fn fetch_project(pool: web::Data<DBPool>, project_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let project = web::block(move || -> Result<_, diesel::result::Error> {
+        let (project_id, _) = find_project(project_id.into_inner(), user.id, &conn)?;
+
+        ProjectDetail::load(project_id, &conn)
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(project))
+}
+
+/// Creates a project owned by the user, the user becomes its first member
+#[utoipa::path(
+    post,
+    path = "/project",
+    tag = "projects",
+    request_body = ProjectNameRequest,
+    responses((status = 200, body = ProjectDetail)),
+    security(("bearer" = [])),
+)]
+#[post("project")]
+pub async fn create_project(pool: web::Data<DBPool>, user: User, request: SanitizedJson<ProjectNameRequest>) -> DefaultResponse {
+    if !can_write(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let name = request.into_inner().name;
+
+    if !project::is_valid_name(&name) {
+        return Err(ExperimentErrorMessage::InvalidProjectName.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    let project = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let project_id = diesel::insert_into(projects::table)
+            .values((projects::name.eq(name), projects::owner_id.eq(user.id)))
+            .returning(projects::id)
+            .get_result::<ModelId>(&conn)?;
+
+        diesel::insert_into(project_members::table)
+            .values((project_members::project_id.eq(project_id), project_members::user_id.eq(user.id)))
+            .execute(&conn)?;
+
+        ProjectDetail::load(project_id, &conn)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(project))
+}
+
+/// Projects are modified only by their owners, it fails with 404 if the user is not a member of the project and
+/// with 403 if the user is not its owner
+#[utoipa::path(
+    put,
+    path = "/project/{id}",
+    tag = "projects",
+    params(("id" = Uuid, Path)),
+    request_body = ProjectNameRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("project/{id}")]
+pub async fn update_project_name(pool: web::Data<DBPool>, project_id: web::Path<Uuid>, user: User, request: SanitizedJson<ProjectNameRequest>)
+                                 -> DefaultResponse {
+    let name = request.into_inner().name;
+
+    if !project::is_valid_name(&name) {
+        return Err(ExperimentErrorMessage::InvalidProjectName.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let (project_id, owner_id) = find_project(project_id.into_inner(), user.id, &conn)?;
+
+        if !can_manage_project(&user, owner_id) {
+            return Err(ErrorMessage::NotAllowed.into());
+        }
+
+        diesel::update(projects::table.find(project_id))
+            .set(projects::name.eq(name))
+            .execute(&conn)?;
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Experiments of the deleted project are kept, they are only accessible to their owners afterwards
+#[utoipa::path(
+    delete,
+    path = "/project/{id}",
+    tag = "projects",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[delete("project/{id}")]
+pub async fn delete_project(pool: web::Data<DBPool>, project_id: web::Path<Uuid>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let (project_id, owner_id) = find_project(project_id.into_inner(), user.id, &conn)?;
+
+        if !can_manage_project(&user, owner_id) {
+            return Err(ErrorMessage::NotAllowed.into());
+        }
+
+        diesel::delete(projects::table.find(project_id))
+            .execute(&conn)?;
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Adds the user with the given email to the project, members view the experiments of the project along with
+/// their jobs. Adding a member again does not fail.
+#[utoipa::path(
+    post,
+    path = "/project/{id}/member",
+    tag = "projects",
+    params(("id" = Uuid, Path)),
+    request_body = ProjectMemberRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[post("project/{id}/member")]
+pub async fn add_project_member(pool: web::Data<DBPool>, project_id: web::Path<Uuid>, user: User, request: web::Json<ProjectMemberRequest>)
+                                -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let email = request.into_inner().email;
+
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let (project_id, owner_id) = find_project(project_id.into_inner(), user.id, &conn)?;
+
+        if !can_manage_project(&user, owner_id) {
+            return Err(ErrorMessage::NotAllowed.into());
+        }
+
+        let member_id = users::table
+            .filter(users::email.eq(&email))
+            .select(users::id)
+            .first::<UserId>(&conn)?;
+
+        let added = diesel::insert_into(project_members::table)
+            .values((project_members::project_id.eq(project_id), project_members::user_id.eq(member_id)))
+            .on_conflict_do_nothing()
+            .execute(&conn)?;
+
+        if added > 0 {
+            AuditEntry::new(Some(user.id), "project.member.add")
+                .target("project", project_id)
+                .details(email)
+                .record(&conn)?;
+        }
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Owners remove the members of their projects, members may leave the projects by removing themselves. Owners can
+/// not be removed from their projects.
+#[utoipa::path(
+    delete,
+    path = "/project/{id}/member/{user_id}",
+    tag = "projects",
+    params(("id" = Uuid, Path), ("user_id" = i32, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[delete("project/{id}/member/{user_id}")]
+pub async fn remove_project_member(pool: web::Data<DBPool>, path: web::Path<(Uuid, UserId)>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let (project_id, member_id) = path.into_inner();
+
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let (project_id, owner_id) = find_project(project_id, user.id, &conn)?;
+
+        if member_id == owner_id {
+            return Err(ExperimentErrorMessage::InvalidProjectMember.into());
+        }
+
+        if !can_manage_project(&user, owner_id) && member_id != user.id {
+            return Err(ErrorMessage::NotAllowed.into());
+        }
+
+        let removed = diesel::delete(
+            project_members::table
+                .filter(project_members::project_id.eq(project_id))
+                .filter(project_members::user_id.eq(member_id))
+        )
+            .execute(&conn)?;
+
+        if removed == 0 {
+            return Err(ErrorMessage::ItemNotFound.into());
+        }
+
+        AuditEntry::new(Some(user.id), "project.member.remove")
+            .target("project", project_id)
+            .details(member_id.0.to_string())
+            .record(&conn)?;
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Experiments of the project which do not have preferred labels of their own prefer the runners with these labels,
+/// see `update_experiment_runner_strategy`
+#[utoipa::path(
+    put,
+    path = "/project/{id}/default-labels",
+    tag = "projects",
+    params(("id" = Uuid, Path)),
+    request_body = ProjectDefaultLabelsRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("project/{id}/default-labels")]
+pub async fn update_project_default_labels(
+    pool: web::Data<DBPool>,
+    project_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<ProjectDefaultLabelsRequest>,
+) -> DefaultResponse {
+    let labels = request.into_inner().labels;
+
+    if labels.len() > MAX_PREFERRED_LABELS || labels.iter().any(|label| label.is_empty() || label.len() > MAX_LABEL_LENGTH) {
+        return Err(ExperimentErrorMessage::InvalidLabels.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let (project_id, owner_id) = find_project(project_id.into_inner(), user.id, &conn)?;
+
+        if !can_manage_project(&user, owner_id) {
+            return Err(ErrorMessage::NotAllowed.into());
+        }
+
+        diesel::update(projects::table.find(project_id))
+            .set(projects::default_labels.eq(labels))
+            .execute(&conn)?;
+
+        Ok(())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Experiments of all the members of the project, listed like `experiments`
+#[utoipa::path(
+    get,
+    path = "/project/{id}/experiments",
+    tag = "projects",
+    params(("id" = Uuid, Path), PaginationRequest, ExperimentsRequest),
+    responses((status = 200, body = Pagination<SlimExperiment>)),
+    security(("bearer" = [])),
+)]
+#[get("project/{id}/experiments")]
+pub async fn fetch_project_experiments(
+    pool: web::Data<DBPool>,
+    project_id: web::Path<Uuid>,
+    user: User,
+    pagination: web::Query<PaginationRequest>,
+    request: web::Query<ExperimentsRequest>,
+    req: HttpRequest,
+) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let experiments = web::block(move || -> Result<_, diesel::result::Error> {
+        let (project_id, _) = find_project(project_id.into_inner(), user.id, &conn)?;
+
+        let mut query = experiments::table
+            .filter(can_view_experiment(user.id))
+            .filter(experiments::project_id.eq(project_id))
+            .into_boxed();
+
+        if let Some(starred) = request.starred {
+            query = query.filter(experiments::starred.eq(starred));
+        }
+
+        if !request.include_archived.unwrap_or(false) {
+            query = query.filter(experiments::archived_at.is_null());
+        }
+
+        query
+            .order((experiments::starred.desc(), experiments::created_at.desc()))
+            .select((SLIM_EXPERIMENT_COLUMNS, CountStarOver))
+            .paginate(pagination.page)
+            .per_page(pagination.per_page)
+            .load_and_count_pages::<SlimExperiment>(&conn)
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(experiments.with_links(&req)))
+}
+
+/// Jobs of all the experiments of the project, filtered and sorted like `jobs`
+#[utoipa::path(
+    get,
+    path = "/project/{id}/jobs",
+    tag = "projects",
+    params(("id" = Uuid, Path), PaginationRequest, JobsRequest),
+    responses((status = 200, body = Pagination<SlimJob>)),
+    security(("bearer" = [])),
+)]
+#[get("project/{id}/jobs")]
+pub async fn fetch_project_jobs(
+    pool: web::Data<DBPool>,
+    project_id: web::Path<Uuid>,
+    user: User,
+    pagination: web::Query<PaginationRequest>,
+    request: web::Query<JobsRequest>,
+    req: HttpRequest,
+) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let jobs = web::block(move || -> Result<_, diesel::result::Error> {
+        let (project_id, _) = find_project(project_id.into_inner(), user.id, &conn)?;
+
+        load_jobs(user.id, Some(project_id), request.into_inner(), pagination.into_inner(), &conn)
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(jobs.with_links(&req)))
+}
+
+/// Id and owner of the project, it is not found if the user is not a member of it
+fn find_project(project_id: Uuid, user_id: UserId, conn: &PgConnection) -> QueryResult<(ModelId, UserId)> {
+    projects::table
+        .filter(can_view_project(user_id))
+        .filter(projects::uuid.eq(project_id))
+        .select((projects::id, projects::owner_id))
+        .first::<(ModelId, UserId)>(conn)
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/runner/{id}/name",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    request_body = RunnerNameRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("admin/runner/{id}/name")]
+pub async fn update_runner_name(pool: web::Data<DBPool>, runner_id: web::Path<Uuid>, user: User, request: SanitizedJson<RunnerNameRequest>)
+                                -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+    let name = request.into_inner().name;
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let runner_id = Runner::id_of(runner_id, &conn)?;
+
+        diesel::update(runners::table.find(runner_id))
+            .set(runners::name.eq(&name))
+            .get_result::<Runner>(&conn)?;
+
+        AuditEntry::new(Some(user.id), "runner.rename")
+            .target("runner", runner_id)
+            .details(name)
+            .record(&conn)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/runner/{id}/labels",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    request_body = RunnerLabelsRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("admin/runner/{id}/labels")]
+pub async fn update_runner_labels(pool: web::Data<DBPool>, runner_id: web::Path<Uuid>, user: User, request: SanitizedJson<RunnerLabelsRequest>)
+                                  -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+    let labels = request.into_inner().labels;
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let runner_id = Runner::id_of(runner_id, &conn)?;
+
+        diesel::update(runners::table.find(runner_id))
+            .set(runners::labels.eq(&labels))
+            .get_result::<Runner>(&conn)?;
+
+        AuditEntry::new(Some(user.id), "runner.labels")
+            .target("runner", runner_id)
+            .details(labels.join(","))
+            .record(&conn)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Disabled runners can not join the server and the connected ones do not receive new jobs.
+#[utoipa::path(
+    put,
+    path = "/admin/runner/{id}/disabled",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    request_body = RunnerDisabledRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("admin/runner/{id}/disabled")]
+pub async fn update_runner_disabled(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    runner_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<RunnerDisabledRequest>,
+) -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+    let disabled = request.disabled;
+
+    let runner_id = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let runner_id = Runner::id_of(runner_id, &conn)?;
+
+        diesel::update(runners::table.find(runner_id))
+            .set(runners::disabled.eq(disabled))
+            .get_result::<Runner>(&conn)?;
+
+        AuditEntry::new(Some(user.id), if disabled { "runner.disable" } else { "runner.enable" })
+            .target("runner", runner_id)
+            .record(&conn)?;
+
+        Ok(runner_id)
+    }))
+        .await?;
+
+    experiment_server.do_send(SetRunnerDisabledMessage { runner_id, disabled });
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Runner can not be deleted while it is running a job. Historical jobs of the runner are kept.
+#[utoipa::path(
+    delete,
+    path = "/admin/runner/{id}",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[delete("admin/runner/{id}")]
+pub async fn delete_runner(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    runner_id: web::Path<Uuid>,
+    user: User,
+) -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+
+    let runner_id = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let runner_id = Runner::id_of(runner_id, &conn)?;
+
+        let running_jobs = jobs::table
+            .filter(jobs::runner_id.eq(runner_id))
+            .filter(jobs::status.eq(JobStatus::Running.value()))
+            .select(jobs::id)
+            .load::<JobId>(&conn)?;
+
+        if !running_jobs.is_empty() {
+            return Err(ErrorMessage::InvalidOperationForStatus.into());
+        }
+
+        diesel::delete(runners::table.find(runner_id))
+            .get_result::<Runner>(&conn)?;
+
+        AuditEntry::new(Some(user.id), "runner.delete")
+            .target("runner", runner_id)
+            .record(&conn)?;
+
+        Ok(runner_id)
+    }))
+        .await?;
+
+    experiment_server.do_send(RemoveRunnerMessage { runner_id });
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Creates a runner without a claim code, returned token is given to the runner for connecting. Useful for
+/// the runners which are set up by the admins themselves.
+#[utoipa::path(
+    post,
+    path = "/admin/runner",
+    tag = "admin",
+    request_body = ProvisionRunnerRequest,
+    responses((status = 200, body = ProvisionedRunner)),
+    security(("bearer" = [])),
+)]
+#[post("admin/runner")]
+pub async fn provision_runner(pool: web::Data<DBPool>, hash: web::Data<Hash>, user: User, request: SanitizedJson<ProvisionRunnerRequest>)
+                              -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let request = request.into_inner();
+
+    let provisioned = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let (runner, token) = claim::create_runner(request.name, request.labels.unwrap_or_default(), &hash, &conn)?;
+
+        AuditEntry::new(Some(user.id), "runner.provision")
+            .target("runner", runner.id)
+            .details(runner.name.clone())
+            .record(&conn)?;
+
+        Ok(ProvisionedRunner { runner_id: runner.uuid, token })
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(provisioned))
+}
+
+/// Issues a new access key for the runner, previous key of the runner is invalidated. Only the hash of
+/// the key is stored, the returned token can not be recovered later.
+#[utoipa::path(
+    post,
+    path = "/admin/runner/{id}/access-key",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = TokenResponse)),
+    security(("bearer" = [])),
+)]
+#[post("admin/runner/{id}/access-key")]
+pub async fn issue_runner_access_key(pool: web::Data<DBPool>, hash: web::Data<Hash>, runner_id: web::Path<Uuid>, user: User)
+                                     -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    let runner_id = runner_id.into_inner();
+
+    let (token, _) = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let runner_id = Runner::id_of(runner_id, &conn)?;
+        let issued = RunnerToken::issue(runner_id, None, &hash, &conn)?;
+
+        AuditEntry::new(Some(user.id), "runner.access_key")
+            .target("runner", runner_id)
+            .record(&conn)?;
+
+        Ok(issued)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(TokenResponse { token }))
+}
+
+/// Rotates the access key of the runner presenting a valid token. Presented key stays valid until
+/// the runner connects with the new one.
+#[utoipa::path(
+    post,
+    path = "/runner/token",
+    tag = "runner-client",
+    request_body = TokenResponse,
+    responses((status = 200, body = TokenResponse)),
+)]
+#[post("runner/token")]
+pub async fn rotate_runner_token(pool: web::Data<DBPool>, hash: web::Data<Hash>, request: web::Json<TokenResponse>) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let token = RunnerToken::decode(request.token.as_str(), &hash)?;
+
+    let (token, _) = web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        let access_key_hash = hash.sign256(token.access_key.as_str());
+
+        let runner = runners::table
+            .filter(runners::access_key_hash.eq(&access_key_hash).or(runners::previous_access_key_hash.eq(&access_key_hash)))
+            .first::<Runner>(&conn)?;
+
+        if !token.is_issued_for(runner.id) {
+            return Err(ErrorMessage::InvalidToken.into());
+        }
+
+        if runner.disabled {
+            return Err(ExperimentErrorMessage::RunnerDisabled.into());
+        }
+
+        RunnerToken::issue(runner.id, Some(access_key_hash), &hash, &conn)
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(TokenResponse { token }))
+}
+
+/// Mints a one-time ticket for connecting to `ws`, so that the token of the runner is not put in the url. The
+/// ticket expires in a short while and can only be redeemed once.
+#[utoipa::path(
+    post,
+    path = "/runner/ticket",
+    tag = "runner-client",
+    responses((status = 200, body = ConnectTicket)),
+    security(("bearer" = [])),
+)]
+#[post("runner/ticket")]
+pub async fn create_connect_ticket(pool: web::Data<DBPool>, hash: web::Data<Hash>, req: HttpRequest) -> DefaultResponse {
+    let token = bearer_token(&req)
+        .ok_or(ExperimentErrorMessage::CredentialsNotFound)?;
+
+    let (runner, access_key_hash, token) = identify(pool.get_ref(), hash.get_ref(), token).await?;
+    let expires_at = token.claims.exp;
+
+    if runner.disabled {
+        return Err(ExperimentErrorMessage::RunnerDisabled.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    let ticket = web::block(move || ConnectTicket::issue(runner.id, access_key_hash, expires_at, &hash, &conn))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ticket))
+}
+
+/// Generates a short lived code which is used by a new runner to enroll itself.
+#[utoipa::path(
+    post,
+    path = "/admin/claim-code",
+    tag = "admin",
+    request_body = ClaimCodeRequest,
+    responses((status = 200, body = ClaimCode)),
+    security(("bearer" = [])),
+)]
+#[post("admin/claim-code")]
+pub async fn create_claim_code(pool: web::Data<DBPool>, hash: web::Data<Hash>, user: User, request: SanitizedJson<ClaimCodeRequest>)
+                               -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let request = request.into_inner();
+
+    let claim_code = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        AuditEntry::new(Some(user.id), "claim_code.create")
+            .details(request.name.clone())
+            .record(&conn)?;
+
+        claim::create_code(user.id, request.name, request.labels.unwrap_or_default(), &hash, &conn)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(claim_code))
+}
+
+/// Enrolls a new runner with the claim code, returned token is used by the runner for connecting.
+#[utoipa::path(
+    post,
+    path = "/runner/claim",
+    tag = "runner-client",
+    request_body = ClaimRunnerRequest,
+    responses((status = 200, body = TokenResponse)),
+)]
+#[post("runner/claim")]
+pub async fn claim_runner(pool: web::Data<DBPool>, hash: web::Data<Hash>, request: web::Json<ClaimRunnerRequest>) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let token = web::block(move || claim::claim(request.code.as_str(), &hash, &conn))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(TokenResponse { token }))
+}
+
+/// Restricts the networks the runner can connect from, in addition to the global allowlist. Empty list
+/// lifts the restriction.
+#[utoipa::path(
+    put,
+    path = "/admin/runner/{id}/allowed-networks",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    request_body = RunnerAllowedNetworksRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("admin/runner/{id}/allowed-networks")]
+pub async fn update_runner_allowed_networks(pool: web::Data<DBPool>, runner_id: web::Path<Uuid>, user: User, request: web::Json<RunnerAllowedNetworksRequest>)
+                                            -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let networks = request.into_inner().networks
+        .into_iter()
+        .map(|network| parse_network(network.trim()).map(|n| n.to_string()))
+        .collect::<Option<Vec<String>>>()
+        .ok_or_else(|| ExperimentErrorMessage::InvalidNetwork)?;
+
+    let conn = pool.get().unwrap();
+
+    let runner_id = runner_id.into_inner();
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let runner_id = Runner::id_of(runner_id, &conn)?;
+
+        diesel::update(runners::table.find(runner_id))
+            .set(runners::allowed_networks.eq(&networks))
+            .get_result::<Runner>(&conn)?;
+
+        AuditEntry::new(Some(user.id), "runner.allowed_networks")
+            .target("runner", runner_id)
+            .details(networks.join(","))
+            .record(&conn)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/runner/{id}/certificate",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    request_body = RunnerCertificateRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("admin/runner/{id}/certificate")]
+pub async fn update_runner_certificate(pool: web::Data<DBPool>, runner_id: web::Path<Uuid>, user: User, request: web::Json<RunnerCertificateRequest>)
+                                       -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let fingerprint = match &request.fingerprint {
+        Some(fingerprint) => Some(normalize_fingerprint(fingerprint).ok_or_else(|| ExperimentErrorMessage::InvalidFingerprint)?),
+        None => None
+    };
+
+    let conn = pool.get().unwrap();
+
+    let runner_id = runner_id.into_inner();
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let runner_id = Runner::id_of(runner_id, &conn)?;
+
+        diesel::update(runners::table.find(runner_id))
+            .set(runners::certificate_fingerprint.eq(&fingerprint))
+            .get_result::<Runner>(&conn)?;
+
+        let entry = AuditEntry::new(Some(user.id), "runner.certificate")
+            .target("runner", runner_id);
+
+        match fingerprint {
+            Some(fingerprint) => entry.details(fingerprint).record(&conn),
+            None => entry.record(&conn)
+        }
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/runner/{id}/auto-update",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    request_body = RunnerAutoUpdateRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("admin/runner/{id}/auto-update")]
+pub async fn update_runner_auto_update(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    runner_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<RunnerAutoUpdateRequest>,
+) -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+    let auto_update = request.auto_update;
+
+    let runner_id = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let runner_id = Runner::id_of(runner_id, &conn)?;
+
+        diesel::update(runners::table.find(runner_id))
+            .set(runners::auto_update.eq(auto_update))
+            .get_result::<Runner>(&conn)?;
+
+        AuditEntry::new(Some(user.id), "runner.auto_update")
+            .target("runner", runner_id)
+            .details(auto_update.to_string())
+            .record(&conn)?;
+
+        Ok(runner_id)
+    }))
+        .await?;
+
+    if auto_update {
+        experiment_server.do_send(CheckClientUpdateMessage { runner_ids: Some(vec![runner_id]) });
+    }
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/client-releases",
+    tag = "admin",
+    responses((status = 200, body = Vec<ClientRelease>)),
+    security(("bearer" = [])),
+)]
+#[get("admin/client-releases")]
+pub async fn fetch_client_releases(pool: web::Data<DBPool>, user: User) -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    let releases = web::block(move || client_releases::table
+        .order(client_releases::created_at.desc())
+        .load::<ClientRelease>(&conn)
+    )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(releases))
+}
+
+/// Publishes a client release which is signed offline, connected runners opted in to auto update
+/// are moved to it immediately.
+#[utoipa::path(
+    post,
+    path = "/admin/client-release",
+    tag = "admin",
+    request_body = ClientReleaseRequest,
+    responses((status = 200, body = ClientRelease)),
+    security(("bearer" = [])),
+)]
+#[post("admin/client-release")]
+pub async fn create_client_release(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    user: User,
+    request: web::Json<ClientReleaseRequest>,
+) -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let request = request.into_inner();
+
+    let is_valid = !request.version.is_empty() && request.version.len() <= 32 &&
+        !request.os.is_empty() && request.os.len() <= 32 &&
+        !request.arch.is_empty() && request.arch.len() <= 32 &&
+        (request.url.starts_with("https://") || request.url.starts_with("http://")) &&
+        request.sha256.len() == 64 && request.sha256.chars().all(|c| c.is_ascii_hexdigit()) &&
+        // Ed25519 signatures are 64 bytes
+        base64::decode(request.signature.as_str()).map_or(false, |signature| signature.len() == 64);
+
+    if !is_valid {
+        return Err(ExperimentErrorMessage::InvalidRelease.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    let release = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let release = diesel::insert_into(client_releases::table)
+            .values((
+                client_releases::version.eq(request.version),
+                client_releases::os.eq(request.os),
+                client_releases::arch.eq(request.arch),
+                client_releases::url.eq(request.url),
+                client_releases::sha256.eq(request.sha256.to_lowercase()),
+                client_releases::signature.eq(request.signature),
+            ))
+            .get_result::<ClientRelease>(&conn)?;
+
+        AuditEntry::new(Some(user.id), "client_release.create")
+            .target("client_release", release.id)
+            .details(format!("{} {}/{}", release.version, release.os, release.arch))
+            .record(&conn)?;
+
+        Ok(release)
+    }))
+        .await?;
+
+    experiment_server.do_send(CheckClientUpdateMessage { runner_ids: None });
+
+    Ok(HttpResponse::Ok().json(release))
+}
+
+/// Sends an administrative command to a connected runner. Result of the command is recorded once the
+/// runner reports it.
+#[utoipa::path(
+    post,
+    path = "/admin/runner/{id}/command",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    request_body = RunnerCommandRequest,
+    responses((status = 200, body = RunnerCommand)),
+    security(("bearer" = [])),
+)]
+#[post("admin/runner/{id}/command")]
+pub async fn create_runner_command(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    runner_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<RunnerCommandRequest>,
+) -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+    let kind = request.kind;
+
+    let runner_id = web::block(move || Runner::id_of(runner_id, &conn))
+        .await?;
+
+    let connected_runners = experiment_server.send(FetchConnectedRunnersMessage)
+        .await
+        .map_err(|e| {
+            error!("Error while fetching connected runners from ExperimentServer: {:?}", e);
+            ErrorMessage::UnknownError
+        })?;
+
+    if !connected_runners.contains(&runner_id) {
+        return Err(ExperimentErrorMessage::RunnerNotConnected.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    let command = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        AuditEntry::new(Some(user.id), "runner.command")
+            .target("runner", runner_id)
+            .details(kind.value())
+            .record(&conn)?;
+
+        diesel::insert_into(runner_commands::table)
+            .values((
+                runner_commands::runner_id.eq(runner_id),
+                runner_commands::kind.eq(kind.value()),
+                runner_commands::created_by.eq(user.id),
+            ))
+            .returning(RUNNER_COMMAND_COLUMNS)
+            .get_result::<RunnerCommand>(&conn)
+    }))
+        .await?;
+
+    let delivered = experiment_server.send(RunnerCommandMessage {
+        runner_id,
+        command: client::RunnerCommand { command_id: command.id, kind: kind.into() },
+    })
+        .await
+        .map_err(|e| {
+            error!("Error while sending runner command to ExperimentServer: {:?}", e);
+            ErrorMessage::UnknownError
+        })?;
+
+    // Runner may disconnect in the meantime
+    if !delivered {
+        let conn = pool.get().unwrap();
+        let command_id = command.id;
+
+        web::block(move || RunnerCommand::finish(command_id, runner_id, CommandStatus::Failed, "runner is not connected".to_string(), &conn))
+            .await?;
+
+        return Err(ExperimentErrorMessage::RunnerNotConnected.into());
+    }
+
+    Ok(HttpResponse::Ok().json(command))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/runner/{id}/commands",
+    tag = "admin",
+    params(("id" = Uuid, Path), PaginationRequest),
+    responses((status = 200, body = Pagination<RunnerCommand>)),
+    security(("bearer" = [])),
+)]
+#[get("admin/runner/{id}/commands")]
+pub async fn fetch_runner_commands(
+    pool: web::Data<DBPool>,
+    runner_id: web::Path<Uuid>,
+    user: User,
+    pagination: web::Query<PaginationRequest>,
+    req: HttpRequest,
+) -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+
+    let commands = web::block(move || runner_commands::table
+        .inner_join(runners::table)
+        .filter(runners::uuid.eq(runner_id))
+        .order(runner_commands::created_at.desc())
+        .select((RUNNER_COMMAND_COLUMNS, CountStarOver))
+        .paginate(pagination.page)
+        .per_page(pagination.per_page)
+        .load_and_count_pages::<RunnerCommand>(&conn)
+    )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(commands.with_links(&req)))
+}
+
+/// Changes the log filter of a connected runner's client and optionally makes it ship its own logs
+/// for a limited time.
+#[utoipa::path(
+    put,
+    path = "/admin/runner/{id}/log-level",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    request_body = RunnerLogLevelRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("admin/runner/{id}/log-level")]
+pub async fn update_runner_log_level(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    runner_id: web::Path<Uuid>,
+    user: User,
+    request: web::Json<RunnerLogLevelRequest>,
+) -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let request = request.into_inner();
+
+    // filters in the RUST_LOG syntax, e.g. testbed=debug,awc=info
+    let is_valid_level = request.level.as_ref().map_or(true, |level| {
+        !level.is_empty() && level.len() <= 255 &&
+            level.chars().all(|c| c.is_ascii_alphanumeric() || "_=,:-".contains(c))
+    });
+
+    let is_valid_window = request.ship_seconds
+        .map_or(true, |seconds| seconds > 0 && seconds <= MAX_LOG_SHIPPING.as_secs());
+
+    if !is_valid_level || !is_valid_window {
+        return Err(ExperimentErrorMessage::InvalidLogLevel.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+
+    let runner_id = web::block(move || Runner::id_of(runner_id, &conn))
+        .await?;
+
+    let delivered = experiment_server.send(RunnerLogLevelMessage {
+        runner_id,
+        log_level: client::SetLogLevel { level: request.level, ship_seconds: request.ship_seconds },
+    })
+        .await
+        .map_err(|e| {
+            error!("Error while sending log level to ExperimentServer: {:?}", e);
+            ErrorMessage::UnknownError
+        })?;
+
+    if !delivered {
+        return Err(ExperimentErrorMessage::RunnerNotConnected.into());
+    }
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/runner/{id}/client-logs",
+    tag = "admin",
+    params(("id" = Uuid, Path), PaginationRequest),
+    responses((status = 200, body = Pagination<RunnerClientLog>)),
+    security(("bearer" = [])),
+)]
+#[get("admin/runner/{id}/client-logs")]
+pub async fn fetch_runner_client_logs(
+    pool: web::Data<DBPool>,
+    runner_id: web::Path<Uuid>,
+    user: User,
+    pagination: web::Query<PaginationRequest>,
+    req: HttpRequest,
+) -> DefaultResponse {
+    if !can_view_runner_logs(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let runner_id = runner_id.into_inner();
+
+    let logs = web::block(move || runner_client_logs::table
+        .inner_join(runners::table)
+        .filter(runners::uuid.eq(runner_id))
+        .order(runner_client_logs::created_at.desc())
+        .select((runner_client_logs::all_columns, CountStarOver))
+        .paginate(pagination.page)
+        .per_page(pagination.per_page)
+        .load_and_count_pages::<RunnerClientLog>(&conn)
+    )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(logs.with_links(&req)))
+}
+
+/// Counts of the message limit violations of the runners since the app is started.
+#[utoipa::path(
+    get,
+    path = "/admin/runners/limit-metrics",
+    tag = "admin",
+    responses((status = 200, body = LimitMetricsSnapshot)),
+    security(("bearer" = [])),
+)]
+#[get("admin/runners/limit-metrics")]
+pub async fn fetch_runner_limit_metrics(limits: web::Data<SessionLimits>, user: User) -> DefaultResponse {
+    if !can_admin_runner(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    Ok(HttpResponse::Ok().json(limits.metrics()))
+}
+
+// an hour
+const DEFAULT_STUCK_JOB_AGE: i64 = 60 * 60;
+
+/// Jobs which are pending or running for longer than expected, e.g. since their runner does not report
+/// the result of the job while it is still connected.
+#[utoipa::path(
+    get,
+    path = "/admin/jobs/stuck",
+    tag = "admin",
+    params(StuckJobsRequest),
+    responses((status = 200, body = Vec<SlimJob>)),
+    security(("bearer" = [])),
+)]
+#[get("admin/jobs/stuck")]
+pub async fn fetch_stuck_jobs(pool: web::Data<DBPool>, user: User, request: web::Query<StuckJobsRequest>) -> DefaultResponse {
+    if !can_admin_jobs(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let older_than = request.older_than.unwrap_or(DEFAULT_STUCK_JOB_AGE);
+
+    let jobs = web::block(move || jobs::table
+        .inner_join(experiments::table)
+        .left_join(runners::table)
+        .filter(
+            jobs::status.eq(JobStatus::Pending.value()).and(jobs::created_at.lt(now - older_than.seconds()))
+                .or(jobs::status.eq(JobStatus::Running.value()).and(jobs::started_at.lt((now - older_than.seconds()).nullable())))
+        )
+        .order(jobs::id.asc())
+        .select(slim_job_columns())
+        .load::<SlimJob>(&conn)
+    )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(jobs))
+}
+
+/// Queues a copy of the job, the job itself is cancelled if it is not finished yet. Like cancelling, a
+/// running job is not interrupted on its runner. Jobs moved into the archive can be requeued as well.
+#[utoipa::path(
+    post,
+    path = "/admin/job/{id}/requeue",
+    tag = "admin",
+    params(("id" = Uuid, Path), RequeueJobRequest),
+    responses((status = 200, body = PublicJob)),
+    security(("bearer" = [])),
+)]
+#[post("admin/job/{id}/requeue")]
+pub async fn requeue_job(
+    pool: web::Data<DBPool>,
+    experiment_server: web::Data<Addr<ExperimentServer>>,
+    job_id: web::Path<Uuid>,
+    user: User,
+    request: web::Query<RequeueJobRequest>,
+) -> DefaultResponse {
+    if !can_admin_jobs(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let job_id = job_id.into_inner();
+    let runner_id = request.runner_id;
+
+    let job = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let job = jobs::table
+            .filter(jobs::uuid.eq(job_id))
+            .for_update()
+            .first::<Job>(&conn)
+            .optional()?;
+
+        // archived jobs are finished, they are requeued without being locked
+        let job = match job {
+            Some(job) => job,
+            None => all_jobs::table
+                .filter(all_jobs::uuid.eq(job_id))
+                .select(all_jobs::all_columns)
+                .first::<Job>(&conn)?
+        };
+
+        let runner = match runner_id {
+            Some(runner_id) => runners::table
+                .filter(runners::uuid.eq(runner_id))
+                .first::<Runner>(&conn)?,
+            None => runners::table
+                .find(job.runner_id.ok_or(ErrorMessage::InvalidOperationForStatus)?)
+                .first::<Runner>(&conn)?
+        };
+
+        if runner.disabled {
+            return Err(ExperimentErrorMessage::RunnerDisabled.into());
+        }
+
+        let archived = experiments::table
+            .find(job.experiment_id)
+            .select(experiments::archived_at.is_not_null())
+            .first::<bool>(&conn)?;
+
+        if archived {
+            return Err(ExperimentErrorMessage::ExperimentArchived.into());
+        }
+
+        if !job.status.is_terminal() {
+            // Users are notified about the cancelled jobs by the job status events
+            JobStatus::transition_to(job.id, JobStatus::Cancelled).apply(&conn)?;
+        }
+
+        let requeued = diesel::insert_into(jobs::table)
+            .values((
+                jobs::experiment_id.eq(job.experiment_id),
+                jobs::runner_id.eq(runner.id),
+                jobs::code_hash.eq(job.code_hash),
+                jobs::ansi_mode.eq(job.ansi_mode.value()),
+                jobs::hooks.eq(job.hooks),
+                jobs::firmware_id.eq(job.firmware_id)
+            ))
+            .get_result::<Job>(&conn)?;
+
+        AuditEntry::new(Some(user.id), "job.requeue")
+            .target("job", requeued.id)
+            .details(format!("requeued from job {}", job.id))
+            .record(&conn)?;
+
+        ActivityEntry::new(requeued.experiment_id, Some(user.id), ActivityKind::RunStarted)
+            .job(requeued.id)
+            .details(runner.name)
+            .record(&conn)?;
+
+        Ok(PublicJob::load(requeued, &conn)?)
+    }))
+        .await?;
+
+    let job_id = job.job.id;
+
+    if let Err(e) = experiment_server.send(RunExperimentMessage { job_id })
+        .await {
+        error!("Error while sending run to ExperimentServer: {:?}", e);
+
+        web::block(move || JobStatus::transition_to(job_id, JobStatus::Failed)
+            .apply(&pool.get().unwrap())
+        )
+            .await?;
+    }
+
+    Ok(HttpResponse::Ok().json(job))
+}
+
+/// Quotas of the project, runs and moving the experiments into the project fail with 409 once they are reached.
+/// Lowering a quota does not affect the experiments or the jobs the project already has.
+#[utoipa::path(
+    put,
+    path = "/admin/project/{id}/quota",
+    tag = "admin",
+    params(("id" = Uuid, Path)),
+    request_body = ProjectQuotaRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[put("admin/project/{id}/quota")]
+pub async fn update_project_quota(pool: web::Data<DBPool>, project_id: web::Path<Uuid>, user: User, request: web::Json<ProjectQuotaRequest>)
+                                  -> DefaultResponse {
+    if !can_admin_projects(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let request = request.into_inner();
+
+    if request.max_experiments.is_some_and(|max| max < 1) || request.max_active_jobs.is_some_and(|max| max < 1) {
+        return Err(ExperimentErrorMessage::InvalidProjectQuota.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let project_id = diesel::update(projects::table.filter(projects::uuid.eq(project_id.into_inner())))
+            .set((projects::max_experiments.eq(request.max_experiments), projects::max_active_jobs.eq(request.max_active_jobs)))
+            .returning(projects::id)
+            .get_result::<ModelId>(&conn)?;
+
+        let limit = |max: Option<i32>| max.map(|max| max.to_string()).unwrap_or_else(|| String::from("unlimited"));
+
+        AuditEntry::new(Some(user.id), "project.quota")
+            .target("project", project_id)
+            .details(format!("experiments={},active_jobs={}", limit(request.max_experiments), limit(request.max_active_jobs)))
+            .record(&conn)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Deactivates the user, e.g. once they leave the lab. User can not sign in afterwards, their sessions are revoked
+/// and the queued jobs of their experiments are cancelled. Experiments and the finished jobs are kept, the running
+/// jobs are let finish.
+#[utoipa::path(
+    post,
+    path = "/admin/user/{id}/deactivate",
+    tag = "admin",
+    params(("id" = UserId, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[post("admin/user/{id}/deactivate")]
+pub async fn deactivate_user(pool: web::Data<DBPool>, user_id: web::Path<UserId>, user: User) -> DefaultResponse {
+    let user_id = user_id.into_inner();
+
+    // admins can not lock themselves out
+    if !user.is_admin() || user_id == user.id {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let status = users::table
+            .find(user_id)
+            .select(users::status)
+            .first::<UserStatus>(&conn)?;
+
+        if status == UserStatus::Deactivated {
+            return Err(ErrorMessage::InvalidOperationForStatus.into());
+        }
+
+        diesel::update(users::table.find(user_id))
+            .set((
+                users::status.eq(UserStatus::Deactivated.value()),
+                users::status_before_deactivation.eq(status.value()),
+            ))
+            .execute(&conn)?;
+
+        diesel::delete(sessions::table.filter(sessions::user_id.eq(user_id)))
+            .execute(&conn)?;
+
+        // locked, so that the queued jobs are not dispatched while they are cancelled
+        let queued = jobs::table
+            .inner_join(experiments::table)
+            .filter(owned_by(user_id))
+            .filter(jobs::status.eq(JobStatus::Pending.value()))
+            .select(jobs::id)
+            .for_update()
+            .load::<JobId>(&conn)?;
+
+        for job_id in &queued {
+            JobStatus::transition_to(*job_id, JobStatus::Cancelled).apply(&conn)?;
+        }
+
+        AuditEntry::new(Some(user.id), "user.deactivate")
+            .target("user", user_id)
+            .details(format!("cancelled_jobs={}", queued.len()))
+            .record(&conn)?;
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Lets the deactivated user sign in again with the status they had before, the cancelled jobs are not queued back.
+#[utoipa::path(
+    post,
+    path = "/admin/user/{id}/reactivate",
+    tag = "admin",
+    params(("id" = UserId, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[post("admin/user/{id}/reactivate")]
+pub async fn reactivate_user(pool: web::Data<DBPool>, user_id: web::Path<UserId>, user: User) -> DefaultResponse {
+    if !user.is_admin() {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let user_id = user_id.into_inner();
+
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let (status, previous) = users::table
+            .find(user_id)
+            .select((users::status, users::status_before_deactivation))
+            .for_update()
+            .first::<(UserStatus, Option<String>)>(&conn)?;
+
+        if status != UserStatus::Deactivated {
+            return Err(ErrorMessage::InvalidOperationForStatus.into());
+        }
+
+        // the users deactivated before this was recorded are not known to have verified their email
+        let restored = previous
+            .map(UserStatus::build_from_string)
+            .unwrap_or(UserStatus::NotVerified);
+
+        diesel::update(users::table.find(user_id))
+            .set((
+                users::status.eq(restored.value()),
+                users::status_before_deactivation.eq(None::<String>),
+            ))
+            .execute(&conn)?;
+
+        AuditEntry::new(Some(user.id), "user.reactivate")
+            .target("user", user_id)
+            .record(&conn)?;
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+const MAX_AUDIT_LOGS: i64 = 1000;
+
+/// Entries of the audit log in the order they are recorded. Following entries are fetched by passing the
+/// id of the last entry as `after`.
+#[utoipa::path(
+    get,
+    path = "/admin/audit-logs",
+    tag = "admin",
+    params(AuditLogsRequest),
+    responses((status = 200, body = Vec<AuditLog>)),
+    security(("bearer" = [])),
+)]
+#[get("admin/audit-logs")]
+pub async fn fetch_audit_logs(pool: web::Data<DBPool>, user: User, request: web::Query<AuditLogsRequest>) -> DefaultResponse {
+    if !can_view_audit_logs(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let request = request.into_inner();
+
+    let logs = web::block(move || {
+        let mut query = audit_logs::table
+            .filter(audit_logs::id.gt(request.after.unwrap_or(0)))
+            .into_boxed();
+
+        if let Some(since) = request.since {
+            query = query.filter(audit_logs::created_at.ge(since));
+        }
+
+        if let Some(until) = request.until {
+            query = query.filter(audit_logs::created_at.lt(until));
+        }
+
+        if let Some(action) = request.action {
+            query = query.filter(audit_logs::action.eq(action));
+        }
+
+        query
+            .order(audit_logs::id.asc())
+            .limit(request.limit.unwrap_or(MAX_AUDIT_LOGS).clamp(1, MAX_AUDIT_LOGS))
+            .load::<AuditLog>(&conn)
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(logs))
+}
+
+const DEFAULT_STATS_DAYS: i64 = 30;
+const MAX_STATS_DAYS: i64 = 365;
+
+/// Totals and the daily statistics of the testbed for the operations dashboard
+#[utoipa::path(
+    get,
+    path = "/admin/stats",
+    tag = "admin",
+    params(AdminStatsRequest),
+    responses((status = 200, body = AdminStats)),
+    security(("bearer" = [])),
+)]
+#[get("admin/stats")]
+pub async fn fetch_admin_stats(pool: web::Data<DBPool>, user: User, request: web::Query<AdminStatsRequest>) -> DefaultResponse {
+    if !can_view_stats(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let days = request.days.unwrap_or(DEFAULT_STATS_DAYS).clamp(1, MAX_STATS_DAYS);
+
+    let stats = web::block(move || AdminStats::load(days, &conn))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+const DEFAULT_ACCOUNTING_MONTHS: i64 = 12;
+const MAX_ACCOUNTING_MONTHS: i64 = 120;
+
+/// Costs of the jobs rolled up per month and cost center, the last months including the current one. Costs are
+/// recorded and rolled up periodically, so the recently finished jobs may not be counted yet.
+#[utoipa::path(
+    get,
+    path = "/accounting/monthly",
+    tag = "admin",
+    params(MonthlyCostsRequest),
+    responses((status = 200, body = [MonthlyCost])),
+    security(("bearer" = [])),
+)]
+#[get("accounting/monthly")]
+pub async fn fetch_monthly_costs(pool: web::Data<DBPool>, user: User, request: web::Query<MonthlyCostsRequest>) -> DefaultResponse {
+    if !can_view_accounting(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let months = request.months.unwrap_or(DEFAULT_ACCOUNTING_MONTHS).clamp(1, MAX_ACCOUNTING_MONTHS);
+
+    let costs = web::block(move || load_monthly_costs(months, &conn))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(costs))
+}
+
+/// Monthly costs as csv for the chargeback reports, see `fetch_monthly_costs`
+#[utoipa::path(
+    get,
+    path = "/accounting/monthly/export",
+    tag = "admin",
+    params(MonthlyCostsRequest),
+    responses((status = 200, body = String, content_type = "text/csv")),
+    security(("bearer" = [])),
+)]
+#[get("accounting/monthly/export")]
+pub async fn export_monthly_costs(pool: web::Data<DBPool>, user: User, request: web::Query<MonthlyCostsRequest>) -> DefaultResponse {
+    if !can_view_accounting(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let months = request.months.unwrap_or(DEFAULT_ACCOUNTING_MONTHS).clamp(1, MAX_ACCOUNTING_MONTHS);
+
+    let costs = web::block(move || load_monthly_costs(months, &conn))
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .header(header::CONTENT_DISPOSITION, "attachment; filename=\"monthly-costs.csv\"")
+        .body(accounting::monthly_costs_csv(&costs)))
+}
+
+fn load_monthly_costs(months: i64, conn: &PgConnection) -> QueryResult<Vec<MonthlyCost>> {
+    monthly_costs::table
+        .filter(monthly_costs::month.ge(accounting::first_month(months)))
+        .order((monthly_costs::month.desc(), monthly_costs::cost_center.asc()))
+        .select(MONTHLY_COST_COLUMNS)
+        .load::<MonthlyCost>(conn)
+}
+
+/// Costs of each job finished in the month as csv, optionally of a single cost center. Records of the jobs are kept
+/// after the jobs are purged.
+#[utoipa::path(
+    get,
+    path = "/accounting/jobs/export",
+    tag = "admin",
+    params(JobCostsRequest),
+    responses((status = 200, body = String, content_type = "text/csv")),
+    security(("bearer" = [])),
+)]
+#[get("accounting/jobs/export")]
+pub async fn export_job_costs(pool: web::Data<DBPool>, user: User, request: web::Query<JobCostsRequest>) -> DefaultResponse {
+    if !can_view_accounting(&user) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let request = request.into_inner();
+
+    let month = accounting::parse_month(&request.month)
+        .ok_or(ExperimentErrorMessage::InvalidAccountingMonth)?;
+
+    let conn = pool.get().unwrap();
+
+    let costs = web::block(move || {
+        let mut query = job_costs::table
+            .filter(job_costs::finished_at.ge(month.and_hms_opt(0, 0, 0).unwrap()))
+            .filter(job_costs::finished_at.lt(accounting::next_month(month).and_hms_opt(0, 0, 0).unwrap()))
+            .into_boxed();
+
+        if let Some(cost_center) = request.cost_center {
+            query = query.filter(job_costs::cost_center.eq(cost_center));
+        }
+
+        query
+            .order(job_costs::finished_at.asc())
+            .select(JOB_COST_COLUMNS)
+            .load::<JobCost>(&conn)
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"job-costs-{}.csv\"", month.format("%Y-%m")))
+        .body(accounting::job_costs_csv(&costs)))
+}
+
+/// Job counts, success rate, runtime, energy and the most used runners of the user. Statistics are rolled up periodically,
+/// so the recently finished jobs may not be counted yet.
+#[utoipa::path(
+    get,
+    path = "/me/stats",
+    tag = "jobs",
+    params(UserStatsRequest),
+    responses((status = 200, body = UserStats)),
+    security(("bearer" = [])),
+)]
+#[get("me/stats")]
+pub async fn fetch_user_stats(pool: web::Data<DBPool>, user: User, request: web::Query<UserStatsRequest>) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+    let days = request.days.map(|days| days.clamp(1, MAX_STATS_DAYS));
+
+    let stats = web::block(move || UserStats::load(user.id, days, &conn))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+/// Archive of the user's experiments, the metadata of their jobs and their results for the data portability
+/// requests, see `export::build` for its layout.
+#[utoipa::path(
+    get,
+    path = "/me/export",
+    tag = "user",
+    responses((status = 200, body = [u8], content_type = "application/gzip")),
+    security(("bearer" = [])),
+)]
+#[get("me/export")]
+pub async fn export_user_data(pool: web::Data<DBPool>, user: User) -> DefaultResponse {
+    let conn = pool.get().unwrap();
+
+    let archive = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        AuditEntry::new(Some(user.id), "user.export")
+            .record(&conn)?;
+
+        export::build(&user, &conn)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/gzip")
+        .header(header::CONTENT_DISPOSITION, "attachment; filename=\"testbed-export.tar.gz\"")
+        .body(archive))
+}
@@ -0,0 +1,79 @@
+use actix_web::HttpRequest;
+use diesel::dsl::now;
+use diesel::pg::expression::extensions::IntervalDsl;
+use diesel::prelude::*;
+
+use core::schema::{idempotency_keys, jobs, processed_run_results};
+use core::types::{JobId, ModelId, UserId};
+
+use crate::ErrorMessage;
+use crate::models::job::Job;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+// Retried requests arriving later than this are treated as new requests
+const IDEMPOTENCY_WINDOW: i64 = 60 * 60 * 24;
+
+const MAX_KEY_LENGTH: usize = 255;
+
+pub fn idempotency_key(req: &HttpRequest) -> Result<Option<String>, ErrorMessage> {
+    let key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(key) => key.to_str().map_err(|_| ErrorMessage::InvalidIdempotencyKey)?,
+        None => return Ok(None)
+    };
+
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err(ErrorMessage::InvalidIdempotencyKey);
+    }
+
+    Ok(Some(key.to_string()))
+}
+
+/// Returns the job created by an earlier request with the same key, if the key is still in window.
+pub fn find_job(user_id: UserId, key: &str, conn: &PgConnection) -> QueryResult<Option<Job>> {
+    diesel::delete(
+        idempotency_keys::table
+            .filter(idempotency_keys::user_id.eq(user_id))
+            .filter(idempotency_keys::key.eq(key))
+            .filter(idempotency_keys::created_at.lt(now - IDEMPOTENCY_WINDOW.seconds()))
+    )
+        .execute(conn)?;
+
+    idempotency_keys::table
+        .inner_join(jobs::table)
+        .filter(idempotency_keys::user_id.eq(user_id))
+        .filter(idempotency_keys::key.eq(key))
+        .select(jobs::all_columns)
+        .first::<Job>(conn)
+        .optional()
+}
+
+/// Stores the key for the job, returns false if a concurrent request with the same key has stored it first. The job
+/// should be dropped then by rolling the transaction back, and the stored one returned by `find_job` instead.
+pub fn store_key(user_id: UserId, key: &str, job_id: JobId, conn: &PgConnection) -> QueryResult<bool> {
+    diesel::insert_into(idempotency_keys::table)
+        .values((
+            idempotency_keys::user_id.eq(user_id),
+            idempotency_keys::key.eq(key),
+            idempotency_keys::job_id.eq(job_id)
+        ))
+        .on_conflict_do_nothing()
+        .returning(idempotency_keys::id)
+        .get_result::<ModelId>(conn)
+        .optional()
+        .map(|stored| stored.is_some())
+}
+
+/// Records the result message of the job as processed, returns false if it is already processed. Should be called in
+/// the same transaction the result is applied, so that a result failed to be applied is not skipped when it is resent.
+pub fn record_result(job_id: JobId, message_id: &str, conn: &PgConnection) -> QueryResult<bool> {
+    diesel::insert_into(processed_run_results::table)
+        .values((
+            processed_run_results::job_id.eq(job_id),
+            processed_run_results::message_id.eq(message_id)
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .map(|inserted| inserted > 0)
+}
@@ -1,32 +1,494 @@
+use actix_web::http::StatusCode;
 use actix_web::web;
+use utoipa::OpenApi;
 
+pub use connection::aggregator::StatsAggregator;
+pub use connection::archiver::JobArchiver;
+pub use connection::audit_events::listen_audit_events;
+pub use connection::backplane::Backplane;
+pub use connection::cleaner::{ExperimentCleaner, RetentionPolicy};
+pub use connection::grpc::RunnerService;
+pub use connection::job_events::listen_job_events;
+pub use connection::limits::SessionLimits;
+pub use connection::messages::ShutdownServerMessage;
+pub use connection::reaper::Reaper;
+pub use certificate::ClientCertificate;
 pub use connection::server::ExperimentServer;
+pub use graphql::{build_schema as build_graphql_schema, TestbedSchema};
+pub use policy::RunnerPolicy;
+use core::error::{ErrorMessaging, HttpError};
 use core::middlewares::auth::Auth;
 
+mod authorization;
+mod certificate;
+mod claim;
+mod criteria;
+mod download;
+mod graphql;
 mod handlers;
 mod connection;
+mod idempotency;
+mod logs;
+mod markdown;
 pub mod models;
+mod notifications;
+mod policy;
+mod queue;
 mod requests;
 
+#[derive(OpenApi)]
+#[openapi(paths(
+    handlers::join_server,
+    handlers::rotate_runner_token,
+    handlers::create_connect_ticket,
+    handlers::claim_runner,
+    handlers::join_user_server,
+    handlers::execute_graphql,
+    handlers::join_graphql_server,
+    handlers::fetch_experiments,
+    handlers::fetch_experiment,
+    handlers::fetch_experiment_by_slug,
+    handlers::fetch_experiment_job_stats,
+    handlers::fetch_experiment_activity,
+    handlers::create_new_experiment,
+    handlers::update_experiment_name,
+    handlers::star_experiment,
+    handlers::unstar_experiment,
+    handlers::archive_experiment,
+    handlers::unarchive_experiment,
+    handlers::update_experiment_code,
+    handlers::update_experiment_description,
+    handlers::fetch_experiment_description,
+    handlers::update_experiment_firmware,
+    handlers::delete_experiment_firmware,
+    handlers::run_experiment,
+    handlers::run_batch,
+    handlers::validate_experiment,
+    handlers::fetch_runners,
+    handlers::fetch_runner,
+    handlers::fetch_runner_job_stats,
+    handlers::fetch_jobs,
+    handlers::fetch_job,
+    handlers::wait_job,
+    handlers::fetch_job_output,
+    handlers::sign_job_output_url,
+    handlers::download_job_output,
+    handlers::fetch_job_stream,
+    handlers::fetch_job_artifact,
+    handlers::sign_job_artifact_url,
+    handlers::download_job_artifact,
+    handlers::fetch_job_queue_info,
+    handlers::fetch_runner_queue,
+    handlers::reorder_runner_queue,
+    handlers::delete_experiment,
+    handlers::bulk_delete_experiments,
+    handlers::bulk_cancel_jobs,
+    handlers::fetch_batch,
+    handlers::cancel_batch,
+    handlers::purge_jobs,
+    handlers::update_experiment_cost_center,
+    handlers::update_experiment_network_emulation,
+    handlers::update_experiment_performance,
+    handlers::update_experiment_project,
+    handlers::update_experiment_retention,
+    handlers::update_experiment_runner_strategy,
+    handlers::update_experiment_sdr,
+    handlers::update_experiment_success_criteria,
+    handlers::update_experiment_thermal_guard,
+    handlers::update_experiment_warmup_runs,
+    handlers::update_job_protected,
+    handlers::update_job_annotation,
+    handlers::fetch_projects,
+    handlers::fetch_project,
+    handlers::create_project,
+    handlers::update_project_name,
+    handlers::delete_project,
+    handlers::add_project_member,
+    handlers::remove_project_member,
+    handlers::update_project_default_labels,
+    handlers::fetch_project_experiments,
+    handlers::fetch_project_jobs,
+    handlers::update_runner_name,
+    handlers::update_runner_labels,
+    handlers::update_runner_disabled,
+    handlers::delete_runner,
+    handlers::provision_runner,
+    handlers::issue_runner_access_key,
+    handlers::create_claim_code,
+    handlers::update_runner_allowed_networks,
+    handlers::update_runner_certificate,
+    handlers::update_runner_auto_update,
+    handlers::fetch_client_releases,
+    handlers::create_client_release,
+    handlers::create_runner_command,
+    handlers::fetch_runner_commands,
+    handlers::update_runner_log_level,
+    handlers::fetch_runner_client_logs,
+    handlers::fetch_runner_limit_metrics,
+    handlers::fetch_stuck_jobs,
+    handlers::requeue_job,
+    handlers::update_project_quota,
+    handlers::deactivate_user,
+    handlers::reactivate_user,
+    handlers::fetch_audit_logs,
+    handlers::fetch_admin_stats,
+    handlers::fetch_monthly_costs,
+    handlers::export_monthly_costs,
+    handlers::export_job_costs,
+    handlers::fetch_user_stats,
+    handlers::export_user_data,
+))]
+pub struct ApiDoc;
+
 pub fn register(config: &mut web::ServiceConfig) {
     config
         .service(
             web::scope("/api/experiment")
                 .service(handlers::join_server)
+                .service(handlers::rotate_runner_token)
+                .service(handlers::create_connect_ticket)
+                .service(handlers::claim_runner)
+                .service(handlers::download_job_output)
+                .service(handlers::download_job_artifact)
                 .service(
                     web::scope("")
                         .wrap(Auth)
+                        .service(handlers::join_user_server)
+                        .service(handlers::execute_graphql)
+                        .service(handlers::join_graphql_server)
                         .service(handlers::fetch_experiments)
+                        .service(handlers::fetch_experiment_by_slug)
                         .service(handlers::fetch_experiment)
+                        .service(handlers::fetch_experiment_job_stats)
+                        .service(handlers::fetch_experiment_activity)
                         .service(handlers::create_new_experiment)
                         .service(handlers::update_experiment_name)
+                        .service(handlers::star_experiment)
+                        .service(handlers::unstar_experiment)
+                        .service(handlers::archive_experiment)
+                        .service(handlers::unarchive_experiment)
                         .service(handlers::update_experiment_code)
+                        .service(handlers::update_experiment_description)
+                        .service(handlers::fetch_experiment_description)
+                        .service(handlers::update_experiment_firmware)
+                        .service(handlers::delete_experiment_firmware)
                         .service(handlers::run_experiment)
+                        .service(handlers::run_batch)
+                        .service(handlers::validate_experiment)
+                        .service(handlers::fetch_runners)
+                        .service(handlers::fetch_runner)
+                        .service(handlers::fetch_runner_job_stats)
+                        .service(handlers::fetch_jobs)
+                        .service(handlers::fetch_job)
+                        .service(handlers::wait_job)
+                        .service(handlers::fetch_job_output)
+                        .service(handlers::sign_job_output_url)
+                        .service(handlers::fetch_job_stream)
+                        .service(handlers::fetch_job_artifact)
+                        .service(handlers::sign_job_artifact_url)
+                        .service(handlers::fetch_job_queue_info)
+                        .service(handlers::fetch_runner_queue)
+                        .service(handlers::reorder_runner_queue)
                         .service(handlers::delete_experiment)
+                        .service(handlers::bulk_delete_experiments)
+                        .service(handlers::bulk_cancel_jobs)
+                        .service(handlers::fetch_batch)
+                        .service(handlers::cancel_batch)
+                        .service(handlers::purge_jobs)
+                        .service(handlers::update_experiment_cost_center)
+                        .service(handlers::update_experiment_network_emulation)
+                        .service(handlers::update_experiment_performance)
+                        .service(handlers::update_experiment_project)
+                        .service(handlers::update_experiment_retention)
+                        .service(handlers::update_experiment_runner_strategy)
+                        .service(handlers::update_experiment_sdr)
+                        .service(handlers::update_experiment_success_criteria)
+                        .service(handlers::update_experiment_thermal_guard)
+                        .service(handlers::update_experiment_warmup_runs)
+                        .service(handlers::update_job_protected)
+                        .service(handlers::update_job_annotation)
+                        .service(handlers::fetch_projects)
+                        .service(handlers::fetch_project)
+                        .service(handlers::create_project)
+                        .service(handlers::update_project_name)
+                        .service(handlers::delete_project)
+                        .service(handlers::add_project_member)
+                        .service(handlers::remove_project_member)
+                        .service(handlers::update_project_default_labels)
+                        .service(handlers::fetch_project_experiments)
+                        .service(handlers::fetch_project_jobs)
+                        .service(handlers::update_runner_name)
+                        .service(handlers::update_runner_labels)
+                        .service(handlers::update_runner_disabled)
+                        .service(handlers::delete_runner)
+                        .service(handlers::provision_runner)
+                        .service(handlers::issue_runner_access_key)
+                        .service(handlers::create_claim_code)
+                        .service(handlers::update_runner_allowed_networks)
+                        .service(handlers::update_runner_certificate)
+                        .service(handlers::update_runner_auto_update)
+                        .service(handlers::fetch_client_releases)
+                        .service(handlers::create_client_release)
+                        .service(handlers::create_runner_command)
+                        .service(handlers::fetch_runner_commands)
+                        .service(handlers::update_runner_log_level)
+                        .service(handlers::fetch_runner_client_logs)
+                        .service(handlers::fetch_runner_limit_metrics)
+                        .service(handlers::fetch_stuck_jobs)
+                        .service(handlers::requeue_job)
+                        .service(handlers::update_project_quota)
+                        .service(handlers::deactivate_user)
+                        .service(handlers::reactivate_user)
+                        .service(handlers::fetch_audit_logs)
+                        .service(handlers::fetch_admin_stats)
+                        .service(handlers::fetch_monthly_costs)
+                        .service(handlers::export_monthly_costs)
+                        .service(handlers::export_job_costs)
+                        .service(handlers::fetch_user_stats)
+                        .service(handlers::export_user_data)
                 )
         );
 }
 
+#[derive(Debug)]
+pub enum ErrorMessage {
+    InvalidIdempotencyKey,
+    TooManyItems,
+    RunnerDisabled,
+    InvalidClaimCode,
+    InvalidNetwork,
+    AddressNotAllowed,
+    TooManyConnections,
+    InvalidFingerprint,
+    CredentialsNotFound,
+    InvalidRelease,
+    RunnerNotConnected,
+    InvalidLogLevel,
+    InvalidHook,
+    InvalidFirmware,
+    FirmwareTooLarge,
+    ValidationTimedOut,
+    EmailNotVerified,
+    DescriptionTooLong,
+    ExperimentArchived,
+    ExperimentNameExists,
+    InvalidRetention,
+    InvalidBatch,
+    InvalidLabels,
+    AnnotationTooLong,
+    InvalidCriteria,
+    InvalidWarmupRuns,
+    InvalidThermalGuard,
+    InvalidPerformanceSettings,
+    InvalidNetworkEmulation,
+    InvalidSdrSettings,
+    InvalidCostCenter,
+    InvalidAccountingMonth,
+    InvalidProjectName,
+    InvalidProjectQuota,
+    ProjectQuotaExceeded,
+    InvalidProjectMember,
+    InvalidDownloadSignature,
+    NoItemsSelected,
+}
+
+impl ErrorMessaging for ErrorMessage {
+    fn value(&self) -> HttpError {
+        match self {
+            ErrorMessage::InvalidIdempotencyKey => HttpError {
+                code: StatusCode::BAD_REQUEST,
+                error_code: 120,
+                message: String::from("invalid_idempotency_key"),
+            },
+            ErrorMessage::TooManyItems => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 121,
+                message: String::from("too_many_items"),
+            },
+            ErrorMessage::RunnerDisabled => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 122,
+                message: String::from("runner_disabled"),
+            },
+            ErrorMessage::InvalidClaimCode => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 123,
+                message: String::from("invalid_claim_code"),
+            },
+            ErrorMessage::InvalidNetwork => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 124,
+                message: String::from("invalid_network"),
+            },
+            ErrorMessage::AddressNotAllowed => HttpError {
+                code: StatusCode::FORBIDDEN,
+                error_code: 125,
+                message: String::from("address_not_allowed"),
+            },
+            ErrorMessage::TooManyConnections => HttpError {
+                code: StatusCode::TOO_MANY_REQUESTS,
+                error_code: 126,
+                message: String::from("too_many_connections"),
+            },
+            ErrorMessage::InvalidFingerprint => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 127,
+                message: String::from("invalid_fingerprint"),
+            },
+            ErrorMessage::CredentialsNotFound => HttpError {
+                code: StatusCode::UNAUTHORIZED,
+                error_code: 128,
+                message: String::from("credentials_not_found"),
+            },
+            ErrorMessage::InvalidRelease => HttpError {
+                code: StatusCode::BAD_REQUEST,
+                error_code: 129,
+                message: String::from("invalid_release"),
+            },
+            ErrorMessage::RunnerNotConnected => HttpError {
+                code: StatusCode::CONFLICT,
+                error_code: 130,
+                message: String::from("runner_not_connected"),
+            },
+            ErrorMessage::InvalidLogLevel => HttpError {
+                code: StatusCode::BAD_REQUEST,
+                error_code: 131,
+                message: String::from("invalid_log_level"),
+            },
+            ErrorMessage::InvalidHook => HttpError {
+                code: StatusCode::BAD_REQUEST,
+                error_code: 132,
+                message: String::from("invalid_hook"),
+            },
+            ErrorMessage::InvalidFirmware => HttpError {
+                code: StatusCode::BAD_REQUEST,
+                error_code: 133,
+                message: String::from("invalid_firmware"),
+            },
+            ErrorMessage::FirmwareTooLarge => HttpError {
+                code: StatusCode::PAYLOAD_TOO_LARGE,
+                error_code: 134,
+                message: String::from("firmware_too_large"),
+            },
+            ErrorMessage::ValidationTimedOut => HttpError {
+                code: StatusCode::GATEWAY_TIMEOUT,
+                error_code: 135,
+                message: String::from("validation_timed_out"),
+            },
+            ErrorMessage::EmailNotVerified => HttpError {
+                code: StatusCode::FORBIDDEN,
+                error_code: 136,
+                message: String::from("email_not_verified"),
+            },
+            ErrorMessage::DescriptionTooLong => HttpError {
+                code: StatusCode::PAYLOAD_TOO_LARGE,
+                error_code: 137,
+                message: String::from("description_too_long"),
+            },
+            ErrorMessage::ExperimentArchived => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 138,
+                message: String::from("experiment_archived"),
+            },
+            ErrorMessage::ExperimentNameExists => HttpError {
+                code: StatusCode::CONFLICT,
+                error_code: 139,
+                message: String::from("experiment_name_exists"),
+            },
+            ErrorMessage::InvalidRetention => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 150,
+                message: String::from("invalid_retention"),
+            },
+            ErrorMessage::InvalidBatch => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 151,
+                message: String::from("invalid_batch"),
+            },
+            ErrorMessage::InvalidLabels => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 152,
+                message: String::from("invalid_labels"),
+            },
+            ErrorMessage::AnnotationTooLong => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 153,
+                message: String::from("annotation_too_long"),
+            },
+            ErrorMessage::InvalidCriteria => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 154,
+                message: String::from("invalid_criteria"),
+            },
+            ErrorMessage::InvalidWarmupRuns => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 155,
+                message: String::from("invalid_warmup_runs"),
+            },
+            ErrorMessage::InvalidThermalGuard => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 156,
+                message: String::from("invalid_thermal_guard"),
+            },
+            ErrorMessage::InvalidPerformanceSettings => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 157,
+                message: String::from("invalid_performance_settings"),
+            },
+            ErrorMessage::InvalidNetworkEmulation => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 158,
+                message: String::from("invalid_network_emulation"),
+            },
+            ErrorMessage::InvalidSdrSettings => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 159,
+                message: String::from("invalid_sdr_settings"),
+            },
+            ErrorMessage::InvalidCostCenter => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 160,
+                message: String::from("invalid_cost_center"),
+            },
+            ErrorMessage::InvalidAccountingMonth => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 161,
+                message: String::from("invalid_accounting_month"),
+            },
+            ErrorMessage::InvalidProjectName => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 162,
+                message: String::from("invalid_project_name"),
+            },
+            ErrorMessage::InvalidProjectQuota => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 163,
+                message: String::from("invalid_project_quota"),
+            },
+            ErrorMessage::ProjectQuotaExceeded => HttpError {
+                code: StatusCode::CONFLICT,
+                error_code: 164,
+                message: String::from("project_quota_exceeded"),
+            },
+            ErrorMessage::InvalidProjectMember => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 165,
+                message: String::from("invalid_project_member"),
+            },
+            ErrorMessage::InvalidDownloadSignature => HttpError {
+                code: StatusCode::FORBIDDEN,
+                error_code: 166,
+                message: String::from("invalid_download_signature"),
+            },
+            ErrorMessage::NoItemsSelected => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 167,
+                message: String::from("no_items_selected"),
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
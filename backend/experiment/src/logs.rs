@@ -0,0 +1,157 @@
+use actix_web::web::Bytes;
+use futures::{Stream, StreamExt};
+
+use crate::models::job::AnsiMode;
+
+const LOG_CHUNK_SIZE: usize = 8 * 1024;
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{7}';
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Text,
+    Escape,
+    // Control Sequence Introducer, ESC [
+    Csi,
+    // Operating System Command, ESC ], terminated by BEL or ESC \
+    Osc,
+    OscEscape,
+}
+
+/// Removes ANSI escape sequences from text that arrives in chunks. A sequence split across
+/// two chunks is still removed since the filter keeps its state between calls.
+pub struct AnsiFilter {
+    state: State,
+}
+
+impl AnsiFilter {
+    pub fn new() -> Self {
+        AnsiFilter {
+            state: State::Text
+        }
+    }
+
+    pub fn filter(&mut self, chunk: &str) -> String {
+        let mut filtered = String::with_capacity(chunk.len());
+
+        for c in chunk.chars() {
+            self.state = match (self.state, c) {
+                (State::Text, ESC) => State::Escape,
+                (State::Text, c) => {
+                    filtered.push(c);
+                    State::Text
+                }
+                (State::Escape, '[') => State::Csi,
+                (State::Escape, ']') => State::Osc,
+                // Any other escape is a two character sequence
+                (State::Escape, _) => State::Text,
+                // Final byte of a CSI sequence is in range 0x40..=0x7E
+                (State::Csi, '\u{40}'..='\u{7e}') => State::Text,
+                (State::Csi, _) => State::Csi,
+                (State::Osc, BEL) => State::Text,
+                (State::Osc, ESC) => State::OscEscape,
+                (State::Osc, _) => State::Osc,
+                (State::OscEscape, '\\') => State::Text,
+                (State::OscEscape, _) => State::Osc,
+            };
+        }
+
+        filtered
+    }
+}
+
+/// Streams the output in chunks, passing each chunk through the [`AnsiFilter`] if it is requested.
+pub fn output_stream(output: String, mode: AnsiMode) -> impl Stream<Item=Result<Bytes, actix_web::Error>> {
+    let mut filter = AnsiFilter::new();
+
+    futures::stream::iter(into_chunks(output))
+        .map(move |chunk| Ok(match mode {
+            AnsiMode::Strip => Bytes::from(filter.filter(chunk.as_str())),
+            AnsiMode::Preserve => Bytes::from(chunk),
+        }))
+}
+
+fn into_chunks(output: String) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = output.as_str();
+
+    while !rest.is_empty() {
+        let mut end = LOG_CHUNK_SIZE.min(rest.len());
+
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+
+        chunks.push(rest[..end].to_string());
+        rest = &rest[end..];
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(chunks: &[&str]) -> String {
+        let mut filter = AnsiFilter::new();
+
+        chunks.iter().map(|chunk| filter.filter(chunk)).collect()
+    }
+
+    #[test]
+    fn strips_control_sequences() {
+        assert_eq!(strip(&["\u{1b}[1;31merror\u{1b}[0m: failed"]), "error: failed");
+        assert_eq!(strip(&["50%\u{1b}[2K\r100%"]), "50%\r100%");
+        assert_eq!(strip(&["\u{1b}[?25lhidden cursor\u{1b}[?25h"]), "hidden cursor");
+    }
+
+    #[test]
+    fn strips_operating_system_commands() {
+        assert_eq!(strip(&["\u{1b}]0;title\u{7}text"]), "text");
+        assert_eq!(strip(&["\u{1b}]8;;https://example.com\u{1b}\\link\u{1b}]8;;\u{1b}\\"]), "link");
+        // escape inside the command does not end it unless it is followed by a backslash
+        assert_eq!(strip(&["\u{1b}]0;a\u{1b}b\u{7}text"]), "text");
+    }
+
+    #[test]
+    fn strips_two_character_escapes() {
+        assert_eq!(strip(&["a\u{1b}7b\u{1b}8c"]), "abc");
+    }
+
+    #[test]
+    fn strips_sequences_split_across_chunks() {
+        assert_eq!(strip(&["red \u{1b}", "[31", "mtext\u{1b}[", "0m"]), "red text");
+        assert_eq!(strip(&["\u{1b}]0;ti", "tle\u{1b}", "\\text"]), "text");
+    }
+
+    #[test]
+    fn keeps_plain_text() {
+        assert_eq!(strip(&["plain ", "text\nwith ünicode"]), "plain text\nwith ünicode");
+    }
+
+    #[test]
+    fn chunks_on_character_boundaries() {
+        let output = format!("{}ü{}", "a".repeat(LOG_CHUNK_SIZE - 1), "b".repeat(LOG_CHUNK_SIZE));
+        let chunks = into_chunks(output.clone());
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].len(), LOG_CHUNK_SIZE + 1);
+        assert_eq!(chunks.concat(), output);
+        assert!(into_chunks(String::new()).is_empty());
+    }
+
+    #[test]
+    fn streams_the_output_in_the_requested_mode() {
+        let output = format!("{}\u{1b}[31mred\u{1b}[0m", "a".repeat(LOG_CHUNK_SIZE - 2));
+
+        let collect = |mode| futures::executor::block_on(output_stream(output.clone(), mode).collect::<Vec<_>>())
+            .into_iter()
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+            .collect::<String>();
+
+        assert_eq!(collect(AnsiMode::Strip), format!("{}red", "a".repeat(LOG_CHUNK_SIZE - 2)));
+        assert_eq!(collect(AnsiMode::Preserve), output);
+    }
+}
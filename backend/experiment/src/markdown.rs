@@ -0,0 +1,75 @@
+use pulldown_cmark::{html, Options, Parser};
+
+/// Renders the Markdown into HTML which is safe to be inserted into the web app. Raw HTML in the Markdown is
+/// passed through the sanitizer along with the rendered one, so scripts, event handlers and the like are removed.
+pub fn render(markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let mut rendered = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut rendered, Parser::new_ext(markdown, options));
+
+    ammonia::Builder::default()
+        .link_rel(Some("noopener noreferrer nofollow"))
+        .clean(rendered.as_str())
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_removes_scripts() {
+        let rendered = render("hello\n\n<script>alert(1)</script>");
+
+        assert!(rendered.contains("hello"));
+        assert!(!rendered.contains("<script"));
+        assert!(!rendered.contains("alert(1)"));
+    }
+
+    #[test]
+    fn test_render_removes_event_handlers() {
+        let rendered = render(r#"<img src="x.png" onerror="alert(1)">"#);
+
+        assert!(rendered.contains("<img"));
+        assert!(!rendered.contains("onerror"));
+    }
+
+    #[test]
+    fn test_render_removes_javascript_links() {
+        let rendered = render("[click](javascript:alert(1)) <a href=\"javascript:alert(1)\">raw</a>");
+
+        assert!(!rendered.contains("javascript:"));
+        assert!(rendered.contains("click"));
+        assert!(rendered.contains("raw"));
+    }
+
+    #[test]
+    fn test_render_strips_disallowed_raw_html() {
+        let rendered = render("<iframe src=\"https://example.com\"></iframe><style>body{}</style><b>bold</b>");
+
+        assert!(!rendered.contains("<iframe"));
+        assert!(!rendered.contains("<style"));
+        assert!(rendered.contains("<b>bold</b>"));
+    }
+
+    #[test]
+    fn test_render_adds_rel_to_links() {
+        let rendered = render("[docs](https://example.com)");
+
+        assert!(rendered.contains(r#"href="https://example.com""#));
+        assert!(rendered.contains(r#"rel="noopener noreferrer nofollow""#));
+    }
+
+    #[test]
+    fn test_render_keeps_markdown() {
+        let rendered = render("# Title\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n~~old~~");
+
+        assert!(rendered.contains("<h1>Title</h1>"));
+        assert!(rendered.contains("<table>"));
+        assert!(rendered.contains("<del>old</del>"));
+    }
+}
@@ -0,0 +1,281 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Utc};
+use diesel::{Insertable, Queryable};
+use diesel::dsl::{exists, not, sql};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double, Nullable, Timestamp};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use core::schema::{all_jobs, experiment_cost_centers, experiments, job_costs, monthly_costs, runners, users};
+use core::types::JobId;
+
+use crate::models::batch::parse_metric;
+
+/// Jobs of the experiments without a cost center are charged to this one, hence it can not be assigned
+pub const UNASSIGNED_COST_CENTER: &str = "unassigned";
+pub const MAX_COST_CENTER_LENGTH: usize = 64;
+// jobs report their energy consumption with this metric, see `parse_metric`
+pub const ENERGY_METRIC: &str = "energy_mj";
+// outputs of the jobs are loaded to read their energy, hence the costs are recorded in small batches
+const JOB_COST_BATCH_SIZE: i64 = 50;
+
+/// Cost centers are given by the labs, e.g. a grant or a course code
+pub fn is_valid_cost_center(cost_center: &str) -> bool {
+    !cost_center.is_empty() &&
+        cost_center.len() <= MAX_COST_CENTER_LENGTH &&
+        cost_center != UNASSIGNED_COST_CENTER &&
+        cost_center.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+#[derive(Insertable)]
+#[table_name = "job_costs"]
+struct NewJobCost {
+    job_id: JobId,
+    job_uuid: Uuid,
+    experiment_uuid: Uuid,
+    experiment_name: String,
+    user_email: String,
+    runner_name: Option<String>,
+    cost_center: String,
+    status: String,
+    runtime_seconds: f64,
+    energy_mj: Option<f64>,
+    storage_bytes: i64,
+    finished_at: NaiveDateTime,
+}
+
+/// Usage of a finished job for the chargeback reports
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobCost {
+    pub job_id: Uuid,
+    pub experiment_id: Uuid,
+    pub experiment_name: String,
+    pub user_email: String,
+    pub runner_name: Option<String>,
+    pub cost_center: String,
+    pub status: String,
+    pub runtime_seconds: f64,
+    // none if the job did not report its energy consumption
+    pub energy_mj: Option<f64>,
+    // output, streams and artifacts of the job
+    pub storage_bytes: i64,
+    pub finished_at: NaiveDateTime,
+}
+
+pub const JOB_COST_COLUMNS: (job_costs::job_uuid, job_costs::experiment_uuid, job_costs::experiment_name, job_costs::user_email, job_costs::runner_name, job_costs::cost_center, job_costs::status, job_costs::runtime_seconds, job_costs::energy_mj, job_costs::storage_bytes, job_costs::finished_at) = (
+    job_costs::job_uuid,
+    job_costs::experiment_uuid,
+    job_costs::experiment_name,
+    job_costs::user_email,
+    job_costs::runner_name,
+    job_costs::cost_center,
+    job_costs::status,
+    job_costs::runtime_seconds,
+    job_costs::energy_mj,
+    job_costs::storage_bytes,
+    job_costs::finished_at,
+);
+
+/// Job costs of a cost center rolled up per month
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthlyCost {
+    // first day of the month
+    pub month: NaiveDate,
+    pub cost_center: String,
+    pub job_count: i64,
+    // jobs which reported their energy consumption, the others are not counted in the energy
+    pub metered_jobs: i64,
+    pub runtime_seconds: f64,
+    pub energy_mj: f64,
+    pub storage_bytes: i64,
+}
+
+pub const MONTHLY_COST_COLUMNS: (monthly_costs::month, monthly_costs::cost_center, monthly_costs::job_count, monthly_costs::metered_jobs, monthly_costs::runtime_seconds, monthly_costs::energy_mj, monthly_costs::storage_bytes) = (
+    monthly_costs::month,
+    monthly_costs::cost_center,
+    monthly_costs::job_count,
+    monthly_costs::metered_jobs,
+    monthly_costs::runtime_seconds,
+    monthly_costs::energy_mj,
+    monthly_costs::storage_bytes,
+);
+
+/// Records the costs of the finished jobs which are not recorded yet. Jobs which never started, e.g. the ones
+/// cancelled while pending, are not charged. Jobs moved into the archive before their costs are recorded are
+/// charged as well. Returns the number of the recorded jobs.
+pub fn record_job_costs(conn: &PgConnection) -> QueryResult<usize> {
+    let mut recorded = 0;
+
+    loop {
+        let finished = all_jobs::table
+            .inner_join(experiments::table.inner_join(users::table).left_join(experiment_cost_centers::table))
+            .left_join(runners::table)
+            .filter(all_jobs::started_at.is_not_null())
+            .filter(all_jobs::finished_at.is_not_null())
+            .filter(not(exists(job_costs::table.filter(job_costs::job_id.eq(all_jobs::id)))))
+            .select((
+                all_jobs::id,
+                all_jobs::uuid,
+                experiments::uuid,
+                experiments::name,
+                users::email,
+                runners::name.nullable(),
+                experiment_cost_centers::cost_center.nullable(),
+                all_jobs::status,
+                all_jobs::output,
+                sql::<Double>("date_part('epoch', all_jobs.finished_at - all_jobs.started_at)"),
+                sql::<BigInt>(
+                    "(octet_length(all_jobs.output)
+                      + COALESCE((SELECT SUM(octet_length(output)) FROM all_job_streams WHERE all_job_streams.job_id = all_jobs.id), 0)
+                      + COALESCE((SELECT SUM(size) FROM job_artifacts WHERE job_artifacts.job_id = all_jobs.id), 0))::bigint"
+                ),
+                all_jobs::finished_at,
+            ))
+            .order(all_jobs::id.asc())
+            .limit(JOB_COST_BATCH_SIZE)
+            .load::<(JobId, Uuid, Uuid, String, String, Option<String>, Option<String>, String, String, f64, i64, Option<NaiveDateTime>)>(conn)?;
+
+        if finished.is_empty() {
+            return Ok(recorded);
+        }
+
+        let costs = finished.into_iter()
+            .map(|(job_id, job_uuid, experiment_uuid, experiment_name, user_email, runner_name, cost_center, status, output, runtime_seconds, storage_bytes, finished_at)| NewJobCost {
+                job_id,
+                job_uuid,
+                experiment_uuid,
+                experiment_name,
+                user_email,
+                runner_name,
+                cost_center: cost_center.unwrap_or_else(|| UNASSIGNED_COST_CENTER.to_string()),
+                status,
+                runtime_seconds,
+                energy_mj: parse_metric(&output, ENERGY_METRIC),
+                storage_bytes,
+                // filtered to be present
+                finished_at: finished_at.unwrap_or_default(),
+            })
+            .collect::<Vec<NewJobCost>>();
+
+        // jobs may be recorded concurrently by another instance of the server
+        recorded += diesel::insert_into(job_costs::table)
+            .values(&costs)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        if costs.len() < JOB_COST_BATCH_SIZE as usize {
+            return Ok(recorded);
+        }
+    }
+}
+
+/// Rolls the job costs up into the monthly costs of their cost centers. Recorded costs do not change, so only the
+/// months since the last rolled up one are computed again, costs of that month may have been recorded after the
+/// previous rollup.
+pub fn rollup_monthly_costs(conn: &PgConnection) -> QueryResult<()> {
+    let since = monthly_costs::table
+        .select(diesel::dsl::max(monthly_costs::month))
+        .first::<Option<NaiveDate>>(conn)?;
+
+    diesel::delete(monthly_costs::table.filter(monthly_costs::month.nullable().ge(since)))
+        .execute(conn)?;
+
+    diesel::sql_query(
+        "INSERT INTO monthly_costs (month, cost_center, job_count, metered_jobs, runtime_seconds, energy_mj, storage_bytes)
+         SELECT date_trunc('month', finished_at)::date,
+                cost_center,
+                COUNT(*),
+                COUNT(energy_mj),
+                SUM(runtime_seconds),
+                COALESCE(SUM(energy_mj), 0),
+                SUM(storage_bytes)::bigint
+         FROM job_costs
+         WHERE $1 IS NULL OR finished_at >= $1
+         GROUP BY date_trunc('month', finished_at)::date, cost_center"
+    )
+        .bind::<Nullable<Timestamp>, _>(since.map(|since| since.and_hms_opt(0, 0, 0).unwrap()))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// First day of the month `months - 1` months before the current one, so that the current month is the last of the
+/// `months` months
+pub fn first_month(months: i64) -> NaiveDate {
+    let today = Utc::now().naive_utc().date();
+    let index = today.year() as i64 * 12 + today.month0() as i64 - (months - 1);
+
+    NaiveDate::from_ymd_opt((index / 12) as i32, (index % 12) as u32 + 1, 1).unwrap_or(today)
+}
+
+/// First day of the month, e.g. 2021-02
+pub fn parse_month(month: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(format!("{}-01", month).as_str(), "%Y-%m-%d").ok()
+}
+
+/// First day of the month following the given one
+pub fn next_month(month: NaiveDate) -> NaiveDate {
+    match month.month() {
+        12 => NaiveDate::from_ymd_opt(month.year() + 1, 1, 1),
+        m => NaiveDate::from_ymd_opt(month.year(), m + 1, 1)
+    }
+        .unwrap_or(month)
+}
+
+/// Monthly costs of the cost centers as csv, for the spreadsheets of the chargeback reports
+pub fn monthly_costs_csv(costs: &[MonthlyCost]) -> String {
+    let mut csv = String::from("month,cost_center,job_count,metered_jobs,runtime_seconds,energy_mj,storage_bytes\n");
+
+    for cost in costs {
+        csv_row(&mut csv, &[
+            cost.month.format("%Y-%m").to_string(),
+            cost.cost_center.clone(),
+            cost.job_count.to_string(),
+            cost.metered_jobs.to_string(),
+            cost.runtime_seconds.to_string(),
+            cost.energy_mj.to_string(),
+            cost.storage_bytes.to_string(),
+        ]);
+    }
+
+    csv
+}
+
+/// Job costs as csv, jobs without an energy report have an empty energy
+pub fn job_costs_csv(costs: &[JobCost]) -> String {
+    let mut csv = String::from("job_id,experiment_id,experiment_name,user_email,runner_name,cost_center,status,runtime_seconds,energy_mj,storage_bytes,finished_at\n");
+
+    for cost in costs {
+        csv_row(&mut csv, &[
+            cost.job_id.to_string(),
+            cost.experiment_id.to_string(),
+            cost.experiment_name.clone(),
+            cost.user_email.clone(),
+            cost.runner_name.clone().unwrap_or_default(),
+            cost.cost_center.clone(),
+            cost.status.clone(),
+            cost.runtime_seconds.to_string(),
+            cost.energy_mj.map(|energy_mj| energy_mj.to_string()).unwrap_or_default(),
+            cost.storage_bytes.to_string(),
+            cost.finished_at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        ]);
+    }
+
+    csv
+}
+
+/// Fields containing a separator, a quote or a line break are quoted as in RFC 4180
+fn csv_row(csv: &mut String, fields: &[String]) {
+    let fields = fields.iter()
+        .map(|field| match field.contains([',', '"', '\n', '\r']) {
+            true => format!("\"{}\"", field.replace('"', "\"\"")),
+            false => field.clone()
+        })
+        .collect::<Vec<String>>();
+
+    csv.push_str(fields.join(",").as_str());
+    csv.push('\n');
+}
@@ -0,0 +1,106 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::Nullable;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::{Insertable, Queryable};
+use diesel::sql_types::VarChar;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use core::db::DieselEnum;
+use core::schema::{experiment_activities, jobs};
+use core::types::{ExperimentId, JobId, ModelId, UserId};
+
+/// Event in the history of an experiment, e.g. its code is updated or one of its runs is finished
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Activity {
+    pub id: ModelId,
+    pub actor_id: Option<UserId>,
+    pub kind: ActivityKind,
+    // job of the run events, missing if the job is purged
+    pub job_id: Option<Uuid>,
+    pub details: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+pub type ActivityColumns = (experiment_activities::id, experiment_activities::actor_id, experiment_activities::kind, Nullable<jobs::uuid>, experiment_activities::details, experiment_activities::created_at);
+
+/// Columns of `Activity`, the query should left join the jobs of the events
+pub fn activity_columns() -> ActivityColumns {
+    (
+        experiment_activities::id,
+        experiment_activities::actor_id,
+        experiment_activities::kind,
+        jobs::uuid.nullable(),
+        experiment_activities::details,
+        experiment_activities::created_at,
+    )
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub enum ActivityKind {
+    NameUpdated,
+    CodeUpdated,
+    DescriptionUpdated,
+    FirmwareUpdated,
+    FirmwareRemoved,
+    RunStarted,
+    RunFinished,
+    Archived,
+    Unarchived,
+}
+
+impl Default for ActivityKind {
+    fn default() -> Self {
+        ActivityKind::CodeUpdated
+    }
+}
+
+impl Queryable<VarChar, Pg> for ActivityKind {
+    type Row = String;
+
+    fn build(row: Self::Row) -> Self {
+        Self::build_from_string(row)
+    }
+}
+
+/// Event to be added to the history of an experiment. Like the audit entries, events should be recorded in the
+/// same transaction as the change.
+#[derive(Insertable)]
+#[table_name = "experiment_activities"]
+pub struct ActivityEntry {
+    experiment_id: ExperimentId,
+    actor_id: Option<UserId>,
+    kind: String,
+    job_id: Option<JobId>,
+    details: Option<String>,
+}
+
+impl ActivityEntry {
+    pub fn new(experiment_id: ExperimentId, actor_id: Option<UserId>, kind: ActivityKind) -> Self {
+        ActivityEntry {
+            experiment_id,
+            actor_id,
+            kind: kind.value(),
+            job_id: None,
+            details: None,
+        }
+    }
+
+    pub fn job(self, job_id: JobId) -> Self {
+        ActivityEntry { job_id: Some(job_id), ..self }
+    }
+
+    pub fn details(self, details: String) -> Self {
+        ActivityEntry { details: Some(details), ..self }
+    }
+
+    pub fn record(self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::insert_into(experiment_activities::table)
+            .values(&self)
+            .execute(conn)
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+
+use actix_web::web::{self, Bytes};
+use chrono::NaiveDateTime;
+use diesel::dsl::sql;
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+use diesel::sql_types::Bytea;
+use futures::Stream;
+use log::error;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use core::schema::job_artifacts;
+use core::types::{DBPool, JobId, ModelId};
+
+// content of the artifacts is read from the database in chunks of this size while it is served
+const CONTENT_CHUNK_SIZE: i64 = 1024 * 1024;
+
+/// File collected by the runner during the job, e.g. a compressed packet capture. Its content is served separately.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobArtifact {
+    pub name: String,
+    // in bytes
+    pub size: i32,
+    pub created_at: NaiveDateTime,
+    // how the runner collected the file, e.g. the frequency of a radio recording
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Decoded artifact reported by the runner along with the result of the job
+pub struct NewJobArtifact {
+    pub name: String,
+    pub data: Vec<u8>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Records the artifacts of the job, the ones of a previous run of the job are replaced if they have the same name
+pub fn record(job_id: JobId, artifacts: Vec<NewJobArtifact>, conn: &PgConnection) -> QueryResult<()> {
+    for artifact in artifacts {
+        let size = artifact.data.len() as i32;
+        // serializing a map of strings does not fail
+        let metadata = serde_json::to_string(&artifact.metadata).unwrap();
+
+        diesel::insert_into(job_artifacts::table)
+            .values((
+                job_artifacts::job_id.eq(job_id),
+                job_artifacts::name.eq(artifact.name),
+                job_artifacts::data.eq(artifact.data),
+                job_artifacts::size.eq(size),
+                job_artifacts::metadata.eq(metadata),
+            ))
+            .on_conflict((job_artifacts::job_id, job_artifacts::name))
+            .do_update()
+            .set((
+                job_artifacts::data.eq(excluded(job_artifacts::data)),
+                job_artifacts::size.eq(excluded(job_artifacts::size)),
+                job_artifacts::metadata.eq(excluded(job_artifacts::metadata)),
+                job_artifacts::created_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Artifacts of the job ordered by their names, without their content
+pub fn load(job_id: JobId, conn: &PgConnection) -> QueryResult<Vec<JobArtifact>> {
+    let artifacts = job_artifacts::table
+        .filter(job_artifacts::job_id.eq(job_id))
+        .order(job_artifacts::name.asc())
+        .select((job_artifacts::name, job_artifacts::size, job_artifacts::created_at, job_artifacts::metadata))
+        .load::<(String, i32, NaiveDateTime, String)>(conn)?;
+
+    Ok(artifacts
+        .into_iter()
+        .map(|(name, size, created_at, metadata)| JobArtifact {
+            metadata: serde_json::from_str(&metadata).unwrap_or_else(|e| {
+                error!("metadata of artifact {} of job {} could not be parsed: {:?}", name, job_id, e);
+                BTreeMap::new()
+            }),
+            name,
+            size,
+            created_at,
+        })
+        .collect())
+}
+
+/// Inclusive range of the bytes of an artifact
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ByteRange {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl ByteRange {
+    pub fn whole(size: i64) -> Self {
+        ByteRange { start: 0, end: size - 1 }
+    }
+
+    pub fn length(&self) -> i64 {
+        self.end - self.start + 1
+    }
+}
+
+/// Part of the artifact requested with the Range header
+#[derive(Debug, PartialEq)]
+pub enum RangeRequest {
+    Whole,
+    Partial(ByteRange),
+    Unsatisfiable,
+}
+
+/// Parses the Range header for an artifact of the given size. Only a single range is supported, e.g. `bytes=0-1023`,
+/// `bytes=1024-` or `bytes=-1024`, the whole artifact is served for the others as the header may be ignored.
+pub fn parse_range(header: Option<&str>, size: i64) -> RangeRequest {
+    let spec = match header.and_then(|header| header.trim().strip_prefix("bytes=")) {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return RangeRequest::Whole
+    };
+
+    let (start, end) = match spec.split_once('-') {
+        Some(bounds) => bounds,
+        None => return RangeRequest::Whole
+    };
+
+    let range = match (parse_bound(start), parse_bound(end)) {
+        // last bytes of the artifact
+        (None, Some(suffix)) if start.is_empty() => {
+            if suffix == 0 {
+                return RangeRequest::Unsatisfiable;
+            }
+
+            ByteRange { start: (size - suffix).max(0), end: size - 1 }
+        }
+        (Some(start), None) if end.is_empty() => ByteRange { start, end: size - 1 },
+        (Some(start), Some(end)) if start <= end => ByteRange { start, end: end.min(size - 1) },
+        _ => return RangeRequest::Whole
+    };
+
+    if range.start >= size {
+        return RangeRequest::Unsatisfiable;
+    }
+
+    RangeRequest::Partial(range)
+}
+
+// bounds are only digits, signs are not accepted by the header
+fn parse_bound(bound: &str) -> Option<i64> {
+    if bound.is_empty() || !bound.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    bound.parse::<i64>().ok()
+}
+
+/// Streams the range of the content of the artifact. Content is read a chunk at a time, so that the large
+/// artifacts are not loaded into the memory at once.
+pub fn content_stream(pool: DBPool, artifact_id: ModelId, range: ByteRange) -> impl Stream<Item=Result<Bytes, actix_web::Error>> {
+    futures::stream::unfold(range.start, move |offset| {
+        let pool = pool.clone();
+
+        async move {
+            if offset > range.end {
+                return None;
+            }
+
+            let length = CONTENT_CHUNK_SIZE.min(range.end - offset + 1);
+
+            let chunk = web::block(move || -> Result<Vec<u8>, String> {
+                let conn = pool.get().map_err(|e| e.to_string())?;
+
+                read_chunk(artifact_id, offset, length, &conn).map_err(|e| e.to_string())
+            })
+                .await;
+
+            match chunk {
+                Ok(chunk) => Some((Ok(Bytes::from(chunk)), offset + length)),
+                Err(e) => {
+                    error!("reading artifact {} at {} is failed: {:?}", artifact_id, offset, e);
+                    // stream is ended after the error
+                    Some((Err(actix_web::error::ErrorInternalServerError("reading artifact is failed")), range.end + 1))
+                }
+            }
+        }
+    })
+}
+
+fn read_chunk(artifact_id: ModelId, offset: i64, length: i64, conn: &PgConnection) -> QueryResult<Vec<u8>> {
+    // substring of bytea counts from 1
+    job_artifacts::table
+        .find(artifact_id)
+        .select(sql::<Bytea>(format!("substring(data from {} for {})", offset + 1, length).as_str()))
+        .first::<Vec<u8>>(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: i64 = 1000;
+
+    fn partial(start: i64, end: i64) -> RangeRequest {
+        RangeRequest::Partial(ByteRange { start, end })
+    }
+
+    #[test]
+    fn parses_bounded_ranges() {
+        assert_eq!(parse_range(Some("bytes=0-99"), SIZE), partial(0, 99));
+        assert_eq!(parse_range(Some("bytes=500-500"), SIZE), partial(500, 500));
+        assert_eq!(parse_range(Some(" bytes= 10-19 "), SIZE), partial(10, 19));
+        assert_eq!(ByteRange { start: 0, end: 99 }.length(), 100);
+        assert_eq!(ByteRange::whole(SIZE), ByteRange { start: 0, end: 999 });
+    }
+
+    #[test]
+    fn parses_open_ended_ranges() {
+        assert_eq!(parse_range(Some("bytes=100-"), SIZE), partial(100, 999));
+        assert_eq!(parse_range(Some("bytes=999-"), SIZE), partial(999, 999));
+    }
+
+    #[test]
+    fn parses_suffix_ranges() {
+        assert_eq!(parse_range(Some("bytes=-100"), SIZE), partial(900, 999));
+        // suffix longer than the artifact is the whole artifact
+        assert_eq!(parse_range(Some("bytes=-5000"), SIZE), partial(0, 999));
+        assert_eq!(parse_range(Some("bytes=-0"), SIZE), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn clamps_or_rejects_ranges_past_the_end() {
+        assert_eq!(parse_range(Some("bytes=900-5000"), SIZE), partial(900, 999));
+        assert_eq!(parse_range(Some("bytes=1000-1099"), SIZE), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=1000-"), SIZE), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-"), 0), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=-10"), 0), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn serves_the_whole_artifact_for_the_other_headers() {
+        assert_eq!(parse_range(None, SIZE), RangeRequest::Whole);
+        assert_eq!(parse_range(Some("bytes=0-9,20-29"), SIZE), RangeRequest::Whole);
+        assert_eq!(parse_range(Some("items=0-9"), SIZE), RangeRequest::Whole);
+        assert_eq!(parse_range(Some("bytes=9-0"), SIZE), RangeRequest::Whole);
+        assert_eq!(parse_range(Some("bytes=a-b"), SIZE), RangeRequest::Whole);
+        assert_eq!(parse_range(Some("bytes=10"), SIZE), RangeRequest::Whole);
+        assert_eq!(parse_range(Some("bytes=-"), SIZE), RangeRequest::Whole);
+        assert_eq!(parse_range(Some("bytes=--5"), SIZE), RangeRequest::Whole);
+        assert_eq!(parse_range(Some("bytes=+5-10"), SIZE), RangeRequest::Whole);
+        assert_eq!(parse_range(Some("bytes=99999999999999999999-"), SIZE), RangeRequest::Whole);
+    }
+}
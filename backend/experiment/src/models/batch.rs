@@ -0,0 +1,498 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::Queryable;
+use diesel::sql_types::VarChar;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use core::db::DieselEnum;
+use core::schema::{experiments, job_batches, jobs, runners};
+use core::types::{JobId, ModelId};
+
+use crate::models::job::{FailureReason, JobStatus, slim_job_columns, SlimJob, TransitionError, Verdict};
+
+const MAX_METRIC_NAME_LENGTH: usize = 64;
+const MAX_REPEAT_TIMES: i32 = 20;
+const MAX_REPEAT_METRICS: usize = 16;
+const DEFAULT_MAX_VARIATION: f64 = 0.1;
+
+/// Jobs launched together, e.g. a sweep of the same experiment over multiple runners
+#[derive(Queryable)]
+pub struct JobBatch {
+    pub id: ModelId,
+    pub uuid: Uuid,
+    pub experiment_id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub abort_after_failures: Option<i32>,
+    pub abort_metric: Option<String>,
+    pub abort_threshold: Option<f64>,
+    pub abort_crossing: Option<ThresholdCrossing>,
+    pub aborted_at: Option<NaiveDateTime>,
+    pub repeat_times: i32,
+    pub repeat_metrics: Vec<String>,
+    pub max_variation: Option<f64>,
+}
+
+/// Columns of `JobBatch`, the query should join the experiments of the batches
+pub const JOB_BATCH_COLUMNS: (job_batches::id, job_batches::uuid, experiments::uuid, job_batches::created_at, job_batches::abort_after_failures, job_batches::abort_metric, job_batches::abort_threshold, job_batches::abort_crossing, job_batches::aborted_at, job_batches::repeat_times, job_batches::repeat_metrics, job_batches::max_variation) = (
+    job_batches::id,
+    job_batches::uuid,
+    experiments::uuid,
+    job_batches::created_at,
+    job_batches::abort_after_failures,
+    job_batches::abort_metric,
+    job_batches::abort_threshold,
+    job_batches::abort_crossing,
+    job_batches::aborted_at,
+    job_batches::repeat_times,
+    job_batches::repeat_metrics,
+    job_batches::max_variation,
+);
+
+impl JobBatch {
+    pub fn abort_policy(&self) -> AbortPolicy {
+        let metric = match (&self.abort_metric, self.abort_threshold, self.abort_crossing) {
+            (Some(name), Some(threshold), Some(crossing)) => Some(MetricThreshold { name: name.clone(), threshold, crossing }),
+            _ => None
+        };
+
+        AbortPolicy {
+            after_failures: self.abort_after_failures,
+            metric,
+        }
+    }
+
+    pub fn repeat_policy(&self) -> RepeatPolicy {
+        RepeatPolicy {
+            times: self.repeat_times,
+            metrics: self.repeat_metrics.clone(),
+            max_variation: self.max_variation,
+        }
+    }
+}
+
+/// When the pending jobs of a batch are cancelled, the running ones are let to finish. Failures include the timed
+/// out jobs.
+#[derive(Clone, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AbortPolicy {
+    pub after_failures: Option<i32>,
+    pub metric: Option<MetricThreshold>,
+}
+
+impl AbortPolicy {
+    pub fn is_valid(&self) -> bool {
+        let valid_failures = self.after_failures.is_none_or(|failures| failures > 0);
+
+        let valid_metric = self.metric.as_ref()
+            .is_none_or(|metric| is_valid_metric_name(&metric.name) && metric.threshold.is_finite());
+
+        valid_failures && valid_metric
+    }
+}
+
+fn is_valid_metric_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= MAX_METRIC_NAME_LENGTH && !name.contains(char::is_whitespace)
+}
+
+/// Runs the experiment `times` times on each runner of the batch to find out whether its results are flaky. Runs
+/// on a runner are flaky if some of them succeed while the others fail, or if one of the `metrics` varies more
+/// than `max_variation`, which is the standard deviation relative to the mean.
+#[derive(Clone, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RepeatPolicy {
+    pub times: i32,
+    #[serde(default)]
+    pub metrics: Vec<String>,
+    // 0.1 by default, i.e. the metrics may vary by 10% around their means
+    pub max_variation: Option<f64>,
+}
+
+impl Default for RepeatPolicy {
+    fn default() -> Self {
+        RepeatPolicy {
+            times: 1,
+            metrics: Vec::new(),
+            max_variation: None,
+        }
+    }
+}
+
+impl RepeatPolicy {
+    pub fn is_valid(&self) -> bool {
+        (1..=MAX_REPEAT_TIMES).contains(&self.times) &&
+            self.metrics.len() <= MAX_REPEAT_METRICS &&
+            self.metrics.iter().all(|metric| is_valid_metric_name(metric)) &&
+            self.max_variation.is_none_or(|variation| variation.is_finite() && variation >= 0.0)
+    }
+}
+
+/// Metric reported by the jobs in their output, see `parse_metric`
+#[derive(Clone, Deserialize, Serialize, ToSchema)]
+pub struct MetricThreshold {
+    pub name: String,
+    pub threshold: f64,
+    pub crossing: ThresholdCrossing,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub enum ThresholdCrossing {
+    Above,
+    Below,
+}
+
+impl Default for ThresholdCrossing {
+    fn default() -> Self {
+        ThresholdCrossing::Above
+    }
+}
+
+impl Queryable<VarChar, Pg> for ThresholdCrossing {
+    type Row = String;
+
+    fn build(row: Self::Row) -> Self {
+        Self::build_from_string(row)
+    }
+}
+
+impl MetricThreshold {
+    fn crossed(&self, value: f64) -> bool {
+        match self.crossing {
+            ThresholdCrossing::Above => value > self.threshold,
+            ThresholdCrossing::Below => value < self.threshold,
+        }
+    }
+}
+
+/// Experiments report their metrics by printing `METRIC <name>=<value>` lines, the last value printed is taken
+pub fn parse_metric(output: &str, name: &str) -> Option<f64> {
+    output.lines()
+        .rev()
+        .filter_map(|line| line.trim().strip_prefix("METRIC "))
+        .filter_map(|metric| metric.split_once('='))
+        .find(|(metric, _)| metric.trim() == name)
+        .and_then(|(_, value)| value.trim().parse::<f64>().ok())
+}
+
+/// Evaluates the abort policy of the job's batch with the result of the job, which should already be applied.
+/// Pending jobs of the batch are cancelled once the policy is triggered, returns the number of the cancelled jobs.
+pub fn abort_if_triggered(job_id: JobId, output: &str, conn: &PgConnection) -> QueryResult<usize> {
+    let batch_id = match jobs::table.find(job_id).select(jobs::batch_id).first::<Option<ModelId>>(conn)? {
+        Some(batch_id) => batch_id,
+        None => return Ok(0)
+    };
+
+    let batch = job_batches::table
+        .inner_join(experiments::table)
+        .filter(job_batches::id.eq(batch_id))
+        .select(JOB_BATCH_COLUMNS)
+        .first::<JobBatch>(conn)?;
+
+    if batch.aborted_at.is_some() {
+        return Ok(0);
+    }
+
+    let policy = batch.abort_policy();
+
+    let metric_crossed = policy.metric
+        .is_some_and(|metric| parse_metric(output, &metric.name).is_some_and(|value| metric.crossed(value)));
+
+    let failures_exceeded = match policy.after_failures {
+        Some(after_failures) => jobs::table
+            .filter(jobs::batch_id.eq(batch.id))
+            .filter(jobs::status.eq_any(vec![JobStatus::Failed.value(), JobStatus::TimedOut.value()]))
+            .count()
+            .get_result::<i64>(conn)? >= after_failures as i64,
+        None => false
+    };
+
+    if !metric_crossed && !failures_exceeded {
+        return Ok(0);
+    }
+
+    // another result of the batch may have triggered the policy in the meantime
+    let aborted = diesel::update(job_batches::table.find(batch.id).filter(job_batches::aborted_at.is_null()))
+        .set(job_batches::aborted_at.eq(now))
+        .execute(conn)?;
+
+    if aborted == 0 {
+        return Ok(0);
+    }
+
+    let pending_jobs = jobs::table
+        .filter(jobs::batch_id.eq(batch.id))
+        .filter(jobs::status.eq(JobStatus::Pending.value()))
+        .select(jobs::id)
+        .load::<JobId>(conn)?;
+
+    let mut cancelled = 0;
+
+    for job_id in pending_jobs {
+        // job may have been dispatched in the meantime, which is rejected by the transition
+        match JobStatus::transition_to(job_id, JobStatus::Cancelled).apply(conn) {
+            Ok(()) => cancelled += 1,
+            Err(TransitionError::Illegal { .. }) => {}
+            Err(TransitionError::DB(e)) => return Err(e)
+        }
+    }
+
+    Ok(cancelled)
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSummary {
+    pub id: Uuid,
+    pub experiment_id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub abort_policy: AbortPolicy,
+    // set once the abort policy is triggered
+    pub aborted_at: Option<NaiveDateTime>,
+    pub job_count: usize,
+    // warm-up runs are counted among the jobs and the statuses, they are left out of the rest of the summary
+    pub warmup_count: usize,
+    // statuses without any job are left out
+    pub status_counts: Vec<StatusCount>,
+    // among the jobs which have run to completion, i.e. succeeded, failed or timed out on their runner, and are not
+    // marked as invalid by the user or warm-up runs
+    pub fastest: Option<BatchJobDuration>,
+    pub slowest: Option<BatchJobDuration>,
+    pub failures: Vec<BatchFailure>,
+    pub repeat_policy: RepeatPolicy,
+    // only for the batches repeating their runs
+    pub stability: Option<BatchStability>,
+    pub jobs: Vec<SlimJob>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StatusCount {
+    pub status: JobStatus,
+    pub count: usize,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchJobDuration {
+    pub job_id: Uuid,
+    pub runner_id: Option<Uuid>,
+    pub duration_seconds: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchFailure {
+    pub job_id: Uuid,
+    pub runner_id: Option<Uuid>,
+    pub status: JobStatus,
+    pub failure_reason: Option<FailureReason>,
+}
+
+const STATUSES: [JobStatus; 6] = [
+    JobStatus::Pending,
+    JobStatus::Running,
+    JobStatus::Successful,
+    JobStatus::Failed,
+    JobStatus::Cancelled,
+    JobStatus::TimedOut,
+];
+
+impl BatchSummary {
+    pub fn load(batch: JobBatch, conn: &PgConnection) -> QueryResult<BatchSummary> {
+        let jobs = jobs::table
+            .inner_join(experiments::table)
+            .left_join(runners::table)
+            .filter(jobs::batch_id.eq(batch.id))
+            .order(jobs::id.asc())
+            .select(slim_job_columns())
+            .load::<SlimJob>(conn)?;
+
+        let status_counts = STATUSES.iter()
+            .map(|status| StatusCount { status: *status, count: jobs.iter().filter(|job| job.status == *status).count() })
+            .filter(|status_count| status_count.count > 0)
+            .collect();
+
+        let mut durations = jobs.iter()
+            .filter(|job| matches!(job.status, JobStatus::Successful | JobStatus::Failed | JobStatus::TimedOut))
+            .filter(|job| job.verdict != Some(Verdict::Invalid) && !job.warmup)
+            .filter_map(|job| match (job.started_at, job.finished_at) {
+                (Some(started_at), Some(finished_at)) => Some(BatchJobDuration {
+                    job_id: job.id,
+                    runner_id: job.runner_id,
+                    duration_seconds: (finished_at - started_at).num_milliseconds() as f64 / 1000.0,
+                }),
+                _ => None
+            })
+            .collect::<Vec<BatchJobDuration>>();
+
+        durations.sort_by(|a, b| a.duration_seconds.total_cmp(&b.duration_seconds));
+
+        let failures = jobs.iter()
+            .filter(|job| matches!(job.status, JobStatus::Failed | JobStatus::TimedOut) && !job.warmup)
+            .map(|job| BatchFailure {
+                job_id: job.id,
+                runner_id: job.runner_id,
+                status: job.status,
+                failure_reason: job.failure_reason,
+            })
+            .collect();
+
+        let stability = if batch.repeat_times > 1 {
+            Some(BatchStability::load(&batch, conn)?)
+        } else {
+            None
+        };
+
+        Ok(BatchSummary {
+            id: batch.uuid,
+            experiment_id: batch.experiment_id,
+            created_at: batch.created_at,
+            abort_policy: batch.abort_policy(),
+            aborted_at: batch.aborted_at,
+            job_count: jobs.len(),
+            warmup_count: jobs.iter().filter(|job| job.warmup).count(),
+            status_counts,
+            fastest: durations.first().cloned(),
+            slowest: durations.last().cloned(),
+            failures,
+            repeat_policy: batch.repeat_policy(),
+            stability,
+            jobs,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, ToSchema)]
+pub enum Stability {
+    Stable,
+    Flaky,
+    // fewer than two runs have finished yet
+    Undecided,
+}
+
+/// Whether the repeated runs of a batch are flaky, runs are compared with the other runs on the same runner. Runs
+/// which have not run to completion, are marked as invalid by the user or are warm-up runs are left out.
+#[derive(Serialize, ToSchema)]
+pub struct BatchStability {
+    pub stability: Stability,
+    pub runners: Vec<RunnerStability>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerStability {
+    pub runner_id: Option<Uuid>,
+    pub runs: usize,
+    pub successful_runs: usize,
+    pub stability: Stability,
+    pub metrics: Vec<MetricVariation>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricVariation {
+    pub name: String,
+    // number of the runs reporting the metric
+    pub samples: usize,
+    pub mean: Option<f64>,
+    pub std_dev: Option<f64>,
+    // standard deviation relative to the mean, null if the mean is zero
+    pub variation: Option<f64>,
+    pub flaky: bool,
+}
+
+impl MetricVariation {
+    fn new(name: &str, samples: &[f64], max_variation: f64) -> Self {
+        if samples.len() < 2 {
+            return MetricVariation {
+                name: name.to_string(),
+                samples: samples.len(),
+                mean: samples.first().copied(),
+                std_dev: None,
+                variation: None,
+                flaky: false,
+            };
+        }
+
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let std_dev = (samples.iter().map(|sample| (sample - mean).powi(2)).sum::<f64>() / (samples.len() - 1) as f64).sqrt();
+        let variation = Some(mean.abs()).filter(|mean| *mean > 0.0).map(|mean| std_dev / mean);
+
+        MetricVariation {
+            name: name.to_string(),
+            samples: samples.len(),
+            mean: Some(mean),
+            std_dev: Some(std_dev),
+            variation,
+            flaky: match variation {
+                Some(variation) => variation > max_variation,
+                None => std_dev > 0.0
+            },
+        }
+    }
+}
+
+impl BatchStability {
+    fn load(batch: &JobBatch, conn: &PgConnection) -> QueryResult<BatchStability> {
+        let runs = jobs::table
+            .left_join(runners::table)
+            .filter(jobs::batch_id.eq(batch.id))
+            .filter(jobs::warmup.eq(false))
+            .filter(jobs::status.eq_any(vec![JobStatus::Successful.value(), JobStatus::Failed.value(), JobStatus::TimedOut.value()]))
+            .order(jobs::id.asc())
+            .select((runners::uuid.nullable(), jobs::status, jobs::verdict, jobs::output))
+            .load::<(Option<Uuid>, JobStatus, Option<Verdict>, String)>(conn)?;
+
+        let mut runs_by_runner: BTreeMap<Option<Uuid>, Vec<(JobStatus, String)>> = BTreeMap::new();
+
+        for (runner_id, status, verdict, output) in runs {
+            if verdict != Some(Verdict::Invalid) {
+                runs_by_runner.entry(runner_id).or_default().push((status, output));
+            }
+        }
+
+        let max_variation = batch.max_variation.unwrap_or(DEFAULT_MAX_VARIATION);
+
+        let runners = runs_by_runner.into_iter()
+            .map(|(runner_id, runs)| {
+                let successful_runs = runs.iter().filter(|(status, _)| *status == JobStatus::Successful).count();
+
+                let metrics = batch.repeat_metrics.iter()
+                    .map(|name| {
+                        let samples = runs.iter()
+                            .filter_map(|(_, output)| parse_metric(output, name))
+                            .collect::<Vec<f64>>();
+
+                        MetricVariation::new(name, &samples, max_variation)
+                    })
+                    .collect::<Vec<MetricVariation>>();
+
+                let mixed_outcomes = successful_runs > 0 && successful_runs < runs.len();
+
+                let stability = if runs.len() < 2 {
+                    Stability::Undecided
+                } else if mixed_outcomes || metrics.iter().any(|metric| metric.flaky) {
+                    Stability::Flaky
+                } else {
+                    Stability::Stable
+                };
+
+                RunnerStability { runner_id, runs: runs.len(), successful_runs, stability, metrics }
+            })
+            .collect::<Vec<RunnerStability>>();
+
+        let stability = if runners.iter().any(|runner| runner.stability == Stability::Flaky) {
+            Stability::Flaky
+        } else if runners.is_empty() || runners.iter().any(|runner| runner.stability == Stability::Undecided) {
+            Stability::Undecided
+        } else {
+            Stability::Stable
+        };
+
+        Ok(BatchStability { stability, runners })
+    }
+}
@@ -0,0 +1,28 @@
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+
+use core::schema::code_blobs;
+use core::utils::fingerprint;
+
+/// Stores the code once for all of the jobs running it and returns its hash, jobs refer to their code with it.
+/// References are counted by the database as the jobs are inserted and deleted, see the `code_blobs` migration.
+pub fn store(code: &str, conn: &PgConnection) -> QueryResult<String> {
+    let hash = fingerprint(code.as_bytes());
+
+    // existing blob is locked by the update, so that it is not removed by a job deleted meanwhile
+    diesel::insert_into(code_blobs::table)
+        .values((code_blobs::hash.eq(&hash), code_blobs::code.eq(code)))
+        .on_conflict(code_blobs::hash)
+        .do_update()
+        .set(code_blobs::hash.eq(excluded(code_blobs::hash)))
+        .execute(conn)?;
+
+    Ok(hash)
+}
+
+pub fn load(hash: &str, conn: &PgConnection) -> QueryResult<String> {
+    code_blobs::table
+        .find(hash)
+        .select(code_blobs::code)
+        .first::<String>(conn)
+}
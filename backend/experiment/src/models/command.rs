@@ -0,0 +1,123 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::Queryable;
+use diesel::sql_types::VarChar;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use core::db::DieselEnum;
+use core::schema::runner_commands;
+use core::types::{ModelId, RunnerId, UserId};
+use shared::websocket_messages::client;
+
+/// Administrative command sent to a runner, its result is reported back by the runner
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerCommand {
+    pub id: ModelId,
+    // commands are fetched by the uuid of their runner
+    #[serde(skip_serializing)]
+    pub runner_id: RunnerId,
+    pub kind: CommandKind,
+    pub status: CommandStatus,
+    pub output: Option<String>,
+    pub created_by: Option<UserId>,
+    pub created_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+pub const RUNNER_COMMAND_COLUMNS: (runner_commands::id, runner_commands::runner_id, runner_commands::kind, runner_commands::status, runner_commands::output, runner_commands::created_by, runner_commands::created_at, runner_commands::finished_at) = (
+    runner_commands::id,
+    runner_commands::runner_id,
+    runner_commands::kind,
+    runner_commands::status,
+    runner_commands::output,
+    runner_commands::created_by,
+    runner_commands::created_at,
+    runner_commands::finished_at,
+);
+
+// longer outputs are cut, diagnostics bundles are expected to be far smaller
+const MAX_OUTPUT_SIZE: usize = 64 * 1024;
+
+impl RunnerCommand {
+    /// Records the result of a pending command of the runner.
+    pub fn finish(command_id: ModelId, runner_id: RunnerId, status: CommandStatus, mut output: String, conn: &PgConnection)
+                  -> QueryResult<usize> {
+        if output.len() > MAX_OUTPUT_SIZE {
+            let mut end = MAX_OUTPUT_SIZE;
+
+            while !output.is_char_boundary(end) {
+                end -= 1;
+            }
+
+            output.truncate(end);
+        }
+
+        diesel::update(runner_commands::table
+            .find(command_id)
+            .filter(runner_commands::runner_id.eq(runner_id))
+            .filter(runner_commands::status.eq(CommandStatus::Pending.value()))
+        )
+            .set((
+                runner_commands::status.eq(status.value()),
+                runner_commands::output.eq(output),
+                runner_commands::finished_at.eq(now.nullable()),
+            ))
+            .execute(conn)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub enum CommandKind {
+    RestartClient,
+    ClearWorkspace,
+    FetchDiagnostics,
+}
+
+impl Default for CommandKind {
+    fn default() -> Self {
+        CommandKind::FetchDiagnostics
+    }
+}
+
+impl From<CommandKind> for client::RunnerCommandKind {
+    fn from(kind: CommandKind) -> Self {
+        match kind {
+            CommandKind::RestartClient => client::RunnerCommandKind::RestartClient,
+            CommandKind::ClearWorkspace => client::RunnerCommandKind::ClearWorkspace,
+            CommandKind::FetchDiagnostics => client::RunnerCommandKind::FetchDiagnostics,
+        }
+    }
+}
+
+impl Queryable<VarChar, Pg> for CommandKind {
+    type Row = String;
+
+    fn build(row: Self::Row) -> Self {
+        Self::build_from_string(row)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub enum CommandStatus {
+    Pending,
+    Successful,
+    Failed,
+}
+
+impl Default for CommandStatus {
+    fn default() -> Self {
+        CommandStatus::Pending
+    }
+}
+
+impl Queryable<VarChar, Pg> for CommandStatus {
+    type Row = String;
+
+    fn build(row: Self::Row) -> Self {
+        Self::build_from_string(row)
+    }
+}
@@ -0,0 +1,46 @@
+use diesel::prelude::*;
+use diesel::pg::upsert::excluded;
+use log::error;
+
+use core::schema::job_environments;
+use core::types::JobId;
+use shared::websocket_messages::server::Environment;
+
+/// Records the environment the runner reported for the job, a job run again after its runner is lost keeps the
+/// environment of its last run
+pub fn record(job_id: JobId, environment: &Environment, conn: &PgConnection) -> QueryResult<()> {
+    // serializing the maps of strings and numbers does not fail
+    let snapshot = serde_json::to_string(environment).unwrap();
+
+    diesel::insert_into(job_environments::table)
+        .values((
+            job_environments::job_id.eq(job_id),
+            job_environments::snapshot.eq(snapshot),
+        ))
+        .on_conflict(job_environments::job_id)
+        .do_update()
+        .set((
+            job_environments::snapshot.eq(excluded(job_environments::snapshot)),
+            job_environments::recorded_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Returns none if the runner of the job did not report its environment, e.g. an older client ran the job
+pub fn load(job_id: JobId, conn: &PgConnection) -> QueryResult<Option<Environment>> {
+    let snapshot = job_environments::table
+        .find(job_id)
+        .select(job_environments::snapshot)
+        .first::<String>(conn)
+        .optional()?;
+
+    Ok(snapshot.and_then(|snapshot| match serde_json::from_str::<Environment>(&snapshot) {
+        Ok(environment) => Some(environment),
+        Err(e) => {
+            error!("environment of job {} could not be parsed: {:?}", job_id, e);
+            None
+        }
+    }))
+}
@@ -1,34 +1,227 @@
 use chrono::NaiveDateTime;
 use diesel::{Identifiable, Queryable};
-use serde::Serialize;
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+use diesel::sql_types::VarChar;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
 
-use core::schema::experiments;
-use core::types::ModelId;
+use shared::websocket_messages::client::{NetworkEmulation, PerformanceSettings, SdrRecording, SdrRecordingKind, SdrSettings};
+use shared::websocket_messages::server::Diagnostic;
 
-#[derive(Identifiable, Queryable, Serialize)]
+use core::db::DieselEnum;
+use core::schema::{experiment_sdr_settings, experiments};
+use core::types::{ExperimentId, ModelId, UserId};
+
+/// Experiments are referred with their uuids outside, sequential ids are only used internally
+#[derive(Identifiable, Queryable, Serialize, ToSchema)]
 pub struct Experiment {
-    pub id: ModelId,
-    pub user_id: ModelId,
+    #[serde(skip_serializing)]
+    pub id: ExperimentId,
+    pub user_id: UserId,
     pub name: String,
     pub code: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    // flashed to the device before each run, if it is given
+    pub firmware_id: Option<ModelId>,
+    #[serde(rename = "id")]
+    pub uuid: Uuid,
+    // starred experiments are listed first
+    pub starred: bool,
+    // Markdown, it is rendered into HTML by the server for display
+    pub description: String,
+    // archived experiments are hidden from the listing and can not be run, their jobs are kept
+    pub archived_at: Option<NaiveDateTime>,
+    // unique among the experiments of the user, derived from the name
+    pub slug: String,
+    // deleted experiments are hidden by the authorization filters until the `ExperimentCleaner` removes them
+    #[serde(skip_serializing)]
+    pub deleted_at: Option<NaiveDateTime>,
+    // finished jobs older than these many days, or beyond these many latest jobs are removed, the retention policy
+    // of the app is applied if they are not given
+    pub retention_days: Option<i32>,
+    pub retention_jobs: Option<i32>,
+    // how the server picks a runner for the jobs of the experiment
+    pub runner_strategy: RunnerStrategy,
+    // runners having more of these labels are preferred by the LabelAffinity strategy
+    pub preferred_labels: Vec<String>,
+    // verdicts of the jobs are derived from their metrics with these criteria, see `criteria::Criteria`
+    pub success_criteria: Option<String>,
+    // batches start with this many warm-up runs on each runner, which are left out of the batch statistics
+    pub warmup_runs: i32,
+    // jobs wait for a runner whose hottest thermal zone is below this many degrees Celsius, at most for
+    // thermal_timeout seconds, so that the measurements are not skewed by a device still hot from the previous job
+    pub max_temperature: Option<f64>,
+    pub thermal_timeout: i32,
+    // performance settings applied by the runners during the jobs, see `load_performance_settings`
+    pub cpu_governor: Option<String>,
+    pub cpu_frequency_khz: Option<i32>,
+    pub disable_turbo: bool,
+    // network conditions emulated by the runners during the jobs, see `load_network_emulation`
+    pub netem_delay_ms: Option<i32>,
+    pub netem_jitter_ms: Option<i32>,
+    pub netem_loss_percent: Option<f64>,
+    pub netem_rate_kbit: Option<i32>,
+    // experiments of a project are shared with its members, see `models::project`
+    #[serde(skip_serializing)]
+    pub project_id: Option<ModelId>,
+}
+
+/// Strategy of picking one of the idle runners for a job, see `connection::strategy` for the details
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Serialize, PartialEq, ToSchema)]
+pub enum RunnerStrategy {
+    BestScore,
+    LeastLoaded,
+    RoundRobin,
+    FastestHistorical,
+    LabelAffinity,
+}
+
+impl Default for RunnerStrategy {
+    fn default() -> Self {
+        RunnerStrategy::BestScore
+    }
+}
+
+impl Queryable<VarChar, Pg> for RunnerStrategy {
+    type Row = String;
+
+    fn build(row: Self::Row) -> Self {
+        Self::build_from_string(row)
+    }
 }
 
-#[derive(Queryable, Serialize)]
+#[derive(Queryable, Serialize, ToSchema)]
 pub struct SlimExperiment {
-    pub id: ModelId,
-    pub user_id: ModelId,
+    #[serde(rename = "id")]
+    pub uuid: Uuid,
+    pub user_id: UserId,
     pub name: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub starred: bool,
+    pub archived_at: Option<NaiveDateTime>,
+    pub slug: String,
 }
 
-pub const SLIM_EXPERIMENT_COLUMNS: (experiments::id, experiments::user_id, experiments::name, experiments::created_at, experiments::updated_at) = (
-    experiments::id,
+pub const SLIM_EXPERIMENT_COLUMNS: (experiments::uuid, experiments::user_id, experiments::name, experiments::created_at, experiments::updated_at, experiments::starred, experiments::archived_at, experiments::slug) = (
+    experiments::uuid,
     experiments::user_id,
     experiments::name,
     experiments::created_at,
-    experiments::updated_at
+    experiments::updated_at,
+    experiments::starred,
+    experiments::archived_at,
+    experiments::slug
 );
 
+const MAX_SLUG_LENGTH: usize = 96;
+
+/// Slug of the experiment name, made of lowercase ascii letters, digits and dashes. Names without any of them
+/// are given a generic slug.
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len().min(MAX_SLUG_LENGTH));
+
+    for c in name.chars() {
+        if slug.len() == MAX_SLUG_LENGTH {
+            break;
+        }
+
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug = slug.trim_end_matches('-');
+
+    if slug.is_empty() {
+        String::from("experiment")
+    } else {
+        String::from(slug)
+    }
+}
+
+/// Performance settings the runners apply while the jobs of the experiment run, none if it does not request any
+pub fn load_performance_settings(experiment_id: ExperimentId, conn: &PgConnection) -> QueryResult<Option<PerformanceSettings>> {
+    let (governor, frequency_khz, disable_turbo) = experiments::table
+        .find(experiment_id)
+        .select((experiments::cpu_governor, experiments::cpu_frequency_khz, experiments::disable_turbo))
+        .first::<(Option<String>, Option<i32>, bool)>(conn)?;
+
+    if governor.is_none() && frequency_khz.is_none() && !disable_turbo {
+        return Ok(None);
+    }
+
+    Ok(Some(PerformanceSettings {
+        governor,
+        disable_turbo,
+        frequency_khz: frequency_khz.map(|khz| khz as u64),
+    }))
+}
+
+/// Network conditions the runners emulate while the jobs of the experiment run, none if it does not request any
+pub fn load_network_emulation(experiment_id: ExperimentId, conn: &PgConnection) -> QueryResult<Option<NetworkEmulation>> {
+    let (delay_ms, jitter_ms, loss_percent, rate_kbit) = experiments::table
+        .find(experiment_id)
+        .select((experiments::netem_delay_ms, experiments::netem_jitter_ms, experiments::netem_loss_percent, experiments::netem_rate_kbit))
+        .first::<(Option<i32>, Option<i32>, Option<f64>, Option<i32>)>(conn)?;
+
+    if delay_ms.is_none() && loss_percent.is_none() && rate_kbit.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(NetworkEmulation {
+        delay_ms: delay_ms.map(|ms| ms as u32),
+        jitter_ms: jitter_ms.map(|ms| ms as u32),
+        loss_percent,
+        rate_kbit: rate_kbit.map(|kbit| kbit as u64),
+    }))
+}
+
+/// Settings the runners tune their SDR to before the jobs of the experiment, none if it does not request any
+pub fn load_sdr_settings(experiment_id: ExperimentId, conn: &PgConnection) -> QueryResult<Option<SdrSettings>> {
+    let settings = experiment_sdr_settings::table
+        .find(experiment_id)
+        .select((
+            experiment_sdr_settings::frequency_hz,
+            experiment_sdr_settings::gain_db,
+            experiment_sdr_settings::sample_rate,
+            experiment_sdr_settings::recording,
+            experiment_sdr_settings::recording_ms,
+        ))
+        .first::<(i64, f64, i32, Option<String>, Option<i32>)>(conn)
+        .optional()?;
+
+    Ok(settings.map(|(frequency_hz, gain_db, sample_rate, recording, recording_ms)| {
+        let kind = match recording.as_deref() {
+            Some("iq") => Some(SdrRecordingKind::Iq),
+            Some("spectrum") => Some(SdrRecordingKind::Spectrum),
+            _ => None
+        };
+
+        SdrSettings {
+            frequency_hz: frequency_hz as u64,
+            gain_db,
+            sample_rate: sample_rate as u32,
+            recording: kind.zip(recording_ms).map(|(kind, duration_ms)| SdrRecording { kind, duration_ms: duration_ms as u32 }),
+        }
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RenderedDescription {
+    pub html: String,
+}
+
+/// Diagnostics of the experiment's code reported by the runner, it is valid if none of them is an error
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExperimentValidation {
+    pub valid: bool,
+    pub runner_id: Uuid,
+    pub diagnostics: Vec<Diagnostic>,
+}
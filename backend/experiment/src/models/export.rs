@@ -0,0 +1,97 @@
+use std::io;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use log::error;
+use serde::Serialize;
+use tar::{Builder, Header};
+use uuid::Uuid;
+
+use core::error::ErrorMessaging;
+use core::ErrorMessage;
+use core::schema::{all_jobs, code_blobs, experiments, job_artifacts};
+use user::models::user::User;
+
+use crate::authorization::can_edit_experiment;
+use crate::models::experiment::Experiment;
+use crate::models::job::Job;
+
+/// Job along with the uuid of its experiment and its code, its output is written into the archive separately
+#[derive(Serialize)]
+struct ExportedJob {
+    #[serde(flatten)]
+    job: Job,
+    experiment_id: Uuid,
+    code: String,
+}
+
+/// Data of the user for the data portability requests, a gzipped tar of
+/// - `profile.json`, the user
+/// - `experiments.json`, experiments of the user
+/// - `jobs.json`, metadata of the jobs of the experiments
+/// - `jobs/{id}/output.txt` and `jobs/{id}/artifacts/{name}`, results of the jobs
+///
+/// Jobs moved into the archive are exported along with the others. Experiments shared with the user through the
+/// projects and the deleted ones are not exported.
+pub fn build(user: &User, conn: &PgConnection) -> Result<Vec<u8>, Box<dyn ErrorMessaging>> {
+    let experiments = experiments::table
+        .filter(can_edit_experiment(user.id))
+        .order(experiments::created_at.asc())
+        .load::<Experiment>(conn)?;
+
+    let jobs = all_jobs::table
+        .inner_join(experiments::table)
+        .inner_join(code_blobs::table)
+        .filter(can_edit_experiment(user.id))
+        .order(all_jobs::created_at.asc())
+        .select((all_jobs::all_columns, experiments::uuid, code_blobs::code))
+        .load::<(Job, Uuid, String)>(conn)?
+        .into_iter()
+        .map(|(job, experiment_id, code)| ExportedJob { job, experiment_id, code })
+        .collect::<Vec<ExportedJob>>();
+
+    let artifacts = job_artifacts::table
+        .inner_join(all_jobs::table.inner_join(experiments::table))
+        .filter(can_edit_experiment(user.id))
+        .select((all_jobs::uuid, job_artifacts::name, job_artifacts::data))
+        .load::<(Uuid, String, Vec<u8>)>(conn)?;
+
+    encode(user, &experiments, &jobs, &artifacts)
+        .map_err(|e| {
+            error!("Error while encoding the export of user {}: {:?}", user.id, e);
+            ErrorMessage::UnknownError.into()
+        })
+}
+
+fn encode(user: &User, experiments: &[Experiment], jobs: &[ExportedJob], artifacts: &[(Uuid, String, Vec<u8>)]) -> io::Result<Vec<u8>> {
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    let mtime = Utc::now().timestamp() as u64;
+
+    append(&mut builder, "profile.json", &serde_json::to_vec_pretty(user)?, mtime)?;
+    append(&mut builder, "experiments.json", &serde_json::to_vec_pretty(experiments)?, mtime)?;
+    append(&mut builder, "jobs.json", &serde_json::to_vec_pretty(jobs)?, mtime)?;
+
+    for job in jobs {
+        append(&mut builder, &format!("jobs/{}/output.txt", job.job.uuid), job.job.output.as_bytes(), mtime)?;
+    }
+
+    for (job_id, name, data) in artifacts {
+        // names are given by the runners, they should not escape the directory of the job
+        let name = name.replace(['/', '\\'], "_");
+
+        append(&mut builder, &format!("jobs/{}/artifacts/{}", job_id, name), data, mtime)?;
+    }
+
+    builder.into_inner()?.finish()
+}
+
+fn append<W: io::Write>(builder: &mut Builder<W>, path: &str, data: &[u8], mtime: u64) -> io::Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+
+    builder.append_data(&mut header, path, data)
+}
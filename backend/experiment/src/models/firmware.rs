@@ -0,0 +1,28 @@
+use chrono::NaiveDateTime;
+use diesel::Queryable;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use core::schema::firmwares;
+use core::types::{ModelId, UserId};
+
+/// Firmware image of an experiment, flashed to the device under test before the experiment code
+/// runs. Images are kept while jobs refer to them, uploading a new one does not change the past jobs.
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Firmware {
+    pub id: ModelId,
+    pub user_id: UserId,
+    pub name: String,
+    // size of the image in bytes
+    pub size: i32,
+    pub created_at: NaiveDateTime,
+}
+
+pub const FIRMWARE_COLUMNS: (firmwares::id, firmwares::user_id, firmwares::name, firmwares::size, firmwares::created_at) = (
+    firmwares::id,
+    firmwares::user_id,
+    firmwares::name,
+    firmwares::size,
+    firmwares::created_at,
+);
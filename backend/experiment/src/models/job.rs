@@ -1,31 +1,202 @@
 use chrono::NaiveDateTime;
-use diesel::{Identifiable, Queryable};
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+use diesel::dsl::{now, Nullable};
 use diesel::pg::Pg;
+use diesel::prelude::*;
 use diesel::sql_types::VarChar;
+use log::warn;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
 
 use core::db::DieselEnum;
-use core::schema::jobs;
-use core::types::ModelId;
+use core::error::{ErrorMessaging, HttpError};
+use core::ErrorMessage;
+use core::schema::{all_jobs, code_blobs, experiments, job_streams, jobs, runners};
+use core::types::{ExperimentId, JobId, ModelId, RunnerId};
+use shared::websocket_messages::server::Environment;
 
-#[derive(Identifiable, Queryable, Serialize)]
+use crate::models::activity::{ActivityEntry, ActivityKind};
+use crate::models::artifact::JobArtifact;
+
+/// Experiment and runner of the job are referred with their uuids by `PublicJob`
+#[derive(Identifiable, Queryable, Serialize, ToSchema)]
 pub struct Job {
-    pub id: ModelId,
-    pub experiment_id: ModelId,
-    pub runner_id: ModelId,
-    pub code: String,
+    #[serde(skip_serializing)]
+    pub id: JobId,
+    #[serde(skip_serializing)]
+    pub experiment_id: ExperimentId,
+    #[serde(skip_serializing)]
+    pub runner_id: Option<RunnerId>,
+    // jobs running the same code share it, see `models::code`
+    pub code_hash: String,
     pub status: JobStatus,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    #[serde(skip_serializing)]
+    pub output: String,
+    pub output_truncated: bool,
+    pub ansi_mode: AnsiMode,
+    pub failure_reason: Option<FailureReason>,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+    // optional hooks of the runner, e.g. power cycling the device, run around the job
+    pub hooks: Vec<String>,
+    pub firmware_id: Option<ModelId>,
+    pub flash_status: Option<FlashStatus>,
+    #[serde(rename = "id")]
+    pub uuid: Uuid,
+    // protected jobs are exempt from the retention policy and the purges
+    pub protected: bool,
+    // batch the job is launched with, if it is launched along with other jobs
+    #[serde(skip_serializing)]
+    pub batch_id: Option<ModelId>,
+    // renewed by the heartbeats of the runner while the job is running
+    #[serde(skip_serializing)]
+    pub lease_expires_at: Option<NaiveDateTime>,
+    // times the job is put back into the queue since its lease has expired
+    pub reclaim_count: i32,
+    // pending jobs with higher priorities are dispatched first
+    pub queue_priority: i32,
+    pub annotation: Option<String>,
+    // given by the users, overrides the status in the comparisons
+    pub verdict: Option<Verdict>,
+    pub annotated_at: Option<NaiveDateTime>,
+    // derived from the metrics of the job by the success criteria of its experiment
+    pub derived_verdict: Option<Verdict>,
+    // warm-up runs of a batch are left out of its statistics
+    pub warmup: bool,
+}
+
+/// Job as it is shown to the users, along with the uuids of its experiment and runner
+#[derive(Serialize, ToSchema)]
+pub struct PublicJob {
+    #[serde(flatten)]
+    pub job: Job,
+    pub experiment_id: Uuid,
+    pub runner_id: Option<Uuid>,
+    pub code: String,
+}
+
+impl PublicJob {
+    pub fn load(job: Job, conn: &PgConnection) -> QueryResult<PublicJob> {
+        // job may be in the archive
+        let (experiment_id, runner_id, code) = all_jobs::table
+            .inner_join(experiments::table)
+            .left_join(runners::table)
+            .inner_join(code_blobs::table)
+            .filter(all_jobs::id.eq(job.id))
+            .select((experiments::uuid, runners::uuid.nullable(), code_blobs::code))
+            .first::<(Uuid, Option<Uuid>, String)>(conn)?;
+
+        Ok(PublicJob { job, experiment_id, runner_id, code })
+    }
+}
+
+/// Selected by `slim_job_columns`, jobs are queried along with their experiments and runners
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SlimJob {
+    pub id: Uuid,
+    pub experiment_id: Uuid,
+    pub runner_id: Option<Uuid>,
+    pub status: JobStatus,
+    pub failure_reason: Option<FailureReason>,
+    pub created_at: NaiveDateTime,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+    pub protected: bool,
+    pub verdict: Option<Verdict>,
+    pub annotation: Option<String>,
+    pub derived_verdict: Option<Verdict>,
+    pub warmup: bool,
+}
+
+pub type SlimJobColumns = (jobs::uuid, experiments::uuid, Nullable<runners::uuid>, jobs::status, jobs::failure_reason, jobs::created_at, jobs::started_at, jobs::finished_at, jobs::protected, jobs::verdict, jobs::annotation, jobs::derived_verdict, jobs::warmup);
+
+/// Columns of `SlimJob`, the query should join the experiments and left join the runners of the jobs
+pub fn slim_job_columns() -> SlimJobColumns {
+    (
+        jobs::uuid,
+        experiments::uuid,
+        runners::uuid.nullable(),
+        jobs::status,
+        jobs::failure_reason,
+        jobs::created_at,
+        jobs::started_at,
+        jobs::finished_at,
+        jobs::protected,
+        jobs::verdict,
+        jobs::annotation,
+        jobs::derived_verdict,
+        jobs::warmup,
+    )
 }
 
+pub type AllSlimJobColumns = (all_jobs::uuid, experiments::uuid, Nullable<runners::uuid>, all_jobs::status, all_jobs::failure_reason, all_jobs::created_at, all_jobs::started_at, all_jobs::finished_at, all_jobs::protected, all_jobs::verdict, all_jobs::annotation, all_jobs::derived_verdict, all_jobs::warmup);
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+/// Same as `slim_job_columns` for the jobs including the archived ones
+pub fn all_slim_job_columns() -> AllSlimJobColumns {
+    (
+        all_jobs::uuid,
+        experiments::uuid,
+        runners::uuid.nullable(),
+        all_jobs::status,
+        all_jobs::failure_reason,
+        all_jobs::created_at,
+        all_jobs::started_at,
+        all_jobs::finished_at,
+        all_jobs::protected,
+        all_jobs::verdict,
+        all_jobs::annotation,
+        all_jobs::derived_verdict,
+        all_jobs::warmup,
+    )
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct JobDetail {
+    #[serde(flatten)]
+    pub job: PublicJob,
+    pub streams: Vec<JobStream>,
+    // state of the runner when the job started, if the runner reports it
+    pub environment: Option<Environment>,
+    // files collected by the runner, served by `job/{id}/artifact/{name}`
+    pub artifacts: Vec<JobArtifact>,
+}
+
+/// Output of a job recorded besides stdout and stderr, e.g. the serial console of the device
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStream {
+    #[serde(skip_serializing)]
+    pub id: ModelId,
+    #[serde(skip_serializing)]
+    pub job_id: JobId,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub output: String,
+    pub truncated: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "job_streams"]
+pub struct NewJobStream {
+    pub job_id: JobId,
+    pub name: String,
+    pub output: String,
+    pub truncated: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
 pub enum JobStatus {
     Pending,
     Running,
     Successful,
     Failed,
+    Cancelled,
+    TimedOut,
 }
 
 impl Default for JobStatus {
@@ -34,6 +205,190 @@ impl Default for JobStatus {
     }
 }
 
+impl JobStatus {
+    /// Statuses from which a job can move into this status. A pending job can fail or be cancelled
+    /// before it is dispatched to a runner, a running job is put back into the queue if its runner loses its lease.
+    pub fn sources(&self) -> &'static [JobStatus] {
+        match self {
+            JobStatus::Pending => &[JobStatus::Running],
+            JobStatus::Running => &[JobStatus::Pending],
+            JobStatus::Successful | JobStatus::TimedOut => &[JobStatus::Running],
+            JobStatus::Failed | JobStatus::Cancelled => &[JobStatus::Pending, JobStatus::Running],
+        }
+    }
+
+    pub fn can_transition_to(&self, next: JobStatus) -> bool {
+        next.sources().contains(self)
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            JobStatus::Pending | JobStatus::Running => false,
+            _ => true
+        }
+    }
+
+    /// Starts a transition of the job into the `next` status. Transition is only applied if it is
+    /// legal for the job's current status.
+    pub fn transition_to(job_id: JobId, next: JobStatus) -> Transition {
+        Transition {
+            job_id,
+            next,
+            runner_id: None,
+            output: None,
+            streams: Vec::new(),
+            flash_status: None,
+            failure_reason: None,
+        }
+    }
+}
+
+pub struct Transition {
+    job_id: JobId,
+    next: JobStatus,
+    runner_id: Option<RunnerId>,
+    output: Option<(String, bool)>,
+    streams: Vec<NewJobStream>,
+    flash_status: Option<FlashStatus>,
+    failure_reason: Option<FailureReason>,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "jobs"]
+struct TransitionChangeset {
+    status: String,
+    runner_id: Option<RunnerId>,
+    output: Option<String>,
+    output_truncated: Option<bool>,
+    flash_status: Option<String>,
+    failure_reason: Option<String>,
+}
+
+impl Transition {
+    pub fn runner_id(self, runner_id: RunnerId) -> Self {
+        Transition { runner_id: Some(runner_id), ..self }
+    }
+
+    pub fn output(self, output: String, truncated: bool) -> Self {
+        Transition { output: Some((output, truncated)), ..self }
+    }
+
+    /// Streams are stored only if the transition is applied
+    pub fn streams(self, streams: Vec<NewJobStream>) -> Self {
+        Transition { streams, ..self }
+    }
+
+    pub fn flash_status(self, flash_status: FlashStatus) -> Self {
+        Transition { flash_status: Some(flash_status), ..self }
+    }
+
+    pub fn failure_reason(self, failure_reason: FailureReason) -> Self {
+        Transition { failure_reason: Some(failure_reason), ..self }
+    }
+
+    pub fn apply(self, conn: &PgConnection) -> Result<(), TransitionError> {
+        let job_id = self.job_id;
+        let next = self.next;
+        let sources = next.sources().iter().map(|s| s.value()).collect::<Vec<String>>();
+        let (output, output_truncated) = match self.output {
+            Some((output, truncated)) => (Some(output), Some(truncated)),
+            None => (None, None)
+        };
+
+        let changeset = TransitionChangeset {
+            status: next.value(),
+            runner_id: self.runner_id,
+            output,
+            output_truncated,
+            flash_status: self.flash_status.map(|s| s.value()),
+            failure_reason: self.failure_reason.map(|r| r.value()),
+        };
+
+        // Checking the current status in the same statement avoids racing with other updates
+        let target = jobs::table
+            .find(job_id)
+            .filter(jobs::status.eq_any(sources));
+
+        let updated = match next {
+            // requeued job is dispatched from scratch, possibly to another runner
+            JobStatus::Pending => diesel::update(target)
+                .set((
+                    &changeset,
+                    jobs::runner_id.eq(None::<RunnerId>),
+                    jobs::started_at.eq(None::<NaiveDateTime>),
+                    jobs::lease_expires_at.eq(None::<NaiveDateTime>),
+                    jobs::reclaim_count.eq(jobs::reclaim_count + 1),
+                ))
+                .returning(jobs::experiment_id)
+                .get_result::<ExperimentId>(conn),
+            JobStatus::Running => diesel::update(target)
+                .set((&changeset, jobs::started_at.eq(now.nullable())))
+                .returning(jobs::experiment_id)
+                .get_result::<ExperimentId>(conn),
+            next if next.is_terminal() => diesel::update(target)
+                .set((&changeset, jobs::finished_at.eq(now.nullable())))
+                .returning(jobs::experiment_id)
+                .get_result::<ExperimentId>(conn),
+            _ => diesel::update(target)
+                .set(&changeset)
+                .returning(jobs::experiment_id)
+                .get_result::<ExperimentId>(conn)
+        }
+            .optional()
+            .map_err(TransitionError::DB)?;
+
+        if let Some(experiment_id) = updated {
+            if !self.streams.is_empty() {
+                diesel::insert_into(job_streams::table)
+                    .values(&self.streams)
+                    .execute(conn)
+                    .map_err(TransitionError::DB)?;
+            }
+
+            if next.is_terminal() {
+                ActivityEntry::new(experiment_id, None, ActivityKind::RunFinished)
+                    .job(job_id)
+                    .details(next.value())
+                    .record(conn)
+                    .map_err(TransitionError::DB)?;
+            }
+
+            return Ok(());
+        }
+
+        let current = jobs::table
+            .find(job_id)
+            .select(jobs::status)
+            .first::<JobStatus>(conn)
+            .map_err(TransitionError::DB)?;
+
+        warn!("illegal transition of job {} from {:?} to {:?} is rejected", job_id, current, next);
+
+        Err(TransitionError::Illegal { from: current, to: next })
+    }
+}
+
+#[derive(Debug)]
+pub enum TransitionError {
+    Illegal { from: JobStatus, to: JobStatus },
+    DB(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for TransitionError {
+    fn from(e: diesel::result::Error) -> Self {
+        TransitionError::DB(e)
+    }
+}
+
+impl ErrorMessaging for TransitionError {
+    fn value(&self) -> HttpError {
+        match self {
+            TransitionError::Illegal { .. } => ErrorMessage::InvalidOperationForStatus.value(),
+            TransitionError::DB(e) => e.value(),
+        }
+    }
+}
+
 impl Queryable<VarChar, Pg> for JobStatus {
     type Row = String;
 
@@ -41,3 +396,143 @@ impl Queryable<VarChar, Pg> for JobStatus {
         Self::build_from_string(row)
     }
 }
+
+/// Reason of a job's failure, if it is known.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub enum FailureReason {
+    // runner executing the job disconnected and did not come back in time
+    LostRunner,
+    // firmware could not be flashed to the device, experiment code is not run
+    FlashFailed,
+}
+
+impl Default for FailureReason {
+    fn default() -> Self {
+        FailureReason::LostRunner
+    }
+}
+
+impl Queryable<VarChar, Pg> for FailureReason {
+    type Row = String;
+
+    fn build(row: Self::Row) -> Self {
+        Self::build_from_string(row)
+    }
+}
+
+/// Whether ANSI escape sequences are kept in the job output served to users.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub enum AnsiMode {
+    Strip,
+    Preserve,
+}
+
+impl Default for AnsiMode {
+    fn default() -> Self {
+        AnsiMode::Preserve
+    }
+}
+
+impl Queryable<VarChar, Pg> for AnsiMode {
+    type Row = String;
+
+    fn build(row: Self::Row) -> Self {
+        Self::build_from_string(row)
+    }
+}
+
+/// Outcome of flashing the firmware of the job to the device, flash logs are kept in the `flash` stream
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub enum FlashStatus {
+    Flashed,
+    Failed,
+}
+
+impl Default for FlashStatus {
+    fn default() -> Self {
+        FlashStatus::Flashed
+    }
+}
+
+impl Queryable<VarChar, Pg> for FlashStatus {
+    type Row = String;
+
+    fn build(row: Self::Row) -> Self {
+        Self::build_from_string(row)
+    }
+}
+
+/// Outcome of the job according to its user, e.g. a successful run is marked as invalid due to environmental noise,
+/// or according to the success criteria of its experiment
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub enum Verdict {
+    Passed,
+    Failed,
+    Invalid,
+}
+
+impl Default for Verdict {
+    fn default() -> Self {
+        Verdict::Passed
+    }
+}
+
+impl Queryable<VarChar, Pg> for Verdict {
+    type Row = String;
+
+    fn build(row: Self::Row) -> Self {
+        Self::build_from_string(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUSES: [JobStatus; 6] = [
+        JobStatus::Pending,
+        JobStatus::Running,
+        JobStatus::Successful,
+        JobStatus::Failed,
+        JobStatus::Cancelled,
+        JobStatus::TimedOut,
+    ];
+
+    #[test]
+    fn allows_only_the_lifecycle_transitions() {
+        let allowed = [
+            (JobStatus::Pending, JobStatus::Running),
+            (JobStatus::Pending, JobStatus::Failed),
+            (JobStatus::Pending, JobStatus::Cancelled),
+            // requeue of a job whose runner lost its lease
+            (JobStatus::Running, JobStatus::Pending),
+            (JobStatus::Running, JobStatus::Successful),
+            (JobStatus::Running, JobStatus::Failed),
+            (JobStatus::Running, JobStatus::Cancelled),
+            (JobStatus::Running, JobStatus::TimedOut),
+        ];
+
+        for from in STATUSES {
+            for to in STATUSES {
+                assert_eq!(
+                    from.can_transition_to(to),
+                    allowed.contains(&(from, to)),
+                    "transition from {:?} to {:?}", from, to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn terminal_statuses_are_final() {
+        for status in STATUSES {
+            let terminal = !matches!(status, JobStatus::Pending | JobStatus::Running);
+
+            assert_eq!(status.is_terminal(), terminal, "{:?}", status);
+
+            if terminal {
+                assert!(STATUSES.iter().all(|next| !status.can_transition_to(*next)), "{:?} is left", status);
+            }
+        }
+    }
+}
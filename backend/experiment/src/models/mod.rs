@@ -1,3 +1,15 @@
+pub mod accounting;
+pub mod activity;
+pub mod artifact;
+pub mod batch;
+pub mod code;
+pub mod command;
+pub mod environment;
 pub mod experiment;
+pub mod export;
+pub mod firmware;
 pub mod job;
-pub mod runner;
\ No newline at end of file
+pub mod project;
+pub mod release;
+pub mod runner;
+pub mod stats;
\ No newline at end of file
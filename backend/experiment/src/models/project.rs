@@ -0,0 +1,162 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::Queryable;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use core::db::DieselEnum;
+use core::error::ErrorMessaging;
+use core::schema::{experiments, jobs, project_members, projects, users};
+use core::types::{ModelId, UserId};
+
+use crate::ErrorMessage;
+use crate::models::job::JobStatus;
+
+pub const MAX_PROJECT_NAME_LENGTH: usize = 255;
+
+/// Group of experiments shared by the members of a research group. Experiments are still modified only by their
+/// owners, members can view them along with their jobs.
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    #[serde(rename = "id")]
+    pub uuid: Uuid,
+    pub name: String,
+    pub owner_id: UserId,
+    // quotas given by the admins, unlimited if they are not given
+    pub max_experiments: Option<i32>,
+    // pending and running jobs of the project's experiments at once
+    pub max_active_jobs: Option<i32>,
+    // preferred runner labels of the experiments which do not have any of their own
+    pub default_labels: Vec<String>,
+    pub created_at: NaiveDateTime,
+}
+
+pub const PROJECT_COLUMNS: (projects::uuid, projects::name, projects::owner_id, projects::max_experiments, projects::max_active_jobs, projects::default_labels, projects::created_at) = (
+    projects::uuid,
+    projects::name,
+    projects::owner_id,
+    projects::max_experiments,
+    projects::max_active_jobs,
+    projects::default_labels,
+    projects::created_at,
+);
+
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectMember {
+    pub user_id: UserId,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub joined_at: NaiveDateTime,
+}
+
+/// Project along with its members and the usage of its quotas
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectDetail {
+    #[serde(flatten)]
+    pub project: Project,
+    pub members: Vec<ProjectMember>,
+    pub experiment_count: i64,
+    pub active_jobs: i64,
+}
+
+impl ProjectDetail {
+    pub fn load(project_id: ModelId, conn: &PgConnection) -> QueryResult<ProjectDetail> {
+        let project = projects::table
+            .find(project_id)
+            .select(PROJECT_COLUMNS)
+            .first::<Project>(conn)?;
+
+        let members = project_members::table
+            .inner_join(users::table)
+            .filter(project_members::project_id.eq(project_id))
+            .order(project_members::created_at.asc())
+            .select((users::id, users::first_name, users::last_name, users::email, project_members::created_at))
+            .load::<ProjectMember>(conn)?;
+
+        Ok(ProjectDetail {
+            project,
+            members,
+            experiment_count: count_experiments(project_id, conn)?,
+            active_jobs: count_active_jobs(project_id, conn)?,
+        })
+    }
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    !name.trim().is_empty() && name.len() <= MAX_PROJECT_NAME_LENGTH
+}
+
+/// Experiments of the project, the deleted ones waiting to be cleaned up are not counted
+pub fn count_experiments(project_id: ModelId, conn: &PgConnection) -> QueryResult<i64> {
+    experiments::table
+        .filter(experiments::project_id.eq(project_id))
+        .filter(experiments::deleted_at.is_null())
+        .count()
+        .get_result::<i64>(conn)
+}
+
+/// Pending and running jobs of the project's experiments
+pub fn count_active_jobs(project_id: ModelId, conn: &PgConnection) -> QueryResult<i64> {
+    jobs::table
+        .inner_join(experiments::table)
+        .filter(experiments::project_id.eq(project_id))
+        .filter(jobs::status.eq_any(vec![JobStatus::Pending.value(), JobStatus::Running.value()]))
+        .count()
+        .get_result::<i64>(conn)
+}
+
+/// Fails if running the given number of new jobs exceeds the active jobs quota of the project. Project is locked
+/// until the end of the transaction, so that the concurrent runs do not exceed the quota together.
+pub fn check_job_quota(project_id: Option<ModelId>, new_jobs: usize, conn: &PgConnection) -> Result<(), Box<dyn ErrorMessaging>> {
+    let project_id = match project_id {
+        Some(project_id) => project_id,
+        None => return Ok(())
+    };
+
+    let max_active_jobs = projects::table
+        .find(project_id)
+        .select(projects::max_active_jobs)
+        .for_update()
+        .first::<Option<i32>>(conn)?;
+
+    if let Some(max_active_jobs) = max_active_jobs {
+        if count_active_jobs(project_id, conn)? + new_jobs as i64 > max_active_jobs as i64 {
+            return Err(ErrorMessage::ProjectQuotaExceeded.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails if moving the experiment into the project exceeds its experiments quota, the project is locked as in
+/// `check_job_quota`. Experiment is not counted if it is already in the project.
+pub fn check_experiment_quota(project_id: ModelId, experiment_id: Uuid, conn: &PgConnection) -> Result<(), Box<dyn ErrorMessaging>> {
+    let max_experiments = projects::table
+        .find(project_id)
+        .select(projects::max_experiments)
+        .for_update()
+        .first::<Option<i32>>(conn)?;
+
+    let max_experiments = match max_experiments {
+        Some(max_experiments) => max_experiments as i64,
+        None => return Ok(())
+    };
+
+    let experiment_count = experiments::table
+        .filter(experiments::project_id.eq(project_id))
+        .filter(experiments::deleted_at.is_null())
+        .filter(experiments::uuid.ne(experiment_id))
+        .count()
+        .get_result::<i64>(conn)?;
+
+    if experiment_count >= max_experiments {
+        return Err(ErrorMessage::ProjectQuotaExceeded.into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use chrono::NaiveDateTime;
+use diesel::Queryable;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use core::types::ModelId;
+
+/// Build of the testbed client, runners which opted in to auto update are moved to the latest
+/// release of their platform
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientRelease {
+    pub id: ModelId,
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub url: String,
+    // base64 encoded Ed25519 signature of the release manifest, verified by the runner with its configured key
+    pub signature: String,
+    pub created_at: NaiveDateTime,
+    // hex encoded SHA-256 digest of the binary, releases signed before the manifests do not have it
+    pub sha256: Option<String>,
+}
+
@@ -1,18 +1,343 @@
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDateTime, Utc};
 use diesel::Queryable;
+use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
 
-use core::types::ModelId;
+use core::error::ErrorMessaging;
+use core::ErrorMessage;
+use core::models::paginate::Pagination;
+use core::models::token::{Audience, Claims, Scope};
+use core::schema::{runner_connect_tickets, runner_legacy_access_keys, runners};
+use core::types::{ModelId, RunnerId};
+use core::utils::{Hash, JWTErrorKind, random_key};
+
+use crate::models::job::SlimJob;
 
 #[derive(Queryable)]
 pub struct Runner {
-    pub id: ModelId,
-    pub access_key: String,
+    pub id: RunnerId,
+    // keyed hash of the access key, plain access key is only given to the runner
+    pub access_key_hash: String,
     pub created_at: NaiveDateTime,
+    pub name: String,
+    pub labels: Vec<String>,
+    pub last_seen_at: Option<NaiveDateTime>,
+    pub os: Option<String>,
+    pub arch: Option<String>,
+    pub client_version: Option<String>,
+    pub disabled: bool,
+    // key which is replaced by a rotation, accepted until the runner connects with the new key
+    pub previous_access_key_hash: Option<String>,
+    // runner may only connect from these networks, empty means any network
+    pub allowed_networks: Vec<String>,
+    // SHA-256 fingerprint of the client certificate the runner can authenticate with instead of a token
+    pub certificate_fingerprint: Option<String>,
+    // runner is sent the latest client release for its platform
+    pub auto_update: bool,
+    // hardware inventory reported by the runner when it connects
+    pub kernel: Option<String>,
+    pub cpu_model: Option<String>,
+    pub memory_bytes: Option<i64>,
+    pub peripherals: Vec<String>,
+    // labels derived from the inventory, kept apart from the labels given by the admins
+    pub capabilities: Vec<String>,
+    // runners are referred with their uuids outside
+    pub uuid: Uuid,
+}
+
+impl Runner {
+    /// Internal id of the runner referred with its uuid
+    pub fn id_of(uuid: Uuid, conn: &PgConnection) -> QueryResult<RunnerId> {
+        runners::table
+            .filter(runners::uuid.eq(uuid))
+            .select(runners::id)
+            .first::<RunnerId>(conn)
+    }
+
+    /// Hashes the access keys which were stored in plain before the keys are hashed, returns the number of the
+    /// hashed keys. Runners keep connecting with their keys, plain keys are removed afterwards.
+    pub fn hash_legacy_access_keys(hash: &Hash, conn: &PgConnection) -> QueryResult<usize> {
+        conn.transaction(|| {
+            let legacy_keys = runner_legacy_access_keys::table
+                .for_update()
+                .load::<(RunnerId, String)>(conn)?;
+
+            for (runner_id, access_key) in &legacy_keys {
+                diesel::update(runners::table.find(runner_id))
+                    .set(runners::access_key_hash.eq(hash.sign256(access_key.as_str())))
+                    .execute(conn)?;
+            }
+
+            diesel::delete(runner_legacy_access_keys::table)
+                .execute(conn)?;
+
+            Ok(legacy_keys.len())
+        })
+    }
 }
 
+pub const RUNNER_TOKEN_TIMEOUT: i64 = 60 * 60 * 24 * 365;
+const ACCESS_KEY_LENGTH: usize = 32;
+
+/// Token of the runner, access key in it identifies the runner while the subject must match the identified runner
 #[derive(Deserialize, Serialize)]
 pub struct RunnerToken {
+    #[serde(flatten)]
+    pub claims: Claims<RunnerId>,
     pub access_key: String,
-    pub exp: i64,
-}
\ No newline at end of file
+    // token is issued in the previous format without the claims, see `RunnerToken::decode`
+    #[serde(skip)]
+    pub legacy: bool,
+}
+
+/// Token of the runners issued before the tokens carry their claims, it only has the access key and the expire time
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LegacyRunnerToken {
+    access_key: String,
+    exp: i64,
+}
+
+impl From<LegacyRunnerToken> for RunnerToken {
+    fn from(token: LegacyRunnerToken) -> Self {
+        RunnerToken {
+            // subject is not known, runner is identified with the access key alone, see `RunnerToken::is_issued_for`
+            claims: Claims::new(Audience::Runner, RunnerId(0), vec![Scope::RunnerConnect], token.exp - RUNNER_TOKEN_TIMEOUT, token.exp),
+            access_key: token.access_key,
+            legacy: true,
+        }
+    }
+}
+
+impl RunnerToken {
+    pub fn new(runner_id: RunnerId, access_key: String, timeout: i64) -> Self {
+        let now = Utc::now().timestamp();
+
+        RunnerToken {
+            claims: Claims::new(Audience::Runner, runner_id, vec![Scope::RunnerConnect], now, now + timeout),
+            access_key,
+            legacy: false,
+        }
+    }
+
+    /// Verifies the token, it must be issued for the runners and allow them to connect.
+    ///
+    /// Tokens issued in the previous format, i.e. only with the access key and the expire time, are accepted until
+    /// they expire, so that the deployed runners keep connecting after the upgrade. Runners connected with them are
+    /// sent a token in the current format right after they join, see `Session`, and their previous access key is
+    /// released once they connect with it. Every token of the previous format is expired at most
+    /// `RUNNER_TOKEN_TIMEOUT` after the upgrade, the runners which do not connect until then must be given a new
+    /// token by the admins.
+    pub fn decode(token: &str, hash: &Hash) -> Result<Self, ErrorMessage> {
+        let token = hash.decode_for::<RunnerToken>(token, Audience::Runner)
+            .or_else(|e| match e.kind() {
+                JWTErrorKind::ExpiredSignature => Err(e),
+                _ => hash.decode::<LegacyRunnerToken>(token).map(RunnerToken::from)
+            })
+            .map_err(|e| match e.kind() {
+                JWTErrorKind::ExpiredSignature => ErrorMessage::ExpiredToken,
+                _ => ErrorMessage::InvalidToken
+            })?;
+
+        if !token.claims.has_scope(Scope::RunnerConnect) {
+            return Err(ErrorMessage::InvalidToken);
+        }
+
+        Ok(token)
+    }
+
+    /// Whether the token is issued for the given runner, the one its access key belongs to. Tokens of the previous
+    /// format do not name their runner, they are identified with the access key alone.
+    pub fn is_issued_for(&self, runner_id: RunnerId) -> bool {
+        self.legacy || self.claims.sub == runner_id
+    }
+
+    /// Generates a new access key for the runner and returns the encoded token with its expire time.
+    /// Given previous key hash stays valid until the runner connects with the new key.
+    pub fn issue(runner_id: RunnerId, previous_access_key_hash: Option<String>, hash: &Hash, conn: &PgConnection)
+                 -> Result<(String, i64), Box<dyn ErrorMessaging>> {
+        let (access_key, access_key_hash) = RunnerToken::generate_access_key(hash);
+
+        diesel::update(runners::table.find(runner_id))
+            .set((
+                runners::access_key_hash.eq(access_key_hash),
+                runners::previous_access_key_hash.eq(previous_access_key_hash)
+            ))
+            .get_result::<Runner>(conn)?;
+
+        RunnerToken::new(runner_id, access_key, RUNNER_TOKEN_TIMEOUT).encode(hash)
+    }
+
+    /// Generates a new access key and returns it together with its hash
+    pub fn generate_access_key(hash: &Hash) -> (String, String) {
+        let access_key = random_key(ACCESS_KEY_LENGTH);
+        let access_key_hash = hash.sign256(access_key.as_str());
+
+        (access_key, access_key_hash)
+    }
+
+    /// Returns the encoded token with its expire time
+    pub fn encode(&self, hash: &Hash) -> Result<(String, i64), Box<dyn ErrorMessaging>> {
+        Ok((hash.encode(self)?, self.claims.exp))
+    }
+}
+
+// tickets are redeemed right after they are minted, a runner failing to connect in time mints another one
+pub const CONNECT_TICKET_TIMEOUT: i64 = 30;
+const CONNECT_TICKET_LENGTH: usize = 32;
+
+/// One-time ticket the runner joins the server with instead of its token, so that the token is not put into the
+/// url of the websocket where the proxies may log it
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectTicket {
+    pub ticket: String,
+    // unix timestamp of the expire time of the ticket
+    pub expires_at: i64,
+}
+
+impl ConnectTicket {
+    /// Mints a ticket for the runner identified by the token with the given access key hash and expire time. Only
+    /// the hash of the ticket is stored, the expired tickets are removed along the way.
+    pub fn issue(runner_id: RunnerId, access_key_hash: String, token_expires_at: i64, hash: &Hash, conn: &PgConnection)
+                 -> QueryResult<ConnectTicket> {
+        let now = Utc::now();
+        let ticket = random_key(CONNECT_TICKET_LENGTH);
+        let expires_at = now + Duration::seconds(CONNECT_TICKET_TIMEOUT);
+
+        diesel::delete(runner_connect_tickets::table.filter(runner_connect_tickets::expires_at.le(now.naive_utc())))
+            .execute(conn)?;
+
+        diesel::insert_into(runner_connect_tickets::table)
+            .values((
+                runner_connect_tickets::ticket_hash.eq(hash.sign256(ticket.as_str())),
+                runner_connect_tickets::runner_id.eq(runner_id),
+                runner_connect_tickets::access_key_hash.eq(access_key_hash),
+                runner_connect_tickets::token_expires_at.eq(token_expires_at),
+                runner_connect_tickets::expires_at.eq(expires_at.naive_utc()),
+            ))
+            .execute(conn)?;
+
+        Ok(ConnectTicket { ticket, expires_at: expires_at.timestamp() })
+    }
+
+    /// Consumes the ticket and returns the runner along with the access key hash and the expire time of the token
+    /// the ticket is minted with. None is returned if the ticket is already used or expired, or the access key is
+    /// rotated since then.
+    pub fn redeem(ticket: &str, hash: &Hash, conn: &PgConnection) -> QueryResult<Option<(Runner, String, i64)>> {
+        let redeemed = diesel::delete(runner_connect_tickets::table
+            .filter(runner_connect_tickets::ticket_hash.eq(hash.sign256(ticket)))
+            .filter(runner_connect_tickets::expires_at.gt(Utc::now().naive_utc()))
+        )
+            .returning((runner_connect_tickets::runner_id, runner_connect_tickets::access_key_hash, runner_connect_tickets::token_expires_at))
+            .get_result::<(RunnerId, String, i64)>(conn)
+            .optional()?;
+
+        let (runner_id, access_key_hash, token_expires_at) = match redeemed {
+            Some(redeemed) => redeemed,
+            None => return Ok(None)
+        };
+
+        let runner = runners::table
+            .find(runner_id)
+            .filter(runners::access_key_hash.eq(&access_key_hash).or(runners::previous_access_key_hash.eq(&access_key_hash)))
+            .first::<Runner>(conn)
+            .optional()?;
+
+        Ok(runner.map(|runner| (runner, access_key_hash, token_expires_at)))
+    }
+}
+
+/// Labels describing what the runner is capable of, e.g. `os:linux`, `arch:aarch64` or `peripheral:nucleo-f401re`
+pub fn capability_labels(os: &str, arch: &str, peripherals: &[String]) -> Vec<String> {
+    let mut labels = vec![format!("os:{}", os), format!("arch:{}", arch)];
+
+    labels.extend(peripherals.iter().map(|peripheral| format!("peripheral:{}", peripheral)));
+
+    labels
+}
+
+#[derive(Queryable)]
+pub struct SlimRunner {
+    pub id: RunnerId,
+    pub name: String,
+    pub labels: Vec<String>,
+    pub last_seen_at: Option<NaiveDateTime>,
+    pub disabled: bool,
+    pub created_at: NaiveDateTime,
+    pub uuid: Uuid,
+}
+
+pub const SLIM_RUNNER_COLUMNS: (runners::id, runners::name, runners::labels, runners::last_seen_at, runners::disabled, runners::created_at, runners::uuid) = (
+    runners::id,
+    runners::name,
+    runners::labels,
+    runners::last_seen_at,
+    runners::disabled,
+    runners::created_at,
+    runners::uuid,
+);
+
+/// Runner with its current state, as it is shown to the users.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerStatus {
+    pub id: Uuid,
+    pub name: String,
+    pub labels: Vec<String>,
+    pub online: bool,
+    pub queue_length: i64,
+    // only given if the running job belongs to the user
+    pub running_job_id: Option<Uuid>,
+    pub disabled: bool,
+    pub last_seen_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerDetail {
+    pub id: Uuid,
+    pub name: String,
+    pub labels: Vec<String>,
+    pub online: bool,
+    pub os: Option<String>,
+    pub arch: Option<String>,
+    pub client_version: Option<String>,
+    pub kernel: Option<String>,
+    pub cpu_model: Option<String>,
+    pub memory_bytes: Option<i64>,
+    pub peripherals: Vec<String>,
+    pub capabilities: Vec<String>,
+    pub disabled: bool,
+    pub auto_update: bool,
+    pub last_seen_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub stats: RunnerStats,
+    // recent jobs of the user executed on this runner
+    pub jobs: Pagination<SlimJob>,
+}
+
+/// Batch of the logs shipped by the client of the runner
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerClientLog {
+    pub id: ModelId,
+    #[serde(skip_serializing)]
+    pub runner_id: RunnerId,
+    pub lines: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerStats {
+    pub total_jobs: i64,
+    pub successful_jobs: i64,
+    pub failed_jobs: i64,
+    pub busy_seconds_last_day: f64,
+    // ratio of the busy time to the last day
+    pub utilization_last_day: f64,
+}
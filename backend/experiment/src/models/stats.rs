@@ -0,0 +1,262 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use diesel::dsl::{now, sql};
+use diesel::pg::expression::extensions::IntervalDsl;
+use diesel::Queryable;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Date, Double, Nullable};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use core::schema::{all_jobs, experiment_job_stats, experiments, runner_job_stats, runners, user_job_stats, users};
+use core::types::{RunnerId, UserId};
+
+use crate::models::job::JobStatus;
+
+/// Rolling statistics of the recently finished jobs of a runner or an experiment, computed
+/// periodically by the `StatsAggregator`. Durations are in seconds.
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStats {
+    pub job_count: i64,
+    pub p50_duration: Option<f64>,
+    pub p95_duration: Option<f64>,
+    // ratio of the failed and timed out jobs to the finished ones
+    pub failure_rate: f64,
+    pub updated_at: NaiveDateTime,
+}
+
+pub const RUNNER_JOB_STATS_COLUMNS: (runner_job_stats::job_count, runner_job_stats::p50_duration, runner_job_stats::p95_duration, runner_job_stats::failure_rate, runner_job_stats::updated_at) = (
+    runner_job_stats::job_count,
+    runner_job_stats::p50_duration,
+    runner_job_stats::p95_duration,
+    runner_job_stats::failure_rate,
+    runner_job_stats::updated_at,
+);
+
+pub const EXPERIMENT_JOB_STATS_COLUMNS: (experiment_job_stats::job_count, experiment_job_stats::p50_duration, experiment_job_stats::p95_duration, experiment_job_stats::failure_rate, experiment_job_stats::updated_at) = (
+    experiment_job_stats::job_count,
+    experiment_job_stats::p50_duration,
+    experiment_job_stats::p95_duration,
+    experiment_job_stats::failure_rate,
+    experiment_job_stats::updated_at,
+);
+
+/// Overall statistics of the testbed for the operations dashboard, daily counts cover the last `days` days
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminStats {
+    pub days: i64,
+    pub total_users: i64,
+    pub total_experiments: i64,
+    pub total_jobs: i64,
+    pub total_runners: i64,
+    pub jobs_per_day: Vec<DailyJobCount>,
+    // users having created a job on the day
+    pub active_users_per_day: Vec<DailyCount>,
+    pub runner_utilization: Vec<RunnerUtilization>,
+    // seconds the jobs started in the window have waited in the queue, None if none of them has started
+    pub average_queue_wait: Option<f64>,
+    pub queue_wait_per_day: Vec<DailyAverage>,
+}
+
+#[derive(Queryable, Serialize, ToSchema)]
+pub struct DailyJobCount {
+    pub day: NaiveDate,
+    pub status: JobStatus,
+    pub count: i64,
+}
+
+#[derive(Queryable, Serialize, ToSchema)]
+pub struct DailyCount {
+    pub day: NaiveDate,
+    pub count: i64,
+}
+
+#[derive(Queryable, Serialize, ToSchema)]
+pub struct DailyAverage {
+    pub day: NaiveDate,
+    pub average: f64,
+}
+
+/// Share of the window the runner has spent running jobs, idle runners are listed with zero
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerUtilization {
+    pub runner_id: Uuid,
+    pub name: String,
+    pub busy_seconds: f64,
+    pub utilization: f64,
+}
+
+const SECONDS_IN_DAY: f64 = 60.0 * 60.0 * 24.0;
+
+/// Sum of the seconds the runners are busy with the jobs within the last days, the part of the jobs before the window
+/// is left out
+pub fn busy_seconds_since(days: i64) -> String {
+    format!("SUM(date_part('epoch', finished_at - GREATEST(started_at, CURRENT_TIMESTAMP - INTERVAL '1 day' * {})))", days)
+}
+
+impl AdminStats {
+    /// Each of the statistics is aggregated by the database with a single query, archived jobs are counted as well
+    pub fn load(days: i64, conn: &PgConnection) -> QueryResult<AdminStats> {
+        let since = now - days.days();
+
+        let total_users = users::table.count().get_result::<i64>(conn)?;
+        let total_experiments = experiments::table.count().get_result::<i64>(conn)?;
+        let total_jobs = all_jobs::table.count().get_result::<i64>(conn)?;
+        let total_runners = runners::table.count().get_result::<i64>(conn)?;
+
+        let jobs_per_day = all_jobs::table
+            .filter(all_jobs::created_at.ge(since))
+            .group_by((sql::<Date>("created_at::date"), all_jobs::status))
+            .select((sql::<Date>("created_at::date"), all_jobs::status, sql::<BigInt>("COUNT(*)")))
+            .order(sql::<Date>("created_at::date").asc())
+            .load::<DailyJobCount>(conn)?;
+
+        let active_users_per_day = all_jobs::table
+            .inner_join(experiments::table)
+            .filter(all_jobs::created_at.ge(since))
+            .group_by(sql::<Date>("all_jobs.created_at::date"))
+            .select((sql::<Date>("all_jobs.created_at::date"), sql::<BigInt>("COUNT(DISTINCT experiments.user_id)")))
+            .order(sql::<Date>("all_jobs.created_at::date").asc())
+            .load::<DailyCount>(conn)?;
+
+        // jobs started before the window are counted from its start
+        let busy_seconds = all_jobs::table
+            .filter(all_jobs::runner_id.is_not_null())
+            .filter(all_jobs::started_at.is_not_null())
+            .filter(all_jobs::finished_at.ge(since.nullable()))
+            .group_by(all_jobs::runner_id)
+            .select((all_jobs::runner_id, sql::<Double>(busy_seconds_since(days).as_str())))
+            .load::<(Option<RunnerId>, f64)>(conn)?;
+
+        let runner_utilization = runners::table
+            .order(runners::id.asc())
+            .select((runners::id, runners::uuid, runners::name))
+            .load::<(RunnerId, Uuid, String)>(conn)?
+            .into_iter()
+            .map(|(id, runner_id, name)| {
+                let busy_seconds = busy_seconds.iter()
+                    .find(|(busy_runner_id, _)| *busy_runner_id == Some(id))
+                    .map_or(0.0, |(_, seconds)| *seconds);
+
+                RunnerUtilization {
+                    runner_id,
+                    name,
+                    busy_seconds,
+                    utilization: (busy_seconds / (days as f64 * SECONDS_IN_DAY)).min(1.0),
+                }
+            })
+            .collect();
+
+        let queue_wait = "date_part('epoch', started_at - created_at)";
+
+        let average_queue_wait = all_jobs::table
+            .filter(all_jobs::started_at.ge(since.nullable()))
+            .select(sql::<Nullable<Double>>(format!("AVG({})", queue_wait).as_str()))
+            .first::<Option<f64>>(conn)?;
+
+        let queue_wait_per_day = all_jobs::table
+            .filter(all_jobs::started_at.ge(since.nullable()))
+            .group_by(sql::<Date>("started_at::date"))
+            .select((sql::<Date>("started_at::date"), sql::<Double>(format!("AVG({})", queue_wait).as_str())))
+            .order(sql::<Date>("started_at::date").asc())
+            .load::<DailyAverage>(conn)?;
+
+        Ok(AdminStats {
+            days,
+            total_users,
+            total_experiments,
+            total_jobs,
+            total_runners,
+            jobs_per_day,
+            active_users_per_day,
+            runner_utilization,
+            average_queue_wait,
+            queue_wait_per_day,
+        })
+    }
+}
+
+/// Usage of the testbed by a user, computed from the daily statistics rolled up by the `StatsAggregator`, so the
+/// jobs finished in the last few minutes may not be counted yet
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStats {
+    pub job_count: i64,
+    pub successful_jobs: i64,
+    // failed and timed out jobs
+    pub failed_jobs: i64,
+    // ratio of the successful jobs to the ones which have either succeeded or failed, None if there is not any
+    pub success_rate: Option<f64>,
+    pub runtime_seconds: f64,
+    // energy of the jobs which reported it, in the unit of `accounting::ENERGY_METRIC`
+    pub energy_mj: f64,
+    pub top_runners: Vec<RunnerUsage>,
+}
+
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerUsage {
+    pub runner_id: Uuid,
+    pub name: String,
+    pub job_count: i64,
+    pub runtime_seconds: f64,
+}
+
+const TOP_RUNNERS: i64 = 5;
+
+impl UserStats {
+    /// Statistics of the jobs finished in the last `days` days including today, of all the jobs if it is not given
+    pub fn load(user_id: UserId, days: Option<i64>, conn: &PgConnection) -> QueryResult<UserStats> {
+        let since = days.map(|days| Utc::now().naive_utc().date() - Duration::days(days - 1));
+
+        let filter = |since: Option<NaiveDate>| {
+            let mut query = user_job_stats::table
+                .filter(user_job_stats::user_id.eq(user_id))
+                .into_boxed();
+
+            if let Some(since) = since {
+                query = query.filter(user_job_stats::day.ge(since));
+            }
+
+            query
+        };
+
+        let (job_count, successful_jobs, failed_jobs, runtime_seconds, energy_mj) = filter(since)
+            .select((
+                sql::<BigInt>("COALESCE(SUM(job_count), 0)::bigint"),
+                sql::<BigInt>("COALESCE(SUM(successful_jobs), 0)::bigint"),
+                sql::<BigInt>("COALESCE(SUM(failed_jobs), 0)::bigint"),
+                sql::<Double>("COALESCE(SUM(runtime_seconds), 0)"),
+                sql::<Double>("COALESCE(SUM(energy_mj), 0)"),
+            ))
+            .first::<(i64, i64, i64, f64, f64)>(conn)?;
+
+        let top_runners = filter(since)
+            .inner_join(runners::table)
+            .group_by(runners::id)
+            .select((
+                runners::uuid,
+                runners::name,
+                sql::<BigInt>("SUM(job_count)::bigint"),
+                sql::<Double>("SUM(runtime_seconds)"),
+            ))
+            .order(sql::<BigInt>("SUM(job_count)").desc())
+            .limit(TOP_RUNNERS)
+            .load::<RunnerUsage>(conn)?;
+
+        Ok(UserStats {
+            job_count,
+            successful_jobs,
+            failed_jobs,
+            success_rate: Some(successful_jobs + failed_jobs)
+                .filter(|finished| *finished > 0)
+                .map(|finished| successful_jobs as f64 / finished as f64),
+            runtime_seconds,
+            energy_mj,
+            top_runners,
+        })
+    }
+}
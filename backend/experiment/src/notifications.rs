@@ -0,0 +1,48 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::job::{FailureReason, JobStatus};
+
+/// Notifications pushed to the users over their websocket connection.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "kind", content = "data")]
+pub enum Notification {
+    JobStatus(JobStatusNotification),
+    QueueInfo(QueueInfoNotification),
+    Security(SecurityNotification),
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatusNotification {
+    pub job_id: Uuid,
+    pub experiment_id: Uuid,
+    pub status: JobStatus,
+    pub failure_reason: Option<FailureReason>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueInfoNotification {
+    pub job_id: Uuid,
+    pub experiment_id: Uuid,
+    pub position: usize,
+    pub eta_seconds: Option<f64>,
+}
+
+/// Security relevant entries of the audit log the users are notified about, they are mailed as well
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub enum SecurityEvent {
+    ApiKeyCreated,
+    NewDeviceLogin,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityNotification {
+    pub event: SecurityEvent,
+    // name of the api key, or the ip and the user agent of the login
+    pub details: Option<String>,
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,102 @@
+use std::net::IpAddr;
+
+use actix_web::HttpRequest;
+use ipnet::IpNet;
+
+use crate::certificate::{client_certificate, ClientCertificate, grpc_client_certificate};
+use crate::connection::admission::{ConnectionSlot, ConnectionSlots};
+
+/// Connection policy applied to the runners joining the server.
+#[derive(Clone)]
+pub struct RunnerPolicy {
+    // runners may only connect from these networks, empty means any network
+    allowed_networks: Vec<IpNet>,
+    max_connections_per_token: Option<usize>,
+    // open connections of the credentials, shared by the clones of the policy
+    connections: ConnectionSlots,
+    // use the address given by the reverse proxy instead of the peer address
+    trust_forwarded_for: bool,
+    // use the client certificate given by the reverse proxy
+    trust_client_cert_header: bool,
+}
+
+impl RunnerPolicy {
+    /// `allowed_networks` is a comma separated list of networks in CIDR notation or plain addresses.
+    pub fn new(allowed_networks: &str, max_connections_per_token: Option<usize>, trust_forwarded_for: bool, trust_client_cert_header: bool)
+               -> Result<Self, String> {
+        let allowed_networks = allowed_networks.split(',')
+            .map(|network| network.trim())
+            .filter(|network| !network.is_empty())
+            .map(|network| parse_network(network).ok_or_else(|| format!("invalid network {}", network)))
+            .collect::<Result<Vec<IpNet>, String>>()?;
+
+        Ok(RunnerPolicy {
+            allowed_networks,
+            max_connections_per_token,
+            connections: ConnectionSlots::default(),
+            trust_forwarded_for,
+            trust_client_cert_header,
+        })
+    }
+
+    /// Reserves a connection for the credential, unless it has as many open connections as allowed
+    pub fn reserve_connection(&self, credential: &str) -> Option<ConnectionSlot> {
+        self.connections.reserve(credential, self.max_connections_per_token)
+    }
+
+    pub fn client_certificate(&self, req: &HttpRequest) -> Option<ClientCertificate> {
+        client_certificate(req, self.trust_client_cert_header)
+    }
+
+    pub fn remote_addr(&self, req: &HttpRequest) -> Option<IpAddr> {
+        if self.trust_forwarded_for {
+            let connection_info = req.connection_info();
+
+            return connection_info.realip_remote_addr()
+                .and_then(|addr| addr.parse::<IpAddr>().ok()
+                    .or_else(|| addr.parse::<std::net::SocketAddr>().ok().map(|s| s.ip()))
+                );
+        }
+
+        req.peer_addr().map(|addr| addr.ip())
+    }
+
+    pub fn grpc_client_certificate<T>(&self, request: &tonic::Request<T>) -> Option<ClientCertificate> {
+        grpc_client_certificate(request, self.trust_client_cert_header)
+    }
+
+    /// Same as [RunnerPolicy::remote_addr], the first address of X-Forwarded-For is the client if it is trusted.
+    pub fn grpc_remote_addr<T>(&self, request: &tonic::Request<T>) -> Option<IpAddr> {
+        let forwarded_for = request.metadata().get("x-forwarded-for")
+            .filter(|_| self.trust_forwarded_for)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .map(|addr| addr.trim());
+
+        if let Some(addr) = forwarded_for {
+            return addr.parse::<IpAddr>().ok()
+                .or_else(|| addr.parse::<std::net::SocketAddr>().ok().map(|s| s.ip()));
+        }
+
+        request.remote_addr().map(|addr| addr.ip())
+    }
+
+    /// Address has to be in the global allowlist and in the runner's own allowlist, if they are given.
+    pub fn is_allowed(&self, addr: IpAddr, runner_networks: &[String]) -> bool {
+        let globally_allowed = self.allowed_networks.is_empty() ||
+            self.allowed_networks.iter().any(|network| network.contains(&addr));
+
+        let runner_allowed = runner_networks.is_empty() ||
+            runner_networks.iter()
+                .filter_map(|network| parse_network(network))
+                .any(|network| network.contains(&addr));
+
+        globally_allowed && runner_allowed
+    }
+}
+
+pub fn parse_network(network: &str) -> Option<IpNet> {
+    network.parse::<IpNet>()
+        .ok()
+        .or_else(|| network.parse::<IpAddr>().ok().map(IpNet::from))
+}
@@ -0,0 +1,146 @@
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::Double;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use core::db::DieselEnum;
+use core::schema::{experiments, jobs, runners};
+use core::types::{RunnerId, UserId};
+
+use crate::models::job::JobStatus;
+
+// Number of the latest finished jobs of a runner used for estimating the job duration
+const DURATION_SAMPLE_SIZE: i64 = 20;
+
+#[derive(Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueInfo {
+    pub job_id: Uuid,
+    pub runner_id: Option<Uuid>,
+    /// Position in the runner queue, starting from 1 for the next job to run
+    pub position: usize,
+    /// Estimated seconds until the job starts, missing if the runner has not finished any job yet
+    pub eta_seconds: Option<f64>,
+}
+
+/// Queued job along with the experiment and user owning it.
+pub struct QueuedJob {
+    pub user_id: UserId,
+    pub experiment_id: Uuid,
+    pub priority: i32,
+    pub info: QueueInfo,
+}
+
+/// Queued job of a runner, as it is listed to the admins and to the owners of the jobs
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerQueueEntry {
+    pub job_id: Uuid,
+    pub experiment_id: Uuid,
+    pub user_id: UserId,
+    pub priority: i32,
+    pub position: usize,
+    pub eta_seconds: Option<f64>,
+}
+
+impl From<QueuedJob> for RunnerQueueEntry {
+    fn from(queued: QueuedJob) -> Self {
+        RunnerQueueEntry {
+            job_id: queued.info.job_id,
+            experiment_id: queued.experiment_id,
+            user_id: queued.user_id,
+            priority: queued.priority,
+            position: queued.info.position,
+            eta_seconds: queued.info.eta_seconds,
+        }
+    }
+}
+
+/// Computes the queue position and ETA of the pending jobs, either of a single runner or all of them.
+/// Jobs are ordered by their priorities, then by their creation times within the queue of the runner they are
+/// requested for, which is the order the `ExperimentServer` dispatches them in.
+pub fn queued_jobs(runner_id: Option<RunnerId>, conn: &PgConnection) -> QueryResult<Vec<QueuedJob>> {
+    let mut query = jobs::table
+        .inner_join(experiments::table)
+        .left_join(runners::table)
+        .filter(jobs::status.eq(JobStatus::Pending.value()))
+        .order((jobs::runner_id.asc(), jobs::queue_priority.desc(), jobs::created_at.asc(), jobs::id.asc()))
+        .select((jobs::uuid, jobs::runner_id, runners::uuid.nullable(), experiments::uuid, experiments::user_id, jobs::queue_priority))
+        .into_boxed();
+
+    if let Some(runner_id) = runner_id {
+        query = query.filter(jobs::runner_id.eq(runner_id));
+    }
+
+    let pending = query.load::<(Uuid, Option<RunnerId>, Option<Uuid>, Uuid, UserId, i32)>(conn)?;
+
+    let mut queued = Vec::with_capacity(pending.len());
+    let mut current: Option<(Option<RunnerId>, Option<f64>, f64)> = None;
+    let mut position = 0;
+
+    for (job_id, runner_id, runner_uuid, experiment_id, user_id, priority) in pending {
+        let (average, remaining) = match current {
+            Some((id, average, remaining)) if id == runner_id => (average, remaining),
+            _ => {
+                let (average, remaining) = match runner_id {
+                    Some(runner_id) => runner_estimates(runner_id, conn)?,
+                    None => (None, 0.0)
+                };
+
+                current = Some((runner_id, average, remaining));
+                position = 0;
+
+                (average, remaining)
+            }
+        };
+
+        position += 1;
+
+        queued.push(QueuedJob {
+            user_id,
+            experiment_id,
+            priority,
+            info: QueueInfo {
+                job_id,
+                runner_id: runner_uuid,
+                position,
+                eta_seconds: average.map(|average| remaining + (position - 1) as f64 * average),
+            },
+        });
+    }
+
+    Ok(queued)
+}
+
+/// Returns the average duration of the latest finished jobs of the runner and the estimated remaining
+/// time of the job it is currently running, in seconds
+fn runner_estimates(runner_id: RunnerId, conn: &PgConnection) -> QueryResult<(Option<f64>, f64)> {
+    let durations = jobs::table
+        .filter(jobs::runner_id.eq(runner_id))
+        .filter(jobs::started_at.is_not_null())
+        .filter(jobs::finished_at.is_not_null())
+        .order(jobs::finished_at.desc())
+        .limit(DURATION_SAMPLE_SIZE)
+        .select(sql::<Double>("date_part('epoch', finished_at - started_at)"))
+        .load::<f64>(conn)?;
+
+    if durations.is_empty() {
+        return Ok((None, 0.0));
+    }
+
+    let average = durations.iter().sum::<f64>() / durations.len() as f64;
+
+    let elapsed = jobs::table
+        .filter(jobs::runner_id.eq(runner_id))
+        .filter(jobs::status.eq(JobStatus::Running.value()))
+        .filter(jobs::started_at.is_not_null())
+        .select(sql::<Double>("date_part('epoch', CURRENT_TIMESTAMP - started_at)"))
+        .first::<f64>(conn)
+        .optional()?;
+
+    let remaining = elapsed.map_or(0.0, |elapsed| (average - elapsed).max(0.0));
+
+    Ok((Some(average), remaining))
+}
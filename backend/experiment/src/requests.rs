@@ -1,14 +1,433 @@
+use chrono::NaiveDateTime;
 use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
 
 use core::sanitized::Sanitize;
+use core::types::ModelId;
 use derive::Sanitize;
 
-#[derive(Deserialize, Sanitize)]
+use crate::models::batch::{AbortPolicy, RepeatPolicy};
+use crate::models::command::CommandKind;
+use crate::models::experiment::RunnerStrategy;
+use crate::models::job::{AnsiMode, JobStatus, Verdict};
+
+#[derive(Deserialize, Sanitize, ToSchema)]
 pub struct ExperimentNameRequest {
     pub name: String,
 }
 
-#[derive(Deserialize, Sanitize)]
+#[derive(Deserialize, Sanitize, ToSchema)]
 pub struct ExperimentCodeRequest {
     pub code: String,
-}
\ No newline at end of file
+}
+
+// not sanitized since escaping would break the Markdown, HTML is sanitized once the description is rendered instead
+#[derive(Deserialize, ToSchema)]
+pub struct ExperimentDescriptionRequest {
+    pub description: String,
+}
+
+// named so that the experiment and the runner ids can not be swapped
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct RunExperimentPath {
+    pub experiment_id: Uuid,
+    pub runner_id: Uuid,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RunExperimentRequest {
+    pub ansi: Option<AnsiMode>,
+    // comma separated names of the runner's optional hooks, e.g. power_cycle
+    pub hooks: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RunBatchRequest {
+    pub runner_ids: Vec<Uuid>,
+    pub ansi: Option<AnsiMode>,
+    // comma separated names of the runners' optional hooks, each runner should support them
+    pub hooks: Option<String>,
+    pub abort: Option<AbortPolicy>,
+    // runs are made once on each runner by default
+    pub repeat: Option<RepeatPolicy>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobWaitRequest {
+    // seconds to wait for the job to finish, 60 by default and 300 at most
+    pub timeout: Option<u64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ValidateExperimentRequest {
+    pub runner_id: Option<Uuid>,
+    // comma separated names of the runner's optional hooks, like for the runs
+    pub hooks: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FirmwareRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobOutputRequest {
+    pub ansi: Option<AnsiMode>,
+}
+
+/// Expire time and signature of a signed download url, see `download::Download`
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DownloadRequest {
+    pub expires: i64,
+    pub signature: String,
+    // only applies to the output of the job
+    pub ansi: Option<AnsiMode>,
+}
+
+/// Experiments are either given by their ids or selected by the filters of `ExperimentsRequest`. Selecting by the
+/// filters has to be confirmed with `all`, since it deletes every experiment matching them.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkDeleteExperimentsRequest {
+    pub ids: Option<Vec<Uuid>>,
+    pub starred: Option<bool>,
+    pub include_archived: Option<bool>,
+    pub all: Option<bool>,
+}
+
+/// Jobs are either given by their ids or selected by the filters.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkCancelJobsRequest {
+    pub ids: Option<Vec<Uuid>>,
+    pub experiment_id: Option<Uuid>,
+    pub status: Option<JobStatus>,
+}
+
+/// Null annotation and verdict remove the ones given before
+#[derive(Deserialize, ToSchema)]
+pub struct JobAnnotationRequest {
+    pub annotation: Option<String>,
+    pub verdict: Option<Verdict>,
+}
+
+// derive can not skip the fields which are not strings
+impl Sanitize for JobAnnotationRequest {
+    fn sanitize(self) -> Self {
+        JobAnnotationRequest {
+            annotation: self.annotation.sanitize(),
+            ..self
+        }
+    }
+}
+
+// not sanitized since escaping would break the comparisons, criteria are validated before they are stored instead
+#[derive(Deserialize, ToSchema)]
+pub struct ExperimentSuccessCriteriaRequest {
+    // null removes the criteria, derived verdicts of the finished jobs are kept
+    pub criteria: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExperimentWarmupRunsRequest {
+    pub runs: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExperimentThermalGuardRequest {
+    // in degrees Celsius, guard is removed if it is not given
+    pub max_temperature: Option<f64>,
+    // in seconds
+    pub timeout: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExperimentNetworkEmulationRequest {
+    pub delay_ms: Option<i32>,
+    // only allowed along with the delay
+    pub jitter_ms: Option<i32>,
+    pub loss_percent: Option<f64>,
+    pub rate_kbit: Option<i32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExperimentCostCenterRequest {
+    // jobs are charged to the unassigned cost center if it is not given
+    pub cost_center: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExperimentSdrRequest {
+    // frequency, gain and sample rate are given together, settings are cleared if none of them is given
+    pub frequency_hz: Option<i64>,
+    pub gain_db: Option<f64>,
+    pub sample_rate: Option<i32>,
+    // iq or spectrum, recorded while the jobs run
+    pub recording: Option<String>,
+    // only allowed along with the recording
+    pub recording_ms: Option<i32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExperimentProjectRequest {
+    // experiment is removed from its project if it is not given
+    pub project_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, Sanitize, ToSchema)]
+pub struct ProjectNameRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ProjectMemberRequest {
+    // members are added with the email of their accounts
+    pub email: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ProjectQuotaRequest {
+    // quotas which are not given are unlimited
+    pub max_experiments: Option<i32>,
+    pub max_active_jobs: Option<i32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ProjectDefaultLabelsRequest {
+    pub labels: Vec<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExperimentPerformanceRequest {
+    // cpufreq governor, e.g. performance
+    pub governor: Option<String>,
+    pub disable_turbo: bool,
+    // in kHz
+    pub frequency_khz: Option<i32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct PurgeJobsRequest {
+    pub statuses: Option<Vec<JobStatus>>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExperimentRunnerStrategyRequest {
+    pub strategy: RunnerStrategy,
+    // preferred labels of the LabelAffinity strategy
+    pub labels: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExperimentRetentionRequest {
+    // the retention policy of the app is applied if they are not given
+    pub days: Option<i32>,
+    pub jobs: Option<i32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct JobProtectedRequest {
+    pub protected: bool,
+}
+
+#[derive(Deserialize, Sanitize, ToSchema)]
+pub struct RunnerNameRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RunnerQueueReorderRequest {
+    // pending jobs of the runner, the first one is run first
+    pub job_ids: Vec<Uuid>,
+}
+
+#[derive(Deserialize, Sanitize, ToSchema)]
+pub struct RunnerLabelsRequest {
+    pub labels: Vec<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RunnerDisabledRequest {
+    pub disabled: bool,
+}
+
+#[derive(Deserialize, Sanitize, ToSchema)]
+pub struct ClaimCodeRequest {
+    pub name: String,
+    pub labels: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Sanitize, ToSchema)]
+pub struct ProvisionRunnerRequest {
+    pub name: String,
+    pub labels: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ClaimRunnerRequest {
+    pub code: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RunnerAllowedNetworksRequest {
+    pub networks: Vec<String>,
+}
+
+/// Runners connect with a ticket, or with a token or a client certificate when the ticket is not given
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JoinServerRequest {
+    /// Deprecated, token should be given in the `Authorization` header
+    pub token: Option<String>,
+    /// One-time ticket minted by `runner/ticket`
+    pub ticket: Option<String>,
+}
+
+/// Pins the certificate of the runner, null removes the pinned certificate.
+#[derive(Deserialize, ToSchema)]
+pub struct RunnerCertificateRequest {
+    pub fingerprint: Option<String>,
+}
+
+/// Signature is given over the manifest of the release, see `ClientUpdate::manifest`
+#[derive(Deserialize, ToSchema)]
+pub struct ClientReleaseRequest {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub url: String,
+    pub sha256: String,
+    pub signature: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RunnerAutoUpdateRequest {
+    pub auto_update: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RunnerCommandRequest {
+    pub kind: CommandKind,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RunnerLogLevelRequest {
+    pub level: Option<String>,
+    pub ship_seconds: Option<u64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase")]
+pub struct StuckJobsRequest {
+    // jobs are stuck if they are pending or running for longer than this many seconds, an hour by default
+    pub older_than: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase")]
+pub struct RequeueJobRequest {
+    // job is queued for its previous runner if it is not given
+    pub runner_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditLogsRequest {
+    // entries are returned in the order they are recorded, starting after the entry with this id
+    pub after: Option<ModelId>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+    pub action: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AdminStatsRequest {
+    // daily statistics cover this many days, 30 by default
+    pub days: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MonthlyCostsRequest {
+    // months including the current one, 12 by default
+    pub months: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobCostsRequest {
+    // e.g. 2021-02
+    pub month: String,
+    // costs of all the cost centers are given if it is not given
+    pub cost_center: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserStatsRequest {
+    // statistics cover this many days including today, all of the jobs if it is not given
+    pub days: Option<i64>,
+}
+
+/// Filters of the jobs across all of the user's experiments, jobs are given by their creation times unless `sort`
+/// is given.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobsRequest {
+    pub status: Option<JobStatus>,
+    pub experiment: Option<Uuid>,
+    pub runner: Option<Uuid>,
+    // only the jobs created after this time are listed
+    pub since: Option<NaiveDateTime>,
+    pub sort: Option<JobSort>,
+    // descending by default, i.e. the newest first
+    pub order: Option<SortOrder>,
+    // jobs of the experiments shared with the user are listed as well if it is true, see `ExperimentsRequest`
+    pub include_shared: Option<bool>,
+    // jobs moved into the archive are listed as well if it is true, see `JobArchiver`
+    pub include_archived: Option<bool>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RunnerDetailRequest {
+    // jobs moved into the archive are listed as well if it is true, see `JobsRequest`
+    pub include_archived: Option<bool>,
+}
+
+#[derive(Clone, Copy, Deserialize, ToSchema)]
+pub enum JobSort {
+    Created,
+    Started,
+    Finished,
+}
+
+#[derive(Clone, Copy, Deserialize, ToSchema)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExperimentsRequest {
+    // only the starred experiments are listed if it is true
+    pub starred: Option<bool>,
+    // archived experiments are hidden unless it is true
+    pub include_archived: Option<bool>,
+    // experiments shared with the user through the projects, or all the experiments for the auditors, are listed
+    // along with the user's own ones if it is true
+    pub include_shared: Option<bool>,
+}
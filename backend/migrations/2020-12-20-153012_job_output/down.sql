@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+alter table jobs
+    drop column output,
+    drop column output_truncated,
+    drop column ansi_mode;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+alter table jobs
+    add column output           text       NOT NULL DEFAULT '',
+    add column output_truncated boolean    NOT NULL DEFAULT false,
+    add column ansi_mode        varchar(8) NOT NULL DEFAULT 'Preserve' CHECK ( ansi_mode in ('Strip', 'Preserve') );
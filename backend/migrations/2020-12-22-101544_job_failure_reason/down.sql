@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+alter table jobs
+    drop column failure_reason;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+alter table jobs
+    add column failure_reason varchar(16) CHECK ( failure_reason in ('LostRunner') );
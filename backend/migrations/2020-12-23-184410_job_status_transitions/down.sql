@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+update jobs
+set status = 'Failed'
+where status in ('Cancelled', 'TimedOut');
+
+alter table jobs
+    drop constraint jobs_status_check,
+    add constraint jobs_status_check CHECK ( status in ('Pending', 'Running', 'Successful', 'Failed') );
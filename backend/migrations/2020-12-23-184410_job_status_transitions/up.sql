@@ -0,0 +1,4 @@
+-- Your SQL goes here
+alter table jobs
+    drop constraint jobs_status_check,
+    add constraint jobs_status_check CHECK ( status in ('Pending', 'Running', 'Successful', 'Failed', 'Cancelled', 'TimedOut') );
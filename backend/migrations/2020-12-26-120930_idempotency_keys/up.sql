@@ -0,0 +1,12 @@
+-- Your SQL goes here
+create table idempotency_keys
+(
+    id         serial PRIMARY KEY NOT NULL,
+    user_id    integer            NOT NULL,
+    key        varchar(255)       NOT NULL,
+    job_id     integer            NOT NULL,
+    created_at timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT idempotency_key_user_id_key UNIQUE (user_id, key),
+    CONSTRAINT idempotency_key_user_id FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    CONSTRAINT idempotency_key_job_id FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+alter table runners
+    drop column name,
+    drop column labels,
+    drop column last_seen_at;
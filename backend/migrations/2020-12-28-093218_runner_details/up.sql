@@ -0,0 +1,8 @@
+-- Your SQL goes here
+alter table runners
+    add column name         varchar(255) NOT NULL DEFAULT '',
+    add column labels       text[]       NOT NULL DEFAULT '{}',
+    add column last_seen_at timestamp;
+
+update runners
+set name = 'runner_' || id;
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+alter table runners
+    drop column os,
+    drop column arch,
+    drop column client_version;
+
+alter table jobs
+    drop column started_at,
+    drop column finished_at;
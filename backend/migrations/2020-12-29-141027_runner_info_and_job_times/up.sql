@@ -0,0 +1,9 @@
+-- Your SQL goes here
+alter table runners
+    add column os             varchar(64),
+    add column arch           varchar(64),
+    add column client_version varchar(64);
+
+alter table jobs
+    add column started_at  timestamp,
+    add column finished_at timestamp;
@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+delete
+from jobs
+where runner_id is null;
+
+alter table jobs
+    alter column runner_id set not null,
+    drop constraint job_runner_id,
+    add constraint job_runner_id FOREIGN KEY (runner_id) REFERENCES runners (id) ON DELETE NO ACTION ON UPDATE NO ACTION;
+
+alter table runners
+    drop column disabled;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+alter table runners
+    add column disabled boolean NOT NULL DEFAULT false;
+
+-- Historical jobs are kept when their runner is deleted
+alter table jobs
+    alter column runner_id drop not null,
+    drop constraint job_runner_id,
+    add constraint job_runner_id FOREIGN KEY (runner_id) REFERENCES runners (id) ON DELETE SET NULL ON UPDATE NO ACTION;
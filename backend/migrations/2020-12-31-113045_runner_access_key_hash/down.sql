@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+-- keys which are already hashed can not be restored, they have to be re-issued
+update runners
+set access_key_hash = runner_legacy_access_keys.access_key
+from runner_legacy_access_keys
+where runner_legacy_access_keys.runner_id = runners.id;
+
+drop table runner_legacy_access_keys;
+
+alter table runners
+    rename column access_key_hash to access_key;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+alter table runners
+    rename column access_key to access_key_hash;
+
+-- Hashes of the plain keys can not be computed here since the key of the hash is only known by the app. Plain keys
+-- are moved aside and hashed by the app on its next start, so that the runners keep connecting with them.
+create table runner_legacy_access_keys
+(
+    runner_id  integer primary key references runners (id) on delete cascade,
+    access_key varchar not null
+);
+
+insert into runner_legacy_access_keys (runner_id, access_key)
+select id, access_key_hash
+from runners;
+
+update runners
+set access_key_hash = 'legacy_' || md5(random()::text || id::text);
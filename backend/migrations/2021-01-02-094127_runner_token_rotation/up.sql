@@ -0,0 +1,3 @@
+-- Your SQL goes here
+alter table runners
+    add column previous_access_key_hash varchar(191);
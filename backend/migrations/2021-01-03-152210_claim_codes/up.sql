@@ -0,0 +1,15 @@
+-- Your SQL goes here
+create table claim_codes
+(
+    id         serial PRIMARY KEY  NOT NULL,
+    code_hash  varchar(191) UNIQUE NOT NULL,
+    name       varchar(255)        NOT NULL,
+    labels     text[]              NOT NULL DEFAULT '{}',
+    created_by integer             NOT NULL,
+    runner_id  integer,
+    expires_at timestamp           NOT NULL,
+    claimed_at timestamp,
+    created_at timestamp           NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT claim_code_created_by FOREIGN KEY (created_by) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    CONSTRAINT claim_code_runner_id FOREIGN KEY (runner_id) REFERENCES runners (id) ON DELETE SET NULL ON UPDATE NO ACTION
+);
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+alter table runners
+    add column allowed_networks text[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+alter table runners
+    add column certificate_fingerprint varchar(64) UNIQUE;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+alter table runners
+    drop column auto_update;
+
+drop table client_releases;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+create table client_releases
+(
+    id         serial PRIMARY KEY NOT NULL,
+    version    varchar(32)        NOT NULL,
+    os         varchar(32)        NOT NULL,
+    arch       varchar(32)        NOT NULL,
+    url        text               NOT NULL,
+    signature  varchar(128)       NOT NULL,
+    created_at timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (version, os, arch)
+);
+
+alter table runners
+    add column auto_update boolean NOT NULL DEFAULT false;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+create table runner_commands
+(
+    id          serial PRIMARY KEY NOT NULL,
+    runner_id   integer            NOT NULL,
+    kind        varchar(32)        NOT NULL,
+    status      varchar(32)        NOT NULL DEFAULT 'Pending',
+    output      text,
+    created_by  integer,
+    created_at  timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at timestamp,
+    CONSTRAINT runner_command_runner_id FOREIGN KEY (runner_id) REFERENCES runners (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    CONSTRAINT runner_command_created_by FOREIGN KEY (created_by) REFERENCES users (id) ON DELETE SET NULL ON UPDATE NO ACTION
+);
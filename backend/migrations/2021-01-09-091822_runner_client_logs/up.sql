@@ -0,0 +1,9 @@
+-- Your SQL goes here
+create table runner_client_logs
+(
+    id         serial PRIMARY KEY NOT NULL,
+    runner_id  integer            NOT NULL,
+    lines      text               NOT NULL,
+    created_at timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT runner_client_log_runner_id FOREIGN KEY (runner_id) REFERENCES runners (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+create table job_streams
+(
+    id         serial PRIMARY KEY NOT NULL,
+    job_id     integer            NOT NULL,
+    name       varchar(64)        NOT NULL,
+    output     text               NOT NULL,
+    truncated  boolean            NOT NULL DEFAULT false,
+    created_at timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT job_stream_job_id FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    CONSTRAINT job_stream_job_id_name UNIQUE (job_id, name)
+);
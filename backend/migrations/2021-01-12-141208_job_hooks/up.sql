@@ -0,0 +1,3 @@
+-- Your SQL goes here
+alter table jobs
+    add column hooks text[] NOT NULL DEFAULT '{}';
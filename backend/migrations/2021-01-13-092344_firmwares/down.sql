@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+alter table jobs
+    drop column flash_status,
+    drop column firmware_id;
+
+alter table experiments
+    drop column firmware_id;
+
+drop table firmwares;
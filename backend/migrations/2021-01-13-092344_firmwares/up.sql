@@ -0,0 +1,20 @@
+-- Your SQL goes here
+create table firmwares
+(
+    id         serial PRIMARY KEY NOT NULL,
+    user_id    integer            NOT NULL,
+    name       varchar(255)       NOT NULL,
+    data       bytea              NOT NULL,
+    size       integer            NOT NULL,
+    created_at timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT firmware_user_id FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+
+alter table experiments
+    add column firmware_id integer,
+    add CONSTRAINT experiment_firmware_id FOREIGN KEY (firmware_id) REFERENCES firmwares (id) ON DELETE SET NULL ON UPDATE NO ACTION;
+
+alter table jobs
+    add column firmware_id integer,
+    add column flash_status varchar(32),
+    add CONSTRAINT job_firmware_id FOREIGN KEY (firmware_id) REFERENCES firmwares (id) ON DELETE SET NULL ON UPDATE NO ACTION;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+drop table experiment_job_stats;
+
+drop table runner_job_stats;
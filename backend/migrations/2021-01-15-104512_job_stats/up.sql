@@ -0,0 +1,22 @@
+-- Your SQL goes here
+create table runner_job_stats
+(
+    runner_id    integer PRIMARY KEY NOT NULL,
+    job_count    bigint              NOT NULL,
+    p50_duration double precision,
+    p95_duration double precision,
+    failure_rate double precision    NOT NULL,
+    updated_at   timestamp           NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT runner_job_stat_runner_id FOREIGN KEY (runner_id) REFERENCES runners (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+
+create table experiment_job_stats
+(
+    experiment_id integer PRIMARY KEY NOT NULL,
+    job_count     bigint              NOT NULL,
+    p50_duration  double precision,
+    p95_duration  double precision,
+    failure_rate  double precision    NOT NULL,
+    updated_at    timestamp           NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT experiment_job_stat_experiment_id FOREIGN KEY (experiment_id) REFERENCES experiments (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+drop trigger job_status_notify on jobs;
+
+drop function notify_job_status();
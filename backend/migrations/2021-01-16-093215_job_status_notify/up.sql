@@ -0,0 +1,25 @@
+-- Your SQL goes here
+create function notify_job_status() returns trigger as
+$$
+begin
+    if TG_OP = 'UPDATE' and OLD.status = NEW.status then
+        return NEW;
+    end if;
+
+    perform pg_notify('job_status', json_build_object(
+        'userId', (select user_id from experiments where id = NEW.experiment_id),
+        'jobId', NEW.id,
+        'experimentId', NEW.experiment_id,
+        'status', NEW.status,
+        'failureReason', NEW.failure_reason
+    )::text);
+
+    return NEW;
+end;
+$$ language plpgsql;
+
+create trigger job_status_notify
+    after insert or update of status
+    on jobs
+    for each row
+execute procedure notify_job_status();
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+create table scheduled_runs
+(
+    job_id      integer PRIMARY KEY NOT NULL,
+    position    bigserial           NOT NULL,
+    runner_id   integer,
+    assigned_at timestamp,
+    CONSTRAINT scheduled_run_job_id FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    CONSTRAINT scheduled_run_runner_id FOREIGN KEY (runner_id) REFERENCES runners (id) ON DELETE SET NULL ON UPDATE NO ACTION
+);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+alter table runners
+    drop column capabilities,
+    drop column peripherals,
+    drop column memory_bytes,
+    drop column cpu_model,
+    drop column kernel;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+alter table runners
+    add column kernel       varchar(255),
+    add column cpu_model    varchar(255),
+    add column memory_bytes bigint,
+    add column peripherals  text[] NOT NULL DEFAULT '{}',
+    add column capabilities text[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+create table user_api_keys
+(
+    id           serial PRIMARY KEY NOT NULL,
+    user_id      integer            NOT NULL,
+    name         varchar(255)       NOT NULL,
+    expires_at   timestamp          NOT NULL,
+    last_used_at timestamp,
+    created_at   timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT user_api_key_user_id FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
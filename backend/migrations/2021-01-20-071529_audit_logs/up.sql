@@ -0,0 +1,12 @@
+-- Your SQL goes here
+create table audit_logs
+(
+    id          serial PRIMARY KEY NOT NULL,
+    actor_id    integer,
+    action      varchar(64)        NOT NULL,
+    target_type varchar(64),
+    target_id   integer,
+    details     text,
+    created_at  timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT audit_log_actor_id FOREIGN KEY (actor_id) REFERENCES users (id) ON DELETE SET NULL ON UPDATE NO ACTION
+);
@@ -0,0 +1,28 @@
+-- This file should undo anything in `up.sql`
+create or replace function notify_job_status() returns trigger as
+$$
+begin
+    if TG_OP = 'UPDATE' and OLD.status = NEW.status then
+        return NEW;
+    end if;
+
+    perform pg_notify('job_status', json_build_object(
+        'userId', (select user_id from experiments where id = NEW.experiment_id),
+        'jobId', NEW.id,
+        'experimentId', NEW.experiment_id,
+        'status', NEW.status,
+        'failureReason', NEW.failure_reason
+    )::text);
+
+    return NEW;
+end;
+$$ language plpgsql;
+
+alter table runners
+    drop column uuid;
+
+alter table jobs
+    drop column uuid;
+
+alter table experiments
+    drop column uuid;
@@ -0,0 +1,31 @@
+-- Your SQL goes here
+create extension if not exists pgcrypto;
+
+alter table experiments
+    add column uuid uuid UNIQUE NOT NULL DEFAULT gen_random_uuid();
+
+alter table jobs
+    add column uuid uuid UNIQUE NOT NULL DEFAULT gen_random_uuid();
+
+alter table runners
+    add column uuid uuid UNIQUE NOT NULL DEFAULT gen_random_uuid();
+
+-- users are notified about their jobs with the uuids
+create or replace function notify_job_status() returns trigger as
+$$
+begin
+    if TG_OP = 'UPDATE' and OLD.status = NEW.status then
+        return NEW;
+    end if;
+
+    perform pg_notify('job_status', json_build_object(
+        'userId', (select user_id from experiments where id = NEW.experiment_id),
+        'jobId', NEW.uuid,
+        'experimentId', (select uuid from experiments where id = NEW.experiment_id),
+        'status', NEW.status,
+        'failureReason', NEW.failure_reason
+    )::text);
+
+    return NEW;
+end;
+$$ language plpgsql;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+create table sessions
+(
+    id                 serial PRIMARY KEY NOT NULL,
+    user_id            integer            NOT NULL,
+    refresh_token_hash varchar(255)       NOT NULL UNIQUE,
+    user_agent         varchar(255),
+    ip                 varchar(64),
+    expires_at         timestamp          NOT NULL,
+    last_used_at       timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at         timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT session_user_id FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+create table password_reset_tokens
+(
+    id         serial PRIMARY KEY NOT NULL,
+    user_id    integer            NOT NULL,
+    token_hash varchar(255)       NOT NULL UNIQUE,
+    expires_at timestamp          NOT NULL,
+    used_at    timestamp,
+    created_at timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT password_reset_token_user_id FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
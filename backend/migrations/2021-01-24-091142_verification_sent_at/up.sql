@@ -0,0 +1,2 @@
+-- Your SQL goes here
+alter table users add column verification_sent_at timestamp;
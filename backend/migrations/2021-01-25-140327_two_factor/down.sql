@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+drop table user_recovery_codes;
+drop table user_two_factors;
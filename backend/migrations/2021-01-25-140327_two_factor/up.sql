@@ -0,0 +1,20 @@
+-- Your SQL goes here
+create table user_two_factors
+(
+    user_id        integer PRIMARY KEY NOT NULL,
+    secret         varchar(64)         NOT NULL,
+    enabled_at     timestamp,
+    last_used_step bigint,
+    created_at     timestamp           NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT user_two_factor_user_id FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+
+create table user_recovery_codes
+(
+    id         serial PRIMARY KEY NOT NULL,
+    user_id    integer            NOT NULL,
+    code_hash  varchar(255)       NOT NULL,
+    used_at    timestamp,
+    created_at timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT user_recovery_code_user_id FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
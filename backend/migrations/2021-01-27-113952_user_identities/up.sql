@@ -0,0 +1,13 @@
+-- Your SQL goes here
+create table user_identities
+(
+    id            serial PRIMARY KEY NOT NULL,
+    user_id       integer            NOT NULL,
+    issuer        varchar(255)       NOT NULL,
+    subject       varchar(255)       NOT NULL,
+    email         varchar(255),
+    last_login_at timestamp,
+    created_at    timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT user_identity_user_id FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    CONSTRAINT user_identity_issuer_subject UNIQUE (issuer, subject)
+);
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+create table failed_logins
+(
+    id         serial PRIMARY KEY NOT NULL,
+    email      varchar(255)       NOT NULL,
+    ip         varchar(64),
+    created_at timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+create index failed_logins_email_created_at on failed_logins (email, created_at);
+create index failed_logins_ip_created_at on failed_logins (ip, created_at);
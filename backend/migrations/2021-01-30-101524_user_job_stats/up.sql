@@ -0,0 +1,17 @@
+-- Your SQL goes here
+create table user_job_stats
+(
+    id              serial PRIMARY KEY NOT NULL,
+    user_id         integer            NOT NULL,
+    -- null for the jobs cancelled before they are started, or if the runner is deleted
+    runner_id       integer,
+    day             date               NOT NULL,
+    job_count       bigint             NOT NULL,
+    successful_jobs bigint             NOT NULL,
+    failed_jobs     bigint             NOT NULL,
+    runtime_seconds double precision   NOT NULL,
+    CONSTRAINT user_job_stat_user_id FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    CONSTRAINT user_job_stat_runner_id FOREIGN KEY (runner_id) REFERENCES runners (id) ON DELETE SET NULL ON UPDATE NO ACTION
+);
+
+create index user_job_stats_user_id_day on user_job_stats (user_id, day);
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+create table experiment_activities
+(
+    id            serial PRIMARY KEY NOT NULL,
+    experiment_id integer            NOT NULL,
+    -- user performing the change, null for the events of the runs or if the user is deleted
+    actor_id      integer,
+    kind          varchar(32)        NOT NULL,
+    job_id        integer,
+    details       text,
+    created_at    timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT experiment_activity_experiment_id FOREIGN KEY (experiment_id) REFERENCES experiments (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    CONSTRAINT experiment_activity_actor_id FOREIGN KEY (actor_id) REFERENCES users (id) ON DELETE SET NULL ON UPDATE NO ACTION,
+    CONSTRAINT experiment_activity_job_id FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE SET NULL ON UPDATE NO ACTION
+);
+
+create index experiment_activities_experiment_id_created_at on experiment_activities (experiment_id, created_at);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+alter table experiments
+    drop column starred;
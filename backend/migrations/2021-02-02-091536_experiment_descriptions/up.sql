@@ -0,0 +1,3 @@
+-- Your SQL goes here
+alter table experiments
+    add column description text NOT NULL DEFAULT '';
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+alter table experiments
+    add column archived_at timestamp;
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+alter table experiments
+    add column slug varchar(128);
+
+update experiments
+set slug = coalesce(nullif(trim(both '-' from left(regexp_replace(lower(name), '[^a-z0-9]+', '-', 'g'), 96)), ''), 'experiment');
+
+-- experiments of a user sharing a name are told apart by their ids
+update experiments
+set slug = slug || '-' || id
+where id in (
+    select id
+    from (select id, row_number() over (partition by user_id, slug order by id) as n from experiments) as duplicates
+    where n > 1
+);
+
+alter table experiments
+    alter column slug set NOT NULL,
+    add CONSTRAINT experiment_user_id_slug UNIQUE (user_id, slug);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+drop index experiments_deleted_at;
+drop index experiments_user_id_slug;
+
+alter table experiments
+    drop column deleted_at,
+    add CONSTRAINT experiment_user_id_slug UNIQUE (user_id, slug);
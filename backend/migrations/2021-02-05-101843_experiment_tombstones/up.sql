@@ -0,0 +1,9 @@
+-- Your SQL goes here
+alter table experiments
+    add column deleted_at timestamp,
+    drop constraint experiment_user_id_slug;
+
+-- slugs of the deleted experiments are released right away instead of once they are cleaned up
+create unique index experiments_user_id_slug on experiments (user_id, slug) where deleted_at IS NULL;
+
+create index experiments_deleted_at on experiments (deleted_at) where deleted_at IS NOT NULL;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+alter table jobs
+    drop column protected;
+
+alter table experiments
+    drop column retention_days,
+    drop column retention_jobs;
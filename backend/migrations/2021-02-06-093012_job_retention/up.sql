@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- override the retention policy of the app for the jobs of the experiment
+alter table experiments
+    add column retention_days integer,
+    add column retention_jobs integer;
+
+-- protected jobs are not removed by the retention policy
+alter table jobs
+    add column protected boolean NOT NULL DEFAULT false;
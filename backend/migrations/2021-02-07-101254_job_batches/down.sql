@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+alter table jobs
+    drop column batch_id;
+
+drop table job_batches;
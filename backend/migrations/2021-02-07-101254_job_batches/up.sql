@@ -0,0 +1,18 @@
+-- Your SQL goes here
+create table job_batches
+(
+    id            serial PRIMARY KEY NOT NULL,
+    uuid          uuid UNIQUE        NOT NULL DEFAULT gen_random_uuid(),
+    experiment_id integer            NOT NULL,
+    created_by    integer,
+    created_at    timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT job_batch_experiment_id FOREIGN KEY (experiment_id) REFERENCES experiments (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    CONSTRAINT job_batch_created_by FOREIGN KEY (created_by) REFERENCES users (id) ON DELETE SET NULL ON UPDATE NO ACTION
+);
+
+-- jobs launched together, e.g. the same experiment on multiple runners
+alter table jobs
+    add column batch_id integer,
+    add CONSTRAINT job_batch_id FOREIGN KEY (batch_id) REFERENCES job_batches (id) ON DELETE SET NULL ON UPDATE NO ACTION;
+
+create index jobs_batch_id on jobs (batch_id) where batch_id IS NOT NULL;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+alter table job_batches
+    drop column abort_after_failures,
+    drop column abort_metric,
+    drop column abort_threshold,
+    drop column abort_crossing,
+    drop column aborted_at;
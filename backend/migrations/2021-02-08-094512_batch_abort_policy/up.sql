@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- remaining jobs of a batch are cancelled once it has the given number of failures, or once a job reports a metric
+-- crossing the threshold
+alter table job_batches
+    add column abort_after_failures integer,
+    add column abort_metric         varchar(64),
+    add column abort_threshold      double precision,
+    add column abort_crossing       varchar(5) CHECK ( abort_crossing in ('Above', 'Below') ),
+    add column aborted_at           timestamp;
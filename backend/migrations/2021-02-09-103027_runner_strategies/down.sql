@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+alter table experiments
+    drop column runner_strategy,
+    drop column preferred_labels;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- how the server picks a runner for the jobs of the experiment, preferred labels are used by the LabelAffinity strategy
+alter table experiments
+    add column runner_strategy  varchar(17) NOT NULL DEFAULT 'BestScore' CHECK ( runner_strategy in ('BestScore', 'LeastLoaded', 'RoundRobin', 'FastestHistorical', 'LabelAffinity') ),
+    add column preferred_labels text[]      NOT NULL DEFAULT '{}';
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+alter table jobs
+    drop column lease_expires_at,
+    drop column reclaim_count;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- running jobs are leased to their runners, jobs whose lease is not renewed in time are put back into the queue
+alter table jobs
+    add column lease_expires_at timestamp,
+    add column reclaim_count    integer NOT NULL DEFAULT 0;
+
+create index jobs_lease_expires_at on jobs (lease_expires_at) where lease_expires_at IS NOT NULL;
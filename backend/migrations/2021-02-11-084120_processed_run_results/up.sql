@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- results resent by the runners after reconnecting are applied only once
+create table processed_run_results
+(
+    job_id       integer     NOT NULL,
+    message_id   varchar(64) NOT NULL,
+    processed_at timestamp   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (job_id, message_id),
+    CONSTRAINT processed_run_result_job_id FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- pending jobs with higher priorities are dispatched first, admins bump the jobs by raising their priorities
+alter table jobs
+    add column queue_priority integer NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+alter table jobs
+    drop column annotation,
+    drop column verdict,
+    drop column annotated_at;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- given by the users, kept apart from the status reported by the runner
+alter table jobs
+    add column annotation   text         NULL,
+    add column verdict      varchar(7)   NULL CHECK ( verdict in ('Passed', 'Failed', 'Invalid') ),
+    add column annotated_at timestamp    NULL;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+alter table jobs
+    drop column derived_verdict;
+
+alter table experiments
+    drop column success_criteria;
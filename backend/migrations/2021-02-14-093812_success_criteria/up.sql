@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- criteria over the metrics reported by the jobs, the verdicts derived from them are kept apart from the statuses
+alter table experiments
+    add column success_criteria text NULL;
+
+alter table jobs
+    add column derived_verdict varchar(7) NULL CHECK ( derived_verdict in ('Passed', 'Failed', 'Invalid') );
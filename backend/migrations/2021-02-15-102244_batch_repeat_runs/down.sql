@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+alter table job_batches
+    drop column repeat_times,
+    drop column repeat_metrics,
+    drop column max_variation;
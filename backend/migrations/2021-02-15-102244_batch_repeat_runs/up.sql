@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- experiment is run this many times on each runner of the batch, the runs are flaky if their outcomes differ or the
+-- given metrics vary more than the max variation, i.e. the coefficient of variation
+alter table job_batches
+    add column repeat_times   integer   NOT NULL DEFAULT 1,
+    add column repeat_metrics text[]    NOT NULL DEFAULT '{}',
+    add column max_variation  double precision;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+alter table jobs
+    drop column warmup;
+
+alter table experiments
+    drop column warmup_runs;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+-- batches of the experiment start with this many warm-up runs on each runner, they are run like the others but they
+-- are left out of the batch statistics
+alter table experiments
+    add column warmup_runs integer NOT NULL DEFAULT 0 CHECK ( warmup_runs >= 0 );
+
+alter table jobs
+    add column warmup boolean NOT NULL DEFAULT false;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- state of the runner when the job started, e.g. its kernel, cpu governors, temperatures and the firmware versions
+-- of the attached devices, kept as the json reported by the runner
+create table job_environments
+(
+    job_id      integer PRIMARY KEY NOT NULL,
+    snapshot    text                NOT NULL,
+    recorded_at timestamp           NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT job_environment_job_id FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- jobs of the experiment are dispatched only to the runners whose hottest thermal zone is below this many degrees
+-- Celsius, unless they have waited for a cool runner longer than the timeout
+alter table experiments
+    add column max_temperature double precision CHECK ( max_temperature > 0 ),
+    add column thermal_timeout integer NOT NULL DEFAULT 600 CHECK ( thermal_timeout > 0 );
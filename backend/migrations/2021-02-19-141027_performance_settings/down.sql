@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+alter table experiments
+    drop column disable_turbo,
+    drop column cpu_frequency_khz,
+    drop column cpu_governor;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- runners apply these settings while the jobs of the experiment run and restore them afterwards, the ones which are
+-- not given are left as is
+alter table experiments
+    add column cpu_governor varchar(32),
+    add column cpu_frequency_khz integer CHECK ( cpu_frequency_khz > 0 ),
+    add column disable_turbo boolean NOT NULL DEFAULT false;
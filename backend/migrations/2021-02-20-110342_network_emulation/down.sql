@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+alter table experiments
+    drop column netem_rate_kbit,
+    drop column netem_loss_percent,
+    drop column netem_jitter_ms,
+    drop column netem_delay_ms;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- runners emulate these network conditions on their configured interface while the jobs of the experiment run
+alter table experiments
+    add column netem_delay_ms integer CHECK ( netem_delay_ms >= 0 ),
+    add column netem_jitter_ms integer CHECK ( netem_jitter_ms >= 0 ),
+    add column netem_loss_percent double precision CHECK ( netem_loss_percent >= 0 AND netem_loss_percent <= 100 ),
+    add column netem_rate_kbit integer CHECK ( netem_rate_kbit > 0 );
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+-- files collected by the runner during the job, e.g. the compressed packet captures
+create table job_artifacts
+(
+    id         serial PRIMARY KEY NOT NULL,
+    job_id     integer            NOT NULL,
+    name       varchar(64)        NOT NULL,
+    data       bytea              NOT NULL,
+    size       integer            NOT NULL,
+    created_at timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT job_artifact_job_id FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    CONSTRAINT job_artifact_job_id_name UNIQUE (job_id, name)
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+alter table job_artifacts
+    drop column metadata;
+
+drop table experiment_sdr_settings;
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+-- runners tune their SDR with these settings before the jobs of the experiment, and record the IQ samples or a
+-- spectrum snapshot while the jobs run if a recording is requested
+create table experiment_sdr_settings
+(
+    experiment_id integer PRIMARY KEY NOT NULL,
+    frequency_hz  bigint              NOT NULL CHECK ( frequency_hz > 0 ),
+    gain_db       double precision    NOT NULL,
+    sample_rate   integer             NOT NULL CHECK ( sample_rate > 0 ),
+    recording     varchar(16) CHECK ( recording IN ('iq', 'spectrum') ),
+    recording_ms  integer CHECK ( recording_ms > 0 ),
+    CONSTRAINT experiment_sdr_settings_experiment_id FOREIGN KEY (experiment_id) REFERENCES experiments (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+
+-- describes how the artifact is collected, e.g. the frequency of a recording, as a json object of strings
+alter table job_artifacts
+    add column metadata text NOT NULL DEFAULT '{}';
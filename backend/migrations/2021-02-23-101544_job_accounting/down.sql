@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+drop table monthly_costs;
+drop table job_costs;
+drop table experiment_cost_centers;
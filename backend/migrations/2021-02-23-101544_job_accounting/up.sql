@@ -0,0 +1,43 @@
+-- Your SQL goes here
+-- jobs of the experiment are charged to this cost center, they are charged to the unassigned one if it is not given
+create table experiment_cost_centers
+(
+    experiment_id integer PRIMARY KEY NOT NULL,
+    cost_center   varchar(64)         NOT NULL,
+    CONSTRAINT experiment_cost_center_experiment_id FOREIGN KEY (experiment_id) REFERENCES experiments (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+
+-- usage of the finished jobs for the chargeback reports. Records are not tied to the jobs, experiments or users so
+-- that they are kept after those are purged or deleted, hence the details are copied
+create table job_costs
+(
+    job_id          integer PRIMARY KEY NOT NULL,
+    job_uuid        uuid                NOT NULL,
+    experiment_uuid uuid                NOT NULL,
+    experiment_name varchar(255)        NOT NULL,
+    user_email      varchar(255)        NOT NULL,
+    runner_name     varchar(255),
+    cost_center     varchar(64)         NOT NULL,
+    status          varchar(32)         NOT NULL,
+    runtime_seconds double precision    NOT NULL,
+    energy_mj       double precision,
+    storage_bytes   bigint              NOT NULL,
+    finished_at     timestamp           NOT NULL,
+    recorded_at     timestamp           NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+create index job_costs_finished_at on job_costs (finished_at);
+
+-- job costs rolled up per month and cost center
+create table monthly_costs
+(
+    month           date             NOT NULL,
+    cost_center     varchar(64)      NOT NULL,
+    job_count       bigint           NOT NULL,
+    -- jobs which reported their energy consumption
+    metered_jobs    bigint           NOT NULL,
+    runtime_seconds double precision NOT NULL,
+    energy_mj       double precision NOT NULL,
+    storage_bytes   bigint           NOT NULL,
+    PRIMARY KEY (month, cost_center)
+);
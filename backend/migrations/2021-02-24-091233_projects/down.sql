@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+alter table experiments
+    drop column project_id;
+
+drop table project_members;
+drop table projects;
@@ -0,0 +1,35 @@
+-- Your SQL goes here
+-- experiments of a research group, shared with the members of the project
+create table projects
+(
+    id              serial PRIMARY KEY NOT NULL,
+    uuid            uuid UNIQUE        NOT NULL DEFAULT gen_random_uuid(),
+    name            varchar(255)       NOT NULL,
+    owner_id        integer            NOT NULL,
+    -- quotas given by the admins, unlimited if they are not given
+    max_experiments integer,
+    max_active_jobs integer,
+    -- preferred runner labels of the experiments which do not have any of their own
+    default_labels  text[]             NOT NULL DEFAULT '{}',
+    created_at      timestamp          NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT project_owner_id FOREIGN KEY (owner_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+
+-- owners are members of their projects as well
+create table project_members
+(
+    project_id integer   NOT NULL,
+    user_id    integer   NOT NULL,
+    created_at timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (project_id, user_id),
+    CONSTRAINT project_member_project_id FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    CONSTRAINT project_member_user_id FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+
+create index project_members_user_id on project_members (user_id);
+
+alter table experiments
+    add column project_id integer,
+    add CONSTRAINT experiment_project_id FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE SET NULL ON UPDATE NO ACTION;
+
+create index experiments_project_id on experiments (project_id) where project_id IS NOT NULL;
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+update users
+set role_id = (select id from roles where name = 'user')
+where role_id = (select id from roles where name = 'auditor');
+
+delete
+from roles
+where name = 'auditor';
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- auditors view the experiments, jobs and audit logs of all the users, e.g. the thesis supervisors
+insert into roles (name)
+values ('auditor');
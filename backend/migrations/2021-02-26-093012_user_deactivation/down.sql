@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+update users
+set status = 'Banned'
+where status = 'Deactivated';
+
+alter table users
+    drop constraint users_status_check,
+    add constraint users_status_check CHECK ( status in ('NotVerified', 'Verified', 'Banned') );
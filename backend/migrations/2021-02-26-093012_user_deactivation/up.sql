@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- deactivated users can not sign in, their experiments and jobs are kept
+alter table users
+    drop constraint users_status_check,
+    add constraint users_status_check CHECK ( status in ('NotVerified', 'Verified', 'Banned', 'Deactivated') );
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+drop table security_mails;
+
+drop trigger audit_event_notify on audit_logs;
+
+drop function notify_audit_event();
+
+drop index audit_logs_actor_id_action;
@@ -0,0 +1,34 @@
+-- Your SQL goes here
+-- logins of the users are looked up by their devices to tell the new ones apart
+create index audit_logs_actor_id_action on audit_logs (actor_id, action);
+
+-- users are notified about the security relevant entries of the audit log, see experiment::connection::audit_events
+create function notify_audit_event() returns trigger as
+$$
+begin
+    perform pg_notify('audit_events', json_build_object(
+        'id', NEW.id,
+        'userId', NEW.actor_id,
+        'action', NEW.action,
+        'details', NEW.details,
+        'createdAt', NEW.created_at
+    )::text);
+
+    return NEW;
+end;
+$$ language plpgsql;
+
+create trigger audit_event_notify
+    after insert
+    on audit_logs
+    for each row
+    when ( NEW.actor_id is not null and NEW.action in ('api_key.create', 'session.new_device') )
+execute procedure notify_audit_event();
+
+-- entries mailed to their users, every replica receives the notifications but only one of them mails an entry
+create table security_mails
+(
+    audit_log_id integer   PRIMARY KEY NOT NULL,
+    created_at   timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT security_mail_audit_log_id FOREIGN KEY (audit_log_id) REFERENCES audit_logs (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
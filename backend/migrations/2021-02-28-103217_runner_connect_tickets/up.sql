@@ -0,0 +1,12 @@
+-- Your SQL goes here
+-- one-time tickets the runners join the server with, so that their tokens are not put into the urls
+create table runner_connect_tickets
+(
+    ticket_hash      varchar(191) PRIMARY KEY NOT NULL,
+    runner_id        integer                  NOT NULL,
+    -- token the ticket is minted with, the ticket is rejected once its access key is rotated
+    access_key_hash  varchar(191)             NOT NULL,
+    token_expires_at bigint                   NOT NULL,
+    expires_at       timestamp                NOT NULL,
+    CONSTRAINT runner_connect_ticket_runner_id FOREIGN KEY (runner_id) REFERENCES runners (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
@@ -0,0 +1,18 @@
+-- This file should undo anything in `up.sql`
+drop trigger code_blob_references on jobs;
+
+drop function count_code_blob_references();
+
+alter table jobs
+    add column code text;
+
+update jobs
+set code = code_blobs.code
+from code_blobs
+where code_blobs.hash = jobs.code_hash;
+
+alter table jobs
+    alter column code set NOT NULL,
+    drop column code_hash;
+
+drop table code_blobs;
@@ -0,0 +1,53 @@
+-- Your SQL goes here
+-- code of the jobs is stored once for the jobs running the same code, e.g. the runs of a batch, keyed by its hex
+-- encoded SHA-256 digest
+create table code_blobs
+(
+    hash       varchar(64) PRIMARY KEY NOT NULL,
+    code       text        NOT NULL,
+    -- number of the jobs referring the blob, it is removed once there is none
+    ref_count  integer     NOT NULL DEFAULT 0,
+    created_at timestamp   NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+insert into code_blobs (hash, code, ref_count)
+select encode(sha256(convert_to(code, 'UTF8')), 'hex'), code, count(*)
+from jobs
+group by code;
+
+alter table jobs
+    add column code_hash varchar(64);
+
+update jobs
+set code_hash = encode(sha256(convert_to(code, 'UTF8')), 'hex');
+
+alter table jobs
+    alter column code_hash set NOT NULL,
+    add CONSTRAINT job_code_hash FOREIGN KEY (code_hash) REFERENCES code_blobs (hash) ON DELETE NO ACTION ON UPDATE NO ACTION,
+    drop column code;
+
+create index jobs_code_hash on jobs (code_hash);
+
+-- references are counted by the database, so that the jobs removed by the purges, the retention policy or along
+-- with their experiments release their blobs
+create function count_code_blob_references() returns trigger as
+$$
+begin
+    if TG_OP in ('INSERT', 'UPDATE') then
+        update code_blobs set ref_count = ref_count + 1 where hash = NEW.code_hash;
+    end if;
+
+    if TG_OP in ('DELETE', 'UPDATE') then
+        update code_blobs set ref_count = ref_count - 1 where hash = OLD.code_hash;
+        delete from code_blobs where hash = OLD.code_hash and ref_count = 0;
+    end if;
+
+    return null;
+end;
+$$ language plpgsql;
+
+create trigger code_blob_references
+    after insert or delete or update of code_hash
+    on jobs
+    for each row
+execute procedure count_code_blob_references();
@@ -0,0 +1,37 @@
+-- This file should undo anything in `up.sql`
+drop view all_job_streams;
+
+drop view all_jobs;
+
+drop trigger archived_job_references_release on archived_jobs;
+
+drop trigger job_references_release on jobs;
+
+drop function release_job_references();
+
+insert into jobs
+select *
+from archived_jobs;
+
+insert into job_streams
+select *
+from archived_job_streams;
+
+-- releases the code blobs referred by the archived jobs
+delete
+from archived_jobs;
+
+drop index experiment_activities_job_id;
+
+alter table experiment_activities
+    add CONSTRAINT experiment_activity_job_id FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE SET NULL ON UPDATE NO ACTION;
+
+alter table job_environments
+    add CONSTRAINT job_environment_job_id FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE ON UPDATE NO ACTION;
+
+alter table job_artifacts
+    add CONSTRAINT job_artifact_job_id FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE ON UPDATE NO ACTION;
+
+drop table archived_job_streams;
+
+drop table archived_jobs;
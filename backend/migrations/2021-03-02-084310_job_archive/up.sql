@@ -0,0 +1,82 @@
+-- Your SQL goes here
+-- finished jobs are moved here once they are old, so that the queries of the active jobs stay fast, see
+-- experiment::connection::archiver. Jobs are moved with their columns named in the archiver, so the columns added to
+-- the jobs should be added here and there as well.
+create table archived_jobs
+(
+    like jobs including defaults including constraints including indexes
+);
+
+alter table archived_jobs
+    add CONSTRAINT archived_job_experiment_id FOREIGN KEY (experiment_id) REFERENCES experiments (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+    add CONSTRAINT archived_job_runner_id FOREIGN KEY (runner_id) REFERENCES runners (id) ON DELETE SET NULL ON UPDATE NO ACTION,
+    add CONSTRAINT archived_job_firmware_id FOREIGN KEY (firmware_id) REFERENCES firmwares (id) ON DELETE SET NULL ON UPDATE NO ACTION,
+    add CONSTRAINT archived_job_batch_id FOREIGN KEY (batch_id) REFERENCES job_batches (id) ON DELETE SET NULL ON UPDATE NO ACTION,
+    add CONSTRAINT archived_job_code_hash FOREIGN KEY (code_hash) REFERENCES code_blobs (hash) ON DELETE NO ACTION ON UPDATE NO ACTION;
+
+create table archived_job_streams
+(
+    like job_streams including defaults including constraints including indexes,
+    CONSTRAINT archived_job_stream_job_id FOREIGN KEY (job_id) REFERENCES archived_jobs (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+
+create trigger archived_code_blob_references
+    after insert or delete or update of code_hash
+    on archived_jobs
+    for each row
+execute procedure count_code_blob_references();
+
+-- artifacts, environments and activities refer to the jobs wherever they are, they are kept while the jobs are
+-- moved into the archive and removed along with the jobs otherwise
+alter table job_artifacts
+    drop constraint job_artifact_job_id;
+
+alter table job_environments
+    drop constraint job_environment_job_id;
+
+alter table experiment_activities
+    drop constraint experiment_activity_job_id;
+
+create index experiment_activities_job_id on experiment_activities (job_id) where job_id IS NOT NULL;
+
+create function release_job_references() returns trigger as
+$$
+begin
+    if TG_TABLE_NAME = 'jobs' and exists(select 1 from archived_jobs where id = OLD.id) then
+        return null;
+    end if;
+
+    delete from job_artifacts where job_id = OLD.id;
+    delete from job_environments where job_id = OLD.id;
+    update experiment_activities set job_id = null where job_id = OLD.id;
+
+    return null;
+end;
+$$ language plpgsql;
+
+create trigger job_references_release
+    after delete
+    on jobs
+    for each row
+execute procedure release_job_references();
+
+create trigger archived_job_references_release
+    after delete
+    on archived_jobs
+    for each row
+execute procedure release_job_references();
+
+-- read by the endpoints serving a job wherever it is, and by the listings when the archived jobs are asked for
+create view all_jobs as
+select *
+from jobs
+union all
+select *
+from archived_jobs;
+
+create view all_job_streams as
+select *
+from job_streams
+union all
+select *
+from archived_job_streams;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- releases are signed over their manifest of the version, the platform and the hex encoded SHA-256 digest of the
+-- binary, so that a signed binary can not be advertised as another release. Releases signed before do not have the
+-- digest, they are not advertised anymore
+alter table client_releases
+    add column sha256 varchar(64);
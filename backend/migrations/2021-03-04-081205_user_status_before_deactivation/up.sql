@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- the status is restored on the reactivation, so that a user deactivated before verifying their email stays unverified
+alter table users add column status_before_deactivation varchar;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+alter table user_job_stats drop column energy_mj;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- energy of the jobs which reported it, see job_costs.energy_mj
+alter table user_job_stats add column energy_mj double precision NOT NULL DEFAULT 0;
+
+-- rolled up again along with the energy of the jobs by the next aggregation
+delete from user_job_stats;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+alter table sessions drop column previous_refresh_token_hash;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- a rotated refresh token presented again means it has leaked, the session is revoked then
+alter table sessions add column previous_refresh_token_hash varchar;
@@ -1 +1,2 @@
-pub mod mail;
\ No newline at end of file
+pub mod mail;
+pub mod oidc;
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use actix_web::client::{Client, ClientResponse, Connector};
+use actix_web::error::PayloadError;
+use actix_web::web::Bytes;
+use futures::Stream;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use rustls::ClientConfig;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// clocks of the provider and the app may drift
+const ID_TOKEN_LEEWAY: u64 = 60;
+
+/// Configuration of the OpenID Connect provider, e.g. the single sign on of a university
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    // page of the web app the provider redirects back to with the authorization code
+    pub redirect_url: String,
+    // claim holding the roles or the groups of the user, roles are not mapped if it is not given
+    pub role_claim: Option<String>,
+    // values of the role claim which make the user an admin
+    pub admin_roles: Vec<String>,
+    // values of the role claim which make the user an auditor, admin roles take precedence
+    pub auditor_roles: Vec<String>,
+}
+
+/// Role of the user mapped from the role claim
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OidcRole {
+    Admin,
+    Auditor,
+    User,
+}
+
+/// Claims of a verified id token which are used for linking and creating the accounts
+pub struct OidcClaims {
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub name: Option<String>,
+    // None if the roles are not mapped, i.e. the role of the user is kept as is
+    pub role: Option<OidcRole>,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TokenRequest<'a> {
+    grant_type: &'a str,
+    code: &'a str,
+    redirect_uri: &'a str,
+    client_id: &'a str,
+    client_secret: &'a str,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+    given_name: Option<String>,
+    family_name: Option<String>,
+    name: Option<String>,
+    #[serde(flatten)]
+    other: HashMap<String, Value>,
+}
+
+struct Provider {
+    metadata: Metadata,
+    keys: HashMap<String, DecodingKey<'static>>,
+}
+
+/// Client of the OpenID Connect provider. Metadata and the signing keys of the provider are fetched on the first
+/// use, keys are fetched again if an id token is signed with an unknown key, i.e. the provider has rotated them.
+#[derive(Clone)]
+pub struct OidcClient {
+    config: Arc<OidcConfig>,
+    provider: Arc<RwLock<Option<Arc<Provider>>>>,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig) -> Self {
+        OidcClient {
+            config: Arc::new(config),
+            provider: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    pub fn issuer(&self) -> &str {
+        self.config.issuer.as_str()
+    }
+
+    /// Url of the provider the user is redirected to, given state and nonce are returned back by the provider
+    pub async fn authorization_url(&self, state: &str, nonce: &str) -> Result<String, Error> {
+        let provider = self.provider(false).await?;
+
+        let query = serde_urlencoded::to_string([
+            ("response_type", "code"),
+            ("client_id", self.config.client_id.as_str()),
+            ("redirect_uri", self.config.redirect_url.as_str()),
+            ("scope", "openid email profile"),
+            ("state", state),
+            ("nonce", nonce),
+        ])
+            .map_err(|e| Error::Request(e.to_string()))?;
+
+        Ok(format!("{}?{}", provider.metadata.authorization_endpoint, query))
+    }
+
+    /// Exchanges the authorization code for an id token and returns its claims once the token is verified, the
+    /// nonce must match the one given for the authorization url.
+    pub async fn exchange(&self, code: &str, nonce: &str) -> Result<OidcClaims, Error> {
+        let provider = self.provider(false).await?;
+
+        let response = client()
+            .post(provider.metadata.token_endpoint.as_str())
+            .send_form(&TokenRequest {
+                grant_type: "authorization_code",
+                code,
+                redirect_uri: self.config.redirect_url.as_str(),
+                client_id: self.config.client_id.as_str(),
+                client_secret: self.config.client_secret.as_str(),
+            })
+            .await
+            .map_err(|e| Error::Request(e.to_string()))?;
+
+        let id_token = parse::<TokenResponse, _>(response).await?.id_token;
+
+        let kid = jsonwebtoken::decode_header(id_token.as_str())
+            .map_err(|e| Error::InvalidToken(e.to_string()))?
+            .kid
+            .ok_or_else(|| Error::InvalidToken(String::from("id token does not have a key id")))?;
+
+        let provider = if provider.keys.contains_key(&kid) {
+            provider
+        } else {
+            self.provider(true).await?
+        };
+
+        let key = provider.keys.get(&kid)
+            .ok_or_else(|| Error::InvalidToken(format!("unknown key {}", kid)))?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.leeway = ID_TOKEN_LEEWAY;
+        validation.iss = Some(self.config.issuer.clone());
+        validation.set_audience(&[self.config.client_id.as_str()]);
+
+        let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token.as_str(), key, &validation)
+            .map_err(|e| Error::InvalidToken(e.to_string()))?
+            .claims;
+
+        // nonce binds the id token to the authorization request, a token issued for another request is not accepted
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(Error::InvalidToken(String::from("nonce does not match")));
+        }
+
+        let role = self.config.role_claim.as_ref()
+            .map(|role_claim| {
+                let roles = match claims.other.get(role_claim) {
+                    Some(Value::String(role)) => vec![role.as_str()],
+                    Some(Value::Array(roles)) => roles.iter().filter_map(Value::as_str).collect(),
+                    _ => Vec::new()
+                };
+
+                let has_any = |mapped: &[String]| roles.iter().any(|role| mapped.iter().any(|mapped| mapped == role));
+
+                if has_any(&self.config.admin_roles) {
+                    OidcRole::Admin
+                } else if has_any(&self.config.auditor_roles) {
+                    OidcRole::Auditor
+                } else {
+                    OidcRole::User
+                }
+            });
+
+        Ok(OidcClaims {
+            subject: claims.sub,
+            email: claims.email,
+            email_verified: claims.email_verified,
+            given_name: claims.given_name,
+            family_name: claims.family_name,
+            name: claims.name,
+            role,
+        })
+    }
+
+    async fn provider(&self, refresh: bool) -> Result<Arc<Provider>, Error> {
+        if !refresh {
+            if let Some(provider) = self.provider.read().unwrap().as_ref() {
+                return Ok(provider.clone());
+            }
+        }
+
+        let discovery_url = format!("{}/.well-known/openid-configuration", self.config.issuer.trim_end_matches('/'));
+
+        let metadata = get::<Metadata>(discovery_url.as_str()).await?;
+
+        let keys = get::<JwkSet>(metadata.jwks_uri.as_str()).await?
+            .keys
+            .into_iter()
+            .filter(|jwk| jwk.kty == "RSA")
+            .filter_map(|jwk| match (jwk.kid, jwk.n, jwk.e) {
+                (Some(kid), Some(n), Some(e)) => Some((kid, DecodingKey::from_rsa_components(n.as_str(), e.as_str()).into_static())),
+                _ => None
+            })
+            .collect();
+
+        let provider = Arc::new(Provider { metadata, keys });
+
+        *self.provider.write().unwrap() = Some(provider.clone());
+
+        Ok(provider)
+    }
+}
+
+fn client() -> Client {
+    let mut config = ClientConfig::new();
+    config.root_store.add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+
+    Client::builder()
+        .connector(Connector::new().rustls(Arc::new(config)).finish())
+        .timeout(REQUEST_TIMEOUT)
+        .finish()
+}
+
+async fn get<T: DeserializeOwned>(url: &str) -> Result<T, Error> {
+    let response = client()
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| Error::Request(e.to_string()))?;
+
+    parse(response).await
+}
+
+async fn parse<T, S>(mut response: ClientResponse<S>) -> Result<T, Error>
+    where T: DeserializeOwned,
+          S: Stream<Item=Result<Bytes, PayloadError>> + Unpin {
+    if !response.status().is_success() {
+        return Err(Error::InvalidResponse(format!("provider responded with status {}", response.status())));
+    }
+
+    response.json::<T>()
+        .await
+        .map_err(|e| Error::InvalidResponse(e.to_string()))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Request(String),
+    InvalidResponse(String),
+    InvalidToken(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Request(e) => write!(f, "request to the provider is failed, {}", e),
+            Error::InvalidResponse(e) => write!(f, "invalid response from the provider, {}", e),
+            Error::InvalidToken(e) => write!(f, "invalid id token, {}", e),
+        }
+    }
+}
@@ -1,10 +1,13 @@
 pub use clients::mail::{MailClient, MailClientMock, MailService, SendMailMessage};
+pub use clients::oidc::{Error as OidcError, OidcClaims, OidcClient, OidcConfig, OidcRole};
 
 mod clients;
 
 #[derive(Clone)]
 pub struct ClientServices {
-    pub mail: MailService
+    pub mail: MailService,
+    // login with the OpenID Connect provider is offered if it is configured
+    pub oidc: Option<OidcClient>,
 }
 
 #[cfg(test)]
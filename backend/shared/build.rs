@@ -0,0 +1,6 @@
+fn main() {
+    // grpc service of the runners is generated only if it is used
+    if std::env::var("CARGO_FEATURE_GRPC").is_ok() {
+        tonic_build::compile_protos("proto/runner.proto").expect("Failed to compile runner.proto");
+    }
+}
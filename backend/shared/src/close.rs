@@ -0,0 +1,48 @@
+/// Reasons of the server for closing a runner connection, sent as application close codes in the
+/// private range of RFC 6455. Runner decides whether to reconnect depending on the reason.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CloseKind {
+    /// Token of the runner is expired or revoked, runner needs a new one
+    AuthExpired,
+    /// Runner sends messages the server does not understand, runner needs an update
+    ProtocolMismatch,
+    /// Server is shutting down, runner can connect to another server right away
+    ServerShutdown,
+    /// Another connection of the runner has replaced this one
+    DuplicateConnection,
+    /// Runner is removed by an admin
+    KickedByAdmin,
+}
+
+impl CloseKind {
+    pub fn code(self) -> u16 {
+        match self {
+            CloseKind::AuthExpired => 4001,
+            CloseKind::ProtocolMismatch => 4002,
+            CloseKind::ServerShutdown => 4003,
+            CloseKind::DuplicateConnection => 4004,
+            CloseKind::KickedByAdmin => 4005,
+        }
+    }
+
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code {
+            4001 => Some(CloseKind::AuthExpired),
+            4002 => Some(CloseKind::ProtocolMismatch),
+            4003 => Some(CloseKind::ServerShutdown),
+            4004 => Some(CloseKind::DuplicateConnection),
+            4005 => Some(CloseKind::KickedByAdmin),
+            _ => None
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            CloseKind::AuthExpired => "token is expired",
+            CloseKind::ProtocolMismatch => "protocol mismatch",
+            CloseKind::ServerShutdown => "server is shutting down",
+            CloseKind::DuplicateConnection => "runner connected from another session",
+            CloseKind::KickedByAdmin => "runner is removed by an admin",
+        }
+    }
+}
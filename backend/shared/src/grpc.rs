@@ -0,0 +1,3 @@
+//! Grpc service of the runners, generated from `proto/runner.proto`.
+
+tonic::include_proto!("runner");
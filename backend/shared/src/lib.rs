@@ -1,3 +1,6 @@
+pub mod close;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod websocket_messages;
 
 #[derive(Debug)]
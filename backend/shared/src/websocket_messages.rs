@@ -3,11 +3,19 @@ use serde::{Deserialize, Serialize};
 type ModelId = i32;
 
 pub mod server {
+    use std::collections::BTreeMap;
+
     use super::{Deserialize, ModelId, Serialize};
 
     #[derive(Deserialize, Serialize)]
     pub enum SocketMessageKind {
-        RunResult
+        RunResult,
+        RunnerInfo,
+        CommandResult,
+        ClientLogs,
+        DiskPressure,
+        ValidationResult,
+        ThermalState,
     }
 
     #[derive(Deserialize, Serialize)]
@@ -26,6 +34,148 @@ pub mod server {
         pub job_id: ModelId,
         pub output: String,
         pub successful: bool,
+        // output is cut at the runner's configured limit
+        #[serde(default)]
+        pub truncated: bool,
+        // output recorded besides stdout and stderr, e.g. the serial console of the device
+        #[serde(default)]
+        pub streams: Vec<LogStream>,
+        // whether the firmware of the job is flashed, none if the job has no firmware
+        #[serde(default)]
+        pub flashed: Option<bool>,
+        // same for the resends of the result, left out by the older clients
+        #[serde(default)]
+        pub message_id: Option<String>,
+        // captured when the job starts, left out by the older clients
+        #[serde(default)]
+        pub environment: Option<Environment>,
+        // files collected during the job, e.g. the packet captures, left out by the older clients
+        #[serde(default)]
+        pub artifacts: Vec<Artifact>,
+    }
+
+    /// File collected by the runner during a job
+    #[derive(Deserialize, Serialize)]
+    pub struct Artifact {
+        // file name, e.g. eth1.pcap0.gz
+        pub name: String,
+        // base64 encoded
+        pub data: String,
+        // how the file is collected, e.g. the frequency and the sample rate of a radio recording
+        #[serde(default)]
+        pub metadata: BTreeMap<String, String>,
+    }
+
+    /// State of the runner when a job starts, so that anomalous results can be traced to environmental drift.
+    /// Details which cannot be read on the runner are left out.
+    #[derive(Default, Deserialize, Serialize)]
+    #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+    pub struct Environment {
+        pub kernel: Option<String>,
+        // cpufreq governor of each cpu, e.g. cpu0 -> performance
+        pub governors: BTreeMap<String, String>,
+        // temperature of each thermal zone in degrees Celsius, e.g. cpu-thermal -> 48.3
+        pub temperatures: BTreeMap<String, f64>,
+        // firmware version of each attached device, read with the commands configured on the runner
+        pub firmware_versions: BTreeMap<String, String>,
+        // performance settings applied for the job, if the job requests any
+        #[serde(default)]
+        pub performance: Option<PerformanceReport>,
+    }
+
+    /// Performance settings in effect while the job runs, they are read back after the requested ones are written
+    #[derive(Default, Deserialize, Serialize)]
+    #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+    pub struct PerformanceReport {
+        // cpufreq governor of each cpu
+        pub governors: BTreeMap<String, String>,
+        // lower and upper frequency limits of each cpu in kHz, they are equal if the frequency is fixed
+        pub min_frequencies_khz: BTreeMap<String, u64>,
+        pub max_frequencies_khz: BTreeMap<String, u64>,
+        // none if the node has no turbo or boost control
+        pub turbo_disabled: Option<bool>,
+        // requested settings which could not be applied
+        pub errors: Vec<String>,
+    }
+
+    /// Named output of a job
+    #[derive(Deserialize, Serialize)]
+    pub struct LogStream {
+        pub name: String,
+        pub output: String,
+        pub truncated: bool,
+    }
+
+    /// Sent by the runner right after connecting
+    #[derive(Deserialize, Serialize)]
+    pub struct RunnerInfo {
+        pub os: String,
+        pub arch: String,
+        pub client_version: String,
+        // hardware inventory, left out by the older clients or if it cannot be detected
+        #[serde(default)]
+        pub kernel: Option<String>,
+        #[serde(default)]
+        pub cpu_model: Option<String>,
+        #[serde(default)]
+        pub memory_bytes: Option<u64>,
+        // names of the devices attached to the runner, e.g. the board under test
+        #[serde(default)]
+        pub peripherals: Vec<String>,
+    }
+
+    /// Result of a runner command
+    #[derive(Deserialize, Serialize)]
+    pub struct CommandResult {
+        pub command_id: ModelId,
+        pub successful: bool,
+        pub output: String,
+    }
+
+    /// Logs of the client itself, shipped while it is requested by a `client::SetLogLevel`
+    #[derive(Deserialize, Serialize)]
+    pub struct ClientLogs {
+        pub lines: Vec<String>,
+    }
+
+    /// Sent when the free space of the runner's workspace falls below or recovers above its minimum,
+    /// runners under pressure are not given jobs
+    #[derive(Deserialize, Serialize)]
+    pub struct DiskPressure {
+        pub under_pressure: bool,
+        // free space of the workspace, in bytes
+        pub free_space: u64,
+    }
+
+    /// Sent periodically while the runner is idle, jobs of the experiments requiring a cool device wait until the
+    /// hottest zone is below their limit
+    #[derive(Deserialize, Serialize)]
+    pub struct ThermalState {
+        // temperature of each thermal zone in degrees Celsius, as in the `Environment`
+        pub temperatures: BTreeMap<String, f64>,
+    }
+
+    /// Answer of a `client::ValidateExperiment`
+    #[derive(Deserialize, Serialize)]
+    pub struct ValidationResult {
+        pub validation_id: u64,
+        pub diagnostics: Vec<Diagnostic>,
+    }
+
+    #[derive(Deserialize, Serialize)]
+    #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+    pub struct Diagnostic {
+        // e.g. syntax, dependency, hardware
+        pub check: String,
+        pub level: DiagnosticLevel,
+        pub message: String,
+    }
+
+    #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+    #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+    pub enum DiagnosticLevel {
+        Error,
+        Warning,
     }
 }
 
@@ -34,7 +184,12 @@ pub mod client {
 
     #[derive(Deserialize, Serialize)]
     pub enum SocketMessageKind {
-        RunExperiment
+        RunExperiment,
+        TokenRefresh,
+        ClientUpdate,
+        RunnerCommand,
+        SetLogLevel,
+        ValidateExperiment,
     }
 
     #[derive(Deserialize, Serialize)]
@@ -52,5 +207,139 @@ pub mod client {
     pub struct RunExperiment {
         pub job_id: ModelId,
         pub code: String,
+        // optional hooks of the runner requested for the job, the ones in its configuration always run
+        #[serde(default)]
+        pub hooks: Vec<String>,
+        #[serde(default)]
+        pub firmware: Option<Firmware>,
+        // applied before the hooks run and restored after them, left out if the experiment does not request any
+        #[serde(default)]
+        pub performance: Option<PerformanceSettings>,
+        // traffic of the runner's emulation interface is shaped during the job if it is given
+        #[serde(default)]
+        pub network: Option<NetworkEmulation>,
+        // SDR of the runner is configured before the job if it is given
+        #[serde(default)]
+        pub sdr: Option<SdrSettings>,
+    }
+
+    /// Performance settings of the node requested by the experiment, the ones which are not given are left as is
+    #[derive(Clone, Debug, Default, Deserialize, Serialize)]
+    pub struct PerformanceSettings {
+        // cpufreq governor of every cpu, e.g. performance or userspace
+        pub governor: Option<String>,
+        pub disable_turbo: bool,
+        // every cpu is pinned at this frequency, in kHz
+        pub frequency_khz: Option<u64>,
+    }
+
+    /// Image flashed to the device before the code of the job runs
+    #[derive(Deserialize, Serialize)]
+    pub struct Firmware {
+        pub name: String,
+        // base64 encoded
+        pub data: String,
+    }
+
+    /// Latency, loss and bandwidth applied with netem to the interface configured on the runner, the ones which are
+    /// not given are not emulated
+    #[derive(Clone, Debug, Default, Deserialize, Serialize)]
+    pub struct NetworkEmulation {
+        pub delay_ms: Option<u32>,
+        // variation of the delay, only applied along with it
+        pub jitter_ms: Option<u32>,
+        pub loss_percent: Option<f64>,
+        pub rate_kbit: Option<u64>,
+    }
+
+    /// Settings the SDR attached to the runner is tuned to before the job
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct SdrSettings {
+        // center frequency
+        pub frequency_hz: u64,
+        pub gain_db: f64,
+        // samples per second
+        pub sample_rate: u32,
+        // taken while the job runs and uploaded as an artifact of the job, if it is given
+        pub recording: Option<SdrRecording>,
+    }
+
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct SdrRecording {
+        pub kind: SdrRecordingKind,
+        pub duration_ms: u32,
+    }
+
+    #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum SdrRecordingKind {
+        // raw IQ samples in the format of the runner's recording tool
+        Iq,
+        // power of the frequency bins around the center frequency
+        Spectrum,
     }
-}
\ No newline at end of file
+
+    /// Replaces the runner's token, which is used for the next connections
+    #[derive(Deserialize, Serialize)]
+    pub struct TokenRefresh {
+        pub token: String,
+    }
+
+    /// Advertises a newer client release, which is applied if the runner trusts its signature
+    #[derive(Deserialize, Serialize)]
+    pub struct ClientUpdate {
+        pub version: String,
+        pub os: String,
+        pub arch: String,
+        pub url: String,
+        // hex encoded SHA-256 digest of the binary
+        pub sha256: String,
+        // base64 encoded Ed25519 signature of the manifest
+        pub signature: String,
+    }
+
+    impl ClientUpdate {
+        /// Signed part of the release. Binary is bound to its version and platform through its digest, so that
+        /// the signature of a release can not be replayed for another one.
+        pub fn manifest(&self) -> String {
+            format!("nrg-testbed-client\n{}\n{}\n{}\n{}", self.version, self.os, self.arch, self.sha256.to_lowercase())
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+    pub enum RunnerCommandKind {
+        // restarts the client process after the current job is completed
+        RestartClient,
+        // removes the leftovers of the jobs from the workspace directory
+        ClearWorkspace,
+        // collects the state of the client and its host
+        FetchDiagnostics,
+    }
+
+    /// Administrative command, answered with a `server::CommandResult`
+    #[derive(Deserialize, Serialize)]
+    pub struct RunnerCommand {
+        pub command_id: ModelId,
+        pub kind: RunnerCommandKind,
+    }
+
+    /// Changes the log filter of the client at runtime
+    #[derive(Deserialize, Serialize)]
+    pub struct SetLogLevel {
+        // filter in the RUST_LOG syntax, configured filter is restored if it is not given
+        pub level: Option<String>,
+        // client ships its logs to the server for this many seconds
+        pub ship_seconds: Option<u64>,
+    }
+
+    /// Checks whether the job would run on the runner without running it, answered with a
+    /// `server::ValidationResult`
+    #[derive(Deserialize, Serialize)]
+    pub struct ValidateExperiment {
+        pub validation_id: u64,
+        pub code: String,
+        pub hooks: Vec<String>,
+        // firmware itself is not sent, only the flashing tool is checked
+        pub has_firmware: bool,
+    }
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Exponential backoff used for reconnecting to the server. Delays are randomized by jitter so that
+/// the runners do not reconnect all at once after a server restart.
+pub struct Backoff {
+    base: Duration,
+    cap: Duration,
+    // fraction of the delay which is randomized, between 0 and 1
+    jitter: f64,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, cap: Duration, jitter: f64) -> Self {
+        Backoff {
+            base,
+            cap,
+            jitter: jitter.max(0.0).min(1.0),
+            attempt: 0,
+        }
+    }
+
+    /// Reads the parameters from `RECONNECT_BACKOFF_BASE` and `RECONNECT_BACKOFF_CAP` in seconds and
+    /// from `RECONNECT_BACKOFF_JITTER` as a fraction
+    pub fn from_env() -> Self {
+        let base = std::env::var("RECONNECT_BACKOFF_BASE")
+            .map(|base| base.parse::<f64>().expect("invalid RECONNECT_BACKOFF_BASE is provided"))
+            .unwrap_or(2.0);
+        let cap = std::env::var("RECONNECT_BACKOFF_CAP")
+            .map(|cap| cap.parse::<f64>().expect("invalid RECONNECT_BACKOFF_CAP is provided"))
+            .unwrap_or(60.0);
+        let jitter = std::env::var("RECONNECT_BACKOFF_JITTER")
+            .map(|jitter| jitter.parse::<f64>().expect("invalid RECONNECT_BACKOFF_JITTER is provided"))
+            .unwrap_or(0.5);
+
+        Backoff::new(Duration::from_secs_f64(base), Duration::from_secs_f64(cap), jitter)
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        // limit the exponent in order not to overflow
+        let delay = self.base.as_secs_f64() * 2f64.powi(self.attempt.min(32) as i32);
+        let delay = delay.min(self.cap.as_secs_f64());
+
+        self.attempt = self.attempt.saturating_add(1);
+
+        let jitter = delay * self.jitter * rand::thread_rng().gen::<f64>();
+
+        Duration::from_secs_f64(delay - jitter)
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(delay: Duration) -> f64 {
+        delay.as_secs_f64()
+    }
+
+    #[test]
+    fn doubles_the_delay_up_to_the_cap() {
+        let mut backoff = Backoff::new(Duration::from_secs(2), Duration::from_secs(60), 0.0);
+
+        let delays = (0..7).map(|_| secs(backoff.next_delay())).collect::<Vec<f64>>();
+
+        assert_eq!(delays, vec![2.0, 4.0, 8.0, 16.0, 32.0, 60.0, 60.0]);
+    }
+
+    #[test]
+    fn restarts_from_the_base_after_a_reset() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(60), 0.0);
+
+        backoff.next_delay();
+        backoff.next_delay();
+        backoff.reset();
+
+        assert_eq!(secs(backoff.next_delay()), 1.0);
+    }
+
+    #[test]
+    fn randomizes_the_delay_within_the_jitter() {
+        let mut backoff = Backoff::new(Duration::from_secs(10), Duration::from_secs(10), 0.5);
+
+        for _ in 0..100 {
+            let delay = secs(backoff.next_delay());
+
+            assert!((5.0..=10.0).contains(&delay), "delay {} is out of the jitter", delay);
+        }
+    }
+
+    #[test]
+    fn clamps_the_jitter() {
+        assert_eq!(Backoff::new(Duration::from_secs(1), Duration::from_secs(1), 2.0).jitter, 1.0);
+        assert_eq!(Backoff::new(Duration::from_secs(1), Duration::from_secs(1), -1.0).jitter, 0.0);
+    }
+
+    #[test]
+    fn does_not_overflow_after_many_attempts() {
+        let mut backoff = Backoff::new(Duration::from_secs(2), Duration::from_secs(60), 0.0);
+        backoff.attempt = u32::MAX;
+
+        assert_eq!(secs(backoff.next_delay()), 60.0);
+        assert_eq!(secs(backoff.next_delay()), 60.0);
+    }
+}
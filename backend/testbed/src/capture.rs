@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::time::SystemTime;
+
+use log::{error, info, warn};
+
+use shared::websocket_messages::server::Artifact;
+
+use crate::config::Capture;
+
+// captures are written into this directory of the job's workspace
+const CAPTURE_DIR: &str = "capture";
+const MEGABYTE: u64 = 1024 * 1024;
+
+/// tcpdump processes capturing the traffic of the configured interfaces while a job is running. Each interface is
+/// captured into a ring of files, so that at most the configured size is kept on the disk. Captures are stopped
+/// when it is dropped, in case the job fails before they are finished.
+pub struct PacketCapture {
+    dir: PathBuf,
+    processes: Vec<(String, Child)>,
+    max_size: u64,
+    // recorded in the metadata of the artifacts
+    filter: Option<String>,
+}
+
+impl PacketCapture {
+    pub fn start(capture: &Capture, job_dir: &Path) -> io::Result<PacketCapture> {
+        let dir = job_dir.join(CAPTURE_DIR);
+
+        std::fs::create_dir_all(&dir)?;
+
+        // ring of each interface is kept under the total size
+        let files = (capture.max_size_mb / capture.rotate_mb / capture.interfaces.len() as u64).max(1);
+
+        let mut packet_capture = PacketCapture {
+            dir: dir.clone(),
+            processes: Vec::with_capacity(capture.interfaces.len()),
+            max_size: capture.max_size_mb * MEGABYTE,
+            filter: capture.filter.clone(),
+        };
+
+        for interface in &capture.interfaces {
+            let mut command = std::process::Command::new("tcpdump");
+
+            command
+                .args(["-i", interface.as_str(), "-n", "-U"])
+                .args(["-w", dir.join(format!("{}.pcap", interface)).to_string_lossy().as_ref()])
+                .args(["-C", capture.rotate_mb.to_string().as_str(), "-W", files.to_string().as_str()]);
+
+            if let Some(filter) = &capture.filter {
+                command.arg(filter);
+            }
+
+            info!("capturing packets of {}", interface);
+
+            // started captures are stopped by the drop if one of them fails
+            let child = command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()?;
+
+            packet_capture.processes.push((interface.clone(), child));
+        }
+
+        Ok(packet_capture)
+    }
+
+    /// Stops the captures and compresses the captured files. Newest files are kept if they exceed the maximum
+    /// size once they are compressed.
+    pub fn finish(mut self) -> io::Result<Vec<Artifact>> {
+        self.stop();
+
+        let mut files = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+            .map(|entry| {
+                let modified = entry.metadata().and_then(|metadata| metadata.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+                (modified, entry.path())
+            })
+            .collect::<Vec<(SystemTime, PathBuf)>>();
+
+        files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+
+        let mut artifacts = Vec::new();
+        let mut size = 0;
+
+        for (_, path) in files {
+            let data = compress(&path)?;
+
+            if size + data.len() as u64 > self.max_size {
+                warn!("{} is left out since the captures exceed {} bytes", path.display(), self.max_size);
+                continue;
+            }
+
+            size += data.len() as u64;
+
+            let mut metadata = BTreeMap::new();
+            metadata.insert("kind".to_string(), "pcap".to_string());
+
+            if let Some(filter) = &self.filter {
+                metadata.insert("filter".to_string(), filter.clone());
+            }
+
+            artifacts.push(Artifact {
+                name: format!("{}.gz", path.file_name().unwrap_or_default().to_string_lossy()),
+                data: base64::encode(data),
+                metadata,
+            });
+        }
+
+        Ok(artifacts)
+    }
+
+    fn stop(&mut self) {
+        for (interface, mut child) in self.processes.drain(..) {
+            // tcpdump flushes its buffers on SIGTERM, killing it may leave a truncated packet at the end
+            let terminated = std::process::Command::new("kill")
+                .args(["-TERM", child.id().to_string().as_str()])
+                .status()
+                .is_ok_and(|status| status.success());
+
+            if !terminated {
+                let _ = child.kill();
+            }
+
+            match child.wait() {
+                Ok(status) => info!("capture of {} is stopped, {}", interface, status),
+                Err(e) => error!("waiting for the capture of {} is failed, {:?}", interface, e)
+            }
+        }
+    }
+}
+
+impl Drop for PacketCapture {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Compresses the file with gzip in place and returns the compressed content
+pub fn compress(path: &Path) -> io::Result<Vec<u8>> {
+    let status = std::process::Command::new("gzip")
+        .args(["-n", "-f"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .status()?;
+
+    if !status.success() {
+        return Err(io::Error::other(format!("compressing {} is failed, {}", path.display(), status)));
+    }
+
+    let mut compressed = path.as_os_str().to_owned();
+    compressed.push(".gz");
+
+    std::fs::read(compressed)
+}
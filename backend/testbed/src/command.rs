@@ -0,0 +1,66 @@
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::status::Status;
+
+#[derive(Serialize)]
+struct Diagnostics<'a> {
+    version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    pid: u32,
+    status: serde_json::Value,
+    config: &'a Config,
+    workspace_entries: Option<usize>,
+    docker_version: String,
+}
+
+/// Removes everything under the workspace directory, returns the number of removed entries.
+/// Must not be called while a job is running.
+pub fn clear_workspace(workspace_dir: &str) -> std::io::Result<usize> {
+    let entries = match std::fs::read_dir(workspace_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e)
+    };
+
+    let mut removed = 0;
+
+    for entry in entries {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            std::fs::remove_dir_all(&path)?;
+        } else {
+            std::fs::remove_file(&path)?;
+        }
+
+        removed += 1;
+    }
+
+    Ok(removed)
+}
+
+/// Collects the state of the client and its host as json, secrets are not included
+pub fn diagnostics(config: &Config, status: &Status) -> String {
+    let workspace_entries = std::fs::read_dir(config.workspace_dir.as_str())
+        .ok()
+        .map(|entries| entries.count());
+
+    let docker_version = match std::process::Command::new("docker").args(["version", "--format", "{{.Server.Version}}"]).output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        Ok(output) => String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        Err(e) => e.to_string()
+    };
+
+    serde_json::to_string_pretty(&Diagnostics {
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        pid: std::process::id(),
+        status: status.to_value(),
+        config,
+        workspace_entries,
+        docker_version,
+    }).unwrap()
+}
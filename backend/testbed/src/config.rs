@@ -0,0 +1,703 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::str::FromStr;
+
+use clap::{App, Arg, ArgMatches};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+
+use crate::serial;
+use crate::status;
+
+const DEFAULT_WORKSPACE_DIR: &str = "/tmp/testbed";
+const DEFAULT_DOCKER_IMAGE: &str = "python:rc-alpine";
+const DEFAULT_MAX_OUTPUT_SIZE: usize = 1024 * 1024;
+const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 60;
+const DEFAULT_MIN_FREE_SPACE_MB: u64 = 1024;
+const DEFAULT_BAUD_RATE: u32 = 115200;
+const DEFAULT_WASM_RUNTIME: &str = "wasmtime";
+const DEFAULT_CAPTURE_ROTATE_MB: u64 = 1;
+const DEFAULT_CAPTURE_SIZE_MB: u64 = 4;
+const DEFAULT_SDR_RECORDING_SIZE_MB: u64 = 4;
+// artifacts are sent to the server along with the result, they should fit in its frame size with the rest of it
+const MAX_ARTIFACTS_SIZE_MB: u64 = 8;
+// filter is sent in the metadata of the captures, whose values are limited by the server
+const MAX_CAPTURE_FILTER_LENGTH: usize = 256;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    // jobs run in a python container
+    Docker,
+    // jobs run directly on the host with python3, resource limits are not applied
+    Process,
+    // jobs run in a WASI python interpreter under wasmtime, they can only reach the job directory
+    Wasm,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "docker" => Ok(Backend::Docker),
+            "process" => Ok(Backend::Process),
+            "wasm" => Ok(Backend::Wasm),
+            _ => Err(format!("unknown execution backend {}, expected docker, process or wasm", s))
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    // maximum number of bytes captured from each of stdout and stderr of a job
+    pub max_output_size: usize,
+    // passed to docker as --memory, e.g. 512m
+    pub memory: Option<String>,
+    // passed to docker as --cpus, e.g. 1.5
+    pub cpus: Option<String>,
+    // total size of the job directories in the workspace, in megabytes
+    pub disk_quota_mb: Option<u64>,
+    // passed to wasmtime as the fuel of the module, roughly the number of instructions it can execute
+    pub fuel: Option<u64>,
+    // wasm jobs are interrupted after running this many seconds
+    pub timeout: Option<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_output_size: DEFAULT_MAX_OUTPUT_SIZE,
+            memory: None,
+            cpus: None,
+            disk_quota_mb: None,
+            fuel: None,
+            timeout: None,
+        }
+    }
+}
+
+/// Sandbox of the wasm backend. WASI modules have no access to the host except the directories
+/// preopened for them, and no network access since it is not granted to them.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Wasm {
+    #[serde(default = "default_wasm_runtime")]
+    pub runtime: String,
+    // python interpreter compiled to WASI, e.g. python.wasm of the CPython WASI builds
+    pub module: String,
+    // directories preopened in addition to the job directory as host::guest, e.g. the standard library
+    #[serde(default)]
+    pub dirs: Vec<String>,
+    // linear memory of the module is capped at this many bytes
+    pub max_memory: Option<u64>,
+}
+
+fn default_wasm_runtime() -> String {
+    DEFAULT_WASM_RUNTIME.to_string()
+}
+
+/// Serial port of the device under test, its output is captured while a job is running
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Serial {
+    // e.g. /dev/ttyUSB0
+    pub port: String,
+    #[serde(default = "default_baud_rate")]
+    pub baud_rate: u32,
+}
+
+fn default_baud_rate() -> u32 {
+    DEFAULT_BAUD_RATE
+}
+
+/// Firmware of a job is written to a file and this placeholder in the flash command is replaced with its path
+pub const FIRMWARE_PLACEHOLDER: &str = "{firmware}";
+
+/// Tool flashing the firmware of the jobs to the device, e.g. openocd, esptool or dfu-util
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Flash {
+    // program and its arguments, one of them contains the firmware placeholder
+    pub command: Vec<String>,
+}
+
+/// Step of a hook run before or after a job
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum HookAction {
+    // drives the sysfs gpio line as an output, e.g. { gpio = { pin = 17, value = 0 } }
+    Gpio { pin: u64, value: u8 },
+    // runs the relay tool, e.g. { relay = { command = ["usbrelay", "BITFT_1=0"] } }
+    Relay { command: Vec<String> },
+    // waits between the steps, e.g. while the device is powered off
+    Sleep { millis: u64 },
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hook {
+    pub pre_run: Vec<HookAction>,
+    // post run actions are also run if the job fails
+    pub post_run: Vec<HookAction>,
+}
+
+/// Traffic of the interfaces captured with tcpdump during every job, the compressed captures are uploaded as the
+/// artifacts of the job
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Capture {
+    pub interfaces: Vec<String>,
+    // pcap filter expression, e.g. "udp port 5683"
+    #[serde(default)]
+    pub filter: Option<String>,
+    // captures are rotated at this size, the oldest files are dropped once the maximum size is reached
+    #[serde(default = "default_capture_rotate_mb")]
+    pub rotate_mb: u64,
+    // total size of the captures of a job, before and after the compression
+    #[serde(default = "default_capture_size_mb")]
+    pub max_size_mb: u64,
+}
+
+fn default_capture_rotate_mb() -> u64 {
+    DEFAULT_CAPTURE_ROTATE_MB
+}
+
+fn default_capture_size_mb() -> u64 {
+    DEFAULT_CAPTURE_SIZE_MB
+}
+
+/// Software defined radio attached to the node, e.g. an RTL-SDR. It is tuned with the settings requested by the
+/// experiment before its jobs, and the recordings taken during the jobs are uploaded as their artifacts. In the
+/// commands {frequency_hz}, {gain_db} and {sample_rate} are replaced with the settings, {start_hz} and {end_hz} with
+/// the edges of the band as wide as the sample rate, {samples} and {duration_s} with the length of the recording and
+/// {output} with the file the recording is written into.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sdr {
+    // program and its arguments applying the settings to the radio, e.g. a SoapySDR script. Radio is assumed to be
+    // tuned by the recording commands if it is not given
+    #[serde(default)]
+    pub configure: Option<Vec<String>>,
+    // records the IQ samples into the output, e.g. ["rtl_sdr", "-f", "{frequency_hz}", "-n", "{samples}", "{output}"]
+    #[serde(default)]
+    pub iq_command: Option<Vec<String>>,
+    // writes a spectrum snapshot into the output, e.g. ["rtl_power", "-f", "{start_hz}:{end_hz}:10k", "-1", "{output}"]
+    #[serde(default)]
+    pub spectrum_command: Option<Vec<String>>,
+    // size of a compressed recording, it is left out of the artifacts if it is larger
+    #[serde(default = "default_sdr_recording_size_mb")]
+    pub max_size_mb: u64,
+}
+
+fn default_sdr_recording_size_mb() -> u64 {
+    DEFAULT_SDR_RECORDING_SIZE_MB
+}
+
+/// Scripts run around every job, e.g. resetting the radio, clearing the caches or fixing the cpu frequency. Their
+/// output is recorded as the setup and teardown streams of the job.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Conditions {
+    // program and its arguments of each script, job does not start if one of them fails
+    pub setup: Vec<Vec<String>>,
+    // run even if the job or its setup fails
+    pub teardown: Vec<Vec<String>>,
+}
+
+/// Configuration of the testbed client. Values are read from the config file first, then
+/// overridden by the environment variables and lastly by the command line arguments.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // servers are tried in order
+    pub server_urls: Vec<String>,
+    // secrets are left out of the diagnostics
+    #[serde(skip_serializing)]
+    pub access_token: Option<String>,
+    pub access_token_file: Option<String>,
+    pub claim_url: Option<String>,
+    #[serde(skip_serializing)]
+    pub claim_code: Option<String>,
+    // jobs are written under this directory before they are executed
+    pub workspace_dir: String,
+    // seconds to keep the directories of the finished jobs, they are removed right away if it is 0
+    pub workspace_retention: u64,
+    // runner reports disk pressure to the server when the free space of the workspace is below this
+    pub min_free_space_mb: u64,
+    pub backend: Backend,
+    pub docker_image: String,
+    // required by the wasm backend
+    pub wasm: Option<Wasm>,
+    pub limits: Limits,
+    pub serial: Option<Serial>,
+    pub flash: Option<Flash>,
+    pub capture: Option<Capture>,
+    pub sdr: Option<Sdr>,
+    // run around every job
+    pub hooks: Hook,
+    // run around the jobs which request them by name
+    pub optional_hooks: BTreeMap<String, Hook>,
+    // run after the pre run hooks and before the post run hooks of every job
+    pub conditions: Conditions,
+    pub log_level: Option<String>,
+    // status is served on the unix socket and the local http address for diagnosing the node
+    pub status_socket: Option<String>,
+    pub status_address: Option<String>,
+    // seconds to wait for the current job on shutdown before cancelling it
+    pub shutdown_timeout: u64,
+    // base64 encoded Ed25519 public key, updates advertised by the server are applied only if it is given
+    pub update_public_key: Option<String>,
+    // names of the devices attached to the node, reported to the server along with the hardware inventory
+    pub peripherals: Vec<String>,
+    // device -> program and its arguments printing the firmware version of the device, recorded with each job
+    pub firmware_version_commands: BTreeMap<String, Vec<String>>,
+    // performance settings requested by the experiments, e.g. the cpufreq governor, are applied during their jobs.
+    // Client should be able to write into the cpufreq files of the sysfs
+    pub performance_control: bool,
+    // network emulation requested by the experiments is applied to this interface with tc netem during their jobs
+    pub netem_interface: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            server_urls: Vec::new(),
+            access_token: None,
+            access_token_file: None,
+            claim_url: None,
+            claim_code: None,
+            workspace_dir: DEFAULT_WORKSPACE_DIR.to_string(),
+            workspace_retention: 0,
+            min_free_space_mb: DEFAULT_MIN_FREE_SPACE_MB,
+            backend: Backend::Docker,
+            docker_image: DEFAULT_DOCKER_IMAGE.to_string(),
+            wasm: None,
+            limits: Limits::default(),
+            serial: None,
+            flash: None,
+            capture: None,
+            sdr: None,
+            hooks: Hook::default(),
+            optional_hooks: BTreeMap::new(),
+            conditions: Conditions::default(),
+            log_level: None,
+            status_socket: None,
+            status_address: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            update_public_key: None,
+            peripherals: Vec::new(),
+            firmware_version_commands: BTreeMap::new(),
+            performance_control: false,
+            netem_interface: None,
+        }
+    }
+}
+
+impl Config {
+    /// Builds the configuration from the config file, environment and command line arguments.
+    /// Returns the configuration and whether only its validation is requested.
+    pub fn load() -> Result<(Self, bool), String> {
+        let matches = app().get_matches();
+
+        let mut config = match matches.value_of("config").map(String::from).or_else(|| std::env::var("CONFIG_FILE").ok()) {
+            Some(file) => {
+                let content = std::fs::read_to_string(&file)
+                    .map_err(|e| format!("reading {} is failed, {}", file, e))?;
+
+                toml::from_str::<Config>(content.as_str())
+                    .map_err(|e| format!("parsing {} is failed, {}", file, e))?
+            }
+            None => Config::default()
+        };
+
+        config.apply_env()?;
+        config.apply_args(&matches)?;
+
+        Ok((config, matches.is_present("check-config")))
+    }
+
+    fn apply_env(&mut self) -> Result<(), String> {
+        if let Ok(server_urls) = std::env::var("SERVER_URL") {
+            // multiple servers can be given as a comma separated list
+            self.server_urls = server_urls
+                .split(',')
+                .map(|url| url.trim())
+                .filter(|url| !url.is_empty())
+                .map(|url| url.to_string())
+                .collect();
+        }
+
+        if let Ok(access_token) = std::env::var("BACKEND_ACCESS_TOKEN") {
+            self.access_token = Some(access_token);
+        }
+
+        if let Ok(access_token_file) = std::env::var("ACCESS_TOKEN_FILE") {
+            self.access_token_file = Some(access_token_file);
+        }
+
+        if let Ok(claim_url) = std::env::var("CLAIM_URL") {
+            self.claim_url = Some(claim_url);
+        }
+
+        if let Ok(claim_code) = std::env::var("CLAIM_CODE") {
+            self.claim_code = Some(claim_code);
+        }
+
+        if let Ok(workspace_dir) = std::env::var("WORKSPACE_DIR") {
+            self.workspace_dir = workspace_dir;
+        }
+
+        if let Ok(backend) = std::env::var("EXECUTION_BACKEND") {
+            self.backend = backend.parse()?;
+        }
+
+        if let Ok(max_output_size) = std::env::var("MAX_OUTPUT_SIZE") {
+            self.limits.max_output_size = max_output_size.parse()
+                .map_err(|_| "Invalid MAX_OUTPUT_SIZE is provided, please give a positive integer".to_string())?;
+        }
+
+        if let Ok(disk_quota_mb) = std::env::var("DISK_QUOTA_MB") {
+            self.limits.disk_quota_mb = Some(disk_quota_mb.parse()
+                .map_err(|_| "Invalid DISK_QUOTA_MB is provided, please give a positive integer".to_string())?);
+        }
+
+        if let Ok(workspace_retention) = std::env::var("WORKSPACE_RETENTION") {
+            self.workspace_retention = workspace_retention.parse()
+                .map_err(|_| "Invalid WORKSPACE_RETENTION is provided, please give a positive integer".to_string())?;
+        }
+
+        if let Ok(min_free_space_mb) = std::env::var("MIN_FREE_SPACE_MB") {
+            self.min_free_space_mb = min_free_space_mb.parse()
+                .map_err(|_| "Invalid MIN_FREE_SPACE_MB is provided, please give a positive integer".to_string())?;
+        }
+
+        if let Ok(module) = std::env::var("WASM_MODULE") {
+            match &mut self.wasm {
+                Some(wasm) => wasm.module = module,
+                None => self.wasm = Some(Wasm { runtime: default_wasm_runtime(), module, dirs: Vec::new(), max_memory: None })
+            }
+        }
+
+        if let Ok(port) = std::env::var("SERIAL_PORT") {
+            self.serial = Some(Serial { port, baud_rate: DEFAULT_BAUD_RATE });
+        }
+
+        if let Ok(baud_rate) = std::env::var("SERIAL_BAUD_RATE") {
+            let baud_rate = baud_rate.parse()
+                .map_err(|_| "Invalid SERIAL_BAUD_RATE is provided, please give a positive integer".to_string())?;
+
+            match &mut self.serial {
+                Some(serial) => serial.baud_rate = baud_rate,
+                None => return Err("SERIAL_BAUD_RATE is provided without a serial port".to_string())
+            }
+        }
+
+        if let Ok(shutdown_timeout) = std::env::var("SHUTDOWN_TIMEOUT") {
+            self.shutdown_timeout = shutdown_timeout.parse()
+                .map_err(|_| "Invalid SHUTDOWN_TIMEOUT is provided, please give a positive integer".to_string())?;
+        }
+
+        if let Ok(update_public_key) = std::env::var("UPDATE_PUBLIC_KEY") {
+            self.update_public_key = Some(update_public_key);
+        }
+
+        if let Ok(peripherals) = std::env::var("PERIPHERALS") {
+            self.peripherals = peripherals
+                .split(',')
+                .map(|peripheral| peripheral.trim())
+                .filter(|peripheral| !peripheral.is_empty())
+                .map(|peripheral| peripheral.to_string())
+                .collect();
+        }
+
+        if let Ok(status_socket) = std::env::var("STATUS_SOCKET") {
+            self.status_socket = Some(status_socket);
+        }
+
+        if let Ok(status_address) = std::env::var("STATUS_ADDRESS") {
+            self.status_address = Some(status_address);
+        }
+
+        Ok(())
+    }
+
+    fn apply_args(&mut self, matches: &ArgMatches) -> Result<(), String> {
+        if let Some(server_urls) = matches.values_of("server-url") {
+            self.server_urls = server_urls.map(String::from).collect();
+        }
+
+        if let Some(access_token) = matches.value_of("token") {
+            self.access_token = Some(access_token.to_string());
+        }
+
+        if let Some(workspace_dir) = matches.value_of("workspace") {
+            self.workspace_dir = workspace_dir.to_string();
+        }
+
+        if let Some(backend) = matches.value_of("backend") {
+            self.backend = backend.parse()?;
+        }
+
+        if let Some(log_level) = matches.value_of("log-level") {
+            self.log_level = Some(log_level.to_string());
+        }
+
+        if let Some(status_socket) = matches.value_of("status-socket") {
+            self.status_socket = Some(status_socket.to_string());
+        }
+
+        if let Some(status_address) = matches.value_of("status-address") {
+            self.status_address = Some(status_address.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// `has_client_certificate` tells whether the runner can authenticate with its client certificate
+    pub fn validate(&self, has_client_certificate: bool) -> Result<(), String> {
+        if self.server_urls.is_empty() {
+            return Err("no server url is provided".to_string());
+        }
+
+        // token file is written by the refreshes and the claims, it is only a credential once it holds a token
+        let has_token_file = self.access_token_file.as_ref()
+            .is_some_and(|file| std::fs::read_to_string(file).is_ok_and(|token| !token.trim().is_empty()));
+
+        if self.access_token.is_none() && self.claim_code.is_none() && !has_token_file && !has_client_certificate {
+            return match &self.access_token_file {
+                Some(file) => Err(format!("access token file {} is missing or empty, and neither an access token, a claim code nor a client certificate is provided", file)),
+                None => Err("neither an access token, a claim code nor a client certificate is provided".to_string())
+            };
+        }
+
+        if self.claim_code.is_some() && self.claim_url.is_none() {
+            return Err("claim code is provided without a claim url".to_string());
+        }
+
+        if self.workspace_dir.is_empty() {
+            return Err("workspace directory is empty".to_string());
+        }
+
+        if self.backend == Backend::Wasm && self.wasm.is_none() {
+            return Err("wasm backend is selected without a wasm module".to_string());
+        }
+
+        if self.limits.max_output_size == 0 {
+            return Err("max output size must be positive".to_string());
+        }
+
+        if let Some(serial) = &self.serial {
+            if serial::speed(serial.baud_rate).is_none() {
+                return Err(format!("unsupported baud rate {}", serial.baud_rate));
+            }
+        }
+
+        if let Some(flash) = &self.flash {
+            if flash.command.is_empty() || !flash.command.iter().any(|arg| arg.contains(FIRMWARE_PLACEHOLDER)) {
+                return Err(format!("flash command must contain the firmware placeholder {}", FIRMWARE_PLACEHOLDER));
+            }
+        }
+
+        if let Some(capture) = &self.capture {
+            if capture.interfaces.is_empty() || capture.interfaces.iter().any(|interface| interface.is_empty()) {
+                return Err("capture interfaces are not given".to_string());
+            }
+
+            if capture.rotate_mb == 0 || capture.rotate_mb > capture.max_size_mb || capture.max_size_mb > MAX_ARTIFACTS_SIZE_MB {
+                return Err(format!("capture rotation size must be positive and below the maximum size, which can be at most {} mb", MAX_ARTIFACTS_SIZE_MB));
+            }
+
+            if capture.filter.as_ref().is_some_and(|filter| filter.len() > MAX_CAPTURE_FILTER_LENGTH) {
+                return Err(format!("capture filter can be at most {} characters", MAX_CAPTURE_FILTER_LENGTH));
+            }
+        }
+
+        if let Some(sdr) = &self.sdr {
+            let commands = [&sdr.configure, &sdr.iq_command, &sdr.spectrum_command];
+
+            if commands.iter().any(|command| command.as_ref().is_some_and(|command| command.is_empty())) {
+                return Err("sdr command is empty".to_string());
+            }
+
+            let captures_size_mb = self.capture.as_ref().map_or(0, |capture| capture.max_size_mb);
+
+            if sdr.max_size_mb == 0 || sdr.max_size_mb + captures_size_mb > MAX_ARTIFACTS_SIZE_MB {
+                return Err(format!("sdr recording size must be positive and at most {} mb along with the captures", MAX_ARTIFACTS_SIZE_MB));
+            }
+        }
+
+        let actions = self.hooks.pre_run.iter()
+            .chain(self.hooks.post_run.iter())
+            .chain(self.optional_hooks.values().flat_map(|hook| hook.pre_run.iter().chain(hook.post_run.iter())));
+
+        for action in actions {
+            match action {
+                HookAction::Gpio { value, .. } if *value > 1 => return Err(format!("gpio value must be 0 or 1, {} is given", value)),
+                HookAction::Relay { command } if command.is_empty() => return Err("relay command is empty".to_string()),
+                _ => {}
+            }
+        }
+
+        if self.conditions.setup.iter().chain(self.conditions.teardown.iter()).any(|script| script.is_empty()) {
+            return Err("setup or teardown script is empty".to_string());
+        }
+
+        if self.netem_interface.as_deref() == Some("") {
+            return Err("netem interface is empty".to_string());
+        }
+
+        if let Some(device) = self.firmware_version_commands.iter().find(|(_, command)| command.is_empty()).map(|(device, _)| device) {
+            return Err(format!("firmware version command of {} is empty", device));
+        }
+
+        if let Some(update_public_key) = &self.update_public_key {
+            // Ed25519 public keys are 32 bytes
+            if base64::decode(update_public_key).map_or(true, |key| key.len() != 32) {
+                return Err("invalid update public key".to_string());
+            }
+        }
+
+        if let Some(status_address) = &self.status_address {
+            status::parse_address(status_address)
+                .ok_or_else(|| format!("invalid status address {}", status_address))?;
+        }
+
+        if let Some(log_level) = &self.log_level {
+            LevelFilter::from_str(log_level)
+                .map_err(|_| format!("invalid log level {}", log_level))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the access token into the file, which is only readable by the owner since the token is a credential.
+/// Permissions of an existing file are restricted as well.
+pub fn write_access_token(file: &str, token: &str) -> std::io::Result<()> {
+    let mut f = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(file)?;
+
+    f.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+
+    f.write_all(token.as_bytes())
+}
+
+fn app() -> App<'static, 'static> {
+    App::new("testbed")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Runs the experiments received from the testbed server")
+        .arg(Arg::with_name("config")
+            .short("c")
+            .long("config")
+            .value_name("FILE")
+            .help("TOML config file, CONFIG_FILE is used if not given")
+            .takes_value(true))
+        .arg(Arg::with_name("server-url")
+            .long("server-url")
+            .value_name("URL")
+            .help("Server url, can be given multiple times for failover")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("token")
+            .long("token")
+            .value_name("TOKEN")
+            .help("Access token of the runner")
+            .takes_value(true))
+        .arg(Arg::with_name("workspace")
+            .long("workspace")
+            .value_name("DIR")
+            .help("Directory where the jobs are written")
+            .takes_value(true))
+        .arg(Arg::with_name("backend")
+            .long("backend")
+            .value_name("BACKEND")
+            .help("Execution backend of the jobs")
+            .possible_values(&["docker", "process"])
+            .takes_value(true))
+        .arg(Arg::with_name("log-level")
+            .long("log-level")
+            .value_name("LEVEL")
+            .help("Log level, overrides RUST_LOG")
+            .takes_value(true))
+        .arg(Arg::with_name("status-socket")
+            .long("status-socket")
+            .value_name("PATH")
+            .help("Unix socket serving the status of the client")
+            .takes_value(true))
+        .arg(Arg::with_name("status-address")
+            .long("status-address")
+            .value_name("ADDRESS")
+            .help("Local address serving the status of the client over http, e.g. 127.0.0.1:8041, a port alone is bound on 127.0.0.1")
+            .takes_value(true))
+        .arg(Arg::with_name("check-config")
+            .long("check-config")
+            .help("Validates the configuration and exits"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(access_token_file: &str) -> Config {
+        Config {
+            server_urls: vec![String::from("https://testbed.example")],
+            access_token_file: Some(access_token_file.to_string()),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_missing_token_file() {
+        let file = std::env::temp_dir().join(format!("nrg-testbed-missing-{}", std::process::id()));
+
+        assert!(config(file.to_str().unwrap()).validate(false).is_err());
+        // runners with a client certificate do not need a token
+        assert!(config(file.to_str().unwrap()).validate(true).is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_token_file() {
+        let file = std::env::temp_dir().join(format!("nrg-testbed-empty-{}", std::process::id()));
+        std::fs::write(&file, "\n").unwrap();
+
+        let result = config(file.to_str().unwrap()).validate(false);
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_validate_accepts_token_file() {
+        let file = std::env::temp_dir().join(format!("nrg-testbed-token-{}", std::process::id()));
+        std::fs::write(&file, "token\n").unwrap();
+
+        let result = config(file.to_str().unwrap()).validate(false);
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_write_access_token_restricts_permissions() {
+        let file = std::env::temp_dir().join(format!("nrg-testbed-written-{}", std::process::id()));
+        std::fs::write(&file, "old token").unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_access_token(file.to_str().unwrap(), "token").unwrap();
+
+        let content = std::fs::read_to_string(&file).unwrap();
+        let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!("token", content);
+        assert_eq!(0o600, mode & 0o777);
+    }
+}
@@ -1,125 +1,666 @@
-use std::cmp::min;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 
 use actix::{Actor, Context, StreamHandler, WrapFuture};
 use actix::clock::Duration;
 use actix::io::SinkWrite;
 use actix::prelude::*;
 use actix_codec::Framed;
-use awc::{BoxedSocket, Client};
-use awc::error::{WsClientError, WsProtocolError};
-use awc::ws::{Codec, Frame, Message};
-use futures::stream::{SplitSink, StreamExt};
-use log::{error, info};
-
+use actix_http::ws::Item;
+use awc::BoxedSocket;
+use awc::error::{ConnectError, SendRequestError, WsClientError, WsProtocolError};
+use awc::ws::{CloseCode, CloseReason, Codec, Frame, Message};
+use futures::{Sink, Stream};
+use futures::stream::StreamExt;
+use log::{error, info, warn};
+use serde::Serialize;
+
+use shared::close::CloseKind;
 use shared::SocketErrorKind;
 use shared::websocket_messages::{client, server};
 
-use crate::messages::{RunMessage, RunResultMessage, UpdateExecutorMessage};
-
-type Write = SinkWrite<Message, SplitSink<Framed<BoxedSocket, Codec>, Message>>;
-
-const MAX_TIMING: usize = 5;
-
-const TIMINGS: [u8; MAX_TIMING] = [
-    // 0, 15, 30, 75, 120
-    0, 2, 4, 6, 8
-];
+use crate::backoff::Backoff;
+use crate::command;
+use crate::config::{self, Config};
+use crate::logger;
+use crate::executor::{self, CurrentJob};
+use crate::grpc;
+use crate::inventory;
+use crate::messages::{DiskPressureMessage, DrainMessage, RunMessage, RunResultMessage, ShutdownMessage, ThermalStateMessage, UpdateExecutorMessage};
+use crate::status::Status;
+use crate::systemd;
+use crate::transport::Transport;
+use crate::update;
+use crate::validation;
+use crate::write_buffer::{TrackedSink, WriteBuffer};
+use crate::ModelId;
+
+// server is joined either over the websocket or over grpc, both are used through the websocket messages and frames
+pub type FrameSink = Pin<Box<dyn Sink<Message, Error=WsProtocolError>>>;
+pub type FrameStream = Pin<Box<dyn Stream<Item=Result<Frame, WsProtocolError>>>>;
+
+type Write = SinkWrite<Message, TrackedSink<FrameSink>>;
+
+const LOG_SHIPPING_INTERVAL: Duration = Duration::from_secs(5);
+// runs carry the base64 encoded firmware of the job, if there is one. Fragmented messages are capped with the same size
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+// log shipping pauses while more than this many bytes are waiting to be sent
+const WRITE_BUFFER_HIGH_WATERMARK: usize = 1024 * 1024;
+// server is considered stuck if this many bytes are waiting to be sent, connection is dropped then
+const MAX_WRITE_BUFFER: usize = 64 * 1024 * 1024;
+// results which could not be sent before shutdown are kept in this file under the workspace
+const PENDING_RESULTS_FILE: &str = "pending_results.json";
 
 pub struct Connection {
-    server_url: String,
-    access_token: String,
+    // servers are tried in order, connection fails over to the next one
+    server_urls: Vec<String>,
+    current_server_index: usize,
+    // runner authenticates with its client certificate if there is no token
+    access_token: Option<String>,
+    // refreshed tokens are persisted into this file, if it is given
+    token_file: Option<String>,
+    transport: Transport,
     sink: Option<Write>,
-    // this is the delay until we try connecting again
-    current_timing_index: usize,
+    write_buffer: WriteBuffer,
+    stream: Option<SpawnHandle>,
+    // fragments of the message being received, if the server has fragmented it
+    fragments: Option<Vec<u8>>,
+    // reason of the server for closing the connection, decides how to reconnect
+    close_kind: Option<CloseKind>,
+    backoff: Backoff,
+    status: Status,
     executor: Option<Recipient<RunMessage>>,
+    drain: Option<Recipient<DrainMessage>>,
+    current_job: CurrentJob,
+    // results which could not be sent while disconnected
+    pending_results: Vec<RunResultMessage>,
+    shutting_down: bool,
+    // current job is cancelled if it does not complete in this duration during shutdown
+    shutdown_timeout: Duration,
+    // updates advertised by the server are only applied if they are signed with this key
+    update_public_key: Option<Vec<u8>>,
+    updating: bool,
+    // last disk pressure reported by the executor, sent again on every connect
+    disk_pressure: Option<DiskPressureMessage>,
+    // last temperatures reported by the executor, sent again on every connect
+    thermal_state: Option<ThermalStateMessage>,
+    // kept for the diagnostics and the workspace commands
+    config: Config,
 }
 
 impl Connection {
-    pub fn new(server_url: String, access_token: String) -> Self {
+    pub fn new(config: &Config, access_token: Option<String>, transport: Transport, backoff: Backoff, status: Status) -> Self {
+        // key is validated with the configuration
+        let update_public_key = config.update_public_key.as_ref()
+            .map(|key| base64::decode(key).unwrap());
+
         Connection {
-            server_url,
+            server_urls: config.server_urls.clone(),
+            current_server_index: 0,
             access_token,
+            token_file: config.access_token_file.clone(),
+            transport,
             sink: None,
-            current_timing_index: 0,
+            write_buffer: WriteBuffer::default(),
+            stream: None,
+            fragments: None,
+            close_kind: None,
+            backoff,
+            status,
             executor: None,
+            drain: None,
+            current_job: CurrentJob::default(),
+            pending_results: Vec::new(),
+            shutting_down: false,
+            shutdown_timeout: Duration::from_secs(config.shutdown_timeout),
+            update_public_key,
+            updating: false,
+            disk_pressure: None,
+            thermal_state: None,
+            config: config.clone(),
         }
     }
 
     fn handle_frame(&mut self, frame: Frame, ctx: &mut <Self as Actor>::Context) -> Result<(), SocketErrorKind> {
         match frame {
-            Frame::Ping(_) | Frame::Pong(_) => {
+            Frame::Ping(bytes) => {
+                // server disconnects the runners which do not answer its heartbeats
+                self.write(Message::Pong(bytes), ctx);
+            }
+            Frame::Pong(_) => {
                 //update hb
             }
-            Frame::Text(bytes) => {
-                let text = String::from_utf8(bytes.to_vec())
-                    .map_err(|_| SocketErrorKind::InvalidMessage)?;
+            // messages are JSON encoded, either in text or binary frames
+            Frame::Text(bytes) | Frame::Binary(bytes) => self.handle_message(&bytes, ctx)?,
+            Frame::Continuation(item) => {
+                if let Some(message) = self.reassemble(item)? {
+                    self.handle_message(&message, ctx)?;
+                }
+            }
+            Frame::Close(reason) => {
+                self.close_kind = reason.and_then(|reason| match reason.code {
+                    CloseCode::Other(code) => CloseKind::from_code(code),
+                    _ => None
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Collects the fragments of a message, returns the message once its last fragment is received.
+    fn reassemble(&mut self, item: Item) -> Result<Option<Vec<u8>>, SocketErrorKind> {
+        let (first, bytes, last) = match item {
+            Item::FirstText(bytes) | Item::FirstBinary(bytes) => (true, bytes, false),
+            Item::Continue(bytes) => (false, bytes, false),
+            Item::Last(bytes) => (false, bytes, true),
+        };
 
-                let text = text.as_str();
+        // a message cannot start before the previous one is completed, nor continue without a start
+        if first == self.fragments.is_some() {
+            self.fragments = None;
+            return Err(SocketErrorKind::InvalidMessage);
+        }
 
-                let base = serde_json::from_str::<'_, client::BaseMessage>(text)
+        let fragments = self.fragments.get_or_insert_with(Vec::new);
+
+        if fragments.len() + bytes.len() > MAX_FRAME_SIZE {
+            error!("fragmented message exceeds {} bytes, dropping it", MAX_FRAME_SIZE);
+            self.fragments = None;
+            return Err(SocketErrorKind::InvalidMessage);
+        }
+
+        fragments.extend_from_slice(&bytes);
+
+        if last {
+            return Ok(self.fragments.take());
+        }
+
+        Ok(None)
+    }
+
+    fn handle_message(&mut self, bytes: &[u8], ctx: &mut <Self as Actor>::Context) -> Result<(), SocketErrorKind> {
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+        let base = serde_json::from_str::<'_, client::BaseMessage>(text)
+            .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+        match base.kind {
+            client::SocketMessageKind::RunExperiment => {
+                let run_experiment = serde_json::from_str::<'_, client::SocketMessage<client::RunExperiment>>(text)
                     .map_err(|_| SocketErrorKind::InvalidMessage)?;
 
-                match base.kind {
-                    client::SocketMessageKind::RunExperiment => {
-                        let run_experiment = serde_json::from_str::<'_, client::SocketMessage<client::RunExperiment>>(text)
-                            .map_err(|_| SocketErrorKind::InvalidMessage)?;
-
-                        info!("received run from server, id {}", run_experiment.data.job_id);
-
-                        if let Some(executor) = &self.executor {
-                            let msg = RunMessage {
-                                job_id: run_experiment.data.job_id,
-                                code: run_experiment.data.code,
-                            };
-                            let addr = executor.clone();
-
-                            async move {
-                                if let Err(e) = addr.send(msg)
-                                    .await {
-                                    error!("sending run message to executor is failed: {:?}", e);
-                                }
-                            }
-                                .into_actor(self)
-                                .spawn(ctx);
+                info!("received run from server, id {}", run_experiment.data.job_id);
+
+                if self.shutting_down {
+                    warn!("client is shutting down, run {} is ignored", run_experiment.data.job_id);
+                    return Ok(());
+                }
+
+                if let Some(executor) = &self.executor {
+                    let msg = RunMessage {
+                        job_id: run_experiment.data.job_id,
+                        code: run_experiment.data.code,
+                        hooks: run_experiment.data.hooks,
+                        firmware: run_experiment.data.firmware,
+                        performance: run_experiment.data.performance,
+                        network: run_experiment.data.network,
+                        sdr: run_experiment.data.sdr,
+                    };
+                    let addr = executor.clone();
+
+                    async move {
+                        if let Err(e) = addr.send(msg)
+                            .await {
+                            error!("sending run message to executor is failed: {:?}", e);
                         }
                     }
+                        .into_actor(self)
+                        .spawn(ctx);
+                }
+            }
+            client::SocketMessageKind::TokenRefresh => {
+                let token_refresh = serde_json::from_str::<'_, client::SocketMessage<client::TokenRefresh>>(text)
+                    .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+                info!("received refreshed token from server");
+
+                if let Some(token_file) = &self.token_file {
+                    if let Err(e) = config::write_access_token(token_file, token_refresh.data.token.as_str()) {
+                        error!("persisting refreshed token is failed: {:?}", e);
+                    }
+                }
+
+                self.access_token = Some(token_refresh.data.token);
+            }
+            client::SocketMessageKind::ClientUpdate => {
+                let client_update = serde_json::from_str::<'_, client::SocketMessage<client::ClientUpdate>>(text)
+                    .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+                self.handle_client_update(client_update.data, ctx);
+            }
+            client::SocketMessageKind::RunnerCommand => {
+                let runner_command = serde_json::from_str::<'_, client::SocketMessage<client::RunnerCommand>>(text)
+                    .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+                self.handle_command(runner_command.data, ctx);
+            }
+            client::SocketMessageKind::ValidateExperiment => {
+                let validate = serde_json::from_str::<'_, client::SocketMessage<client::ValidateExperiment>>(text)
+                    .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+                self.handle_validation(validate.data, ctx);
+            }
+            client::SocketMessageKind::SetLogLevel => {
+                let set_log_level = serde_json::from_str::<'_, client::SocketMessage<client::SetLogLevel>>(text)
+                    .map_err(|_| SocketErrorKind::InvalidMessage)?;
+
+                info!("log level is changed by server to {:?}", set_log_level.data.level);
+
+                logger::set_filter(set_log_level.data.level.as_deref());
+
+                if let Some(seconds) = set_log_level.data.ship_seconds {
+                    logger::ship_for(Duration::from_secs(seconds));
                 }
             }
-            _ => {}
         }
         Ok(())
     }
 
-    async fn connect(server_url: String, access_token: String) -> Result<Framed<BoxedSocket, Codec>, WsClientError> {
-        Client::new()
-            .ws(format!("{}?token={}", server_url, access_token))
+    fn handle_client_update(&mut self, update: client::ClientUpdate, ctx: &mut <Self as Actor>::Context) {
+        let public_key = match &self.update_public_key {
+            Some(public_key) => public_key.clone(),
+            None => {
+                warn!("updates are not enabled, client update to {} is ignored", update.version);
+                return;
+            }
+        };
+
+        if self.updating || self.shutting_down || update.version == env!("CARGO_PKG_VERSION") {
+            return;
+        }
+
+        info!("updating client to {}", update.version);
+
+        self.updating = true;
+
+        let transport = self.transport.clone();
+
+        async move { update::stage(&transport, public_key.as_slice(), &update).await }
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(()) => {
+                        info!("update is staged, restarting after the current job is completed");
+                        ctx.notify(ShutdownMessage { cancel_job: false });
+                    }
+                    Err(e) => {
+                        error!("updating client is failed, {}", e);
+                        act.status.record_error(format!("updating client is failed, {}", e));
+                        act.updating = false;
+                    }
+                }
+
+                fut::ready(())
+            })
+            .spawn(ctx);
+    }
+
+    fn handle_command(&mut self, command: client::RunnerCommand, ctx: &mut <Self as Actor>::Context) {
+        info!("received command {:?} from server, id {}", command.kind, command.command_id);
+
+        let command_id = command.command_id;
+
+        let result = match command.kind {
+            client::RunnerCommandKind::RestartClient => {
+                update::request_restart();
+                ctx.notify(ShutdownMessage { cancel_job: false });
+
+                Ok("restarting after the current job is completed".to_string())
+            }
+            client::RunnerCommandKind::ClearWorkspace if self.current_job.is_running() => {
+                Err("workspace can not be cleared while a job is running".to_string())
+            }
+            client::RunnerCommandKind::ClearWorkspace => {
+                let workspace_dir = self.config.workspace_dir.clone();
+
+                actix_threadpool::run(move || command::clear_workspace(workspace_dir.as_str()))
+                    .into_actor(self)
+                    .then(move |res, act, ctx| {
+                        let result = res
+                            .map(|removed| format!("{} entries are removed", removed))
+                            .map_err(|e| format!("clearing workspace is failed, {:?}", e));
+
+                        act.send_command_result(command_id, result, ctx);
+
+                        fut::ready(())
+                    })
+                    .spawn(ctx);
+
+                return;
+            }
+            client::RunnerCommandKind::FetchDiagnostics => {
+                let config = self.config.clone();
+                let status = self.status.clone();
+
+                actix_threadpool::run(move || -> Result<String, ()> { Ok(command::diagnostics(&config, &status)) })
+                    .into_actor(self)
+                    .then(move |res, act, ctx| {
+                        let result = res.map_err(|e| format!("collecting diagnostics is failed, {:?}", e));
+
+                        act.send_command_result(command_id, result, ctx);
+
+                        fut::ready(())
+                    })
+                    .spawn(ctx);
+
+                return;
+            }
+        };
+
+        self.send_command_result(command_id, result, ctx);
+    }
+
+    /// Validation runs besides the current job, it does not wait for the executor
+    fn handle_validation(&mut self, request: client::ValidateExperiment, ctx: &mut <Self as Actor>::Context) {
+        info!("received validation from server, id {}", request.validation_id);
+
+        let validation_id = request.validation_id;
+        let config = self.config.clone();
+
+        actix_threadpool::run(move || -> Result<_, ()> { Ok(validation::validate(&config, &request)) })
+            .into_actor(self)
+            .then(move |res, act, ctx| {
+                let diagnostics = res.unwrap_or_else(|e| {
+                    error!("validation is failed, {:?}", e);
+                    vec![validation::error("backend", "validation is failed on the runner".to_string())]
+                });
+
+                act.send(server::SocketMessageKind::ValidationResult, server::ValidationResult { validation_id, diagnostics }, ctx);
+
+                fut::ready(())
+            })
+            .spawn(ctx);
+    }
+
+    fn send_command_result(&mut self, command_id: ModelId, result: Result<String, String>, ctx: &mut <Self as Actor>::Context) {
+        let (successful, output) = match result {
+            Ok(output) => (true, output),
+            Err(output) => (false, output)
+        };
+
+        // server fails the commands which are not answered before the disconnect
+        self.send(server::SocketMessageKind::CommandResult, server::CommandResult { command_id, successful, output }, ctx);
+    }
+
+    fn ship_logs(&mut self, ctx: &mut <Self as Actor>::Context) {
+        // logs are kept buffered while disconnected or while the server is slow to take the messages
+        if self.sink.is_none() || self.write_buffer.queued() > WRITE_BUFFER_HIGH_WATERMARK {
+            return;
+        }
+
+        let lines = logger::take_shipped();
+
+        if !lines.is_empty() {
+            self.send(server::SocketMessageKind::ClientLogs, server::ClientLogs { lines }, ctx);
+        }
+    }
+
+    fn send_runner_info(&mut self, ctx: &mut <Self as Actor>::Context) {
+        self.send(server::SocketMessageKind::RunnerInfo, inventory::runner_info(&self.config), ctx);
+    }
+
+    fn send_disk_pressure(&mut self, ctx: &mut <Self as Actor>::Context) {
+        if let Some(disk_pressure) = &self.disk_pressure {
+            let disk_pressure = server::DiskPressure {
+                under_pressure: disk_pressure.under_pressure,
+                free_space: disk_pressure.free_space,
+            };
+
+            self.send(server::SocketMessageKind::DiskPressure, disk_pressure, ctx);
+        }
+    }
+
+    fn send_thermal_state(&mut self, ctx: &mut <Self as Actor>::Context) {
+        if let Some(thermal_state) = &self.thermal_state {
+            let thermal_state = server::ThermalState { temperatures: thermal_state.temperatures.clone() };
+
+            self.send(server::SocketMessageKind::ThermalState, thermal_state, ctx);
+        }
+    }
+
+    fn send_result(&mut self, msg: RunResultMessage, ctx: &mut <Self as Actor>::Context) {
+        // results are sent again after reconnecting
+        if !self.writable(ctx) {
+            self.pending_results.push(msg);
+            return;
+        }
+
+        let result = run_result(msg);
+
+        // connection may be closing even though it was writable, the result is kept for the next connection then
+        if !self.send(server::SocketMessageKind::RunResult, &result, ctx) {
+            self.pending_results.push(run_result_message(result));
+        }
+    }
+
+    fn pending_results_file(&self) -> PathBuf {
+        Path::new(self.config.workspace_dir.as_str()).join(PENDING_RESULTS_FILE)
+    }
+
+    /// Results which could not be sent before the last shutdown are sent on the first connect. Server applies
+    /// a result only once, hence sending it again is harmless.
+    fn load_pending_results(&mut self) {
+        let file = self.pending_results_file();
+
+        let content = match std::fs::read(&file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => {
+                error!("could not read the pending results, {}", e);
+                return;
+            }
+        };
+
+        match serde_json::from_slice::<Vec<server::RunResult>>(content.as_slice()) {
+            Ok(results) => {
+                info!("{} results of the last run are pending", results.len());
+                self.pending_results.extend(results.into_iter().map(run_result_message));
+            }
+            Err(e) => error!("pending results are malformed, they are dropped, {}", e)
+        }
+
+        if let Err(e) = std::fs::remove_file(&file) {
+            error!("could not remove the pending results, {}", e);
+        }
+    }
+
+    /// Keeps the results which could not be sent, so that they are sent after the restart
+    fn persist_pending_results(&mut self) {
+        if self.pending_results.is_empty() {
+            return;
+        }
+
+        let results = std::mem::take(&mut self.pending_results)
+            .into_iter()
+            .map(run_result)
+            .collect::<Vec<server::RunResult>>();
+
+        let persisted = std::fs::create_dir_all(self.config.workspace_dir.as_str())
+            .and_then(|_| std::fs::write(self.pending_results_file(), serde_json::to_vec(&results).unwrap()));
+
+        match persisted {
+            Ok(_) => info!("{} results could not be sent before shutdown, they are kept for the next run", results.len()),
+            Err(e) => error!("{} results could not be sent before shutdown and could not be kept, {}", results.len(), e)
+        }
+    }
+
+    fn send<T: Serialize>(&mut self, kind: server::SocketMessageKind, data: T, ctx: &mut <Self as Actor>::Context) -> bool {
+        self.write(Message::Text(serde_json::to_string(&server::SocketMessage { kind, data }).unwrap()), ctx)
+    }
+
+    /// Queues the message for the server, returns false if it is not queued
+    fn write(&mut self, message: Message, ctx: &mut <Self as Actor>::Context) -> bool {
+        if !self.writable(ctx) {
+            return false;
+        }
+
+        let buffer = self.write_buffer.clone();
+
+        match &mut self.sink {
+            Some(sink) => {
+                buffer.add(&message);
+
+                match sink.write(message) {
+                    None => true,
+                    Some(message) => {
+                        warn!("connection is closing, message is dropped");
+                        buffer.drain(&message);
+                        false
+                    }
+                }
+            }
+            None => false
+        }
+    }
+
+    /// Checks whether there is a connection which keeps up with the messages. Connection is dropped if
+    /// the server does not take the queued messages, instead of buffering them without a limit.
+    fn writable(&mut self, ctx: &mut <Self as Actor>::Context) -> bool {
+        if self.sink.is_none() {
+            return false;
+        }
+
+        let queued = self.write_buffer.queued();
+
+        if queued <= MAX_WRITE_BUFFER {
+            return true;
+        }
+
+        error!("server does not keep up with the messages, {} bytes are queued, dropping the connection", queued);
+        self.status.record_error(format!("connection is dropped since the server does not keep up, {} bytes are queued", queued));
+
+        // close frame would wait behind the queued messages, drop the connection right away
+        if let Some(sink) = self.sink.take() {
+            ctx.cancel_future(sink.handle());
+        }
+
+        if let Some(stream) = self.stream.take() {
+            ctx.cancel_future(stream);
+        }
+
+        self.disconnected(ctx);
+
+        false
+    }
+
+    fn close(&mut self, ctx: &mut <Self as Actor>::Context) {
+        self.persist_pending_results();
+
+        match &mut self.sink {
+            Some(sink) => {
+                sink.write(Message::Close(Some(CloseReason::from(CloseCode::Away))));
+                sink.close();
+
+                // server closes the connection after the close frame, give it some time
+                ctx.run_later(Duration::from_secs(5), |_, _| System::current().stop());
+            }
+            None => System::current().stop()
+        }
+    }
+
+    /// Joins the server over the websocket, or over grpc if the url has a grpc scheme
+    async fn connect(transport: Transport, server_url: String, access_token: Option<String>) -> Result<(FrameSink, FrameStream), String> {
+        if grpc::is_grpc_url(server_url.as_str()) {
+            return grpc::connect(transport, server_url, access_token).await;
+        }
+
+        let (sink, stream) = Self::connect_websocket(transport, server_url, access_token)
+            .await
+            .map_err(|e| format!("{:?}", e))?
+            .split();
+
+        Ok((Box::pin(sink), Box::pin(stream)))
+    }
+
+    async fn connect_websocket(transport: Transport, server_url: String, access_token: Option<String>) -> Result<Framed<BoxedSocket, Codec>, WsClientError> {
+        let (url, address) = transport.resolve(server_url.as_str())
+            .await
+            .map_err(|e| {
+                error!("resolving server url is failed, {}", e);
+                WsClientError::SendRequest(SendRequestError::Connect(ConnectError::Unresolved))
+            })?;
+
+        let mut request = transport.client().ws(url)
+            .max_frame_size(MAX_FRAME_SIZE);
+
+        if let Some(address) = address {
+            request = request.address(address);
+        }
+
+        // token is not put in the url, so that it does not end up in the logs of the proxies
+        if let Some(access_token) = access_token {
+            request = request.bearer_auth(access_token);
+        }
+
+        request
             .connect()
             .await
             .map(|f| f.1)
     }
 
     fn try_connect(act: &mut Connection, ctx: &mut <Self as Actor>::Context) {
-        Self::connect(act.server_url.clone(), act.access_token.clone())
+        if act.shutting_down {
+            return;
+        }
+
+        let server_url = act.server_urls[act.current_server_index].clone();
+
+        info!("Connecting to server {}", server_url);
+
+        Self::connect(act.transport.clone(), server_url.clone(), act.access_token.clone())
             .into_actor(act)
-            .then(move |framed, act, ctx| {
-                match framed {
-                    Ok(framed) => {
+            .then(move |connection, act, ctx| {
+                match connection {
+                    Ok((sink, stream)) => {
                         info!("Connected to server");
 
-                        let (sink, stream) = framed.split();
-                        Self::add_stream(stream, ctx);
-                        act.sink = Some(SinkWrite::new(sink, ctx));
-                        // we have connected now, reset timing
-                        act.current_timing_index = 0;
+                        act.status.set_connected(server_url.clone());
+                        systemd::notify(format!("STATUS=Connected to {}", server_url).as_str());
+
+                        act.stream = Some(Self::add_stream(stream, ctx));
+                        act.write_buffer = WriteBuffer::default();
+                        act.fragments = None;
+                        act.close_kind = None;
+                        act.sink = Some(SinkWrite::new(TrackedSink::new(sink, act.write_buffer.clone()), ctx));
+                        // we have connected now, reset backoff
+                        act.backoff.reset();
+
+                        act.send_runner_info(ctx);
+                        act.send_disk_pressure(ctx);
+                        act.send_thermal_state(ctx);
+
+                        for result in std::mem::take(&mut act.pending_results) {
+                            act.send_result(result, ctx);
+                        }
                     }
                     Err(e) => {
-                        error!("{:?}", e);
+                        error!("{}", e);
 
-                        act.current_timing_index = min(act.current_timing_index + 1, MAX_TIMING - 1);
+                        act.status.record_error(format!("could not connect to {}, {}", server_url, e));
 
-                        info!("Could not connect to server, will retry in {} seconds", TIMINGS[act.current_timing_index]);
+                        act.current_server_index = (act.current_server_index + 1) % act.server_urls.len();
 
-                        ctx.run_later(Duration::from_secs(TIMINGS[act.current_timing_index] as u64), |act, ctx| {
+                        // back off only after all the servers are tried
+                        let delay = if act.current_server_index == 0 {
+                            act.backoff.next_delay()
+                        } else {
+                            Duration::from_secs(0)
+                        };
+
+                        info!("Could not connect to server, will retry in {:.1} seconds", delay.as_secs_f64());
+
+                        ctx.run_later(delay, |act, ctx| {
                             Self::try_connect(act, ctx);
                         });
                     }
@@ -129,12 +670,64 @@ impl Connection {
             })
             .spawn(ctx);
     }
+
+    /// Reconnects unless the client is shutting down or the server has closed the connection for a
+    /// reason which reconnecting does not help
+    fn disconnected(&mut self, ctx: &mut <Self as Actor>::Context) {
+        self.sink = None;
+        self.status.set_disconnected();
+
+        if self.shutting_down {
+            System::current().stop();
+            return;
+        }
+
+        match self.close_kind.take() {
+            Some(kind @ CloseKind::AuthExpired) |
+            Some(kind @ CloseKind::ProtocolMismatch) |
+            Some(kind @ CloseKind::DuplicateConnection) => {
+                error!("Server closed the connection, {}, not reconnecting until the client is restarted", kind.description());
+                systemd::notify(format!("STATUS=Halted, {}", kind.description()).as_str());
+                self.status.set_halted(kind.description().to_string());
+            }
+            Some(CloseKind::ServerShutdown) => {
+                info!("Server is shutting down, connecting to the next server");
+                systemd::notify("STATUS=Disconnected");
+                self.current_server_index = (self.current_server_index + 1) % self.server_urls.len();
+                Self::try_connect(self, ctx);
+            }
+            Some(CloseKind::KickedByAdmin) => {
+                let delay = self.backoff.next_delay();
+
+                warn!("Server closed the connection, {}, will retry in {:.1} seconds", CloseKind::KickedByAdmin.description(), delay.as_secs_f64());
+                systemd::notify("STATUS=Disconnected");
+                self.status.record_error(CloseKind::KickedByAdmin.description().to_string());
+
+                ctx.run_later(delay, |act, ctx| {
+                    Self::try_connect(act, ctx);
+                });
+            }
+            None => {
+                info!("Server disconnected, trying to reconnect");
+                systemd::notify("STATUS=Disconnected");
+                Self::try_connect(self, ctx);
+            }
+        }
+    }
 }
 
 impl Actor for Connection {
     type Context = Context<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
+        if let Some(interval) = systemd::watchdog_interval() {
+            ctx.run_interval(interval, |_, _| systemd::notify("WATCHDOG=1"));
+        }
+
+        ctx.run_interval(LOG_SHIPPING_INTERVAL, |act, ctx| act.ship_logs(ctx));
+
+        self.load_pending_results();
+
         Self::try_connect(self, ctx);
     }
 
@@ -154,9 +747,8 @@ impl StreamHandler<Result<Frame, WsProtocolError>> for Connection {
     }
 
     fn finished(&mut self, ctx: &mut Context<Self>) {
-        info!("Server disconnected, trying to reconnect");
-        self.sink = None;
-        Self::try_connect(self, ctx);
+        self.stream = None;
+        self.disconnected(ctx);
     }
 }
 
@@ -165,24 +757,105 @@ impl Handler<UpdateExecutorMessage> for Connection {
 
     fn handle(&mut self, msg: UpdateExecutorMessage, _: &mut Self::Context) {
         self.executor = Some(msg.executor);
+        self.drain = Some(msg.drain);
+        self.current_job = msg.current_job;
     }
 }
 
 impl Handler<RunResultMessage> for Connection {
     type Result = ();
 
-    fn handle(&mut self, msg: RunResultMessage, _: &mut Self::Context) {
-        if let Some(sink) = &mut self.sink {
-            sink.write(Message::Text(serde_json::to_string(&server::SocketMessage {
-                kind: server::SocketMessageKind::RunResult,
-                data: server::RunResult {
-                    job_id: msg.job_id,
-                    output: msg.output,
-                    successful: msg.successful,
-                },
-            }).unwrap()));
+    fn handle(&mut self, msg: RunResultMessage, ctx: &mut Self::Context) {
+        self.send_result(msg, ctx);
+    }
+}
+
+impl Handler<DiskPressureMessage> for Connection {
+    type Result = ();
+
+    fn handle(&mut self, msg: DiskPressureMessage, ctx: &mut Self::Context) {
+        self.disk_pressure = Some(msg);
+        self.send_disk_pressure(ctx);
+    }
+}
+
+impl Handler<ThermalStateMessage> for Connection {
+    type Result = ();
+
+    fn handle(&mut self, msg: ThermalStateMessage, ctx: &mut Self::Context) {
+        self.thermal_state = Some(msg);
+        self.send_thermal_state(ctx);
+    }
+}
+
+impl Handler<ShutdownMessage> for Connection {
+    type Result = ();
+
+    fn handle(&mut self, msg: ShutdownMessage, ctx: &mut Self::Context) {
+        if self.shutting_down {
+            return;
+        }
+
+        info!("Shutting down, waiting for the current job to complete");
+
+        self.shutting_down = true;
+        systemd::notify("STOPPING=1");
+
+        if msg.cancel_job {
+            ctx.run_later(self.shutdown_timeout, |act, _| {
+                if act.current_job.cancel() {
+                    warn!("current job did not complete in time, it is cancelled");
+                }
+            });
+        }
+
+        match self.drain.clone() {
+            Some(drain) => {
+                drain.send(DrainMessage)
+                    .into_actor(self)
+                    .then(|res, act, ctx| {
+                        if let Err(e) = res {
+                            error!("draining executor is failed, {:?}", e);
+                        }
+
+                        act.close(ctx);
+
+                        fut::ready(())
+                    })
+                    .wait(ctx);
+            }
+            None => self.close(ctx)
         }
     }
 }
 
 impl actix::io::WriteHandler<WsProtocolError> for Connection {}
+
+fn run_result(msg: RunResultMessage) -> server::RunResult {
+    server::RunResult {
+        job_id: msg.job_id,
+        output: msg.output,
+        successful: msg.successful,
+        truncated: msg.truncated,
+        streams: msg.streams,
+        flashed: msg.flashed,
+        message_id: Some(msg.message_id),
+        environment: msg.environment,
+        artifacts: msg.artifacts,
+    }
+}
+
+fn run_result_message(result: server::RunResult) -> RunResultMessage {
+    RunResultMessage {
+        job_id: result.job_id,
+        output: result.output,
+        successful: result.successful,
+        truncated: result.truncated,
+        streams: result.streams,
+        flashed: result.flashed,
+        // results are persisted with their message id, an id is generated just in case
+        message_id: result.message_id.unwrap_or_else(executor::result_message_id),
+        environment: result.environment,
+        artifacts: result.artifacts,
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Stdio;
+
+use log::warn;
+
+use shared::websocket_messages::server;
+
+use crate::inventory;
+
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+const THERMAL_DIR: &str = "/sys/class/thermal";
+// firmware versions longer than this are cut, tools printing their banners are not expected
+const MAX_VERSION_LENGTH: usize = 255;
+
+/// Captures the state of the node at the start of a job. Devices whose version commands fail are left out.
+pub fn snapshot(firmware_commands: &BTreeMap<String, Vec<String>>) -> server::Environment {
+    server::Environment {
+        kernel: inventory::kernel(),
+        governors: governors(),
+        temperatures: temperatures(),
+        firmware_versions: firmware_commands.iter()
+            .filter_map(|(device, command)| firmware_version(device, command).map(|version| (device.clone(), version)))
+            .collect(),
+        // filled by the executor once the settings requested by the job are applied
+        performance: None,
+    }
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let content = content.trim();
+
+    if content.is_empty() {
+        return None;
+    }
+
+    Some(content.to_string())
+}
+
+fn governors() -> BTreeMap<String, String> {
+    let entries = match std::fs::read_dir(CPU_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return BTreeMap::new()
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+
+            // cpufreq and cpuidle directories are next to the cpus
+            if !name.strip_prefix("cpu")?.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+
+            let governor = read_trimmed(&entry.path().join("cpufreq/scaling_governor"))?;
+
+            Some((name, governor))
+        })
+        .collect()
+}
+
+/// Temperatures of the thermal zones in degrees Celsius, empty if the node does not expose any
+pub fn temperatures() -> BTreeMap<String, f64> {
+    let entries = match std::fs::read_dir(THERMAL_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return BTreeMap::new()
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("thermal_zone"))
+        .filter_map(|entry| {
+            // zones are named after their sensors if they report one, e.g. cpu-thermal
+            let name = read_trimmed(&entry.path().join("type"))
+                .unwrap_or_else(|| entry.file_name().to_string_lossy().into_owned());
+
+            // in millidegrees Celsius
+            let millidegrees = read_trimmed(&entry.path().join("temp"))?.parse::<i64>().ok()?;
+
+            Some((name, millidegrees as f64 / 1000.0))
+        })
+        .collect()
+}
+
+/// First line the command prints is taken as the version
+fn firmware_version(device: &str, command: &[String]) -> Option<String> {
+    let output = std::process::Command::new(command.first()?)
+        .args(&command[1..])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            warn!("firmware version command of {} is failed, status {:?}", device, output.status);
+            return None;
+        }
+        Err(e) => {
+            warn!("firmware version command of {} could not be run, {:?}", device, e);
+            return None;
+        }
+    };
+
+    let version = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())?
+        .chars()
+        .take(MAX_VERSION_LENGTH)
+        .collect::<String>();
+
+    Some(version)
+}
@@ -1,99 +1,802 @@
-use std::io::Write;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use actix::prelude::*;
-use log::{error, info};
+use log::{error, info, warn};
 
+use shared::websocket_messages::client::{Firmware, NetworkEmulation, SdrSettings};
+use shared::websocket_messages::server::{Artifact, LogStream, PerformanceReport};
+
+use crate::capture::PacketCapture;
+use crate::config::{Backend, Capture, Conditions, Config, Flash, Hook, HookAction, Limits, Sdr, Serial, Wasm, FIRMWARE_PLACEHOLDER};
+use crate::environment;
+use crate::hooks;
 use crate::connection::Connection;
-use crate::messages::{RunMessage, RunResultMessage};
+use crate::messages::{DiskPressureMessage, DrainMessage, RunMessage, RunResultMessage, ThermalStateMessage};
+use crate::netem;
+use crate::performance;
+use crate::sdr::{self, Recording};
+use crate::serial::SerialCapture;
+use crate::status::Status;
+use crate::workspace::Workspace;
 use crate::ModelId;
 
+const TRUNCATION_MARKER: &str = "\n[output truncated]\n";
+const SERIAL_STREAM: &str = "serial";
+const FLASH_STREAM: &str = "flash";
+const SETUP_STREAM: &str = "setup";
+const TEARDOWN_STREAM: &str = "teardown";
+// firmware is written into this directory of the job's workspace
+const FIRMWARE_DIR: &str = "firmware";
+const DISK_PRESSURE_INTERVAL: Duration = Duration::from_secs(60);
+// server holds the jobs requiring a cool device back until a report below their limit arrives
+const THERMAL_STATE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Process of the running job, shared so that the job can be cancelled from outside of the executor thread
+#[derive(Clone, Default)]
+pub struct CurrentJob {
+    pid: Arc<Mutex<Option<u32>>>,
+}
+
+impl CurrentJob {
+    fn set(&self, pid: Option<u32>) {
+        *self.pid.lock().unwrap() = pid;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.pid.lock().unwrap().is_some()
+    }
+
+    /// Terminates the running job, if any. Docker forwards the signal to the container.
+    pub fn cancel(&self) -> bool {
+        let pid = match *self.pid.lock().unwrap() {
+            Some(pid) => pid,
+            None => return false
+        };
+
+        match std::process::Command::new("kill").args(["-TERM", pid.to_string().as_str()]).status() {
+            Ok(status) => status.success(),
+            Err(e) => {
+                error!("could not cancel the job, {:?}", e);
+                false
+            }
+        }
+    }
+}
+
 pub struct Executor {
-    connection: Addr<Connection>
+    connection: Addr<Connection>,
+    workspace: Workspace,
+    backend: Backend,
+    docker_image: String,
+    wasm: Option<Wasm>,
+    limits: Limits,
+    // serial console of the device under test, captured during each job
+    serial: Option<Serial>,
+    // flashes the firmware of the jobs which have one
+    flash: Option<Flash>,
+    // traffic of the interfaces captured during each job
+    capture: Option<Capture>,
+    // tuned and recorded during the jobs requesting it
+    sdr: Option<Sdr>,
+    hooks: Hook,
+    optional_hooks: BTreeMap<String, Hook>,
+    conditions: Conditions,
+    // recorded in the environment snapshot of each job
+    firmware_version_commands: BTreeMap<String, Vec<String>>,
+    // whether the performance settings requested by the jobs are applied
+    performance_control: bool,
+    netem_interface: Option<String>,
+    status: Status,
+    current_job: CurrentJob,
+    // last disk pressure reported to the connection
+    under_pressure: bool,
+}
+
+struct Output {
+    stdout: String,
+    stderr: String,
+    truncated: bool,
+    streams: Vec<LogStream>,
+    flashed: Option<bool>,
+    artifacts: Vec<Artifact>,
+}
+
+struct Captured {
+    stdout: Vec<u8>,
+    stdout_truncated: bool,
+    stderr: Vec<u8>,
+    stderr_truncated: bool,
+    status: ExitStatus,
 }
 
 impl Executor {
-    pub fn new(connection: Addr<Connection>) -> Self {
+    pub fn new(connection: Addr<Connection>, workspace: Workspace, config: &Config, status: Status) -> Self {
         Executor {
-            connection
+            connection,
+            workspace,
+            backend: config.backend,
+            docker_image: config.docker_image.clone(),
+            wasm: config.wasm.clone(),
+            limits: config.limits.clone(),
+            serial: config.serial.clone(),
+            flash: config.flash.clone(),
+            capture: config.capture.clone(),
+            sdr: config.sdr.clone(),
+            hooks: config.hooks.clone(),
+            optional_hooks: config.optional_hooks.clone(),
+            conditions: config.conditions.clone(),
+            firmware_version_commands: config.firmware_version_commands.clone(),
+            performance_control: config.performance_control,
+            netem_interface: config.netem_interface.clone(),
+            status,
+            current_job: CurrentJob::default(),
+            under_pressure: false,
+        }
+    }
+
+    /// Reports the changes of the disk pressure to the connection
+    fn check_disk_pressure(&mut self) {
+        let free_space = match self.workspace.free_space() {
+            Ok(free_space) => free_space,
+            Err(e) => {
+                error!("checking free space is failed, {:?}", e);
+                return;
+            }
+        };
+
+        let under_pressure = self.workspace.is_under_pressure(free_space);
+
+        if under_pressure != self.under_pressure {
+            warn!("disk pressure is changed to {}, free space {} bytes", under_pressure, free_space);
+
+            self.under_pressure = under_pressure;
+            self.connection.do_send(DiskPressureMessage { under_pressure, free_space });
+        }
+    }
+
+    /// Reports the temperatures of the node to the connection. Jobs block the executor, hence the temperatures are
+    /// only reported between the jobs.
+    fn report_thermal_state(&self) {
+        let temperatures = environment::temperatures();
+
+        if !temperatures.is_empty() {
+            self.connection.do_send(ThermalStateMessage { temperatures });
+        }
+    }
+
+    pub fn current_job(&self) -> CurrentJob {
+        self.current_job.clone()
+    }
+
+    fn command(&self, dir: &Path) -> std::process::Command {
+        match self.backend {
+            Backend::Docker => {
+                let mut command = std::process::Command::new("/usr/bin/docker");
+
+                command
+                    .arg("run")
+                    .arg("--rm")
+                    .args(&["--volume", format!("{}:/usr/local/scripts/", dir.display()).as_str()]);
+
+                if let Some(memory) = &self.limits.memory {
+                    command.args(&["--memory", memory.as_str()]);
+                }
+
+                if let Some(cpus) = &self.limits.cpus {
+                    command.args(&["--cpus", cpus.as_str()]);
+                }
+
+                command
+                    .arg(self.docker_image.as_str())
+                    .args(&["python", "/usr/local/scripts/job.py"]);
+
+                command
+            }
+            Backend::Process => {
+                let mut command = std::process::Command::new("python3");
+
+                command
+                    .arg("job.py")
+                    .current_dir(dir);
+
+                command
+            }
+            // presence is checked while validating the configuration
+            Backend::Wasm => wasm_command(self.wasm.as_ref().unwrap(), &self.limits, dir)
+        }
+    }
+
+    /// Actions to run before and after the job, the optional hooks follow the ones which always run
+    fn hook_actions(&self, requested: &[String]) -> Result<(Vec<HookAction>, Vec<HookAction>), Error> {
+        let mut pre_run = self.hooks.pre_run.clone();
+        let mut post_run = self.hooks.post_run.clone();
+
+        for name in requested {
+            let hook = self.optional_hooks.get(name)
+                .ok_or_else(|| Error::UnknownHook(name.clone()))?;
+
+            pre_run.extend(hook.pre_run.iter().cloned());
+            post_run.extend(hook.post_run.iter().cloned());
+        }
+
+        Ok((pre_run, post_run))
+    }
+
+    fn handle_execution(&self, job_id: ModelId, code: String, hooks: &[String], firmware: Option<Firmware>, network: Option<NetworkEmulation>,
+                        sdr_settings: Option<SdrSettings>) -> Result<Output, Error> {
+        let (pre_run, post_run) = self.hook_actions(hooks)?;
+
+        if firmware.is_some() && self.flash.is_none() {
+            return Err(Error::FlashNotConfigured);
+        }
+
+        // results of the protocol experiments are meaningless without the emulation, hence the job is not run
+        let netem_interface = match (&network, &self.netem_interface) {
+            (Some(_), None) => return Err(Error::NetemNotConfigured),
+            (Some(_), Some(interface)) => Some(interface.as_str()),
+            (None, _) => None
+        };
+
+        if let Some(settings) = &sdr_settings {
+            let configured = self.sdr.as_ref().is_some_and(|sdr| {
+                settings.recording.as_ref().is_none_or(|recording| sdr::recording_command(sdr, recording.kind).is_some())
+            });
+
+            if !configured {
+                return Err(Error::SdrNotConfigured);
+            }
+        }
+
+        let dir = self.workspace.prepare(job_id)
+            .map_err(|e| Error::IO(e))?;
+
+        // port is opened before the hooks so that the boot output of the device is not missed
+        let serial = match &self.serial {
+            Some(serial) => Some(SerialCapture::start(serial, self.limits.max_output_size).map_err(|e| Error::Serial(e))?),
+            None => None
+        };
+
+        // captures are stopped when they are dropped, if the job fails before they are finished
+        let capture = match &self.capture {
+            Some(capture) => Some(PacketCapture::start(capture, &dir).map_err(Error::PacketCapture)?),
+            None => None
+        };
+
+        let result = hooks::run(&pre_run)
+            .map_err(|e| Error::Hook("pre-run", e))
+            .and_then(|_| match (netem_interface, &network) {
+                (Some(interface), Some(network)) => netem::apply(interface, network).map_err(Error::Netem),
+                _ => Ok(())
+            })
+            .and_then(|_| self.start_sdr(&dir, sdr_settings.as_ref()))
+            .and_then(|recording| {
+                let mut output = self.setup_and_execute(job_id, &dir, code, firmware)?;
+
+                // recording is taken before the teardown, which may reset the radio
+                if let Some(recording) = recording {
+                    output.artifacts.extend(recording.finish().map_err(Error::Sdr)?);
+                }
+
+                Ok(output)
+            });
+
+        // emulation is removed even if it is partially applied or the job fails
+        let netem_result = match netem_interface {
+            Some(interface) => netem::clear(interface).map_err(Error::Netem),
+            None => Ok(())
+        };
+
+        // failure of the job is reported instead, while the emulation left on the interface affects the next jobs
+        if let Err(e) = &netem_result {
+            error!("clearing the network emulation of job {} is failed, {}", job_id, e);
+        }
+
+        // device is put back into a known state even if the job fails
+        let teardown_result = self.run_scripts(TEARDOWN_STREAM, &self.conditions.teardown, &dir);
+
+        let post_run_result = hooks::run(&post_run)
+            .map_err(|e| Error::Hook("post-run", e));
+
+        let mut output = result?;
+        netem_result?;
+        let teardown = teardown_result?;
+        post_run_result?;
+
+        if let Some((teardown, succeeded)) = teardown {
+            if !succeeded {
+                warn!("teardown of job {} is failed", job_id);
+                self.status.record_error(format!("teardown of job {} is failed, see its teardown stream", job_id));
+            }
+
+            output.streams.push(teardown);
+        }
+
+        if let Some(capture) = capture {
+            output.artifacts.extend(capture.finish().map_err(Error::PacketCapture)?);
+        }
+
+        if let Some(serial) = serial {
+            let (serial_output, truncated) = serial.finish()
+                .map_err(|e| Error::Serial(e))?;
+
+            output.streams.push(LogStream {
+                name: SERIAL_STREAM.to_string(),
+                output: into_string(serial_output, truncated),
+                truncated,
+            });
+        }
+
+        self.workspace.release(job_id)
+            .map_err(|e| Error::IO(e))?;
+
+        Ok(output)
+    }
+
+    /// Tunes the radio and starts its recording before the setup scripts, if the job requests them
+    fn start_sdr(&self, dir: &Path, settings: Option<&SdrSettings>) -> Result<Option<Recording>, Error> {
+        let (sdr, settings) = match (&self.sdr, settings) {
+            (Some(sdr), Some(settings)) => (sdr, settings),
+            _ => return Ok(None)
+        };
+
+        sdr::configure(sdr, settings, dir).map_err(Error::Sdr)?;
+
+        match &settings.recording {
+            Some(recording) => Recording::start(sdr, settings, recording, dir).map(Some).map_err(Error::Sdr),
+            None => Ok(None)
+        }
+    }
+
+    /// Experiment code is not run if one of the setup scripts fails
+    fn setup_and_execute(&self, job_id: ModelId, dir: &Path, code: String, firmware: Option<Firmware>) -> Result<Output, Error> {
+        let setup = match self.run_scripts(SETUP_STREAM, &self.conditions.setup, dir)? {
+            Some((setup, true)) => Some(setup),
+            Some((setup, false)) => {
+                warn!("setup of job {} is failed, the job is not started", job_id);
+
+                return Ok(Output {
+                    stdout: String::new(),
+                    stderr: "setup of the runner is failed, see the setup stream of the job".to_string(),
+                    truncated: false,
+                    streams: vec![setup],
+                    flashed: None,
+                    artifacts: Vec::new(),
+                });
+            }
+            None => None
+        };
+
+        let mut output = self.flash_and_execute(dir, code, firmware)?;
+
+        if let Some(setup) = setup {
+            output.streams.insert(0, setup);
+        }
+
+        Ok(output)
+    }
+
+    /// Runs the scripts in the job directory in order and stops at the first failing one. Output of the scripts is
+    /// kept in a single stream, each preceded by its command. Returns the stream and whether all of the scripts
+    /// succeeded, none if there is not any script.
+    fn run_scripts(&self, name: &str, scripts: &[Vec<String>], dir: &Path) -> Result<Option<(LogStream, bool)>, Error> {
+        if scripts.is_empty() {
+            return Ok(None);
+        }
+
+        let mut log = String::new();
+        let mut truncated = false;
+
+        for script in scripts {
+            info!("running {} script {:?}", name, script);
+
+            let mut command = std::process::Command::new(&script[0]);
+            command
+                .args(&script[1..])
+                .current_dir(dir);
+
+            log.push_str(format!("$ {}\n", script.join(" ")).as_str());
+
+            let captured = match self.capture(command) {
+                Ok(captured) => captured,
+                // script could not be started, e.g. it does not exist
+                Err(Error::IO(e)) => {
+                    log.push_str(format!("{}\n", e).as_str());
+                    return Ok(Some((LogStream { name: name.to_string(), output: log, truncated }, false)));
+                }
+                Err(e) => return Err(e)
+            };
+
+            log.push_str(into_string(captured.stdout, captured.stdout_truncated).as_str());
+            log.push_str(into_string(captured.stderr, captured.stderr_truncated).as_str());
+            truncated = truncated || captured.stdout_truncated || captured.stderr_truncated;
+
+            if !captured.status.success() {
+                log.push_str(format!("{} script is failed, {}\n", name, captured.status).as_str());
+                return Ok(Some((LogStream { name: name.to_string(), output: log, truncated }, false)));
+            }
         }
+
+        Ok(Some((LogStream { name: name.to_string(), output: log, truncated }, true)))
     }
 
-    fn handle_execution(job_id: ModelId, code: String) -> Result<String, Error> {
-        let dir = format!("/tmp/testbed/{}", job_id);
-        let file = dir.clone() + "/job.py";
+    /// Experiment code is not run if the firmware could not be flashed
+    fn flash_and_execute(&self, dir: &Path, code: String, firmware: Option<Firmware>) -> Result<Output, Error> {
+        let firmware = match firmware {
+            Some(firmware) => firmware,
+            None => return self.execute(dir, code)
+        };
+
+        let (flash_log, truncated, flashed) = self.flash(dir, firmware)?;
+
+        let flash_stream = LogStream {
+            name: FLASH_STREAM.to_string(),
+            output: flash_log,
+            truncated,
+        };
+
+        if !flashed {
+            return Ok(Output {
+                stdout: String::new(),
+                stderr: "firmware flashing is failed, see the flash stream of the job".to_string(),
+                truncated: false,
+                streams: vec![flash_stream],
+                flashed: Some(false),
+                artifacts: Vec::new(),
+            });
+        }
+
+        let mut output = self.execute(dir, code)?;
+
+        output.streams.insert(0, flash_stream);
+        output.flashed = Some(true);
+
+        Ok(output)
+    }
 
-        std::fs::create_dir_all(dir.as_str())
+    /// Writes the image into the job directory and runs the flash command on it. Returns the log of
+    /// the command, whether it is truncated and whether the command succeeded.
+    fn flash(&self, dir: &Path, firmware: Firmware) -> Result<(String, bool, bool), Error> {
+        let flash = self.flash.as_ref().ok_or(Error::FlashNotConfigured)?;
+
+        let data = base64::decode(&firmware.data)
+            .map_err(|_| Error::InvalidFirmware)?;
+
+        // name is validated by the server, only its last component is used regardless
+        let name = Path::new(&firmware.name).file_name()
+            .ok_or(Error::InvalidFirmware)?;
+
+        let firmware_dir = dir.join(FIRMWARE_DIR);
+
+        std::fs::create_dir_all(&firmware_dir)
             .map_err(|e| Error::IO(e))?;
 
-        let mut f = std::fs::File::create(file.as_str())
+        let path = firmware_dir.join(name);
+
+        std::fs::write(&path, data)
+            .map_err(|e| Error::IO(e))?;
+
+        let args = flash.command.iter()
+            .map(|arg| arg.replace(FIRMWARE_PLACEHOLDER, path.to_string_lossy().as_ref()))
+            .collect::<Vec<String>>();
+
+        info!("flashing firmware {:?}", args);
+
+        let mut command = std::process::Command::new(&args[0]);
+        command.args(&args[1..]);
+
+        let captured = self.capture(command)?;
+
+        info!("flashing is finished, status {:?}", captured.status);
+
+        // tools log into both of the streams, they are kept in a single log
+        let mut log = into_string(captured.stdout, captured.stdout_truncated);
+        log.push_str(into_string(captured.stderr, captured.stderr_truncated).as_str());
+
+        Ok((log, captured.stdout_truncated || captured.stderr_truncated, captured.status.success()))
+    }
+
+    fn execute(&self, dir: &Path, code: String) -> Result<Output, Error> {
+        let mut f = std::fs::File::create(dir.join("job.py"))
             .map_err(|e| Error::IO(e))?;
 
         f.write(code.as_bytes())
             .map_err(|e| Error::IO(e))?;
 
-        let output = std::process::Command::new("/usr/bin/docker")
-            .arg("run")
-            .arg("--rm")
-            .args(&["--volume", (dir.clone() + ":/usr/local/scripts/").as_str()])
-            .arg("python:rc-alpine")
-            .args(&["python", "/usr/local/scripts/job.py"])
-            .output()
+        let captured = self.capture(self.command(dir))?;
+
+        let output = Output {
+            stdout: into_string(captured.stdout, captured.stdout_truncated),
+            stderr: into_string(captured.stderr, captured.stderr_truncated),
+            truncated: captured.stdout_truncated || captured.stderr_truncated,
+            streams: Vec::new(),
+            flashed: None,
+            artifacts: Vec::new(),
+        };
+
+        info!("execution is finished, status {:?}, truncated {}", captured.status, output.truncated);
+
+        Ok(output)
+    }
+
+    /// Runs the command as the current job and captures its output
+    fn capture(&self, mut command: std::process::Command) -> Result<Captured, Error> {
+        let max_output_size = self.limits.max_output_size;
+
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
             .map_err(|e| Error::IO(e))?;
 
-        if output.stderr.len() > 0 {
-            return Err(Error::Output(String::from_utf8(output.stderr).map_err(|e| Error::String(e))?));
-        }
+        self.current_job.set(Some(child.id()));
 
-        info!(
-            "successful execution, status {:?}, stdout {:?}, stderr {:?}",
-            output.status, String::from_utf8(output.stdout.clone()).map_err(|e| Error::String(e))?,
-            String::from_utf8(output.stderr).map_err(|e| Error::String(e))?);
+        // Both pipes have to be drained concurrently, otherwise the child may block on a full pipe
+        let stdout = child.stdout.take().unwrap();
+        let stdout_reader = std::thread::spawn(move || read_capped(stdout, max_output_size));
+
+        let (stderr, stderr_truncated) = read_capped(child.stderr.take().unwrap(), max_output_size)
+            .map_err(|e| Error::IO(e))?;
 
-        std::fs::remove_dir_all(dir.as_str())
+        let (stdout, stdout_truncated) = stdout_reader.join()
+            .map_err(|_| Error::Capture)?
             .map_err(|e| Error::IO(e))?;
 
-        Ok(String::from_utf8(output.stdout)
-            .map_err(|e| Error::String(e))?)
+        let status = child.wait()
+            .map_err(|e| Error::IO(e))?;
+
+        self.current_job.set(None);
+
+        Ok(Captured { stdout, stdout_truncated, stderr, stderr_truncated, status })
+    }
+}
+
+/// Reads at most `limit` bytes from the reader and discards the rest. Returns the captured bytes
+/// and whether anything was discarded.
+fn read_capped<R: Read>(mut reader: R, limit: usize) -> std::io::Result<(Vec<u8>, bool)> {
+    let mut buf = Vec::new();
+
+    (&mut reader).take(limit as u64).read_to_end(&mut buf)?;
+
+    let discarded = std::io::copy(&mut reader, &mut std::io::sink())?;
+
+    Ok((buf, discarded > 0))
+}
+
+pub fn result_message_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+fn wasm_command(wasm: &Wasm, limits: &Limits, dir: &Path) -> std::process::Command {
+    let mut command = std::process::Command::new(wasm.runtime.as_str());
+
+    command
+        .arg("run")
+        .args(["--dir", format!("{}::/job", dir.display()).as_str()]);
+
+    for dir in &wasm.dirs {
+        command.args(["--dir", dir.as_str()]);
+    }
+
+    if let Some(max_memory) = wasm.max_memory {
+        command.args(["-W", format!("max-memory-size={}", max_memory).as_str()]);
     }
+
+    // module traps once it runs out of fuel or time, the job is failed then
+    if let Some(fuel) = limits.fuel {
+        command.args(["-W", format!("fuel={}", fuel).as_str()]);
+    }
+
+    if let Some(timeout) = limits.timeout {
+        command.args(["-W", format!("timeout={}s", timeout).as_str()]);
+    }
+
+    command
+        .arg(wasm.module.as_str())
+        .arg("/job/job.py");
+
+    command
+}
+
+fn into_string(bytes: Vec<u8>, truncated: bool) -> String {
+    // Truncation may split a multi byte character, hence the lossy conversion
+    let mut s = String::from_utf8_lossy(&bytes).into_owned();
+
+    if truncated {
+        s.push_str(TRUNCATION_MARKER);
+    }
+
+    s
 }
 
 impl Actor for Executor {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.check_disk_pressure();
+
+        ctx.run_interval(DISK_PRESSURE_INTERVAL, |act, _| act.check_disk_pressure());
+
+        // emulation may be left on the interface if the client is stopped during a job
+        if let Some(interface) = &self.netem_interface {
+            if let Err(e) = netem::clear(interface) {
+                error!("clearing network emulation of {} is failed, {:?}", interface, e);
+            }
+        }
+
+        self.report_thermal_state();
+
+        ctx.run_interval(THERMAL_STATE_INTERVAL, |act, _| act.report_thermal_state());
+    }
 }
 
 impl Handler<RunMessage> for Executor {
     type Result = ();
 
-    fn handle(&mut self, msg: RunMessage, ctx: &mut Self::Context) {
+    fn handle(&mut self, msg: RunMessage, _: &mut Self::Context) {
         info!("got some run for ExecutorMock, id: {}, code: {}", msg.job_id, msg.code);
 
         let job_id = msg.job_id;
 
-        let addr = self.connection.clone();
+        self.status.start_job(job_id);
 
-        let (output, successful) = match Self::handle_execution(msg.job_id, msg.code) {
-            Ok(output) => (output, true),
-            Err(e) => {
-                error!("could not execute the job, {:?}", e);
+        // captured before the hooks, which may power cycle or reflash the device
+        let mut environment = environment::snapshot(&self.firmware_version_commands);
+
+        // settings the job runs with are reported along with the environment, they are restored after the hooks
+        let restore = match &msg.performance {
+            Some(_) if !self.performance_control => {
+                environment.performance = Some(PerformanceReport {
+                    errors: vec!["performance control is not enabled on the runner".to_string()],
+                    ..PerformanceReport::default()
+                });
+
+                None
+            }
+            Some(settings) => {
+                let (report, restore) = performance::apply(settings);
+
+                environment.performance = Some(report);
 
-                (format!("{:?}", e), false)
+                Some(restore)
             }
+            None => None
         };
 
-        async move {
-            if let Err(e) = addr.send(RunResultMessage { job_id, output, successful })
-                .await {
-                error!("could not send run result to connection, {:?}", e);
+        let result = match self.handle_execution(msg.job_id, msg.code, &msg.hooks, msg.firmware, msg.network, msg.sdr) {
+            Ok(output) => {
+                let successful = output.stderr.is_empty();
+
+                RunResultMessage {
+                    job_id,
+                    output: if successful { output.stdout } else { output.stderr },
+                    successful,
+                    truncated: output.truncated,
+                    streams: output.streams,
+                    flashed: output.flashed,
+                    message_id: result_message_id(),
+                    environment: Some(environment),
+                    artifacts: output.artifacts,
+                }
+            }
+            Err(e) => {
+                error!("could not execute the job, {}", e);
+
+                self.status.record_error(format!("could not execute the job {}, {}", job_id, e));
+
+                RunResultMessage {
+                    job_id,
+                    output: e.to_string(),
+                    successful: false,
+                    truncated: false,
+                    streams: Vec::new(),
+                    flashed: None,
+                    message_id: result_message_id(),
+                    environment: Some(environment),
+                    artifacts: Vec::new(),
+                }
             }
+        };
+
+        if let Some(restore) = restore {
+            restore.restore();
         }
-            .into_actor(self)
-            .spawn(ctx);
+
+        // pid is not cleared if the execution fails midway
+        self.current_job.set(None);
+        self.status.finish_job();
+
+        // result is queued immediately so that it reaches the connection before the answer of a drain
+        self.connection.do_send(result);
+
+        self.check_disk_pressure();
+        self.report_thermal_state();
     }
 }
 
+impl Handler<DrainMessage> for Executor {
+    type Result = ();
+
+    // Messages are handled in order, hence the current job is already completed here
+    fn handle(&mut self, _: DrainMessage, _: &mut Self::Context) {}
+}
+
 #[derive(Debug)]
 pub enum Error {
     IO(std::io::Error),
-    String(std::string::FromUtf8Error),
-    Output(String),
-}
\ No newline at end of file
+    Capture,
+    Serial(std::io::Error),
+    // stage of the failing hook, i.e. pre-run or post-run
+    Hook(&'static str, std::io::Error),
+    UnknownHook(String),
+    FlashNotConfigured,
+    InvalidFirmware,
+    NetemNotConfigured,
+    Netem(std::io::Error),
+    PacketCapture(std::io::Error),
+    SdrNotConfigured,
+    Sdr(std::io::Error),
+}
+
+/// Errors are given to the users as the output of their jobs
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::IO(e) => write!(f, "running the job is failed, {}", e),
+            Error::Capture => write!(f, "capturing the output of the job is failed"),
+            Error::Serial(e) => write!(f, "capturing the serial console is failed, {}", e),
+            Error::Hook(stage, e) => write!(f, "{} hook is failed, {}", stage, e),
+            Error::UnknownHook(name) => write!(f, "hook {} is not configured on the runner", name),
+            Error::FlashNotConfigured => write!(f, "runner is not configured for flashing firmwares"),
+            Error::InvalidFirmware => write!(f, "firmware of the job is invalid"),
+            Error::NetemNotConfigured => write!(f, "runner is not configured for emulating the network"),
+            Error::Netem(e) => write!(f, "emulating the network with tc is failed, {}", e),
+            Error::PacketCapture(e) => write!(f, "capturing the packets is failed, {}", e),
+            Error::SdrNotConfigured => write!(f, "runner is not configured for the requested sdr recording"),
+            Error::Sdr(e) => write!(f, "tuning or recording the sdr is failed, {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wasm() -> Wasm {
+        Wasm {
+            runtime: "wasmtime".to_string(),
+            module: "/opt/python.wasm".to_string(),
+            dirs: Vec::new(),
+            max_memory: None,
+        }
+    }
+
+    fn args(command: &std::process::Command) -> Vec<String> {
+        command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_wasm_command_without_limits() {
+        let command = wasm_command(&wasm(), &Limits::default(), Path::new("/workspace/1"));
+
+        assert_eq!("wasmtime", command.get_program());
+        assert_eq!(vec!["run", "--dir", "/workspace/1::/job", "/opt/python.wasm", "/job/job.py"], args(&command));
+    }
+
+    #[test]
+    fn test_wasm_command_passes_limits() {
+        let wasm = Wasm { max_memory: Some(1024), ..wasm() };
+        let limits = Limits { fuel: Some(1000), timeout: Some(60), ..Limits::default() };
+
+        let command = wasm_command(&wasm, &limits, Path::new("/workspace/1"));
+
+        assert_eq!(
+            vec!["run", "--dir", "/workspace/1::/job", "-W", "max-memory-size=1024", "-W", "fuel=1000", "-W", "timeout=60s", "/opt/python.wasm", "/job/job.py"],
+            args(&command)
+        );
+    }
+}
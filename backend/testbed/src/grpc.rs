@@ -0,0 +1,89 @@
+use std::io;
+
+use awc::error::WsProtocolError;
+use awc::ws::{CloseCode, CloseReason, Frame, Message};
+use futures::{future, SinkExt, stream, StreamExt};
+use futures::channel::mpsc;
+use tonic::Request;
+
+use shared::grpc::{Close, Frame as GrpcFrame};
+use shared::grpc::frame::Kind;
+use shared::grpc::runner_client::RunnerClient;
+
+use crate::connection::{FrameSink, FrameStream};
+use crate::transport::Transport;
+
+// messages are handed to the grpc stream as it takes them, the rest waits in the write buffer of the connection
+const FRAME_BUFFER: usize = 16;
+
+/// Server urls with the grpc schemes are connected over grpc instead of the websocket
+pub fn is_grpc_url(url: &str) -> bool {
+    url.starts_with("grpc://") || url.starts_with("grpcs://")
+}
+
+/// Joins the server over grpc. Frames of the stream carry the same messages as the websocket frames, they
+/// are converted so that the connection handles both transports the same way.
+pub async fn connect(transport: Transport, server_url: String, access_token: Option<String>) -> Result<(FrameSink, FrameStream), String> {
+    let channel = transport.grpc_channel(server_url.as_str()).await?;
+
+    let (sender, receiver) = mpsc::channel(FRAME_BUFFER);
+    let mut request = Request::new(receiver);
+
+    // runner authenticates with its client certificate if there is no token
+    if let Some(access_token) = access_token {
+        let value = format!("Bearer {}", access_token).parse()
+            .map_err(|_| "access token is not a valid metadata value".to_string())?;
+
+        request.metadata_mut().insert("authorization", value);
+    }
+
+    let frames = RunnerClient::new(channel).join(request)
+        .await
+        .map_err(|status| format!("joining server is failed, {:?}, {}", status.code(), status.message()))?
+        .into_inner();
+
+    let sink = sender
+        .sink_map_err(|_| WsProtocolError::Io(io::Error::new(io::ErrorKind::BrokenPipe, "grpc stream is closed")))
+        .with_flat_map(|message| stream::iter(into_frame(message).map(Ok)));
+
+    let stream = frames.filter_map(|frame| future::ready(match frame {
+        Ok(frame) => from_frame(frame).map(Ok),
+        Err(status) => Some(Err(WsProtocolError::Io(io::Error::new(io::ErrorKind::ConnectionAborted, status.message().to_string()))))
+    }));
+
+    Ok((Box::pin(sink), Box::pin(stream)))
+}
+
+fn into_frame(message: Message) -> Option<GrpcFrame> {
+    let kind = match message {
+        Message::Text(text) => Kind::Text(text),
+        Message::Binary(bytes) => Kind::Binary(bytes.to_vec()),
+        Message::Ping(bytes) => Kind::Ping(bytes.to_vec()),
+        Message::Pong(bytes) => Kind::Pong(bytes.to_vec()),
+        Message::Close(reason) => Kind::Close(reason.map_or_else(Close::default, |reason| Close {
+            code: Into::<u16>::into(reason.code) as u32,
+            description: reason.description.unwrap_or_default(),
+        })),
+        // connection does not fragment its messages
+        Message::Continuation(_) | Message::Nop => return None
+    };
+
+    Some(GrpcFrame { kind: Some(kind) })
+}
+
+fn from_frame(frame: GrpcFrame) -> Option<Frame> {
+    let frame = match frame.kind? {
+        Kind::Text(text) => Frame::Text(text.into()),
+        Kind::Binary(bytes) => Frame::Binary(bytes.into()),
+        Kind::Ping(bytes) => Frame::Ping(bytes.into()),
+        Kind::Pong(bytes) => Frame::Pong(bytes.into()),
+        // code is zero if the server has not given a reason
+        Kind::Close(close) if close.code == 0 => Frame::Close(None),
+        Kind::Close(close) => Frame::Close(Some(CloseReason {
+            code: CloseCode::from(close.code as u16),
+            description: Some(close.description).filter(|description| !description.is_empty()),
+        })),
+    };
+
+    Some(frame)
+}
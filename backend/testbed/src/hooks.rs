@@ -0,0 +1,41 @@
+use std::io;
+use std::time::Duration;
+
+use log::info;
+use sysfs_gpio::{Direction, Pin};
+
+use crate::config::HookAction;
+
+/// Runs the actions in order, stops at the first failing one
+pub fn run(actions: &[HookAction]) -> io::Result<()> {
+    for action in actions {
+        info!("running hook action {:?}", action);
+
+        match action {
+            HookAction::Gpio { pin, value } => set_gpio(*pin, *value)?,
+            HookAction::Relay { command } => {
+                let status = std::process::Command::new(&command[0])
+                    .args(&command[1..])
+                    .status()
+                    .map_err(|e| io::Error::other(format!("relay command {:?} could not be run, {}", command, e)))?;
+
+                if !status.success() {
+                    return Err(io::Error::other(format!("relay command {:?} is failed, {}", command, status)));
+                }
+            }
+            HookAction::Sleep { millis } => std::thread::sleep(Duration::from_millis(*millis)),
+        }
+    }
+
+    Ok(())
+}
+
+fn set_gpio(pin: u64, value: u8) -> io::Result<()> {
+    let pin = Pin::new(pin);
+
+    // line is left exported so that its value is kept after the hook
+    pin.export()
+        .and_then(|_| pin.set_direction(Direction::Out))
+        .and_then(|_| pin.set_value(value))
+        .map_err(|e| io::Error::other(format!("setting gpio {} is failed, {}", pin.get_pin_num(), e)))
+}
@@ -0,0 +1,57 @@
+use shared::websocket_messages::server;
+
+use crate::config::Config;
+
+/// Hardware inventory of the node, reported to the server on every connect. Details which cannot be
+/// read on the platform are left out.
+pub fn runner_info(config: &Config) -> server::RunnerInfo {
+    server::RunnerInfo {
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        client_version: env!("CARGO_PKG_VERSION").to_string(),
+        kernel: kernel(),
+        cpu_model: cpu_model(),
+        memory_bytes: memory_bytes(),
+        peripherals: config.peripherals.clone(),
+    }
+}
+
+pub fn kernel() -> Option<String> {
+    let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+    let release = release.trim();
+
+    if release.is_empty() {
+        return None;
+    }
+
+    Some(release.to_string())
+}
+
+fn cpu_model() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+
+    // x86 reports "model name", while arm boards usually report "Model" or "Hardware"
+    ["model name", "Model", "Hardware"].iter()
+        .find_map(|key| cpuinfo_value(cpuinfo.as_str(), key))
+}
+
+fn cpuinfo_value(cpuinfo: &str, key: &str) -> Option<String> {
+    cpuinfo.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn memory_bytes() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+
+    let kilobytes = meminfo.lines()
+        .find(|line| line.starts_with("MemTotal:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse::<u64>()
+        .ok()?;
+
+    Some(kilobytes * 1024)
+}
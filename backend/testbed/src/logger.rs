@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use env_logger::{Builder, Logger};
+use lazy_static::lazy_static;
+use log::{Log, Metadata, Record};
+
+// lines logged beyond this while they are not shipped are dropped
+const MAX_BUFFERED_LINES: usize = 1000;
+
+struct Shipping {
+    until: Instant,
+    lines: VecDeque<String>,
+}
+
+/// Wraps env_logger so that its filter can be changed at runtime and the logs can be shipped to the server
+struct ClientLogger {
+    inner: RwLock<Logger>,
+    // filter given by the configuration, restored when the remote filter is reset
+    configured_filter: Mutex<Option<String>>,
+    shipping: Mutex<Option<Shipping>>,
+}
+
+lazy_static! {
+    static ref LOGGER: ClientLogger = ClientLogger {
+        inner: RwLock::new(Builder::from_default_env().build()),
+        configured_filter: Mutex::new(None),
+        shipping: Mutex::new(None),
+    };
+}
+
+impl Log for ClientLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.read().unwrap().enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let inner = self.inner.read().unwrap();
+
+        if !inner.matches(record) {
+            return;
+        }
+
+        inner.log(record);
+
+        let mut shipping = self.shipping.lock().unwrap();
+
+        if let Some(shipping) = shipping.as_mut().filter(|shipping| Instant::now() <= shipping.until) {
+            if shipping.lines.len() == MAX_BUFFERED_LINES {
+                shipping.lines.pop_front();
+            }
+
+            shipping.lines.push_back(format!(
+                "[{} {} {}] {}",
+                humantime::format_rfc3339_seconds(SystemTime::now()),
+                record.level(),
+                record.target(),
+                record.args()
+            ));
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.read().unwrap().flush();
+    }
+}
+
+fn build(filter: Option<&str>) -> Logger {
+    let mut builder = Builder::from_default_env();
+
+    if let Some(filter) = filter {
+        builder.parse_filters(filter);
+    }
+
+    builder.build()
+}
+
+/// Installs the logger, given filter overrides RUST_LOG
+pub fn init(filter: Option<&str>) {
+    *LOGGER.configured_filter.lock().unwrap() = filter.map(String::from);
+
+    set_filter(None);
+
+    log::set_logger(&*LOGGER).expect("Failed to set logger");
+}
+
+/// Replaces the filter of the logger, configured filter is restored if none is given
+pub fn set_filter(filter: Option<&str>) {
+    let configured_filter = LOGGER.configured_filter.lock().unwrap().clone();
+    let logger = build(filter.or_else(|| configured_filter.as_deref()));
+
+    log::set_max_level(logger.filter());
+
+    *LOGGER.inner.write().unwrap() = logger;
+}
+
+/// Starts buffering the logs for shipping them to the server during the given duration
+pub fn ship_for(duration: Duration) {
+    *LOGGER.shipping.lock().unwrap() = Some(Shipping {
+        until: Instant::now() + duration,
+        lines: VecDeque::new(),
+    });
+}
+
+/// Takes the logs buffered for shipping, the shipping ends once its window is passed and the buffer is taken
+pub fn take_shipped() -> Vec<String> {
+    let mut shipping = LOGGER.shipping.lock().unwrap();
+
+    let (lines, expired) = match shipping.as_mut() {
+        Some(s) => (s.lines.drain(..).collect(), Instant::now() > s.until),
+        None => return Vec::new()
+    };
+
+    if expired {
+        *shipping = None;
+    }
+
+    lines
+}
@@ -1,24 +1,65 @@
 use std::sync::mpsc::channel;
+use std::time::Duration;
 
-use actix::{Actor, Addr, Arbiter, Recipient, System};
+use actix::{Actor, Addr, Arbiter, System};
+use actix_rt::signal::unix::{signal, SignalKind};
+use futures::future;
+use log::{info, warn};
 
+use crate::backoff::Backoff;
+use crate::config::Config;
 use crate::connection::Connection;
-use crate::executor::Executor;
-use crate::messages::{RunMessage, UpdateExecutorMessage};
+use crate::executor::{CurrentJob, Executor};
+use crate::messages::{DrainMessage, RunMessage, ShutdownMessage, UpdateExecutorMessage};
+use crate::proxy::Proxy;
+use crate::status::Status;
+use crate::transport::Transport;
+use crate::workspace::Workspace;
 
+mod backoff;
+mod capture;
+mod command;
+mod config;
 mod connection;
+mod environment;
 mod executor;
+mod grpc;
+mod hooks;
+mod inventory;
+mod logger;
 mod messages;
+mod netem;
+mod performance;
+mod provision;
+mod proxy;
+mod sdr;
+mod serial;
+mod status;
+mod systemd;
+mod tls;
+mod transport;
+mod update;
+mod validation;
+mod workspace;
+mod write_buffer;
 
 type ModelId = i32;
 
-fn setup_executor(connection: Addr<Connection>) -> Recipient<RunMessage> {
-    let (tx, rx) = channel::<Recipient<RunMessage>>();
+fn setup_executor(connection: Addr<Connection>, config: &Config, status: Status) -> (Addr<Executor>, CurrentJob) {
+    let (tx, rx) = channel::<(Addr<Executor>, CurrentJob)>();
+    let workspace = Workspace::new(
+        config.workspace_dir.clone(),
+        config.limits.disk_quota_mb.map(|mb| mb * 1024 * 1024),
+        Duration::from_secs(config.workspace_retention),
+        config.min_free_space_mb * 1024 * 1024,
+    );
+    let config = config.clone();
 
     std::thread::Builder::new().name("executor".to_string()).spawn(move || {
         let sys = System::new("executor");
-        let executor = Executor::new(connection).start();
-        tx.send(executor.recipient::<RunMessage>()).expect("Failed to send Executor from thread");
+        let executor = Executor::new(connection, workspace, &config, status);
+        let current_job = executor.current_job();
+        tx.send((executor.start(), current_job)).expect("Failed to send Executor from thread");
         sys.run()
     }).expect("Failed to initialize thread");
 
@@ -29,24 +70,104 @@ fn main() {
     // Load .env
     dotenv::dotenv().ok();
 
-    let access_token = std::env::var("BACKEND_ACCESS_TOKEN").expect("BACKEND_ACCESS_TOKEN is not provided in env");
-    let server_url = std::env::var("SERVER_URL").expect("SERVER_URL is not provided in env");
+    let (config, check_config) = Config::load().unwrap_or_else(|e| panic!("Invalid configuration, {}", e));
+    // runners can authenticate with their client certificate instead of a token
+    let has_client_certificate = std::env::var("TLS_CLIENT_CERT_FILE").is_ok();
+
+    if let Err(e) = config.validate(has_client_certificate) {
+        eprintln!("Invalid configuration, {}", e);
+        std::process::exit(1);
+    }
+
+    let proxy = match Proxy::from_env() {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            eprintln!("Invalid proxy is provided, {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if check_config {
+        println!("Configuration is valid");
+        return;
+    }
+
+    let token_file = config.access_token_file.clone();
+    // token persisted by a previous refresh or claim takes precedence over the provisioned one
+    let access_token = match token_file.as_ref().and_then(|f| std::fs::read_to_string(f).ok()) {
+        Some(token) if !token.trim().is_empty() => Some(token.trim().to_string()),
+        _ => config.access_token.clone()
+    };
+    let transport = Transport::new(tls::setup_tls(), proxy);
+    // fresh devices without any credentials enroll themselves with the claim code
+    let claim = match (&access_token, &config.claim_code) {
+        (Some(_), _) => None,
+        (None, Some(claim_code)) => Some((config.claim_url.clone().unwrap(), claim_code.clone())),
+        (None, None) if has_client_certificate => None,
+        (None, None) => panic!("Neither an access token, a claim code nor a client certificate is provided")
+    };
 
     // Enable logger
-    env_logger::init();
+    logger::init(config.log_level.as_deref());
 
-    let sys = System::new("websocket-client");
+    let status = Status::default();
 
+    if let Some(status_socket) = &config.status_socket {
+        status.serve_unix(status_socket.clone()).expect("Failed to serve status on unix socket");
+    }
+
+    if let Some(status_address) = &config.status_address {
+        status.serve_http(status_address.clone()).expect("Failed to serve status on http");
+    }
+
+    let sys = System::new("websocket-client");
     Arbiter::spawn(async move {
-        let connection = Connection::new(server_url, access_token).start();
+        let access_token = match claim {
+            Some((claim_url, claim_code)) => {
+                let access_token = provision::claim(&transport, claim_url.as_str(), claim_code.as_str())
+                    .await
+                    .unwrap_or_else(|e| panic!("Failed to claim the runner: {}", e));
+
+                info!("Runner is claimed");
 
-        let executor = setup_executor(connection.clone());
+                match &token_file {
+                    Some(token_file) => config::write_access_token(token_file, access_token.as_str())
+                        .expect("Failed to persist the claimed token"),
+                    None => warn!("Access token file is not configured, claimed token will be lost after restart")
+                }
+
+                Some(access_token)
+            }
+            None => access_token
+        };
+
+        let connection = Connection::new(&config, access_token, transport, Backoff::from_env(), status.clone()).start();
+
+        let (executor, current_job) = setup_executor(connection.clone(), &config, status);
 
         connection
-            .send(UpdateExecutorMessage { executor })
+            .send(UpdateExecutorMessage {
+                executor: executor.clone().recipient::<RunMessage>(),
+                drain: executor.recipient::<DrainMessage>(),
+                current_job,
+            })
             .await
             .unwrap();
+
+        systemd::notify("READY=1");
+
+        let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen SIGTERM");
+        let mut interrupt = signal(SignalKind::interrupt()).expect("Failed to listen SIGINT");
+
+        future::select(Box::pin(terminate.recv()), Box::pin(interrupt.recv())).await;
+
+        connection.do_send(ShutdownMessage { cancel_job: true });
     });
 
     sys.run().unwrap();
+
+    if update::restart_requested() {
+        info!("Restarting into the updated client");
+        panic!("Failed to restart into the updated client, {}", update::restart());
+    }
 }
\ No newline at end of file
@@ -1,5 +1,11 @@
+use std::collections::BTreeMap;
+
 use actix::{Message, Recipient};
 
+use shared::websocket_messages::client::{Firmware, NetworkEmulation, PerformanceSettings, SdrSettings};
+use shared::websocket_messages::server::{Artifact, Environment, LogStream};
+
+use crate::executor::CurrentJob;
 use crate::ModelId;
 
 #[derive(Message)]
@@ -7,6 +13,11 @@ use crate::ModelId;
 pub struct RunMessage {
     pub job_id: ModelId,
     pub code: String,
+    pub hooks: Vec<String>,
+    pub firmware: Option<Firmware>,
+    pub performance: Option<PerformanceSettings>,
+    pub network: Option<NetworkEmulation>,
+    pub sdr: Option<SdrSettings>,
 }
 
 #[derive(Message)]
@@ -15,10 +26,48 @@ pub struct RunResultMessage {
     pub job_id: ModelId,
     pub output: String,
     pub successful: bool,
+    pub truncated: bool,
+    pub streams: Vec<LogStream>,
+    pub flashed: Option<bool>,
+    // generated once, so that the server applies the result only once even if it is resent
+    pub message_id: String,
+    // captured when the job starts
+    pub environment: Option<Environment>,
+    pub artifacts: Vec<Artifact>,
 }
 
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct UpdateExecutorMessage {
-    pub executor: Recipient<RunMessage>
+    pub executor: Recipient<RunMessage>,
+    pub drain: Recipient<DrainMessage>,
+    pub current_job: CurrentJob,
+}
+
+/// Stops the client gracefully
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ShutdownMessage {
+    // whether the current job is cancelled if it does not complete in the shutdown timeout
+    pub cancel_job: bool,
+}
+
+/// Answered by the executor once the job it is running, if any, is completed
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct DrainMessage;
+
+/// Sent by the executor when the free space of the workspace crosses the configured minimum
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct DiskPressureMessage {
+    pub under_pressure: bool,
+    pub free_space: u64,
+}
+
+/// Sent by the executor periodically while it is idle, if the node has any thermal zones
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ThermalStateMessage {
+    pub temperatures: BTreeMap<String, f64>,
 }
@@ -0,0 +1,68 @@
+use std::io;
+use std::process::Stdio;
+
+use log::{info, warn};
+
+use shared::websocket_messages::client::NetworkEmulation;
+
+/// Shapes the egress traffic of the interface with a netem qdisc at its root, replacing the previous root qdisc
+pub fn apply(interface: &str, emulation: &NetworkEmulation) -> io::Result<()> {
+    let mut args = vec!["qdisc".to_string(), "replace".to_string(), "dev".to_string(), interface.to_string(), "root".to_string(), "netem".to_string()];
+
+    if let Some(delay_ms) = emulation.delay_ms {
+        args.extend(["delay".to_string(), format!("{}ms", delay_ms)]);
+
+        if let Some(jitter_ms) = emulation.jitter_ms {
+            args.push(format!("{}ms", jitter_ms));
+        }
+    }
+
+    if let Some(loss_percent) = emulation.loss_percent {
+        args.extend(["loss".to_string(), format!("{}%", loss_percent)]);
+    }
+
+    if let Some(rate_kbit) = emulation.rate_kbit {
+        args.extend(["rate".to_string(), format!("{}kbit", rate_kbit)]);
+    }
+
+    info!("applying network emulation, tc {}", args.join(" "));
+
+    tc(&args)
+}
+
+/// Removes the netem qdisc of the interface, if there is any, so that the interface is back to its default qdisc.
+/// Qdiscs of the other kinds are left as is.
+pub fn clear(interface: &str) -> io::Result<()> {
+    let output = std::process::Command::new("tc")
+        .args(["qdisc", "show", "dev", interface, "root"])
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!("listing qdiscs of {} is failed, {}", interface, String::from_utf8_lossy(&output.stderr).trim())));
+    }
+
+    if !String::from_utf8_lossy(&output.stdout).contains("netem") {
+        return Ok(());
+    }
+
+    info!("removing network emulation from {}", interface);
+
+    tc(&["qdisc", "del", "dev", interface, "root"].map(String::from))
+}
+
+fn tc(args: &[String]) -> io::Result<()> {
+    let output = std::process::Command::new("tc")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        warn!("tc {} is failed, {}", args.join(" "), stderr.trim());
+
+        return Err(io::Error::other(format!("tc is failed, {}", stderr.trim())));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+
+use shared::websocket_messages::client::PerformanceSettings;
+use shared::websocket_messages::server::PerformanceReport;
+
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+// intel_pstate disables turbo with 1, acpi-cpufreq and the others disable boost with 0
+const INTEL_NO_TURBO: &str = "/sys/devices/system/cpu/intel_pstate/no_turbo";
+const CPUFREQ_BOOST: &str = "/sys/devices/system/cpu/cpufreq/boost";
+const USERSPACE_GOVERNOR: &str = "userspace";
+
+/// Previous values of the files written while applying the settings, written back once the job is completed
+#[derive(Default)]
+pub struct Restore {
+    previous: Vec<(PathBuf, String)>,
+}
+
+impl Restore {
+    /// Values are written back in the reverse order, so that the frequency limits never cross each other
+    pub fn restore(self) {
+        for (path, value) in self.previous.into_iter().rev() {
+            if let Err(e) = std::fs::write(&path, &value) {
+                warn!("restoring {} to {} is failed, {:?}", path.display(), value, e);
+            }
+        }
+    }
+
+    fn write(&mut self, path: &Path, value: &str) -> std::io::Result<()> {
+        let previous = std::fs::read_to_string(path)?;
+
+        std::fs::write(path, value)?;
+
+        self.previous.push((path.to_path_buf(), previous.trim().to_string()));
+
+        Ok(())
+    }
+}
+
+/// Applies the settings to every cpu having cpufreq, the settings which could not be applied are given in the
+/// errors of the report. Report is read back from the node after the settings are written.
+pub fn apply(settings: &PerformanceSettings) -> (PerformanceReport, Restore) {
+    let cpus = cpufreq_dirs();
+    let mut restore = Restore::default();
+    let mut errors = Vec::new();
+
+    info!("applying performance settings {:?}", settings);
+
+    if cpus.is_empty() && (settings.governor.is_some() || settings.frequency_khz.is_some()) {
+        errors.push("node does not have cpufreq, governor and frequency could not be set".to_string());
+    }
+
+    if let Some(governor) = &settings.governor {
+        let failures = cpus.values()
+            .filter_map(|dir| restore.write(&dir.join("scaling_governor"), governor).err())
+            .collect::<Vec<std::io::Error>>();
+
+        if let Some(e) = failures.first() {
+            errors.push(format!("governor {} could not be set on {} of {} cpus, {}", governor, failures.len(), cpus.len(), e));
+        }
+    }
+
+    if let Some(frequency_khz) = settings.frequency_khz {
+        let failures = cpus.values()
+            .filter_map(|dir| pin_frequency(dir, frequency_khz, &mut restore).err())
+            .collect::<Vec<std::io::Error>>();
+
+        if let Some(e) = failures.first() {
+            errors.push(format!("frequency {} kHz could not be set on {} of {} cpus, {}", frequency_khz, failures.len(), cpus.len(), e));
+        }
+    }
+
+    if settings.disable_turbo {
+        let result = match turbo_control() {
+            Some((path, disabled_value)) => restore.write(&path, disabled_value)
+                .map_err(|e| format!("turbo could not be disabled, {}", e)),
+            None => Err("node does not have a turbo or boost control".to_string())
+        };
+
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+
+    let read = |dir: &PathBuf, file: &str| std::fs::read_to_string(dir.join(file)).ok().map(|value| value.trim().to_string());
+    let read_khz = |dir: &PathBuf, file: &str| read(dir, file).and_then(|value| value.parse::<u64>().ok());
+
+    let report = PerformanceReport {
+        governors: cpus.iter()
+            .filter_map(|(cpu, dir)| read(dir, "scaling_governor").map(|governor| (cpu.clone(), governor)))
+            .collect(),
+        min_frequencies_khz: cpus.iter()
+            .filter_map(|(cpu, dir)| read_khz(dir, "scaling_min_freq").map(|khz| (cpu.clone(), khz)))
+            .collect(),
+        max_frequencies_khz: cpus.iter()
+            .filter_map(|(cpu, dir)| read_khz(dir, "scaling_max_freq").map(|khz| (cpu.clone(), khz)))
+            .collect(),
+        turbo_disabled: turbo_control().and_then(|(path, disabled_value)| {
+            std::fs::read_to_string(path).ok().map(|value| value.trim() == disabled_value)
+        }),
+        errors,
+    };
+
+    (report, restore)
+}
+
+/// Sets both of the frequency limits of the cpu, along with the speed if the cpu is under the userspace governor
+fn pin_frequency(dir: &Path, frequency_khz: u64, restore: &mut Restore) -> std::io::Result<()> {
+    let frequency = frequency_khz.to_string();
+
+    let current_min = std::fs::read_to_string(dir.join("scaling_min_freq"))?
+        .trim()
+        .parse::<u64>()
+        .unwrap_or(0);
+
+    // the minimum can not be raised above the maximum, nor the maximum lowered below the minimum
+    if frequency_khz >= current_min {
+        restore.write(&dir.join("scaling_max_freq"), &frequency)?;
+        restore.write(&dir.join("scaling_min_freq"), &frequency)?;
+    } else {
+        restore.write(&dir.join("scaling_min_freq"), &frequency)?;
+        restore.write(&dir.join("scaling_max_freq"), &frequency)?;
+    }
+
+    let governor = std::fs::read_to_string(dir.join("scaling_governor"))?;
+
+    if governor.trim() == USERSPACE_GOVERNOR {
+        restore.write(&dir.join("scaling_setspeed"), &frequency)?;
+    }
+
+    Ok(())
+}
+
+/// Turbo control of the node and the value disabling it
+fn turbo_control() -> Option<(PathBuf, &'static str)> {
+    [(INTEL_NO_TURBO, "1"), (CPUFREQ_BOOST, "0")]
+        .iter()
+        .map(|(path, disabled_value)| (PathBuf::from(path), *disabled_value))
+        .find(|(path, _)| path.exists())
+}
+
+/// cpu name -> cpufreq directory of the cpu
+fn cpufreq_dirs() -> BTreeMap<String, PathBuf> {
+    let entries = match std::fs::read_dir(CPU_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return BTreeMap::new()
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+
+            if !name.strip_prefix("cpu")?.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+
+            let dir = entry.path().join("cpufreq");
+
+            if !dir.is_dir() {
+                return None;
+            }
+
+            Some((name, dir))
+        })
+        .collect()
+}
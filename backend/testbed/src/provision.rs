@@ -0,0 +1,61 @@
+use awc::error::{JsonPayloadError, SendRequestError};
+use awc::http::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::transport::Transport;
+
+#[derive(Serialize)]
+struct ClaimRequest<'a> {
+    code: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ClaimResponse {
+    token: String,
+}
+
+/// Enrolls this device as a new runner with the one-time claim code, returns the access token of it.
+pub async fn claim(transport: &Transport, claim_url: &str, code: &str) -> Result<String, Error> {
+    let (claim_url, address) = transport.resolve(claim_url)
+        .await
+        .map_err(|e| Error::Resolve(e))?;
+
+    let mut request = transport.client().post(claim_url);
+
+    if let Some(address) = address {
+        request = request.address(address);
+    }
+
+    let mut response = request
+        .send_json(&ClaimRequest { code })
+        .await
+        .map_err(|e| Error::Send(e))?;
+
+    if !response.status().is_success() {
+        return Err(Error::Status(response.status()));
+    }
+
+    response.json::<ClaimResponse>()
+        .await
+        .map(|r| r.token)
+        .map_err(|e| Error::Payload(e))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Resolve(String),
+    Send(SendRequestError),
+    Status(StatusCode),
+    Payload(JsonPayloadError),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Resolve(e) => write!(f, "resolving claim url is failed, {}", e),
+            Error::Send(e) => write!(f, "sending claim request is failed, {}", e),
+            Error::Status(status) => write!(f, "claim is rejected by the server with status {}", status),
+            Error::Payload(e) => write!(f, "invalid claim response, {}", e),
+        }
+    }
+}
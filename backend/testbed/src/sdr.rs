@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::{error, info, warn};
+
+use shared::websocket_messages::client::{SdrRecording, SdrRecordingKind, SdrSettings};
+use shared::websocket_messages::server::Artifact;
+
+use crate::capture::compress;
+use crate::config::Sdr;
+
+// recordings are written into this directory of the job's workspace
+const SDR_DIR: &str = "sdr";
+const MEGABYTE: u64 = 1024 * 1024;
+// recording tools are stopped if they are still running this long after the requested duration
+const RECORDING_GRACE: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Command of the radio taking the recording, none if the runner is not configured for it
+pub fn recording_command(sdr: &Sdr, kind: SdrRecordingKind) -> Option<&Vec<String>> {
+    match kind {
+        SdrRecordingKind::Iq => sdr.iq_command.as_ref(),
+        SdrRecordingKind::Spectrum => sdr.spectrum_command.as_ref(),
+    }
+}
+
+/// Tunes the radio with the configure command, if the runner has one
+pub fn configure(sdr: &Sdr, settings: &SdrSettings, dir: &Path) -> io::Result<()> {
+    let command = match &sdr.configure {
+        Some(command) => command,
+        None => return Ok(())
+    };
+
+    let args = substitute(command, settings, None);
+
+    info!("configuring sdr, {}", args.join(" "));
+
+    let output = std::process::Command::new(&args[0])
+        .args(&args[1..])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!("configuring sdr is failed, {}", String::from_utf8_lossy(&output.stderr).trim())));
+    }
+
+    Ok(())
+}
+
+/// Recording tool of the radio running along with a job. Tools stop by themselves once the requested duration is
+/// recorded, they are stopped when it is dropped if the job fails before the recording is finished.
+pub struct Recording {
+    child: Option<Child>,
+    output: PathBuf,
+    kind: SdrRecordingKind,
+    deadline: Instant,
+    max_size: u64,
+    metadata: BTreeMap<String, String>,
+}
+
+impl Recording {
+    pub fn start(sdr: &Sdr, settings: &SdrSettings, recording: &SdrRecording, job_dir: &Path) -> io::Result<Recording> {
+        let command = recording_command(sdr, recording.kind)
+            .ok_or_else(|| io::Error::other(format!("{} recording is not configured", kind_name(recording.kind))))?;
+
+        let dir = job_dir.join(SDR_DIR);
+
+        std::fs::create_dir_all(&dir)?;
+
+        let output = dir.join(kind_name(recording.kind));
+        let args = substitute(command, settings, Some((recording.duration_ms, &output)));
+
+        info!("starting sdr recording, {}", args.join(" "));
+
+        let child = std::process::Command::new(&args[0])
+            .args(&args[1..])
+            .current_dir(&dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let started_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+        let metadata = [
+            ("kind", kind_name(recording.kind).to_string()),
+            ("frequency_hz", settings.frequency_hz.to_string()),
+            ("gain_db", settings.gain_db.to_string()),
+            ("sample_rate", settings.sample_rate.to_string()),
+            ("duration_ms", recording.duration_ms.to_string()),
+            ("started_at", started_at.to_string()),
+        ]
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect();
+
+        Ok(Recording {
+            child: Some(child),
+            output,
+            kind: recording.kind,
+            deadline: Instant::now() + Duration::from_millis(recording.duration_ms as u64) + RECORDING_GRACE,
+            max_size: sdr.max_size_mb * MEGABYTE,
+            metadata,
+        })
+    }
+
+    /// Waits for the recording to complete and compresses it. Returns none if the compressed recording exceeds the
+    /// maximum size.
+    pub fn finish(mut self) -> io::Result<Option<Artifact>> {
+        // presence is only cleared here and by the drop
+        let mut child = self.child.take().unwrap();
+
+        let (status, stopped) = loop {
+            if let Some(status) = child.try_wait()? {
+                break (status, false);
+            }
+
+            if Instant::now() >= self.deadline {
+                warn!("sdr recording is still running after its duration, it is stopped");
+                stop(&mut child);
+                break (child.wait()?, true);
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        };
+
+        info!("sdr recording is finished, {}", status);
+
+        if !status.success() && !stopped {
+            return Err(io::Error::other(format!("sdr recording is failed, {}", status)));
+        }
+
+        let data = compress(&self.output)?;
+
+        if data.len() as u64 > self.max_size {
+            warn!("sdr recording is left out since it exceeds {} bytes", self.max_size);
+            return Ok(None);
+        }
+
+        let mut metadata = std::mem::take(&mut self.metadata);
+        // stopped recordings may be shorter than requested
+        metadata.insert("stopped".to_string(), stopped.to_string());
+
+        Ok(Some(Artifact {
+            name: format!("sdr-{}.gz", kind_name(self.kind)),
+            data: base64::encode(data),
+            metadata,
+        }))
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            stop(&mut child);
+
+            if let Err(e) = child.wait() {
+                error!("waiting for the sdr recording is failed, {:?}", e);
+            }
+        }
+    }
+}
+
+/// Recording tools flush their buffers on SIGTERM, they are killed if it can not be sent
+fn stop(child: &mut Child) {
+    let terminated = std::process::Command::new("kill")
+        .args(["-TERM", child.id().to_string().as_str()])
+        .status()
+        .is_ok_and(|status| status.success());
+
+    if !terminated {
+        let _ = child.kill();
+    }
+}
+
+fn kind_name(kind: SdrRecordingKind) -> &'static str {
+    match kind {
+        SdrRecordingKind::Iq => "iq",
+        SdrRecordingKind::Spectrum => "spectrum",
+    }
+}
+
+/// Replaces the placeholders of the command, see `config::Sdr`. Duration and output are only given for the
+/// recordings.
+fn substitute(command: &[String], settings: &SdrSettings, recording: Option<(u32, &Path)>) -> Vec<String> {
+    let half_band = settings.sample_rate as u64 / 2;
+
+    let mut values = vec![
+        ("{frequency_hz}", settings.frequency_hz.to_string()),
+        ("{gain_db}", settings.gain_db.to_string()),
+        ("{sample_rate}", settings.sample_rate.to_string()),
+        ("{start_hz}", settings.frequency_hz.saturating_sub(half_band).to_string()),
+        ("{end_hz}", (settings.frequency_hz + half_band).to_string()),
+    ];
+
+    if let Some((duration_ms, output)) = recording {
+        values.extend([
+            ("{samples}", (settings.sample_rate as u64 * duration_ms as u64 / 1000).to_string()),
+            ("{duration_s}", (duration_ms as u64).div_ceil(1000).to_string()),
+            ("{output}", output.to_string_lossy().into_owned()),
+        ]);
+    }
+
+    command.iter()
+        .map(|arg| values.iter().fold(arg.clone(), |arg, (placeholder, value)| arg.replace(placeholder, value)))
+        .collect()
+}
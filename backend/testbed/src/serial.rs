@@ -0,0 +1,136 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+
+use crate::config::Serial;
+
+// captured bytes and whether anything was discarded
+type Captured = io::Result<(Vec<u8>, bool)>;
+
+/// Records the output of the device's UART while a job is running
+pub struct SerialCapture {
+    stop: Arc<AtomicBool>,
+    reader: Option<JoinHandle<Captured>>,
+}
+
+impl SerialCapture {
+    /// Opens the serial port and starts reading it on a separate thread, at most `limit` bytes are kept
+    pub fn start(serial: &Serial, limit: usize) -> io::Result<Self> {
+        let port = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(&serial.port)?;
+
+        configure(&port, serial.baud_rate)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let reader_stop = stop.clone();
+
+        let reader = std::thread::Builder::new()
+            .name("serial".to_string())
+            .spawn(move || read_until_stopped(port, reader_stop, limit))?;
+
+        Ok(SerialCapture { stop, reader: Some(reader) })
+    }
+
+    /// Stops reading and returns the captured bytes and whether anything was discarded
+    pub fn finish(mut self) -> Captured {
+        self.stop.store(true, Ordering::Relaxed);
+
+        self.reader.take().unwrap().join()
+            .map_err(|_| io::Error::other("serial reader panicked"))?
+    }
+}
+
+impl Drop for SerialCapture {
+    // reader is not left running if the job fails before the capture is finished
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn read_until_stopped(mut port: File, stop: Arc<AtomicBool>, limit: usize) -> Captured {
+    let mut captured = Vec::new();
+    let mut truncated = false;
+    let mut buf = [0u8; 1024];
+
+    loop {
+        // output written right before the job exits is read once more after stopping
+        let stopped = stop.load(Ordering::Relaxed);
+
+        // reads time out periodically, see `configure`
+        let n = match port.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e)
+        };
+
+        let available = limit - captured.len();
+
+        if n > available {
+            truncated = true;
+        }
+
+        captured.extend_from_slice(&buf[..n.min(available)]);
+
+        if stopped {
+            return Ok((captured, truncated));
+        }
+    }
+}
+
+/// Puts the port into raw mode with the given baud rate. Reads return after 100ms without any input.
+fn configure(port: &File, baud_rate: u32) -> io::Result<()> {
+    let speed = speed(baud_rate)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported baud rate {}", baud_rate)))?;
+
+    let fd = port.as_raw_fd();
+
+    unsafe {
+        let mut termios: libc::termios = std::mem::zeroed();
+
+        if libc::tcgetattr(fd, &mut termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        libc::cfmakeraw(&mut termios);
+        termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+        termios.c_cc[libc::VMIN] = 0;
+        termios.c_cc[libc::VTIME] = 1;
+
+        if libc::cfsetispeed(&mut termios, speed) != 0 || libc::cfsetospeed(&mut termios, speed) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // output of the previous job is not attributed to this one
+        if libc::tcflush(fd, libc::TCIFLUSH) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+pub fn speed(baud_rate: u32) -> Option<libc::speed_t> {
+    let speed = match baud_rate {
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        460800 => libc::B460800,
+        921600 => libc::B921600,
+        _ => return None
+    };
+
+    Some(speed)
+}
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
+use std::os::unix::net::UnixListener;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{error, info};
+use serde::Serialize;
+
+use crate::ModelId;
+
+const MAX_RECENT_ERRORS: usize = 20;
+// requests are answered one at a time, a client which does not send its request in time is dropped
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+/// Parses the address the status is served over http on, a port alone is bound on the loopback address
+pub fn parse_address(address: &str) -> Option<SocketAddr> {
+    match address.trim().trim_start_matches(':').parse::<u16>() {
+        Ok(port) => Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)),
+        Err(_) => address.trim().parse::<SocketAddr>().ok()
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ErrorEntry {
+    at: u64,
+    message: String,
+}
+
+#[derive(Default, Serialize)]
+struct State {
+    connected: bool,
+    server_url: Option<String>,
+    connected_at: Option<u64>,
+    current_job: Option<ModelId>,
+    jobs_executed: u64,
+    // reason of the client for not reconnecting, set until the client is restarted
+    halted: Option<String>,
+    recent_errors: VecDeque<ErrorEntry>,
+}
+
+/// State of the client which is reported to the operators through the local status endpoints
+#[derive(Clone, Default)]
+pub struct Status {
+    state: Arc<Mutex<State>>,
+}
+
+impl Status {
+    pub fn set_connected(&self, server_url: String) {
+        let mut state = self.state.lock().unwrap();
+        state.connected = true;
+        state.server_url = Some(server_url);
+        state.connected_at = Some(now());
+    }
+
+    pub fn set_disconnected(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.connected = false;
+        state.connected_at = None;
+    }
+
+    pub fn set_halted(&self, reason: String) {
+        self.record_error(format!("halted, {}", reason));
+        self.state.lock().unwrap().halted = Some(reason);
+    }
+
+    pub fn start_job(&self, job_id: ModelId) {
+        self.state.lock().unwrap().current_job = Some(job_id);
+    }
+
+    pub fn finish_job(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.current_job = None;
+        state.jobs_executed += 1;
+    }
+
+    pub fn record_error(&self, message: String) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.recent_errors.len() == MAX_RECENT_ERRORS {
+            state.recent_errors.pop_front();
+        }
+
+        state.recent_errors.push_back(ErrorEntry { at: now(), message });
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(&*self.state.lock().unwrap()).unwrap()
+    }
+
+    pub fn to_value(&self) -> serde_json::Value {
+        serde_json::to_value(&*self.state.lock().unwrap()).unwrap()
+    }
+
+    /// Writes the status as json to every client connecting to the unix socket, e.g. `nc -U <path>`
+    pub fn serve_unix(&self, path: String) -> std::io::Result<()> {
+        // socket of a previous run is left behind if the client is not stopped gracefully
+        let _ = std::fs::remove_file(path.as_str());
+
+        let listener = UnixListener::bind(path.as_str())?;
+        let status = self.clone();
+
+        info!("Serving status on unix socket {}", path);
+
+        std::thread::Builder::new().name("status-unix".to_string()).spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(|mut stream| stream.write_all(status.to_json().as_bytes()));
+
+                if let Err(e) = result {
+                    error!("serving status is failed, {:?}", e);
+                }
+            }
+        })?;
+
+        Ok(())
+    }
+
+    /// Answers every http request with the status, meant to be bound to a local address. A port alone is bound on
+    /// the loopback address, see `parse_address`.
+    pub fn serve_http(&self, address: String) -> std::io::Result<()> {
+        let address = parse_address(address.as_str())
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid status address {}", address)))?;
+        let listener = TcpListener::bind(address)?;
+        let status = self.clone();
+
+        info!("Serving status on http://{}", address);
+
+        std::thread::Builder::new().name("status-http".to_string()).spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(|mut stream| {
+                    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+                    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+
+                    // request itself is not relevant, only its headers are consumed
+                    let mut reader = BufReader::new((&stream).take(MAX_REQUEST_SIZE));
+                    let mut line = String::new();
+
+                    while reader.read_line(&mut line)? > 2 {
+                        line.clear();
+                    }
+
+                    let body = status.to_json();
+
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                });
+
+                if let Err(e) = result {
+                    error!("serving status is failed, {:?}", e);
+                }
+            }
+        })?;
+
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(parse_address("8041"), Some("127.0.0.1:8041".parse().unwrap()));
+        assert_eq!(parse_address(":8041"), Some("127.0.0.1:8041".parse().unwrap()));
+        assert_eq!(parse_address("0.0.0.0:8041"), Some("0.0.0.0:8041".parse().unwrap()));
+        assert_eq!(parse_address("[::1]:8041"), Some("[::1]:8041".parse().unwrap()));
+        assert_eq!(parse_address("localhost"), None);
+    }
+}
@@ -0,0 +1,34 @@
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+use log::error;
+
+/// Sends the state to the service manager, e.g. `READY=1`. Does nothing if the client is not
+/// started by systemd with `Type=notify`.
+pub fn notify(state: &str) {
+    let socket_path = match std::env::var("NOTIFY_SOCKET") {
+        Ok(socket_path) => socket_path,
+        Err(_) => return
+    };
+
+    let result = UnixDatagram::unbound()
+        .and_then(|socket| socket.send_to(state.as_bytes(), socket_path.as_str()));
+
+    if let Err(e) = result {
+        error!("notifying service manager is failed, {:?}", e);
+    }
+}
+
+/// Interval of the watchdog pings, half of the timeout configured with `WatchdogSec`
+pub fn watchdog_interval() -> Option<Duration> {
+    let timeout = std::env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+
+    // watchdog may be meant for another process
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+
+    Some(Duration::from_micros(timeout / 2))
+}
@@ -0,0 +1,117 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use awc::http::Uri;
+use awc::http::uri::Authority;
+use log::warn;
+use rustls::ClientConfig;
+use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+
+/// TLS configuration of the connections made to the backend
+#[derive(Clone)]
+pub struct Tls {
+    config: Arc<ClientConfig>,
+    // name used for SNI and verifying the server certificate instead of the host in the url
+    server_name: Option<String>,
+}
+
+impl Tls {
+    pub fn config(&self) -> Arc<ClientConfig> {
+        self.config.clone()
+    }
+
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// Replaces the host of the url with the server name, if it is given. Returned address should be
+    /// used for connecting, since the new host may not resolve into the server.
+    /// Host is looked up on the blocking pool of the runtime, so that the actors are not held up by a slow resolver.
+    pub async fn resolve(&self, url: &str) -> Result<(String, Option<SocketAddr>), String> {
+        let server_name = match &self.server_name {
+            Some(server_name) => server_name,
+            None => return Ok((url.to_string(), None))
+        };
+
+        let uri = url.parse::<Uri>().map_err(|e| e.to_string())?;
+        let host = uri.host().ok_or_else(|| format!("url has no host, {}", url))?.to_string();
+        let port = match (uri.port_u16(), uri.scheme_str()) {
+            (Some(port), _) => port,
+            (None, Some("https")) | (None, Some("wss")) => 443,
+            _ => 80
+        };
+
+        let address = tokio::net::lookup_host((host.as_str(), port))
+            .await
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| format!("{} is not resolved", host))?;
+
+        let mut parts = uri.into_parts();
+        parts.authority = Some(format!("{}:{}", server_name, port).parse::<Authority>().map_err(|e| e.to_string())?);
+
+        let uri = Uri::from_parts(parts).map_err(|e| e.to_string())?;
+
+        Ok((uri.to_string(), Some(address)))
+    }
+}
+
+/// Builds the TLS configuration from the env. Given CA bundle is trusted in addition to the system
+/// roots, unless the system roots are disabled. Client certificate is presented to the server, which
+/// can be used for authenticating instead of a token.
+pub fn setup_tls() -> Tls {
+    let ca_file = std::env::var("TLS_CA_FILE").ok();
+    let disable_system_roots = std::env::var("TLS_DISABLE_SYSTEM_ROOTS").map_or(false, |disable| disable == "true");
+
+    let mut config = ClientConfig::new();
+
+    if !disable_system_roots {
+        config.root_store = match rustls_native_certs::load_native_certs() {
+            Ok(store) => store,
+            Err((Some(store), e)) => {
+                warn!("some of the system roots could not be loaded, {:?}", e);
+                store
+            }
+            Err((None, e)) => {
+                warn!("system roots could not be loaded, falling back to the bundled roots, {:?}", e);
+                let mut store = rustls::RootCertStore::empty();
+                store.add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+                store
+            }
+        };
+    }
+
+    match ca_file {
+        Some(ca_file) => {
+            config.root_store.add_pem_file(&mut BufReader::new(File::open(ca_file).expect("Failed to open TLS_CA_FILE")))
+                .expect("Failed to read TLS_CA_FILE");
+        }
+        None if disable_system_roots => panic!("TLS_CA_FILE is not provided in env while system roots are disabled"),
+        None => {}
+    }
+
+    if let Ok(cert_file) = std::env::var("TLS_CLIENT_CERT_FILE") {
+        let key_file = std::env::var("TLS_CLIENT_KEY_FILE").expect("TLS_CLIENT_KEY_FILE is not provided in env");
+
+        let cert_chain = certs(&mut BufReader::new(File::open(cert_file).expect("Failed to open TLS_CLIENT_CERT_FILE")))
+            .expect("Failed to read TLS_CLIENT_CERT_FILE");
+
+        let mut keys = pkcs8_private_keys(&mut BufReader::new(File::open(&key_file).expect("Failed to open TLS_CLIENT_KEY_FILE")))
+            .expect("Failed to read TLS_CLIENT_KEY_FILE");
+
+        if keys.is_empty() {
+            keys = rsa_private_keys(&mut BufReader::new(File::open(&key_file).expect("Failed to open TLS_CLIENT_KEY_FILE")))
+                .expect("Failed to read TLS_CLIENT_KEY_FILE");
+        }
+
+        config.set_single_client_cert(cert_chain, keys.pop().expect("No private key is found in TLS_CLIENT_KEY_FILE"))
+            .expect("Invalid TLS client certificate or key");
+    }
+
+    Tls {
+        config: Arc::new(config),
+        server_name: std::env::var("TLS_SERVER_NAME").ok(),
+    }
+}
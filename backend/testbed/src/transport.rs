@@ -0,0 +1,83 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use awc::{Client, Connector};
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+
+use crate::proxy::{GrpcProxyConnector, Proxy, ProxyConnector};
+use crate::tls::Tls;
+
+// HTTP/2 pings keep the grpc connection alive through the NATs and proxies, connection is dropped if
+// they are not answered in time
+const GRPC_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(20);
+const GRPC_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Describes how the connections to the backend are made
+#[derive(Clone)]
+pub struct Transport {
+    tls: Tls,
+    proxy: Option<Proxy>,
+}
+
+impl Transport {
+    pub fn new(tls: Tls, proxy: Option<Proxy>) -> Self {
+        Transport {
+            tls,
+            proxy,
+        }
+    }
+
+    pub fn client(&self) -> Client {
+        let connector = Connector::new().rustls(self.tls.config());
+
+        match &self.proxy {
+            Some(proxy) => Client::builder()
+                .connector(connector.connector(ProxyConnector::new(proxy.clone())).finish())
+                .finish(),
+            None => Client::builder()
+                .connector(connector.finish())
+                .finish()
+        }
+    }
+
+    /// Connects to the grpc service of the server, urls with the `grpcs` scheme are connected over TLS.
+    pub async fn grpc_channel(&self, url: &str) -> Result<Channel, String> {
+        let (uri, secure) = match (url.strip_prefix("grpcs://"), url.strip_prefix("grpc://")) {
+            (Some(rest), _) => (format!("https://{}", rest), true),
+            (None, Some(rest)) => (format!("http://{}", rest), false),
+            _ => return Err(format!("{} is not a grpc url", url))
+        };
+
+        let mut endpoint = Endpoint::from_shared(uri)
+            .map_err(|e| e.to_string())?
+            .http2_keep_alive_interval(GRPC_KEEPALIVE_INTERVAL)
+            .keep_alive_timeout(GRPC_KEEPALIVE_TIMEOUT)
+            .keep_alive_while_idle(true);
+
+        if secure {
+            let mut config = (*self.tls.config()).clone();
+            // grpc is only served over HTTP/2
+            config.set_protocols(&[b"h2".to_vec()]);
+
+            let mut tls_config = ClientTlsConfig::new().rustls_client_config(config);
+
+            if let Some(server_name) = self.tls.server_name() {
+                tls_config = tls_config.domain_name(server_name);
+            }
+
+            endpoint = endpoint.tls_config(tls_config)
+                .map_err(|e| e.to_string())?;
+        }
+
+        let channel = match &self.proxy {
+            Some(proxy) => endpoint.connect_with_connector(GrpcProxyConnector::new(proxy.clone())).await,
+            None => endpoint.connect().await
+        };
+
+        channel.map_err(|e| format!("{:?}", e))
+    }
+
+    pub async fn resolve(&self, url: &str) -> Result<(String, Option<SocketAddr>), String> {
+        self.tls.resolve(url).await
+    }
+}
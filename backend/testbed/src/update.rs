@@ -0,0 +1,226 @@
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use awc::error::{PayloadError, SendRequestError};
+use awc::http::StatusCode;
+use ring::digest::{digest, SHA256};
+use ring::signature::{ED25519, UnparsedPublicKey};
+
+use shared::websocket_messages::client::ClientUpdate;
+
+use crate::transport::Transport;
+
+const MAX_BINARY_SIZE: usize = 256 * 1024 * 1024;
+
+// set once a new binary is staged, the client restarts into it after shutting down
+static RESTART_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Verifies the manifest of the release with the public key, downloads the release and replaces the running binary
+/// with it once its digest matches the manifest. Releases of other platforms and the ones not newer than the running
+/// version are rejected, so that an older signed release can not be rolled back to.
+pub async fn stage(transport: &Transport, public_key: &[u8], update: &ClientUpdate) -> Result<(), Error> {
+    verify_manifest(public_key, update, env!("CARGO_PKG_VERSION"))?;
+
+    let (url, address) = transport.resolve(update.url.as_str())
+        .await
+        .map_err(|e| Error::Resolve(e))?;
+
+    let mut request = transport.client().get(url);
+
+    if let Some(address) = address {
+        request = request.address(address);
+    }
+
+    let mut response = request
+        .send()
+        .await
+        .map_err(|e| Error::Send(e))?;
+
+    if !response.status().is_success() {
+        return Err(Error::Status(response.status()));
+    }
+
+    let binary = response.body()
+        .limit(MAX_BINARY_SIZE)
+        .await
+        .map_err(|e| Error::Payload(e))?;
+
+    if hex(digest(&SHA256, &binary).as_ref()) != update.sha256.to_lowercase() {
+        return Err(Error::Digest);
+    }
+
+    let exe = std::env::current_exe()
+        .map_err(|e| Error::IO(e))?;
+    // staged next to the binary so that the rename is atomic
+    let staged = exe.with_extension("new");
+
+    std::fs::write(&staged, &binary)
+        .map_err(|e| Error::IO(e))?;
+
+    std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))
+        .map_err(|e| Error::IO(e))?;
+
+    std::fs::rename(&staged, &exe)
+        .map_err(|e| Error::IO(e))?;
+
+    request_restart();
+
+    Ok(())
+}
+
+fn verify_manifest(public_key: &[u8], update: &ClientUpdate, current_version: &str) -> Result<(), Error> {
+    if update.os != std::env::consts::OS || update.arch != std::env::consts::ARCH {
+        return Err(Error::Platform(format!("{}/{}", update.os, update.arch)));
+    }
+
+    if !is_newer(update.version.as_str(), current_version) {
+        return Err(Error::Version(update.version.clone()));
+    }
+
+    let signature = base64::decode(update.signature.as_str())
+        .map_err(|_| Error::Signature)?;
+
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(update.manifest().as_bytes(), &signature)
+        .map_err(|_| Error::Signature)
+}
+
+/// Compares the dotted numeric versions like `1.4.2`, a leading `v` and the pre-release part are ignored.
+/// Versions which can not be parsed are never newer.
+fn is_newer(version: &str, current: &str) -> bool {
+    fn parse(version: &str) -> Option<Vec<u64>> {
+        version.trim_start_matches('v')
+            .split(['-', '+'])
+            .next()?
+            .split('.')
+            .map(|part| part.parse::<u64>().ok())
+            .collect()
+    }
+
+    match (parse(version), parse(current)) {
+        (Some(version), Some(current)) => version > current,
+        _ => false
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn request_restart() {
+    RESTART_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub fn restart_requested() -> bool {
+    RESTART_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Replaces the process with the staged binary, keeping the arguments. Only returns if it fails.
+pub fn restart() -> std::io::Error {
+    match std::env::current_exe() {
+        Ok(exe) => std::process::Command::new(exe)
+            .args(std::env::args_os().skip(1))
+            .exec(),
+        Err(e) => e
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Resolve(String),
+    Send(SendRequestError),
+    Status(StatusCode),
+    Payload(PayloadError),
+    Platform(String),
+    Version(String),
+    Signature,
+    Digest,
+    IO(std::io::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Resolve(e) => write!(f, "resolving update url is failed, {}", e),
+            Error::Send(e) => write!(f, "downloading update is failed, {}", e),
+            Error::Status(status) => write!(f, "update download is rejected with status {}", status),
+            Error::Payload(e) => write!(f, "reading update is failed, {}", e),
+            Error::Platform(platform) => write!(f, "update is built for another platform, {}", platform),
+            Error::Version(version) => write!(f, "update {} is not newer than the running version", version),
+            Error::Signature => write!(f, "signature of the update is invalid"),
+            Error::Digest => write!(f, "digest of the update does not match its manifest"),
+            Error::IO(e) => write!(f, "staging update is failed, {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    use super::*;
+
+    fn signed_update(key_pair: &Ed25519KeyPair, version: &str) -> ClientUpdate {
+        let mut update = ClientUpdate {
+            version: version.to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            url: String::from("https://releases.example.com/testbed"),
+            sha256: hex(digest(&SHA256, b"binary").as_ref()),
+            signature: String::new(),
+        };
+
+        update.signature = base64::encode(key_pair.sign(update.manifest().as_bytes()));
+
+        update
+    }
+
+    #[test]
+    fn compares_versions() {
+        assert!(is_newer("1.2.4", "1.2.3"));
+        assert!(is_newer("1.10.0", "1.9.9"));
+        assert!(is_newer("v2.0.0", "1.9.9"));
+        assert!(is_newer("1.2.3.1", "1.2.3"));
+
+        assert!(!is_newer("1.2.3", "1.2.3"));
+        assert!(!is_newer("1.2.3-rc1", "1.2.3"));
+        assert!(!is_newer("1.2.2", "1.2.3"));
+        assert!(!is_newer("latest", "1.2.3"));
+        assert!(!is_newer("1.2.4", "unknown"));
+    }
+
+    #[test]
+    fn verifies_the_manifest() {
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(&[7; 32]).unwrap();
+        let public_key = key_pair.public_key().as_ref();
+
+        assert!(verify_manifest(public_key, &signed_update(&key_pair, "1.2.4"), "1.2.3").is_ok());
+
+        // older and same releases are rejected even if they are signed
+        assert!(matches!(verify_manifest(public_key, &signed_update(&key_pair, "1.2.3"), "1.2.3"), Err(Error::Version(_))));
+        assert!(matches!(verify_manifest(public_key, &signed_update(&key_pair, "1.0.0"), "1.2.3"), Err(Error::Version(_))));
+
+        let other_key_pair = Ed25519KeyPair::from_seed_unchecked(&[8; 32]).unwrap();
+        assert!(matches!(verify_manifest(public_key, &signed_update(&other_key_pair, "1.2.4"), "1.2.3"), Err(Error::Signature)));
+    }
+
+    #[test]
+    fn rejects_tampered_manifests() {
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(&[7; 32]).unwrap();
+        let public_key = key_pair.public_key().as_ref();
+
+        // signature of a release is not valid for another version or binary
+        let mut update = signed_update(&key_pair, "1.2.4");
+        update.version = String::from("1.2.5");
+        assert!(matches!(verify_manifest(public_key, &update, "1.2.3"), Err(Error::Signature)));
+
+        let mut update = signed_update(&key_pair, "1.2.4");
+        update.sha256 = hex(digest(&SHA256, b"another binary").as_ref());
+        assert!(matches!(verify_manifest(public_key, &update, "1.2.3"), Err(Error::Signature)));
+
+        let mut update = signed_update(&key_pair, "1.2.4");
+        update.arch = String::from("another-arch");
+        assert!(matches!(verify_manifest(public_key, &update, "1.2.3"), Err(Error::Platform(_))));
+    }
+}
@@ -0,0 +1,180 @@
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use shared::websocket_messages::client::ValidateExperiment;
+use shared::websocket_messages::server::{Diagnostic, DiagnosticLevel};
+
+use crate::config::{Backend, Config, HookAction};
+
+// Parses the code read from stdin and looks up its top level imports, prints the diagnostics as json
+const PROBE_SCRIPT: &str = r#"
+import ast, importlib.util, json, sys
+diagnostics = []
+try:
+    tree = ast.parse(sys.stdin.read(), 'job.py')
+except SyntaxError as e:
+    diagnostics.append({'check': 'syntax', 'level': 'Error', 'message': '%s at line %s' % (e.msg, e.lineno)})
+else:
+    modules = set()
+    for node in ast.walk(tree):
+        if isinstance(node, ast.Import):
+            modules.update(alias.name.split('.')[0] for alias in node.names)
+        elif isinstance(node, ast.ImportFrom) and node.module and node.level == 0:
+            modules.add(node.module.split('.')[0])
+    for module in sorted(modules):
+        if importlib.util.find_spec(module) is None:
+            diagnostics.append({'check': 'dependency', 'level': 'Error', 'message': 'module %s is not found' % module})
+print(json.dumps(diagnostics))
+"#;
+
+/// Checks the code with the python of the configured backend and probes the hardware the job needs,
+/// nothing is run on the device
+pub fn validate(config: &Config, request: &ValidateExperiment) -> Vec<Diagnostic> {
+    let mut diagnostics = match probe(config, request.code.as_str()) {
+        Ok(diagnostics) => diagnostics,
+        Err(message) => vec![error("backend", message)]
+    };
+
+    diagnostics.extend(check_hardware(config, request));
+
+    diagnostics
+}
+
+fn probe(config: &Config, code: &str) -> Result<Vec<Diagnostic>, String> {
+    let mut command = python_command(config)?;
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("python of the {:?} backend could not be run, {}", config.backend, e))?;
+
+    // stdin is closed once the code is written so that the probe can read it to the end
+    let write_result = child.stdin.take().unwrap().write_all(code.as_bytes());
+
+    let output = child.wait_with_output()
+        .map_err(|e| format!("python of the {:?} backend could not be run, {}", config.backend, e))?;
+
+    if !output.status.success() {
+        return Err(format!("probing the code is failed, {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    write_result.map_err(|e| format!("writing the code to the probe is failed, {}", e))?;
+
+    serde_json::from_slice::<Vec<Diagnostic>>(&output.stdout)
+        .map_err(|e| format!("output of the probe is invalid, {}", e))
+}
+
+fn python_command(config: &Config) -> Result<Command, String> {
+    let command = match config.backend {
+        Backend::Docker => {
+            let mut command = Command::new("/usr/bin/docker");
+
+            command
+                .args(["run", "--rm", "-i", "--network", "none"])
+                .arg(config.docker_image.as_str())
+                .args(["python", "-c", PROBE_SCRIPT]);
+
+            command
+        }
+        Backend::Process => {
+            let mut command = Command::new("python3");
+
+            command.args(["-c", PROBE_SCRIPT]);
+
+            command
+        }
+        Backend::Wasm => {
+            let wasm = config.wasm.as_ref()
+                .ok_or_else(|| "wasm backend is not configured".to_string())?;
+
+            let mut command = Command::new(wasm.runtime.as_str());
+
+            command.arg("run");
+
+            for dir in &wasm.dirs {
+                command.args(["--dir", dir.as_str()]);
+            }
+
+            command
+                .arg(wasm.module.as_str())
+                .args(["-c", PROBE_SCRIPT]);
+
+            command
+        }
+    };
+
+    Ok(command)
+}
+
+fn check_hardware(config: &Config, request: &ValidateExperiment) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if let Some(serial) = &config.serial {
+        // port is not configured, only its availability is checked
+        let opened = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NOCTTY | libc::O_NONBLOCK)
+            .open(&serial.port);
+
+        if let Err(e) = opened {
+            diagnostics.push(error("hardware", format!("serial port {} is not available, {}", serial.port, e)));
+        }
+    }
+
+    let mut actions = config.hooks.pre_run.iter()
+        .chain(config.hooks.post_run.iter())
+        .collect::<Vec<&HookAction>>();
+
+    for name in &request.hooks {
+        match config.optional_hooks.get(name) {
+            Some(hook) => actions.extend(hook.pre_run.iter().chain(hook.post_run.iter())),
+            None => diagnostics.push(error("hooks", format!("hook {} is not configured on the runner", name)))
+        }
+    }
+
+    for action in actions {
+        match action {
+            HookAction::Gpio { pin, .. } if !Path::new("/sys/class/gpio").exists() => {
+                diagnostics.push(error("hardware", format!("gpio {} is not available, sysfs gpio is missing", pin)));
+            }
+            HookAction::Relay { command } if !program_exists(command[0].as_str()) => {
+                diagnostics.push(error("hardware", format!("relay command {} is not found", command[0])));
+            }
+            _ => {}
+        }
+    }
+
+    if request.has_firmware {
+        match &config.flash {
+            Some(flash) if !program_exists(flash.command[0].as_str()) => {
+                diagnostics.push(error("firmware", format!("flash command {} is not found", flash.command[0])));
+            }
+            Some(_) => {}
+            None => diagnostics.push(error("firmware", "runner is not configured for flashing firmware".to_string()))
+        }
+    }
+
+    diagnostics
+}
+
+/// Looks up the program like a shell would, paths are checked as they are
+fn program_exists(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+
+    std::env::var_os("PATH")
+        .map_or(false, |paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+pub fn error(check: &str, message: String) -> Diagnostic {
+    Diagnostic {
+        check: check.to_string(),
+        level: DiagnosticLevel::Error,
+        message,
+    }
+}
@@ -0,0 +1,192 @@
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use log::{info, warn};
+
+use crate::ModelId;
+
+/// Manages the directories of the jobs under the workspace root
+pub struct Workspace {
+    root: PathBuf,
+    // total size of the job directories, in bytes
+    quota: Option<u64>,
+    // directories of the finished jobs are kept this long, e.g. for debugging
+    retention: Duration,
+    // runner is under disk pressure below this much free space, in bytes
+    min_free_space: u64,
+}
+
+impl Workspace {
+    pub fn new(root: String, quota: Option<u64>, retention: Duration, min_free_space: u64) -> Self {
+        Workspace {
+            root: PathBuf::from(root),
+            quota,
+            retention,
+            min_free_space,
+        }
+    }
+
+    /// Creates an empty directory for the job, after cleaning up the expired ones
+    pub fn prepare(&self, job_id: ModelId) -> io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.root)?;
+
+        self.cleanup(self.retention)?;
+
+        if let Some(quota) = self.quota {
+            // evict the kept directories before giving up
+            if dir_size(&self.root)? >= quota {
+                self.cleanup(Duration::from_secs(0))?;
+            }
+
+            if dir_size(&self.root)? >= quota {
+                return Err(io::Error::other("workspace quota is exceeded"));
+            }
+        }
+
+        let dir = self.root.join(job_id.to_string());
+
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+
+        std::fs::create_dir(&dir)?;
+
+        Ok(dir)
+    }
+
+    /// Removes the directory of the finished job unless it is retained
+    pub fn release(&self, job_id: ModelId) -> io::Result<()> {
+        if self.retention.as_secs() > 0 {
+            return Ok(());
+        }
+
+        let dir = self.root.join(job_id.to_string());
+
+        match std::fs::remove_dir_all(&dir) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(())
+        }
+    }
+
+    /// Removes the job directories which are not modified in the given duration. Other entries of the workspace
+    /// are left alone, e.g. the results kept over a restart.
+    fn cleanup(&self, older_than: Duration) -> io::Result<()> {
+        let now = SystemTime::now();
+
+        for entry in std::fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+
+            let is_job_dir = metadata.is_dir() && entry.file_name().to_str()
+                .is_some_and(|name| name.parse::<ModelId>().is_ok());
+
+            if !is_job_dir {
+                continue;
+            }
+
+            let modified = metadata.modified()?;
+
+            if now.duration_since(modified).unwrap_or_default() < older_than {
+                continue;
+            }
+
+            info!("removing old workspace {:?}", entry.path());
+
+            if let Err(e) = std::fs::remove_dir_all(entry.path()) {
+                warn!("removing old workspace {:?} is failed, {:?}", entry.path(), e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Free space available on the file system of the workspace, in bytes
+    pub fn free_space(&self) -> io::Result<u64> {
+        std::fs::create_dir_all(&self.root)?;
+
+        let path = CString::new(self.root.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+
+    pub fn is_under_pressure(&self, free_space: u64) -> bool {
+        free_space < self.min_free_space
+    }
+}
+
+fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        size += if metadata.is_dir() { dir_size(&entry.path())? } else { metadata.len() };
+    }
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(name: &str, retention: Duration) -> Workspace {
+        let root = std::env::temp_dir().join(format!("nrg-testbed-workspace-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+
+        Workspace::new(root.to_str().unwrap().to_string(), None, retention, 0)
+    }
+
+    fn make_old(path: &Path) {
+        std::fs::File::open(path).unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(60 * 60))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_without_retention_only_removes_job_dirs() {
+        let workspace = workspace("zero", Duration::from_secs(0));
+        std::fs::create_dir(workspace.root.join("1")).unwrap();
+        std::fs::create_dir(workspace.root.join("cache")).unwrap();
+        std::fs::write(workspace.root.join("pending_results.json"), "[]").unwrap();
+
+        workspace.cleanup(Duration::from_secs(0)).unwrap();
+
+        let job_dir_exists = workspace.root.join("1").exists();
+        let cache_exists = workspace.root.join("cache").exists();
+        let file_exists = workspace.root.join("pending_results.json").exists();
+        std::fs::remove_dir_all(&workspace.root).unwrap();
+
+        assert!(!job_dir_exists);
+        assert!(cache_exists);
+        assert!(file_exists);
+    }
+
+    #[test]
+    fn test_cleanup_keeps_retained_job_dirs() {
+        let workspace = workspace("retained", Duration::from_secs(60));
+        std::fs::create_dir(workspace.root.join("1")).unwrap();
+        std::fs::create_dir(workspace.root.join("2")).unwrap();
+        make_old(&workspace.root.join("1"));
+
+        workspace.cleanup(workspace.retention).unwrap();
+
+        let old_exists = workspace.root.join("1").exists();
+        let recent_exists = workspace.root.join("2").exists();
+        std::fs::remove_dir_all(&workspace.root).unwrap();
+
+        assert!(!old_exists);
+        assert!(recent_exists);
+    }
+}
@@ -0,0 +1,67 @@
+use std::cell::Cell;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use awc::ws::Message;
+use futures::Sink;
+
+/// Size of the messages which are queued for the server but not handed to the socket yet. `SinkWrite`
+/// buffers the messages without a limit, so this is how a server which does not keep up is noticed.
+#[derive(Clone, Default)]
+pub struct WriteBuffer(Rc<Cell<usize>>);
+
+impl WriteBuffer {
+    pub fn queued(&self) -> usize {
+        self.0.get()
+    }
+
+    pub fn add(&self, message: &Message) {
+        self.0.set(self.0.get() + size(message));
+    }
+
+    pub fn drain(&self, message: &Message) {
+        self.0.set(self.0.get().saturating_sub(size(message)));
+    }
+}
+
+fn size(message: &Message) -> usize {
+    match message {
+        Message::Text(text) => text.len(),
+        Message::Binary(bytes) | Message::Ping(bytes) | Message::Pong(bytes) => bytes.len(),
+        _ => 0
+    }
+}
+
+/// Drains the buffer as the messages are taken by the underlying sink
+pub struct TrackedSink<S> {
+    sink: S,
+    buffer: WriteBuffer,
+}
+
+impl<S> TrackedSink<S> {
+    pub fn new(sink: S, buffer: WriteBuffer) -> Self {
+        TrackedSink { sink, buffer }
+    }
+}
+
+impl<S: Sink<Message> + Unpin> Sink<Message> for TrackedSink<S> {
+    type Error = S::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.sink).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        self.buffer.drain(&item);
+        Pin::new(&mut self.sink).start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.sink).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.sink).poll_close(cx)
+    }
+}
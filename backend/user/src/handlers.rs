@@ -1,20 +1,51 @@
-use actix_web::{get, HttpResponse, put, Result, web};
+use actix_web::{delete, get, HttpRequest, HttpResponse, post, put, Result, web};
+use chrono::{DateTime, Duration, Utc};
 use diesel::prelude::*;
+use validator::Validate;
 
-use core::responses::SuccessResponse;
+use core::error::{ErrorMessaging, ValidationError};
+use core::ErrorMessage;
+use core::models::audit_log::AuditEntry;
+use core::models::token::{AuthToken, OidcState, Scope};
+use core::responses::{RedirectResponse, SuccessResponse};
 use core::sanitized::SanitizedJson;
-use core::schema::users;
-use core::types::DBPool;
+use core::schema::{sessions, user_api_keys, user_identities, users};
+use core::types::{DBPool, ModelId};
 use core::utils::Hash;
+use service::ClientServices;
 
+use crate::models::api_key::{API_KEY_COLUMNS, ApiKey, CreatedApiKey};
+use crate::ErrorMessage as UserErrorMessage;
+use crate::models::identity::{Identity, IDENTITY_COLUMNS};
+use crate::models::impersonation::{self, IMPERSONATION_TIMEOUT, ImpersonationToken};
+use crate::models::login_throttle::{Lockout, LoginThrottle};
+use crate::models::session::{Session, SESSION_COLUMNS, SessionResponse};
+use crate::models::two_factor::{RecoveryCodes, TwoFactor, TwoFactorQrPayload, TwoFactorSecret, TwoFactorStatus};
 use crate::models::user::User;
-use crate::requests::{UpdatePasswordRequest, UpdateProfileRequest};
+use crate::requests::{CreateApiKeyRequest, ImpersonateRequest, OidcCallbackRequest, TwoFactorCodeRequest, UnlockAccountRequest, UpdatePasswordRequest, UpdateProfileRequest};
 
+const DEFAULT_API_KEY_EXPIRE_DAYS: i64 = 90;
+
+#[utoipa::path(
+    get,
+    path = "/profile",
+    tag = "user",
+    responses((status = 200, body = User)),
+    security(("bearer" = [])),
+)]
 #[get("/profile")]
 pub async fn fetch_profile(user: User) -> Result<HttpResponse> {
     Ok(HttpResponse::Ok().json(user))
 }
 
+#[utoipa::path(
+    put,
+    path = "/profile",
+    tag = "user",
+    request_body = UpdateProfileRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
 #[put("/profile")]
 pub async fn update_profile(pool: web::Data<DBPool>, user: User, request: SanitizedJson<UpdateProfileRequest>) -> Result<HttpResponse> {
     let conn = pool.get().unwrap();
@@ -29,8 +60,19 @@ pub async fn update_profile(pool: web::Data<DBPool>, user: User, request: Saniti
     Ok(HttpResponse::Ok().json(SuccessResponse::default()))
 }
 
+#[utoipa::path(
+    put,
+    path = "/password",
+    tag = "user",
+    request_body = UpdatePasswordRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
 #[put("/password")]
-pub async fn update_password(pool: web::Data<DBPool>, hash: web::Data<Hash>, user: User, request: web::Json<UpdatePasswordRequest>) -> Result<HttpResponse> {
+pub async fn update_password(pool: web::Data<DBPool>, req: HttpRequest, hash: web::Data<Hash>, user: User, request: web::Json<UpdatePasswordRequest>)
+                             -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
     let conn = pool.get().unwrap();
 
     web::block(move || {
@@ -43,4 +85,734 @@ pub async fn update_password(pool: web::Data<DBPool>, hash: web::Data<Hash>, use
         .await?;
 
     Ok(HttpResponse::Ok().json(SuccessResponse::default()))
-}
\ No newline at end of file
+}
+
+#[utoipa::path(
+    get,
+    path = "/api-keys",
+    tag = "user",
+    responses((status = 200, body = Vec<ApiKey>)),
+    security(("bearer" = [])),
+)]
+#[get("/api-keys")]
+pub async fn fetch_api_keys(pool: web::Data<DBPool>, user: User) -> Result<HttpResponse> {
+    let conn = pool.get().unwrap();
+
+    let api_keys = web::block(move || user_api_keys::table
+        .filter(user_api_keys::user_id.eq(user.id))
+        .order(user_api_keys::created_at.desc())
+        .select(API_KEY_COLUMNS)
+        .load::<ApiKey>(&conn)
+    )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(api_keys))
+}
+
+/// Creates an api key for the scripts and the command line client of the user. Returned token is used
+/// like the token of the login, but it stays valid until the key expires or is revoked. Keys are only created with
+/// the token of a login, not with the token of another key.
+#[utoipa::path(
+    post,
+    path = "/api-key",
+    tag = "user",
+    request_body = CreateApiKeyRequest,
+    responses((status = 200, body = CreatedApiKey)),
+    security(("bearer" = [])),
+)]
+#[post("/api-key")]
+pub async fn create_api_key(pool: web::Data<DBPool>, req: HttpRequest, hash: web::Data<Hash>, user: User, request: SanitizedJson<CreateApiKeyRequest>)
+                            -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    require_session(&req)?;
+
+    let conn = pool.get().unwrap();
+    let request = request.into_inner();
+
+    request.validate()
+        .map_err(ValidationError::from)?;
+
+    let user_id = user.id;
+    let now = Utc::now();
+    let expires_at = now + Duration::days(request.expires_in_days.unwrap_or(DEFAULT_API_KEY_EXPIRE_DAYS));
+
+    let api_key = web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let api_key = diesel::insert_into(user_api_keys::table)
+            .values((
+                user_api_keys::user_id.eq(user_id),
+                user_api_keys::name.eq(request.name),
+                user_api_keys::expires_at.eq(expires_at.naive_utc())
+            ))
+            .returning(API_KEY_COLUMNS)
+            .get_result::<ApiKey>(&conn)?;
+
+        AuditEntry::new(Some(user_id), "api_key.create")
+            .target("api_key", api_key.id)
+            .details(api_key.name.clone())
+            .record(&conn)?;
+
+        Ok(api_key)
+    }))
+        .await?;
+
+    let token = hash.encode(&AuthToken::api_key(
+        user.id,
+        user.role_id,
+        api_key.id,
+        now.timestamp(),
+        expires_at.timestamp(),
+    ))?;
+
+    Ok(HttpResponse::Ok().json(CreatedApiKey { api_key, token }))
+}
+
+/// Revokes the api key, requests made with its token are rejected afterwards.
+#[utoipa::path(
+    delete,
+    path = "/api-key/{id}",
+    tag = "user",
+    params(("id" = ModelId, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[delete("/api-key/{id}")]
+pub async fn delete_api_key(pool: web::Data<DBPool>, req: HttpRequest, api_key_id: web::Path<ModelId>, user: User) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
+    let conn = pool.get().unwrap();
+
+    let api_key_id = api_key_id.into_inner();
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let deleted = diesel::delete(
+            user_api_keys::table
+                .filter(user_api_keys::user_id.eq(user.id))
+                .find(api_key_id)
+        )
+            .execute(&conn)?;
+
+        if deleted == 0 {
+            return Err(diesel::result::Error::NotFound);
+        }
+
+        AuditEntry::new(Some(user.id), "api_key.revoke")
+            .target("api_key", api_key_id)
+            .record(&conn)?;
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Live sessions of the user, i.e. the devices the user is logged in, most recently used first
+#[utoipa::path(
+    get,
+    path = "/me/sessions",
+    tag = "user",
+    responses((status = 200, body = Vec<SessionResponse>)),
+    security(("bearer" = [])),
+)]
+#[get("/me/sessions")]
+pub async fn fetch_sessions(pool: web::Data<DBPool>, req: HttpRequest, user: User) -> Result<HttpResponse> {
+    let conn = pool.get().unwrap();
+    let current_session_id = current_session_id(&req);
+
+    let sessions = web::block(move || sessions::table
+        .filter(sessions::user_id.eq(user.id))
+        .filter(sessions::expires_at.gt(Utc::now().naive_utc()))
+        .order(sessions::last_used_at.desc())
+        .select(SESSION_COLUMNS)
+        .load::<Session>(&conn)
+    )
+        .await?
+        .into_iter()
+        .map(|session| SessionResponse { current: Some(session.id) == current_session_id, session })
+        .collect::<Vec<SessionResponse>>();
+
+    Ok(HttpResponse::Ok().json(sessions))
+}
+
+/// Revokes the session, its refresh token and access tokens are rejected afterwards.
+#[utoipa::path(
+    delete,
+    path = "/me/session/{id}",
+    tag = "user",
+    params(("id" = ModelId, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[delete("/me/session/{id}")]
+pub async fn delete_session(pool: web::Data<DBPool>, req: HttpRequest, session_id: web::Path<ModelId>, user: User) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
+    let conn = pool.get().unwrap();
+
+    let session_id = session_id.into_inner();
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let deleted = diesel::delete(
+            sessions::table
+                .filter(sessions::user_id.eq(user.id))
+                .find(session_id)
+        )
+            .execute(&conn)?;
+
+        if deleted == 0 {
+            return Err(diesel::result::Error::NotFound);
+        }
+
+        AuditEntry::new(Some(user.id), "session.revoke")
+            .target("session", session_id)
+            .record(&conn)?;
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Revokes all the sessions of the user except the one making the request, e.g. after the user loses a device.
+#[utoipa::path(
+    delete,
+    path = "/me/sessions",
+    tag = "user",
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[delete("/me/sessions")]
+pub async fn delete_other_sessions(pool: web::Data<DBPool>, req: HttpRequest, user: User) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
+    let conn = pool.get().unwrap();
+    let current_session_id = current_session_id(&req);
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let mut query = diesel::delete(sessions::table)
+            .filter(sessions::user_id.eq(user.id))
+            .into_boxed();
+
+        if let Some(current_session_id) = current_session_id {
+            query = query.filter(sessions::id.ne(current_session_id));
+        }
+
+        let deleted = query.execute(&conn)?;
+
+        AuditEntry::new(Some(user.id), "session.revoke_others")
+            .details(format!("{} sessions", deleted))
+            .record(&conn)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/two-factor",
+    tag = "user",
+    responses((status = 200, body = TwoFactorStatus)),
+    security(("bearer" = [])),
+)]
+#[get("/two-factor")]
+pub async fn fetch_two_factor(pool: web::Data<DBPool>, user: User) -> Result<HttpResponse> {
+    let conn = pool.get().unwrap();
+
+    let status = web::block(move || TwoFactor::status(user.id, &conn))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// Starts the enrollment of the two factor authentication by provisioning a new secret. Enrollment is completed
+/// once a code generated with the secret is confirmed.
+#[utoipa::path(
+    post,
+    path = "/two-factor",
+    tag = "user",
+    responses((status = 200, body = TwoFactorSecret)),
+    security(("bearer" = [])),
+)]
+#[post("/two-factor")]
+pub async fn provision_two_factor(pool: web::Data<DBPool>, req: HttpRequest, user: User) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
+    let conn = pool.get().unwrap();
+
+    let secret = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(||
+        TwoFactor::provision(user.id, &conn)?
+            .ok_or_else(|| UserErrorMessage::TwoFactorAlreadyEnabled.into())
+    ))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(TwoFactorSecret { secret }))
+}
+
+/// Payload of the QR code the authenticator apps scan for the pending enrollment
+#[utoipa::path(
+    get,
+    path = "/two-factor/qr",
+    tag = "user",
+    responses((status = 200, body = TwoFactorQrPayload)),
+    security(("bearer" = [])),
+)]
+#[get("/two-factor/qr")]
+pub async fn fetch_two_factor_qr(pool: web::Data<DBPool>, req: HttpRequest, user: User) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
+    let conn = pool.get().unwrap();
+
+    let payload = web::block(move || -> Result<_, Box<dyn ErrorMessaging>> {
+        TwoFactor::qr_payload(user.id, user.email.as_str(), &conn)?
+            .ok_or_else(|| UserErrorMessage::TwoFactorNotPending.into())
+    })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(TwoFactorQrPayload { payload }))
+}
+
+/// Completes the pending enrollment with a code of the authenticator app. Returned recovery codes are shown only
+/// once.
+#[utoipa::path(
+    post,
+    path = "/two-factor/enable",
+    tag = "user",
+    request_body = TwoFactorCodeRequest,
+    responses((status = 200, body = RecoveryCodes)),
+    security(("bearer" = [])),
+)]
+#[post("/two-factor/enable")]
+pub async fn enable_two_factor(pool: web::Data<DBPool>, req: HttpRequest, hash: web::Data<Hash>, user: User, request: web::Json<TwoFactorCodeRequest>)
+                               -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
+    let conn = pool.get().unwrap();
+
+    let codes = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let codes = TwoFactor::enable(user.id, request.code.as_str(), &hash, &conn)?
+            .ok_or(UserErrorMessage::InvalidTwoFactorCode)?;
+
+        AuditEntry::new(Some(user.id), "two_factor.enable")
+            .record(&conn)?;
+
+        Ok(codes)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodes { codes }))
+}
+
+/// Disables the two factor authentication, a code of the authenticator app or a recovery code is required.
+#[utoipa::path(
+    post,
+    path = "/two-factor/disable",
+    tag = "user",
+    request_body = TwoFactorCodeRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[post("/two-factor/disable")]
+pub async fn disable_two_factor(pool: web::Data<DBPool>, req: HttpRequest, hash: web::Data<Hash>, user: User, request: web::Json<TwoFactorCodeRequest>)
+                                -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
+    let conn = pool.get().unwrap();
+
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        if !TwoFactor::verify(user.id, request.code.as_str(), &hash, &conn)? {
+            return Err(UserErrorMessage::InvalidTwoFactorCode.into());
+        }
+
+        TwoFactor::disable(user.id, &conn)?;
+
+        AuditEntry::new(Some(user.id), "two_factor.disable")
+            .record(&conn)?;
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Replaces the recovery codes of the user, e.g. after most of them are used. A code of the authenticator app or a
+/// recovery code is required.
+#[utoipa::path(
+    post,
+    path = "/two-factor/recovery-codes",
+    tag = "user",
+    request_body = TwoFactorCodeRequest,
+    responses((status = 200, body = RecoveryCodes)),
+    security(("bearer" = [])),
+)]
+#[post("/two-factor/recovery-codes")]
+pub async fn regenerate_recovery_codes(pool: web::Data<DBPool>, req: HttpRequest, hash: web::Data<Hash>, user: User, request: web::Json<TwoFactorCodeRequest>)
+                                       -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
+    let conn = pool.get().unwrap();
+
+    let codes = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        if !TwoFactor::verify(user.id, request.code.as_str(), &hash, &conn)? {
+            return Err(UserErrorMessage::InvalidTwoFactorCode.into());
+        }
+
+        let codes = TwoFactor::regenerate_recovery_codes(user.id, &hash, &conn)?;
+
+        AuditEntry::new(Some(user.id), "two_factor.recovery_codes")
+            .record(&conn)?;
+
+        Ok(codes)
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodes { codes }))
+}
+
+/// Accounts of the OpenID Connect provider linked to the user
+#[utoipa::path(
+    get,
+    path = "/identities",
+    tag = "user",
+    responses((status = 200, body = Vec<Identity>)),
+    security(("bearer" = [])),
+)]
+#[get("/identities")]
+pub async fn fetch_identities(pool: web::Data<DBPool>, user: User) -> Result<HttpResponse> {
+    let conn = pool.get().unwrap();
+
+    let identities = web::block(move || user_identities::table
+        .filter(user_identities::user_id.eq(user.id))
+        .order(user_identities::created_at.desc())
+        .select(IDENTITY_COLUMNS)
+        .load::<Identity>(&conn)
+    )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(identities))
+}
+
+/// Login page of the OpenID Connect provider for linking its account to the user. Provider redirects back to the
+/// web app, which completes the linking with the returned code and state.
+#[utoipa::path(
+    get,
+    path = "/identity/authorize",
+    tag = "user",
+    responses((status = 200, body = RedirectResponse)),
+    security(("bearer" = [])),
+)]
+#[get("/identity/authorize")]
+pub async fn authorize_identity(req: HttpRequest, hash: web::Data<Hash>, client_services: web::Data<ClientServices>, user: User)
+                                -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
+    let oidc = client_services.oidc.as_ref()
+        .ok_or(UserErrorMessage::OidcNotConfigured)?;
+
+    let state = OidcState::new(Some(user.id));
+
+    let url = oidc.authorization_url(hash.encode(&state)?.as_str(), state.nonce.as_str())
+        .await
+        .map_err(|_| UserErrorMessage::OidcFailed)?;
+
+    Ok(HttpResponse::Ok().json(RedirectResponse { url }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/identity",
+    tag = "user",
+    request_body = OidcCallbackRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[post("/identity")]
+pub async fn link_identity(
+    pool: web::Data<DBPool>,
+    req: HttpRequest,
+    hash: web::Data<Hash>,
+    client_services: web::Data<ClientServices>,
+    user: User,
+    request: web::Json<OidcCallbackRequest>,
+) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
+    let conn = pool.get().unwrap();
+
+    let oidc = client_services.oidc.clone()
+        .ok_or(UserErrorMessage::OidcNotConfigured)?;
+
+    let state = hash.decode::<OidcState>(request.state.as_str())
+        .map_err(|_| ErrorMessage::InvalidToken)?;
+
+    // state is bound to the user starting the linking, so that an account of the provider can not be linked to
+    // another user by tricking them into completing the linking
+    if state.user_id != Some(user.id) {
+        return Err(ErrorMessage::InvalidToken.into());
+    }
+
+    let claims = oidc.exchange(request.code.as_str(), state.nonce.as_str())
+        .await
+        .map_err(|_| UserErrorMessage::OidcFailed)?;
+
+    web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        if !Identity::link(user.id, oidc.issuer(), claims.subject.as_str(), claims.email.as_deref(), &conn)? {
+            return Err(UserErrorMessage::IdentityAlreadyLinked.into());
+        }
+
+        AuditEntry::new(Some(user.id), "identity.link")
+            .details(oidc.issuer().to_string())
+            .record(&conn)?;
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Unlinks the account of the OpenID Connect provider, the user can not sign in with it afterwards.
+#[utoipa::path(
+    delete,
+    path = "/identity/{id}",
+    tag = "user",
+    params(("id" = ModelId, Path)),
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[delete("/identity/{id}")]
+pub async fn delete_identity(pool: web::Data<DBPool>, req: HttpRequest, identity_id: web::Path<ModelId>, user: User) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    deny_impersonation(&req)?;
+
+    let conn = pool.get().unwrap();
+
+    let identity_id = identity_id.into_inner();
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let deleted = diesel::delete(
+            user_identities::table
+                .filter(user_identities::user_id.eq(user.id))
+                .find(identity_id)
+        )
+            .execute(&conn)?;
+
+        if deleted == 0 {
+            return Err(diesel::result::Error::NotFound);
+        }
+
+        AuditEntry::new(Some(user.id), "identity.unlink")
+            .target("identity", identity_id)
+            .record(&conn)?;
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Accounts that failed to log in recently, along with the time they are locked until if they are locked
+#[utoipa::path(
+    get,
+    path = "/admin/lockouts",
+    tag = "admin",
+    responses((status = 200, body = Vec<Lockout>)),
+    security(("bearer" = [])),
+)]
+#[get("/admin/lockouts")]
+pub async fn fetch_lockouts(pool: web::Data<DBPool>, user: User) -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    if !user.is_admin() {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+
+    let lockouts = web::block(move || LoginThrottle::lockouts(&conn))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(lockouts))
+}
+
+/// Forgets the failed logins of the account, the account can log in again without waiting.
+#[utoipa::path(
+    post,
+    path = "/admin/unlock",
+    tag = "admin",
+    request_body = UnlockAccountRequest,
+    responses((status = 200, body = SuccessResponse)),
+    security(("bearer" = [])),
+)]
+#[post("/admin/unlock")]
+pub async fn unlock_account(pool: web::Data<DBPool>, user: User, request: web::Json<UnlockAccountRequest>)
+                            -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    if !user.is_admin() {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let request = request.into_inner();
+
+    web::block(move || conn.transaction::<_, diesel::result::Error, _>(|| {
+        let cleared = LoginThrottle::clear(request.email.as_str(), &conn)?;
+
+        if cleared > 0 {
+            AuditEntry::new(Some(user.id), "user.unlock")
+                .details(request.email)
+                .record(&conn)?;
+        }
+
+        Ok(())
+    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessResponse::default()))
+}
+
+/// Issues a short lived token acting as the user, so that the admin can reproduce the reports of the user exactly as
+/// they see the app. Issuing the token and each request made with it are recorded in the audit log. Admins can not
+/// be impersonated.
+#[utoipa::path(
+    post,
+    path = "/admin/impersonate",
+    tag = "admin",
+    request_body = ImpersonateRequest,
+    responses((status = 200, body = ImpersonationToken)),
+    security(("bearer" = [])),
+)]
+#[post("/admin/impersonate")]
+pub async fn impersonate_user(pool: web::Data<DBPool>, req: HttpRequest, hash: web::Data<Hash>, user: User, request: SanitizedJson<ImpersonateRequest>)
+                              -> Result<HttpResponse, Box<dyn ErrorMessaging>> {
+    if !user.is_admin() {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    // impersonation tokens are not issued through another one
+    deny_impersonation(&req)?;
+
+    let request = request.into_inner();
+
+    request.validate()
+        .map_err(ValidationError::from)?;
+
+    if request.user_id == user.id {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    let conn = pool.get().unwrap();
+    let admin_id = user.id;
+
+    let impersonated = web::block(move || conn.transaction::<_, Box<dyn ErrorMessaging>, _>(|| {
+        let impersonated = users::table
+            .find(request.user_id)
+            .first::<User>(&conn)
+            .optional()?
+            .ok_or(ErrorMessage::UserNotFound)?;
+
+        if impersonated.is_admin() {
+            return Err(ErrorMessage::NotAllowed.into());
+        }
+
+        AuditEntry::new(Some(admin_id), "user.impersonate")
+            .target("user", impersonated.id)
+            .details(request.reason)
+            .record(&conn)?;
+
+        Ok(impersonated)
+    }))
+        .await?;
+
+    let banner = impersonation::banner(&user, &impersonated);
+    let auth_token = AuthToken::impersonation(impersonated.id, impersonated.role_id, user.id, banner.clone(), IMPERSONATION_TIMEOUT);
+    let expires_at = DateTime::from_timestamp(auth_token.claims.exp, 0).unwrap_or_default().naive_utc();
+
+    Ok(HttpResponse::Ok().json(ImpersonationToken {
+        token: hash.encode(&auth_token)?,
+        banner,
+        expires_at,
+    }))
+}
+
+/// Session of the access token, requests made with the api keys do not have one
+fn current_session_id(req: &HttpRequest) -> Option<ModelId> {
+    req.extensions().get::<AuthToken>().and_then(|token| token.session_id)
+}
+
+/// Credentials, sessions and linked accounts of the user can not be changed with an impersonation token, they would
+/// let the admin act as the user after the token expires or lock the user out
+fn deny_impersonation(req: &HttpRequest) -> Result<(), Box<dyn ErrorMessaging>> {
+    if req.extensions().get::<AuthToken>().is_some_and(|token| token.impersonator_id.is_some()) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    Ok(())
+}
+
+/// Api keys are only created with the tokens of the login sessions, otherwise a leaked api key could create the
+/// keys which outlive its own revoke. Impersonation tokens do not have a session either.
+fn require_session(req: &HttpRequest) -> Result<(), Box<dyn ErrorMessaging>> {
+    if !req.extensions().get::<AuthToken>().is_some_and(is_session_token) {
+        return Err(ErrorMessage::NotAllowed.into());
+    }
+
+    Ok(())
+}
+
+fn is_session_token(token: &AuthToken) -> bool {
+    token.claims.has_scope(Scope::Session) &&
+        token.session_id.is_some() &&
+        token.api_key_id.is_none() &&
+        token.impersonator_id.is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use core::models::role::Roles;
+    use core::types::UserId;
+
+    use super::*;
+
+    fn request_with(token: AuthToken) -> HttpRequest {
+        let req = TestRequest::default().to_http_request();
+        req.extensions_mut().insert(token);
+        req
+    }
+
+    fn session_token() -> AuthToken {
+        AuthToken::new(UserId(1), Roles::User as ModelId, 1, 60)
+    }
+
+    fn api_key_token() -> AuthToken {
+        let now = Utc::now().timestamp();
+        AuthToken::api_key(UserId(1), Roles::User as ModelId, 1, now, now + 60)
+    }
+
+    fn impersonation_token() -> AuthToken {
+        AuthToken::impersonation(UserId(1), Roles::User as ModelId, UserId(2), String::from("banner"), 60)
+    }
+
+    #[test]
+    fn rejects_impersonation_tokens() {
+        assert!(deny_impersonation(&request_with(impersonation_token())).is_err());
+
+        assert!(deny_impersonation(&request_with(session_token())).is_ok());
+        assert!(deny_impersonation(&request_with(api_key_token())).is_ok());
+    }
+
+    #[test]
+    fn requires_session_tokens() {
+        assert!(require_session(&request_with(session_token())).is_ok());
+
+        assert!(require_session(&request_with(api_key_token())).is_err());
+        assert!(require_session(&request_with(impersonation_token())).is_err());
+        assert!(require_session(&TestRequest::default().to_http_request()).is_err());
+
+        // scope alone is not enough, a token of an api key carrying it is rejected as well
+        let mut token = api_key_token();
+        token.claims.scopes.push(Scope::Session);
+        token.session_id = Some(1);
+        assert!(require_session(&request_with(token)).is_err());
+    }
+}
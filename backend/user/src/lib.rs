@@ -1,14 +1,44 @@
 #[macro_use]
 extern crate diesel;
 
+use actix_web::http::StatusCode;
 use actix_web::web;
+use utoipa::OpenApi;
 
+use core::error::{ErrorMessaging, HttpError};
 use core::middlewares::auth::Auth;
 
 mod handlers;
 pub mod models;
 mod requests;
 
+#[derive(OpenApi)]
+#[openapi(paths(
+    handlers::fetch_profile,
+    handlers::update_profile,
+    handlers::update_password,
+    handlers::fetch_api_keys,
+    handlers::create_api_key,
+    handlers::delete_api_key,
+    handlers::fetch_sessions,
+    handlers::delete_session,
+    handlers::delete_other_sessions,
+    handlers::fetch_two_factor,
+    handlers::provision_two_factor,
+    handlers::fetch_two_factor_qr,
+    handlers::enable_two_factor,
+    handlers::disable_two_factor,
+    handlers::regenerate_recovery_codes,
+    handlers::fetch_identities,
+    handlers::authorize_identity,
+    handlers::link_identity,
+    handlers::delete_identity,
+    handlers::fetch_lockouts,
+    handlers::unlock_account,
+    handlers::impersonate_user,
+))]
+pub struct ApiDoc;
+
 pub fn register(config: &mut web::ServiceConfig) {
     config
         .service(
@@ -17,9 +47,87 @@ pub fn register(config: &mut web::ServiceConfig) {
                 .service(handlers::fetch_profile)
                 .service(handlers::update_profile)
                 .service(handlers::update_password)
+                .service(handlers::fetch_api_keys)
+                .service(handlers::create_api_key)
+                .service(handlers::delete_api_key)
+                .service(handlers::fetch_sessions)
+                .service(handlers::delete_session)
+                .service(handlers::delete_other_sessions)
+                .service(handlers::fetch_two_factor)
+                .service(handlers::provision_two_factor)
+                .service(handlers::fetch_two_factor_qr)
+                .service(handlers::enable_two_factor)
+                .service(handlers::disable_two_factor)
+                .service(handlers::regenerate_recovery_codes)
+                .service(handlers::fetch_identities)
+                .service(handlers::authorize_identity)
+                .service(handlers::link_identity)
+                .service(handlers::delete_identity)
+                .service(handlers::fetch_lockouts)
+                .service(handlers::unlock_account)
+                .service(handlers::impersonate_user)
         );
 }
 
+#[derive(Debug)]
+pub enum ErrorMessage {
+    TwoFactorRequired,
+    InvalidTwoFactorCode,
+    TwoFactorAlreadyEnabled,
+    TwoFactorNotPending,
+    OidcNotConfigured,
+    OidcFailed,
+    OidcAccountNotLinked,
+    IdentityAlreadyLinked,
+}
+
+impl ErrorMessaging for ErrorMessage {
+    fn value(&self) -> HttpError {
+        match self {
+            ErrorMessage::TwoFactorRequired => HttpError {
+                code: StatusCode::UNAUTHORIZED,
+                error_code: 140,
+                message: String::from("two_factor_required"),
+            },
+            ErrorMessage::InvalidTwoFactorCode => HttpError {
+                code: StatusCode::UNPROCESSABLE_ENTITY,
+                error_code: 141,
+                message: String::from("invalid_two_factor_code"),
+            },
+            ErrorMessage::TwoFactorAlreadyEnabled => HttpError {
+                code: StatusCode::CONFLICT,
+                error_code: 142,
+                message: String::from("two_factor_already_enabled"),
+            },
+            ErrorMessage::TwoFactorNotPending => HttpError {
+                code: StatusCode::NOT_FOUND,
+                error_code: 143,
+                message: String::from("two_factor_not_pending"),
+            },
+            ErrorMessage::OidcNotConfigured => HttpError {
+                code: StatusCode::NOT_FOUND,
+                error_code: 144,
+                message: String::from("oidc_not_configured"),
+            },
+            ErrorMessage::OidcFailed => HttpError {
+                code: StatusCode::BAD_GATEWAY,
+                error_code: 145,
+                message: String::from("oidc_failed"),
+            },
+            ErrorMessage::OidcAccountNotLinked => HttpError {
+                code: StatusCode::CONFLICT,
+                error_code: 146,
+                message: String::from("oidc_account_not_linked"),
+            },
+            ErrorMessage::IdentityAlreadyLinked => HttpError {
+                code: StatusCode::CONFLICT,
+                error_code: 147,
+                message: String::from("identity_already_linked"),
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
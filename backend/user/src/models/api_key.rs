@@ -0,0 +1,32 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use core::schema::user_api_keys;
+use core::types::ModelId;
+
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKey {
+    pub id: ModelId,
+    pub name: String,
+    pub expires_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+pub const API_KEY_COLUMNS: (user_api_keys::id, user_api_keys::name, user_api_keys::expires_at, user_api_keys::last_used_at, user_api_keys::created_at) = (
+    user_api_keys::id,
+    user_api_keys::name,
+    user_api_keys::expires_at,
+    user_api_keys::last_used_at,
+    user_api_keys::created_at,
+);
+
+/// Newly created api key along with its token, token is only returned once.
+#[derive(Serialize, ToSchema)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub token: String,
+}
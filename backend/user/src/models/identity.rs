@@ -0,0 +1,55 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use core::schema::user_identities;
+use core::types::{ModelId, UserId};
+
+/// Account of the user at the OpenID Connect provider, the user can sign in with it besides the password
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Identity {
+    pub id: ModelId,
+    pub issuer: String,
+    pub email: Option<String>,
+    pub last_login_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+pub const IDENTITY_COLUMNS: (user_identities::id, user_identities::issuer, user_identities::email, user_identities::last_login_at, user_identities::created_at) = (
+    user_identities::id,
+    user_identities::issuer,
+    user_identities::email,
+    user_identities::last_login_at,
+    user_identities::created_at,
+);
+
+impl Identity {
+    /// User the account of the provider is linked to, None is returned if it is not linked yet
+    pub fn sign_in(issuer: &str, subject: &str, conn: &PgConnection) -> QueryResult<Option<UserId>> {
+        diesel::update(user_identities::table
+            .filter(user_identities::issuer.eq(issuer))
+            .filter(user_identities::subject.eq(subject))
+        )
+            .set(user_identities::last_login_at.eq(Utc::now().naive_utc()))
+            .returning(user_identities::user_id)
+            .get_result::<UserId>(conn)
+            .optional()
+    }
+
+    /// Links the account of the provider to the user, false is returned if it is already linked to a user
+    pub fn link(user_id: UserId, issuer: &str, subject: &str, email: Option<&str>, conn: &PgConnection) -> QueryResult<bool> {
+        let inserted = diesel::insert_into(user_identities::table)
+            .values((
+                user_identities::user_id.eq(user_id),
+                user_identities::issuer.eq(issuer),
+                user_identities::subject.eq(subject),
+                user_identities::email.eq(email)
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        Ok(inserted > 0)
+    }
+}
@@ -0,0 +1,21 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::models::user::User;
+
+// impersonation tokens are not refreshed, admins request a new one once it expires
+pub const IMPERSONATION_TIMEOUT: i64 = 60 * 15;
+
+/// Token acting as the user, it is issued to the admins for reproducing the reports of the user
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationToken {
+    pub token: String,
+    pub banner: String,
+    pub expires_at: NaiveDateTime,
+}
+
+pub fn banner(impersonator: &User, user: &User) -> String {
+    format!("{} is impersonating {} <{}>", impersonator.full_name(), user.full_name(), user.email)
+}
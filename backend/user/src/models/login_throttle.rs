@@ -0,0 +1,169 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text, Timestamp};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use core::schema::failed_logins;
+
+// failed logins older than this are not counted, they are removed once a new failure is recorded
+const THROTTLE_WINDOW: i64 = 60 * 60;
+// attempts after these many failures are delayed, the delay doubles with each failure
+const FREE_ATTEMPTS: i64 = 3;
+const MAX_DELAY: i64 = 60;
+const LOCKOUT_ATTEMPTS: i64 = 10;
+// each failure after the lockout attempts locks the account again for this long
+const LOCKOUT_DURATION: i64 = 60 * 15;
+// addresses trying many accounts are blocked regardless of the accounts
+const MAX_IP_FAILURES: i64 = 50;
+// namespaces of the advisory locks the attempts of an account and an address are serialized with
+const EMAIL_LOCK: i32 = 1;
+const IP_LOCK: i32 = 2;
+
+/// Whether the address given by the reverse proxy in X-Forwarded-For is throttled instead of the peer address, it
+/// is only trusted behind a reverse proxy, see `TRUST_FORWARDED_FOR`
+#[derive(Clone)]
+pub struct ThrottlePolicy {
+    pub trust_forwarded_for: bool,
+}
+
+/// Reason a login attempt is rejected before its credentials are checked, along with the seconds to wait
+pub enum Throttle {
+    Delayed(i64),
+    Locked(i64),
+}
+
+/// Account that failed to log in recently, it is locked if `locked_until` is given
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Lockout {
+    pub email: String,
+    pub failed_attempts: i64,
+    pub last_failed_at: NaiveDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+/// Failed logins of the accounts and the addresses. Accounts are tracked by email, so that the unknown accounts
+/// are throttled the same way and the responses do not reveal which emails are registered.
+pub struct LoginThrottle;
+
+impl LoginThrottle {
+    /// Serializes the attempts of the account and the address until the transaction ends, otherwise the parallel
+    /// attempts would be checked before any of their failures is recorded. Account is locked before the address, so
+    /// that the attempts waiting for each other can not deadlock.
+    pub fn lock(email: &str, ip: Option<&str>, conn: &PgConnection) -> QueryResult<()> {
+        diesel::sql_query("SELECT pg_advisory_xact_lock($1, hashtext($2))")
+            .bind::<Integer, _>(EMAIL_LOCK)
+            .bind::<Text, _>(email)
+            .execute(conn)?;
+
+        if let Some(ip) = ip {
+            diesel::sql_query("SELECT pg_advisory_xact_lock($1, hashtext($2))")
+                .bind::<Integer, _>(IP_LOCK)
+                .bind::<Text, _>(ip)
+                .execute(conn)?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether the login attempt must be rejected without checking its credentials. It should be called after
+    /// `lock` within the same transaction as `record_failure`.
+    pub fn check(email: &str, ip: Option<&str>, conn: &PgConnection) -> QueryResult<Option<Throttle>> {
+        let now = Utc::now().naive_utc();
+        let window_start = now - Duration::seconds(THROTTLE_WINDOW);
+
+        if let Some(ip) = ip {
+            let ip_failures = failed_logins::table
+                .filter(failed_logins::ip.eq(ip))
+                .filter(failed_logins::created_at.gt(window_start))
+                .count()
+                .get_result::<i64>(conn)?;
+
+            if ip_failures >= MAX_IP_FAILURES {
+                return Ok(Some(Throttle::Locked(LOCKOUT_DURATION)));
+            }
+        }
+
+        let (failures, last_failed_at) = failed_logins::table
+            .filter(failed_logins::email.eq(email))
+            .filter(failed_logins::created_at.gt(window_start))
+            .select((sql::<BigInt>("COUNT(*)"), sql::<Nullable<Timestamp>>("MAX(created_at)")))
+            .first::<(i64, Option<NaiveDateTime>)>(conn)?;
+
+        let last_failed_at = match last_failed_at {
+            Some(last_failed_at) => last_failed_at,
+            None => return Ok(None)
+        };
+
+        let elapsed = (now - last_failed_at).num_seconds();
+
+        if failures >= LOCKOUT_ATTEMPTS {
+            if elapsed < LOCKOUT_DURATION {
+                return Ok(Some(Throttle::Locked(LOCKOUT_DURATION - elapsed)));
+            }
+        } else if failures >= FREE_ATTEMPTS {
+            let delay = 2i64.pow((failures - FREE_ATTEMPTS) as u32).min(MAX_DELAY);
+
+            if elapsed < delay {
+                return Ok(Some(Throttle::Delayed(delay - elapsed)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Records the failed login, true is returned if the account is locked with it
+    pub fn record_failure(email: &str, ip: Option<&str>, conn: &PgConnection) -> QueryResult<bool> {
+        let window_start = Utc::now().naive_utc() - Duration::seconds(THROTTLE_WINDOW);
+
+        diesel::delete(failed_logins::table.filter(failed_logins::created_at.le(window_start)))
+            .execute(conn)?;
+
+        diesel::insert_into(failed_logins::table)
+            .values((
+                failed_logins::email.eq(email),
+                failed_logins::ip.eq(ip)
+            ))
+            .execute(conn)?;
+
+        let failures = failed_logins::table
+            .filter(failed_logins::email.eq(email))
+            .count()
+            .get_result::<i64>(conn)?;
+
+        Ok(failures >= LOCKOUT_ATTEMPTS)
+    }
+
+    /// Forgets the failed logins of the account, e.g. once the user logs in or an admin unlocks it. Returns the
+    /// number of the forgotten failures.
+    pub fn clear(email: &str, conn: &PgConnection) -> QueryResult<usize> {
+        diesel::delete(failed_logins::table.filter(failed_logins::email.eq(email)))
+            .execute(conn)
+    }
+
+    /// Accounts that failed to log in within the throttle window, most recent failure first
+    pub fn lockouts(conn: &PgConnection) -> QueryResult<Vec<Lockout>> {
+        let now = Utc::now().naive_utc();
+        let window_start = now - Duration::seconds(THROTTLE_WINDOW);
+
+        let failures = failed_logins::table
+            .filter(failed_logins::created_at.gt(window_start))
+            .group_by(failed_logins::email)
+            .select((failed_logins::email, sql::<BigInt>("COUNT(*)"), sql::<Timestamp>("MAX(created_at)")))
+            .order(sql::<Timestamp>("MAX(created_at)").desc())
+            .load::<(String, i64, NaiveDateTime)>(conn)?;
+
+        Ok(failures.into_iter()
+            .map(|(email, failed_attempts, last_failed_at)| Lockout {
+                email,
+                failed_attempts,
+                last_failed_at,
+                locked_until: Some(last_failed_at + Duration::seconds(LOCKOUT_DURATION))
+                    .filter(|locked_until| failed_attempts >= LOCKOUT_ATTEMPTS && *locked_until > now),
+            })
+            .collect())
+    }
+}
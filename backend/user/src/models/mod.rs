@@ -1 +1,8 @@
+pub mod api_key;
+pub mod identity;
+pub mod impersonation;
+pub mod login_throttle;
+pub mod password_reset;
+pub mod session;
+pub mod two_factor;
 pub mod user;
\ No newline at end of file
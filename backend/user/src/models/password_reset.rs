@@ -0,0 +1,72 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+
+use core::schema::password_reset_tokens;
+use core::types::UserId;
+use core::utils::{Hash, random_key};
+
+// reset link in the mail is valid for this long
+const RESET_TOKEN_TIMEOUT: i64 = 60 * 60;
+const RESET_TOKEN_LENGTH: usize = 32;
+// users can not request more resets than this in the timeout window, so that the mailbox is not flooded
+const MAX_RESET_REQUESTS: i64 = 3;
+
+/// Token sent by mail for resetting the password of a user. Only its hash is stored, and it can be used once.
+pub struct PasswordResetToken;
+
+impl PasswordResetToken {
+    /// Issues a reset token for the user, None is returned if the user has requested too many resets recently.
+    /// Previously issued tokens of the user stay valid until they expire.
+    pub fn issue(user_id: UserId, hash: &Hash, conn: &PgConnection) -> QueryResult<Option<String>> {
+        let now = Utc::now();
+
+        let recent_requests = password_reset_tokens::table
+            .filter(password_reset_tokens::user_id.eq(user_id))
+            .filter(password_reset_tokens::created_at.gt((now - Duration::seconds(RESET_TOKEN_TIMEOUT)).naive_utc()))
+            .count()
+            .get_result::<i64>(conn)?;
+
+        if recent_requests >= MAX_RESET_REQUESTS {
+            return Ok(None);
+        }
+
+        let token = random_key(RESET_TOKEN_LENGTH);
+
+        diesel::insert_into(password_reset_tokens::table)
+            .values((
+                password_reset_tokens::user_id.eq(user_id),
+                password_reset_tokens::token_hash.eq(hash.sign256(token.as_str())),
+                password_reset_tokens::expires_at.eq((now + Duration::seconds(RESET_TOKEN_TIMEOUT)).naive_utc())
+            ))
+            .execute(conn)?;
+
+        Ok(Some(token))
+    }
+
+    /// Marks the token as used and returns the user it is issued for, None is returned if the token is unknown,
+    /// expired or already used. Other tokens of the user are invalidated too.
+    pub fn consume(token: &str, hash: &Hash, conn: &PgConnection) -> QueryResult<Option<UserId>> {
+        let now = Utc::now().naive_utc();
+
+        let user_id = diesel::update(password_reset_tokens::table
+            .filter(password_reset_tokens::token_hash.eq(hash.sign256(token)))
+            .filter(password_reset_tokens::used_at.is_null())
+            .filter(password_reset_tokens::expires_at.gt(now))
+        )
+            .set(password_reset_tokens::used_at.eq(now))
+            .returning(password_reset_tokens::user_id)
+            .get_result::<UserId>(conn)
+            .optional()?;
+
+        if let Some(user_id) = user_id {
+            diesel::update(password_reset_tokens::table
+                .filter(password_reset_tokens::user_id.eq(user_id))
+                .filter(password_reset_tokens::used_at.is_null())
+            )
+                .set(password_reset_tokens::used_at.eq(now))
+                .execute(conn)?;
+        }
+
+        Ok(user_id)
+    }
+}
@@ -0,0 +1,155 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use core::models::audit_log::AuditEntry;
+use core::schema::{audit_logs, sessions};
+use core::types::{ModelId, UserId};
+use core::utils::{Hash, random_key};
+
+// session expires if it is not refreshed within this window, each refresh extends it
+pub const SESSION_TIMEOUT: i64 = 60 * 60 * 24 * 30;
+const REFRESH_TOKEN_LENGTH: usize = 32;
+const MAX_USER_AGENT_LENGTH: usize = 255;
+const LOGIN_ACTION: &str = "session.start";
+const NEW_DEVICE_ACTION: &str = "session.new_device";
+const REUSE_ACTION: &str = "session.token_reuse";
+
+/// Login of the user on a device, it is kept alive by refreshing its token
+#[derive(Queryable, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub id: ModelId,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub expires_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+pub const SESSION_COLUMNS: (sessions::id, sessions::user_agent, sessions::ip, sessions::expires_at, sessions::last_used_at, sessions::created_at) = (
+    sessions::id,
+    sessions::user_agent,
+    sessions::ip,
+    sessions::expires_at,
+    sessions::last_used_at,
+    sessions::created_at,
+);
+
+/// Session along with whether the request is made with it
+#[derive(Serialize, ToSchema)]
+pub struct SessionResponse {
+    #[serde(flatten)]
+    pub session: Session,
+    pub current: bool,
+}
+
+impl Session {
+    /// Starts a session for the user and returns its id along with its refresh token. Only the hash of the
+    /// refresh token is stored.
+    pub fn start(user_id: UserId, user_agent: Option<String>, ip: Option<String>, hash: &Hash, conn: &PgConnection)
+                 -> QueryResult<(ModelId, String)> {
+        let refresh_token = random_key(REFRESH_TOKEN_LENGTH);
+        let user_agent = user_agent.map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_LENGTH).collect::<String>());
+        let device = format!("{} {}", ip.as_deref().unwrap_or("unknown"), user_agent.as_deref().unwrap_or("unknown"));
+
+        let new_device = is_new_device(user_id, device.as_str(), conn)?;
+
+        let session_id = diesel::insert_into(sessions::table)
+            .values((
+                sessions::user_id.eq(user_id),
+                sessions::refresh_token_hash.eq(hash.sign256(refresh_token.as_str())),
+                sessions::user_agent.eq(user_agent),
+                sessions::ip.eq(ip),
+                sessions::expires_at.eq(expires_at())
+            ))
+            .returning(sessions::id)
+            .get_result::<ModelId>(conn)?;
+
+        AuditEntry::new(Some(user_id), LOGIN_ACTION)
+            .target("session", session_id)
+            .details(device.clone())
+            .record(conn)?;
+
+        if new_device {
+            AuditEntry::new(Some(user_id), NEW_DEVICE_ACTION)
+                .target("session", session_id)
+                .details(device)
+                .record(conn)?;
+        }
+
+        Ok((session_id, refresh_token))
+    }
+
+    /// Replaces the refresh token of the session with a new one and extends the session. Given token can not be
+    /// used again, None is returned if it does not belong to a live session. Presenting the token which is already
+    /// rotated means that it is leaked, the whole session is revoked then, so that neither party can keep using it.
+    pub fn rotate(refresh_token: &str, hash: &Hash, conn: &PgConnection) -> QueryResult<Option<(ModelId, UserId, String)>> {
+        let next_refresh_token = random_key(REFRESH_TOKEN_LENGTH);
+        let refresh_token_hash = hash.sign256(refresh_token);
+        let now = Utc::now().naive_utc();
+
+        let session = diesel::update(sessions::table
+            .filter(sessions::refresh_token_hash.eq(refresh_token_hash.as_str()))
+            .filter(sessions::expires_at.gt(now))
+        )
+            .set((
+                sessions::previous_refresh_token_hash.eq(refresh_token_hash.as_str()),
+                sessions::refresh_token_hash.eq(hash.sign256(next_refresh_token.as_str())),
+                sessions::expires_at.eq(expires_at()),
+                sessions::last_used_at.eq(now)
+            ))
+            .returning((sessions::id, sessions::user_id))
+            .get_result::<(ModelId, UserId)>(conn)
+            .optional()?;
+
+        if session.is_none() {
+            let revoked = diesel::delete(sessions::table
+                .filter(sessions::previous_refresh_token_hash.eq(refresh_token_hash.as_str()))
+            )
+                .returning((sessions::id, sessions::user_id))
+                .get_result::<(ModelId, UserId)>(conn)
+                .optional()?;
+
+            if let Some((session_id, user_id)) = revoked {
+                AuditEntry::new(Some(user_id), REUSE_ACTION)
+                    .target("session", session_id)
+                    .record(conn)?;
+            }
+        }
+
+        Ok(session.map(|(session_id, user_id)| (session_id, user_id, next_refresh_token)))
+    }
+
+    /// Whether the session is neither revoked nor expired, access tokens of the session are rejected otherwise
+    pub fn is_live(session_id: ModelId, user_id: UserId, conn: &PgConnection) -> QueryResult<bool> {
+        diesel::select(diesel::dsl::exists(sessions::table
+            .filter(sessions::id.eq(session_id))
+            .filter(sessions::user_id.eq(user_id))
+            .filter(sessions::expires_at.gt(Utc::now().naive_utc()))
+        ))
+            .get_result::<bool>(conn)
+    }
+}
+
+/// Logins from a device the user has not signed in from before are additionally recorded as `session.new_device`,
+/// which the user is notified about. Device is told apart by the ip and the user agent. First login of the user is
+/// not notified.
+fn is_new_device(user_id: UserId, device: &str, conn: &PgConnection) -> QueryResult<bool> {
+    let logins = || audit_logs::table
+        .filter(audit_logs::actor_id.eq(user_id))
+        .filter(audit_logs::action.eq(LOGIN_ACTION));
+
+    let signed_in = diesel::select(diesel::dsl::exists(logins()))
+        .get_result::<bool>(conn)?;
+
+    let known_device = diesel::select(diesel::dsl::exists(logins().filter(audit_logs::details.eq(device))))
+        .get_result::<bool>(conn)?;
+
+    Ok(signed_in && !known_device)
+}
+
+fn expires_at() -> NaiveDateTime {
+    (Utc::now() + Duration::seconds(SESSION_TIMEOUT)).naive_utc()
+}
@@ -0,0 +1,282 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use core::schema::{user_recovery_codes, user_two_factors};
+use core::types::UserId;
+use core::utils::{base32_decode, base32_encode, Hash, random_bytes, totp, totp_step};
+
+const ISSUER: &str = "NRG Testbed";
+// 160 bits, the length RFC 4226 recommends for the secrets
+const SECRET_LENGTH: usize = 20;
+// codes of the previous and the next steps are accepted too, clocks of the phones drift
+const ALLOWED_STEP_SKEW: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 5;
+
+/// Second factor of the login, either the code of the authenticator app or one of the recovery codes
+pub struct TwoFactor;
+
+/// Secret of a pending enrollment, it is added to the authenticator app by hand or via the QR payload
+#[derive(Serialize, ToSchema)]
+pub struct TwoFactorSecret {
+    pub secret: String,
+}
+
+/// `otpauth` uri of the pending enrollment, it is rendered as a QR code for the authenticator apps
+#[derive(Serialize, ToSchema)]
+pub struct TwoFactorQrPayload {
+    pub payload: String,
+}
+
+/// Recovery codes are shown only once, each of them can be used in place of a code of the authenticator app
+#[derive(Serialize, ToSchema)]
+pub struct RecoveryCodes {
+    pub codes: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    pub remaining_recovery_codes: i64,
+}
+
+/// Whether the admins must use the two factor authentication. Admins without it are treated as regular users
+/// until they enable it, so that they can still sign in and enroll.
+#[derive(Clone, Default)]
+pub struct TwoFactorPolicy {
+    pub require_for_admins: bool,
+}
+
+impl TwoFactor {
+    /// Whether the user has completed the enrollment, i.e. the second factor is asked during the login
+    pub fn is_enabled(user_id: UserId, conn: &PgConnection) -> QueryResult<bool> {
+        diesel::select(diesel::dsl::exists(user_two_factors::table
+            .filter(user_two_factors::user_id.eq(user_id))
+            .filter(user_two_factors::enabled_at.is_not_null())
+        ))
+            .get_result::<bool>(conn)
+    }
+
+    pub fn status(user_id: UserId, conn: &PgConnection) -> QueryResult<TwoFactorStatus> {
+        let enabled = TwoFactor::is_enabled(user_id, conn)?;
+
+        let remaining_recovery_codes = user_recovery_codes::table
+            .filter(user_recovery_codes::user_id.eq(user_id))
+            .filter(user_recovery_codes::used_at.is_null())
+            .count()
+            .get_result::<i64>(conn)?;
+
+        Ok(TwoFactorStatus { enabled, remaining_recovery_codes })
+    }
+
+    /// Provisions a new secret for the user, secret of a previous pending enrollment is replaced. None is returned
+    /// if the user has already enabled it.
+    pub fn provision(user_id: UserId, conn: &PgConnection) -> QueryResult<Option<String>> {
+        if TwoFactor::is_enabled(user_id, conn)? {
+            return Ok(None);
+        }
+
+        let secret = base32_encode(&random_bytes(SECRET_LENGTH));
+
+        diesel::delete(user_two_factors::table.find(user_id))
+            .execute(conn)?;
+
+        diesel::insert_into(user_two_factors::table)
+            .values((
+                user_two_factors::user_id.eq(user_id),
+                user_two_factors::secret.eq(&secret)
+            ))
+            .execute(conn)?;
+
+        Ok(Some(secret))
+    }
+
+    /// `otpauth` uri of the pending enrollment of the user, None is returned if there is not any
+    pub fn qr_payload(user_id: UserId, email: &str, conn: &PgConnection) -> QueryResult<Option<String>> {
+        let secret = user_two_factors::table
+            .filter(user_two_factors::user_id.eq(user_id))
+            .filter(user_two_factors::enabled_at.is_null())
+            .select(user_two_factors::secret)
+            .first::<String>(conn)
+            .optional()?;
+
+        Ok(secret.map(|secret| {
+            let issuer = utf8_percent_encode(ISSUER, NON_ALPHANUMERIC);
+
+            format!(
+                "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits=6&period=30",
+                issuer,
+                utf8_percent_encode(email, NON_ALPHANUMERIC),
+                secret,
+                issuer
+            )
+        }))
+    }
+
+    /// Completes the pending enrollment if the code is generated with its secret, recovery codes of the user are
+    /// returned. None is returned if the code is not valid or there is not any pending enrollment.
+    pub fn enable(user_id: UserId, code: &str, hash: &Hash, conn: &PgConnection) -> QueryResult<Option<Vec<String>>> {
+        if !TwoFactor::verify_code(user_id, false, code, conn)? {
+            return Ok(None);
+        }
+
+        diesel::update(user_two_factors::table.find(user_id))
+            .set(user_two_factors::enabled_at.eq(Utc::now().naive_utc()))
+            .execute(conn)?;
+
+        TwoFactor::regenerate_recovery_codes(user_id, hash, conn).map(Some)
+    }
+
+    /// Checks the second factor of the enabled user, the code is either generated by the authenticator app or one
+    /// of the recovery codes. Both kinds can be used only once.
+    pub fn verify(user_id: UserId, code: &str, hash: &Hash, conn: &PgConnection) -> QueryResult<bool> {
+        if TwoFactor::verify_code(user_id, true, code, conn)? {
+            return Ok(true);
+        }
+
+        let code = normalize_recovery_code(code);
+
+        let used = diesel::update(user_recovery_codes::table
+            .filter(user_recovery_codes::user_id.eq(user_id))
+            .filter(user_recovery_codes::code_hash.eq(hash.sign256(code.as_str())))
+            .filter(user_recovery_codes::used_at.is_null())
+        )
+            .set(user_recovery_codes::used_at.eq(Utc::now().naive_utc()))
+            .execute(conn)?;
+
+        Ok(used > 0)
+    }
+
+    /// Replaces the recovery codes of the user, previous ones can not be used afterwards
+    pub fn regenerate_recovery_codes(user_id: UserId, hash: &Hash, conn: &PgConnection) -> QueryResult<Vec<String>> {
+        let codes = (0..RECOVERY_CODE_COUNT)
+            .map(|_| base32_encode(&random_bytes(RECOVERY_CODE_LENGTH)))
+            .collect::<Vec<String>>();
+
+        diesel::delete(user_recovery_codes::table.filter(user_recovery_codes::user_id.eq(user_id)))
+            .execute(conn)?;
+
+        let rows = codes.iter()
+            .map(|code| (
+                user_recovery_codes::user_id.eq(user_id),
+                user_recovery_codes::code_hash.eq(hash.sign256(code.as_str()))
+            ))
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(user_recovery_codes::table)
+            .values(&rows)
+            .execute(conn)?;
+
+        Ok(codes)
+    }
+
+    /// Removes the secret and the recovery codes of the user
+    pub fn disable(user_id: UserId, conn: &PgConnection) -> QueryResult<()> {
+        diesel::delete(user_recovery_codes::table.filter(user_recovery_codes::user_id.eq(user_id)))
+            .execute(conn)?;
+
+        diesel::delete(user_two_factors::table.find(user_id))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    fn verify_code(user_id: UserId, enabled: bool, code: &str, conn: &PgConnection) -> QueryResult<bool> {
+        let code = match code.trim().parse::<u32>() {
+            Ok(code) => code,
+            Err(_) => return Ok(false)
+        };
+
+        let mut query = user_two_factors::table
+            .filter(user_two_factors::user_id.eq(user_id))
+            .select((user_two_factors::secret, user_two_factors::last_used_step))
+            .into_boxed();
+
+        query = if enabled {
+            query.filter(user_two_factors::enabled_at.is_not_null())
+        } else {
+            query.filter(user_two_factors::enabled_at.is_null())
+        };
+
+        let (secret, last_used_step) = match query.first::<(String, Option<i64>)>(conn).optional()? {
+            Some(two_factor) => two_factor,
+            None => return Ok(false)
+        };
+
+        let secret = match base32_decode(secret.as_str()) {
+            Some(secret) => secret,
+            None => return Ok(false)
+        };
+
+        let step = match matching_step(&secret, code, Utc::now().timestamp()) {
+            Some(step) => step,
+            None => return Ok(false)
+        };
+
+        // a code can not be replayed, neither can the codes of the earlier steps once a later one is used
+        if last_used_step.is_some_and(|last_used_step| step <= last_used_step) {
+            return Ok(false);
+        }
+
+        let updated = diesel::update(user_two_factors::table
+            .find(user_id)
+            .filter(user_two_factors::last_used_step.is_null().or(user_two_factors::last_used_step.lt(step)))
+        )
+            .set(user_two_factors::last_used_step.eq(step))
+            .execute(conn)?;
+
+        Ok(updated > 0)
+    }
+}
+
+/// Step of the code around the given unix timestamp, none if the code does not belong to any of the allowed steps
+fn matching_step(secret: &[u8], code: u32, timestamp: i64) -> Option<i64> {
+    let current_step = totp_step(timestamp);
+
+    (current_step - ALLOWED_STEP_SKEW..=current_step + ALLOWED_STEP_SKEW)
+        .find(|step| totp(secret, *step) == code)
+}
+
+// recovery codes are easier to type with lowercase letters and separators
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"12345678901234567890";
+    // 2009-02-13T23:31:30Z, the first second of its step
+    const TIMESTAMP: i64 = 1_234_567_890;
+
+    #[test]
+    fn accepts_the_codes_of_the_adjacent_steps() {
+        let step = totp_step(TIMESTAMP);
+
+        for skew in -ALLOWED_STEP_SKEW..=ALLOWED_STEP_SKEW {
+            assert_eq!(matching_step(SECRET, totp(SECRET, step + skew), TIMESTAMP), Some(step + skew));
+        }
+    }
+
+    #[test]
+    fn rejects_the_codes_out_of_the_skew() {
+        let step = totp_step(TIMESTAMP);
+
+        assert_eq!(matching_step(SECRET, totp(SECRET, step - ALLOWED_STEP_SKEW - 1), TIMESTAMP), None);
+        assert_eq!(matching_step(SECRET, totp(SECRET, step + ALLOWED_STEP_SKEW + 1), TIMESTAMP), None);
+        assert_eq!(matching_step(b"another secret", totp(SECRET, step), TIMESTAMP), None);
+    }
+
+    #[test]
+    fn normalizes_recovery_codes() {
+        assert_eq!(normalize_recovery_code(" ab1c-d2e3f "), "AB1CD2E3F");
+    }
+}
@@ -1,5 +1,6 @@
 use actix_web::{error::BlockingError, FromRequest, HttpRequest, HttpResponse, web};
 use actix_web::dev::Payload;
+use chrono::{NaiveDateTime, Utc};
 use diesel::{Identifiable, Insertable, Queryable};
 use diesel::pg::Pg;
 use diesel::prelude::*;
@@ -8,18 +9,24 @@ use diesel::sql_types::VarChar;
 use futures::future::LocalBoxFuture;
 use futures::FutureExt;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use core::db::DieselEnum;
 use core::error::ErrorMessaging;
 use core::ErrorMessage;
+use core::models::audit_log::AuditEntry;
+use core::models::role::Roles;
 use core::models::token::AuthToken;
-use core::schema::users;
-use core::types::{DBPool, ModelId};
+use core::schema::{user_api_keys, users};
+use core::types::{DBPool, ModelId, UserId};
 
-#[derive(Queryable, Identifiable, Serialize)]
+use crate::models::session::Session;
+use crate::models::two_factor::{TwoFactor, TwoFactorPolicy};
+
+#[derive(Queryable, Identifiable, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct User {
-    pub id: ModelId,
+    pub id: UserId,
     pub first_name: String,
     pub last_name: String,
     pub email: String,
@@ -27,12 +34,24 @@ pub struct User {
     pub password: String,
     pub status: UserStatus,
     pub role_id: ModelId,
+    #[serde(skip_serializing)]
+    pub verification_sent_at: Option<NaiveDateTime>,
+    #[serde(skip_serializing)]
+    pub status_before_deactivation: Option<String>,
 }
 
 impl User {
     pub fn full_name(&self) -> String {
         self.first_name.clone() + " " + self.last_name.as_str()
     }
+
+    pub fn is_admin(&self) -> bool {
+        self.role_id == Roles::Admin as ModelId
+    }
+
+    pub fn is_auditor(&self) -> bool {
+        self.role_id == Roles::Auditor as ModelId
+    }
 }
 
 impl FromRequest for User {
@@ -45,16 +64,76 @@ impl FromRequest for User {
             .ok_or_else(|| ErrorMessage::DBError.error())
             .map(|c| c.get().unwrap());
 
-        let user_id = req.head().extensions().get::<AuthToken>()
+        let token = req.head().extensions().get::<AuthToken>()
             .ok_or_else(|| ErrorMessage::UserNotFound.error())
-            .map(|a| a.user_id);
+            .map(|a| (a.user_id(), a.api_key_id, a.session_id, a.impersonator_id));
+
+        let request_line = format!("{} {}", req.method(), req.path());
 
+        let require_two_factor = req.app_data::<web::Data<TwoFactorPolicy>>()
+            .is_some_and(|policy| policy.require_for_admins);
 
         async move {
-            let user_id = user_id?;
+            let (user_id, api_key_id, session_id, impersonator_id) = token?;
             let conn = conn?;
 
-            web::block(move || users::table.find(user_id).first::<User>(&conn))
+            web::block(move || -> Result<Option<User>, Error> {
+                // tokens outlive the revoke of their session until they expire, they are checked here
+                if let Some(session_id) = session_id {
+                    if !Session::is_live(session_id, user_id, &conn)? {
+                        return Ok(None);
+                    }
+                }
+
+                // tokens of the api keys are accepted as long as their key is not revoked
+                if let Some(api_key_id) = api_key_id {
+                    let now = Utc::now().naive_utc();
+
+                    let updated = diesel::update(user_api_keys::table
+                        .filter(user_api_keys::id.eq(api_key_id))
+                        .filter(user_api_keys::user_id.eq(user_id))
+                        .filter(user_api_keys::expires_at.gt(now))
+                    )
+                        .set(user_api_keys::last_used_at.eq(now))
+                        .execute(&conn)?;
+
+                    if updated == 0 {
+                        return Ok(None);
+                    }
+                }
+
+                // impersonation tokens are rejected once their admin loses the role, every request made with them
+                // is recorded on behalf of the admin
+                if let Some(impersonator_id) = impersonator_id {
+                    let is_admin = diesel::select(diesel::dsl::exists(users::table
+                        .filter(users::id.eq(impersonator_id))
+                        .filter(users::role_id.eq(Roles::Admin as ModelId))
+                    ))
+                        .get_result::<bool>(&conn)?;
+
+                    if !is_admin {
+                        return Ok(None);
+                    }
+
+                    AuditEntry::new(Some(impersonator_id), "user.impersonate.request")
+                        .target("user", user_id)
+                        .details(request_line)
+                        .record(&conn)?;
+                }
+
+                let mut user = users::table.find(user_id).first::<User>(&conn)?;
+
+                // tokens issued before the deactivation are rejected along with the sessions
+                if user.status == UserStatus::Deactivated {
+                    return Ok(None);
+                }
+
+                if require_two_factor && user.is_admin() && !TwoFactor::is_enabled(user.id, &conn)? {
+                    user.role_id = Roles::User as ModelId;
+                }
+
+                Ok(Some(user))
+            })
                 .await
                 .map_err(|e| match e {
                     BlockingError::Error(e) => {
@@ -64,7 +143,8 @@ impl FromRequest for User {
                         }
                     }
                     BlockingError::Canceled => ErrorMessage::BlockingCanceled.error()
-                })
+                })?
+                .ok_or_else(|| ErrorMessage::InvalidToken.error())
         }.boxed_local()
     }
 }
@@ -72,7 +152,7 @@ impl FromRequest for User {
 #[derive(Queryable, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SlimUser {
-    pub id: ModelId,
+    pub id: UserId,
     pub first_name: String,
     pub last_name: String,
     pub role_id: ModelId,
@@ -96,11 +176,13 @@ pub const SLIM_USER_COLUMNS: (users::id, users::first_name, users::last_name, us
     users::role_id,
 );
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
 pub enum UserStatus {
     NotVerified,
     Verified,
     Banned,
+    // deactivated by the admins, e.g. the user left the lab, their experiments and jobs are kept
+    Deactivated,
 }
 
 impl Default for UserStatus {
@@ -1,10 +1,13 @@
 use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
 
 use core::sanitized::Sanitize;
 use core::schema::users;
+use core::types::UserId;
 use derive::Sanitize;
 
-#[derive(AsChangeset, Sanitize, Deserialize)]
+#[derive(AsChangeset, Sanitize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 #[table_name = "users"]
 pub struct UpdateProfileRequest {
@@ -12,7 +15,62 @@ pub struct UpdateProfileRequest {
     pub last_name: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct UpdatePasswordRequest {
     pub password: String
-}
\ No newline at end of file
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    // key expires after 90 days if it is not given
+    #[validate(range(min = 1, max = 365))]
+    pub expires_in_days: Option<i64>,
+}
+
+// derive can not skip the fields which are not strings
+impl Sanitize for CreateApiKeyRequest {
+    fn sanitize(self) -> Self {
+        CreateApiKeyRequest {
+            name: self.name.sanitize(),
+            ..self
+        }
+    }
+}
+#[derive(Deserialize, ToSchema)]
+pub struct TwoFactorCodeRequest {
+    pub code: String
+}
+
+/// Authorization code and the state the OpenID Connect provider redirects back with
+#[derive(Deserialize, ToSchema)]
+pub struct OidcCallbackRequest {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UnlockAccountRequest {
+    pub email: String
+}
+
+/// User to be impersonated along with the reason recorded in the audit log, e.g. the report being reproduced
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonateRequest {
+    pub user_id: UserId,
+    #[validate(length(min = 1, max = 1024))]
+    pub reason: String,
+}
+
+// derive can not skip the fields which are not strings
+impl Sanitize for ImpersonateRequest {
+    fn sanitize(self) -> Self {
+        ImpersonateRequest {
+            reason: self.reason.sanitize(),
+            ..self
+        }
+    }
+}
@@ -41,7 +41,7 @@ export class AuthViewModelService extends MainViewModelService {
   }
 
   resetPassword(token: string, password: any): Observable<SuccessResponse> {
-    return this.requestService.makePutRequest(routes.auth.resetPassword, {token, password});
+    return this.requestService.makePostRequest(routes.auth.resetPassword, {token, password});
   }
 
   forgotPassword(email: string): Observable<SuccessResponse> {
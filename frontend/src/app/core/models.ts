@@ -82,10 +82,12 @@ export interface Alert {
 }
 
 export interface Pagination<T> {
+  total: number;
+  page: number;
   perPage: number;
-  currentPage: number;
   totalPages: number;
-  totalItems: number;
+  next: string | null;
+  prev: string | null;
   items: T[];
 }
 
@@ -4,6 +4,36 @@ export const locales = {
     invalid_credentials: $localize`:@@errors.invalid_credentials:Invalid Credentials`,
     user_exists: $localize`:@@errors.user_exists:This email is already in use`,
     not_verified: $localize`:@@errors.not_verified:Your account is not verified. Please verify it via email`,
+    two_factor_required: $localize`:@@errors.two_factor_required:Enter the code of your authenticator app`,
+    invalid_two_factor_code: $localize`:@@errors.invalid_two_factor_code:Code is not valid`,
+    too_many_login_attempts: $localize`:@@errors.too_many_login_attempts:Too many failed attempts, please wait a moment before trying again`,
+    account_locked: $localize`:@@errors.account_locked:Your account is locked due to too many failed attempts, please try again later`,
+    oidc_failed: $localize`:@@errors.oidc_failed:Signing in with the single sign on failed, please try again`,
+    oidc_account_not_linked: $localize`:@@errors.oidc_account_not_linked:This email is already in use, sign in with your password and link your account from your profile`,
+    account_deactivated: $localize`:@@errors.account_deactivated:Your account is deactivated, please contact the administrators`,
+    // experiment
+    email_not_verified: $localize`:@@errors.email_not_verified:Please verify your email before running experiments`,
+    description_too_long: $localize`:@@errors.description_too_long:Description is too long`,
+    experiment_archived: $localize`:@@errors.experiment_archived:Archived experiments can not be run, unarchive it first`,
+    experiment_name_exists: $localize`:@@errors.experiment_name_exists:You already have an experiment with a similar name`,
+    invalid_retention: $localize`:@@errors.invalid_retention:Retention must be a positive number of days or jobs`,
+    invalid_batch: $localize`:@@errors.invalid_batch:A batch should run on 1 to 100 runners, at most 200 jobs in total, and have valid abort and repeat policies`,
+    invalid_labels: $localize`:@@errors.invalid_labels:At most 16 labels of up to 64 characters can be given`,
+    annotation_too_long: $localize`:@@errors.annotation_too_long:Annotation can be at most 4096 characters`,
+    invalid_criteria: $localize`:@@errors.invalid_criteria:Success criteria should compare metrics with numbers, e.g. energy_mj < 500 && throughput > 10`,
+    invalid_warmup_runs: $localize`:@@errors.invalid_warmup_runs:Warm-up runs should be between 0 and 10`,
+    invalid_thermal_guard: $localize`:@@errors.invalid_thermal_guard:Temperature limit should be up to 150 °C and the timeout between 1 and 3600 seconds`,
+    invalid_performance_settings: $localize`:@@errors.invalid_performance_settings:Governor should be one of the cpufreq governors and the frequency should be given in kHz`,
+    invalid_network_emulation: $localize`:@@errors.invalid_network_emulation:Delay should be up to 60000 ms with a jitter below it, loss between 0 and 100 percent and rate up to 10000000 kbit`,
+    invalid_sdr_settings: $localize`:@@errors.invalid_sdr_settings:Frequency up to 10 GHz, gain and sample rate up to 100 MS/s should be given together, and the recording should be iq or spectrum up to 60000 ms`,
+    invalid_cost_center: $localize`:@@errors.invalid_cost_center:Cost center should be up to 64 letters, digits, dashes, underscores or dots and cannot be unassigned`,
+    invalid_accounting_month: $localize`:@@errors.invalid_accounting_month:Month should be given as YYYY-MM`,
+    invalid_project_name: $localize`:@@errors.invalid_project_name:Project name should not be empty and be up to 255 characters`,
+    invalid_project_quota: $localize`:@@errors.invalid_project_quota:Project quotas should be positive`,
+    project_quota_exceeded: $localize`:@@errors.project_quota_exceeded:Quota of the project is exceeded`,
+    invalid_project_member: $localize`:@@errors.invalid_project_member:Owner of the project cannot be removed from it`,
+    invalid_download_signature: $localize`:@@errors.invalid_download_signature:Download link is expired or invalid`,
+    no_items_selected: $localize`:@@errors.no_items_selected:Select the items by their ids or confirm selecting all of them`,
     validation_errors: $localize`:@@errors.validation_errors:There are some validation errors`,
     validationErrors: {}
   },